@@ -0,0 +1,139 @@
+//! Measures how extracting streams with `Bank::extract_all_parallel` scales with the number of threads,
+//! compared to extracting them one at a time with `Bank::extract_with`.
+//!
+//! Streams are encoded as FLAC files, which is expensive enough for the encoders to dominate.
+//! A synthetic sound bank of PCM16 noise is used unless a path to a sound bank is given:
+//!
+//! ```text
+//! cargo bench --bench parallel --features parallel,flac -- [bank.fsb]
+//! ```
+
+// benchmarks share the dependencies of the library, most of which they don't use directly
+#![allow(unused_crate_dependencies)]
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use common::test_bank::{TestBank, TestStream};
+use fsbex::{
+    encode::{EncodeOptions, PcmOutput},
+    Bank, ParallelOptions, SinkFactory, StreamInfo,
+};
+use rayon::ThreadPoolBuilder;
+use std::{
+    convert::Infallible,
+    env,
+    error::Error,
+    fs,
+    io::{self, Sink},
+    thread,
+    time::{Duration, Instant},
+};
+
+// The number of times each extraction is repeated, keeping the fastest run.
+const RUNS: u32 = 3;
+
+// Discards the encoded streams, so that only decoding and encoding is measured.
+struct DiscardFactory {
+    options: EncodeOptions<'static>,
+}
+
+impl SinkFactory for DiscardFactory {
+    type Sink = Sink;
+    type Error = Infallible;
+
+    fn create(&mut self, _index: u32, _info: &StreamInfo) -> Result<Option<Sink>, Infallible> {
+        Ok(Some(io::sink()))
+    }
+
+    fn options(&self) -> EncodeOptions<'_> {
+        self.options
+    }
+}
+
+fn factory() -> DiscardFactory {
+    DiscardFactory {
+        options: EncodeOptions::new().pcm_output(PcmOutput::Flac),
+    }
+}
+
+// Builds a version 1 sound bank of 64 streams, each holding one second of stereo 48000 Hz PCM16 noise.
+fn synthetic_bank() -> Vec<u8> {
+    const NUM_STREAMS: u32 = 64;
+    const NUM_SAMPLES: u64 = 48000;
+    // 48000 Hz, 2 channels
+    const STREAM_HEADER: u64 = (9 << 1) | (1 << 5) | (NUM_SAMPLES << 34);
+
+    // a xorshift generator gives noise that FLAC can't compress much, without any dependencies
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut noise = || {
+        let mut data = Vec::new();
+        for _ in 0..NUM_SAMPLES * 2 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            // quiet noise, so that samples are not all at full scale
+            let [high, low, ..] = state.to_be_bytes();
+            let sample = i16::from_be_bytes([high, low]) / 16;
+            data.extend_from_slice(&sample.to_le_bytes());
+        }
+        data
+    };
+
+    let streams = (0..NUM_STREAMS).map(|_| TestStream::new(STREAM_HEADER, &noise()));
+    TestBank::new(2).streams(streams).build()
+}
+
+fn fastest<F: FnMut() -> Result<(), Box<dyn Error + Send + Sync>>>(
+    mut run: F,
+) -> Result<Duration, Box<dyn Error + Send + Sync>> {
+    let mut fastest = Duration::MAX;
+
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        run()?;
+        fastest = fastest.min(start.elapsed());
+    }
+
+    Ok(fastest)
+}
+
+fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+    // `cargo bench` passes `--bench` to benchmarks without the libtest harness
+    let data = match env::args().skip(1).find(|arg| !arg.starts_with("--")) {
+        Some(path) => fs::read(path)?,
+        None => synthetic_bank(),
+    };
+
+    let bank = Bank::new(data.as_slice())?;
+    println!("{} streams, {} bytes", bank.num_streams(), data.len());
+
+    let serial = fastest(|| {
+        let _report = Bank::new(data.as_slice())?.extract_with(factory())?;
+        Ok(())
+    })?;
+    println!("serial:     {serial:>10.2?}");
+
+    let max_threads = thread::available_parallelism()?.get();
+    let thread_counts = (0..)
+        .map(|n| 1 << n)
+        .take_while(|&threads| threads < max_threads)
+        .chain([max_threads]);
+
+    for threads in thread_counts {
+        let pool = ThreadPoolBuilder::new().num_threads(threads).build()?;
+        let parallel = fastest(|| {
+            pool.install(|| {
+                let report = Bank::new(data.as_slice())?
+                    .extract_all_parallel(factory(), ParallelOptions::new())?;
+                assert!(report.is_success());
+                Ok(())
+            })
+        })?;
+
+        let speedup = serial.as_secs_f64() / parallel.as_secs_f64();
+        println!("{threads:>2} threads: {parallel:>10.2?} ({speedup:.2}x)");
+    }
+
+    Ok(())
+}
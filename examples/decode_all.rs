@@ -0,0 +1,95 @@
+//! Decodes every stream of a sound bank to PCM samples with Symphonia,
+//! and checks that each stream decodes to the number of samples declared in the sound bank.
+//!
+//! ```text
+//! cargo run --example decode_all --features symphonia -- <bank.fsb>
+//! ```
+
+// examples share the dependencies of the library, most of which they don't use directly
+#![allow(unused_crate_dependencies)]
+
+use fsbex::Bank;
+use std::{env, error::Error, fs::File, io::BufReader};
+use symphonia::core::{
+    codecs::DecoderOptions,
+    errors::Error as SymphoniaError,
+    formats::FormatOptions,
+    io::{MediaSourceStream, MediaSourceStreamOptions},
+    meta::MetadataOptions,
+};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let path = env::args().nth(1).ok_or("usage: decode_all <bank.fsb>")?;
+    let bank = Bank::new(BufReader::new(File::open(path)?))?;
+    let mut mismatches = 0;
+
+    for stream in bank {
+        let name = stream.name().unwrap_or("unnamed stream").to_owned();
+
+        let frames = match decode(&stream) {
+            Ok(frames) => frames,
+            Err(e) => {
+                println!("{name}: failed to decode stream: {e}");
+                mismatches += 1;
+                continue;
+            }
+        };
+
+        let expected = u64::from(stream.sample_count().get());
+
+        if frames == expected {
+            println!("{name}: decoded {frames} samples");
+        } else {
+            println!("{name}: decoded {frames} samples, but expected {expected}");
+            mismatches += 1;
+        }
+    }
+
+    if mismatches > 0 {
+        return Err(format!("{mismatches} streams did not decode as expected").into());
+    }
+
+    Ok(())
+}
+
+// Decodes the stream, returning the number of decoded sample frames.
+fn decode(stream: &fsbex::Stream) -> Result<u64, Box<dyn Error>> {
+    let source = stream.media_source()?;
+    let hint = source.hint();
+    let source = MediaSourceStream::new(Box::new(source), MediaSourceStreamOptions::default());
+
+    // the final packet of Vorbis streams is only trimmed with gapless playback
+    let options = FormatOptions {
+        enable_gapless: true,
+        ..FormatOptions::default()
+    };
+
+    let mut reader = symphonia::default::get_probe()
+        .format(&hint, source, &options, &MetadataOptions::default())?
+        .format;
+
+    let track = reader.default_track().ok_or("no audio track found")?;
+    let track_id = track.id;
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    let mut frames = 0;
+
+    loop {
+        let packet = match reader.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        frames += decoder.decode(&packet)?.frames() as u64;
+    }
+
+    Ok(frames)
+}
@@ -0,0 +1,52 @@
+//! Plays a stream of a sound bank on the default audio device.
+//!
+//! ```text
+//! cargo run --example preview --features rodio,rodio/playback -- <bank.fsb> [stream index] [--loop]
+//! ```
+//!
+//! With `--loop`, the loop of the stream is repeated until the program is stopped.
+
+// examples share the dependencies of the library, most of which they don't use directly
+#![allow(unused_crate_dependencies)]
+
+use fsbex::Bank;
+use std::{env, error::Error, fs::File, io::BufReader};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = env::args().skip(1);
+    let path = args
+        .next()
+        .ok_or("usage: preview <bank.fsb> [stream index] [--loop]")?;
+
+    let mut index = 0;
+    let mut looping = false;
+
+    for arg in args {
+        if arg == "--loop" {
+            looping = true;
+        } else {
+            index = arg.parse()?;
+        }
+    }
+
+    let bank = Bank::new(BufReader::new(File::open(path)?))?;
+    let stream = bank
+        .into_iter()
+        .nth(index)
+        .ok_or("sound bank has no stream at this index")?;
+
+    println!(
+        "playing {} ({} Hz, {} channels)",
+        stream.name().unwrap_or("unnamed stream"),
+        stream.sample_rate(),
+        stream.channels()
+    );
+
+    let device = rodio::DeviceSinkBuilder::open_default_sink()?;
+    let player = rodio::Player::connect_new(device.mixer());
+
+    player.append(stream.into_source()?.looping(looping));
+    player.sleep_until_end();
+
+    Ok(())
+}
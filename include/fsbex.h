@@ -0,0 +1,162 @@
+/* C API of fsbex, generated by cbindgen from src/ffi.rs. Do not edit by hand. */
+
+#ifndef FSBEX_H
+#define FSBEX_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+// The function succeeded.
+#define FSBEX_OK 0
+
+// A required pointer argument was null, or a string argument was not valid UTF-8.
+#define FSBEX_ERROR_INVALID_ARGUMENT 1
+
+// A file could not be opened, read, or created.
+#define FSBEX_ERROR_IO 2
+
+// The data is not a sound bank that can be parsed.
+#define FSBEX_ERROR_DECODE 3
+
+// The sound bank has no stream at the given index.
+#define FSBEX_ERROR_UNKNOWN_STREAM 4
+
+// The stream data could not be encoded.
+#define FSBEX_ERROR_ENCODE 5
+
+// The buffer is too small to hold the encoded stream. The required size is written to `written`.
+#define FSBEX_ERROR_BUFFER_TOO_SMALL 6
+
+// `fsbex` panicked. This is a bug; the handle that was passed in should not be used again, except to free it.
+#define FSBEX_ERROR_PANIC 7
+
+// A sound bank opened with [`fsbex_bank_open_file`] or [`fsbex_bank_open_memory`].
+//
+// This is an opaque handle, which holds the data of the sound bank in memory.
+// It must be freed with [`fsbex_bank_free`].
+typedef struct FsbexBank FsbexBank;
+
+// Information about a stream of a sound bank, filled in by [`fsbex_bank_stream_info`].
+//
+// Strings are owned by the sound bank, and remain valid until it is freed.
+typedef struct FsbexStreamInfo {
+  // The audio format of the stream, as the format value of FSB5 file headers
+  // (such as 2 for 16-bit PCM, 11 for MPEG, and 15 for Vorbis).
+  uint32_t format;
+  // The sample rate of the stream, in Hz.
+  uint32_t sample_rate;
+  // The number of channels in the stream.
+  uint32_t channels;
+  // The number of samples in the stream, per channel.
+  uint32_t sample_count;
+  // Whether the stream has loop points. If not, `loop_start` and `loop_end` are 0.
+  bool has_loop;
+  // The first sample of the loop.
+  uint32_t loop_start;
+  // The last sample of the loop (inclusive).
+  uint32_t loop_end;
+  // The size of the stream's data in the sound bank, in bytes.
+  uint32_t size;
+  // The name of the stream, or null if the stream has no name.
+  const char *name;
+  // The file extension of the format the stream is extracted as, without a leading dot (such as `wav`).
+  const char *extension;
+} FsbexStreamInfo;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Opens the sound bank at `path`, reading the whole file into memory.
+// On success, the sound bank is written to `out`, and must be freed with [`fsbex_bank_free`].
+//
+// Returns [`FSBEX_ERROR_IO`] if the file could not be read,
+// or [`FSBEX_ERROR_DECODE`] if the file is not a sound bank that can be parsed.
+//
+// # Safety
+//
+// `path` must point to a null-terminated string, and `out` must be valid for writes.
+int fsbex_bank_open_file(const char *path, struct FsbexBank **out);
+
+// Opens a sound bank from `len` bytes at `data`, which are copied, so the memory can be freed afterwards.
+// On success, the sound bank is written to `out`, and must be freed with [`fsbex_bank_free`].
+//
+// Returns [`FSBEX_ERROR_DECODE`] if the data is not a sound bank that can be parsed.
+//
+// # Safety
+//
+// `data` must be valid for reads of `len` bytes, and `out` must be valid for writes.
+int fsbex_bank_open_memory(const uint8_t *data,
+                           size_t len,
+                           struct FsbexBank **out);
+
+// Writes the number of streams in the sound bank to `out`.
+//
+// # Safety
+//
+// `bank` must be a sound bank that hasn't been freed, and `out` must be valid for writes.
+int fsbex_bank_stream_count(const struct FsbexBank *bank, uint32_t *out);
+
+// Writes information about the stream at `index` to `out`.
+//
+// Returns [`FSBEX_ERROR_UNKNOWN_STREAM`] if the sound bank has no stream at `index`.
+//
+// # Safety
+//
+// `bank` must be a sound bank that hasn't been freed, and `out` must be valid for writes.
+int fsbex_bank_stream_info(const struct FsbexBank *bank,
+                           uint32_t index,
+                           struct FsbexStreamInfo *out);
+
+// Extracts the stream at `index` into a buffer of `capacity` bytes at `buffer`, writing the size of the
+// extracted file to `written`. The stream is encoded like when it's written to a file.
+//
+// If the buffer is too small, nothing is written to it, and [`FSBEX_ERROR_BUFFER_TOO_SMALL`] is returned
+// with the required size written to `written`. The required size can be queried by passing a null `buffer`
+// with a `capacity` of 0.
+//
+// Returns [`FSBEX_ERROR_UNKNOWN_STREAM`] if the sound bank has no stream at `index`,
+// or [`FSBEX_ERROR_ENCODE`] if the stream could not be encoded.
+//
+// # Safety
+//
+// `bank` must be a sound bank that hasn't been freed and isn't used by another thread at the same time,
+// `buffer` must be valid for writes of `capacity` bytes (or null if `capacity` is 0),
+// and `written` must be valid for writes.
+int fsbex_bank_extract_to_buffer(struct FsbexBank *bank,
+                                 uint32_t index,
+                                 uint8_t *buffer,
+                                 size_t capacity,
+                                 size_t *written);
+
+// Extracts the stream at `index` to a file at `path`, which is created or overwritten.
+//
+// Returns [`FSBEX_ERROR_IO`] if the file could not be created, [`FSBEX_ERROR_UNKNOWN_STREAM`]
+// if the sound bank has no stream at `index`, or [`FSBEX_ERROR_ENCODE`] if the stream could not be encoded
+// (including when the file could not be written).
+//
+// # Safety
+//
+// `bank` must be a sound bank that hasn't been freed and isn't used by another thread at the same time,
+// and `path` must point to a null-terminated string.
+int fsbex_bank_extract_to_file(struct FsbexBank *bank,
+                               uint32_t index,
+                               const char *path);
+
+// Frees a sound bank. Passing null does nothing.
+//
+// # Safety
+//
+// `bank` must be null, or a sound bank that hasn't been freed already.
+void fsbex_bank_free(struct FsbexBank *bank);
+
+// Returns a message describing the last error on the calling thread, or null if no function has failed
+// on this thread. The message remains valid until another function fails on the same thread.
+const char *fsbex_last_error_message(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* FSBEX_H */
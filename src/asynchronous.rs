@@ -0,0 +1,347 @@
+use crate::bank::{Bank, DecodeError, SeekStreamError, SeekStreamErrorKind};
+use crate::encode::EncodeOptions;
+use crate::header::{
+    error::{HeaderError, HeaderErrorKind},
+    AudioFormat, ParseOptions, StreamInfo,
+};
+use crate::stream::Stream;
+use std::{
+    io::{empty, Empty, Error as IoError, Read, Result as IoResult, SeekFrom},
+    num::NonZeroU32,
+};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
+
+/// An FMOD sound bank read from an asynchronous source, enabled with the `async` feature.
+///
+/// This is the asynchronous counterpart of [`Bank::seekable`], for sources such as objects in remote storage
+/// that shouldn't block a thread while they're read. Parsing and encoding use the same code as [`Bank`]:
+/// the file header is read into memory in chunks until it can be parsed, and [`AsyncBank::extract_stream`]
+/// reads the data of a stream into memory, encodes it, then writes the encoded file to the sink.
+/// Memory use is therefore proportional to the size of a single stream, rather than the whole sound bank.
+///
+/// Sources must implement tokio's [`AsyncRead`] and [`AsyncSeek`], and sinks tokio's [`AsyncWrite`].
+/// Streams can be extracted in any order, and more than once.
+///
+/// # Examples
+///
+/// ```
+/// use fsbex::{encode::EncodeOptions, AsyncBank};
+/// use std::error::Error;
+/// use tokio::fs::File;
+///
+/// async fn extract_all(path: &str) -> Result<(), Box<dyn Error>> {
+///     let mut bank = AsyncBank::new(File::open(path).await?).await?;
+///     let names: Vec<_> = (bank.stream_infos().iter())
+///         .map(|info| info.suggested_filename(EncodeOptions::default()))
+///         .collect();
+///
+///     for (index, name) in (0..).zip(names) {
+///         bank.extract_stream(index, File::create(name).await?).await?;
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct AsyncBank<R> {
+    // the parsed file header; stream data is read from `source` instead
+    bank: Bank<Empty>,
+    source: R,
+    // position of the start of the sound bank in `source`
+    start: u64,
+}
+
+// The file header is read in chunks of at least this many bytes.
+const HEADER_CHUNK_SIZE: usize = 4096;
+
+impl<R: AsyncRead + AsyncSeek + Unpin> AsyncBank<R> {
+    /// Creates a new [`AsyncBank<R>`] by parsing from an asynchronous I/O stream, starting at its current position.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if getting the current position of `source` failed,
+    /// or if parsing of the sound bank's file header failed. See [`DecodeError`] for more information.
+    pub async fn new(source: R) -> Result<Self, DecodeError> {
+        Self::with_options(source, ParseOptions::default()).await
+    }
+
+    /// Creates a new [`AsyncBank<R>`] by parsing from an asynchronous I/O stream, with the given [`ParseOptions`].
+    ///
+    /// See [`AsyncBank::new`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if getting the current position of `source` failed,
+    /// or if parsing of the sound bank's file header failed. See [`DecodeError`] for more information.
+    pub async fn with_options(mut source: R, options: ParseOptions) -> Result<Self, DecodeError> {
+        let start = source
+            .stream_position()
+            .await
+            .map_err(HeaderError::io_factory(HeaderErrorKind::Position))?;
+
+        let mut header = Vec::new();
+        let mut error = None;
+        let mut at_end = false;
+
+        // The size of the file header isn't known before parsing it, so parsing is retried with more data
+        // whenever it ran out of data before the end of the source.
+        loop {
+            let mut prefetched = Prefetched {
+                data: &header,
+                error: error.take(),
+                exhausted: false,
+            };
+
+            let bank =
+                Bank::with_options(&mut prefetched, options).map(|bank| bank.with_source(empty()));
+
+            if at_end || !prefetched.exhausted {
+                return bank.map(|bank| Self {
+                    bank,
+                    source,
+                    start,
+                });
+            }
+
+            let len = header.len().max(HEADER_CHUNK_SIZE);
+
+            match (&mut source).take(len as u64).read_to_end(&mut header).await {
+                Ok(read) => at_end = read < len,
+                Err(e) => {
+                    error = Some(e);
+                    at_end = true;
+                }
+            }
+        }
+    }
+
+    /// Seeks to the data of the stream at `index` and reads it into memory as a [`Stream`].
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if there's no stream at `index`, or if seeking to or reading its data failed.
+    /// See [`SeekStreamError`] for more information.
+    pub async fn read_stream(&mut self, index: u32) -> Result<Stream, SeekStreamError> {
+        let (offset, size) = self
+            .bank
+            .stream_location(index)
+            .ok_or_else(|| SeekStreamError::new(index, SeekStreamErrorKind::UnknownStream))?;
+
+        let _position = self
+            .source
+            .seek(SeekFrom::Start(self.start + offset))
+            .await
+            .map_err(SeekStreamError::from_io(index, SeekStreamErrorKind::Seek))?;
+
+        let mut data = vec![0; size as usize];
+        let _data = self
+            .source
+            .read_exact(&mut data)
+            .await
+            .map_err(SeekStreamError::from_io(index, SeekStreamErrorKind::Read))?;
+
+        self.bank
+            .stream_from_data(index, data.into_boxed_slice())
+            .ok_or_else(|| SeekStreamError::new(index, SeekStreamErrorKind::UnknownStream))
+    }
+
+    /// Reads the stream at `index`, encodes it, and writes the encoded file to `sink`.
+    /// This is the asynchronous counterpart of [`Bank::extract_stream`].
+    ///
+    /// The sink is flushed after the file is written, and returned afterwards.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if there's no stream at `index`, if seeking to or reading its data failed,
+    /// if its data could not be encoded, or if writing to `sink` failed.
+    /// See [`SeekStreamError`] for more information.
+    pub async fn extract_stream<W: AsyncWrite + Unpin>(
+        &mut self,
+        index: u32,
+        sink: W,
+    ) -> Result<W, SeekStreamError> {
+        self.extract_stream_with_options(index, sink, EncodeOptions::default())
+            .await
+    }
+
+    /// Reads the stream at `index`, encodes it using the provided [`EncodeOptions`],
+    /// and writes the encoded file to `sink`.
+    ///
+    /// See [`AsyncBank::extract_stream`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if there's no stream at `index`, if seeking to or reading its data failed,
+    /// if its data could not be encoded, or if writing to `sink` failed.
+    /// See [`SeekStreamError`] for more information.
+    pub async fn extract_stream_with_options<W: AsyncWrite + Unpin>(
+        &mut self,
+        index: u32,
+        mut sink: W,
+        options: EncodeOptions<'_>,
+    ) -> Result<W, SeekStreamError> {
+        let file = self
+            .read_stream(index)
+            .await?
+            .write_with_options(Vec::new(), options)
+            .map_err(SeekStreamError::from_encode(index))?;
+
+        let write_error = SeekStreamError::from_io(index, SeekStreamErrorKind::Write);
+
+        match sink.write_all(&file).await {
+            Ok(()) => sink.flush().await,
+            Err(e) => Err(e),
+        }
+        .map_err(write_error)?;
+
+        Ok(sink)
+    }
+}
+
+impl<R> AsyncBank<R> {
+    /// Returns the audio format of the streams in the sound bank. See [`Bank::format`] for more information.
+    #[must_use]
+    pub fn format(&self) -> AudioFormat {
+        self.bank.format()
+    }
+
+    /// Returns the number of streams in the sound bank.
+    #[must_use]
+    pub fn num_streams(&self) -> NonZeroU32 {
+        self.bank.num_streams()
+    }
+
+    /// Returns information about the stream at `index`, or [`None`] if there's no stream at `index`.
+    #[must_use]
+    pub fn stream_info(&self, index: u32) -> Option<&StreamInfo> {
+        self.bank.stream_info(index)
+    }
+
+    /// Returns information about every stream in the sound bank, in order of streams.
+    #[must_use]
+    pub fn stream_infos(&self) -> &[StreamInfo] {
+        self.bank.stream_infos()
+    }
+
+    /// Returns the index of the first stream named `name`, or [`None`] if no stream has that name.
+    /// See [`Bank::find`] for more information.
+    #[must_use]
+    pub fn find(&self, name: &str) -> Option<u32> {
+        self.bank.find(name)
+    }
+
+    /// Returns the metadata of the sound bank and its streams as JSON. See [`Bank::metadata_json`] for the fields.
+    #[must_use]
+    pub fn metadata_json(&self) -> String {
+        self.bank.metadata_json()
+    }
+
+    /// Consumes the [`AsyncBank<R>`], returning its source.
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+}
+
+// Parses the file header from the part of it that was read so far.
+// Reading past the end of that part marks it as exhausted, so parsing can be retried with more data.
+struct Prefetched<'data> {
+    data: &'data [u8],
+    // an error from reading more data, returned once a read reaches it
+    error: Option<IoError>,
+    exhausted: bool,
+}
+
+impl Read for Prefetched<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        // reads aren't retried after a short read, so reads that can't be filled completely count as running out
+        if buf.len() > self.data.len() {
+            if let Some(e) = self.error.take() {
+                return Err(e);
+            }
+
+            self.exhausted = true;
+        }
+
+        Read::read(&mut self.data, buf)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AsyncBank, HEADER_CHUNK_SIZE};
+    use crate::{
+        test_bank::{TestBank, TestStream},
+        SeekStreamErrorKind, Stream,
+    };
+    use std::io::Cursor;
+    use tokio::runtime::Builder;
+
+    // A version 1 sound bank with `count` PCM16 streams of 2 samples, all named "stream".
+    fn bank(count: u32) -> Vec<u8> {
+        // 44100 Hz, 1 channel, 2 samples
+        const STREAM_HEADER: u64 = (8 << 1) | (2 << 34);
+
+        let streams = (0..count).map(|index| {
+            let mut data = [0; 32];
+            data[..4].fill(u8::try_from(index % 256).unwrap());
+            TestStream::new(STREAM_HEADER, &data).name("stream")
+        });
+        TestBank::new(2).streams(streams).build()
+    }
+
+    // Returns the bytes of the first 2 samples of a stream, written as a WAVE file.
+    fn samples(stream: Stream) -> [u8; 4] {
+        stream.write(Vec::new()).unwrap()[44..48].try_into().unwrap()
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        Builder::new_current_thread().build().unwrap().block_on(future)
+    }
+
+    #[test]
+    fn parse_large_header_in_chunks() {
+        // the file header spans several chunks
+        let data = bank(1000);
+        assert!(data.len() > 4 * HEADER_CHUNK_SIZE);
+
+        let mut bank = block_on(AsyncBank::new(Cursor::new(data))).unwrap();
+        assert_eq!(bank.num_streams().get(), 1000);
+        assert_eq!(bank.find("stream"), Some(0));
+
+        let stream = block_on(bank.read_stream(999)).unwrap();
+        assert_eq!(stream.index(), 999);
+        assert_eq!(samples(stream), [231; 4]);
+
+        let stream = block_on(bank.read_stream(1)).unwrap();
+        assert_eq!(samples(stream), [1; 4]);
+    }
+
+    #[test]
+    fn parse_after_start_of_source() {
+        let mut data = vec![0xff; 100];
+        data.extend_from_slice(&bank(1));
+
+        let mut source = Cursor::new(data);
+        source.set_position(100);
+
+        let mut bank = block_on(AsyncBank::new(source)).unwrap();
+        let file = block_on(bank.extract_stream(0, Vec::new())).unwrap();
+        assert_eq!(file[44..48], [0; 4]);
+    }
+
+    #[test]
+    fn report_async_errors() {
+        assert!(block_on(AsyncBank::new(Cursor::new(b"not a sound bank"))).is_err());
+
+        let mut data = bank(1);
+        let mut unknown = block_on(AsyncBank::new(Cursor::new(data.clone()))).unwrap();
+        let error = block_on(unknown.extract_stream(1, Vec::new())).unwrap_err();
+        assert_eq!(error.kind(), SeekStreamErrorKind::UnknownStream);
+
+        // the data of the stream is cut off
+        data.truncate(data.len() - 30);
+        let mut truncated = block_on(AsyncBank::new(Cursor::new(data))).unwrap();
+        let error = block_on(truncated.extract_stream(0, Vec::new())).unwrap_err();
+        assert_eq!(error.kind(), SeekStreamErrorKind::Read);
+    }
+}
@@ -1,11 +1,35 @@
-use crate::header::{error::HeaderError, AudioFormat, Header};
+use crate::checksum::{checksum, ChecksumWriter, StreamChecksums};
+use crate::decrypt::Decrypt;
+#[cfg(feature = "decode-vorbis")]
+use crate::encode::check_vorbis;
+use crate::encode::{check_mpeg, encode_with_stats, EmptyStreams, EncodeError, EncodeOptions};
+use crate::extract::{
+    DirectoryFactory, ExtractError, ExtractErrorKind, ExtractOptions, ExtractedFile,
+    ExtractionReport, LenientReport, PartialStream, Progress, ProgressSink, SinkFactory,
+};
+use crate::file::{BankFile, OpenError};
+use crate::header::{
+    error::{HeaderError, HeaderErrorKind},
+    AudioFormat, BankFlags, Header, OffsetWarning, ParseOptions, StreamInfo, FSB5_MAGIC,
+};
+use crate::metadata;
+#[cfg(feature = "parallel")]
+use crate::parallel::{self, ParallelOptions};
 use crate::read::{ReadError, Reader};
-use crate::stream::{LazyStream, Stream, StreamIntoIter};
+use crate::stream::{LazyStream, Stream, StreamIntoIter, Streams};
+use crate::verify::{FindingKind, FindingSource, VerifyOptions, VerifyReport};
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
+#[cfg(feature = "mmap")]
+use std::fs::File;
 use std::{
+    cmp::min,
     error::Error,
     fmt::{Display, Formatter, Result as FmtResult},
-    io::Read,
+    fs::metadata,
+    io::{Cursor, Error as IoError, Read, Seek, SeekFrom, Write},
     num::NonZeroU32,
+    path::Path,
 };
 use tap::Pipe;
 
@@ -43,13 +67,26 @@ use tap::Pipe;
 /// }
 /// ```
 ///
+/// [`Bank::open`] does the same in one call, with errors that include the path of the file.
+///
+/// # Serialization
+///
+/// The metadata of a sound bank can be written as JSON with [`Bank::metadata_json`], or serialized with the `serde` feature,
+/// as an inventory of its streams without any stream data. Both have the same stable fields: `index`, `version`,
+/// `minor_version`, `format`, `flags`, `hash`, `guid`, `offset_warnings`, and `num_streams`, named after the accessor
+/// that returns their value, followed by `streams`, which holds the information about every stream
+/// (see [`StreamInfo`] for its fields).
+///
 /// [`AudioFormat`]: crate::header::AudioFormat
 /// [`File`]: std::fs::File
 /// [`Path`]: std::path::Path
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Bank<R: Read> {
+    index: u32,
     header: Header,
     read: Reader<R>,
+    // position of the start of stream data, right after the file header
+    data_start: u64,
 }
 
 impl<R: Read> Bank<R> {
@@ -67,9 +104,105 @@ impl<R: Read> Bank<R> {
     /// [`File`]: std::fs::File
     /// [`BufReader`]: std::io::BufReader
     pub fn new(source: R) -> Result<Self, DecodeError> {
-        let mut read = Reader::new(source);
-        let header = Header::parse(&mut read)?;
-        Ok(Self { header, read })
+        Self::with_options(source, ParseOptions::default())
+    }
+
+    /// Creates a new [`Bank<R>`] by parsing from an I/O stream, with the given [`ParseOptions`].
+    ///
+    /// See [`Bank::new`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if parsing of the sound bank's file header failed.
+    /// See [`DecodeError`] for more information.
+    pub fn with_options(source: R, options: ParseOptions) -> Result<Self, DecodeError> {
+        Self::from_reader(Reader::new(source), options)
+    }
+
+    fn from_reader(mut read: Reader<R>, options: ParseOptions) -> Result<Self, DecodeError> {
+        let header = Header::parse_with_options(&mut read, options)?;
+        Ok(Self {
+            index: 0,
+            header,
+            data_start: read.position(),
+            read,
+        }
+        .with_offset(0))
+    }
+
+    /// Returns the index of the sound bank within its file.
+    ///
+    /// This is 0 unless the sound bank was parsed with [`Bank::parse_all`] from a file holding multiple sound banks,
+    /// or read from an FMOD Studio bank file with [`StudioBank`](crate::studio::StudioBank).
+    #[must_use]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub(crate) fn with_index(mut self, index: u32) -> Self {
+        self.index = index;
+        self
+    }
+
+    // Sets the position of the sound bank in its file, which locates the data of its streams in the file.
+    pub(crate) fn with_offset(mut self, offset: u64) -> Self {
+        for info in &mut self.header.stream_info {
+            info.data_start = self.data_start;
+            info.bank_offset = offset;
+        }
+        self
+    }
+
+    // Keeps the parsed file header, but reads from another source,
+    // for when stream data is read some other way (such as asynchronously).
+    #[cfg(feature = "async")]
+    pub(crate) fn with_source<S: Read>(self, source: S) -> Bank<S> {
+        Bank {
+            index: self.index,
+            header: self.header,
+            read: Reader::new(source),
+            data_start: self.data_start,
+        }
+    }
+
+    // Returns the position of the data of the stream at `index` relative to the start of the sound bank,
+    // and its size in bytes.
+    #[cfg(feature = "async")]
+    pub(crate) fn stream_location(&self, index: u32) -> Option<(u64, u32)> {
+        self.header
+            .stream_info
+            .get(index as usize)
+            .map(|info| (self.data_start + info.data_offset, info.size))
+    }
+
+    // Builds the stream at `index` from its data, which was read by the caller.
+    #[cfg(feature = "async")]
+    pub(crate) fn stream_from_data(&self, index: u32, data: Box<[u8]>) -> Option<Stream> {
+        self.header.stream_info.get(index as usize).map(|info| {
+            Stream::new(
+                index,
+                self.index,
+                self.header.format,
+                self.header.flags,
+                info.clone(),
+                data,
+            )
+        })
+    }
+
+    /// Returns the major version of the sound bank's format (3, 4, or 5), taken from its file signature.
+    #[must_use]
+    pub fn version(&self) -> u8 {
+        self.header.version
+    }
+
+    /// Returns the minor version of the sound bank's file header.
+    ///
+    /// This is 0 or 1 for FSB5 banks. FSB3 and FSB4 banks store it in the lower 16 bits of their version
+    /// (e.g. 1 for FSB3.1 banks).
+    #[must_use]
+    pub fn minor_version(&self) -> u32 {
+        self.header.minor_version
     }
 
     /// Returns the audio format of streams in the sound bank.
@@ -80,6 +213,41 @@ impl<R: Read> Bank<R> {
         self.header.format
     }
 
+    /// Returns the flags of the sound bank's file header.
+    ///
+    /// See [`BankFlags`] for more information.
+    #[must_use]
+    pub fn flags(&self) -> BankFlags {
+        BankFlags::new(self.header.flags)
+    }
+
+    /// Returns the problems found with the data offsets of streams, if the sound bank was parsed
+    /// with [`ParseOptions::lenient_offsets`] enabled. This is always empty otherwise.
+    ///
+    /// See [`OffsetWarning`] for more information.
+    #[must_use]
+    pub fn offset_warnings(&self) -> &[OffsetWarning] {
+        &self.header.offset_warnings
+    }
+
+    /// Returns the hash stored in the sound bank's file header, exactly as stored.
+    ///
+    /// FMOD changes the hash whenever a bank is rebuilt, so it can be used to detect whether a bank changed
+    /// (e.g. between patches of a game). FSB5 banks store a 16-byte hash and FSB4 banks store an 8-byte hash;
+    /// FSB3 banks have no hash, so this is empty for them.
+    #[must_use]
+    pub fn hash(&self) -> &[u8] {
+        &self.header.hash
+    }
+
+    /// Returns the GUID stored in the sound bank's file header, exactly as stored.
+    ///
+    /// Only FSB4 banks store a GUID, so this is [`None`] for other banks.
+    #[must_use]
+    pub fn guid(&self) -> Option<[u8; 16]> {
+        self.header.guid
+    }
+
     /// Returns the number of streams in the sound bank.
     #[allow(clippy::missing_panics_doc)]
     #[must_use]
@@ -93,6 +261,141 @@ impl<R: Read> Bank<R> {
             .expect("stream count was already validated to be NonZeroU32")
     }
 
+    /// Returns information about the stream at `index`, or `None` if no stream exists at that index.
+    /// No stream data is read, so this can be used to inspect streams before extracting them.
+    #[must_use]
+    pub fn stream_info(&self, index: u32) -> Option<&StreamInfo> {
+        self.header.stream_info.get(index as usize)
+    }
+
+    /// Returns information about every stream in the sound bank, in the order their data is stored.
+    /// No stream data is read, so this can be used to list the contents of a sound bank.
+    #[must_use]
+    pub fn stream_infos(&self) -> &[StreamInfo] {
+        &self.header.stream_info
+    }
+
+    /// Returns the position of the source, in bytes from the start of the sound bank.
+    ///
+    /// Right after parsing, this is the end of the file header, which is where the data of the first stream starts.
+    /// Reading streams moves the source forward (or anywhere in the stream data, for sources that can seek),
+    /// so this is where the last read of stream data stopped.
+    #[must_use]
+    pub fn position(&self) -> u64 {
+        self.read.position()
+    }
+
+    /// Returns a reference to the source that the sound bank is read from.
+    ///
+    /// The source can't be borrowed mutably, since streams are read from the position that the sound bank
+    /// expects the source to be at.
+    #[must_use]
+    pub fn get_ref(&self) -> &R {
+        self.read.get_ref()
+    }
+
+    /// Consumes the [`Bank<R>`], returning the source that it was read from.
+    ///
+    /// Sources are read without any buffering of their own, so the source is left exactly at [`position`](Self::position)
+    /// (relative to where the source was when parsing started): right after the file header if no streams were read,
+    /// or where the last read of stream data stopped. This lets the source be used for other reads,
+    /// such as seeking back to copy the sound bank out of a larger file, without opening it again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fsbex::Bank;
+    /// use std::{
+    ///     error::Error,
+    ///     io::{Cursor, Read, Seek, SeekFrom},
+    /// };
+    ///
+    /// fn copy_file_header(bytes: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    ///     let bank = Bank::seekable(Cursor::new(bytes))?;
+    ///     let header_size = bank.position();
+    ///
+    ///     let mut source = bank.into_inner();
+    ///     source.seek(SeekFrom::Start(0))?;
+    ///
+    ///     let mut header = Vec::new();
+    ///     source.take(header_size).read_to_end(&mut header)?;
+    ///     Ok(header)
+    /// }
+    /// ```
+    #[must_use]
+    pub fn into_inner(self) -> R {
+        self.read.into_inner()
+    }
+
+    /// Returns the metadata of the sound bank and its streams as a JSON object, without reading any stream data.
+    ///
+    /// The fields are listed in the documentation of [`Bank`], and they're the same as the fields serialized with
+    /// the `serde` feature. Every stream is written on its own line, so that the metadata of two versions
+    /// of a sound bank can be compared with line-based diff tools.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fsbex::Bank;
+    /// use std::{error::Error, fs::write};
+    ///
+    /// fn save_inventory(bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+    ///     let bank = Bank::new(bytes)?;
+    ///     write("inventory.json", bank.metadata_json())?;
+    ///     Ok(())
+    /// }
+    /// ```
+    #[allow(clippy::missing_panics_doc)]
+    #[must_use]
+    pub fn metadata_json(&self) -> String {
+        let mut json = Vec::new();
+        self.write_metadata_json(&mut json)
+            .expect("writing to a Vec should never fail");
+        String::from_utf8(json).expect("JSON output should be valid UTF-8")
+    }
+
+    /// Writes the metadata of the sound bank and its streams as a JSON object to `writer`.
+    ///
+    /// See [`Bank::metadata_json`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if writing to `writer` failed.
+    pub fn write_metadata_json<W: Write>(&self, mut writer: W) -> Result<(), IoError> {
+        metadata::write_bank(self, &mut writer)
+    }
+
+    /// Returns the index of the first stream named `name`, or `None` if no stream has that name.
+    /// Names are matched exactly, so the comparison is case-sensitive.
+    ///
+    /// Sound banks can contain multiple streams with the same name; [`Bank::find_all`] returns all of them.
+    #[must_use]
+    pub fn find(&self, name: &str) -> Option<u32> {
+        self.find_with(name, NameMatch::Exact)
+    }
+
+    /// Returns the index of the first stream whose name matches `name` as described by [`NameMatch`],
+    /// or `None` if no stream matches.
+    #[must_use]
+    pub fn find_with(&self, name: &str, matching: NameMatch) -> Option<u32> {
+        self.find_all(name, matching).next()
+    }
+
+    /// Returns an iterator over the indices of every stream whose name matches `name`
+    /// as described by [`NameMatch`], in ascending order.
+    pub fn find_all<'a>(
+        &'a self,
+        name: &'a str,
+        matching: NameMatch,
+    ) -> impl Iterator<Item = u32> + 'a {
+        self.header
+            .stream_info
+            .iter()
+            .zip(0..)
+            .filter(move |(info, _)| info.name().is_some_and(|n| matching.matches(n, name)))
+            .map(|(_, index)| index)
+    }
+
     /// Sequentially reads streams from the sound bank, consuming this [`Bank<R>`].
     /// Streams can be accessed within the function `f` as they are read.
     /// See [`LazyStream`] for more information.
@@ -108,12 +411,18 @@ impl<R: Read> Bank<R> {
     where
         F: Fn(LazyStream<'_, R>) -> Result<(), E>,
     {
+        // streams accessed with `Bank::stream` can leave the reader anywhere in the stream data
+        self.read
+            .seek_to(self.data_start)
+            .map_err(LazyStreamError::from_read(0))?;
+
         for (info, index) in self.header.stream_info.iter().zip(0..) {
-            let size = info.size.get() as usize;
+            let size = u64::from(info.size);
             let start_pos = self.read.position();
 
             f(LazyStream::new(
                 index,
+                self.index,
                 self.header.format,
                 self.header.flags,
                 info,
@@ -127,102 +436,2801 @@ impl<R: Read> Bank<R> {
         }
         Ok(())
     }
-}
 
-impl<R: Read> From<Bank<R>> for StreamIntoIter<R> {
-    fn from(value: Bank<R>) -> Self {
-        Self::new(
-            value.header.format,
-            value.header.flags,
-            value.header.stream_info,
-            value.read,
-        )
+    /// Sequentially writes streams from the sound bank to writers created by `factory`, consuming this [`Bank<R>`].
+    /// Streams are encoded with the options returned by [`SinkFactory::options`].
+    /// Streams that `factory` doesn't create a writer for are skipped without being encoded.
+    ///
+    /// The stream data of each stream is read into memory before it's encoded, so that streams cut short
+    /// by the end of a truncated sound bank can be detected. These streams are extracted from the data that's left,
+    /// and listed in the returned [`ExtractionReport`], along with the streams after them, which aren't extracted.
+    ///
+    /// Streams made up of multiple layers can't be written to a single writer,
+    /// so they have to be written with [`LazyStream::write_layers`] instead.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if:
+    /// - `factory` failed to create a writer
+    /// - a stream could not be successfully written
+    /// - the underlying reader failed to read stream data
+    ///
+    /// See [`ExtractError`] for more information.
+    pub fn extract_with<F: SinkFactory>(
+        mut self,
+        factory: F,
+    ) -> Result<ExtractionReport, ExtractError<F::Error>> {
+        self.extract_from(factory, |_| {}, None)
     }
-}
 
-impl<R: Read> IntoIterator for Bank<R> {
-    type IntoIter = StreamIntoIter<R>;
-    type Item = Stream;
+    /// Sequentially writes streams from the sound bank to writers created by `factory`, consuming this [`Bank<R>`],
+    /// and calls `on_progress` as streams are written.
+    ///
+    /// `on_progress` is called after every [`Progress::INTERVAL`] bytes written to a writer,
+    /// and once more after each stream is done (even if it was skipped). See [`Progress`] for more information,
+    /// and [`Bank::extract_with`] for more information about extraction.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if:
+    /// - `factory` failed to create a writer
+    /// - a stream could not be successfully written
+    /// - the underlying reader failed to read stream data
+    ///
+    /// See [`ExtractError`] for more information.
+    pub fn extract_with_progress<F: SinkFactory, P: FnMut(Progress)>(
+        mut self,
+        factory: F,
+        on_progress: P,
+    ) -> Result<ExtractionReport, ExtractError<F::Error>> {
+        self.extract_from(factory, on_progress, None)
+    }
 
-    fn into_iter(self) -> Self::IntoIter {
-        Self::IntoIter::from(self)
+    /// Sequentially writes streams from the sound bank to writers created by `factory`, consuming this [`Bank<R>`],
+    /// without stopping at streams that fail to be extracted.
+    ///
+    /// A stream that fails to be extracted (because `factory` failed to create its writer, or it could not be
+    /// encoded) is skipped over, and its error is listed in [`LenientReport::errors`], in order of streams.
+    /// Streams that were extracted are listed in [`ExtractionReport::extracted_streams`], so the returned
+    /// [`LenientReport`] has the same shape whether or not any stream failed. A writer whose stream failed
+    /// to be encoded may have been written to in part. See [`Bank::extract_with`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the underlying reader failed to read stream data,
+    /// since the streams after it can't be found. See [`ExtractError`] for more information.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use fsbex::{Bank, DirectoryFactory};
+    /// use std::{error::Error, fs::File, io::BufReader};
+    ///
+    /// fn extract_all(path: &str) -> Result<(), Box<dyn Error>> {
+    ///     let bank = Bank::new(BufReader::new(File::open(path)?))?;
+    ///     let factory = DirectoryFactory::new("output", bank.format());
+    ///     let report = bank.extract_lenient(factory)?;
+    ///
+    ///     println!("{} streams extracted", report.report().extracted_streams().len());
+    ///     for error in report.errors() {
+    ///         println!("{error}");
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn extract_lenient<F: SinkFactory>(
+        self,
+        factory: F,
+    ) -> Result<LenientReport<F::Error>, ExtractError<F::Error>> {
+        self.extract_lenient_with_progress(factory, |_| {})
     }
-}
 
-/// Represents an error that can occur when parsing a sound bank.
-///
-/// This type is returned from [`Bank::new`] when file header parsing fails.
-/// This can be caused by invalid data or the underlying reader encountering an I/O error.
-#[derive(Debug)]
-pub struct DecodeError {
-    inner: Box<HeaderError>,
-}
+    /// Sequentially writes streams from the sound bank to writers created by `factory`, consuming this [`Bank<R>`],
+    /// without stopping at streams that fail to be extracted, and calls `on_progress` as streams are written.
+    ///
+    /// See [`Bank::extract_lenient`] and [`Bank::extract_with_progress`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the underlying reader failed to read stream data,
+    /// since the streams after it can't be found. See [`ExtractError`] for more information.
+    pub fn extract_lenient_with_progress<F: SinkFactory, P: FnMut(Progress)>(
+        mut self,
+        factory: F,
+        on_progress: P,
+    ) -> Result<LenientReport<F::Error>, ExtractError<F::Error>> {
+        let mut errors = Vec::new();
+        let report = self.extract_from(factory, on_progress, Some(&mut errors))?;
 
-impl From<HeaderError> for DecodeError {
-    fn from(value: HeaderError) -> Self {
-        Self {
-            inner: Box::new(value),
-        }
+        Ok(LenientReport { report, errors })
     }
-}
 
-impl Display for DecodeError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        self.inner.fmt(f)
+    /// Writes every stream from the sound bank to a file in `dir`, with the given [`ExtractOptions`].
+    /// The directory is created if it doesn't exist.
+    ///
+    /// Files are named after the names of streams (or `stream_{index}`, with the index padded with zeros so that
+    /// files sort in order of streams), with the extension of the file format that streams are encoded as.
+    /// See [`DirectoryFactory`] for more information. The returned [`ExtractionReport`] lists the path and size
+    /// of every file that was written with [`ExtractionReport::files`], along with the streams that were skipped
+    /// or cut short.
+    ///
+    /// Like [`Bank::extract_with`], streams are read from the start of the stream data, so sound banks that
+    /// weren't created with [`Bank::seekable`] can only be extracted once.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if:
+    /// - the directory or a file could not be created
+    /// - a stream could not be successfully written
+    /// - the underlying reader failed to read stream data
+    ///
+    /// See [`ExtractError`] for more information.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use fsbex::{Bank, ExtractOptions};
+    /// use std::{error::Error, fs::File, io::BufReader};
+    ///
+    /// fn extract_all(path: &str) -> Result<(), Box<dyn Error>> {
+    ///     let mut bank = Bank::new(BufReader::new(File::open(path)?))?;
+    ///     let report = bank.extract_all_to_dir("output", &ExtractOptions::new().skip_existing(true))?;
+    ///
+    ///     for file in report.files() {
+    ///         println!("{}: {} bytes", file.path().display(), file.size());
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn extract_all_to_dir<P: AsRef<Path>>(
+        &mut self,
+        dir: P,
+        options: &ExtractOptions<'_>,
+    ) -> Result<ExtractionReport, ExtractError<IoError>> {
+        self.extract_all_to_dir_with_progress(dir, options, |_| {})
     }
-}
 
-impl Error for DecodeError {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
-        self.inner.source()
-    }
-}
+    /// Writes every stream from the sound bank to a file in `dir`, with the given [`ExtractOptions`],
+    /// and calls `on_progress` as streams are written.
+    ///
+    /// See [`Bank::extract_all_to_dir`] and [`Bank::extract_with_progress`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if:
+    /// - the directory or a file could not be created
+    /// - a stream could not be successfully written
+    /// - the underlying reader failed to read stream data
+    ///
+    /// See [`ExtractError`] for more information.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use fsbex::{Bank, ExtractOptions};
+    /// use std::{error::Error, fs::File, io::BufReader};
+    ///
+    /// fn extract_all(path: &str) -> Result<(), Box<dyn Error>> {
+    ///     let mut bank = Bank::new(BufReader::new(File::open(path)?))?;
+    ///
+    ///     bank.extract_all_to_dir_with_progress("output", &ExtractOptions::new(), |progress| {
+    ///         if progress.is_stream_done() {
+    ///             println!("{}/{} streams", progress.index() + 1, progress.num_streams());
+    ///         }
+    ///     })?;
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn extract_all_to_dir_with_progress<P: AsRef<Path>, F: FnMut(Progress)>(
+        &mut self,
+        dir: P,
+        options: &ExtractOptions<'_>,
+        on_progress: F,
+    ) -> Result<ExtractionReport, ExtractError<IoError>> {
+        let mut factory =
+            DirectoryFactory::with_extract_options(dir.as_ref(), self.header.format, *options)
+                .create_dir(true);
 
-/// Represents an error that can occur when reading sound bank streams with [`Bank::read_streams`].
-#[derive(Debug)]
-pub struct LazyStreamError<E> {
-    index: u32,
-    source: LazyStreamErrorSource<E>,
-}
+        let mut report = self.extract_from(&mut factory, on_progress, None)?;
 
-#[derive(Debug)]
-enum LazyStreamErrorSource<E> {
-    Read(ReadError),
-    Other(E),
-}
+        // files are flushed and closed once their stream is written, so their size is final
+        for (index, path) in factory.created {
+            let size = metadata(&path).map_err(ExtractError::from_factory(index))?.len();
 
-impl<E> LazyStreamError<E> {
-    fn from_read(index: u32) -> impl FnOnce(ReadError) -> Self {
-        move |source| Self {
-            index,
-            source: LazyStreamErrorSource::Read(source),
+            report.files.push(ExtractedFile { index, path, size });
         }
+
+        Ok(report)
     }
 
-    fn from_other(index: u32) -> impl FnOnce(E) -> Self {
-        move |source| Self {
-            index,
-            source: LazyStreamErrorSource::Other(source),
+    // Extracts every stream. With `errors`, streams that fail to be written are skipped over,
+    // and their errors are collected instead of stopping the extraction.
+    fn extract_from<F: SinkFactory, P: FnMut(Progress)>(
+        &mut self,
+        mut factory: F,
+        mut on_progress: P,
+        mut errors: Option<&mut Vec<ExtractError<F::Error>>>,
+    ) -> Result<ExtractionReport, ExtractError<F::Error>> {
+        let mut report = ExtractionReport::default();
+        let mut truncated = false;
+
+        let mut progress = Progress {
+            num_streams: self.num_streams().get(),
+            total_size: self.header.stream_info.iter().map(|info| u64::from(info.size)).sum(),
+            ..Progress::default()
+        };
+
+        // streams accessed with `Bank::stream` can leave the reader anywhere in the stream data
+        self.read
+            .seek_to(self.data_start)
+            .map_err(ExtractError::from_read(0))?;
+
+        for index in 0..progress.num_streams {
+            let size = self.header.stream_info[index as usize].size;
+            progress.start_stream(index, size);
+
+            // no stream data is left after the stream where a truncated sound bank ends
+            if truncated {
+                report.missing_streams.push(index);
+                progress.finish_stream(&mut on_progress);
+                continue;
+            }
+
+            let start_pos = self.read.position();
+            let result = self.extract_next(
+                index,
+                &mut factory,
+                &mut progress,
+                &mut on_progress,
+                &mut report,
+            );
+
+            let available = match (result, errors.as_deref_mut()) {
+                (Ok(available), _) => available,
+                // a stream that failed to be written is skipped over, unless the reader failed
+                (Err(e), Some(errors)) if e.kind() != ExtractErrorKind::ReadStream => {
+                    errors.push(e);
+
+                    let read = self.read.bytes_since(start_pos) as u64;
+                    let skipped = self
+                        .read
+                        .skip_available(u64::from(size).saturating_sub(read))
+                        .map_err(ExtractError::from_read(index))?;
+
+                    read + skipped
+                }
+                (Err(e), _) => return Err(e),
+            };
+
+            truncated = available < u64::from(size);
+            progress.finish_stream(&mut on_progress);
         }
-    }
 
-    /// Returns the index of the stream where the error occurred.
-    pub fn index(&self) -> u32 {
-        self.index
+        Ok(report)
     }
-}
 
-impl<E> Display for LazyStreamError<E> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        f.write_fmt(format_args!("failed to process stream at index {}", self.index))
-    }
-}
+    // Writes a single stream to the writer created by `factory`, returning the size of the stream data that was left.
+    #[allow(clippy::missing_panics_doc)]
+    fn extract_next<F: SinkFactory, P: FnMut(Progress)>(
+        &mut self,
+        index: u32,
+        factory: &mut F,
+        progress: &mut Progress,
+        on_progress: &mut P,
+        report: &mut ExtractionReport,
+    ) -> Result<u64, ExtractError<F::Error>> {
+        let info = &self.header.stream_info[index as usize];
+        let size = info.size as usize;
 
-impl<E: Error + 'static> Error for LazyStreamError<E> {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
-        match &self.source {
-            LazyStreamErrorSource::Read(e) => Some(e),
-            LazyStreamErrorSource::Other(e) => Some(e),
+        // placeholder streams are skipped before a writer is created for them
+        if info.size == 0 && factory.options().empty_streams == EmptyStreams::Skip {
+            factory.skipped(index, info);
+            report.skipped_streams.push(index);
+            return Ok(0);
         }
+
+        let Some(sink) = factory
+            .create(index, info)
+            .map_err(ExtractError::from_factory(index))?
+        else {
+            report.skipped_streams.push(index);
+            return self
+                .read
+                .skip_available(size as u64)
+                .map_err(ExtractError::from_read(index));
+        };
+
+        let start_pos = self.read.position();
+        let algorithm = factory.checksum();
+
+        // Sources that can seek tell how much data is left without reading it,
+        // so stream data is encoded straight from the source instead of being read into memory first.
+        // Checksums are computed from stream data in memory, so it's always read first for them.
+        let remaining = if algorithm.is_none() {
+            self.read.remaining()
+        } else {
+            None
+        };
+        let data = match remaining {
+            Some(remaining) => {
+                let remaining = remaining.map_err(ExtractError::from_read(index))?;
+                Err(min(remaining, u64::from(info.size)))
+            }
+            None => self
+                .read
+                .take_available(size)
+                .map(Ok)
+                .map_err(ExtractError::from_read(index))?,
+        };
+
+        let available = match &data {
+            Ok(data) => data.len() as u64,
+            Err(available) => *available,
+        };
+
+        // streams cut short are encoded as if their size was the size of the data that's left
+        let partial_info;
+        let info = if available < u64::from(info.size) {
+            partial_info = StreamInfo {
+                size: u32::try_from(available).expect("data is smaller than the stream"),
+                ..info.clone()
+            };
+            report.partial_streams.push(PartialStream {
+                index,
+                missing: info.size - partial_info.size,
+            });
+            &partial_info
+        } else {
+            info
+        };
+
+        let source_checksum = match (&data, algorithm) {
+            (Ok(data), Some(algorithm)) => Some(checksum(algorithm, data)),
+            _ => None,
+        };
+        let sink = ChecksumWriter::new(ProgressSink::new(sink, *progress, on_progress), algorithm);
+
+        let (sink, stats) = match &data {
+            Ok(data) => encode_with_stats(
+                self.header.format,
+                self.header.flags,
+                index,
+                info,
+                &mut Reader::new(data.as_slice()),
+                sink,
+                factory.options(),
+            ),
+            Err(_) => encode_with_stats(
+                self.header.format,
+                self.header.flags,
+                index,
+                info,
+                &mut self.read,
+                sink,
+                factory.options(),
+            ),
+        }
+        .map_err(ExtractError::from_encode(index))?;
+        let (sink, output_checksum) = sink.finish();
+
+        *progress = sink.into_progress();
+        report.extracted_streams.push(index);
+        report.add_stats(index, stats);
+
+        if let (Some(source), Some(output)) = (source_checksum, output_checksum) {
+            report.checksums.push(StreamChecksums {
+                index,
+                source,
+                output,
+            });
+        }
+
+        // encoders can stop before the end of the stream data
+        if data.is_err() {
+            self.read
+                .seek_to(start_pos + available)
+                .map_err(ExtractError::from_read(index))?;
+        }
+
+        Ok(available)
+    }
+
+    /// Checks that the sound bank is intact, without encoding any streams.
+    ///
+    /// The file header was already checked when the sound bank was parsed, so this checks what parsing doesn't:
+    /// that the audio format can be encoded, that every stream's data is within the sound bank, and the problems
+    /// that were tolerated while parsing (such as data offsets clamped with [`ParseOptions::lenient_offsets`]).
+    /// Sources that can seek are seeked past the stream data instead of reading it, so this is much cheaper
+    /// than extracting the sound bank. See [`Bank::verify_with_options`] for also checking the frames
+    /// of MPEG streams and the packets of Vorbis streams.
+    ///
+    /// Like [`Bank::extract_all_to_dir`], streams are read from the start of the stream data, so sound banks
+    /// that weren't created with [`Bank::seekable`] can only be verified once, and not extracted after.
+    ///
+    /// Problems are listed in the returned [`VerifyReport`] instead of being returned as errors,
+    /// including failures of the underlying reader, which stop the remaining streams from being checked.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use fsbex::Bank;
+    /// use std::{error::Error, fs::File, io::BufReader};
+    ///
+    /// fn is_intact(path: &str) -> Result<bool, Box<dyn Error>> {
+    ///     let mut bank = Bank::seekable(BufReader::new(File::open(path)?))?;
+    ///     let report = bank.verify();
+    ///
+    ///     for finding in report.findings() {
+    ///         println!("{finding}");
+    ///     }
+    ///
+    ///     Ok(report.is_intact())
+    /// }
+    /// ```
+    pub fn verify(&mut self) -> VerifyReport {
+        self.verify_with_options(VerifyOptions::default())
+    }
+
+    /// Checks that the sound bank is intact, without encoding any streams, with the given [`VerifyOptions`].
+    ///
+    /// With [`VerifyOptions::check_packets`], the frames of MPEG streams are read and have their CRC-16 checked
+    /// like with [`LazyStream::check_mpeg_frames`], and the packets of Vorbis streams are read and checked
+    /// against their setup header without being decoded. See [`Bank::verify`] for more information.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn verify_with_options(&mut self, options: VerifyOptions) -> VerifyReport {
+        let mut report = VerifyReport::default();
+        let format = self.header.format;
+
+        if !format.is_supported() {
+            report.push(FindingKind::UnsupportedFormat, None, None);
+        }
+
+        for warning in &self.header.offset_warnings {
+            report.push(
+                FindingKind::DataOffset(warning.kind()),
+                Some(warning.index()),
+                Some(self.data_start + warning.offset()),
+            );
+        }
+
+        // streams accessed with `Bank::stream` can leave the reader anywhere in the stream data
+        if let Err(e) = self.read.seek_to(self.data_start) {
+            report.push_with_source(
+                FindingKind::ReadFailed,
+                None,
+                Some(self.data_start),
+                FindingSource::Read(e),
+            );
+            return report;
+        }
+
+        let check_packets = options.check_packets
+            && (format == AudioFormat::Mpeg
+                || (cfg!(feature = "decode-vorbis") && format == AudioFormat::Vorbis));
+        let mut truncated = false;
+
+        for (info, index) in self.header.stream_info.iter().zip(0..) {
+            let offset = self.read.position();
+
+            if !info.unknown_chunks.is_empty() {
+                report.push(FindingKind::UnknownChunks, Some(index), Some(offset));
+            }
+
+            // no stream data is left after the stream where a truncated sound bank ends
+            if truncated {
+                report.push(FindingKind::MissingStream, Some(index), Some(offset));
+                continue;
+            }
+
+            let available = if check_packets {
+                self.read.take_available(info.size as usize).map(|data| {
+                    if data.len() == info.size as usize {
+                        check_stream_data(format, index, info, offset, &data, &mut report);
+                    }
+                    data.len() as u64
+                })
+            } else {
+                skip_stream_data(&mut self.read, u64::from(info.size))
+            };
+
+            let available = match available {
+                Ok(available) => available,
+                Err(e) => {
+                    report.push_with_source(
+                        FindingKind::ReadFailed,
+                        Some(index),
+                        Some(self.read.position()),
+                        FindingSource::Read(e),
+                    );
+                    return report;
+                }
+            };
+
+            truncated = available < u64::from(info.size);
+
+            if available == 0 && truncated {
+                report.push(FindingKind::MissingStream, Some(index), Some(offset));
+            } else if truncated {
+                report.push(
+                    FindingKind::TruncatedStream {
+                        missing: info.size
+                            - u32::try_from(available).expect("data is smaller than the stream"),
+                    },
+                    Some(index),
+                    Some(offset + available),
+                );
+            }
+        }
+
+        report
+    }
+}
+
+// Skips up to `size` bytes of stream data, returning the number of bytes that were skipped.
+// Sources that can seek tell how much data is left, so the stream data is seeked past without reading it.
+fn skip_stream_data<R: Read>(read: &mut Reader<R>, size: u64) -> Result<u64, ReadError> {
+    match read.remaining() {
+        Some(remaining) => {
+            let available = min(remaining?, size);
+            read.seek_to(read.position() + available)?;
+            Ok(available)
+        }
+        None => read.skip_available(size),
+    }
+}
+
+// Reads the frames or packets of a stream from its data without encoding it, for `Bank::verify_with_options`.
+fn check_stream_data(
+    format: AudioFormat,
+    index: u32,
+    info: &StreamInfo,
+    offset: u64,
+    data: &[u8],
+    report: &mut VerifyReport,
+) {
+    let result = match format {
+        AudioFormat::Mpeg => check_mpeg(format, info, &mut Reader::new(data)).map(|stats| {
+            let Some(stats) = stats else { return };
+
+            if stats.frames() == 0 {
+                report.push(FindingKind::NoMpegFrames, Some(index), Some(offset));
+            }
+            if stats.crc_mismatches() > 0 {
+                let frames = stats.crc_mismatches();
+                report.push(FindingKind::MpegCrcMismatch { frames }, Some(index), Some(offset));
+            }
+            if stats.skipped_free_format_headers() > 0 {
+                let headers = stats.skipped_free_format_headers();
+                report.push(
+                    FindingKind::MpegFreeFormatHeaders { headers },
+                    Some(index),
+                    Some(offset),
+                );
+            }
+        }),
+        #[cfg(feature = "decode-vorbis")]
+        AudioFormat::Vorbis => check_vorbis(format, info, &mut Reader::new(data)).map(|_| ()),
+        _ => Ok(()),
+    };
+
+    if let Err(e) = result {
+        report.push_with_source(
+            FindingKind::InvalidStreamData,
+            Some(index),
+            Some(offset),
+            FindingSource::Encode(e),
+        );
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<R: Read> Bank<R> {
+    /// Writes every stream from the sound bank to writers created by `factory`, encoding streams in parallel
+    /// with [rayon](https://crates.io/crates/rayon), and using the given [`ParallelOptions`].
+    ///
+    /// Streams are independent of each other, so this is much faster than [`Bank::extract_with`] for sound banks
+    /// with many streams that are expensive to encode, such as Vorbis streams. Stream data is read from the source
+    /// in order of streams, in batches of a few streams per thread, and writers are created for every stream
+    /// of a batch before the batch is encoded in parallel. The stream data and writers of a whole batch are held
+    /// at once, so memory use grows with the number of threads rather than the number of streams.
+    ///
+    /// Streams are encoded in the rayon thread pool that this is called from, so the number of threads can be
+    /// chosen by calling this within [`rayon::ThreadPool::install`]. The global thread pool is used otherwise.
+    ///
+    /// Like [`Bank::extract_with`], streams that `factory` doesn't create a writer for are skipped, and streams
+    /// cut short by the end of a truncated sound bank are extracted from the data that's left. These streams
+    /// are listed in the [`ExtractionReport`] of the returned [`LenientReport`], in order of streams.
+    ///
+    /// By default, a stream that fails to be extracted (because `factory` failed to create its writer,
+    /// or it could not be encoded) doesn't stop the other streams from being extracted. Its error is listed
+    /// in [`LenientReport::errors`] instead, in order of streams. With [`ParallelOptions::fail_fast`],
+    /// the first failure stops the extraction: streams that haven't started encoding yet are skipped,
+    /// and the error of the failed stream with the lowest index is returned.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if:
+    /// - the underlying reader failed to read stream data
+    /// - with [`ParallelOptions::fail_fast`], `factory` failed to create a writer or a stream could not be encoded
+    ///
+    /// See [`ExtractError`] for more information.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use fsbex::{Bank, DirectoryFactory, ParallelOptions};
+    /// use std::{error::Error, fs::File, io::BufReader};
+    ///
+    /// fn extract_all(path: &str) -> Result<(), Box<dyn Error>> {
+    ///     let mut bank = Bank::new(BufReader::new(File::open(path)?))?;
+    ///     let factory = DirectoryFactory::new("output", bank.format());
+    ///     let report = bank.extract_all_parallel(factory, ParallelOptions::new())?;
+    ///
+    ///     for error in report.errors() {
+    ///         println!("{error}");
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn extract_all_parallel<F>(
+        &mut self,
+        factory: F,
+        options: ParallelOptions,
+    ) -> Result<LenientReport<F::Error>, ExtractError<F::Error>>
+    where
+        F: SinkFactory,
+        F::Sink: Send,
+        F::Error: Send,
+    {
+        self.extract_all_parallel_with_progress(factory, options, |_| {})
+    }
+
+    /// Writes every stream from the sound bank to writers created by `factory`, encoding streams in parallel,
+    /// and calls `on_progress` whenever a stream is done.
+    ///
+    /// Unlike [`Bank::extract_with_progress`], progress is only reported once per stream, with
+    /// [`Progress::is_stream_done`] set. Streams finish in any order, and `on_progress` is called from the threads
+    /// that encode them. [`Progress::completed_size`] is the size of the streams that were done before,
+    /// so [`Progress::fraction`] still increases with every report.
+    /// See [`Bank::extract_all_parallel`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if:
+    /// - the underlying reader failed to read stream data
+    /// - with [`ParallelOptions::fail_fast`], `factory` failed to create a writer or a stream could not be encoded
+    ///
+    /// See [`ExtractError`] for more information.
+    pub fn extract_all_parallel_with_progress<F, P>(
+        &mut self,
+        factory: F,
+        options: ParallelOptions,
+        on_progress: P,
+    ) -> Result<LenientReport<F::Error>, ExtractError<F::Error>>
+    where
+        F: SinkFactory,
+        F::Sink: Send,
+        F::Error: Send,
+        P: Fn(Progress) + Sync,
+    {
+        // streams accessed with `Bank::stream` can leave the reader anywhere in the stream data
+        self.read
+            .seek_to(self.data_start)
+            .map_err(ExtractError::from_read(0))?;
+
+        parallel::extract_all(&self.header, &mut self.read, factory, options, on_progress)
+    }
+}
+
+impl<R: Read + Seek> Bank<R> {
+    /// Creates a new [`Bank<R>`] by seeking to `offset` in an I/O stream, then parsing from there.
+    ///
+    /// This parses a sound bank in place, such as one found inside a larger file with [`scan`](crate::scan).
+    /// See [`Bank::new`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if seeking to `offset` failed, or if parsing of the sound bank's
+    /// file header failed. See [`DecodeError`] for more information.
+    pub fn at_offset(mut source: R, offset: u64) -> Result<Self, DecodeError> {
+        source
+            .seek(SeekFrom::Start(offset))
+            .map_err(HeaderError::io_factory(HeaderErrorKind::Seek { offset }))
+            .map_err(DecodeError::from)
+            .and_then(|_| Self::seekable(source))
+            .map(|bank| bank.with_offset(offset))
+    }
+
+    /// Creates a new [`Bank<R>`] by parsing from an I/O stream that can seek.
+    ///
+    /// Like with [`Bank::new`], only the file header is parsed up front. Since the source can seek,
+    /// stream data is never buffered in memory: [`Bank::extract_with`] and [`LazyStream`] encode straight
+    /// from the source, and formats that need to read their data twice (MPEG streams with an ID3v2 tag
+    /// or a Xing header frame, and Vorbis streams decoded to PCM samples) seek back instead of buffering it.
+    /// Individual streams can be accessed in any order with [`Bank::stream`].
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if parsing of the sound bank's file header failed.
+    /// See [`DecodeError`] for more information.
+    pub fn seekable(source: R) -> Result<Self, DecodeError> {
+        Self::seekable_with_options(source, ParseOptions::default())
+    }
+
+    /// Creates a new [`Bank<R>`] by parsing from an I/O stream that can seek, with the given [`ParseOptions`].
+    ///
+    /// See [`Bank::seekable`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if parsing of the sound bank's file header failed.
+    /// See [`DecodeError`] for more information.
+    pub fn seekable_with_options(source: R, options: ParseOptions) -> Result<Self, DecodeError> {
+        Self::from_reader(Reader::seekable(source), options)
+    }
+
+    /// Seeks to the data of the stream at `index`, returning the stream as a [`LazyStream`]
+    /// that reads its data straight from the source.
+    ///
+    /// Streams can be accessed in any order, and more than once.
+    /// Other methods that read streams, such as [`Bank::read_streams`], still start from the first stream.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if there's no stream at `index`, or if seeking to its data failed.
+    /// See [`SeekStreamError`] for more information.
+    pub fn stream(&mut self, index: u32) -> Result<LazyStream<'_, R>, SeekStreamError> {
+        let info = self
+            .header
+            .stream_info
+            .get(index as usize)
+            .ok_or_else(|| SeekStreamError::new(index, SeekStreamErrorKind::UnknownStream))?;
+
+        self.read.enable_seeking();
+        self.read
+            .seek_to(self.data_start + info.data_offset)
+            .map_err(SeekStreamError::from_read(index, SeekStreamErrorKind::Seek))?;
+
+        Ok(LazyStream::new(
+            index,
+            self.index,
+            self.header.format,
+            self.header.flags,
+            info,
+            &mut self.read,
+        ))
+    }
+
+    /// Returns an iterator over the streams of the sound bank, which reads the data of every stream into memory
+    /// as a [`Stream`], in order of streams.
+    ///
+    /// Iterating with [`Bank::into_iter`] consumes the sound bank, so its streams can only be read once.
+    /// This method only borrows the sound bank instead, and every stream is read by seeking to its own data,
+    /// so streams can be iterated over any number of times. For example, streams can be listed first,
+    /// and selected streams extracted afterwards, without parsing the file header again.
+    /// Sound banks read from sources that can't seek only support the single pass of [`Bank::into_iter`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fsbex::Bank;
+    /// use std::{error::Error, io::Cursor};
+    ///
+    /// fn extract_longest(bytes: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    ///     let mut bank = Bank::seekable(Cursor::new(bytes))?;
+    ///
+    ///     for stream in bank.streams() {
+    ///         let stream = stream?;
+    ///         println!("{}: {} bytes", stream.index(), stream.size());
+    ///     }
+    ///
+    ///     let longest = bank
+    ///         .streams()
+    ///         .filter_map(Result::ok)
+    ///         .max_by_key(|stream| stream.sample_count())
+    ///         .ok_or("no stream could be read")?;
+    ///
+    ///     Ok(longest.write(Vec::new())?)
+    /// }
+    /// ```
+    pub fn streams(&mut self) -> Streams<'_, R> {
+        Streams::new(self)
+    }
+
+    pub(crate) fn read_stream(&mut self, index: u32) -> Result<Stream, SeekStreamError> {
+        let info = self
+            .header
+            .stream_info
+            .get(index as usize)
+            .ok_or_else(|| SeekStreamError::new(index, SeekStreamErrorKind::UnknownStream))?;
+
+        self.read.enable_seeking();
+        self.read
+            .seek_to(self.data_start + info.data_offset)
+            .map_err(SeekStreamError::from_read(index, SeekStreamErrorKind::Seek))?;
+
+        let data = self
+            .read
+            .take(info.size as usize)
+            .map_err(SeekStreamError::from_read(index, SeekStreamErrorKind::Read))?;
+
+        Ok(Stream::new(
+            index,
+            self.index,
+            self.header.format,
+            self.header.flags,
+            info.clone(),
+            data.into_boxed_slice(),
+        ))
+    }
+
+    /// Seeks to the data of the stream at `index` and encodes it to `sink`, leaving this [`Bank<R>`] usable
+    /// for further calls. This is a shorthand for [`Bank::stream`] followed by [`LazyStream::write`].
+    ///
+    /// Since every call seeks to the data of its stream, streams can be extracted in any order and more than once,
+    /// and calls can be mixed freely with [`Bank::stream`], even if a previous stream wasn't read to the end.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if there's no stream at `index`, if seeking to its data failed,
+    /// or if its data could not be encoded. See [`SeekStreamError`] for more information.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use fsbex::Bank;
+    /// use std::{error::Error, fs::File, io::BufWriter};
+    ///
+    /// fn export_stream(index: u32) -> Result<(), Box<dyn Error>> {
+    ///     let mut bank = Bank::seekable(File::open("sounds.fsb")?)?;
+    ///     let file = BufWriter::new(File::create(format!("{index}.wav"))?);
+    ///
+    ///     bank.extract_stream(index, file)?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn extract_stream<W: Write>(&mut self, index: u32, sink: W) -> Result<W, SeekStreamError> {
+        self.extract_stream_with_options(index, sink, EncodeOptions::default())
+    }
+
+    /// Seeks to the data of the stream at `index` and encodes it to `sink`, using the provided [`EncodeOptions`].
+    ///
+    /// See [`Bank::extract_stream`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if there's no stream at `index`, if seeking to its data failed,
+    /// or if its data could not be encoded. See [`SeekStreamError`] for more information.
+    pub fn extract_stream_with_options<W: Write>(
+        &mut self,
+        index: u32,
+        sink: W,
+        options: EncodeOptions<'_>,
+    ) -> Result<W, SeekStreamError> {
+        self.stream(index)?
+            .write_with_options(sink, options)
+            .map_err(SeekStreamError::from_encode(index))
+    }
+}
+
+impl<R: Read + Seek> Bank<R> {
+    /// Encodes the stream named `name` to `sink`, leaving this [`Bank<R>`] usable for further calls.
+    /// Names are matched exactly, so the comparison is case-sensitive.
+    ///
+    /// See [`Bank::extract_stream`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if no stream has the name, if multiple streams have the name,
+    /// or if the stream could not be extracted. See [`ExtractByNameError`] for more information.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use fsbex::{Bank, ExtractByNameErrorKind};
+    /// use std::{error::Error, fs::File, io::BufWriter};
+    ///
+    /// fn export_named_stream(name: &str) -> Result<(), Box<dyn Error>> {
+    ///     let mut bank = Bank::seekable(File::open("sounds.fsb")?)?;
+    ///     let file = BufWriter::new(File::create(format!("{name}.wav"))?);
+    ///
+    ///     match bank.extract_by_name(name, file) {
+    ///         Err(e) if e.kind() == ExtractByNameErrorKind::DuplicateName => {
+    ///             println!("streams named {name}: {:?}", e.candidates());
+    ///         }
+    ///         result => drop(result?),
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn extract_by_name<W: Write>(
+        &mut self,
+        name: &str,
+        sink: W,
+    ) -> Result<W, ExtractByNameError> {
+        self.extract_by_name_with_options(name, NameMatch::Exact, sink, EncodeOptions::default())
+    }
+
+    /// Encodes the stream whose name matches `name` as described by [`NameMatch`] to `sink`,
+    /// using the provided [`EncodeOptions`].
+    ///
+    /// See [`Bank::extract_by_name`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if no stream matches the name, if multiple streams match the name,
+    /// or if the stream could not be extracted. See [`ExtractByNameError`] for more information.
+    pub fn extract_by_name_with_options<W: Write>(
+        &mut self,
+        name: &str,
+        matching: NameMatch,
+        sink: W,
+        options: EncodeOptions<'_>,
+    ) -> Result<W, ExtractByNameError> {
+        let candidates: Box<[u32]> = self.find_all(name, matching).collect();
+
+        let index = match *candidates {
+            [index] => index,
+            [] => {
+                return Err(ExtractByNameError::new(
+                    name,
+                    ExtractByNameErrorKind::UnknownName,
+                    candidates,
+                ))
+            }
+            _ => {
+                return Err(ExtractByNameError::new(
+                    name,
+                    ExtractByNameErrorKind::DuplicateName,
+                    candidates,
+                ))
+            }
+        };
+
+        self.extract_stream_with_options(index, sink, options)
+            .map_err(ExtractByNameError::from_stream(name, candidates))
+    }
+}
+
+impl<T: AsRef<[u8]>> Bank<Cursor<T>> {
+    /// Returns the data of the stream at `index` exactly as it is stored in the sound bank, borrowed from the
+    /// bytes that the sound bank was parsed from, or [`None`] if there's no stream at `index`.
+    ///
+    /// No data is copied, which makes this the fastest way to access the raw data of streams in memory,
+    /// such as a file mapped with `Bank::from_mmap`. Streams cut short by the end of a truncated sound bank
+    /// return the data that's left.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fsbex::Bank;
+    /// use std::{error::Error, io::Cursor};
+    ///
+    /// fn total_stream_size(bytes: &[u8]) -> Result<usize, Box<dyn Error>> {
+    ///     let bank = Bank::seekable(Cursor::new(bytes))?;
+    ///     Ok((0..bank.num_streams().get()).filter_map(|index| bank.stream_data(index)).map(<[u8]>::len).sum())
+    /// }
+    /// ```
+    #[must_use]
+    pub fn stream_data(&self, index: u32) -> Option<&[u8]> {
+        let info = self.header.stream_info.get(index as usize)?;
+        let cursor = self.read.get_ref();
+        let bytes = cursor.get_ref().as_ref();
+
+        // positions of the reader are relative to where the cursor was when the sound bank was parsed
+        let bank_start = cursor.position() - self.read.position();
+        let start = bank_start + self.data_start + info.data_offset;
+        let end = start + u64::from(info.size);
+
+        let clamp = |position: u64| {
+            usize::try_from(position).map_or(bytes.len(), |position| min(position, bytes.len()))
+        };
+        bytes.get(clamp(start)..clamp(end))
+    }
+
+    /// Writes the data of the stream at `index` to `sink` exactly as it is stored in the sound bank,
+    /// with a single call to [`Write::write_all`], and returns the writer.
+    ///
+    /// This gives the same output as [`Bank::extract_stream_with_options`] with [`OutputMode::Raw`],
+    /// without reading the stream data through an intermediate buffer. See [`Bank::stream_data`]
+    /// for more information.
+    ///
+    /// [`OutputMode::Raw`]: crate::encode::OutputMode::Raw
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if there's no stream at `index`, if the stream was cut short by the end
+    /// of a truncated sound bank, or if writing to `sink` failed. See [`SeekStreamError`] for more information.
+    pub fn write_stream_data<W: Write>(
+        &self,
+        index: u32,
+        mut sink: W,
+    ) -> Result<W, SeekStreamError> {
+        let data = self
+            .stream_data(index)
+            .ok_or_else(|| SeekStreamError::new(index, SeekStreamErrorKind::UnknownStream))?;
+
+        if data.len() < self.header.stream_info[index as usize].size as usize {
+            return Err(SeekStreamError::new(index, SeekStreamErrorKind::Read));
+        }
+
+        sink.write_all(data)
+            .and_then(|()| sink.flush())
+            .map(|()| sink)
+            .map_err(SeekStreamError::from_io(index, SeekStreamErrorKind::Write))
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl Bank<Cursor<Mmap>> {
+    /// Creates a new [`Bank<R>`] by mapping the file at `path` into memory, then parsing it.
+    ///
+    /// Reading from the mapping is plain memory access, without read calls or intermediate buffers, which is faster
+    /// than reading large sound banks from a [`File`] (even with a [`BufReader`]). Pages of the file are only loaded
+    /// once they're accessed, so sound banks are parsed without reading their stream data. Streams can be accessed
+    /// in any order like sound banks created with [`Bank::seekable`], and the raw data of streams can be borrowed
+    /// straight from the mapping with [`Bank::stream_data`].
+    ///
+    /// [`File`]: std::fs::File
+    /// [`BufReader`]: std::io::BufReader
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while it's mapped, which is until the returned sound bank is
+    /// dropped. The mapping reflects changes made to the file by this or any other process, so such changes
+    /// break the assumption that borrowed data doesn't change, which is undefined behavior. Truncating the file
+    /// can also crash the process (with `SIGBUS` on Unix) when the missing pages are accessed.
+    /// Only map files that aren't written to, or read them with [`Bank::new`] instead.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the file could not be opened or mapped, or if parsing of the sound bank's
+    /// file header failed. See [`DecodeError`] for more information.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use fsbex::Bank;
+    /// use std::{error::Error, fs};
+    ///
+    /// fn copy_raw_streams(path: &str) -> Result<(), Box<dyn Error>> {
+    ///     // SAFETY: the sound bank isn't modified while it's mapped
+    ///     let bank = unsafe { Bank::from_mmap(path) }?;
+    ///
+    ///     for index in 0..bank.num_streams().get() {
+    ///         let file = fs::File::create(format!("stream_{index}.bin"))?;
+    ///         let _file = bank.write_stream_data(index, file)?;
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub unsafe fn from_mmap<P: AsRef<Path>>(path: P) -> Result<Self, DecodeError> {
+        // SAFETY: the caller guarantees that the file isn't modified while it's mapped
+        unsafe { Self::from_mmap_with_options(path, ParseOptions::default()) }
+    }
+
+    /// Creates a new [`Bank<R>`] by mapping the file at `path` into memory, then parsing it with the given
+    /// [`ParseOptions`].
+    ///
+    /// See [`Bank::from_mmap`] for more information.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while it's mapped. See [`Bank::from_mmap`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the file could not be opened or mapped, or if parsing of the sound bank's
+    /// file header failed. See [`DecodeError`] for more information.
+    pub unsafe fn from_mmap_with_options<P: AsRef<Path>>(
+        path: P,
+        options: ParseOptions,
+    ) -> Result<Self, DecodeError> {
+        let file = File::open(path).map_err(HeaderError::io_factory(HeaderErrorKind::Map))?;
+
+        // SAFETY: the caller guarantees that the file isn't modified while it's mapped
+        let map =
+            unsafe { Mmap::map(&file) }.map_err(HeaderError::io_factory(HeaderErrorKind::Map))?;
+
+        Self::seekable_with_options(Cursor::new(map), options)
+    }
+}
+
+impl Bank<BankFile> {
+    /// Creates a new [`Bank<R>`] by opening the file at `path` with buffering, then parsing it.
+    ///
+    /// This is a shortcut for opening the file, wrapping it in a [`BufReader`], and parsing it with
+    /// [`Bank::seekable`], so streams can be accessed in any order. Before parsing, the file signature is checked,
+    /// so that FMOD Studio bank files (.bank) and encrypted sound banks are reported as such,
+    /// instead of as a missing file signature.
+    ///
+    /// [`BufReader`]: std::io::BufReader
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the file could not be opened, if it's not a sound bank, or if parsing of the
+    /// sound bank's file header failed. The error includes `path`. See [`OpenError`] for more information.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use fsbex::Bank;
+    /// use std::error::Error;
+    ///
+    /// fn count_streams(path: &str) -> Result<u32, Box<dyn Error>> {
+    ///     let bank = Bank::open(path)?;
+    ///     Ok(bank.num_streams().get())
+    /// }
+    /// ```
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, OpenError> {
+        Self::open_with_options(path, ParseOptions::default())
+    }
+
+    /// Creates a new [`Bank<R>`] by opening the file at `path` with buffering, then parsing it with the given
+    /// [`ParseOptions`].
+    ///
+    /// See [`Bank::open`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the file could not be opened, if it's not a sound bank, or if parsing of the
+    /// sound bank's file header failed. The error includes `path`. See [`OpenError`] for more information.
+    pub fn open_with_options<P: AsRef<Path>>(
+        path: P,
+        options: ParseOptions,
+    ) -> Result<Self, OpenError> {
+        let path = path.as_ref();
+        Self::seekable_with_options(BankFile::open(path)?, options)
+            .map_err(OpenError::from_decode(path))
+    }
+}
+
+/// Describes how stream names are compared when searching a sound bank by name, with [`Bank::find_with`],
+/// [`Bank::find_all`], or [`Bank::extract_by_name_with_options`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum NameMatch {
+    /// Names have to be identical. This is the default.
+    #[default]
+    Exact,
+    /// Names have to be identical after converting them to lowercase, so letter case is ignored.
+    IgnoreCase,
+}
+
+impl NameMatch {
+    fn matches(self, stream_name: &str, name: &str) -> bool {
+        match self {
+            Self::Exact => stream_name == name,
+            Self::IgnoreCase => stream_name
+                .chars()
+                .flat_map(char::to_lowercase)
+                .eq(name.chars().flat_map(char::to_lowercase)),
+        }
+    }
+}
+
+impl<R: Read> Bank<Decrypt<R>> {
+    /// Creates a new [`Bank<R>`] by parsing from an I/O stream holding a sound bank encrypted with `key`,
+    /// which can be given as raw bytes or a string. The sound bank is decrypted as it is read,
+    /// using FMOD's scheme of reversing the bits of each byte, then combining it with the key using XOR.
+    ///
+    /// See [`Bank::new`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if `key` is empty, if no file signature was found after decryption
+    /// (which usually means the key is wrong, see [`DecodeError::is_wrong_key`]), or if parsing of the
+    /// sound bank's file header failed. See [`DecodeError`] for more information.
+    pub fn decrypt<K: AsRef<[u8]>>(source: R, key: K) -> Result<Self, DecodeError> {
+        Self::decrypt_with_options(source, key, ParseOptions::default())
+    }
+
+    /// Creates a new [`Bank<R>`] by parsing from an I/O stream holding a sound bank encrypted with `key`,
+    /// with the given [`ParseOptions`]. The scheme used for decryption is set with [`ParseOptions::xor_scheme`].
+    ///
+    /// See [`Bank::decrypt`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if `key` is empty, if no file signature was found after decryption
+    /// (which usually means the key is wrong, see [`DecodeError::is_wrong_key`]), or if parsing of the
+    /// sound bank's file header failed. See [`DecodeError`] for more information.
+    pub fn decrypt_with_options<K: AsRef<[u8]>>(
+        source: R,
+        key: K,
+        options: ParseOptions,
+    ) -> Result<Self, DecodeError> {
+        let key = key.as_ref();
+        if key.is_empty() {
+            return Err(HeaderError::new(HeaderErrorKind::EmptyKey).into());
+        }
+
+        Self::with_options(Decrypt::new(source, key, options.xor_scheme), options).map_err(|e| {
+            if e.inner.is_missing_magic() {
+                HeaderError::new(HeaderErrorKind::WrongKey).into()
+            } else {
+                e
+            }
+        })
+    }
+}
+
+// Sound banks stored back to back can be padded with zeros, so that the next bank starts at a multiple of 16 or 32 bytes.
+const MAX_BANK_PADDING: usize = 32;
+
+impl Bank<Cursor<Vec<u8>>> {
+    /// Parses every sound bank in an I/O stream holding multiple FSB5 sound banks back to back,
+    /// such as some FMOD Studio master banks, or files made by concatenating sound banks.
+    ///
+    /// After the data of each sound bank, padding of up to 32 zero bytes is skipped, and parsing continues
+    /// if another FSB5 sound bank follows. Any other data after a sound bank is ignored.
+    /// Each sound bank has an [`index`](Self::index) within the file, which is also available
+    /// on its streams to keep them apart (e.g. with [`DirectoryFactory::bank_index`]).
+    ///
+    /// Unlike [`Bank::new`], the whole I/O stream is read into memory.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use fsbex::{Bank, DirectoryFactory};
+    /// use std::{error::Error, fs::File, io::BufReader};
+    ///
+    /// fn extract_all(path: &str) -> Result<(), Box<dyn Error>> {
+    ///     for bank in Bank::parse_all(BufReader::new(File::open(path)?))? {
+    ///         let factory = DirectoryFactory::new("output", bank.format()).bank_index(bank.index());
+    ///         bank.extract_with(factory)?;
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if reading from the I/O stream failed,
+    /// or if parsing of a sound bank's file header failed. See [`DecodeError`] for more information.
+    ///
+    /// [`DirectoryFactory::bank_index`]: crate::DirectoryFactory::bank_index
+    pub fn parse_all<S: Read>(source: S) -> Result<Vec<Self>, DecodeError> {
+        Self::parse_all_with_options(source, ParseOptions::default())
+    }
+
+    /// Parses every sound bank in an I/O stream holding multiple FSB5 sound banks back to back,
+    /// with the given [`ParseOptions`].
+    ///
+    /// See [`Bank::parse_all`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if reading from the I/O stream failed,
+    /// or if parsing of a sound bank's file header failed. See [`DecodeError`] for more information.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn parse_all_with_options<S: Read>(
+        source: S,
+        options: ParseOptions,
+    ) -> Result<Vec<Self>, DecodeError> {
+        let data = Reader::new(source)
+            .take_available(usize::MAX)
+            .map_err(HeaderError::factory(HeaderErrorKind::Banks))?;
+
+        let mut banks = Vec::new();
+        let mut start = 0;
+
+        for index in 0.. {
+            let mut read = Reader::new(&data[start..]);
+            let header = Header::parse_with_options(&mut read, options)?;
+            let header_size =
+                usize::try_from(read.position()).expect("file header was read from data in memory");
+
+            // the data of the final sound bank can be cut short, like in a single truncated sound bank
+            let data_size: usize = header.stream_info.iter().map(|info| info.size as usize).sum();
+            let end = min(start + header_size + data_size, data.len());
+
+            let mut read = Reader::seekable(Cursor::new(data[start..end].to_vec()));
+            read.advance_to(header_size as u64)
+                .expect("file header was already read from the same data");
+
+            banks.push(
+                Self {
+                    index,
+                    header,
+                    read,
+                    data_start: header_size as u64,
+                }
+                .with_offset(start as u64),
+            );
+
+            start = end
+                + data[end..]
+                    .iter()
+                    .take(MAX_BANK_PADDING)
+                    .take_while(|&&byte| byte == 0)
+                    .count();
+
+            if !data[start..].starts_with(&FSB5_MAGIC) {
+                break;
+            }
+        }
+
+        Ok(banks)
+    }
+}
+
+impl<R: Read> From<Bank<R>> for StreamIntoIter<R> {
+    fn from(mut value: Bank<R>) -> Self {
+        // Streams accessed with `Bank::stream` can leave the reader anywhere in the stream data.
+        // Like other read errors, failing to seek back to the first stream ends the iterator early.
+        let stream_info = match value.read.seek_to(value.data_start) {
+            Ok(()) => value.header.stream_info,
+            Err(_) => Box::default(),
+        };
+
+        Self::new(
+            value.index,
+            value.header.format,
+            value.header.flags,
+            stream_info,
+            value.read,
+        )
+    }
+}
+
+impl<R: Read> IntoIterator for Bank<R> {
+    type IntoIter = StreamIntoIter<R>;
+    type Item = Stream;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Self::IntoIter::from(self)
+    }
+}
+
+/// Represents an error that can occur when parsing a sound bank.
+///
+/// This type is returned from [`Bank::new`] when file header parsing fails.
+/// This can be caused by invalid data or the underlying reader encountering an I/O error.
+#[derive(Debug)]
+pub struct DecodeError {
+    inner: Box<HeaderError>,
+}
+
+impl DecodeError {
+    /// Returns `true` if a sound bank created with [`Bank::decrypt`] had no file signature after decryption,
+    /// which usually means that the key or [`XorScheme`](crate::XorScheme) used was wrong.
+    #[must_use]
+    pub fn is_wrong_key(&self) -> bool {
+        self.inner.is_wrong_key()
+    }
+}
+
+impl From<HeaderError> for DecodeError {
+    fn from(value: HeaderError) -> Self {
+        Self {
+            inner: Box::new(value),
+        }
+    }
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.inner.fmt(f)
+    }
+}
+
+impl Error for DecodeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.inner.source()
+    }
+}
+
+/// Represents an error that can occur when reading sound bank streams with [`Bank::read_streams`].
+#[derive(Debug)]
+pub struct LazyStreamError<E> {
+    index: u32,
+    source: LazyStreamErrorSource<E>,
+}
+
+#[derive(Debug)]
+enum LazyStreamErrorSource<E> {
+    Read(ReadError),
+    Other(E),
+}
+
+impl<E> LazyStreamError<E> {
+    fn from_read(index: u32) -> impl FnOnce(ReadError) -> Self {
+        move |source| Self {
+            index,
+            source: LazyStreamErrorSource::Read(source),
+        }
+    }
+
+    fn from_other(index: u32) -> impl FnOnce(E) -> Self {
+        move |source| Self {
+            index,
+            source: LazyStreamErrorSource::Other(source),
+        }
+    }
+
+    /// Returns the index of the stream where the error occurred.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+}
+
+impl<E> Display for LazyStreamError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_fmt(format_args!("failed to process stream at index {}", self.index))
+    }
+}
+
+impl<E: Error + 'static> Error for LazyStreamError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.source {
+            LazyStreamErrorSource::Read(e) => Some(e),
+            LazyStreamErrorSource::Other(e) => Some(e),
+        }
+    }
+}
+
+/// Represents an error that can occur when accessing a stream with [`Bank::stream`], [`Bank::streams`],
+/// or [`Bank::extract_stream`].
+///
+/// See [`SeekStreamErrorKind`] for the different kinds of errors that can occur.
+#[derive(Debug)]
+pub struct SeekStreamError {
+    index: u32,
+    kind: SeekStreamErrorKind,
+    source: Option<SeekStreamErrorSource>,
+}
+
+#[derive(Debug)]
+enum SeekStreamErrorSource {
+    Read(ReadError),
+    Encode(EncodeError),
+    Io(IoError),
+}
+
+/// A variant of a [`SeekStreamError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SeekStreamErrorKind {
+    /// There was no stream at the given index.
+    UnknownStream,
+    /// Failed to seek to the data of the stream.
+    Seek,
+    /// Failed to read the data of the stream.
+    Read,
+    /// Failed to encode the data of the stream.
+    Encode,
+    /// Failed to write the stream to the sink. Only returned by [`Bank::write_stream_data`] and `AsyncBank`.
+    Write,
+}
+
+impl SeekStreamError {
+    pub(crate) fn new(index: u32, kind: SeekStreamErrorKind) -> Self {
+        Self {
+            index,
+            kind,
+            source: None,
+        }
+    }
+
+    fn from_read(index: u32, kind: SeekStreamErrorKind) -> impl FnOnce(ReadError) -> Self {
+        move |source| Self {
+            index,
+            kind,
+            source: Some(SeekStreamErrorSource::Read(source)),
+        }
+    }
+
+    pub(crate) fn from_encode(index: u32) -> impl FnOnce(EncodeError) -> Self {
+        move |source| Self {
+            index,
+            kind: SeekStreamErrorKind::Encode,
+            source: Some(SeekStreamErrorSource::Encode(source)),
+        }
+    }
+
+    pub(crate) fn from_io(index: u32, kind: SeekStreamErrorKind) -> impl FnOnce(IoError) -> Self {
+        move |source| Self {
+            index,
+            kind,
+            source: Some(SeekStreamErrorSource::Io(source)),
+        }
+    }
+
+    /// Returns the index of the stream that was accessed.
+    #[must_use]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the [`SeekStreamErrorKind`] associated with this error.
+    #[must_use]
+    pub fn kind(&self) -> SeekStreamErrorKind {
+        self.kind
+    }
+}
+
+impl Display for SeekStreamError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_fmt(format_args!("{} at index {}", self.kind, self.index))
+    }
+}
+
+impl Error for SeekStreamError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.source {
+            Some(SeekStreamErrorSource::Read(source)) => Some(source),
+            Some(SeekStreamErrorSource::Encode(source)) => Some(source),
+            Some(SeekStreamErrorSource::Io(source)) => Some(source),
+            None => None,
+        }
+    }
+}
+
+/// Represents an error that can occur when extracting a stream by name with [`Bank::extract_by_name`].
+///
+/// See [`ExtractByNameErrorKind`] for the different kinds of errors that can occur.
+#[derive(Debug)]
+pub struct ExtractByNameError {
+    name: Box<str>,
+    kind: ExtractByNameErrorKind,
+    candidates: Box<[u32]>,
+    source: Option<SeekStreamError>,
+}
+
+/// A variant of an [`ExtractByNameError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ExtractByNameErrorKind {
+    /// No stream had the given name.
+    UnknownName,
+    /// Multiple streams had the given name, so the stream to extract was ambiguous.
+    /// The indices of these streams can be accessed with [`ExtractByNameError::candidates`].
+    DuplicateName,
+    /// The stream with the given name could not be extracted.
+    Stream,
+}
+
+impl ExtractByNameError {
+    fn new(name: &str, kind: ExtractByNameErrorKind, candidates: Box<[u32]>) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+            candidates,
+            source: None,
+        }
+    }
+
+    fn from_stream(
+        name: &str,
+        candidates: Box<[u32]>,
+    ) -> impl FnOnce(SeekStreamError) -> Self + '_ {
+        move |source| Self {
+            name: name.into(),
+            kind: ExtractByNameErrorKind::Stream,
+            candidates,
+            source: Some(source),
+        }
+    }
+
+    /// Returns the name that was searched for.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the [`ExtractByNameErrorKind`] associated with this error.
+    #[must_use]
+    pub fn kind(&self) -> ExtractByNameErrorKind {
+        self.kind
+    }
+
+    /// Returns the indices of every stream that matched the name, in ascending order.
+    /// This is empty if no stream matched the name.
+    #[must_use]
+    pub fn candidates(&self) -> &[u32] {
+        &self.candidates
+    }
+}
+
+impl Display for ExtractByNameError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self.kind {
+            ExtractByNameErrorKind::DuplicateName => f.write_fmt(format_args!(
+                "{} (name {:?}, indices {:?})",
+                self.kind, self.name, self.candidates
+            )),
+            _ => f.write_fmt(format_args!("{} (name {:?})", self.kind, self.name)),
+        }
+    }
+}
+
+impl Error for ExtractByNameError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.source {
+            Some(source) => Some(source),
+            None => None,
+        }
+    }
+}
+
+impl Display for ExtractByNameErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Self::UnknownName => "no stream has the name",
+            Self::DuplicateName => "multiple streams have the name",
+            Self::Stream => "failed to extract stream",
+        })
+    }
+}
+
+impl Display for SeekStreamErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Self::UnknownStream => "no stream exists",
+            Self::Seek => "failed to seek to stream data",
+            Self::Read => "failed to read stream data",
+            Self::Encode => "failed to encode stream data",
+            Self::Write => "failed to write stream",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Bank;
+    use crate::checksum::checksum;
+    #[cfg(feature = "decode-vorbis")]
+    use crate::VerifyOptions;
+    use crate::{
+        encode::{EmptyStreams, EncodeOptions, OutputMode, PcmOutput},
+        test_bank::{TestBank, TestStream},
+        AudioFormat, ChecksumAlgorithm, ExtractByNameErrorKind, ExtractErrorKind, ExtractOptions,
+        FindingKind, NameMatch, OpenErrorKind, ParseOptions, SeekStreamErrorKind, SinkFactory,
+        StreamInfo, XorScheme,
+    };
+    #[cfg(feature = "parallel")]
+    use crate::{ParallelOptions, Progress};
+    #[cfg(feature = "parallel")]
+    use rayon::ThreadPoolBuilder;
+    use std::{
+        env::temp_dir,
+        fs,
+        io::{Cursor, Error as IoError, ErrorKind},
+        iter::zip,
+        process,
+    };
+    #[cfg(feature = "parallel")]
+    use std::{
+        io::{Result as IoResult, Write},
+        sync::{Arc, Mutex},
+    };
+
+    // Collects the streams written and skipped by `Bank::extract_with`,
+    // failing to create a writer for stream `fail_index`.
+    struct TestFactory {
+        created: Vec<u32>,
+        skipped: Vec<u32>,
+        fail_index: Option<u32>,
+        options: EncodeOptions<'static>,
+    }
+
+    impl SinkFactory for TestFactory {
+        type Sink = Vec<u8>;
+        type Error = IoError;
+
+        fn create(&mut self, index: u32, _info: &StreamInfo) -> Result<Option<Vec<u8>>, IoError> {
+            if self.fail_index == Some(index) {
+                return Err(IoError::new(ErrorKind::PermissionDenied, "test"));
+            }
+
+            self.created.push(index);
+            Ok(Some(Vec::new()))
+        }
+
+        fn options(&self) -> EncodeOptions<'_> {
+            self.options
+        }
+
+        fn skipped(&mut self, index: u32, _info: &StreamInfo) {
+            self.skipped.push(index);
+        }
+    }
+
+    // Builds a version 1 sound bank containing a single stream.
+    fn bank_with_stream(format: u32, flags: u32, stream_header: u64, data: &[u8]) -> Vec<u8> {
+        let stream = TestStream::new(stream_header, data);
+        TestBank::new(format).flags(flags).stream(stream).build()
+    }
+
+    // Builds a version 1 sound bank with `num_streams` PCM16 streams of 44100 Hz, 1 channel, and 16 samples,
+    // whose data starts every 32 bytes. Each stream is filled with its index plus 1, and the data is cut
+    // off or padded with zeros to `data_len` bytes.
+    fn test_bank(num_streams: u32, data_len: usize) -> Vec<u8> {
+        const STREAM_HEADER: u64 = (8 << 1) | (16 << 34);
+
+        let streams = (1..=num_streams)
+            .map(|fill| TestStream::new(STREAM_HEADER, &[u8::try_from(fill).unwrap(); 32]));
+        let mut bank = TestBank::new(2).streams(streams).build();
+        bank.resize(bank.len() - 32 * usize::try_from(num_streams).unwrap() + data_len, 0);
+        bank
+    }
+
+    #[test]
+    fn encode_pcm24_stream() {
+        // 44100 Hz, 2 channels, 2 samples
+        const STREAM_HEADER: u64 = (8 << 1) | (1 << 5) | (2 << 34);
+
+        // 2 complete frames, followed by an incomplete frame
+        let data: Vec<_> = (0..16).collect();
+        let bank = bank_with_stream(3, 0, STREAM_HEADER, &data);
+
+        let stream = Bank::new(bank.as_slice()).unwrap().into_iter().next().unwrap();
+        let output = stream.write(Vec::new()).unwrap();
+
+        assert_eq!(output[22..24], 2u16.to_le_bytes());
+        assert_eq!(output[32..36], [6, 0, 24, 0]);
+        assert_eq!(output[40..44], 12u32.to_le_bytes());
+        assert_eq!(output[44..], data[..12]);
+    }
+
+    #[test]
+    fn report_truncated_pcm_streams() {
+        // 44100 Hz, 2 channels, 2 samples
+        const STREAM_HEADER: u64 = (8 << 1) | (1 << 5) | (2 << 34);
+
+        // PCM24 frames of 2 channels are 6 bytes long, so the final 4 bytes are dropped
+        let data: Vec<_> = (0..16).collect();
+        let bank = bank_with_stream(3, 0, STREAM_HEADER, &data);
+        let report = Bank::new(bank.as_slice())
+            .unwrap()
+            .extract_with(TestFactory {
+                created: Vec::new(),
+                skipped: Vec::new(),
+                fail_index: None,
+                options: EncodeOptions::new(),
+            })
+            .unwrap();
+
+        assert_eq!(report.truncated_pcm_streams(), [(0, 4)]);
+        assert!(report.is_complete());
+    }
+
+    #[test]
+    fn encode_big_endian_pcm16_stream() {
+        // 44100 Hz, 1 channel, 2 samples
+        const STREAM_HEADER: u64 = (8 << 1) | (2 << 34);
+
+        // the first bit of the encoding flags marks PCM16 samples as big-endian
+        let bank = bank_with_stream(2, 0x01, STREAM_HEADER, &[0x12, 0x34, 0xFF, 0xFE]);
+
+        let bank = Bank::new(bank.as_slice()).unwrap();
+        assert!(bank.flags().big_endian_pcm());
+        assert!(!bank.flags().basic_headers());
+
+        let stream = bank.into_iter().next().unwrap();
+        let output = stream.write(Vec::new()).unwrap();
+
+        assert_eq!(output[44..], [0x34, 0x12, 0xFE, 0xFF]);
+    }
+
+    #[test]
+    fn read_header_metadata() {
+        const STREAM_HEADER: u64 = (8 << 1) | (2 << 34);
+
+        let mut bank = bank_with_stream(2, 0, STREAM_HEADER, &[0; 4]);
+        let hash: Vec<u8> = (1..=16).collect();
+        bank[0x24..0x34].copy_from_slice(&hash);
+
+        let bank = Bank::new(bank.as_slice()).unwrap();
+        assert_eq!((bank.version(), bank.minor_version()), (5, 1));
+        assert_eq!(bank.hash(), hash);
+        assert_eq!(bank.guid(), None);
+    }
+
+    #[test]
+    fn write_metadata_as_json() {
+        // 44100 Hz, 1 channel, 2 samples
+        const STREAM_HEADER: u64 = (8 << 1) | (2 << 34);
+
+        let bank = bank_with_stream(2, 0x01, STREAM_HEADER, &[0; 4]);
+        let bank = Bank::new(bank.as_slice()).unwrap();
+
+        let json = bank.metadata_json();
+        let (bank_json, streams) = json.split_once('\n').unwrap();
+        assert_eq!(
+            bank_json,
+            concat!(
+                r#"{"index":0,"version":5,"minor_version":1,"format":"pcm16","flags":1,"#,
+                r#""hash":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"guid":null,"offset_warnings":[],"#,
+                r#""num_streams":1,"streams":["#
+            )
+        );
+        assert_eq!(
+            streams,
+            concat!(
+                r#"{"name":null,"codec":"pcm16","sample_rate":44100,"channels":1,"sample_count":2,"#,
+                r#""loop":null,"data_offset":0,"size":4,"peak_volume":null,"channels_overridden":false,"#,
+                r#""atrac9_config":null,"xma_seek_table":null,"vorbis_crc32":null,"vorbis_seek_table":null,"#,
+                r#""unknown_chunks":[]}"#,
+                "\n]}"
+            )
+        );
+    }
+
+    #[test]
+    fn inspect_stream_info() {
+        // 44100 Hz, 1 channel, 2 samples
+        const STREAM_HEADER: u64 = (8 << 1) | (2 << 34);
+
+        let bank = bank_with_stream(2, 0x01, STREAM_HEADER, &[0; 4]);
+        let bank = Bank::new(bank.as_slice()).unwrap();
+
+        let info = bank.stream_info(0).unwrap();
+        assert_eq!(info.codec(), AudioFormat::Pcm16);
+        assert_eq!(info.sample_rate().get(), 44100);
+        assert_eq!(info.channels().get(), 1);
+        assert_eq!(info.sample_count().get(), 2);
+        assert_eq!(info.size(), 4);
+        assert!(!info.is_placeholder());
+        assert_eq!(info.flags(), bank.flags());
+        assert!(info.flags().big_endian_pcm());
+        assert_eq!(info.loop_points(), None);
+        assert_eq!(info.name(), None);
+
+        assert_eq!(bank.stream_infos().len(), 1);
+        assert!(bank.stream_info(1).is_none());
+    }
+
+    #[test]
+    fn extract_streams_with_factory() {
+        const STREAM_HEADER: u64 = (8 << 1) | (2 << 34);
+        let bank = bank_with_stream(2, 0, STREAM_HEADER, &[0; 4]);
+
+        let mut factory = TestFactory {
+            created: Vec::new(),
+            skipped: Vec::new(),
+            fail_index: None,
+            options: EncodeOptions::new(),
+        };
+        assert!(Bank::new(bank.as_slice())
+            .unwrap()
+            .extract_with(&mut factory)
+            .unwrap()
+            .is_complete());
+        assert_eq!(factory.created, [0]);
+
+        // errors from the factory carry the index of the stream
+        let error = Bank::new(bank.as_slice())
+            .unwrap()
+            .extract_with(TestFactory {
+                created: Vec::new(),
+                skipped: Vec::new(),
+                fail_index: Some(0),
+                options: EncodeOptions::new(),
+            })
+            .unwrap_err();
+        assert_eq!(error.index(), 0);
+        assert_eq!(error.kind(), ExtractErrorKind::CreateSink);
+    }
+
+    #[test]
+    fn report_mpeg_fix_stats() {
+        // 44100 Hz, 2 channels, 2304 samples
+        const STREAM_HEADER: u64 = (8 << 1) | (1 << 5) | (2304 << 34);
+        // MPEG-1 Layer III, 128 kbps, 44100 Hz, joint stereo
+        const FRAME_HEADER: [u8; 4] = [0xFF, 0xFB, 0x90, 0x64];
+
+        // 2 frames of 417 bytes, padded to 420 bytes, followed by garbage
+        let mut data = Vec::new();
+        for _ in 0..2 {
+            data.extend(FRAME_HEADER);
+            data.extend([0x11; 413]);
+            data.extend([0; 3]);
+        }
+        data.extend([0x22; 2]);
+        let bank = bank_with_stream(11, 0, STREAM_HEADER, &data);
+
+        let factory = |options| TestFactory {
+            created: Vec::new(),
+            skipped: Vec::new(),
+            fail_index: None,
+            options,
+        };
+
+        // the stream data is buffered when the source can't seek, and read twice when it can
+        let report = Bank::new(bank.as_slice())
+            .unwrap()
+            .extract_with(factory(EncodeOptions::new()))
+            .unwrap();
+        let seekable_report = Bank::seekable(Cursor::new(bank.as_slice()))
+            .unwrap()
+            .extract_with(factory(EncodeOptions::new()))
+            .unwrap();
+
+        for report in [report, seekable_report] {
+            let [(index, stats)] = report.mpeg_fix_stats() else {
+                panic!("expected stats of 1 stream");
+            };
+
+            assert_eq!(*index, 0);
+            assert_eq!(stats.frames(), 2);
+            assert_eq!(stats.frame_bytes(), 834);
+            assert_eq!(stats.padding_bytes(), 6);
+            assert_eq!(stats.unrecognized_bytes(), 2);
+            assert!(!stats.truncated());
+        }
+
+        // streams are written as they're stored without the fix, so there are no stats
+        let report = Bank::new(bank.as_slice())
+            .unwrap()
+            .extract_with(factory(EncodeOptions::new().mpeg_fix(false)))
+            .unwrap();
+        assert!(report.mpeg_fix_stats().is_empty());
+    }
+
+    #[test]
+    fn report_converted_8_bit_streams() {
+        // 44100 Hz, 1 channel, 4 samples
+        const STREAM_HEADER: u64 = (8 << 1) | (4 << 34);
+
+        let bank = bank_with_stream(1, 0, STREAM_HEADER, &[0x00, 0x7F, 0x80, 0xFF]);
+        let extract = |options| {
+            Bank::new(bank.as_slice())
+                .unwrap()
+                .extract_with(TestFactory {
+                    created: Vec::new(),
+                    skipped: Vec::new(),
+                    fail_index: None,
+                    options,
+                })
+                .unwrap()
+        };
+
+        // WAVE files store unsigned 8-bit samples, while AIFF files keep them signed like the stream data
+        assert_eq!(extract(EncodeOptions::new()).converted_8_bit_streams(), [0]);
+        let options = EncodeOptions::new().pcm_output(PcmOutput::Aiff);
+        assert!(extract(options).converted_8_bit_streams().is_empty());
+    }
+
+    #[test]
+    fn encode_version_0_stream() {
+        // 44100 Hz, 1 channel, 2 samples, followed by a loop chunk
+        const STREAM_HEADER: u64 = 1 | (8 << 1) | (2 << 34);
+        const LOOP_CHUNK: u32 = (8 << 1) | (3 << 25);
+
+        let data = [0x12, 0x34, 0x56, 0x78];
+
+        // version 0 file headers have an extra field, so stream headers start at 64 bytes instead of 60
+        let mut bank = Vec::from(*b"FSB5");
+        bank.extend_from_slice(&0u32.to_le_bytes());
+        bank.extend_from_slice(&1u32.to_le_bytes());
+        bank.extend_from_slice(&20u32.to_le_bytes());
+        bank.extend_from_slice(&8u32.to_le_bytes());
+        bank.extend_from_slice(&4u32.to_le_bytes());
+        bank.extend_from_slice(&2u32.to_le_bytes());
+        bank.resize(64, 0);
+        bank.extend_from_slice(&STREAM_HEADER.to_le_bytes());
+        bank.extend_from_slice(&LOOP_CHUNK.to_le_bytes());
+        bank.extend_from_slice(&0u32.to_le_bytes());
+        bank.extend_from_slice(&1u32.to_le_bytes());
+        // name table with a single name, which starts after the name offsets
+        bank.extend_from_slice(&4u32.to_le_bytes());
+        bank.extend_from_slice(b"bgm\0");
+        bank.extend_from_slice(&data);
+
+        let stream = Bank::new(bank.as_slice()).unwrap().into_iter().next().unwrap();
+        assert_eq!(stream.name(), Some("bgm"));
+        assert_eq!(stream.loop_info().map(|l| l.start()), Some(0));
+        assert_eq!(stream.loop_info().map(|l| l.end()), Some(1));
+
+        let output = stream.write(Vec::new()).unwrap();
+        assert_eq!(output[40..44], 4u32.to_le_bytes());
+        assert_eq!(output[44..48], data);
+        assert_eq!(output[48..52], *b"smpl");
+    }
+
+    #[test]
+    fn extract_placeholder_streams() {
+        // 44100 Hz, 1 channel, 2 samples; both streams start at the same offset, so the first stream is empty
+        const STREAM_HEADER: u64 = (8 << 1) | (2 << 34);
+
+        let mut bank = Vec::from(*b"FSB5");
+        bank.extend_from_slice(&1u32.to_le_bytes());
+        bank.extend_from_slice(&2u32.to_le_bytes());
+        bank.extend_from_slice(&16u32.to_le_bytes());
+        bank.extend_from_slice(&0u32.to_le_bytes());
+        bank.extend_from_slice(&4u32.to_le_bytes());
+        bank.extend_from_slice(&2u32.to_le_bytes());
+        bank.resize(60, 0);
+        bank.extend_from_slice(&STREAM_HEADER.to_le_bytes());
+        bank.extend_from_slice(&STREAM_HEADER.to_le_bytes());
+        bank.extend_from_slice(&[0x12, 0x34, 0x56, 0x78]);
+
+        let parsed = Bank::new(bank.as_slice()).unwrap();
+        assert!(parsed.stream_infos()[0].is_placeholder());
+        assert!(!parsed.stream_infos()[1].is_placeholder());
+
+        let mut streams = parsed.into_iter();
+        let first = streams.next().unwrap();
+        assert_eq!(first.size(), 0);
+        assert_eq!(streams.next().map(|stream| stream.size()), Some(4));
+
+        // placeholder streams are written as files without samples by default
+        let output = first.write(Vec::new()).unwrap();
+        assert_eq!(output[40..44], 0u32.to_le_bytes());
+
+        let mut factory = TestFactory {
+            created: Vec::new(),
+            skipped: Vec::new(),
+            fail_index: None,
+            options: EncodeOptions::new().empty_streams(EmptyStreams::Skip),
+        };
+        assert!(Bank::new(bank.as_slice())
+            .unwrap()
+            .extract_with(&mut factory)
+            .unwrap()
+            .is_complete());
+        assert_eq!(factory.created, [1]);
+        assert_eq!(factory.skipped, [0]);
+    }
+
+    #[test]
+    fn extract_truncated_bank() {
+        // the data ends 10 bytes into the second stream
+        let bank = test_bank(3, 42);
+
+        let mut factory = TestFactory {
+            created: Vec::new(),
+            skipped: Vec::new(),
+            fail_index: None,
+            options: EncodeOptions::new(),
+        };
+        let report = Bank::new(bank.as_slice())
+            .unwrap()
+            .extract_with(&mut factory)
+            .unwrap();
+
+        assert_eq!(factory.created, [0, 1]);
+        assert!(!report.is_complete());
+        assert_eq!(report.missing_streams(), [2]);
+
+        let partial = report.partial_streams();
+        assert_eq!(partial.len(), 1);
+        assert_eq!(partial[0].index(), 1);
+        assert_eq!(partial[0].missing_bytes(), 22);
+    }
+
+    #[test]
+    fn extract_lenient_collects_errors() {
+        let bank = test_bank(3, 96);
+
+        let factory = || TestFactory {
+            created: Vec::new(),
+            skipped: Vec::new(),
+            fail_index: Some(1),
+            options: EncodeOptions::new(),
+        };
+
+        // the stream data of the failed stream is skipped over, whether or not the source can seek
+        for bank in [
+            Bank::new(Cursor::new(bank.clone())).unwrap(),
+            Bank::seekable(Cursor::new(bank.clone())).unwrap(),
+        ] {
+            let mut factory = factory();
+            let report = bank.extract_lenient(&mut factory).unwrap();
+
+            assert_eq!(factory.created, [0, 2]);
+            assert!(!report.is_success());
+            assert!(report.report().is_complete());
+            assert_eq!(report.report().extracted_streams(), [0, 2]);
+
+            let errors = report.errors();
+            assert_eq!(errors.len(), 1);
+            assert_eq!(
+                (errors[0].index(), errors[0].kind()),
+                (1, ExtractErrorKind::CreateSink)
+            );
+        }
+
+        // the report has the same shape when every stream is extracted
+        let report = Bank::new(bank.as_slice())
+            .unwrap()
+            .extract_lenient(TestFactory {
+                fail_index: None,
+                ..factory()
+            })
+            .unwrap();
+        assert!(report.is_success());
+        assert_eq!(report.report().extracted_streams(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn verify_truncated_bank() {
+        let mut bank = test_bank(3, 96);
+
+        let report = Bank::new(bank.as_slice()).unwrap().verify();
+        assert!(report.is_intact());
+        assert!(report.findings().is_empty());
+
+        // the data ends 10 bytes into the second stream, which starts 32 bytes after the file header
+        bank.truncate(bank.len() - 54);
+
+        for mut bank in [
+            Bank::new(Cursor::new(bank.clone())).unwrap(),
+            Bank::seekable(Cursor::new(bank.clone())).unwrap(),
+        ] {
+            let report = bank.verify();
+            assert!(!report.is_intact());
+
+            let findings: Vec<_> = report
+                .errors()
+                .map(|finding| (finding.kind(), finding.index(), finding.offset()))
+                .collect();
+            assert_eq!(
+                findings,
+                [
+                    (FindingKind::TruncatedStream { missing: 22 }, Some(1), Some(126)),
+                    (FindingKind::MissingStream, Some(2), Some(126)),
+                ]
+            );
+        }
+    }
+
+    #[cfg(feature = "decode-vorbis")]
+    #[test]
+    fn verify_vorbis_packets() {
+        // 44100 Hz, 1 channel, 2 samples, without the CRC32 of a setup header
+        const STREAM_HEADER: u64 = (8 << 1) | (2 << 34);
+        let bank = bank_with_stream(15, 0, STREAM_HEADER, &[0; 4]);
+
+        // packets are only read when requested
+        assert!(Bank::new(bank.as_slice()).unwrap().verify().is_intact());
+
+        let report = Bank::new(bank.as_slice())
+            .unwrap()
+            .verify_with_options(VerifyOptions::new().check_packets(true));
+
+        let finding = report.errors().next().unwrap();
+        assert_eq!(finding.kind(), FindingKind::InvalidStreamData);
+        assert_eq!(finding.index(), Some(0));
+        assert!(std::error::Error::source(finding).is_some());
+    }
+
+    #[test]
+    fn read_streams_out_of_order() {
+        let mut bank = Bank::seekable(Cursor::new(test_bank(3, 96))).unwrap();
+
+        for index in [2, 0, 1, 0] {
+            let output = bank.stream(index).unwrap().write(Vec::new()).unwrap();
+            let fill = u8::try_from(index).unwrap() + 1;
+            assert!(output.ends_with(&[fill; 32]));
+        }
+
+        assert!(bank
+            .stream(3)
+            .is_err_and(|e| e.index() == 3 && e.kind() == SeekStreamErrorKind::UnknownStream));
+    }
+
+    #[test]
+    fn borrow_stream_data() {
+        // the sound bank starts after some other data, and its data ends 22 bytes into the last stream
+        let mut data = vec![0xff; 5];
+        data.extend(test_bank(3, 86));
+
+        let mut cursor = Cursor::new(data.as_slice());
+        cursor.set_position(5);
+        let mut bank = Bank::seekable(cursor).unwrap();
+
+        assert_eq!(bank.stream_data(0), Some([1; 32].as_slice()));
+        assert_eq!(bank.stream_data(1), Some([2; 32].as_slice()));
+        assert_eq!(bank.stream_data(2), Some([3; 22].as_slice()));
+        assert_eq!(bank.stream_data(3), None);
+
+        let raw = EncodeOptions::new().output_mode(OutputMode::Raw);
+        assert_eq!(
+            bank.write_stream_data(1, Vec::new()).unwrap(),
+            bank.extract_stream_with_options(1, Vec::new(), raw).unwrap()
+        );
+
+        assert!(bank
+            .write_stream_data(2, Vec::new())
+            .is_err_and(|e| e.index() == 2 && e.kind() == SeekStreamErrorKind::Read));
+        assert!(bank
+            .write_stream_data(3, Vec::new())
+            .is_err_and(|e| e.kind() == SeekStreamErrorKind::UnknownStream));
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn map_bank_file() {
+        let path = temp_dir().join(format!("fsbex-mmap-{}.fsb", process::id()));
+        fs::write(
+            &path,
+            bank_with_stream(2, 0, (8 << 1) | (2 << 34), &[0x12, 0x34, 0x56, 0x78]),
+        )
+        .unwrap();
+
+        // SAFETY: the file isn't modified while it's mapped
+        let mut bank = unsafe { Bank::from_mmap(&path) }.unwrap();
+        assert_eq!(bank.format(), AudioFormat::Pcm16);
+        assert_eq!(bank.stream_data(0), Some([0x12, 0x34, 0x56, 0x78].as_slice()));
+
+        let stream = bank.extract_stream(0, Vec::new()).unwrap();
+        assert_eq!(stream[44..], [0x12, 0x34, 0x56, 0x78]);
+
+        drop(bank);
+        fs::remove_file(&path).unwrap();
+
+        // SAFETY: the file doesn't exist
+        assert!(unsafe { Bank::from_mmap(&path) }.is_err());
+    }
+
+    #[test]
+    fn iterate_streams_repeatedly() {
+        let mut data = test_bank(3, 96);
+
+        let mut bank = Bank::seekable(Cursor::new(data.clone())).unwrap();
+        assert_eq!(bank.streams().len(), 3);
+
+        // a stream accessed in between doesn't affect later passes
+        let first: Vec<_> = bank.streams().map(Result::unwrap).collect();
+        let output = bank.stream(1).unwrap().write(Vec::new()).unwrap();
+        let second: Vec<_> = bank.streams().map(Result::unwrap).collect();
+
+        assert_eq!(first, second);
+        assert_eq!(first[1].clone().write(Vec::new()).unwrap(), output);
+        for (stream, fill) in zip(&first, 1..) {
+            let output = stream.clone().write(Vec::new()).unwrap();
+            assert!(output.ends_with(&[fill; 32]));
+        }
+
+        // streams whose data can't be read are reported as errors
+        data.truncate(data.len() - 16);
+        let mut bank = Bank::seekable(Cursor::new(data)).unwrap();
+
+        let results: Vec<_> = bank.streams().collect();
+        assert!(results[0].is_ok() && results[1].is_ok());
+        assert!(results[2]
+            .as_ref()
+            .is_err_and(|e| e.index() == 2 && e.kind() == SeekStreamErrorKind::Read));
+    }
+
+    #[test]
+    fn extract_single_streams() {
+        let mut bank = Bank::seekable(Cursor::new(test_bank(2, 64))).unwrap();
+
+        let output = bank.extract_stream(1, Vec::new()).unwrap();
+        assert!(output.ends_with(&[2; 32]));
+
+        assert!(bank
+            .extract_stream(2, Vec::new())
+            .is_err_and(|e| e.index() == 2 && e.kind() == SeekStreamErrorKind::UnknownStream));
+
+        // streams that weren't read to the end don't affect later calls
+        let _stream = bank.stream(1).unwrap();
+
+        let output = bank.extract_stream(0, Vec::new()).unwrap();
+        assert!(output.ends_with(&[1; 32]));
+        assert_eq!(bank.extract_stream(1, Vec::new()).unwrap().len(), output.len());
+    }
+
+    #[test]
+    fn extract_streams_by_name() {
+        let names: &[&[u8]] = &[b"Music\0", b"hit\0", b"hit\0"];
+
+        let mut name_table = Vec::new();
+        let mut name_offset = 12u32;
+        for name in names {
+            name_table.extend_from_slice(&name_offset.to_le_bytes());
+            name_offset += u32::try_from(name.len()).unwrap();
+        }
+        name_table.extend(names.concat());
+
+        // the name table goes between the stream headers and the stream data
+        let mut bank = test_bank(3, 96);
+        bank[16..20].copy_from_slice(&u32::try_from(name_table.len()).unwrap().to_le_bytes());
+        let data = bank.split_off(84);
+        bank.extend(name_table);
+        bank.extend(data);
+
+        let mut bank = Bank::seekable(Cursor::new(bank)).unwrap();
+
+        assert_eq!(bank.find("Music"), Some(0));
+        assert_eq!(bank.find("music"), None);
+        assert_eq!(bank.find_with("music", NameMatch::IgnoreCase), Some(0));
+        assert_eq!(bank.find("hit"), Some(1));
+        assert_eq!(
+            bank.stream_infos()[2].suggested_filename(EncodeOptions::new()),
+            "hit_2.wav"
+        );
+        assert!(bank.find_all("hit", NameMatch::Exact).eq([1, 2]));
+
+        let output = bank.extract_by_name("Music", Vec::new()).unwrap();
+        assert!(output.ends_with(&[1; 32]));
+
+        let output = bank
+            .extract_by_name_with_options(
+                "MUSIC",
+                NameMatch::IgnoreCase,
+                Vec::new(),
+                EncodeOptions::new(),
+            )
+            .unwrap();
+        assert!(output.ends_with(&[1; 32]));
+
+        assert!(bank.extract_by_name("hit", Vec::new()).is_err_and(|e| {
+            e.kind() == ExtractByNameErrorKind::DuplicateName && e.candidates() == [1, 2]
+        }));
+
+        assert!(bank.extract_by_name("miss", Vec::new()).is_err_and(|e| {
+            e.kind() == ExtractByNameErrorKind::UnknownName && e.candidates().is_empty()
+        }));
+    }
+
+    #[test]
+    fn extract_all_streams_to_directory() {
+        let bank = test_bank(11, 352);
+        let dir = temp_dir().join(format!("fsbex-extract-all-{}", process::id()));
+        let output = dir.join("nested");
+        let mut bank = Bank::seekable(Cursor::new(bank)).unwrap();
+
+        let report = bank.extract_all_to_dir(&output, &ExtractOptions::new()).unwrap();
+        let files = report.files();
+
+        assert!(report.is_complete());
+        assert_eq!(files.len(), 11);
+        assert_eq!(files[0].index(), 0);
+        assert_eq!(files[0].path(), output.join("stream_00.wav"));
+        assert_eq!(files[10].path(), output.join("stream_10.wav"));
+        assert_eq!(files[10].size(), 44 + 32);
+        assert!(report.skipped_streams().is_empty());
+
+        // existing files are skipped, and reported as skipped streams
+        fs::remove_file(output.join("stream_03.wav")).unwrap();
+        let report = bank
+            .extract_all_to_dir(&output, &ExtractOptions::new().skip_existing(true))
+            .unwrap();
+
+        assert_eq!(report.files().len(), 1);
+        assert_eq!(report.files()[0].index(), 3);
+        assert_eq!(report.skipped_streams().len(), 10);
+
+        // checksums are computed from the stream data and the files as they're written
+        let options = ExtractOptions::new().checksum(ChecksumAlgorithm::Crc32);
+        let report = bank.extract_all_to_dir(&output, &options).unwrap();
+        let checksums = report.checksums();
+
+        assert_eq!(checksums.len(), 11);
+        assert_eq!(checksums[10].index(), 10);
+        assert_eq!(checksums[10].source(), checksum(ChecksumAlgorithm::Crc32, &[11; 32]));
+        assert_eq!(
+            checksums[10].output(),
+            checksum(ChecksumAlgorithm::Crc32, &fs::read(files[10].path()).unwrap())
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn report_extraction_progress() {
+        let bank = test_bank(3, 96);
+
+        let factory = TestFactory {
+            created: Vec::new(),
+            skipped: Vec::new(),
+            fail_index: None,
+            options: EncodeOptions::new(),
+        };
+        let mut reports = Vec::new();
+        let report = Bank::new(bank.as_slice())
+            .unwrap()
+            .extract_with_progress(factory, |progress| reports.push(progress))
+            .unwrap();
+
+        assert!(report.is_complete());
+        assert_eq!(reports.len(), 3);
+        for (progress, index) in reports.iter().zip(0..) {
+            assert_eq!(progress.index(), index);
+            assert_eq!(progress.num_streams(), 3);
+            assert!(progress.is_stream_done());
+            assert_eq!(progress.bytes_written(), 44 + 32);
+            assert_eq!(progress.stream_size(), 32);
+            assert_eq!(progress.completed_size(), u64::from(index) * 32);
+            assert_eq!(progress.total_size(), 96);
+        }
+        assert!((reports[2].fraction() - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn parse_concatenated_banks() {
+        // 44100 Hz, 1 channel, 2 samples
+        const STREAM_HEADER: u64 = (8 << 1) | (2 << 34);
+
+        // the second bank starts after zeros that pad the first bank to 96 bytes
+        let mut data = bank_with_stream(2, 0, STREAM_HEADER, &[0x12, 0x34, 0x56, 0x78]);
+        data.resize(96, 0);
+        data.extend(bank_with_stream(2, 0x01, STREAM_HEADER, &[0x9A, 0xBC, 0xDE, 0xF0]));
+        // data that isn't a sound bank is ignored
+        data.extend_from_slice(b"\0\0junk");
+
+        let banks = Bank::parse_all(data.as_slice()).unwrap();
+        assert_eq!(banks.len(), 2);
+        assert_eq!(banks.iter().map(Bank::index).collect::<Vec<_>>(), [0, 1]);
+        assert!(banks[1].flags().big_endian_pcm());
+
+        let stream = banks.into_iter().nth(1).unwrap().into_iter().next().unwrap();
+        assert_eq!((stream.bank_index(), stream.index()), (1, 0));
+
+        let output = stream.write(Vec::new()).unwrap();
+        assert_eq!(output[44..], [0xBC, 0x9A, 0xF0, 0xDE]);
+
+        // a single bank is parsed like with `Bank::new`
+        let banks = Bank::parse_all(&data[..72]).unwrap();
+        assert_eq!(banks.len(), 1);
+        assert!(Bank::parse_all(&data[..40]).is_err());
+    }
+
+    #[test]
+    fn decrypt_encrypted_bank() {
+        // 44100 Hz, 1 channel, 2 samples
+        const STREAM_HEADER: u64 = (8 << 1) | (2 << 34);
+
+        let data = bank_with_stream(2, 0, STREAM_HEADER, &[0x12, 0x34, 0x56, 0x78]);
+        let encrypt = |key: &[u8], scheme: XorScheme| -> Vec<u8> {
+            data.iter()
+                .zip(key.iter().cycle())
+                .map(|(&byte, &key)| match scheme {
+                    XorScheme::ReverseBitsAfterXor => byte.reverse_bits() ^ key,
+                    _ => (byte ^ key).reverse_bits(),
+                })
+                .collect()
+        };
+
+        // keys can be strings or raw bytes
+        let encrypted = encrypt(b"secret", XorScheme::ReverseBits);
+        let bank = Bank::decrypt(encrypted.as_slice(), "secret").unwrap();
+        let output = bank.into_iter().next().unwrap().write(Vec::new()).unwrap();
+        assert_eq!(output[44..], [0x12, 0x34, 0x56, 0x78]);
+
+        assert!(Bank::decrypt(encrypted.as_slice(), b"wrong").is_err_and(|e| e.is_wrong_key()));
+        assert!(Bank::decrypt(encrypted.as_slice(), []).is_err_and(|e| !e.is_wrong_key()));
+        assert!(Bank::new(encrypted.as_slice()).is_err_and(|e| !e.is_wrong_key()));
+
+        let encrypted = encrypt(&[0xAA, 0x55], XorScheme::ReverseBitsAfterXor);
+        let options = ParseOptions::new().xor_scheme(XorScheme::ReverseBitsAfterXor);
+        let bank = Bank::decrypt_with_options(encrypted.as_slice(), [0xAA, 0x55], options).unwrap();
+        assert_eq!(bank.num_streams().get(), 1);
+    }
+
+    #[test]
+    fn unwrap_source() {
+        let data = test_bank(3, 96);
+
+        // the source is left right after the file header
+        let bank = Bank::new(Cursor::new(data.as_slice())).unwrap();
+        assert_eq!(bank.position(), 84);
+        assert_eq!(bank.get_ref().position(), 84);
+        assert_eq!(bank.into_inner().position(), 84);
+
+        // or right after the data of the last stream that was read
+        let mut bank = Bank::seekable(Cursor::new(data.as_slice())).unwrap();
+        let _stream = bank.extract_stream(1, Vec::new()).unwrap();
+        assert_eq!(bank.position(), 84 + 64);
+        assert_eq!(bank.into_inner().position(), 84 + 64);
+
+        // positions are relative to where parsing started
+        let mut file = vec![0xFF; 100];
+        file.extend_from_slice(&data);
+        let bank = Bank::at_offset(Cursor::new(file.as_slice()), 100).unwrap();
+        assert_eq!(bank.position(), 84);
+        assert_eq!(bank.into_inner().position(), 100 + 84);
+    }
+
+    #[test]
+    fn locate_stream_data() {
+        let data = test_bank(3, 96);
+        let raw = EncodeOptions::new().output_mode(OutputMode::Raw);
+
+        // the data of every stream lines up with the data that's extracted
+        let mut bank = Bank::seekable(Cursor::new(data.as_slice())).unwrap();
+        for index in 0..3 {
+            let info = bank.stream_info(index).unwrap();
+            let start = usize::try_from(info.data_offset_in_bank()).unwrap();
+            let end = start + usize::try_from(info.data_size()).unwrap();
+
+            assert_eq!(info.data_offset_in_bank(), 60 + 24 + u64::from(index) * 32);
+            assert_eq!(info.data_offset_in_file(), info.data_offset_in_bank());
+
+            let output = bank.extract_stream_with_options(index, Vec::new(), raw).unwrap();
+            assert_eq!(output, data[start..end]);
+        }
+
+        // sound banks found within a larger file are located in the file
+        let mut file = vec![0xFF; 100];
+        file.extend_from_slice(&data);
+        let mut bank = Bank::at_offset(Cursor::new(file.as_slice()), 100).unwrap();
+        let info = bank.stream_info(2).unwrap();
+        let start = usize::try_from(info.data_offset_in_file()).unwrap();
+        let end = start + usize::try_from(info.data_size()).unwrap();
+
+        assert_eq!(info.data_offset_in_file(), 100 + info.data_offset_in_bank());
+        assert_eq!(bank.stream(2).unwrap().data_offset_in_file(), 100 + 148);
+        let output = bank.extract_stream_with_options(2, Vec::new(), raw).unwrap();
+        assert_eq!(output, file[start..end]);
+
+        // as are sound banks stored back to back
+        let mut file = data.clone();
+        file.resize(file.len() + 4, 0);
+        file.extend_from_slice(&data);
+        let banks = Bank::parse_all(file.as_slice()).unwrap();
+        let offset = (data.len() + 4) as u64;
+
+        let info = banks[1].stream_info(1).unwrap();
+        assert_eq!(info.data_offset_in_file(), offset + info.data_offset_in_bank());
+        assert_eq!(banks[0].stream_info(1).unwrap().data_offset_in_file(), 116);
+    }
+
+    #[test]
+    fn open_bank_file() {
+        // 44100 Hz, 1 channel, 2 samples
+        const STREAM_HEADER: u64 = (8 << 1) | (2 << 34);
+
+        let data = bank_with_stream(2, 0, STREAM_HEADER, &[0x12, 0x34, 0x56, 0x78]);
+        let path = temp_dir().join(format!("fsbex-open-{}.fsb", process::id()));
+        fs::write(&path, &data).unwrap();
+
+        let mut bank = Bank::open(&path).unwrap();
+        let stream = bank.extract_stream(0, Vec::new()).unwrap();
+        assert_eq!(stream[44..], [0x12, 0x34, 0x56, 0x78]);
+        drop(bank);
+
+        // encrypted sound banks are recognized
+        let encrypted: Vec<u8> = zip(&data, b"secret".iter().cycle())
+            .map(|(&byte, &key)| (byte ^ key).reverse_bits())
+            .collect();
+        fs::write(&path, encrypted).unwrap();
+        assert!(Bank::open(&path).is_err_and(|e| e.kind() == OpenErrorKind::Encrypted));
+
+        // so are FMOD Studio bank files
+        let mut studio = Vec::from(*b"RIFF");
+        studio.extend_from_slice(&4u32.to_le_bytes());
+        studio.extend_from_slice(b"FEV ");
+        fs::write(&path, studio).unwrap();
+        assert!(Bank::open(&path).is_err_and(|e| e.kind() == OpenErrorKind::StudioBank));
+
+        fs::write(&path, b"not a sound bank").unwrap();
+        assert!(Bank::open(&path).is_err_and(|e| e.kind() == OpenErrorKind::Magic));
+
+        fs::write(&path, &data[..40]).unwrap();
+        let error = Bank::open(&path).unwrap_err();
+        assert_eq!(error.kind(), OpenErrorKind::Parse);
+        assert_eq!(error.path(), path);
+        assert!(error.to_string().contains(&*path.to_string_lossy()));
+
+        fs::remove_file(&path).unwrap();
+        assert!(Bank::open(&path).is_err_and(|e| e.kind() == OpenErrorKind::Open));
+    }
+
+    #[test]
+    fn open_bank_file_with_large_stream() {
+        // 44100 Hz, 1 channel, 64 samples in each of 300 IMA ADPCM blocks
+        const BLOCKS: u64 = 300;
+        const STREAM_HEADER: u64 = (8 << 1) | ((BLOCKS * 64) << 34);
+
+        // the stream data is larger than the buffer of the file, so blocks are split across reads
+        let mut data = Vec::new();
+        for block in 0..BLOCKS {
+            let block = u8::try_from(block % 89).unwrap();
+            data.extend_from_slice(&[block, 0, block, 0]);
+            data.extend((0..32).map(|nibbles| nibbles ^ block));
+        }
+        assert!(data.len() > 8 * 1024);
+
+        let data = bank_with_stream(7, 0, STREAM_HEADER, &data);
+        let path = temp_dir().join(format!("fsbex-open-large-{}.fsb", process::id()));
+        fs::write(&path, &data).unwrap();
+
+        let mut bank = Bank::open(&path).unwrap();
+        let stream = bank.extract_stream(0, Vec::new()).unwrap();
+        drop(bank);
+        fs::remove_file(&path).unwrap();
+
+        let expected = Bank::new(data.as_slice())
+            .unwrap()
+            .into_iter()
+            .next()
+            .unwrap()
+            .write(Vec::new())
+            .unwrap();
+        assert_eq!(stream.len(), 44 + 300 * 64 * 2);
+        assert_eq!(stream, expected);
+    }
+
+    // Writes every stream to a shared buffer at the index of the stream, for `Bank::extract_all_parallel`,
+    // failing to create the writer of stream `fail_create`, and to write stream `fail_write`.
+    #[cfg(feature = "parallel")]
+    #[derive(Default)]
+    struct SharedFactory {
+        outputs: Arc<Mutex<Vec<Vec<u8>>>>,
+        fail_create: Option<u32>,
+        fail_write: Option<u32>,
+    }
+
+    #[cfg(feature = "parallel")]
+    struct SharedSink {
+        index: usize,
+        outputs: Arc<Mutex<Vec<Vec<u8>>>>,
+        fail: bool,
+    }
+
+    #[cfg(feature = "parallel")]
+    impl Write for SharedSink {
+        fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+            if self.fail {
+                return Err(IoError::new(ErrorKind::PermissionDenied, "test"));
+            }
+
+            self.outputs.lock().unwrap()[self.index].extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> IoResult<()> {
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    impl SinkFactory for SharedFactory {
+        type Sink = SharedSink;
+        type Error = IoError;
+
+        fn create(
+            &mut self,
+            index: u32,
+            _info: &StreamInfo,
+        ) -> Result<Option<SharedSink>, IoError> {
+            if self.fail_create == Some(index) {
+                return Err(IoError::new(ErrorKind::PermissionDenied, "test"));
+            }
+
+            let mut outputs = self.outputs.lock().unwrap();
+            let index = index as usize;
+            if outputs.len() <= index {
+                outputs.resize(index + 1, Vec::new());
+            }
+
+            Ok(Some(SharedSink {
+                index,
+                outputs: Arc::clone(&self.outputs),
+                fail: self.fail_write == Some(u32::try_from(index).unwrap()),
+            }))
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn extract_all_parallel_matches_serial() {
+        let data = test_bank(20, 20 * 32);
+        let factory = SharedFactory::default();
+        let outputs = Arc::clone(&factory.outputs);
+        let progress = Mutex::new(Vec::new());
+
+        let pool = ThreadPoolBuilder::new().num_threads(3).build().unwrap();
+        let report = pool
+            .install(|| {
+                Bank::new(data.as_slice())
+                    .unwrap()
+                    .extract_all_parallel_with_progress(factory, ParallelOptions::new(), |p| {
+                        progress.lock().unwrap().push(p);
+                    })
+            })
+            .unwrap();
+
+        assert!(report.is_success());
+        assert!(report.report().is_complete());
+
+        let mut bank = Bank::seekable(Cursor::new(data)).unwrap();
+        for (output, index) in zip(outputs.lock().unwrap().iter(), 0..) {
+            assert_eq!(*output, bank.extract_stream(index, Vec::new()).unwrap());
+        }
+
+        // every stream is reported once, with the sizes of streams reported before it
+        let mut progress = progress.into_inner().unwrap();
+        progress.sort_by_key(Progress::completed_size);
+        assert!(progress.iter().all(|p| p.is_stream_done() && p.bytes_written() > 0));
+        assert!(zip(&progress, 0..).all(|(p, n)| p.completed_size() == n * 32));
+
+        let mut indices: Vec<_> = progress.iter().map(Progress::index).collect();
+        indices.sort_unstable();
+        assert!(indices.into_iter().eq(0..20));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn extract_all_parallel_collects_errors() {
+        let data = test_bank(20, 20 * 32);
+        let factory = SharedFactory {
+            fail_create: Some(12),
+            fail_write: Some(3),
+            ..SharedFactory::default()
+        };
+        let outputs = Arc::clone(&factory.outputs);
+
+        let report = Bank::new(data.as_slice())
+            .unwrap()
+            .extract_all_parallel(factory, ParallelOptions::new())
+            .unwrap();
+
+        // errors are listed in order of streams, and don't stop other streams from being extracted
+        let errors = report.errors();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            (errors[0].index(), errors[0].kind()),
+            (3, ExtractErrorKind::EncodeStream)
+        );
+        assert_eq!(
+            (errors[1].index(), errors[1].kind()),
+            (12, ExtractErrorKind::CreateSink)
+        );
+
+        let outputs = outputs.lock().unwrap();
+        assert_eq!(outputs.len(), 20);
+        assert!(zip(outputs.iter(), 0..)
+            .all(|(output, index)| output.is_empty() == [3, 12].contains(&index)));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn extract_all_parallel_fail_fast() {
+        let data = test_bank(20, 20 * 32);
+        let options = ParallelOptions::new().fail_fast(true);
+
+        let factory = SharedFactory {
+            fail_write: Some(3),
+            ..SharedFactory::default()
+        };
+        let error = Bank::new(data.as_slice())
+            .unwrap()
+            .extract_all_parallel(factory, options)
+            .unwrap_err();
+        assert_eq!((error.index(), error.kind()), (3, ExtractErrorKind::EncodeStream));
+
+        let factory = SharedFactory {
+            fail_create: Some(12),
+            ..SharedFactory::default()
+        };
+        let error = Bank::new(data.as_slice())
+            .unwrap()
+            .extract_all_parallel(factory, options)
+            .unwrap_err();
+        assert_eq!((error.index(), error.kind()), (12, ExtractErrorKind::CreateSink));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn extract_all_parallel_truncated_bank() {
+        let mut data = test_bank(3, 96);
+        // the data ends 10 bytes into the second stream
+        data.truncate(data.len() - 54);
+
+        let report = Bank::new(data.as_slice())
+            .unwrap()
+            .extract_all_parallel(SharedFactory::default(), ParallelOptions::new())
+            .unwrap();
+
+        let serial = Bank::new(data.as_slice())
+            .unwrap()
+            .extract_with(SharedFactory::default())
+            .unwrap();
+
+        assert_eq!(*report.report(), serial);
+        assert_eq!(report.report().missing_streams(), [2]);
     }
 }
@@ -0,0 +1,270 @@
+#[cfg(feature = "sha256")]
+use sha2::{Digest, Sha256};
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    io::{Result as IoResult, Write},
+};
+#[cfg(feature = "xxhash")]
+use xxhash_rust::xxh3::Xxh3;
+
+/// An algorithm for computing the checksums of streams while they are extracted.
+///
+/// Checksums are computed when chosen with [`SinkFactory::checksum`] or [`ExtractOptions::checksum`],
+/// and listed in [`ExtractionReport::checksums`].
+///
+/// [`SinkFactory::checksum`]: crate::SinkFactory::checksum
+/// [`ExtractOptions::checksum`]: crate::ExtractOptions::checksum
+/// [`ExtractionReport::checksums`]: crate::ExtractionReport::checksums
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ChecksumAlgorithm {
+    /// CRC-32 with polynomial 0x04C11DB7, as used by zip and PNG files.
+    Crc32,
+    /// The 64-bit variant of XXH3, which is much faster than the other algorithms.
+    /// Requires the `xxhash` feature.
+    #[cfg(feature = "xxhash")]
+    Xxh3,
+    /// SHA-256, which is much slower than the other algorithms, but also resistant to collisions on purpose.
+    /// Requires the `sha256` feature.
+    #[cfg(feature = "sha256")]
+    Sha256,
+}
+
+/// A checksum computed with a [`ChecksumAlgorithm`].
+///
+/// Checksums are displayed as lowercase hexadecimal digits, with the most significant byte first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Checksum {
+    /// A checksum computed with [`ChecksumAlgorithm::Crc32`].
+    Crc32(u32),
+    /// A checksum computed with [`ChecksumAlgorithm::Xxh3`].
+    #[cfg(feature = "xxhash")]
+    Xxh3(u64),
+    /// A checksum computed with [`ChecksumAlgorithm::Sha256`].
+    #[cfg(feature = "sha256")]
+    Sha256([u8; 32]),
+}
+
+impl Checksum {
+    /// Returns the [`ChecksumAlgorithm`] that this checksum was computed with.
+    #[must_use]
+    pub fn algorithm(&self) -> ChecksumAlgorithm {
+        match self {
+            Self::Crc32(_) => ChecksumAlgorithm::Crc32,
+            #[cfg(feature = "xxhash")]
+            Self::Xxh3(_) => ChecksumAlgorithm::Xxh3,
+            #[cfg(feature = "sha256")]
+            Self::Sha256(_) => ChecksumAlgorithm::Sha256,
+        }
+    }
+
+    /// Returns the bytes of the checksum, with the most significant byte first.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Crc32(crc) => crc.to_be_bytes().to_vec(),
+            #[cfg(feature = "xxhash")]
+            Self::Xxh3(hash) => hash.to_be_bytes().to_vec(),
+            #[cfg(feature = "sha256")]
+            Self::Sha256(hash) => hash.to_vec(),
+        }
+    }
+}
+
+impl Display for Checksum {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.to_bytes()
+            .iter()
+            .try_for_each(|byte| f.write_fmt(format_args!("{byte:02x}")))
+    }
+}
+
+/// The checksums of a stream, computed while it was extracted. See [`ExtractionReport::checksums`].
+///
+/// [`ExtractionReport::checksums`]: crate::ExtractionReport::checksums
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct StreamChecksums {
+    pub(crate) index: u32,
+    pub(crate) source: Checksum,
+    pub(crate) output: Checksum,
+}
+
+impl StreamChecksums {
+    /// Returns the index of the stream within the sound bank.
+    #[must_use]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the checksum of the stream's data, exactly as stored in the sound bank.
+    /// For streams cut short by the end of a truncated sound bank, this is the checksum of the data that was left.
+    #[must_use]
+    pub fn source(&self) -> Checksum {
+        self.source
+    }
+
+    /// Returns the checksum of everything written to the stream's writer.
+    #[must_use]
+    pub fn output(&self) -> Checksum {
+        self.output
+    }
+}
+
+// Computes a checksum from data given a chunk at a time.
+pub(crate) enum Hasher {
+    Crc32(u32),
+    #[cfg(feature = "xxhash")]
+    Xxh3(Box<Xxh3>),
+    #[cfg(feature = "sha256")]
+    Sha256(Sha256),
+}
+
+impl Hasher {
+    pub(crate) fn new(algorithm: ChecksumAlgorithm) -> Self {
+        match algorithm {
+            // the register starts with every bit set, which is inverted again when the checksum is finished
+            ChecksumAlgorithm::Crc32 => Self::Crc32(u32::MAX),
+            #[cfg(feature = "xxhash")]
+            ChecksumAlgorithm::Xxh3 => Self::Xxh3(Box::default()),
+            #[cfg(feature = "sha256")]
+            ChecksumAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+        }
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Crc32(crc) => {
+                *crc = data.iter().fold(*crc, |crc, &byte| {
+                    (crc >> 8) ^ CRC_TABLE[usize::from(crc.to_le_bytes()[0] ^ byte)]
+                });
+            }
+            #[cfg(feature = "xxhash")]
+            Self::Xxh3(hasher) => hasher.update(data),
+            #[cfg(feature = "sha256")]
+            Self::Sha256(hasher) => hasher.update(data),
+        }
+    }
+
+    pub(crate) fn finish(self) -> Checksum {
+        match self {
+            Self::Crc32(crc) => Checksum::Crc32(!crc),
+            #[cfg(feature = "xxhash")]
+            Self::Xxh3(hasher) => Checksum::Xxh3(hasher.digest()),
+            #[cfg(feature = "sha256")]
+            Self::Sha256(hasher) => Checksum::Sha256(hasher.finalize().into()),
+        }
+    }
+}
+
+// Computes the checksum of data that's already in memory, such as the data of a stream.
+pub(crate) fn checksum(algorithm: ChecksumAlgorithm, data: &[u8]) -> Checksum {
+    let mut hasher = Hasher::new(algorithm);
+    hasher.update(data);
+    hasher.finish()
+}
+
+// Computes the checksum of everything written to the inner writer, if an algorithm was chosen.
+pub(crate) struct ChecksumWriter<W: Write> {
+    inner: W,
+    hasher: Option<Hasher>,
+}
+
+impl<W: Write> ChecksumWriter<W> {
+    pub(crate) fn new(inner: W, algorithm: Option<ChecksumAlgorithm>) -> Self {
+        Self {
+            inner,
+            hasher: algorithm.map(Hasher::new),
+        }
+    }
+
+    pub(crate) fn finish(self) -> (W, Option<Checksum>) {
+        (self.inner, self.hasher.map(Hasher::finish))
+    }
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        let written = self.inner.write(buf)?;
+
+        if let Some(hasher) = &mut self.hasher {
+            hasher.update(&buf[..written]);
+        }
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        self.inner.flush()
+    }
+}
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+// CRC-32 (as used by zip files) uses polynomial 0x04C11DB7 with reflected bits, so the table is built
+// from the reflected polynomial 0xEDB88320.
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut index = 0;
+
+    while index < 256 {
+        #[allow(clippy::cast_possible_truncation)]
+        let mut crc = index as u32;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc = if crc & 1 == 0 {
+                crc >> 1
+            } else {
+                (crc >> 1) ^ 0xEDB8_8320
+            };
+            bit += 1;
+        }
+
+        table[index] = crc;
+        index += 1;
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod test {
+    use super::{checksum, Checksum, ChecksumAlgorithm, ChecksumWriter};
+    use std::io::Write;
+
+    #[test]
+    fn crc_check_value() {
+        assert_eq!(
+            checksum(ChecksumAlgorithm::Crc32, b"123456789"),
+            Checksum::Crc32(0xCBF4_3926)
+        );
+        assert_eq!(
+            checksum(ChecksumAlgorithm::Crc32, b"123456789").to_string(),
+            "cbf43926"
+        );
+    }
+
+    #[cfg(feature = "sha256")]
+    #[test]
+    fn sha256_check_value() {
+        assert_eq!(
+            checksum(ChecksumAlgorithm::Sha256, b"abc").to_string(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn checksum_of_written_data() {
+        let mut writer = ChecksumWriter::new(Vec::new(), Some(ChecksumAlgorithm::Crc32));
+        writer.write_all(b"1234").unwrap();
+        writer.write_all(b"56789").unwrap();
+
+        let (output, crc) = writer.finish();
+        assert_eq!(output, b"123456789");
+        assert_eq!(crc, Some(checksum(ChecksumAlgorithm::Crc32, &output)));
+
+        let (_, crc) = ChecksumWriter::new(Vec::new(), None).finish();
+        assert_eq!(crc, None);
+    }
+}
@@ -0,0 +1,362 @@
+use crate::{header::AudioFormat, scan::FoundBank};
+use std::{
+    cmp::Reverse,
+    io::{Error as IoError, Read},
+};
+
+/// A scheme for decrypting sound banks encrypted with a key, selected with [`ParseOptions::xor_scheme`].
+///
+/// FMOD encrypts sound banks byte by byte, combining each byte with the next byte of the key
+/// (repeating from the start of the key when it runs out) and reversing the order of its bits.
+///
+/// [`ParseOptions::xor_scheme`]: crate::ParseOptions::xor_scheme
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum XorScheme {
+    /// Each byte has its bits reversed, then is combined with the key using XOR. This is the scheme used by FMOD.
+    #[default]
+    ReverseBits,
+    /// Each byte is combined with the key using XOR, then has its bits reversed.
+    /// This variant is used by some games that encrypt sound banks themselves.
+    ReverseBitsAfterXor,
+    /// Each byte is only combined with the key using XOR.
+    Xor,
+}
+
+impl XorScheme {
+    fn decrypt(self, byte: u8, key: u8) -> u8 {
+        match self {
+            Self::ReverseBits => byte.reverse_bits() ^ key,
+            Self::ReverseBitsAfterXor => (byte ^ key).reverse_bits(),
+            Self::Xor => byte ^ key,
+        }
+    }
+
+    // Returns the byte of the key that decrypts `byte` to `plain`.
+    fn key(self, byte: u8, plain: u8) -> u8 {
+        match self {
+            Self::ReverseBits => byte.reverse_bits() ^ plain,
+            Self::ReverseBitsAfterXor => byte ^ plain.reverse_bits(),
+            Self::Xor => byte ^ plain,
+        }
+    }
+}
+
+/// An I/O stream that decrypts a sound bank encrypted with a key as it is read.
+///
+/// This is the source of a sound bank created with [`Bank::decrypt`](crate::Bank::decrypt).
+/// Bytes are decrypted relative to the position of the I/O stream when the sound bank was created,
+/// which should be the start of the sound bank.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Decrypt<R> {
+    source: R,
+    key: Box<[u8]>,
+    scheme: XorScheme,
+    position: usize,
+}
+
+impl<R: Read> Decrypt<R> {
+    // The key must not be empty.
+    pub(crate) fn new(source: R, key: &[u8], scheme: XorScheme) -> Self {
+        Self {
+            source,
+            key: key.into(),
+            scheme,
+            position: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for Decrypt<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        let read = self.source.read(buf)?;
+
+        for byte in &mut buf[..read] {
+            *byte = self.scheme.decrypt(*byte, self.key[self.position]);
+            self.position = (self.position + 1) % self.key.len();
+        }
+
+        Ok(read)
+    }
+}
+
+// Keys longer than this are rare, and can't be fully derived from the bytes of a file header that are usually known.
+const DEFAULT_MAX_KEY_LEN: usize = 32;
+
+const SCHEMES: [XorScheme; 3] = [
+    XorScheme::ReverseBits,
+    XorScheme::ReverseBitsAfterXor,
+    XorScheme::Xor,
+];
+
+// Bytes of an FSB5 file header that are usually 0: the upper bytes of the number of streams,
+// the sizes of the stream headers, name table, and stream data, the unknown field, and the encoding flags.
+const USUALLY_ZERO: [usize; 14] = [10, 11, 14, 15, 18, 19, 23, 28, 29, 30, 31, 33, 34, 35];
+const USUALLY_ZERO_END: usize = 36;
+
+/// A possible key for a sound bank encrypted with an unknown key, found with [`guess_key`].
+///
+/// A candidate can be used directly with [`Bank::decrypt_with_options`](crate::Bank::decrypt_with_options),
+/// along with its scheme:
+///
+/// ```
+/// use fsbex::{guess_key, Bank, ParseOptions};
+/// use std::error::Error;
+///
+/// fn decrypt_unknown_key(bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+///     let candidates = guess_key(&bytes[..bytes.len().min(72)]);
+///     let best = candidates.first().ok_or("no key found")?;
+///
+///     let options = ParseOptions::new().xor_scheme(best.scheme());
+///     let bank = Bank::decrypt_with_options(bytes, best, options)?;
+///     println!("{} streams", bank.num_streams());
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct KeyCandidate {
+    key: Vec<u8>,
+    scheme: XorScheme,
+    score: u32,
+}
+
+impl KeyCandidate {
+    /// Returns the bytes of the key.
+    #[must_use]
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+
+    /// Returns the scheme the key is used with.
+    #[must_use]
+    pub fn scheme(&self) -> XorScheme {
+        self.scheme
+    }
+
+    /// Returns how plausible the file header decrypted with this key looks. Higher is more plausible.
+    #[must_use]
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+}
+
+impl AsRef<[u8]> for KeyCandidate {
+    fn as_ref(&self) -> &[u8] {
+        &self.key
+    }
+}
+
+/// Guesses the key of an encrypted FSB5 sound bank from the start of its data, trying keys of up to 32 bytes.
+///
+/// See [`guess_key_with_max_len`] for more information.
+#[must_use]
+pub fn guess_key(prefix: &[u8]) -> Vec<KeyCandidate> {
+    guess_key_with_max_len(prefix, DEFAULT_MAX_KEY_LEN)
+}
+
+/// Guesses the key of an encrypted FSB5 sound bank from the start of its data, trying keys of up to `max_len` bytes.
+///
+/// Keys are derived from bytes of the file header that are known or usually 0, such as the file signature
+/// and version, for every [`XorScheme`]. Keys that repeat a shorter key are left out, as are keys that can't
+/// be derived from the header. Candidates are sorted by how plausible the decrypted header looks,
+/// then by how much they look like text, then by length, so the first candidate is the most likely key.
+///
+/// `prefix` should hold at least the first 72 bytes of the sound bank (the base header and first stream header);
+/// shorter prefixes give fewer and less reliable candidates.
+#[must_use]
+pub fn guess_key_with_max_len(prefix: &[u8], max_len: usize) -> Vec<KeyCandidate> {
+    let mut candidates = Vec::new();
+
+    for len in 1..=max_len {
+        for scheme in SCHEMES {
+            for version in [1, 0] {
+                let Some(key) = derive_key(prefix, len, scheme, version) else {
+                    continue;
+                };
+
+                // keys that repeat a shorter key were already found with the shorter length
+                let repeats = (1..len)
+                    .filter(|period| len % period == 0)
+                    .any(|period| key.iter().zip(&key[period..]).all(|(a, b)| a == b));
+
+                if repeats
+                    || candidates
+                        .iter()
+                        .any(|c: &KeyCandidate| c.key == key && c.scheme == scheme)
+                {
+                    continue;
+                }
+
+                let score = score_header(prefix, &key, scheme);
+                candidates.push(KeyCandidate { key, scheme, score });
+            }
+        }
+    }
+
+    // Reversing the bits after XOR is the same as XORing with a bit-reversed key before reversing the bits,
+    // so keys that look like text are preferred, since most keys are strings.
+    candidates.sort_by_key(|candidate| {
+        let text = candidate.key.iter().filter(|byte| byte.is_ascii_graphic()).count();
+        (Reverse(candidate.score), Reverse(text), candidate.key.len())
+    });
+    candidates
+}
+
+// Derives a key of `len` bytes from the known bytes of the file header, then fills in the remaining bytes
+// from bytes that are usually 0. Returns `None` if known bytes disagree, or if a byte of the key can't be derived.
+fn derive_key(prefix: &[u8], len: usize, scheme: XorScheme, version: u8) -> Option<Vec<u8>> {
+    let known = [b'F', b'S', b'B', b'5', version, 0, 0, 0];
+    let known = known.iter().enumerate().chain([25, 26, 27].iter().map(|&i| (i, &0)));
+
+    let mut key = vec![None; len];
+
+    for (position, &plain) in known {
+        let Some(&byte) = prefix.get(position) else {
+            continue;
+        };
+
+        let key_byte = scheme.key(byte, plain);
+        match key[position % len] {
+            Some(existing) if existing != key_byte => return None,
+            _ => key[position % len] = Some(key_byte),
+        }
+    }
+
+    // use the most common key byte among bytes that are usually 0
+    for (index, slot) in key.iter_mut().enumerate().filter(|(_, slot)| slot.is_none()) {
+        let mut votes = [0; 256];
+        for position in (index..USUALLY_ZERO_END).step_by(len) {
+            if let (true, Some(&byte)) = (USUALLY_ZERO.contains(&position), prefix.get(position)) {
+                votes[usize::from(scheme.key(byte, 0))] += 1;
+            }
+        }
+
+        *slot = (0..=u8::MAX)
+            .filter(|&byte| votes[usize::from(byte)] > 0)
+            .max_by_key(|&byte| (votes[usize::from(byte)], Reverse(byte)));
+    }
+
+    key.into_iter().collect()
+}
+
+// Returns whether `prefix` looks like the start of an encrypted FSB5 sound bank, which is the case
+// if the most likely key decrypts it to a valid file header.
+pub(crate) fn looks_encrypted(prefix: &[u8]) -> bool {
+    guess_key(prefix).first().is_some_and(|candidate| {
+        FoundBank::parse(0, &decrypt_prefix(prefix, &candidate.key, candidate.scheme)).is_some()
+    })
+}
+
+fn decrypt_prefix(prefix: &[u8], key: &[u8], scheme: XorScheme) -> Vec<u8> {
+    prefix
+        .iter()
+        .zip(key.iter().cycle())
+        .map(|(&byte, &key)| scheme.decrypt(byte, key))
+        .collect()
+}
+
+// Scores how plausible the file header decrypted with a key looks.
+fn score_header(prefix: &[u8], key: &[u8], scheme: XorScheme) -> u32 {
+    let header = decrypt_prefix(prefix, key, scheme);
+
+    let field = |offset: usize| {
+        header
+            .get(offset..offset + 4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().expect("slice has 4 bytes")))
+    };
+
+    let mut score: u32 = USUALLY_ZERO
+        .iter()
+        .map(|&position| u32::from(header.get(position) == Some(&0)))
+        .sum();
+
+    let num_streams = field(8).unwrap_or(0);
+    let checks = [
+        (1..=0xFFFF).contains(&num_streams),
+        field(12).is_some_and(|size| size >= num_streams.saturating_mul(8)),
+        field(16).is_some_and(|size| size == 0 || size >= num_streams.saturating_mul(4)),
+        field(20).is_some_and(|size| size != 0),
+        field(24).is_some_and(|format| AudioFormat::parse_known(format).is_some()),
+    ];
+    score += checks.iter().map(|&check| u32::from(check) * 4).sum::<u32>();
+
+    // the whole header (including the first stream header) is valid
+    if FoundBank::parse(0, &header).is_some() {
+        score += 20;
+    }
+
+    score
+}
+
+#[cfg(test)]
+mod test {
+    use super::{guess_key, guess_key_with_max_len, Decrypt, XorScheme};
+    use std::io::Read;
+
+    #[test]
+    fn decrypt_with_every_scheme() {
+        let key = [0x12, 0x34, 0x56];
+        let data = [0x01, 0x80, 0xFF, 0x0F];
+
+        let expected = [
+            (XorScheme::ReverseBits, [0x92, 0x35, 0xA9, 0xE2]),
+            (XorScheme::ReverseBitsAfterXor, [0xC8, 0x2D, 0x95, 0xB8]),
+            (XorScheme::Xor, [0x13, 0xB4, 0xA9, 0x1D]),
+        ];
+
+        for (scheme, decrypted) in expected {
+            let mut output = Vec::new();
+            let read = Decrypt::new(data.as_slice(), &key, scheme)
+                .read_to_end(&mut output)
+                .unwrap();
+
+            assert_eq!(read, data.len());
+
+            assert_eq!(output, decrypted, "{scheme:?}");
+        }
+    }
+
+    #[test]
+    fn guess_key_from_header() {
+        let mut header = Vec::from(*b"FSB5");
+        for field in [1u32, 3, 3 * 8, 0, 0x1234, 15, 0, 0x01] {
+            header.extend_from_slice(&field.to_le_bytes());
+        }
+        header.resize(60, 0xA5);
+        header.extend_from_slice(&((8 << 1) | (2u64 << 34)).to_le_bytes());
+
+        for (key, scheme) in [
+            (b"DFm3t4lFTW".as_slice(), XorScheme::ReverseBits),
+            (b"key".as_slice(), XorScheme::ReverseBitsAfterXor),
+            (&[0x5A; 1], XorScheme::Xor),
+        ] {
+            // encrypt by decrypting with the inverse of the scheme
+            let encrypted: Vec<u8> = header
+                .iter()
+                .zip(key.iter().cycle())
+                .map(|(&byte, &key)| match scheme {
+                    XorScheme::ReverseBits => (byte ^ key).reverse_bits(),
+                    XorScheme::ReverseBitsAfterXor => byte.reverse_bits() ^ key,
+                    XorScheme::Xor => byte ^ key,
+                })
+                .collect();
+
+            let candidates = guess_key(&encrypted);
+            assert_eq!(candidates[0].key(), key);
+            assert_eq!(candidates[0].scheme(), scheme);
+
+            // repeated keys are left out
+            assert!(!candidates.iter().any(|c| c.key() == key.repeat(2)));
+        }
+
+        // keys longer than the maximum length aren't found
+        let encrypted: Vec<u8> = header
+            .iter()
+            .zip(b"DFm3t4lFTW".iter().cycle())
+            .map(|(&byte, &key)| (byte ^ key).reverse_bits())
+            .collect();
+        assert!(guess_key_with_max_len(&encrypted, 8)
+            .iter()
+            .all(|c| c.key() != b"DFm3t4lFTW"));
+    }
+}
@@ -0,0 +1,142 @@
+use super::wav::{Format, WavSpec};
+use std::io::{Error as IoError, Write};
+
+// AIFF file header information taken from:
+// [1]: https://www-mmsp.ece.mcgill.ca/Documents/AudioFormats/AIFF/AIFF.html
+// [2]: https://www-mmsp.ece.mcgill.ca/Documents/AudioFormats/AIFF/Docs/AIFF-C.9.26.91.pdf
+
+// Plain AIFF files can only hold integer samples, so float samples are written to AIFF-C files.
+// AIFF-C files start with a format version chunk holding the timestamp of version 1 of the specification.
+const AIFC_VERSION_1: u32 = 0xA280_5140;
+
+// Compression type and name of 32-bit float samples in AIFF-C files.
+// The name is stored as a Pascal string (prefixed by its length), padded to an even size.
+const FLOAT_COMPRESSION_TYPE: [u8; 4] = *b"fl32";
+const FLOAT_COMPRESSION_NAME: &[u8] = b"32-bit floating point";
+
+fn is_aifc(spec: WavSpec<'_>) -> bool {
+    spec.format == Format::Float
+}
+
+fn comm_size(spec: WavSpec<'_>) -> u32 {
+    if is_aifc(spec) {
+        let name_len =
+            u32::try_from(FLOAT_COMPRESSION_NAME.len()).expect("compression name is short");
+        18 + 4 + (1 + name_len).next_multiple_of(2)
+    } else {
+        18
+    }
+}
+
+// Returns the size of everything in the file before the samples in the SSND chunk.
+pub(super) fn header_size(spec: WavSpec<'_>) -> u64 {
+    let fver_size = if is_aifc(spec) { 8 + 4 } else { 0 };
+    12 + fver_size + 8 + u64::from(comm_size(spec)) + 8 + 8
+}
+
+// Writes a header for `data_size` bytes of big-endian samples, in a file that is `file_size` bytes long.
+pub(super) fn write_header<W: Write>(
+    spec: WavSpec<'_>,
+    data_size: u64,
+    file_size: u64,
+    sink: &mut W,
+) -> Result<(), IoError> {
+    let form_size = u32::try_from(file_size - 8).expect("FORM size was checked to fit in 32 bits");
+    let block_align = u64::from(spec.channels * spec.bits_per_sample.div_ceil(8));
+    let num_frames =
+        u32::try_from(data_size / block_align).expect("sample count is at most the FORM size");
+
+    sink.write_all(b"FORM")?;
+    sink.write_all(&form_size.to_be_bytes())?;
+
+    if is_aifc(spec) {
+        sink.write_all(b"AIFC")?;
+        sink.write_all(b"FVER")?;
+        sink.write_all(&4u32.to_be_bytes())?;
+        sink.write_all(&AIFC_VERSION_1.to_be_bytes())?;
+    } else {
+        sink.write_all(b"AIFF")?;
+    }
+
+    sink.write_all(b"COMM")?;
+    sink.write_all(&comm_size(spec).to_be_bytes())?;
+    sink.write_all(&spec.channels.to_be_bytes())?;
+    sink.write_all(&num_frames.to_be_bytes())?;
+    sink.write_all(&spec.bits_per_sample.to_be_bytes())?;
+    sink.write_all(&extended(spec.sample_rate))?;
+
+    if is_aifc(spec) {
+        let name_len =
+            u8::try_from(FLOAT_COMPRESSION_NAME.len()).expect("compression name is short");
+
+        sink.write_all(&FLOAT_COMPRESSION_TYPE)?;
+        sink.write_all(&[name_len])?;
+        sink.write_all(FLOAT_COMPRESSION_NAME)?;
+
+        if name_len % 2 == 0 {
+            sink.write_all(&[0])?;
+        }
+    }
+
+    // the size of the SSND chunk includes the offset and block size fields, which are unused
+    sink.write_all(b"SSND")?;
+    sink.write_all(
+        &u32::try_from(8 + data_size)
+            .expect("SSND size is at most the FORM size")
+            .to_be_bytes(),
+    )?;
+    sink.write_all(&0u32.to_be_bytes())?;
+    sink.write_all(&0u32.to_be_bytes())
+}
+
+// Converts an integer to an 80-bit IEEE 754 extended precision float, which is how AIFF files store sample rates.
+// Unlike other float formats, the integer bit of the mantissa is stored explicitly.
+fn extended(value: u32) -> [u8; 10] {
+    let mut bytes = [0; 10];
+
+    if value != 0 {
+        let shift = u64::from(value).leading_zeros();
+        let exponent = u16::try_from(16383 + 63 - shift).expect("exponent is at most 16446");
+        let mantissa = u64::from(value) << shift;
+
+        bytes[..2].copy_from_slice(&exponent.to_be_bytes());
+        bytes[2..].copy_from_slice(&mantissa.to_be_bytes());
+    }
+
+    bytes
+}
+
+#[cfg(test)]
+mod test {
+    use super::{extended, header_size, write_header};
+    use crate::encode::wav::{Format, WavSpec};
+
+    #[test]
+    fn convert_sample_rates() {
+        assert_eq!(extended(44100), [0x40, 0x0E, 0xAC, 0x44, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(extended(48000), [0x40, 0x0E, 0xBB, 0x80, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(extended(1), [0x3F, 0xFF, 0x80, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(extended(0), [0; 10]);
+    }
+
+    #[test]
+    fn write_aifc_header_for_float() {
+        let spec = WavSpec {
+            format: Format::Float,
+            bits_per_sample: 32,
+            ..WavSpec::pcm16(2, 48000)
+        };
+
+        let mut output = Vec::new();
+        write_header(spec, 16, header_size(spec) + 16, &mut output).unwrap();
+
+        assert_eq!(output.len(), 92);
+        assert_eq!(output[8..12], *b"AIFC");
+        assert_eq!(output[12..16], *b"FVER");
+        assert_eq!(output[24..32], [b'C', b'O', b'M', b'M', 0, 0, 0, 44]);
+        assert_eq!(output[50..54], *b"fl32");
+        assert_eq!(output[54], 21);
+        assert_eq!(output[55..76], *b"32-bit floating point");
+        assert_eq!(output[76..84], [b'S', b'S', b'N', b'D', 0, 0, 0, 24]);
+    }
+}
@@ -0,0 +1,359 @@
+use crate::{header::StreamInfo, read::Reader};
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io::{copy, Error as IoError, Read, Write},
+};
+
+// ATRAC9 RIFF file header and configuration data information taken from:
+// [1]: https://github.com/Thealexbarney/LibAtrac9/blob/master/CSharp/LibAtrac9/ConfigData.cs
+// [2]: https://github.com/vgmstream/vgmstream/blob/master/src/meta/riff.c
+
+const FORMAT_TAG: u16 = 0xFFFE;
+const FMT_CHUNK_SIZE: u32 = 0x34;
+const EXTENSIBLE_SIZE: u16 = 0x22;
+const FACT_CHUNK_SIZE: u32 = 0x08;
+const VERSION: u32 = 2;
+
+// {47E142D2-36BA-4D8D-88FC-61654F8C836C}, stored in the mixed-endian GUID layout
+const ATRAC9_GUID: [u8; 16] = [
+    0xD2, 0x42, 0xE1, 0x47, 0xBA, 0x36, 0x8D, 0x4D, 0x88, 0xFC, 0x61, 0x65, 0x4F, 0x8C, 0x83, 0x6C,
+];
+
+// Number of samples at the start of the stream that are only used to prime the decoder.
+const ENCODER_DELAY: u32 = 256;
+
+const SYNC_BYTE: u8 = 0xFE;
+
+// Indexed by the sample rate index of the configuration data.
+const FRAME_SAMPLES_POWER: [u8; 16] = [6, 6, 7, 7, 7, 8, 8, 8, 6, 6, 7, 7, 7, 8, 8, 8];
+
+// Indexed by the channel config index of the configuration data: (channels, channel mask).
+const CHANNEL_CONFIGS: [(u16, u32); 6] = [
+    (1, 0x04),
+    (2, 0x03),
+    (2, 0x03),
+    (6, 0x3F),
+    (8, 0x063F),
+    (4, 0x33),
+];
+
+/// Encodes an ATRAC9 stream by wrapping the raw stream data in an ATRAC9 RIFF file.
+///
+/// The stream data is copied verbatim; only the file header is constructed.
+pub(super) fn encode<R: Read, W: Write>(
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+    mut sink: W,
+) -> Result<W, Atrac9Error> {
+    let config = match info.atrac9_config.as_deref() {
+        Some([config]) => *config,
+        Some([]) | None => return Err(Atrac9Error::new(Atrac9ErrorKind::MissingConfig)),
+        Some(_) => return Err(Atrac9Error::new(Atrac9ErrorKind::MultipleLayers)),
+    };
+
+    let config =
+        Config::parse(config).ok_or_else(|| Atrac9Error::new(Atrac9ErrorKind::InvalidConfig))?;
+
+    write_header(info, &config, &mut sink)?;
+
+    let stream_size = info.size as usize;
+
+    // There could be more data after the stream, so a limit is placed on the number of bytes read.
+    let _bytes_copied = copy(&mut source.limit(stream_size as u64), &mut sink)
+        .map_err(Atrac9Error::from_io(Atrac9ErrorKind::EncodeStream))?;
+
+    sink.flush()
+        .map(|()| sink)
+        .map_err(Atrac9Error::from_io(Atrac9ErrorKind::FinishStream))
+}
+
+// Returns the size of a superframe in bytes, as described by the configuration data.
+pub(crate) fn superframe_size(config: [u8; 4]) -> Option<u16> {
+    Config::parse(config).map(|config| config.superframe_size)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Config {
+    data: [u8; 4],
+    channels: u16,
+    channel_mask: u32,
+    superframe_size: u16,
+    superframe_samples: u16,
+}
+
+impl Config {
+    // Configuration data is a 32-bit big-endian bitfield:
+    // sync byte (8), sample rate index (4), channel config index (3), validation bit (1),
+    // frame size minus 1 (11), superframe index (2), unused (3)
+    fn parse(data: [u8; 4]) -> Option<Self> {
+        let sample_rate_index = data[1] >> 4;
+        let channel_config_index = (data[1] >> 1) & 0x07;
+        let validation_bit = data[1] & 0x01;
+
+        let bits = u16::from_be_bytes([data[2], data[3]]);
+        let frame_size = ((bits >> 5) & 0x07FF) + 1;
+        let superframe_index = (bits >> 3) & 0x03;
+
+        if data[0] != SYNC_BYTE || validation_bit != 0 {
+            return None;
+        }
+
+        let (channels, channel_mask) = *CHANNEL_CONFIGS.get(usize::from(channel_config_index))?;
+
+        // Frames are grouped into superframes of 1, 2, 4, or 8 frames.
+        // The size of a superframe is at most 2048 * 8 bytes, and it holds at most 256 * 8 samples.
+        let frames_per_superframe = 1 << superframe_index;
+        let frame_samples: u16 = 1 << FRAME_SAMPLES_POWER[usize::from(sample_rate_index)];
+
+        Some(Self {
+            data,
+            channels,
+            channel_mask,
+            superframe_size: frame_size * frames_per_superframe,
+            superframe_samples: frame_samples * frames_per_superframe,
+        })
+    }
+}
+
+fn write_header<W: Write>(
+    info: &StreamInfo,
+    config: &Config,
+    sink: &mut W,
+) -> Result<(), Atrac9Error> {
+    let sample_rate = info.sample_rate.get();
+    let data_size = info.size;
+
+    let riff_size = (4 + 8 + FMT_CHUNK_SIZE + 8 + FACT_CHUNK_SIZE + 8)
+        .checked_add(data_size)
+        .ok_or_else(|| Atrac9Error::new(Atrac9ErrorKind::StreamTooLarge))?;
+
+    let bytes_per_sec = u64::from(sample_rate) * u64::from(config.superframe_size)
+        / u64::from(config.superframe_samples);
+
+    (|| {
+        sink.write_all(b"RIFF")?;
+        sink.write_all(&riff_size.to_le_bytes())?;
+        sink.write_all(b"WAVE")?;
+
+        // base WAVEFORMATEX fields; ATRAC9 has no fixed bit depth
+        sink.write_all(b"fmt ")?;
+        sink.write_all(&FMT_CHUNK_SIZE.to_le_bytes())?;
+        sink.write_all(&FORMAT_TAG.to_le_bytes())?;
+        sink.write_all(&config.channels.to_le_bytes())?;
+        sink.write_all(&sample_rate.to_le_bytes())?;
+        sink.write_all(&u32::try_from(bytes_per_sec).unwrap_or(u32::MAX).to_le_bytes())?;
+        sink.write_all(&config.superframe_size.to_le_bytes())?;
+        sink.write_all(&0u16.to_le_bytes())?;
+        sink.write_all(&EXTENSIBLE_SIZE.to_le_bytes())?;
+
+        // WAVEFORMATEXTENSIBLE fields, followed by ATRAC9-specific fields
+        sink.write_all(&config.superframe_samples.to_le_bytes())?;
+        sink.write_all(&config.channel_mask.to_le_bytes())?;
+        sink.write_all(&ATRAC9_GUID)?;
+        sink.write_all(&VERSION.to_le_bytes())?;
+        sink.write_all(&config.data)?;
+        sink.write_all(&0u32.to_le_bytes())?;
+
+        // The sample count of the bank already covers all the stream data, including the encoder delay.
+        sink.write_all(b"fact")?;
+        sink.write_all(&FACT_CHUNK_SIZE.to_le_bytes())?;
+        sink.write_all(&info.num_samples.get().to_le_bytes())?;
+        sink.write_all(&ENCODER_DELAY.to_le_bytes())?;
+
+        sink.write_all(b"data")?;
+        sink.write_all(&data_size.to_le_bytes())
+    })()
+    .map_err(Atrac9Error::from_io(Atrac9ErrorKind::CreateHeader))
+}
+
+/// Represents an error that can occur when encoding an ATRAC9 stream.
+///
+/// See [`Atrac9ErrorKind`] for the different kinds of errors that can occur.
+#[derive(Debug)]
+pub struct Atrac9Error {
+    kind: Atrac9ErrorKind,
+    source: Option<IoError>,
+}
+
+/// A variant of an [`Atrac9Error`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Atrac9ErrorKind {
+    /// Configuration data was not found in the stream header within the sound bank.
+    /// This data is needed to build the file header.
+    MissingConfig,
+    /// The configuration data of the stream was invalid.
+    InvalidConfig,
+    /// The stream is made up of multiple ATRAC9 layers, which can't be stored in a single file.
+    MultipleLayers,
+    /// The stream was too large to fit in an ATRAC9 RIFF file.
+    StreamTooLarge,
+    /// Failed to write the file header due to an underlying I/O error.
+    CreateHeader,
+    /// Failed to encode the entire stream via copying from reader to writer.
+    EncodeStream,
+    /// Failed to flush the writer after encoding the entire stream.
+    FinishStream,
+}
+
+impl Atrac9Error {
+    fn new(kind: Atrac9ErrorKind) -> Self {
+        Self { kind, source: None }
+    }
+
+    fn from_io(kind: Atrac9ErrorKind) -> impl FnOnce(IoError) -> Self {
+        move |source| Self {
+            kind,
+            source: Some(source),
+        }
+    }
+
+    /// Returns the [`Atrac9ErrorKind`] associated with this error.
+    #[must_use]
+    pub fn kind(&self) -> Atrac9ErrorKind {
+        self.kind
+    }
+}
+
+impl Display for Atrac9Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.kind.fmt(f)
+    }
+}
+
+impl Error for Atrac9Error {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.source {
+            Some(source) => Some(source),
+            None => None,
+        }
+    }
+}
+
+impl Display for Atrac9ErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Self::MissingConfig => "file header did not contain ATRAC9 configuration data",
+            Self::InvalidConfig => "ATRAC9 configuration data was invalid",
+            Self::MultipleLayers => "ATRAC9 streams with multiple layers are not supported",
+            Self::StreamTooLarge => "ATRAC9 stream was too large to encode",
+            Self::CreateHeader => "failed to encode file header",
+            Self::EncodeStream => "failed to encode full ATRAC9 stream",
+            Self::FinishStream => "failed to finalize writing ATRAC9 stream data",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{encode, Atrac9ErrorKind, Config, ATRAC9_GUID};
+    use crate::{header::StreamInfo, read::Reader};
+
+    fn u16_at(data: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
+    }
+
+    fn u32_at(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn config_data(
+        sample_rate_index: u32,
+        channel_config_index: u32,
+        frame_size: u32,
+        superframe_index: u32,
+    ) -> [u8; 4] {
+        (0xFE << 24
+            | sample_rate_index << 20
+            | channel_config_index << 17
+            | (frame_size - 1) << 5
+            | superframe_index << 3)
+            .to_be_bytes()
+    }
+
+    #[test]
+    fn parse_config() {
+        // 48000 Hz, stereo, 256-byte frames, 4 frames per superframe
+        let config = Config::parse(config_data(7, 2, 0x100, 2)).unwrap();
+        assert_eq!(config.channels, 2);
+        assert_eq!(config.channel_mask, 0x03);
+        assert_eq!(config.superframe_size, 0x400);
+        assert_eq!(config.superframe_samples, 1024);
+
+        // 12000 Hz, 5.1, maximum frame size, 8 frames per superframe
+        let config = Config::parse(config_data(1, 3, 0x800, 3)).unwrap();
+        assert_eq!(config.channels, 6);
+        assert_eq!(config.channel_mask, 0x3F);
+        assert_eq!(config.superframe_size, 0x4000);
+        assert_eq!(config.superframe_samples, 512);
+
+        // 88200 Hz (band extension range), mono, 1 frame per superframe
+        let config = Config::parse(config_data(11, 0, 0x40, 0)).unwrap();
+        assert_eq!(config.superframe_size, 0x40);
+        assert_eq!(config.superframe_samples, 128);
+    }
+
+    #[test]
+    fn parse_invalid_config() {
+        // wrong sync byte
+        let mut data = config_data(7, 2, 0x100, 2);
+        data[0] = 0xFF;
+        assert_eq!(Config::parse(data), None);
+
+        // validation bit is set
+        let mut data = config_data(7, 2, 0x100, 2);
+        data[1] |= 0x01;
+        assert_eq!(Config::parse(data), None);
+
+        // unknown channel config index
+        assert_eq!(Config::parse(config_data(7, 6, 0x100, 2)), None);
+    }
+
+    #[test]
+    fn encode_with_config() {
+        let info = StreamInfo {
+            atrac9_config: Some(vec![config_data(7, 2, 0x100, 2)].into_boxed_slice()),
+            ..StreamInfo::dummy(2, 1024, 4)
+        };
+        let data = [1, 2, 3, 4, 5, 6];
+        let output = encode(&info, &mut Reader::new(data.as_slice()), Vec::new()).unwrap();
+
+        assert_eq!(output[0..4], *b"RIFF");
+        assert_eq!(u32_at(&output, 4) as usize, output.len() - 8);
+        assert_eq!(output[8..16], *b"WAVEfmt ");
+        assert_eq!(u32_at(&output, 0x10), 0x34);
+        assert_eq!(u16_at(&output, 0x14), 0xFFFE);
+        assert_eq!(u16_at(&output, 0x16), 2);
+        assert_eq!(u32_at(&output, 0x18), 44100);
+        assert_eq!(u32_at(&output, 0x1C), 44100);
+        assert_eq!(u16_at(&output, 0x20), 0x400);
+        assert_eq!(u16_at(&output, 0x24), 0x22);
+        assert_eq!(u16_at(&output, 0x26), 1024);
+        assert_eq!(u32_at(&output, 0x28), 0x03);
+        assert_eq!(output[0x2C..0x3C], ATRAC9_GUID);
+        assert_eq!(output[0x40..0x44], config_data(7, 2, 0x100, 2));
+        assert_eq!(output[0x48..0x50], *b"fact\x08\0\0\0");
+        assert_eq!(u32_at(&output, 0x50), 1024);
+        assert_eq!(u32_at(&output, 0x54), 256);
+        assert_eq!(output[0x58..0x5C], *b"data");
+        assert_eq!(u32_at(&output, 0x5C), 4);
+        assert_eq!(output[0x60..], [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn require_single_config() {
+        let data = [0; 4];
+
+        let info = StreamInfo::dummy(2, 1024, 4);
+        assert!(encode(&info, &mut Reader::new(data.as_slice()), Vec::new())
+            .is_err_and(|e| e.kind() == Atrac9ErrorKind::MissingConfig));
+
+        let info = StreamInfo {
+            atrac9_config: Some(vec![config_data(7, 2, 0x100, 2); 2].into_boxed_slice()),
+            ..StreamInfo::dummy(4, 1024, 4)
+        };
+        assert!(encode(&info, &mut Reader::new(data.as_slice()), Vec::new())
+            .is_err_and(|e| e.kind() == Atrac9ErrorKind::MultipleLayers));
+    }
+}
@@ -0,0 +1,142 @@
+use super::wav::{channel_mask, Format, WavSpec};
+use std::io::{Error as IoError, Write};
+
+// CAF file layout taken from:
+// [1]: https://developer.apple.com/library/archive/documentation/MusicAudio/Reference/CAFSpec/CAF_spec/CAF_spec.html
+
+// Flags of the desc chunk for linear PCM samples.
+// Integer samples are always signed, so 8-bit samples don't need a bias like in WAVE files.
+const FLAG_IS_FLOAT: u32 = 0x1;
+const FLAG_IS_LITTLE_ENDIAN: u32 = 0x2;
+
+// The desc chunk holds the sample rate (as a 64-bit float) and 6 other fields of 4 bytes each.
+const DESC_CHUNK_SIZE: u64 = 8 + 6 * 4;
+
+// The chan chunk holds a layout tag, a channel bitmap, and the number of channel descriptions that follow (always 0).
+const CHAN_CHUNK_SIZE: u64 = 3 * 4;
+
+// Layout tags describing how the channel layout is stored.
+// Channel bitmaps use the same speaker bits as the channel masks of WAVE files.
+const LAYOUT_TAG_USE_CHANNEL_BITMAP: u32 = 1 << 16;
+const LAYOUT_TAG_DISCRETE_IN_ORDER: u32 = 147 << 16;
+
+// The data chunk starts with an edit count, which is unused.
+const EDIT_COUNT_SIZE: u64 = 4;
+
+// A data chunk size of -1 means the data extends to the end of the file, for files written to streaming sinks.
+const UNKNOWN_DATA_SIZE: i64 = -1;
+
+// Like WAVE files with more than 2 channels, CAF files with more than 2 channels describe their speaker layout.
+fn has_chan(spec: WavSpec<'_>) -> bool {
+    spec.channels > 2
+}
+
+// The info chunk holds the number of entries, followed by null-terminated keys and values.
+fn info_size(spec: WavSpec<'_>) -> Option<u64> {
+    let name = spec.info.name()?;
+    Some(4 + (b"title".len() as u64 + 1) + (name.len() as u64 + 1))
+}
+
+// Returns the size of everything in the file before the samples in the data chunk.
+pub(super) fn header_size(spec: WavSpec<'_>) -> u64 {
+    let chan_size = if has_chan(spec) {
+        12 + CHAN_CHUNK_SIZE
+    } else {
+        0
+    };
+    let info_size = info_size(spec).map_or(0, |size| 12 + size);
+
+    8 + 12 + DESC_CHUNK_SIZE + chan_size + info_size + 12 + EDIT_COUNT_SIZE
+}
+
+// Writes a header for `data_size` bytes of little-endian samples.
+// If the size isn't known yet, the data chunk is marked as extending to the end of the file.
+pub(super) fn write_header<W: Write>(
+    spec: WavSpec<'_>,
+    data_size: Option<u64>,
+    sink: &mut W,
+) -> Result<(), IoError> {
+    let block_align = u32::from(spec.channels * spec.bits_per_sample.div_ceil(8));
+    let format_flags = match spec.format {
+        Format::Integer => FLAG_IS_LITTLE_ENDIAN,
+        Format::Float => FLAG_IS_FLOAT | FLAG_IS_LITTLE_ENDIAN,
+    };
+
+    // file type, version 1, and no flags
+    sink.write_all(b"caff")?;
+    sink.write_all(&1u16.to_be_bytes())?;
+    sink.write_all(&0u16.to_be_bytes())?;
+
+    // sample rate, format ID, format flags, bytes per packet, frames per packet, channels, and bits per channel
+    write_chunk_header(*b"desc", DESC_CHUNK_SIZE, sink)?;
+    sink.write_all(&f64::from(spec.sample_rate).to_be_bytes())?;
+    sink.write_all(b"lpcm")?;
+    sink.write_all(&format_flags.to_be_bytes())?;
+    sink.write_all(&block_align.to_be_bytes())?;
+    sink.write_all(&1u32.to_be_bytes())?;
+    sink.write_all(&u32::from(spec.channels).to_be_bytes())?;
+    sink.write_all(&u32::from(spec.bits_per_sample).to_be_bytes())?;
+
+    if has_chan(spec) {
+        // channels without a common speaker arrangement are stored in order, without speaker assignments
+        let (layout_tag, bitmap) = match channel_mask(spec.channels) {
+            0 => (LAYOUT_TAG_DISCRETE_IN_ORDER | u32::from(spec.channels), 0),
+            mask => (LAYOUT_TAG_USE_CHANNEL_BITMAP, mask),
+        };
+
+        write_chunk_header(*b"chan", CHAN_CHUNK_SIZE, sink)?;
+        sink.write_all(&layout_tag.to_be_bytes())?;
+        sink.write_all(&bitmap.to_be_bytes())?;
+        sink.write_all(&0u32.to_be_bytes())?;
+    }
+
+    if let (Some(name), Some(size)) = (spec.info.name(), info_size(spec)) {
+        write_chunk_header(*b"info", size, sink)?;
+        sink.write_all(&1u32.to_be_bytes())?;
+        sink.write_all(b"title\0")?;
+        sink.write_all(name.as_bytes())?;
+        sink.write_all(&[0])?;
+    }
+
+    sink.write_all(b"data")?;
+    match data_size {
+        Some(size) => sink.write_all(&(EDIT_COUNT_SIZE + size).to_be_bytes())?,
+        None => sink.write_all(&UNKNOWN_DATA_SIZE.to_be_bytes())?,
+    }
+    sink.write_all(&0u32.to_be_bytes())
+}
+
+// Chunk sizes are 64-bit signed integers, which don't include the chunk header.
+fn write_chunk_header<W: Write>(id: [u8; 4], size: u64, sink: &mut W) -> Result<(), IoError> {
+    sink.write_all(&id)?;
+    sink.write_all(&size.to_be_bytes())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{header_size, write_header};
+    use crate::encode::wav::WavSpec;
+
+    #[test]
+    fn write_header_for_streaming_sink() {
+        let spec = WavSpec::pcm16(6, 48000);
+
+        let mut output = Vec::new();
+        write_header(spec, None, &mut output).unwrap();
+
+        assert_eq!(output.len() as u64, header_size(spec));
+        assert_eq!(output[0..8], *b"caff\x00\x01\x00\x00");
+        assert_eq!(output[8..20], *b"desc\0\0\0\0\0\0\0\x20");
+        assert_eq!(output[20..28], 48000f64.to_be_bytes());
+        assert_eq!(output[28..32], *b"lpcm");
+        assert_eq!(output[32..36], 2u32.to_be_bytes());
+        assert_eq!(output[36..40], 12u32.to_be_bytes());
+        // 5.1 channel bitmap
+        assert_eq!(output[52..56], *b"chan");
+        assert_eq!(output[64..68], (1u32 << 16).to_be_bytes());
+        assert_eq!(output[68..72], 0x3Fu32.to_be_bytes());
+        // the data chunk extends to the end of the file
+        assert_eq!(output[76..80], *b"data");
+        assert_eq!(output[80..88], (-1i64).to_be_bytes());
+    }
+}
@@ -0,0 +1,295 @@
+use crate::{
+    header::{AudioFormat, StreamInfo},
+    read::{ReadError, Reader},
+};
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io::{Error as IoError, Read, Write},
+};
+
+// FSB CELT frame information taken from vgmstream:
+// [1]: https://github.com/vgmstream/vgmstream/blob/master/src/coding/celt_fsb_decoder.c
+
+// Each frame starts with a sync word and the size of the CELT packet that follows it.
+const FRAME_SYNC: [u8; 4] = [0x17, 0xC3, 0x0D, 0xF3];
+const FRAME_HEADER_SIZE: usize = 0x08;
+
+/// Encodes a CELT stream by removing the FSB framing from each CELT packet.
+///
+/// The output is a sequence of raw CELT 0.11 packets, each preceded by its size as a little-endian `u32`.
+/// Streams with more than 2 channels are made up of stereo layers, which are stored as 1 frame per layer in turn.
+/// This order is kept in the output, so the packets of each layer can be recovered by their position.
+pub(super) fn encode<R: Read, W: Write>(
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+    mut sink: W,
+) -> Result<W, CeltError> {
+    let layers = usize::from(info.channels.get()).div_ceil(2);
+    let stream_size = info.size as usize;
+    let start_pos = source.position();
+    let mut frames = 0;
+
+    while source.bytes_since(start_pos) < stream_size {
+        let remaining = stream_size - source.bytes_since(start_pos);
+        let packet = read_frame(source, remaining)?;
+
+        sink.write_all(&u32::try_from(packet.len()).unwrap_or(u32::MAX).to_le_bytes())
+            .and_then(|()| sink.write_all(&packet))
+            .map_err(CeltError::from_io(CeltErrorKind::EncodePacket))?;
+
+        frames += 1;
+    }
+
+    // every layer should have a frame for each point in time
+    if frames % layers != 0 {
+        return Err(CeltError::new(CeltErrorKind::IncompleteLayers));
+    }
+
+    sink.flush()
+        .map(|()| sink)
+        .map_err(CeltError::from_io(CeltErrorKind::FinishStream))
+}
+
+// Reads the CELT packet of the next frame, which has to fit within the `remaining` bytes of the stream.
+fn read_frame<R: Read>(source: &mut Reader<R>, remaining: usize) -> Result<Vec<u8>, CeltError> {
+    let header = source
+        .take_const::<FRAME_HEADER_SIZE>()
+        .map_err(CeltError::from_read(CeltErrorKind::DecodeFrame))?;
+
+    if header[0x00..0x04] != FRAME_SYNC {
+        return Err(CeltError::new(CeltErrorKind::InvalidSync));
+    }
+
+    // the packet size isn't trusted for allocating the packet until it's checked against the stream size
+    let packet_size = u32::from_le_bytes(header[0x04..0x08].try_into().unwrap()) as usize;
+    if packet_size > remaining.saturating_sub(FRAME_HEADER_SIZE) {
+        return Err(CeltError::new(CeltErrorKind::PacketTooLarge));
+    }
+
+    source
+        .take(packet_size)
+        .map_err(CeltError::from_read(CeltErrorKind::DecodeFrame))
+}
+
+// Returns the version of CELT that streams of the given format were encoded with.
+// FSB5 sound banks are always encoded with CELT 0.11.
+pub(crate) fn celt_version(format: AudioFormat) -> Option<CeltVersion> {
+    (format == AudioFormat::Celt).then_some(CeltVersion::V0_11)
+}
+
+/// The version of the CELT codec that a CELT stream was encoded with.
+///
+/// FMOD shipped incompatible versions of CELT, so a decoder has to be built for the same version as the stream.
+/// Streams in FSB5 sound banks are always encoded with CELT 0.11; the older version used by FSB4 sound banks
+/// isn't supported, since CELT streams in FSB4 sound banks aren't parsed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CeltVersion {
+    /// CELT 0.11.0, used by FSB5 sound banks.
+    V0_11,
+}
+
+impl Display for CeltVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Self::V0_11 => "0.11",
+        })
+    }
+}
+
+/// Represents an error that can occur when encoding a CELT stream.
+///
+/// See [`CeltErrorKind`] for the different kinds of errors that can occur.
+#[derive(Debug)]
+pub struct CeltError {
+    kind: CeltErrorKind,
+    source: Option<CeltErrorSource>,
+}
+
+/// A variant of a [`CeltError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CeltErrorKind {
+    /// Failed to read a frame from the stream data.
+    DecodeFrame,
+    /// A frame did not start with the expected sync word.
+    InvalidSync,
+    /// The packet size in a frame header was larger than the rest of the stream data.
+    PacketTooLarge,
+    /// The stream ended before every layer had a frame for the final point in time.
+    IncompleteLayers,
+    /// Failed to encode a CELT packet to the writer.
+    EncodePacket,
+    /// Failed to flush the writer after encoding the entire stream.
+    FinishStream,
+    /// Decoding CELT streams to PCM samples was requested, but no CELT decoder is available.
+    /// CELT streams can only be written as raw CELT packets, which need a decoder for the given version.
+    DecodeUnsupported {
+        /// The version of CELT that the stream was encoded with.
+        version: CeltVersion,
+    },
+}
+
+#[derive(Debug)]
+enum CeltErrorSource {
+    Io(IoError),
+    Read(ReadError),
+}
+
+impl CeltError {
+    pub(super) fn new(kind: CeltErrorKind) -> Self {
+        Self { kind, source: None }
+    }
+
+    fn from_io(kind: CeltErrorKind) -> impl FnOnce(IoError) -> Self {
+        move |source| Self {
+            kind,
+            source: Some(CeltErrorSource::Io(source)),
+        }
+    }
+
+    fn from_read(kind: CeltErrorKind) -> impl FnOnce(ReadError) -> Self {
+        move |source| Self {
+            kind,
+            source: Some(CeltErrorSource::Read(source)),
+        }
+    }
+
+    /// Returns the [`CeltErrorKind`] associated with this error.
+    #[must_use]
+    pub fn kind(&self) -> CeltErrorKind {
+        self.kind
+    }
+}
+
+impl Display for CeltError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.kind.fmt(f)
+    }
+}
+
+impl Error for CeltError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.source {
+            Some(source) => match source {
+                CeltErrorSource::Io(e) => Some(e),
+                CeltErrorSource::Read(e) => Some(e),
+            },
+            None => None,
+        }
+    }
+}
+
+impl Display for CeltErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Self::DecodeFrame => "failed to read frame from CELT stream",
+            Self::InvalidSync => "CELT frame did not start with a valid sync word",
+            Self::PacketTooLarge => "CELT packet size exceeds the remaining stream data",
+            Self::IncompleteLayers => "CELT stream ended with an incomplete set of layer frames",
+            Self::EncodePacket => "failed to encode CELT packet",
+            Self::FinishStream => "failed to finalize writing CELT stream data",
+            Self::DecodeUnsupported { version } => {
+                return f
+                    .write_fmt(format_args!("decoding CELT {version} streams is not supported"));
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{celt_version, encode, CeltErrorKind, CeltVersion, FRAME_SYNC};
+    use crate::{
+        header::{AudioFormat, StreamInfo},
+        read::{Reader, ShortReader},
+    };
+
+    fn frame(packet: &[u8]) -> Vec<u8> {
+        let mut frame = FRAME_SYNC.to_vec();
+        frame.extend_from_slice(&u32::try_from(packet.len()).unwrap().to_le_bytes());
+        frame.extend_from_slice(packet);
+        frame
+    }
+
+    #[test]
+    fn remove_frame_headers() {
+        let mut data = frame(&[1, 2, 3]);
+        data.extend(frame(&[4, 5]));
+        // data after the stream is ignored
+        data.extend(frame(&[6]));
+
+        let info = StreamInfo::dummy(2, 1024, 0x08 * 2 + 5);
+        let output = encode(&info, &mut Reader::new(data.as_slice()), Vec::new()).unwrap();
+
+        assert_eq!(output, [3, 0, 0, 0, 1, 2, 3, 2, 0, 0, 0, 4, 5]);
+    }
+
+    #[test]
+    fn keep_layer_order() {
+        let mut data = Vec::new();
+        for packet in [[0x00], [0x10], [0x01], [0x11]] {
+            data.extend(frame(&packet));
+        }
+
+        // 4 channels are stored as 2 stereo layers
+        let info = StreamInfo::dummy(4, 1024, 0x09 * 4);
+        let output = encode(&info, &mut Reader::new(data.as_slice()), Vec::new()).unwrap();
+
+        let packets: Vec<_> = output.chunks_exact(5).map(|chunk| chunk[4]).collect();
+        assert_eq!(packets, [0x00, 0x10, 0x01, 0x11]);
+
+        let info = StreamInfo::dummy(4, 1024, 0x09 * 3);
+        assert!(encode(&info, &mut Reader::new(data.as_slice()), Vec::new())
+            .is_err_and(|e| e.kind() == CeltErrorKind::IncompleteLayers));
+    }
+
+    #[test]
+    fn reject_invalid_sync() {
+        let mut data = frame(&[1, 2, 3]);
+        data[0] = 0;
+
+        let info = StreamInfo::dummy(1, 1024, 0x08 + 3);
+        assert!(encode(&info, &mut Reader::new(data.as_slice()), Vec::new())
+            .is_err_and(|e| e.kind() == CeltErrorKind::InvalidSync));
+    }
+
+    #[test]
+    fn reject_packets_larger_than_stream() {
+        let mut data = frame(&[1, 2, 3]);
+        data.extend(frame(&[4, 5]));
+
+        let info = StreamInfo::dummy(1, 1024, 0x08 * 2 + 5);
+        let mut corrupt = data.clone();
+        corrupt[0x04..0x08].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(encode(&info, &mut Reader::new(corrupt.as_slice()), Vec::new())
+            .is_err_and(|e| e.kind() == CeltErrorKind::PacketTooLarge));
+
+        // the final packet can't extend past the end of the stream, even if more data follows
+        let info = StreamInfo::dummy(1, 1024, 0x08 * 2 + 4);
+        assert!(encode(&info, &mut Reader::new(data.as_slice()), Vec::new())
+            .is_err_and(|e| e.kind() == CeltErrorKind::PacketTooLarge));
+    }
+
+    #[test]
+    fn version_of_celt_streams() {
+        assert_eq!(celt_version(AudioFormat::Celt), Some(CeltVersion::V0_11));
+        assert_eq!(celt_version(AudioFormat::Opus), None);
+        assert_eq!(CeltVersion::V0_11.to_string(), "0.11");
+    }
+
+    #[test]
+    fn encode_from_short_reads() {
+        let mut data = frame(&[1, 2, 3]);
+        data.extend(frame(&[4, 5, 6, 7, 8, 9, 10, 11, 12]));
+
+        let info = StreamInfo::dummy(1, 1024, 0x08 * 2 + 12);
+        let output = encode(&info, &mut Reader::new(ShortReader::new(&data)), Vec::new()).unwrap();
+
+        assert_eq!(
+            output,
+            [3, 0, 0, 0, 1, 2, 3, 9, 0, 0, 0, 4, 5, 6, 7, 8, 9, 10, 11, 12]
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use super::{writes_pcm, EncodeOptions, OutputMode, PcmOutput, WavContainer};
+use crate::header::AudioFormat;
+
+/// Represents the file formats that streams are written as.
+///
+/// The container of a stream depends on its [`AudioFormat`] and the [`EncodeOptions`] it's encoded with.
+/// See [`AudioFormat::container`] for more information.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Container {
+    /// A WAVE file holding PCM samples.
+    /// Files too large for the 32-bit sizes of RIFF files are written as RF64 files, which use the same extension.
+    Wav,
+    /// A Wave64 file holding PCM samples.
+    Wave64,
+    /// An AIFF file holding PCM samples.
+    Aiff,
+    /// A Core Audio Format (CAF) file holding PCM samples.
+    Caf,
+    /// A FLAC file. Requires the `flac` feature.
+    #[cfg(feature = "flac")]
+    Flac,
+    /// An MP3 file holding the frames of an MPEG stream.
+    Mp3,
+    /// An Ogg file holding the packets of a Vorbis or Opus stream.
+    Ogg,
+    /// A `VAGp` file holding the data of a VAG stream.
+    Vag,
+    /// A RIFF file holding the data of an XMA (XMA2), ATRAC9, or xWMA stream.
+    Riff,
+    /// Stream data without a container: either the packets of a CELT stream,
+    /// or stream data copied as it is stored in the sound bank (see [`OutputMode::Raw`]).
+    Raw,
+}
+
+impl AudioFormat {
+    /// Returns the [`Container`] that streams of this format are written as, when encoded with `options`.
+    #[must_use]
+    pub fn container(self, options: EncodeOptions<'_>) -> Container {
+        if options.output_mode == OutputMode::Raw {
+            return Container::Raw;
+        }
+
+        if writes_pcm(self, options) {
+            return match options.pcm_output {
+                PcmOutput::Wav if options.wav_container == WavContainer::Wave64 => {
+                    Container::Wave64
+                }
+                PcmOutput::Wav => Container::Wav,
+                PcmOutput::Aiff => Container::Aiff,
+                PcmOutput::Caf => Container::Caf,
+                #[cfg(feature = "flac")]
+                PcmOutput::Flac => Container::Flac,
+            };
+        }
+
+        match self {
+            Self::Mpeg => Container::Mp3,
+            Self::Vorbis | Self::Opus => Container::Ogg,
+            Self::Vag => Container::Vag,
+            Self::Xma | Self::Atrac9 | Self::Xwma => Container::Riff,
+            Self::Celt | Self::Unknown(_) => Container::Raw,
+            // streams of these formats are always written as PCM samples
+            Self::Pcm8
+            | Self::Pcm16
+            | Self::Pcm24
+            | Self::Pcm32
+            | Self::PcmFloat
+            | Self::GcAdpcm
+            | Self::ImaAdpcm
+            | Self::HeVag
+            | Self::FAdpcm => Container::Wav,
+        }
+    }
+
+    /// Returns the file extension (without the leading `.`) of files that streams of this format are written as,
+    /// when encoded with `options`.
+    ///
+    /// Stream data copied without a container (see [`OutputMode::Raw`]) gets an extension named after its format,
+    /// such as `fadpcm` for FADPCM streams.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fsbex::AudioFormat;
+    /// use fsbex::encode::{EncodeOptions, OutputMode, PcmOutput};
+    ///
+    /// let options = EncodeOptions::new();
+    /// assert_eq!(AudioFormat::Mpeg.extension(options), "mp3");
+    /// assert_eq!(AudioFormat::Pcm16.extension(options.pcm_output(PcmOutput::Aiff)), "aiff");
+    /// assert_eq!(AudioFormat::FAdpcm.extension(options.output_mode(OutputMode::Raw)), "fadpcm");
+    /// ```
+    #[must_use]
+    pub fn extension(self, options: EncodeOptions<'_>) -> &'static str {
+        match self.container(options) {
+            Container::Wav => "wav",
+            Container::Wave64 => "w64",
+            Container::Aiff => "aiff",
+            Container::Caf => "caf",
+            #[cfg(feature = "flac")]
+            Container::Flac => "flac",
+            Container::Mp3 => "mp3",
+            Container::Ogg if self == Self::Opus => "opus",
+            Container::Ogg => "ogg",
+            Container::Vag => "vag",
+            Container::Riff | Container::Raw => self.raw_extension(),
+        }
+    }
+
+    /// Returns the MIME type of files that streams of this format are written as, when encoded with `options`.
+    /// Files without a registered MIME type, including stream data without a container,
+    /// have the type `application/octet-stream`.
+    #[must_use]
+    pub fn mime_type(self, options: EncodeOptions<'_>) -> &'static str {
+        match self.container(options) {
+            Container::Wav => "audio/wav",
+            Container::Aiff => "audio/aiff",
+            Container::Caf => "audio/x-caf",
+            #[cfg(feature = "flac")]
+            Container::Flac => "audio/flac",
+            Container::Mp3 => "audio/mpeg",
+            Container::Ogg => "audio/ogg",
+            Container::Wave64 | Container::Vag | Container::Riff | Container::Raw => {
+                "application/octet-stream"
+            }
+        }
+    }
+
+    // Extensions named after formats, for stream data without a container.
+    // Formats written to RIFF files use the extensions common for those files, which are also used for their raw data.
+    fn raw_extension(self) -> &'static str {
+        match self {
+            Self::Pcm8 | Self::Pcm16 | Self::Pcm24 | Self::Pcm32 | Self::PcmFloat => "pcm",
+            Self::GcAdpcm => "gcadpcm",
+            Self::ImaAdpcm => "imaadpcm",
+            Self::Vag => "vag",
+            Self::HeVag => "hevag",
+            Self::Xma => "xma",
+            Self::Mpeg => "mp3",
+            Self::Celt => "celt",
+            Self::Atrac9 => "at9",
+            Self::Xwma => "xwma",
+            Self::Vorbis => "vorbis",
+            Self::FAdpcm => "fadpcm",
+            Self::Opus => "opus",
+            Self::Unknown(_) => "bin",
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Container;
+    use crate::{
+        encode::{EncodeOptions, OutputMode, PcmOutput, WavContainer},
+        header::AudioFormat,
+    };
+
+    #[test]
+    fn choose_container_from_options() {
+        let options = EncodeOptions::new();
+
+        assert_eq!(AudioFormat::Pcm16.container(options), Container::Wav);
+        assert_eq!(AudioFormat::Xma.container(options), Container::Riff);
+        assert_eq!(AudioFormat::Celt.container(options), Container::Raw);
+        assert_eq!(
+            AudioFormat::FAdpcm.container(options.wav_container(WavContainer::Wave64)),
+            Container::Wave64
+        );
+        assert_eq!(
+            AudioFormat::GcAdpcm.container(options.pcm_output(PcmOutput::Caf)),
+            Container::Caf
+        );
+        // PCM output formats don't apply to streams that aren't written as PCM samples
+        assert_eq!(
+            AudioFormat::Opus.container(options.pcm_output(PcmOutput::Aiff)),
+            Container::Ogg
+        );
+        assert_eq!(
+            AudioFormat::Pcm16.container(options.output_mode(OutputMode::Raw)),
+            Container::Raw
+        );
+    }
+
+    #[test]
+    fn choose_extension_and_mime_type() {
+        let options = EncodeOptions::new();
+        let raw = options.output_mode(OutputMode::Raw);
+
+        for (format, extension, mime_type, raw_extension) in [
+            (AudioFormat::Pcm8, "wav", "audio/wav", "pcm"),
+            (AudioFormat::ImaAdpcm, "wav", "audio/wav", "imaadpcm"),
+            (AudioFormat::Mpeg, "mp3", "audio/mpeg", "mp3"),
+            (AudioFormat::Opus, "opus", "audio/ogg", "opus"),
+            (AudioFormat::Atrac9, "at9", "application/octet-stream", "at9"),
+            (AudioFormat::Vag, "vag", "application/octet-stream", "vag"),
+            (AudioFormat::FAdpcm, "wav", "audio/wav", "fadpcm"),
+        ] {
+            assert_eq!(format.extension(options), extension, "{format}");
+            assert_eq!(format.mime_type(options), mime_type, "{format}");
+            assert_eq!(format.extension(raw), raw_extension, "{format}");
+            assert_eq!(format.mime_type(raw), "application/octet-stream", "{format}");
+        }
+
+        let aiff = options.pcm_output(PcmOutput::Aiff);
+        assert_eq!(AudioFormat::Pcm24.extension(aiff), "aiff");
+        assert_eq!(AudioFormat::Pcm24.mime_type(aiff), "audio/aiff");
+    }
+
+    #[cfg(feature = "decode-vorbis")]
+    #[test]
+    fn choose_extension_for_decoded_vorbis() {
+        let options = EncodeOptions::new();
+
+        assert_eq!(AudioFormat::Vorbis.extension(options), "ogg");
+        assert_eq!(AudioFormat::Vorbis.extension(options.decode_vorbis(true)), "wav");
+    }
+}
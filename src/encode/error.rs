@@ -1,6 +1,21 @@
+use super::atrac9::Atrac9Error;
+use super::celt::CeltError;
+use super::fadpcm::FadpcmError;
+#[cfg(feature = "flac")]
+use super::flac::FlacError;
+use super::gc_adpcm::GcAdpcmError;
+use super::hevag::HevagError;
+use super::ima_adpcm::ImaAdpcmError;
 use super::mpeg::MpegError;
+use super::opus::OpusError;
 use super::pcm::PcmError;
+use super::raw::RawError;
+use super::vag::VagError;
+#[cfg(feature = "decode-vorbis")]
 use super::vorbis::VorbisError;
+use super::xma::XmaError;
+use super::xwma::XwmaError;
+use super::PcmOutput;
 use crate::header::AudioFormat;
 use std::{
     error::Error,
@@ -16,15 +31,69 @@ pub enum EncodeError {
         /// The audio format of streams in the sound bank.
         format: AudioFormat,
     },
+    /// The stream can't be written in the file format chosen with [`EncodeOptions::pcm_output`],
+    /// because it isn't written as PCM samples, or because the file format can't hold its samples.
+    ///
+    /// This is only returned when [`EncodeOptions::strict`] is enabled, except for FLAC files,
+    /// which are always rejected for these streams.
+    ///
+    /// [`EncodeOptions::pcm_output`]: super::EncodeOptions::pcm_output
+    /// [`EncodeOptions::strict`]: super::EncodeOptions::strict
+    UnsupportedOutput {
+        /// The audio format of the stream.
+        format: AudioFormat,
+        /// The file format chosen for PCM streams.
+        output: PcmOutput,
+    },
     /// Failed to encode a PCM stream.
     /// See [`PcmError`] for more information.
     Pcm(PcmError),
     /// Failed to encode a Vorbis stream.
     /// See [`VorbisError`] for more information.
+    #[cfg(feature = "decode-vorbis")]
     Vorbis(VorbisError),
     /// Failed to encode an MPEG stream.
     /// See [`MpegError`] for more information.
     Mpeg(MpegError),
+    /// Failed to encode a FADPCM stream.
+    /// See [`FadpcmError`] for more information.
+    Fadpcm(FadpcmError),
+    /// Failed to encode an IMA ADPCM stream.
+    /// See [`ImaAdpcmError`] for more information.
+    ImaAdpcm(ImaAdpcmError),
+    /// Failed to encode a GC ADPCM stream.
+    /// See [`GcAdpcmError`] for more information.
+    GcAdpcm(GcAdpcmError),
+    /// Failed to encode a XMA stream.
+    /// See [`XmaError`] for more information.
+    Xma(XmaError),
+    /// Failed to encode an ATRAC9 stream.
+    /// See [`Atrac9Error`] for more information.
+    Atrac9(Atrac9Error),
+    /// Failed to encode a CELT stream.
+    /// See [`CeltError`] for more information.
+    Celt(CeltError),
+    /// Failed to encode an Opus stream.
+    /// See [`OpusError`] for more information.
+    Opus(OpusError),
+    /// Failed to encode a HEVAG stream.
+    /// See [`HevagError`] for more information.
+    HeVag(HevagError),
+    /// Failed to encode a a VAG stream.
+    /// See [`VagError`] for more information.
+    Vag(VagError),
+    /// Failed to encode a an xWMA stream.
+    /// See [`XwmaError`] for more information.
+    Xwma(XwmaError),
+    /// Failed to write a stream as a FLAC file.
+    /// See [`FlacError`] for more information.
+    #[cfg(feature = "flac")]
+    Flac(FlacError),
+    /// Failed to copy the stream data of a stream, when writing streams with [`OutputMode::Raw`].
+    /// See [`RawError`] for more information.
+    ///
+    /// [`OutputMode::Raw`]: super::OutputMode::Raw
+    Raw(RawError),
 }
 
 impl From<PcmError> for EncodeError {
@@ -33,6 +102,7 @@ impl From<PcmError> for EncodeError {
     }
 }
 
+#[cfg(feature = "decode-vorbis")]
 impl From<VorbisError> for EncodeError {
     fn from(value: VorbisError) -> Self {
         Self::Vorbis(value)
@@ -45,15 +115,105 @@ impl From<MpegError> for EncodeError {
     }
 }
 
+impl From<FadpcmError> for EncodeError {
+    fn from(value: FadpcmError) -> Self {
+        Self::Fadpcm(value)
+    }
+}
+
+impl From<ImaAdpcmError> for EncodeError {
+    fn from(value: ImaAdpcmError) -> Self {
+        Self::ImaAdpcm(value)
+    }
+}
+
+impl From<GcAdpcmError> for EncodeError {
+    fn from(value: GcAdpcmError) -> Self {
+        Self::GcAdpcm(value)
+    }
+}
+
+impl From<XmaError> for EncodeError {
+    fn from(value: XmaError) -> Self {
+        Self::Xma(value)
+    }
+}
+
+impl From<Atrac9Error> for EncodeError {
+    fn from(value: Atrac9Error) -> Self {
+        Self::Atrac9(value)
+    }
+}
+
+impl From<CeltError> for EncodeError {
+    fn from(value: CeltError) -> Self {
+        Self::Celt(value)
+    }
+}
+
+impl From<OpusError> for EncodeError {
+    fn from(value: OpusError) -> Self {
+        Self::Opus(value)
+    }
+}
+
+impl From<HevagError> for EncodeError {
+    fn from(value: HevagError) -> Self {
+        Self::HeVag(value)
+    }
+}
+
+impl From<VagError> for EncodeError {
+    fn from(value: VagError) -> Self {
+        Self::Vag(value)
+    }
+}
+
+impl From<XwmaError> for EncodeError {
+    fn from(value: XwmaError) -> Self {
+        Self::Xwma(value)
+    }
+}
+
+#[cfg(feature = "flac")]
+impl From<FlacError> for EncodeError {
+    fn from(value: FlacError) -> Self {
+        Self::Flac(value)
+    }
+}
+
+impl From<RawError> for EncodeError {
+    fn from(value: RawError) -> Self {
+        Self::Raw(value)
+    }
+}
+
 impl Display for EncodeError {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         match self {
             Self::UnsupportedFormat { format } => {
                 f.write_fmt(format_args!("encoding for {format} streams is not supported"))
             }
+            Self::UnsupportedOutput { format, output } => {
+                f.write_fmt(format_args!("{format} streams can't be written as {output} files"))
+            }
             Self::Pcm(_) => f.write_str("failed to encode PCM stream"),
+            #[cfg(feature = "decode-vorbis")]
             Self::Vorbis(_) => f.write_str("failed to encode Vorbis stream"),
             Self::Mpeg(_) => f.write_str("failed to encode MPEG stream"),
+            Self::Fadpcm(_) => f.write_str("failed to encode FADPCM stream"),
+            Self::ImaAdpcm(_) => f.write_str("failed to encode IMA ADPCM stream"),
+            Self::GcAdpcm(_) => f.write_str("failed to encode GC ADPCM stream"),
+            Self::Xma(_) => f.write_str("failed to encode XMA stream"),
+            Self::Atrac9(_) => f.write_str("failed to encode ATRAC9 stream"),
+            Self::Celt(_) => f.write_str("failed to encode CELT stream"),
+            Self::Opus(_) => f.write_str("failed to encode Opus stream"),
+            Self::HeVag(_) => f.write_str("failed to encode HEVAG stream"),
+            Self::Vag(_) => f.write_str("failed to encode VAG stream"),
+            Self::Xwma(_) => f.write_str("failed to encode an xWMA stream"),
+            #[cfg(feature = "flac")]
+            Self::Flac(_) => f.write_str("failed to write FLAC file"),
+            Self::Raw(_) => f.write_str("failed to copy stream data"),
         }
     }
 }
@@ -61,10 +221,24 @@ impl Display for EncodeError {
 impl Error for EncodeError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
-            Self::UnsupportedFormat { format: _ } => None,
+            Self::UnsupportedFormat { format: _ } | Self::UnsupportedOutput { .. } => None,
             Self::Pcm(e) => Some(e),
+            #[cfg(feature = "decode-vorbis")]
             Self::Vorbis(e) => Some(e),
             Self::Mpeg(e) => Some(e),
+            Self::Fadpcm(e) => Some(e),
+            Self::ImaAdpcm(e) => Some(e),
+            Self::GcAdpcm(e) => Some(e),
+            Self::Xma(e) => Some(e),
+            Self::Atrac9(e) => Some(e),
+            Self::Celt(e) => Some(e),
+            Self::Opus(e) => Some(e),
+            Self::HeVag(e) => Some(e),
+            Self::Vag(e) => Some(e),
+            Self::Xwma(e) => Some(e),
+            #[cfg(feature = "flac")]
+            Self::Flac(e) => Some(e),
+            Self::Raw(e) => Some(e),
         }
     }
 }
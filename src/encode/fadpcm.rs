@@ -0,0 +1,366 @@
+use super::{wav::WavSpec, EncodeOptions};
+use crate::{
+    header::StreamInfo,
+    read::{ReadError, Reader},
+};
+use std::{
+    cmp::min,
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io::{Error as IoError, Read, Write},
+};
+
+// FADPCM decoding logic adapted from vgmstream:
+// [1]: https://github.com/vgmstream/vgmstream/blob/master/src/coding/fadpcm_decoder.c
+
+// Each frame holds the samples of one channel; frames of different channels are interleaved.
+// A frame starts with a 12-byte header (coefficient indices, shifts, and 2 history samples),
+// followed by 8 groups of 16 bytes. Each group contains 32 samples stored as 4-bit nibbles.
+const FRAME_SIZE: usize = 0x8C;
+const FRAME_HEADER_SIZE: usize = 0x0C;
+const SAMPLES_PER_FRAME: usize = (FRAME_SIZE - FRAME_HEADER_SIZE) * 2;
+
+const COEFFICIENTS: [(i32, i32); 7] = [
+    (0, 0),
+    (60, 0),
+    (122, 60),
+    (115, 52),
+    (98, 55),
+    (0, 0),
+    (0, 0),
+];
+
+pub(super) fn encode<R: Read, W: Write>(
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+    mut sink: W,
+    options: EncodeOptions<'_>,
+) -> Result<W, FadpcmError> {
+    let channels = info.channels.get() as usize;
+    let mut decoder = BlockDecoder::new(info, source);
+
+    let data_size = (decoder.samples_left * channels * 2)
+        .try_into()
+        .map_err(|_| FadpcmError::new(FadpcmErrorKind::StreamTooLarge))?;
+
+    let spec = WavSpec::pcm16(
+        channels.try_into().expect("number of channels is less than u16::MAX"),
+        info.sample_rate.get(),
+    )
+    .with_options(info, options, data_size);
+
+    // write the WAVE file header
+    spec.write_header(data_size, &mut sink)
+        .map_err(FadpcmError::from_io(FadpcmErrorKind::CreateHeader))?;
+
+    let mut samples = Vec::new();
+
+    while decoder.next_block(source, &mut samples)? {
+        for &sample in &samples {
+            sink.write_all(&spec.i16_bytes(sample))
+                .map_err(FadpcmError::from_io(FadpcmErrorKind::EncodeSample))?;
+        }
+    }
+
+    spec.write_trailer(data_size, &mut sink)
+        .and_then(|()| sink.flush())
+        .map(|()| sink)
+        .map_err(FadpcmError::from_io(FadpcmErrorKind::FinishStream))
+}
+
+// Decodes the stream one block of frames at a time, producing interleaved samples.
+pub(super) struct BlockDecoder {
+    channels: usize,
+    stream_size: usize,
+    start_pos: u64,
+    pub(super) samples_left: usize,
+    decoded: Vec<[i16; SAMPLES_PER_FRAME]>,
+}
+
+impl BlockDecoder {
+    pub(super) fn new<R: Read>(info: &StreamInfo, source: &Reader<R>) -> Self {
+        let channels = info.channels.get() as usize;
+        let stream_size = info.size as usize;
+
+        // The declared sample count can be smaller than the amount of samples stored in the stream data,
+        // since the final frame is padded. It shouldn't be larger, but the stream data wins if it is.
+        let num_samples = min(
+            info.num_samples.get() as usize,
+            stream_size.div_ceil(FRAME_SIZE * channels) * SAMPLES_PER_FRAME,
+        );
+
+        Self {
+            channels,
+            stream_size,
+            start_pos: source.position(),
+            samples_left: num_samples,
+            decoded: vec![[0; SAMPLES_PER_FRAME]; channels],
+        }
+    }
+
+    // Replaces the contents of `samples` with the samples of the next block of frames,
+    // returning false once every sample has been decoded.
+    pub(super) fn next_block<R: Read>(
+        &mut self,
+        source: &mut Reader<R>,
+        samples: &mut Vec<i16>,
+    ) -> Result<bool, FadpcmError> {
+        samples.clear();
+
+        if self.samples_left == 0 {
+            return Ok(false);
+        }
+
+        // the final block of frames can be cut short, so missing bytes are treated as 0
+        let block_size = FRAME_SIZE * self.channels;
+        let block_len = min(block_size, self.stream_size - source.bytes_since(self.start_pos));
+
+        let block = source
+            .take(block_len)
+            .map_err(FadpcmError::from_read(FadpcmErrorKind::DecodeFrame))?;
+
+        for (channel, decoded) in self.decoded.iter_mut().enumerate() {
+            let frame = block.get(channel * FRAME_SIZE..).unwrap_or_default();
+            let frame_len = min(frame.len(), FRAME_SIZE);
+
+            let mut buf = [0; FRAME_SIZE];
+            buf[..frame_len].copy_from_slice(&frame[..frame_len]);
+            *decoded = decode_frame(&buf);
+        }
+
+        let frame_samples = min(self.samples_left, SAMPLES_PER_FRAME);
+
+        for index in 0..frame_samples {
+            samples.extend(self.decoded.iter().map(|decoded| decoded[index]));
+        }
+
+        self.samples_left -= frame_samples;
+        Ok(true)
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn decode_frame(frame: &[u8; FRAME_SIZE]) -> [i16; SAMPLES_PER_FRAME] {
+    let coeff_indices = u32::from_le_bytes(frame[0x00..0x04].try_into().unwrap());
+    let shifts = u32::from_le_bytes(frame[0x04..0x08].try_into().unwrap());
+    let mut hist1 = i32::from(i16::from_le_bytes(frame[0x08..0x0A].try_into().unwrap()));
+    let mut hist2 = i32::from(i16::from_le_bytes(frame[0x0A..0x0C].try_into().unwrap()));
+
+    let mut samples = [0; SAMPLES_PER_FRAME];
+    let mut samples_iter = samples.iter_mut();
+
+    for (group_index, group) in frame[FRAME_HEADER_SIZE..].chunks_exact(0x10).enumerate() {
+        // indices past the end of the table wrap around (e.g. 7 is the same as 0, and 9 is the same as 2)
+        let (coeff1, coeff2) = COEFFICIENTS
+            [((coeff_indices >> (group_index * 4)) & 0x0F) as usize % COEFFICIENTS.len()];
+
+        // pre-adjusted for sign extension from 4 to 32 bits
+        let shift = 22 - ((shifts >> (group_index * 4)) & 0x0F);
+
+        for nibbles in group.chunks_exact(4) {
+            let nibbles = u32::from_le_bytes(nibbles.try_into().unwrap());
+
+            for nibble_index in 0..8 {
+                #[allow(clippy::cast_possible_wrap)]
+                let nibble = (((nibbles >> (nibble_index * 4)) << 28) as i32) >> shift;
+
+                let sample = ((nibble - hist2 * coeff2 + hist1 * coeff1) >> 6)
+                    .clamp(i16::MIN.into(), i16::MAX.into());
+
+                *samples_iter
+                    .next()
+                    .expect("frame contains exactly SAMPLES_PER_FRAME samples") = sample as i16;
+
+                hist2 = hist1;
+                hist1 = sample;
+            }
+        }
+    }
+
+    samples
+}
+
+/// Represents an error that can occur when encoding a FADPCM stream.
+///
+/// See [`FadpcmErrorKind`] for the different kinds of errors that can occur.
+#[derive(Debug)]
+pub struct FadpcmError {
+    kind: FadpcmErrorKind,
+    source: Option<FadpcmErrorSource>,
+}
+
+/// A variant of a [`FadpcmError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FadpcmErrorKind {
+    /// The decoded stream was too large to fit in a WAVE file.
+    StreamTooLarge,
+    /// Failed to write the file header due to an underlying I/O error.
+    CreateHeader,
+    /// Failed to read a block of frames from the stream data.
+    DecodeFrame,
+    /// Failed to encode a decoded audio sample to the writer.
+    EncodeSample,
+    /// Failed to flush the writer after encoding the entire stream.
+    FinishStream,
+}
+
+#[derive(Debug)]
+enum FadpcmErrorSource {
+    Io(IoError),
+    Read(ReadError),
+}
+
+impl FadpcmError {
+    fn new(kind: FadpcmErrorKind) -> Self {
+        Self { kind, source: None }
+    }
+
+    fn from_io(kind: FadpcmErrorKind) -> impl FnOnce(IoError) -> Self {
+        move |source| Self {
+            kind,
+            source: Some(FadpcmErrorSource::Io(source)),
+        }
+    }
+
+    fn from_read(kind: FadpcmErrorKind) -> impl FnOnce(ReadError) -> Self {
+        move |source| Self {
+            kind,
+            source: Some(FadpcmErrorSource::Read(source)),
+        }
+    }
+
+    /// Returns the [`FadpcmErrorKind`] associated with this error.
+    #[must_use]
+    pub fn kind(&self) -> FadpcmErrorKind {
+        self.kind
+    }
+}
+
+impl Display for FadpcmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.kind.fmt(f)
+    }
+}
+
+impl Error for FadpcmError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.source {
+            Some(source) => match source {
+                FadpcmErrorSource::Io(e) => Some(e),
+                FadpcmErrorSource::Read(e) => Some(e),
+            },
+            None => None,
+        }
+    }
+}
+
+impl Display for FadpcmErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Self::StreamTooLarge => "decoded FADPCM stream was too large to encode",
+            Self::CreateHeader => "failed to encode file header",
+            Self::DecodeFrame => "failed to read frames from FADPCM stream",
+            Self::EncodeSample => "failed to encode sample",
+            Self::FinishStream => "failed to finalize writing FADPCM stream data",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode_frame, encode, FRAME_SIZE, SAMPLES_PER_FRAME};
+    use crate::{encode::EncodeOptions, header::StreamInfo, read::Reader};
+
+    fn frame(
+        coeff_indices: u32,
+        shifts: u32,
+        hist1: i16,
+        hist2: i16,
+        nibbles: u8,
+    ) -> [u8; FRAME_SIZE] {
+        let mut frame = [nibbles; FRAME_SIZE];
+        frame[0x00..0x04].copy_from_slice(&coeff_indices.to_le_bytes());
+        frame[0x04..0x08].copy_from_slice(&shifts.to_le_bytes());
+        frame[0x08..0x0A].copy_from_slice(&hist1.to_le_bytes());
+        frame[0x0A..0x0C].copy_from_slice(&hist2.to_le_bytes());
+        frame
+    }
+
+    #[test]
+    fn decode_silent_frame() {
+        assert_eq!(decode_frame(&frame(0, 0, 0, 0, 0)), [0; SAMPLES_PER_FRAME]);
+    }
+
+    #[test]
+    fn decode_nibbles_without_prediction() {
+        // coefficient index 0 disables prediction, so samples only depend on nibbles and shifts
+        assert_eq!(decode_frame(&frame(0, 0, 0, 0, 0x11)), [1; SAMPLES_PER_FRAME]);
+        assert_eq!(decode_frame(&frame(0, 0, 0, 0, 0xFF)), [-1; SAMPLES_PER_FRAME]);
+        assert_eq!(
+            decode_frame(&frame(0, 0xCCCC_CCCC, 0, 0, 0x77)),
+            [28672; SAMPLES_PER_FRAME]
+        );
+        assert_eq!(
+            decode_frame(&frame(0, 0xCCCC_CCCC, 0, 0, 0x88)),
+            [-32768; SAMPLES_PER_FRAME]
+        );
+    }
+
+    #[test]
+    fn decode_nibbles_with_prediction() {
+        let samples = decode_frame(&frame(0x1111_1111, 0, 64, 0, 0));
+        assert_eq!(samples[..4], [60, 56, 52, 48]);
+
+        // index 8 wraps around to index 1
+        let samples = decode_frame(&frame(0x8888_8888, 0, 64, 0, 0));
+        assert_eq!(samples[..4], [60, 56, 52, 48]);
+
+        // index 7 wraps around to index 0, which ignores previous samples
+        let samples = decode_frame(&frame(0x7777_7777, 0, 64, 0, 0));
+        assert_eq!(samples[..4], [0; 4]);
+    }
+
+    #[test]
+    fn encode_interleaved_channels() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&frame(0, 0, 0, 0, 0x11));
+        data.extend_from_slice(&frame(0, 0, 0, 0, 0xFF));
+
+        let info = StreamInfo::dummy(2, 3, 2 * 0x8C);
+        let output = encode(
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(output.len(), 44 + 3 * 2 * 2);
+        assert_eq!(output[40..44], 12u32.to_le_bytes());
+        assert_eq!(output[44..], [1, 0, 255, 255, 1, 0, 255, 255, 1, 0, 255, 255]);
+    }
+
+    #[test]
+    fn encode_partial_final_block() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&frame(0, 0, 0, 0, 0x11));
+        data.extend_from_slice(&frame(0, 0, 0, 0, 0x11)[..0x10]);
+
+        let info = StreamInfo::dummy(1, 1000, 0x8C + 0x10);
+        let output = encode(
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::default(),
+        )
+        .unwrap();
+
+        // the sample count is capped to the amount of samples in the stream data
+        assert_eq!(output.len(), 44 + 2 * SAMPLES_PER_FRAME * 2);
+        // only 8 samples of the final frame were present in the stream data
+        let final_frame = &output[44 + 2 * SAMPLES_PER_FRAME..];
+        assert_eq!(final_frame[..16], [1, 0].repeat(8));
+        assert!(final_frame[16..].iter().all(|&b| b == 0));
+    }
+}
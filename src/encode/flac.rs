@@ -0,0 +1,400 @@
+use super::wav::SampleLoop;
+use crate::header::StreamInfo;
+use flacenc::{
+    bitsink::ByteSink,
+    component::{BitRepr, StreamInfo as FlacStreamInfo},
+    config::Encoder,
+    encode_fixed_size_frame,
+    error::{Verified, Verify, VerifyError},
+    source::{Fill, FrameBuf},
+};
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io::{Error as IoError, ErrorKind, Write},
+};
+
+// FLAC file layout taken from:
+// [1]: https://xiph.org/flac/format.html
+// [2]: https://www.xiph.org/vorbis/doc/v-comment.html (Vorbis comments)
+
+const STREAMINFO_BLOCK_TYPE: u8 = 0;
+const VORBIS_COMMENT_BLOCK_TYPE: u8 = 4;
+const LAST_BLOCK_FLAG: u8 = 0x80;
+
+// Sizes of metadata blocks are 24 bits wide.
+const MAX_BLOCK_SIZE: usize = (1 << 24) - 1;
+
+const VENDOR: &str = concat!("fsbex ", env!("CARGO_PKG_VERSION"));
+
+// Encodes little-endian PCM samples written to it as FLAC frames, which are written to `sink`.
+// Only one block of samples is held in memory at a time, so whole streams are never buffered.
+pub(super) struct FlacWriter<W> {
+    sink: W,
+    config: Verified<Encoder>,
+    stream_info: FlacStreamInfo,
+    frame: FrameBuf,
+    block: Vec<u8>,
+    block_len: usize,
+    bytes_per_sample: usize,
+    frame_number: usize,
+}
+
+impl<W: Write> FlacWriter<W> {
+    // Writes the FLAC file header for a stream with `num_samples` sample frames of `bits_per_sample` bits each.
+    // The header holds the name and loop points of the stream as Vorbis comments.
+    // The MD5 signature of the samples is left unset, since the header can't be rewritten after the samples are encoded.
+    pub(super) fn new(
+        bits_per_sample: u16,
+        num_samples: u32,
+        info: &StreamInfo,
+        sample_loop: Option<SampleLoop>,
+        mut sink: W,
+    ) -> Result<Self, FlacError> {
+        let config = Encoder::default()
+            .into_verified()
+            .expect("default encoder configuration is valid");
+        let channels = usize::from(info.channels.get());
+        let bytes_per_sample = usize::from(bits_per_sample.div_ceil(8));
+
+        let stream_info =
+            FlacStreamInfo::new(info.sample_rate.get() as usize, channels, bits_per_sample.into())
+                .map_err(FlacError::from_verify(FlacErrorKind::UnsupportedStream))?;
+        let frame = FrameBuf::with_size(channels, config.block_size)
+            .map_err(FlacError::from_verify(FlacErrorKind::UnsupportedStream))?;
+
+        let comments = comments(info.name.as_deref(), sample_loop);
+
+        write_header(&stream_info, config.block_size, num_samples, &comments, &mut sink)
+            .map_err(FlacError::from_io(FlacErrorKind::CreateHeader))?;
+
+        Ok(Self {
+            sink,
+            block: Vec::with_capacity(config.block_size * channels * bytes_per_sample),
+            block_len: config.block_size * channels * bytes_per_sample,
+            config,
+            stream_info,
+            frame,
+            bytes_per_sample,
+            frame_number: 0,
+        })
+    }
+
+    // Encodes the samples held in the block buffer as a frame.
+    // Bytes past the final complete sample frame can't be encoded, so they are dropped.
+    fn encode_block(&mut self) -> Result<(), IoError> {
+        let frame_size = self.bytes_per_sample * self.frame.channels();
+        let len = self.block.len() - self.block.len() % frame_size;
+
+        if len > 0 {
+            self.frame
+                .fill_le_bytes(&self.block[..len], self.bytes_per_sample)
+                .map_err(invalid_data)?;
+
+            let frame = encode_fixed_size_frame(
+                &self.config,
+                &self.frame,
+                self.frame_number,
+                &self.stream_info,
+            )
+            .map_err(invalid_data)?;
+
+            let mut bytes = ByteSink::with_capacity(frame.count_bits());
+            frame.write(&mut bytes).map_err(invalid_data)?;
+
+            self.sink.write_all(bytes.as_slice())?;
+            self.frame_number += 1;
+        }
+
+        self.block.clear();
+        Ok(())
+    }
+
+    // Encodes the final block, which can be shorter than the others, then returns the sink.
+    pub(super) fn finish(mut self) -> Result<W, FlacError> {
+        self.encode_block()
+            .and_then(|()| self.sink.flush())
+            .map(|()| self.sink)
+            .map_err(FlacError::from_io(FlacErrorKind::FinishStream))
+    }
+}
+
+impl<W: Write> Write for FlacWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, IoError> {
+        let len = buf.len().min(self.block_len - self.block.len());
+        self.block.extend_from_slice(&buf[..len]);
+
+        if self.block.len() == self.block_len {
+            self.encode_block()?;
+        }
+
+        Ok(len)
+    }
+
+    // Samples of an incomplete block are kept until more samples are written, or until the writer is finished.
+    fn flush(&mut self) -> Result<(), IoError> {
+        self.sink.flush()
+    }
+}
+
+// Errors of the FLAC encoder can't be sent between threads, so only their messages are kept.
+fn invalid_data<E: Display>(error: E) -> IoError {
+    IoError::new(ErrorKind::InvalidData, error.to_string())
+}
+
+// Returns the Vorbis comments of a stream.
+// Loop points are stored with the `LOOPSTART` and `LOOPLENGTH` fields, which are read by many game audio players.
+fn comments(name: Option<&str>, sample_loop: Option<SampleLoop>) -> Vec<String> {
+    let mut comments = Vec::new();
+
+    if let Some(name) = name {
+        comments.push(format!("TITLE={name}"));
+    }
+
+    if let Some(sample_loop) = sample_loop {
+        comments.push(format!("LOOPSTART={}", sample_loop.start));
+        comments.push(format!("LOOPLENGTH={}", sample_loop.end - sample_loop.start + 1));
+    }
+
+    comments
+}
+
+fn write_header<W: Write>(
+    stream_info: &FlacStreamInfo,
+    block_size: usize,
+    num_samples: u32,
+    comments: &[String],
+    sink: &mut W,
+) -> Result<(), IoError> {
+    let block_size = u16::try_from(block_size).expect("block size is at most 65535");
+    let channels = u64::try_from(stream_info.channels() - 1).expect("channel count is at most 8");
+    let bits_per_sample =
+        u64::try_from(stream_info.bits_per_sample() - 1).expect("bit depth is at most 32");
+    let sample_rate = u64::try_from(stream_info.sample_rate()).expect("sample rate is a u32");
+
+    sink.write_all(b"fLaC")?;
+
+    sink.write_all(&[STREAMINFO_BLOCK_TYPE, 0, 0, 34])?;
+    // minimum and maximum block size; all blocks except the last one have the same size
+    sink.write_all(&block_size.to_be_bytes())?;
+    sink.write_all(&block_size.to_be_bytes())?;
+    // minimum and maximum frame size, which are unknown
+    sink.write_all(&[0; 6])?;
+    // sample rate (20 bits), channel count - 1 (3 bits), bits per sample - 1 (5 bits), and sample count (36 bits)
+    sink.write_all(
+        &(sample_rate << 44 | channels << 41 | bits_per_sample << 36 | u64::from(num_samples))
+            .to_be_bytes(),
+    )?;
+    // MD5 signature of the samples, which is unset
+    sink.write_all(&[0; 16])?;
+
+    // Unlike the rest of the file, Vorbis comments use little-endian lengths.
+    let mut block = Vec::new();
+    write_comment(VENDOR, &mut block)?;
+    block.extend_from_slice(
+        &u32::try_from(comments.len())
+            .expect("there are at most 3 comments")
+            .to_le_bytes(),
+    );
+    for comment in comments {
+        write_comment(comment, &mut block)?;
+    }
+
+    if block.len() > MAX_BLOCK_SIZE {
+        return Err(IoError::new(
+            ErrorKind::InvalidInput,
+            "Vorbis comment block would be larger than 16 MiB",
+        ));
+    }
+
+    let block_len = u32::try_from(block.len()).expect("block size was checked to fit in 24 bits");
+    sink.write_all(&[LAST_BLOCK_FLAG | VORBIS_COMMENT_BLOCK_TYPE])?;
+    sink.write_all(&block_len.to_be_bytes()[1..])?;
+    sink.write_all(&block)
+}
+
+fn write_comment(comment: &str, block: &mut Vec<u8>) -> Result<(), IoError> {
+    let len = u32::try_from(comment.len())
+        .map_err(|_| IoError::new(ErrorKind::InvalidInput, "Vorbis comment is too long"))?;
+
+    block.extend_from_slice(&len.to_le_bytes());
+    block.extend_from_slice(comment.as_bytes());
+    Ok(())
+}
+
+/// Represents an error that can occur when writing a stream as a FLAC file.
+///
+/// See [`FlacErrorKind`] for the different kinds of errors that can occur.
+#[derive(Debug)]
+pub struct FlacError {
+    kind: FlacErrorKind,
+    source: FlacErrorSource,
+}
+
+/// A variant of a [`FlacError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FlacErrorKind {
+    /// The stream's channel count or sample rate can't be stored in a FLAC file.
+    UnsupportedStream,
+    /// Failed to write the file header due to an underlying I/O error.
+    CreateHeader,
+    /// Failed to encode the final frame, or to flush the writer after encoding the entire stream.
+    FinishStream,
+}
+
+#[derive(Debug)]
+enum FlacErrorSource {
+    Io(IoError),
+    Verify(VerifyError),
+}
+
+impl FlacError {
+    fn from_io(kind: FlacErrorKind) -> impl FnOnce(IoError) -> Self {
+        move |source| Self {
+            kind,
+            source: FlacErrorSource::Io(source),
+        }
+    }
+
+    fn from_verify(kind: FlacErrorKind) -> impl FnOnce(VerifyError) -> Self {
+        move |source| Self {
+            kind,
+            source: FlacErrorSource::Verify(source),
+        }
+    }
+
+    /// Returns the [`FlacErrorKind`] associated with this error.
+    #[must_use]
+    pub fn kind(&self) -> FlacErrorKind {
+        self.kind
+    }
+}
+
+impl Display for FlacError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.kind.fmt(f)
+    }
+}
+
+impl Error for FlacError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.source {
+            FlacErrorSource::Io(e) => Some(e),
+            FlacErrorSource::Verify(e) => Some(e),
+        }
+    }
+}
+
+impl Display for FlacErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Self::UnsupportedStream => "stream can't be stored in a FLAC file",
+            Self::CreateHeader => "failed to encode file header",
+            Self::FinishStream => "failed to finalize writing FLAC stream data",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        encode::{encode, EncodeOptions, PcmOutput},
+        header::{AudioFormat, Loop, StreamInfo},
+        read::Reader,
+    };
+    use claxon::FlacReader;
+    use std::io::Cursor;
+
+    fn read_back(output: Vec<u8>) -> FlacReader<Cursor<Vec<u8>>> {
+        FlacReader::new(Cursor::new(output)).unwrap()
+    }
+
+    #[test]
+    fn round_trip_16_bit_stream() {
+        // more than one block of samples, so the final frame is shorter than the others
+        let samples: Vec<i16> =
+            (0..5000 * 2i16).map(|n| n.wrapping_mul(37) % 2000 - 1000).collect();
+        let data: Vec<_> = samples.iter().flat_map(|sample| sample.to_le_bytes()).collect();
+
+        let info = StreamInfo {
+            name: Some("bgm_battle".into()),
+            stream_loop: Some(Loop::dummy(100, 4999)),
+            ..StreamInfo::dummy(2, 5000, 20_000)
+        };
+        let output = encode(
+            AudioFormat::Pcm16,
+            0,
+            0,
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::new().pcm_output(PcmOutput::Flac),
+        )
+        .unwrap();
+
+        let mut reader = read_back(output);
+        let stream_info = reader.streaminfo();
+        assert_eq!(stream_info.sample_rate, 44100);
+        assert_eq!(stream_info.channels, 2);
+        assert_eq!(stream_info.bits_per_sample, 16);
+        assert_eq!(stream_info.samples, Some(5000));
+
+        assert_eq!(reader.get_tag("TITLE").collect::<Vec<_>>(), ["bgm_battle"]);
+        assert_eq!(reader.get_tag("LOOPSTART").collect::<Vec<_>>(), ["100"]);
+        assert_eq!(reader.get_tag("LOOPLENGTH").collect::<Vec<_>>(), ["4900"]);
+
+        let decoded: Vec<_> = reader.samples().map(|sample| sample.unwrap()).collect();
+        assert_eq!(
+            decoded,
+            samples.iter().map(|&sample| i32::from(sample)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn keep_8_bit_samples_signed() {
+        let data = [0x00, 0x7F, 0x80, 0xFF];
+
+        let info = StreamInfo::dummy(1, 4, 4);
+        let output = encode(
+            AudioFormat::Pcm8,
+            0,
+            0,
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::new().pcm_output(PcmOutput::Flac).loop_chunk(false),
+        )
+        .unwrap();
+
+        let mut reader = read_back(output);
+        assert_eq!(reader.streaminfo().bits_per_sample, 8);
+        assert_eq!(reader.get_tag("TITLE").count(), 0);
+
+        let decoded: Vec<_> = reader.samples().map(|sample| sample.unwrap()).collect();
+        assert_eq!(decoded, [0, 127, -128, -1]);
+    }
+
+    #[test]
+    fn round_trip_decoded_stream() {
+        // a single FADPCM frame of silence
+        let data = [0; 0x8C];
+
+        let info = StreamInfo::dummy(1, 256, 0x8C);
+        let output = encode(
+            AudioFormat::FAdpcm,
+            0,
+            0,
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::new().pcm_output(PcmOutput::Flac),
+        )
+        .unwrap();
+
+        let mut reader = read_back(output);
+        assert_eq!(reader.streaminfo().bits_per_sample, 16);
+        assert_eq!(reader.streaminfo().samples, Some(256));
+        assert!(reader.samples().all(|sample| sample.unwrap() == 0));
+    }
+}
@@ -0,0 +1,389 @@
+use super::{wav::WavSpec, EncodeOptions};
+use crate::{
+    header::{DspCoeffs, StreamInfo},
+    read::{ReadError, Reader},
+};
+use std::{
+    cmp::min,
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io::{Error as IoError, Read, Write},
+};
+
+// GC ADPCM (also known as DSP ADPCM) decoding logic adapted from vgmstream:
+// [1]: https://github.com/vgmstream/vgmstream/blob/master/src/coding/ngc_dsp_decoder.c
+
+// A frame starts with a 1-byte header (coefficient index and scale),
+// followed by 7 bytes containing 14 samples stored as 4-bit nibbles.
+// Unlike most other ADPCM formats, decoder state is carried over from one frame to the next.
+const FRAME_SIZE: usize = 0x08;
+const SAMPLES_PER_FRAME: usize = (FRAME_SIZE - 1) * 2;
+
+// Frames of different channels are interleaved in units of 2 bytes.
+const INTERLEAVE: usize = 0x02;
+
+pub(super) fn encode<R: Read, W: Write>(
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+    mut sink: W,
+    options: EncodeOptions<'_>,
+) -> Result<W, GcAdpcmError> {
+    let channels = info.channels.get() as usize;
+    let mut decoder = BlockDecoder::new(info, source)?;
+
+    let data_size = (decoder.samples_left * channels * 2)
+        .try_into()
+        .map_err(|_| GcAdpcmError::new(GcAdpcmErrorKind::StreamTooLarge))?;
+
+    let spec = WavSpec::pcm16(
+        channels.try_into().expect("number of channels is less than u16::MAX"),
+        info.sample_rate.get(),
+    )
+    .with_options(info, options, data_size);
+
+    // write the WAVE file header
+    spec.write_header(data_size, &mut sink)
+        .map_err(GcAdpcmError::from_io(GcAdpcmErrorKind::CreateHeader))?;
+
+    let mut samples = Vec::new();
+
+    while decoder.next_block(source, &mut samples)? {
+        for &sample in &samples {
+            sink.write_all(&spec.i16_bytes(sample))
+                .map_err(GcAdpcmError::from_io(GcAdpcmErrorKind::EncodeSample))?;
+        }
+    }
+
+    spec.write_trailer(data_size, &mut sink)
+        .and_then(|()| sink.flush())
+        .map(|()| sink)
+        .map_err(GcAdpcmError::from_io(GcAdpcmErrorKind::FinishStream))
+}
+
+// Decodes the stream one block of frames at a time, producing interleaved samples.
+pub(super) struct BlockDecoder {
+    channels: usize,
+    stream_size: usize,
+    start_pos: u64,
+    pub(super) samples_left: usize,
+    decoders: Vec<Decoder>,
+    decoded: Vec<[i16; SAMPLES_PER_FRAME]>,
+}
+
+impl BlockDecoder {
+    pub(super) fn new<R: Read>(
+        info: &StreamInfo,
+        source: &Reader<R>,
+    ) -> Result<Self, GcAdpcmError> {
+        // The stream should have contained decoding coefficients for every channel in a header chunk.
+        // Otherwise, the stream cannot be decoded.
+        let coeffs = info
+            .dsp_coeffs
+            .as_deref()
+            .ok_or_else(|| GcAdpcmError::new(GcAdpcmErrorKind::MissingCoefficients))?;
+
+        let channels = info.channels.get() as usize;
+        let stream_size = info.size as usize;
+
+        let num_samples = min(
+            info.num_samples.get() as usize,
+            stream_size.div_ceil(FRAME_SIZE * channels) * SAMPLES_PER_FRAME,
+        );
+
+        Ok(Self {
+            channels,
+            stream_size,
+            start_pos: source.position(),
+            samples_left: num_samples,
+            decoders: coeffs.iter().map(Decoder::new).collect(),
+            decoded: vec![[0; SAMPLES_PER_FRAME]; channels],
+        })
+    }
+
+    // Replaces the contents of `samples` with the samples of the next block of frames,
+    // returning false once every sample has been decoded.
+    pub(super) fn next_block<R: Read>(
+        &mut self,
+        source: &mut Reader<R>,
+        samples: &mut Vec<i16>,
+    ) -> Result<bool, GcAdpcmError> {
+        samples.clear();
+
+        if self.samples_left == 0 {
+            return Ok(false);
+        }
+
+        // the final block of frames can be cut short, so missing bytes are treated as 0
+        let block_size = FRAME_SIZE * self.channels;
+        let block_len = min(block_size, self.stream_size - source.bytes_since(self.start_pos));
+
+        let mut block = source
+            .take(block_len)
+            .map_err(GcAdpcmError::from_read(GcAdpcmErrorKind::DecodeFrame))?;
+        block.resize(block_size, 0);
+
+        for (channel, (decoder, decoded)) in
+            self.decoders.iter_mut().zip(&mut self.decoded).enumerate()
+        {
+            let mut frame = [0; FRAME_SIZE];
+
+            for (index, chunk) in frame.chunks_exact_mut(INTERLEAVE).enumerate() {
+                let offset = (index * self.channels + channel) * INTERLEAVE;
+                chunk.copy_from_slice(&block[offset..offset + INTERLEAVE]);
+            }
+
+            *decoded = decoder.decode_frame(frame);
+        }
+
+        let frame_samples = min(self.samples_left, SAMPLES_PER_FRAME);
+
+        for index in 0..frame_samples {
+            samples.extend(self.decoded.iter().map(|decoded| decoded[index]));
+        }
+
+        self.samples_left -= frame_samples;
+        Ok(true)
+    }
+}
+
+struct Decoder {
+    coefficients: [i16; 16],
+    hist1: i32,
+    hist2: i32,
+}
+
+impl Decoder {
+    fn new(coeffs: &DspCoeffs) -> Self {
+        Self {
+            coefficients: coeffs.coefficients,
+            hist1: coeffs.hist1.into(),
+            hist2: coeffs.hist2.into(),
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn decode_frame(&mut self, frame: [u8; FRAME_SIZE]) -> [i16; SAMPLES_PER_FRAME] {
+        let scale = 1 << (frame[0] & 0x0F);
+        let coeff_index = usize::from((frame[0] >> 4) & 0x07);
+        let coeff1 = i32::from(self.coefficients[coeff_index * 2]);
+        let coeff2 = i32::from(self.coefficients[coeff_index * 2 + 1]);
+
+        let mut samples = [0; SAMPLES_PER_FRAME];
+
+        let nibbles = frame[1..]
+            .iter()
+            .flat_map(|&byte| [byte.cast_signed() >> 4, (byte << 4).cast_signed() >> 4]);
+
+        for (sample, nibble) in samples.iter_mut().zip(nibbles) {
+            let value = (((i32::from(nibble) * scale) << 11)
+                + 0x400
+                + coeff1 * self.hist1
+                + coeff2 * self.hist2)
+                >> 11;
+            let value = value.clamp(i16::MIN.into(), i16::MAX.into());
+
+            *sample = value as i16;
+            self.hist2 = self.hist1;
+            self.hist1 = value;
+        }
+
+        samples
+    }
+}
+
+/// Represents an error that can occur when encoding a GC ADPCM stream.
+///
+/// See [`GcAdpcmErrorKind`] for the different kinds of errors that can occur.
+#[derive(Debug)]
+pub struct GcAdpcmError {
+    kind: GcAdpcmErrorKind,
+    source: Option<GcAdpcmErrorSource>,
+}
+
+/// A variant of a [`GcAdpcmError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum GcAdpcmErrorKind {
+    /// Decoding coefficients were not found in the stream header within the sound bank.
+    /// These coefficients are needed to decode the stream data.
+    MissingCoefficients,
+    /// The decoded stream was too large to fit in a WAVE file.
+    StreamTooLarge,
+    /// Failed to write the file header due to an underlying I/O error.
+    CreateHeader,
+    /// Failed to read a block of frames from the stream data.
+    DecodeFrame,
+    /// Failed to encode a decoded audio sample to the writer.
+    EncodeSample,
+    /// Failed to flush the writer after encoding the entire stream.
+    FinishStream,
+}
+
+#[derive(Debug)]
+enum GcAdpcmErrorSource {
+    Io(IoError),
+    Read(ReadError),
+}
+
+impl GcAdpcmError {
+    fn new(kind: GcAdpcmErrorKind) -> Self {
+        Self { kind, source: None }
+    }
+
+    fn from_io(kind: GcAdpcmErrorKind) -> impl FnOnce(IoError) -> Self {
+        move |source| Self {
+            kind,
+            source: Some(GcAdpcmErrorSource::Io(source)),
+        }
+    }
+
+    fn from_read(kind: GcAdpcmErrorKind) -> impl FnOnce(ReadError) -> Self {
+        move |source| Self {
+            kind,
+            source: Some(GcAdpcmErrorSource::Read(source)),
+        }
+    }
+
+    /// Returns the [`GcAdpcmErrorKind`] associated with this error.
+    #[must_use]
+    pub fn kind(&self) -> GcAdpcmErrorKind {
+        self.kind
+    }
+}
+
+impl Display for GcAdpcmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.kind.fmt(f)
+    }
+}
+
+impl Error for GcAdpcmError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.source {
+            Some(source) => match source {
+                GcAdpcmErrorSource::Io(e) => Some(e),
+                GcAdpcmErrorSource::Read(e) => Some(e),
+            },
+            None => None,
+        }
+    }
+}
+
+impl Display for GcAdpcmErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Self::MissingCoefficients => {
+                "file header did not contain GC ADPCM decoding coefficients"
+            }
+            Self::StreamTooLarge => "decoded GC ADPCM stream was too large to encode",
+            Self::CreateHeader => "failed to encode file header",
+            Self::DecodeFrame => "failed to read frames from GC ADPCM stream",
+            Self::EncodeSample => "failed to encode sample",
+            Self::FinishStream => "failed to finalize writing GC ADPCM stream data",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{encode, Decoder, GcAdpcmErrorKind};
+    use crate::{
+        encode::EncodeOptions,
+        header::{DspCoeffs, StreamInfo},
+        read::{Reader, ShortReader},
+    };
+
+    fn coeffs(coeff1: i16, coeff2: i16) -> DspCoeffs {
+        let mut coefficients = [0; 16];
+        coefficients[2] = coeff1;
+        coefficients[3] = coeff2;
+
+        DspCoeffs {
+            coefficients,
+            hist1: 0,
+            hist2: 0,
+        }
+    }
+
+    #[test]
+    fn decode_frame_without_prediction() {
+        let coeffs = coeffs(0, 0);
+        let mut decoder = Decoder::new(&coeffs);
+
+        // scale of 2^0, coefficient index 0; nibbles are decoded starting from the high nibble
+        let samples = decoder.decode_frame([0x00, 0x17, 0xF8, 0, 0, 0, 0, 0]);
+        assert_eq!(samples[..4], [1, 7, -1, -8]);
+
+        // scale of 2^12
+        let samples = decoder.decode_frame([0x0C, 0x17, 0xF8, 0, 0, 0, 0, 0]);
+        assert_eq!(samples[..4], [4096, 28672, -4096, -32768]);
+    }
+
+    #[test]
+    fn decode_frames_with_prediction() {
+        // coefficients are fixed-point values with 11 fractional bits: 2048 is 1.0
+        let coeffs = coeffs(2048, 0);
+        let mut decoder = Decoder::new(&coeffs);
+
+        let samples = decoder.decode_frame([0x10, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11]);
+        assert_eq!(samples, core::array::from_fn(|i| i16::try_from(i).unwrap() + 1));
+
+        // decoder state is carried over between frames
+        let samples = decoder.decode_frame([0x10, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(samples, [14; 14]);
+    }
+
+    #[test]
+    fn require_coefficients() {
+        let info = StreamInfo::dummy(1, 14, 8);
+        let data = [0; 8];
+
+        assert!(encode(
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::default()
+        )
+        .is_err_and(|e| e.kind() == GcAdpcmErrorKind::MissingCoefficients));
+    }
+
+    #[test]
+    fn encode_interleaved_channels() {
+        let info = StreamInfo {
+            dsp_coeffs: Some(vec![coeffs(0, 0), coeffs(0, 0)].into_boxed_slice()),
+            ..StreamInfo::dummy(2, 2, 16)
+        };
+
+        // frame headers and nibbles of both channels are interleaved in units of 2 bytes
+        let data = [0x00, 0x1F, 0x00, 0xF1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let output = encode(
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(output.len(), 44 + 2 * 2 * 2);
+        assert_eq!(output[44..], [1, 0, 255, 255, 255, 255, 1, 0]);
+    }
+
+    #[test]
+    fn encode_from_short_reads() {
+        let info = StreamInfo {
+            dsp_coeffs: Some(vec![coeffs(0, 0), coeffs(0, 0)].into_boxed_slice()),
+            ..StreamInfo::dummy(2, 2, 16)
+        };
+        let data = [0x00, 0x1F, 0x00, 0xF1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+        let output = encode(
+            &info,
+            &mut Reader::new(ShortReader::new(&data)),
+            Vec::new(),
+            EncodeOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(output[44..], [1, 0, 255, 255, 255, 255, 1, 0]);
+    }
+}
@@ -0,0 +1,407 @@
+use super::{wav::WavSpec, EncodeOptions};
+use crate::{
+    header::StreamInfo,
+    read::{ReadError, Reader},
+};
+use std::{
+    cmp::min,
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io::{Error as IoError, Read, Write},
+};
+
+// VAG and HEVAG decoding logic adapted from vgmstream:
+// [1]: https://github.com/vgmstream/vgmstream/blob/master/src/coding/psx_decoder.c
+// [2]: https://github.com/vgmstream/vgmstream/blob/master/src/coding/hevag_decoder.c
+
+// A frame starts with a 2-byte header (coefficient index, shift, and flags),
+// followed by 14 bytes containing 28 samples stored as 4-bit nibbles.
+// Frames of different channels are interleaved one frame at a time.
+const FRAME_SIZE: usize = 0x10;
+const SAMPLES_PER_FRAME: usize = (FRAME_SIZE - 2) * 2;
+
+// Filter coefficients shared by VAG and HEVAG, as fixed-point values with 6 fractional bits.
+// HEVAG extends this table to 128 entries; streams using the extended entries can't be decoded yet.
+const COEFFICIENTS: [(i32, i32); 5] = [(0, 0), (60, 0), (115, -52), (98, -55), (122, -60)];
+
+// Frames with this flag value mark the end of a stream and decode to silence.
+const FLAG_END: u8 = 0x07;
+
+pub(super) fn encode<R: Read, W: Write>(
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+    mut sink: W,
+    options: EncodeOptions<'_>,
+) -> Result<W, HevagError> {
+    let channels = info.channels.get() as usize;
+    let mut decoder = BlockDecoder::new(info, source);
+
+    let data_size = (decoder.samples_left * channels * 2)
+        .try_into()
+        .map_err(|_| HevagError::new(HevagErrorKind::StreamTooLarge))?;
+
+    let spec = WavSpec::pcm16(
+        channels.try_into().expect("number of channels is less than u16::MAX"),
+        info.sample_rate.get(),
+    )
+    .with_options(info, options, data_size);
+
+    // write the WAVE file header
+    spec.write_header(data_size, &mut sink)
+        .map_err(HevagError::from_io(HevagErrorKind::CreateHeader))?;
+
+    let mut samples = Vec::new();
+
+    while decoder.next_block(source, &mut samples)? {
+        for &sample in &samples {
+            sink.write_all(&spec.i16_bytes(sample))
+                .map_err(HevagError::from_io(HevagErrorKind::EncodeSample))?;
+        }
+    }
+
+    spec.write_trailer(data_size, &mut sink)
+        .and_then(|()| sink.flush())
+        .map(|()| sink)
+        .map_err(HevagError::from_io(HevagErrorKind::FinishStream))
+}
+
+// Decodes the stream one block of frames at a time, producing interleaved samples.
+pub(super) struct BlockDecoder {
+    channels: usize,
+    stream_size: usize,
+    start_pos: u64,
+    pub(super) samples_left: usize,
+    decoders: Vec<Decoder>,
+    decoded: Vec<[i16; SAMPLES_PER_FRAME]>,
+}
+
+impl BlockDecoder {
+    pub(super) fn new<R: Read>(info: &StreamInfo, source: &Reader<R>) -> Self {
+        let channels = info.channels.get() as usize;
+        let stream_size = info.size as usize;
+
+        let num_samples = min(
+            info.num_samples.get() as usize,
+            stream_size.div_ceil(FRAME_SIZE * channels) * SAMPLES_PER_FRAME,
+        );
+
+        Self {
+            channels,
+            stream_size,
+            start_pos: source.position(),
+            samples_left: num_samples,
+            decoders: vec![Decoder::default(); channels],
+            decoded: vec![[0; SAMPLES_PER_FRAME]; channels],
+        }
+    }
+
+    // Replaces the contents of `samples` with the samples of the next block of frames,
+    // returning false once every sample has been decoded.
+    pub(super) fn next_block<R: Read>(
+        &mut self,
+        source: &mut Reader<R>,
+        samples: &mut Vec<i16>,
+    ) -> Result<bool, HevagError> {
+        samples.clear();
+
+        if self.samples_left == 0 {
+            return Ok(false);
+        }
+
+        // the final block of frames can be cut short, so missing bytes are treated as 0
+        let block_size = FRAME_SIZE * self.channels;
+        let block_len = min(block_size, self.stream_size - source.bytes_since(self.start_pos));
+
+        let mut block = source
+            .take(block_len)
+            .map_err(HevagError::from_read(HevagErrorKind::DecodeFrame))?;
+        block.resize(block_size, 0);
+
+        for ((decoder, decoded), frame) in self
+            .decoders
+            .iter_mut()
+            .zip(&mut self.decoded)
+            .zip(block.chunks_exact(FRAME_SIZE))
+        {
+            *decoded = decoder
+                .decode_hevag_frame(frame.try_into().unwrap())
+                .ok_or_else(|| HevagError::new(HevagErrorKind::UnsupportedCoefficients))?;
+        }
+
+        let frame_samples = min(self.samples_left, SAMPLES_PER_FRAME);
+
+        for index in 0..frame_samples {
+            samples.extend(self.decoded.iter().map(|decoded| decoded[index]));
+        }
+
+        self.samples_left -= frame_samples;
+        Ok(true)
+    }
+}
+
+#[derive(Clone, Default)]
+struct Decoder {
+    hist1: i32,
+    hist2: i32,
+}
+
+impl Decoder {
+    // HEVAG stores the upper bits of the coefficient index in the upper nibble of the flag byte.
+    fn decode_hevag_frame(&mut self, frame: [u8; FRAME_SIZE]) -> Option<[i16; SAMPLES_PER_FRAME]> {
+        let coeff_index = (frame[1] & 0xF0) | (frame[0] >> 4);
+        self.decode_frame(frame, coeff_index, frame[1] & 0x0F)
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn decode_frame(
+        &mut self,
+        frame: [u8; FRAME_SIZE],
+        coeff_index: u8,
+        flag: u8,
+    ) -> Option<[i16; SAMPLES_PER_FRAME]> {
+        let mut samples = [0; SAMPLES_PER_FRAME];
+
+        if flag == FLAG_END {
+            self.hist1 = 0;
+            self.hist2 = 0;
+            return Some(samples);
+        }
+
+        let (coeff1, coeff2) = *COEFFICIENTS.get(usize::from(coeff_index))?;
+
+        // shift values above 12 are invalid, and are treated as 9
+        let shift = match frame[0] & 0x0F {
+            shift @ 0..=12 => shift,
+            _ => 9,
+        };
+
+        // nibbles are decoded starting from the low nibble
+        let nibbles = frame[2..]
+            .iter()
+            .flat_map(|&byte| [(byte << 4).cast_signed() >> 4, byte.cast_signed() >> 4]);
+
+        for (sample, nibble) in samples.iter_mut().zip(nibbles) {
+            let scale = (i32::from(nibble) << 12) >> shift;
+            let value = (scale + ((coeff1 * self.hist1 + coeff2 * self.hist2) >> 6))
+                .clamp(i16::MIN.into(), i16::MAX.into());
+
+            *sample = value as i16;
+            self.hist2 = self.hist1;
+            self.hist1 = value;
+        }
+
+        Some(samples)
+    }
+}
+
+/// Represents an error that can occur when encoding a HEVAG stream.
+///
+/// See [`HevagErrorKind`] for the different kinds of errors that can occur.
+#[derive(Debug)]
+pub struct HevagError {
+    kind: HevagErrorKind,
+    source: Option<HevagErrorSource>,
+}
+
+/// A variant of a [`HevagError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum HevagErrorKind {
+    /// The decoded stream was too large to fit in a WAVE file.
+    StreamTooLarge,
+    /// Failed to write the file header due to an underlying I/O error.
+    CreateHeader,
+    /// Failed to read a block of frames from the stream data.
+    DecodeFrame,
+    /// A frame used one of the extended HEVAG filter coefficients, which are not supported yet.
+    UnsupportedCoefficients,
+    /// Failed to encode a decoded audio sample to the writer.
+    EncodeSample,
+    /// Failed to flush the writer after encoding the entire stream.
+    FinishStream,
+}
+
+#[derive(Debug)]
+enum HevagErrorSource {
+    Io(IoError),
+    Read(ReadError),
+}
+
+impl HevagError {
+    fn new(kind: HevagErrorKind) -> Self {
+        Self { kind, source: None }
+    }
+
+    fn from_io(kind: HevagErrorKind) -> impl FnOnce(IoError) -> Self {
+        move |source| Self {
+            kind,
+            source: Some(HevagErrorSource::Io(source)),
+        }
+    }
+
+    fn from_read(kind: HevagErrorKind) -> impl FnOnce(ReadError) -> Self {
+        move |source| Self {
+            kind,
+            source: Some(HevagErrorSource::Read(source)),
+        }
+    }
+
+    /// Returns the [`HevagErrorKind`] associated with this error.
+    #[must_use]
+    pub fn kind(&self) -> HevagErrorKind {
+        self.kind
+    }
+}
+
+impl Display for HevagError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.kind.fmt(f)
+    }
+}
+
+impl Error for HevagError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.source {
+            Some(source) => match source {
+                HevagErrorSource::Io(e) => Some(e),
+                HevagErrorSource::Read(e) => Some(e),
+            },
+            None => None,
+        }
+    }
+}
+
+impl Display for HevagErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Self::StreamTooLarge => "decoded HEVAG stream was too large to encode",
+            Self::CreateHeader => "failed to encode file header",
+            Self::DecodeFrame => "failed to read frames from HEVAG stream",
+            Self::UnsupportedCoefficients => {
+                "HEVAG stream used extended filter coefficients, which are not supported"
+            }
+            Self::EncodeSample => "failed to encode sample",
+            Self::FinishStream => "failed to finalize writing HEVAG stream data",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{encode, Decoder, HevagErrorKind, FRAME_SIZE, SAMPLES_PER_FRAME};
+    use crate::{
+        encode::EncodeOptions,
+        header::StreamInfo,
+        read::{Reader, ShortReader},
+    };
+
+    fn frame(header: u8, flag: u8, nibbles: u8) -> [u8; FRAME_SIZE] {
+        let mut frame = [nibbles; FRAME_SIZE];
+        frame[0] = header;
+        frame[1] = flag;
+        frame
+    }
+
+    #[test]
+    fn decode_frame_without_prediction() {
+        let mut decoder = Decoder::default();
+
+        // coefficient index 0, shift of 12; the low nibble is decoded first
+        let samples = decoder.decode_hevag_frame(frame(0x0C, 0, 0xF1)).unwrap();
+        assert_eq!(samples[..4], [1, -1, 1, -1]);
+
+        // shift of 0
+        let samples = decoder.decode_hevag_frame(frame(0x00, 0, 0x87)).unwrap();
+        assert_eq!(samples[..2], [28672, -32768]);
+    }
+
+    #[test]
+    fn decode_frames_with_prediction() {
+        let mut decoder = Decoder::default();
+        let _ = decoder.decode_hevag_frame(frame(0x08, 0, 0x44)).unwrap();
+
+        // coefficient index 1 (60/64) with silent nibbles decays the previous sample
+        let samples = decoder.decode_hevag_frame(frame(0x1C, 0, 0x00)).unwrap();
+        assert_eq!(samples[..3], [60, 56, 52]);
+
+        // frames marked as the end of the stream are silent
+        let samples = decoder.decode_hevag_frame(frame(0x1C, 0x07, 0x77)).unwrap();
+        assert_eq!(samples, [0; SAMPLES_PER_FRAME]);
+    }
+
+    #[test]
+    fn reject_extended_coefficients() {
+        let mut decoder = Decoder::default();
+
+        assert!(decoder.decode_hevag_frame(frame(0x50, 0, 0)).is_none());
+        assert!(decoder.decode_hevag_frame(frame(0x00, 0x10, 0)).is_none());
+    }
+
+    #[test]
+    fn encode_mono_stream() {
+        let mut data = frame(0x0C, 0, 0x11).to_vec();
+        data.extend(frame(0x0C, 0, 0xFF));
+
+        let info = StreamInfo::dummy(1, 30, 0x20);
+        let output = encode(
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(output.len(), 44 + 30 * 2);
+        assert_eq!(output[44..100], [1, 0].repeat(28));
+        assert_eq!(output[100..], [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn encode_stereo_stream() {
+        // frames of both channels are interleaved
+        let mut data = frame(0x0C, 0, 0x11).to_vec();
+        data.extend(frame(0x0C, 0, 0xFF));
+
+        let info = StreamInfo::dummy(2, 2, 0x20);
+        let output = encode(
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(output[44..], [1, 0, 255, 255, 1, 0, 255, 255]);
+    }
+
+    #[test]
+    fn encode_extended_coefficients() {
+        let data = frame(0x50, 0, 0);
+
+        let info = StreamInfo::dummy(1, 28, 0x10);
+        assert!(encode(
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::default()
+        )
+        .is_err_and(|e| e.kind() == HevagErrorKind::UnsupportedCoefficients));
+    }
+
+    #[test]
+    fn encode_from_short_reads() {
+        let mut data = frame(0x0C, 0, 0x11).to_vec();
+        data.extend(frame(0x0C, 0, 0xFF));
+
+        let info = StreamInfo::dummy(2, 2, 0x20);
+        let output = encode(
+            &info,
+            &mut Reader::new(ShortReader::new(&data)),
+            Vec::new(),
+            EncodeOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(output[44..], [1, 0, 255, 255, 1, 0, 255, 255]);
+    }
+}
@@ -0,0 +1,118 @@
+use std::io::{Error as IoError, ErrorKind, Write};
+
+// ID3v2.3 tag layout taken from:
+// [1]: https://id3.org/id3v2.3.0
+
+// Sizes in the tag header are syncsafe integers, which only use the lower 7 bits of every byte.
+const MAX_TAG_SIZE: usize = (1 << 28) - 1;
+
+// Text frames stored in an ID3v2.3 tag, written before the frames of MPEG streams.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) struct Id3Tag<'a> {
+    pub(super) title: Option<&'a str>,
+    pub(super) track: u32,
+    pub(super) length_ms: u64,
+}
+
+impl Id3Tag<'_> {
+    pub(super) fn write<W: Write>(self, sink: &mut W) -> Result<(), IoError> {
+        let mut frames = Vec::new();
+
+        if let Some(title) = self.title {
+            text_frame(*b"TIT2", title, &mut frames);
+        }
+
+        text_frame(*b"TRCK", &self.track.to_string(), &mut frames);
+        text_frame(*b"TLEN", &self.length_ms.to_string(), &mut frames);
+
+        if frames.len() > MAX_TAG_SIZE {
+            return Err(IoError::new(
+                ErrorKind::InvalidInput,
+                "ID3v2 tag would be larger than 256 MiB",
+            ));
+        }
+
+        // identifier, version 2.3.0, and no flags
+        sink.write_all(b"ID3\x03\x00\x00")?;
+        sink.write_all(&syncsafe(frames.len()))?;
+        sink.write_all(&frames)
+    }
+}
+
+// Appends a text frame to `frames`.
+// Text is encoded as ISO-8859-1 if possible, and as UTF-16 with a byte order mark otherwise.
+fn text_frame(id: [u8; 4], text: &str, frames: &mut Vec<u8>) {
+    let mut content = Vec::with_capacity(text.len() + 1);
+
+    if let Some(latin1) = text.chars().map(|c| u8::try_from(c).ok()).collect::<Option<Vec<_>>>() {
+        content.push(0);
+        content.extend(latin1);
+    } else {
+        content.extend([1, 0xFF, 0xFE]);
+        content.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+    }
+
+    frames.extend_from_slice(&id);
+    // Unlike the tag size, frame sizes in ID3v2.3 are plain 32-bit integers.
+    frames.extend_from_slice(
+        &u32::try_from(content.len())
+            .expect("frame is smaller than the tag")
+            .to_be_bytes(),
+    );
+    // no flags
+    frames.extend_from_slice(&[0, 0]);
+    frames.extend(content);
+}
+
+// Splits a size into 4 groups of 7 bits, from most to least significant.
+#[allow(clippy::cast_possible_truncation)]
+fn syncsafe(size: usize) -> [u8; 4] {
+    [21, 14, 7, 0].map(|shift| ((size >> shift) & 0x7F) as u8)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{syncsafe, Id3Tag};
+    use id3::{Tag, TagLike, Version};
+    use std::io::Cursor;
+
+    #[test]
+    fn encode_syncsafe_sizes() {
+        assert_eq!(syncsafe(0x7F), [0, 0, 0, 0x7F]);
+        assert_eq!(syncsafe(0x80), [0, 0, 1, 0]);
+        assert_eq!(syncsafe(0x0FFF_FFFF), [0x7F; 4]);
+    }
+
+    #[test]
+    fn read_back_tag() {
+        let mut output = Vec::new();
+        Id3Tag {
+            title: Some("bgm_title"),
+            track: 3,
+            length_ms: 12_345,
+        }
+        .write(&mut output)
+        .unwrap();
+
+        let tag = Tag::read_from2(Cursor::new(&output)).unwrap();
+        assert_eq!(tag.version(), Version::Id3v23);
+        assert_eq!(tag.title(), Some("bgm_title"));
+        assert_eq!(tag.track(), Some(3));
+        assert_eq!(tag.duration(), Some(12_345));
+    }
+
+    #[test]
+    fn read_back_unicode_title() {
+        let mut output = Vec::new();
+        Id3Tag {
+            title: Some("戦闘曲"),
+            track: 1,
+            length_ms: 0,
+        }
+        .write(&mut output)
+        .unwrap();
+
+        let tag = Tag::read_from2(Cursor::new(&output)).unwrap();
+        assert_eq!(tag.title(), Some("戦闘曲"));
+    }
+}
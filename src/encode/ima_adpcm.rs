@@ -0,0 +1,475 @@
+use super::{wav::WavSpec, EncodeOptions};
+use crate::{
+    header::StreamInfo,
+    read::{ReadError, Reader},
+};
+use std::{
+    cmp::min,
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io::{Error as IoError, Read, Write},
+};
+
+// IMA ADPCM decoding logic adapted from vgmstream:
+// [1]: https://github.com/vgmstream/vgmstream/blob/master/src/coding/ima_decoder.c
+
+// Each block holds the samples of one channel for every channel, like Xbox IMA ADPCM.
+// A channel's part of a block has a 4-byte header (initial sample, step index, and a reserved byte),
+// followed by 32 bytes of 4-bit nibbles. The initial sample is also the first decoded sample,
+// and like in vgmstream, the final nibble is skipped, so each block holds 64 samples per channel.
+// The headers of all channels come first, then the nibbles are interleaved in groups of 4 bytes per channel.
+const BLOCK_SIZE: usize = 0x24;
+const BLOCK_HEADER_SIZE: usize = 0x04;
+const SAMPLES_PER_BLOCK: usize = (BLOCK_SIZE - BLOCK_HEADER_SIZE) * 2;
+const NIBBLE_GROUP_SIZE: usize = 0x04;
+
+const INDEX_TABLE: [i32; 16] = [-1, -1, -1, -1, 2, 4, 6, 8, -1, -1, -1, -1, 2, 4, 6, 8];
+
+const STEP_TABLE: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66,
+    73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449,
+    494, 544, 598, 658, 724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272,
+    2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630, 9493,
+    10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767,
+];
+
+pub(super) fn encode<R: Read, W: Write>(
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+    mut sink: W,
+    options: EncodeOptions<'_>,
+) -> Result<W, ImaAdpcmError> {
+    let channels = info.channels.get() as usize;
+    let mut decoder = BlockDecoder::new(info, source);
+
+    let data_size = (decoder.samples_left * channels * 2)
+        .try_into()
+        .map_err(|_| ImaAdpcmError::new(ImaAdpcmErrorKind::StreamTooLarge))?;
+
+    let spec = WavSpec::pcm16(
+        channels.try_into().expect("number of channels is less than u16::MAX"),
+        info.sample_rate.get(),
+    )
+    .with_options(info, options, data_size);
+
+    // write the WAVE file header
+    spec.write_header(data_size, &mut sink)
+        .map_err(ImaAdpcmError::from_io(ImaAdpcmErrorKind::CreateHeader))?;
+
+    let mut samples = Vec::new();
+
+    while decoder.next_block(source, &mut samples)? {
+        for &sample in &samples {
+            sink.write_all(&spec.i16_bytes(sample))
+                .map_err(ImaAdpcmError::from_io(ImaAdpcmErrorKind::EncodeSample))?;
+        }
+    }
+
+    spec.write_trailer(data_size, &mut sink)
+        .and_then(|()| sink.flush())
+        .map(|()| sink)
+        .map_err(ImaAdpcmError::from_io(ImaAdpcmErrorKind::FinishStream))
+}
+
+// Decodes the stream one block at a time, producing interleaved samples.
+pub(super) struct BlockDecoder {
+    channels: usize,
+    stream_size: usize,
+    start_pos: u64,
+    pub(super) samples_left: usize,
+    decoded: Vec<[i16; SAMPLES_PER_BLOCK]>,
+}
+
+impl BlockDecoder {
+    pub(super) fn new<R: Read>(info: &StreamInfo, source: &Reader<R>) -> Self {
+        let channels = info.channels.get() as usize;
+        let stream_size = info.size as usize;
+        let block_size = BLOCK_SIZE * channels;
+
+        let full_blocks = stream_size / block_size;
+        let num_samples = min(
+            info.num_samples.get() as usize,
+            full_blocks * SAMPLES_PER_BLOCK
+                + partial_block_samples(stream_size % block_size, channels),
+        );
+
+        Self {
+            channels,
+            stream_size,
+            start_pos: source.position(),
+            samples_left: num_samples,
+            decoded: vec![[0; SAMPLES_PER_BLOCK]; channels],
+        }
+    }
+
+    // Replaces the contents of `samples` with the samples of the next block,
+    // returning false once every sample has been decoded.
+    pub(super) fn next_block<R: Read>(
+        &mut self,
+        source: &mut Reader<R>,
+        samples: &mut Vec<i16>,
+    ) -> Result<bool, ImaAdpcmError> {
+        samples.clear();
+
+        if self.samples_left == 0 {
+            return Ok(false);
+        }
+
+        // The final block can be cut short.
+        // Missing bytes are treated as 0, but the samples decoded from them are never written.
+        let block_size = BLOCK_SIZE * self.channels;
+        let block_len = min(block_size, self.stream_size - source.bytes_since(self.start_pos));
+
+        let block = source
+            .take(block_len)
+            .map_err(ImaAdpcmError::from_read(ImaAdpcmErrorKind::DecodeBlock))?;
+
+        for (channel, decoded) in self.decoded.iter_mut().enumerate() {
+            *decoded = decode_block(&channel_block(&block, channel, self.channels));
+        }
+
+        let block_samples = min(self.samples_left, SAMPLES_PER_BLOCK);
+
+        for index in 0..block_samples {
+            samples.extend(self.decoded.iter().map(|decoded| decoded[index]));
+        }
+
+        self.samples_left -= block_samples;
+        Ok(true)
+    }
+}
+
+// Returns the number of samples that can be decoded for every channel from a partial block.
+fn partial_block_samples(block_len: usize, channels: usize) -> usize {
+    (0..channels)
+        .map(|channel| match valid_len(block_len, channel, channels) {
+            0..BLOCK_HEADER_SIZE => 0,
+            len => min((len - BLOCK_HEADER_SIZE) * 2 + 1, SAMPLES_PER_BLOCK),
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+// Gathers the header and nibbles of one channel from an interleaved block.
+// Missing bytes are treated as 0.
+fn channel_block(block: &[u8], channel: usize, channels: usize) -> [u8; BLOCK_SIZE] {
+    let mut buf = [0; BLOCK_SIZE];
+
+    for (index, byte) in buf.iter_mut().enumerate() {
+        *byte = block
+            .get(source_offset(index, channel, channels))
+            .copied()
+            .unwrap_or_default();
+    }
+
+    buf
+}
+
+// Returns the number of leading bytes of a channel's part that are present in a block of `block_len` bytes.
+fn valid_len(block_len: usize, channel: usize, channels: usize) -> usize {
+    (0..BLOCK_SIZE)
+        .take_while(|&index| source_offset(index, channel, channels) < block_len)
+        .count()
+}
+
+// Maps an offset within a channel's part of a block to its offset within the interleaved block.
+fn source_offset(index: usize, channel: usize, channels: usize) -> usize {
+    if index < BLOCK_HEADER_SIZE {
+        return channel * BLOCK_HEADER_SIZE + index;
+    }
+
+    let offset = index - BLOCK_HEADER_SIZE;
+    let group = offset / NIBBLE_GROUP_SIZE;
+
+    channels * BLOCK_HEADER_SIZE
+        + (group * channels + channel) * NIBBLE_GROUP_SIZE
+        + offset % NIBBLE_GROUP_SIZE
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn decode_block(block: &[u8; BLOCK_SIZE]) -> [i16; SAMPLES_PER_BLOCK] {
+    let mut hist = i32::from(i16::from_le_bytes([block[0], block[1]]));
+    let mut step_index = i32::from(block[2]).clamp(0, 88);
+
+    let mut samples = [0; SAMPLES_PER_BLOCK];
+    samples[0] = hist as i16;
+
+    // the header sample takes the place of the final nibble, which is never decoded
+    let nibbles = block[BLOCK_HEADER_SIZE..]
+        .iter()
+        .flat_map(|byte| [byte & 0x0F, byte >> 4]);
+
+    for (sample, nibble) in samples[1..].iter_mut().zip(nibbles) {
+        (hist, step_index) = expand_nibble(nibble, hist, step_index);
+        *sample = hist as i16;
+    }
+
+    samples
+}
+
+// Decodes a single nibble, returning the new sample and step index.
+fn expand_nibble(nibble: u8, hist: i32, step_index: i32) -> (i32, i32) {
+    let step = STEP_TABLE[step_index.unsigned_abs() as usize];
+
+    let mut delta = step >> 3;
+    if nibble & 0x01 != 0 {
+        delta += step >> 2;
+    }
+    if nibble & 0x02 != 0 {
+        delta += step >> 1;
+    }
+    if nibble & 0x04 != 0 {
+        delta += step;
+    }
+    if nibble & 0x08 != 0 {
+        delta = -delta;
+    }
+
+    let sample = (hist + delta).clamp(i16::MIN.into(), i16::MAX.into());
+    let step_index = (step_index + INDEX_TABLE[usize::from(nibble & 0x0F)]).clamp(0, 88);
+
+    (sample, step_index)
+}
+
+/// Represents an error that can occur when encoding an IMA ADPCM stream.
+///
+/// See [`ImaAdpcmErrorKind`] for the different kinds of errors that can occur.
+#[derive(Debug)]
+pub struct ImaAdpcmError {
+    kind: ImaAdpcmErrorKind,
+    source: Option<ImaAdpcmErrorSource>,
+}
+
+/// A variant of an [`ImaAdpcmError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ImaAdpcmErrorKind {
+    /// The decoded stream was too large to fit in a WAVE file.
+    StreamTooLarge,
+    /// Failed to write the file header due to an underlying I/O error.
+    CreateHeader,
+    /// Failed to read a block from the stream data.
+    DecodeBlock,
+    /// Failed to encode a decoded audio sample to the writer.
+    EncodeSample,
+    /// Failed to flush the writer after encoding the entire stream.
+    FinishStream,
+}
+
+#[derive(Debug)]
+enum ImaAdpcmErrorSource {
+    Io(IoError),
+    Read(ReadError),
+}
+
+impl ImaAdpcmError {
+    fn new(kind: ImaAdpcmErrorKind) -> Self {
+        Self { kind, source: None }
+    }
+
+    fn from_io(kind: ImaAdpcmErrorKind) -> impl FnOnce(IoError) -> Self {
+        move |source| Self {
+            kind,
+            source: Some(ImaAdpcmErrorSource::Io(source)),
+        }
+    }
+
+    fn from_read(kind: ImaAdpcmErrorKind) -> impl FnOnce(ReadError) -> Self {
+        move |source| Self {
+            kind,
+            source: Some(ImaAdpcmErrorSource::Read(source)),
+        }
+    }
+
+    /// Returns the [`ImaAdpcmErrorKind`] associated with this error.
+    #[must_use]
+    pub fn kind(&self) -> ImaAdpcmErrorKind {
+        self.kind
+    }
+}
+
+impl Display for ImaAdpcmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.kind.fmt(f)
+    }
+}
+
+impl Error for ImaAdpcmError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.source {
+            Some(source) => match source {
+                ImaAdpcmErrorSource::Io(e) => Some(e),
+                ImaAdpcmErrorSource::Read(e) => Some(e),
+            },
+            None => None,
+        }
+    }
+}
+
+impl Display for ImaAdpcmErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Self::StreamTooLarge => "decoded IMA ADPCM stream was too large to encode",
+            Self::CreateHeader => "failed to encode file header",
+            Self::DecodeBlock => "failed to read block from IMA ADPCM stream",
+            Self::EncodeSample => "failed to encode sample",
+            Self::FinishStream => "failed to finalize writing IMA ADPCM stream data",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        channel_block, decode_block, encode, expand_nibble, partial_block_samples, BLOCK_SIZE,
+    };
+    use crate::{
+        encode::EncodeOptions,
+        header::StreamInfo,
+        read::{Reader, ShortReader},
+    };
+
+    fn block(hist: i16, step_index: u8, nibbles: u8) -> [u8; BLOCK_SIZE] {
+        let mut block = [nibbles; BLOCK_SIZE];
+        block[0..2].copy_from_slice(&hist.to_le_bytes());
+        block[2] = step_index;
+        block[3] = 0;
+        block
+    }
+
+    // Interleaves the blocks of each channel: headers first, then 4-byte groups of nibbles.
+    fn interleave(blocks: &[[u8; BLOCK_SIZE]]) -> Vec<u8> {
+        let mut data: Vec<u8> = blocks.iter().flat_map(|block| &block[..4]).copied().collect();
+
+        for group in (4..BLOCK_SIZE).step_by(4) {
+            for block in blocks {
+                data.extend_from_slice(&block[group..group + 4]);
+            }
+        }
+
+        data
+    }
+
+    #[test]
+    fn expand_single_nibble() {
+        assert_eq!(expand_nibble(0x0, 0, 0), (0, 0));
+        assert_eq!(expand_nibble(0x7, 0, 0), (11, 8));
+        assert_eq!(expand_nibble(0xF, 0, 0), (-11, 8));
+        assert_eq!(expand_nibble(0x7, 32760, 88), (32767, 88));
+        assert_eq!(expand_nibble(0xF, -32760, 88), (-32768, 88));
+    }
+
+    #[test]
+    fn decode_block_starts_with_header_sample() {
+        let samples = decode_block(&block(1000, 0, 0x00));
+        assert_eq!(samples[..4], [1000, 1000, 1000, 1000]);
+
+        // nibbles are decoded starting from the low nibble of each byte
+        let samples = decode_block(&block(0, 0, 0x70));
+        assert_eq!(samples[..4], [0, 0, 11, 13]);
+
+        // each block holds 64 samples: the header sample and every nibble but the final one
+        let mut last = block(0, 0, 0x00);
+        last[BLOCK_SIZE - 1] = 0x77;
+        let samples = decode_block(&last);
+        assert_eq!(samples.len(), 64);
+        assert_eq!(samples[62..], [0, 11]);
+    }
+
+    #[test]
+    fn count_partial_block_samples() {
+        assert_eq!(partial_block_samples(0, 1), 0);
+        assert_eq!(partial_block_samples(3, 1), 0);
+        assert_eq!(partial_block_samples(4, 1), 1);
+        assert_eq!(partial_block_samples(6, 1), 5);
+        assert_eq!(partial_block_samples(7, 2), 0);
+        assert_eq!(partial_block_samples(8, 2), 1);
+        // only the first channel has a complete group of nibbles
+        assert_eq!(partial_block_samples(12, 2), 1);
+        assert_eq!(partial_block_samples(16, 2), 9);
+        assert_eq!(partial_block_samples(2 * BLOCK_SIZE - 2, 2), 61);
+        assert_eq!(partial_block_samples(BLOCK_SIZE, 1), 64);
+    }
+
+    #[test]
+    fn gather_channel_blocks() {
+        let left = block(100, 1, 0x11);
+        let right = block(-100, 2, 0x22);
+        let data = interleave(&[left, right]);
+
+        assert_eq!(data[..8], [100, 0, 1, 0, 156, 255, 2, 0]);
+        assert_eq!(data[8..16], [0x11, 0x11, 0x11, 0x11, 0x22, 0x22, 0x22, 0x22]);
+        assert_eq!(channel_block(&data, 0, 2), left);
+        assert_eq!(channel_block(&data, 1, 2), right);
+    }
+
+    #[test]
+    fn encode_interleaved_channels() {
+        // a tone in the left channel only, then in the right channel only
+        let mut data = interleave(&[block(0, 0, 0x70), block(0, 0, 0x00)]);
+        data.extend(interleave(&[block(0, 0, 0x00), block(0, 0, 0x70)]));
+
+        let info = StreamInfo::dummy(2, 64 * 2, 4 * 0x24);
+        let output = encode(
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::default(),
+        )
+        .unwrap();
+        let samples: Vec<i16> = output[44..]
+            .chunks_exact(2)
+            .map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+            .collect();
+
+        let (first, second) = samples.split_at(64 * 2);
+        assert!(first.iter().step_by(2).any(|&sample| sample != 0));
+        assert!(first.iter().skip(1).step_by(2).all(|&sample| sample == 0));
+        assert!(second.iter().step_by(2).all(|&sample| sample == 0));
+        assert!(second.iter().skip(1).step_by(2).any(|&sample| sample != 0));
+        assert_eq!(first[..8], [0, 0, 0, 0, 11, 0, 13, 0]);
+    }
+
+    #[test]
+    fn encode_partial_final_block() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&block(100, 0, 0x00));
+        data.extend_from_slice(&block(200, 0, 0x00)[..6]);
+
+        let info = StreamInfo::dummy(1, 1000, 0x24 + 6);
+        let output = encode(
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::default(),
+        )
+        .unwrap();
+
+        // the sample count is capped to the amount of samples in the stream data
+        assert_eq!(output.len(), 44 + (64 + 5) * 2);
+        assert_eq!(output[44 + 64 * 2..], [200, 0, 200, 0, 200, 0, 200, 0, 200, 0]);
+    }
+
+    #[test]
+    fn encode_from_short_reads() {
+        let data = interleave(&[block(0, 0, 0x70), block(0, 0, 0x07)]);
+        let info = StreamInfo::dummy(2, 64, 2 * 0x24);
+
+        let expected = encode(
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::default(),
+        )
+        .unwrap();
+        let output = encode(
+            &info,
+            &mut Reader::new(ShortReader::new(&data)),
+            Vec::new(),
+            EncodeOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(output, expected);
+    }
+}
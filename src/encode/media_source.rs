@@ -0,0 +1,294 @@
+#[cfg(feature = "decode-vorbis")]
+use super::vorbis;
+use super::{channel_mask, encode, writes_pcm, EncodeError, EncodeOptions};
+use crate::header::{AudioFormat, StreamInfo};
+use crate::read::Reader;
+use std::io::{Cursor, Read, Result as IoResult, Seek, SeekFrom};
+#[cfg(feature = "decode-vorbis")]
+use symphonia_core::codecs::CODEC_TYPE_VORBIS;
+use symphonia_core::{
+    audio::Channels,
+    codecs::{
+        CodecParameters, CODEC_TYPE_MP3, CODEC_TYPE_PCM_F32LE, CODEC_TYPE_PCM_S16LE,
+        CODEC_TYPE_PCM_S24LE, CODEC_TYPE_PCM_S32LE, CODEC_TYPE_PCM_U8,
+    },
+    io::MediaSource,
+    probe::Hint,
+};
+
+/// A stream presented as a [`MediaSource`], which Symphonia can demux and decode without a temporary file.
+///
+/// This type is returned from [`Stream::media_source`], and requires the `symphonia` feature.
+/// The stream is written in memory to a file format that Symphonia can read:
+/// - MPEG streams are written as MP3 files, with the same fixes as when they are encoded.
+/// - Vorbis streams are rebuilt as Ogg Vorbis files from their original audio packets, without being re-encoded.
+///   This requires the `decode-vorbis` feature, which looks up the setup header of the stream.
+/// - PCM streams, and streams that are decoded to PCM samples (such as IMA ADPCM streams), are written as WAVE files.
+///
+/// Use [`StreamMediaSource::hint`] when probing the source, and [`StreamMediaSource::codec_params`] for the parameters
+/// declared by the stream header (such as the sample count, which can be compared against the decoded samples).
+/// Enable gapless playback in Symphonia's format options, so that the final packet of Vorbis streams
+/// is trimmed to the number of samples in the stream.
+///
+/// # Examples
+///
+/// ```no_run
+/// use fsbex::Stream;
+/// use std::error::Error;
+/// use symphonia::core::{
+///     formats::{FormatOptions, FormatReader},
+///     io::{MediaSourceStream, MediaSourceStreamOptions},
+///     meta::MetadataOptions,
+/// };
+///
+/// fn format_reader(stream: &Stream) -> Result<Box<dyn FormatReader>, Box<dyn Error>> {
+///     let source = stream.media_source()?;
+///     let hint = source.hint();
+///     let source = MediaSourceStream::new(Box::new(source), MediaSourceStreamOptions::default());
+///
+///     let options = FormatOptions {
+///         enable_gapless: true,
+///         ..FormatOptions::default()
+///     };
+///
+///     let probed = symphonia::default::get_probe().format(
+///         &hint,
+///         source,
+///         &options,
+///         &MetadataOptions::default(),
+///     )?;
+///
+///     Ok(probed.format)
+/// }
+/// ```
+///
+/// [`Stream::media_source`]: crate::Stream::media_source
+#[derive(Debug)]
+pub struct StreamMediaSource {
+    data: Cursor<Vec<u8>>,
+    extension: &'static str,
+    codec_params: CodecParameters,
+}
+
+impl StreamMediaSource {
+    pub(crate) fn new(
+        format: AudioFormat,
+        flags: u32,
+        index: u32,
+        info: &StreamInfo,
+        data: &[u8],
+    ) -> Result<Self, EncodeError> {
+        let mut source = Reader::new(data);
+        let options = EncodeOptions::new();
+
+        let (codec, extension, bits_per_sample) = match format {
+            AudioFormat::Mpeg => (CODEC_TYPE_MP3, "mp3", None),
+            #[cfg(feature = "decode-vorbis")]
+            AudioFormat::Vorbis => (CODEC_TYPE_VORBIS, "ogg", None),
+            AudioFormat::Pcm8 => (CODEC_TYPE_PCM_U8, "wav", Some(8)),
+            AudioFormat::Pcm24 => (CODEC_TYPE_PCM_S24LE, "wav", Some(24)),
+            AudioFormat::Pcm32 => (CODEC_TYPE_PCM_S32LE, "wav", Some(32)),
+            AudioFormat::PcmFloat => (CODEC_TYPE_PCM_F32LE, "wav", Some(32)),
+            // other streams written as PCM samples are written with 16-bit samples
+            format if writes_pcm(format, options) => (CODEC_TYPE_PCM_S16LE, "wav", Some(16)),
+            format => return Err(EncodeError::UnsupportedFormat { format }),
+        };
+
+        let output = match format {
+            #[cfg(feature = "decode-vorbis")]
+            AudioFormat::Vorbis => vorbis::rebuild(info, &mut source, Vec::new())?,
+            format => encode(format, flags, index, info, &mut source, Vec::new(), options)?,
+        };
+
+        let mut codec_params = CodecParameters::new();
+        let _params = codec_params
+            .for_codec(codec)
+            .with_sample_rate(info.sample_rate.get())
+            .with_channels(channels(info.channels.get()))
+            .with_n_frames(info.num_samples.get().into());
+
+        if let Some(bits_per_sample) = bits_per_sample {
+            let _params = codec_params.with_bits_per_sample(bits_per_sample);
+        }
+
+        Ok(Self {
+            data: Cursor::new(output),
+            extension,
+            codec_params,
+        })
+    }
+
+    /// Returns a hint with the file extension of the format that the stream was written to,
+    /// which helps Symphonia pick a format reader when probing the source.
+    #[must_use]
+    pub fn hint(&self) -> Hint {
+        let mut hint = Hint::new();
+        let _hint = hint.with_extension(self.extension);
+        hint
+    }
+
+    /// Returns the file extension of the format that the stream was written to (`mp3`, `ogg`, or `wav`).
+    #[must_use]
+    pub fn extension(&self) -> &'static str {
+        self.extension
+    }
+
+    /// Returns the codec parameters declared by the stream header: the codec, sample rate, channels,
+    /// number of sample frames, and (for PCM samples) the bit depth.
+    ///
+    /// These parameters can be used to create a decoder without probing the source,
+    /// or to check the parameters found by Symphonia's format reader.
+    #[must_use]
+    pub fn codec_params(&self) -> &CodecParameters {
+        &self.codec_params
+    }
+}
+
+// Speaker positions are the same as those of WAVE files, which Symphonia's channels also follow.
+// Streams with more channels than WAVE files have speaker positions for are given the first positions in order.
+fn channels(count: u8) -> Channels {
+    let mask = match channel_mask(count.into()) {
+        0 => (0..count).fold(0, |mask, channel| {
+            mask | 1u32.checked_shl(channel.into()).unwrap_or(0)
+        }),
+        mask => mask,
+    };
+
+    Channels::from_bits_truncate(mask)
+}
+
+impl Read for StreamMediaSource {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        self.data.read(buf)
+    }
+}
+
+impl Seek for StreamMediaSource {
+    fn seek(&mut self, pos: SeekFrom) -> IoResult<u64> {
+        self.data.seek(pos)
+    }
+}
+
+impl MediaSource for StreamMediaSource {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        Some(self.data.get_ref().len() as u64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::StreamMediaSource;
+    use crate::{
+        encode::EncodeError,
+        header::{AudioFormat, StreamInfo},
+    };
+    use symphonia::core::{
+        audio::Channels,
+        codecs::{DecoderOptions, CODEC_TYPE_PCM_S16LE},
+        errors::Error as SymphoniaError,
+        formats::{FormatOptions, FormatReader},
+        io::{MediaSourceStream, MediaSourceStreamOptions},
+        meta::MetadataOptions,
+    };
+
+    fn probe(source: StreamMediaSource) -> Box<dyn FormatReader> {
+        let hint = source.hint();
+        let source = MediaSourceStream::new(Box::new(source), MediaSourceStreamOptions::default());
+
+        symphonia::default::get_probe()
+            .format(
+                &hint,
+                source,
+                &FormatOptions {
+                    enable_gapless: true,
+                    ..FormatOptions::default()
+                },
+                &MetadataOptions::default(),
+            )
+            .unwrap()
+            .format
+    }
+
+    fn decode_frames(reader: &mut dyn FormatReader) -> usize {
+        let track = reader.default_track().unwrap();
+        let mut decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())
+            .unwrap();
+        let mut frames = 0;
+
+        loop {
+            match reader.next_packet() {
+                Ok(packet) => frames += decoder.decode(&packet).unwrap().frames(),
+                Err(SymphoniaError::IoError(_)) => break frames,
+                Err(e) => panic!("failed to read packet: {e}"),
+            }
+        }
+    }
+
+    #[test]
+    fn decode_pcm_stream() {
+        let data: Vec<_> = [1i16, -1, 2, -2, 3, -3, 4, -4]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        let info = StreamInfo::dummy(2, 4, 16);
+
+        let source = StreamMediaSource::new(AudioFormat::Pcm16, 0, 0, &info, &data).unwrap();
+        assert_eq!(source.extension(), "wav");
+
+        let params = source.codec_params();
+        assert_eq!(params.codec, CODEC_TYPE_PCM_S16LE);
+        assert_eq!(params.sample_rate, Some(44100));
+        assert_eq!(params.channels.map(Channels::count), Some(2));
+        assert_eq!(params.n_frames, Some(4));
+        assert_eq!(params.bits_per_sample, Some(16));
+
+        let mut reader = probe(source);
+        assert_eq!(decode_frames(reader.as_mut()), 4);
+    }
+
+    #[cfg(feature = "decode-vorbis")]
+    #[test]
+    fn rebuild_vorbis_stream() {
+        use symphonia::core::codecs::CODEC_TYPE_VORBIS;
+
+        // audio packets that only hold the packet type and mode number, which decode to silence,
+        // with enough packets for multiple pages so that the end of the final packet can be trimmed
+        let data = [1, 0, 0].repeat(300);
+        let info = StreamInfo {
+            vorbis_crc32: Some(0xA722_97FF),
+            ..StreamInfo::dummy(2, 38200, 900)
+        };
+
+        let source = StreamMediaSource::new(AudioFormat::Vorbis, 0, 0, &info, &data).unwrap();
+        assert_eq!(source.extension(), "ogg");
+        assert_eq!(source.codec_params().codec, CODEC_TYPE_VORBIS);
+
+        let mut reader = probe(source);
+        let params = &reader.default_track().unwrap().codec_params;
+        assert_eq!(params.codec, CODEC_TYPE_VORBIS);
+        assert_eq!(params.sample_rate, Some(44100));
+        assert_eq!(params.channels.map(Channels::count), Some(2));
+        assert_eq!(decode_frames(reader.as_mut()), 38200);
+    }
+
+    #[test]
+    fn reject_unsupported_streams() {
+        let info = StreamInfo::dummy(2, 2, 4);
+
+        for format in [
+            AudioFormat::Xma,
+            AudioFormat::Opus,
+            AudioFormat::Unknown(99),
+        ] {
+            assert!(matches!(
+                StreamMediaSource::new(format, 0, 0, &info, &[0; 4]),
+                Err(EncodeError::UnsupportedFormat { format: f }) if f == format
+            ));
+        }
+    }
+}
@@ -1,55 +1,514 @@
 //! Various types associated with encoding stream data from sound banks.
 
-use crate::header::{AudioFormat, StreamInfo};
+use crate::header::{AudioFormat, BankFlags, StreamInfo};
 use crate::read::Reader;
 use std::io::{Read, Write};
 
+mod aiff;
+mod atrac9;
+mod caf;
+mod celt;
+mod container;
 mod error;
+mod fadpcm;
+#[cfg(feature = "flac")]
+mod flac;
+mod gc_adpcm;
+mod hevag;
+mod id3;
+mod ima_adpcm;
+#[cfg(feature = "symphonia")]
+mod media_source;
 mod mpeg;
+#[cfg(feature = "decode-mpeg")]
+mod mpeg_decode;
 mod mpeg_fix;
+mod ogg;
+mod options;
+mod opus;
 mod pcm;
+mod raw;
+mod reader;
+mod samples;
+#[cfg(feature = "rodio")]
+mod source;
+mod vag;
+#[cfg(feature = "decode-vorbis")]
 mod vorbis;
+#[cfg(feature = "decode-vorbis")]
+// generated tables, kept in their original layout
+#[rustfmt::skip]
 mod vorbis_lookup;
+mod wav;
+mod xma;
+mod xwma;
 
+pub(crate) use atrac9::superframe_size as atrac9_superframe_size;
+pub use atrac9::{Atrac9Error, Atrac9ErrorKind};
+pub(crate) use celt::celt_version;
+pub use celt::{CeltError, CeltErrorKind, CeltVersion};
+pub use container::Container;
 pub use error::EncodeError;
-use pcm::{Endianness, Format};
+pub use fadpcm::{FadpcmError, FadpcmErrorKind};
+#[cfg(feature = "flac")]
+pub use flac::{FlacError, FlacErrorKind};
+pub use gc_adpcm::{GcAdpcmError, GcAdpcmErrorKind};
+pub use hevag::{HevagError, HevagErrorKind};
+pub use ima_adpcm::{ImaAdpcmError, ImaAdpcmErrorKind};
+#[cfg(feature = "symphonia")]
+pub use media_source::StreamMediaSource;
+pub use mpeg::{FormatMismatch, MpegError, MpegErrorKind, MpegStats};
+pub use mpeg_fix::{
+    copy_without_fsb_padding, remove_fsb_padding, ChannelMode, Frame, FrameError, FrameErrorKind,
+    MpegFixStats, MpegFrames, MpegVersion,
+};
+pub use options::{BextInfo, EmptyStreams, EncodeOptions, OutputMode, PcmOutput, WavContainer};
+pub use opus::{OpusError, OpusErrorKind};
+use pcm::Endianness;
 pub use pcm::{PcmError, PcmErrorKind};
+pub use raw::{RawError, RawErrorKind};
+pub use reader::StreamReader;
+pub(crate) use samples::check_decode;
+pub use samples::Samples;
+#[cfg(feature = "rodio")]
+pub use source::SampleSource;
+pub use vag::{VagError, VagErrorKind};
+#[cfg(feature = "decode-vorbis")]
 pub use vorbis::{VorbisError, VorbisErrorKind};
+pub(crate) use wav::channel_mask;
+use wav::Format;
+pub use xma::{XmaError, XmaErrorKind};
+pub use xwma::{XwmaError, XwmaErrorKind};
 
 pub(crate) fn encode<R: Read, W: Write>(
     format: AudioFormat,
     flags: u32,
+    index: u32,
     info: &StreamInfo,
     source: &mut Reader<R>,
     sink: W,
+    options: EncodeOptions<'_>,
 ) -> Result<W, EncodeError> {
+    encode_with_stats(format, flags, index, info, source, sink, options).map(|(sink, _stats)| sink)
+}
+
+// Statistics of encoding a stream, which are listed in the `ExtractionReport` of an extraction.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct EncodeStats {
+    // the statistics of removing the padding of MPEG streams, for MPEG streams whose padding was removed
+    pub(crate) mpeg_fix: Option<MpegFixStats>,
+    // the format of decoded MPEG streams, if it differs from the stream header
+    pub(crate) format_mismatch: Option<FormatMismatch>,
+    // whether signed 8-bit samples were converted to unsigned ones, which WAVE files store
+    pub(crate) converted_8_bit: bool,
+    // the number of bytes after the final complete sample frame of PCM streams, which are dropped
+    pub(crate) truncated_bytes: u32,
+}
+
+// Like `encode`, but also returns the statistics of encoding the stream.
+pub(crate) fn encode_with_stats<R: Read, W: Write>(
+    format: AudioFormat,
+    flags: u32,
+    index: u32,
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+    sink: W,
+    options: EncodeOptions<'_>,
+) -> Result<(W, EncodeStats), EncodeError> {
+    if info.size == 0 && options.empty_streams == EmptyStreams::Skip {
+        return Ok((sink, EncodeStats::default()));
+    }
+
+    if options.output_mode == OutputMode::Raw {
+        return Ok((raw::encode(info, source, sink)?, EncodeStats::default()));
+    }
+
+    check_output(format, options)?;
+
+    // placeholder streams have no data to encode, so only a file header is written
+    if info.size == 0 {
+        return Ok((encode_empty(format, info, sink, options)?, EncodeStats::default()));
+    }
+
+    #[cfg(feature = "flac")]
+    if options.pcm_output == PcmOutput::Flac {
+        let sink = flac_writer(format, info, options, sink)?;
+        let (sink, stats) = encode_format(format, flags, index, info, source, sink, options)?;
+        return Ok((sink.finish()?, stats));
+    }
+
+    encode_format(format, flags, index, info, source, sink, options)
+}
+
+fn encode_format<R: Read, W: Write>(
+    format: AudioFormat,
+    flags: u32,
+    index: u32,
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+    sink: W,
+    options: EncodeOptions<'_>,
+) -> Result<(W, EncodeStats), EncodeError> {
+    // PCM and MPEG streams return the statistics of encoding them, while other formats have none
     // method of determining sample endianness for PCM24, PCM32, and PCMFLOAT is currently unknown
-    Ok(match format {
+    let sink = match format {
         AudioFormat::Pcm8 => {
             // endianness doesn't matter when samples are 1 byte wide
-            pcm::encode::<_, _, 1>(Format::Integer, Endianness::Little, info, source, sink)?
+            return Ok(pcm::encode::<_, _, 1>(
+                Format::Integer,
+                Endianness::Little,
+                info,
+                source,
+                sink,
+                options,
+            )?);
         }
         AudioFormat::Pcm16 => {
             // determine sample endianness from flags in file header
-            let order = if flags & 0x01 == 1 {
+            let order = if BankFlags::new(flags).big_endian_pcm() {
                 Endianness::Big
             } else {
                 Endianness::Little
             };
 
-            pcm::encode::<_, _, 2>(Format::Integer, order, info, source, sink)?
+            return Ok(pcm::encode::<_, _, 2>(
+                Format::Integer,
+                order,
+                info,
+                source,
+                sink,
+                options,
+            )?);
         }
         AudioFormat::Pcm24 => {
-            pcm::encode::<_, _, 3>(Format::Integer, Endianness::Little, info, source, sink)?
+            return Ok(pcm::encode::<_, _, 3>(
+                Format::Integer,
+                Endianness::Little,
+                info,
+                source,
+                sink,
+                options,
+            )?)
         }
         AudioFormat::Pcm32 => {
-            pcm::encode::<_, _, 4>(Format::Integer, Endianness::Little, info, source, sink)?
+            return Ok(pcm::encode::<_, _, 4>(
+                Format::Integer,
+                Endianness::Little,
+                info,
+                source,
+                sink,
+                options,
+            )?)
         }
         AudioFormat::PcmFloat => {
-            pcm::encode::<_, _, 4>(Format::Float, Endianness::Little, info, source, sink)?
+            return Ok(pcm::encode::<_, _, 4>(
+                Format::Float,
+                Endianness::Little,
+                info,
+                source,
+                sink,
+                options,
+            )?)
+        }
+        AudioFormat::Mpeg => return Ok(mpeg::encode(info, index, source, sink, options)?),
+        // without an encoder, Vorbis streams can only be decoded or repackaged
+        #[cfg(not(feature = "vorbis"))]
+        AudioFormat::Vorbis if !options.decode_vorbis && !options.repackage_vorbis => {
+            return Err(EncodeError::UnsupportedFormat { format })
+        }
+        #[cfg(feature = "decode-vorbis")]
+        AudioFormat::Vorbis => vorbis::encode(info, source, sink, options)?,
+        AudioFormat::ImaAdpcm => ima_adpcm::encode(info, source, sink, options)?,
+        AudioFormat::FAdpcm => fadpcm::encode(info, source, sink, options)?,
+        AudioFormat::GcAdpcm => gc_adpcm::encode(info, source, sink, options)?,
+        AudioFormat::Xma => xma::encode(info, source, sink)?,
+        AudioFormat::Atrac9 => atrac9::encode(info, source, sink)?,
+        AudioFormat::Celt => celt::encode(info, source, sink)?,
+        AudioFormat::Opus => opus::encode(info, source, sink)?,
+        AudioFormat::HeVag => hevag::encode(info, source, sink, options)?,
+        AudioFormat::Vag => vag::encode(info, source, sink)?,
+        AudioFormat::Xwma => xwma::encode(info, source, sink)?,
+        #[cfg(not(feature = "decode-vorbis"))]
+        AudioFormat::Vorbis => return Err(EncodeError::UnsupportedFormat { format }),
+        AudioFormat::Unknown(_) => return Err(EncodeError::UnsupportedFormat { format }),
+    };
+
+    Ok((sink, EncodeStats::default()))
+}
+
+pub(crate) fn encode_layers<R: Read, W: Write, F: FnMut(usize) -> W>(
+    format: AudioFormat,
+    flags: u32,
+    index: u32,
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+    mut make_sink: F,
+    options: EncodeOptions<'_>,
+) -> Result<Vec<W>, EncodeError> {
+    if options.output_mode == OutputMode::Raw {
+        return encode(format, flags, index, info, source, make_sink(0), options)
+            .map(|sink| vec![sink]);
+    }
+
+    check_output(format, options)?;
+
+    // only MPEG streams are currently split into layers; other formats (and decoded MPEG streams,
+    // whose layers are interleaved) are encoded to a single writer
+    if format == AudioFormat::Mpeg
+        && info.size != 0
+        && options.mpeg_fix
+        && !options.decode_mpeg
+        && mpeg::layer_count(info) > 1
+    {
+        return Ok(mpeg::encode_layers(info, index, source, make_sink, options)?);
+    }
+
+    encode(format, flags, index, info, source, make_sink(0), options).map(|sink| vec![sink])
+}
+
+// Writes a file without any samples for a placeholder stream, which has no stream data.
+// Streams written as PCM samples get a file header (and metadata chunks), while files of other containers are empty.
+fn encode_empty<W: Write>(
+    format: AudioFormat,
+    info: &StreamInfo,
+    sink: W,
+    options: EncodeOptions<'_>,
+) -> Result<W, EncodeError> {
+    if !writes_pcm(format, options) {
+        return Ok(sink);
+    }
+
+    // streams that are decoded are written with 16-bit samples
+    let (sample_format, bits_per_sample) = match format {
+        AudioFormat::Pcm8 => (Format::Integer, 8),
+        AudioFormat::Pcm24 => (Format::Integer, 24),
+        AudioFormat::Pcm32 => (Format::Integer, 32),
+        AudioFormat::PcmFloat => (Format::Float, 32),
+        _ => (Format::Integer, 16),
+    };
+
+    #[cfg(feature = "flac")]
+    if options.pcm_output == PcmOutput::Flac {
+        let sink = flac::FlacWriter::new(bits_per_sample, 0, info, None, sink)?;
+        return Ok(
+            pcm::encode_empty(sample_format, bits_per_sample, info, sink, options)?.finish()?,
+        );
+    }
+
+    Ok(pcm::encode_empty(
+        sample_format,
+        bits_per_sample,
+        info,
+        sink,
+        options,
+    )?)
+}
+
+// Returns whether streams of `format` are written as PCM samples, including streams that are decoded.
+fn writes_pcm(format: AudioFormat, options: EncodeOptions<'_>) -> bool {
+    match format {
+        AudioFormat::Pcm8
+        | AudioFormat::Pcm16
+        | AudioFormat::Pcm24
+        | AudioFormat::Pcm32
+        | AudioFormat::PcmFloat
+        | AudioFormat::ImaAdpcm
+        | AudioFormat::FAdpcm
+        | AudioFormat::GcAdpcm
+        | AudioFormat::HeVag => true,
+        AudioFormat::Mpeg => options.decode_mpeg,
+        AudioFormat::Vorbis => cfg!(feature = "decode-vorbis") && options.decode_vorbis,
+        AudioFormat::Vag
+        | AudioFormat::Xma
+        | AudioFormat::Atrac9
+        | AudioFormat::Celt
+        | AudioFormat::Opus
+        | AudioFormat::Xwma
+        | AudioFormat::Unknown(_) => false,
+    }
+}
+
+// Rejects a non-default PCM output format for streams that aren't written as PCM samples, if strict mode is enabled.
+fn check_output(format: AudioFormat, options: EncodeOptions<'_>) -> Result<(), EncodeError> {
+    let writes_pcm = writes_pcm(format, options);
+
+    // FLAC files can't hold float samples or integer samples wider than 24 bits
+    #[cfg(feature = "flac")]
+    let writes_pcm = writes_pcm
+        && !(options.pcm_output == PcmOutput::Flac
+            && matches!(format, AudioFormat::Pcm32 | AudioFormat::PcmFloat));
+    #[cfg(feature = "flac")]
+    let strict = options.strict || options.pcm_output == PcmOutput::Flac;
+    #[cfg(not(feature = "flac"))]
+    let strict = options.strict;
+
+    if strict && options.pcm_output != PcmOutput::Wav && !writes_pcm {
+        return Err(EncodeError::UnsupportedOutput {
+            format,
+            output: options.pcm_output,
+        });
+    }
+
+    Ok(())
+}
+
+// Wraps `sink` in a writer that encodes the PCM samples of the stream as FLAC frames.
+// Streams that are decoded are written with 16-bit samples.
+#[cfg(feature = "flac")]
+fn flac_writer<W: Write>(
+    format: AudioFormat,
+    info: &StreamInfo,
+    options: EncodeOptions<'_>,
+    sink: W,
+) -> Result<flac::FlacWriter<W>, EncodeError> {
+    let (bits_per_sample, num_samples) = match format {
+        AudioFormat::Pcm8 | AudioFormat::Pcm16 | AudioFormat::Pcm24 => {
+            let bits_per_sample = match format {
+                AudioFormat::Pcm8 => 8,
+                AudioFormat::Pcm16 => 16,
+                _ => 24,
+            };
+            // incomplete sample frames at the end of PCM streams are dropped
+            let frame_size = u32::from(bits_per_sample / 8) * u32::from(info.channels.get());
+            (bits_per_sample, info.size / frame_size)
+        }
+        _ => (16, info.num_samples.get()),
+    };
+
+    Ok(flac::FlacWriter::new(
+        bits_per_sample,
+        num_samples,
+        info,
+        wav::SampleLoop::with_options(info, options),
+        sink,
+    )?)
+}
+
+pub(crate) fn check_mpeg<R: Read>(
+    format: AudioFormat,
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+) -> Result<Option<MpegStats>, EncodeError> {
+    if format != AudioFormat::Mpeg {
+        return Ok(None);
+    }
+
+    Ok(Some(mpeg::check(info, source)?))
+}
+
+// Reads the audio packets of a Vorbis stream without decoding them, returning the number of packets.
+#[cfg(feature = "decode-vorbis")]
+pub(crate) fn check_vorbis<R: Read>(
+    format: AudioFormat,
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+) -> Result<Option<u32>, EncodeError> {
+    if format != AudioFormat::Vorbis {
+        return Ok(None);
+    }
+
+    Ok(Some(vorbis::check(info, source)?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{encode, EmptyStreams, EncodeError, EncodeOptions, PcmOutput};
+    use crate::{
+        header::{AudioFormat, StreamInfo},
+        read::Reader,
+    };
+
+    #[test]
+    fn reject_pcm_output_for_non_pcm_stream_in_strict_mode() {
+        let data = [0; 16];
+        let info = StreamInfo::dummy(1, 16, 16);
+        let options = EncodeOptions::new().pcm_output(PcmOutput::Aiff);
+
+        assert!(encode(
+            AudioFormat::Xma,
+            0,
+            0,
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            options.strict(true),
+        )
+        .is_err_and(|e| matches!(
+            e,
+            EncodeError::UnsupportedOutput {
+                format: AudioFormat::Xma,
+                output: PcmOutput::Aiff,
+            }
+        )));
+
+        // PCM streams accept the option in strict mode
+        assert!(encode(
+            AudioFormat::Pcm16,
+            0,
+            0,
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            options.strict(true),
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn encode_placeholder_streams() {
+        let info = StreamInfo::dummy(2, 1, 0);
+        let encode_empty = |format, options| {
+            encode(
+                format,
+                0,
+                0,
+                &info,
+                &mut Reader::new([].as_slice()),
+                Vec::new(),
+                options,
+            )
+        };
+
+        // PCM streams get a WAVE file header with an empty data chunk
+        let output = encode_empty(AudioFormat::Pcm16, EncodeOptions::new()).unwrap();
+        assert_eq!(output.len(), 44);
+        assert_eq!(output[..4], *b"RIFF");
+        assert_eq!(output[40..44], 0u32.to_le_bytes());
+
+        // streams that are decoded are written with 16-bit samples
+        let output = encode_empty(AudioFormat::GcAdpcm, EncodeOptions::new()).unwrap();
+        assert_eq!(output[34..36], 16u16.to_le_bytes());
+        assert_eq!(output[40..44], 0u32.to_le_bytes());
+
+        // other containers are left empty, since they can't be written without stream data
+        for format in [AudioFormat::Mpeg, AudioFormat::Xma, AudioFormat::Vorbis] {
+            assert!(
+                encode_empty(format, EncodeOptions::new()).is_ok_and(|output| output.is_empty())
+            );
+        }
+
+        let options = EncodeOptions::new().empty_streams(EmptyStreams::Skip);
+        assert!(encode_empty(AudioFormat::Pcm16, options).is_ok_and(|output| output.is_empty()));
+    }
+
+    #[cfg(feature = "flac")]
+    #[test]
+    fn reject_flac_output_without_strict_mode() {
+        let data = [0; 16];
+        let info = StreamInfo::dummy(1, 4, 16);
+        let options = EncodeOptions::new().pcm_output(PcmOutput::Flac);
+
+        for format in [AudioFormat::Xma, AudioFormat::Pcm32, AudioFormat::PcmFloat] {
+            assert!(encode(
+                format,
+                0,
+                0,
+                &info,
+                &mut Reader::new(data.as_slice()),
+                Vec::new(),
+                options,
+            )
+            .is_err_and(|e| matches!(e, EncodeError::UnsupportedOutput { .. })));
         }
-        AudioFormat::Mpeg => mpeg::encode(info, source, sink)?,
-        AudioFormat::Vorbis => vorbis::encode(info, source, sink)?,
-        _ => return Err(EncodeError::UnsupportedFormat { format }),
-    })
+    }
 }
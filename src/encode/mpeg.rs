@@ -1,57 +1,423 @@
-use super::mpeg_fix::fix_fsb5_mpeg;
-use crate::{header::StreamInfo, read::Reader};
+#[cfg(feature = "decode-mpeg")]
+use super::mpeg_decode;
+use super::{
+    id3::Id3Tag,
+    mpeg_fix::{
+        check_fsb5_mpeg, copy_fsb5_mpeg, index_fsb5_mpeg, split_fsb5_mpeg, xing_frame, FixError,
+        FrameIndex, FrameReader, MpegFixStats,
+    },
+    EncodeOptions, EncodeStats,
+};
+use crate::{
+    header::{samples_to_duration, StreamInfo},
+    read::{ReadError, Reader},
+};
 use std::{
     error::Error,
     fmt::{Display, Formatter, Result as FmtResult},
     io::{copy, Error as IoError, Read, Write},
+    iter::zip,
+    num::NonZeroU32,
+    time::Duration,
 };
 
 /// Encodes an MPEG stream by directly copying the raw stream data to the provided sink.
 ///
 /// Unlike PCM or Vorbis, MPEG data in FSB banks is already framed/encoded and should be
-/// written verbatim without modification or header construction.
+/// written verbatim without modification. Only an ID3v2 tag and a Xing header frame are written
+/// before the frames, unless disabled.
+///
+/// Streams with more than 2 channels are made up of multiple layers, which can't be written to a single sink.
+/// These streams have to be encoded with [`encode_layers`] instead.
+///
+/// With the `decode-mpeg` feature, streams can be decoded to a WAVE file instead.
+///
+/// The sink is returned along with the [`MpegFixStats`] of removing the padding, unless the fix is disabled,
+/// and the [`FormatMismatch`] of decoded streams whose format differs from the stream header.
 pub(super) fn encode<R: Read, W: Write>(
     info: &StreamInfo,
+    index: u32,
     source: &mut Reader<R>,
     mut sink: W,
-) -> Result<W, MpegError> {
-    let stream_size = info.size.get() as usize;
+    options: EncodeOptions<'_>,
+) -> Result<(W, EncodeStats), MpegError> {
+    #[cfg(feature = "decode-mpeg")]
+    if options.decode_mpeg {
+        let (sink, stats, format_mismatch) = mpeg_decode::decode(info, source, sink, options)?;
+        let stats = EncodeStats {
+            mpeg_fix: Some(stats),
+            format_mismatch,
+            ..EncodeStats::default()
+        };
+        return Ok((sink, stats));
+    }
 
-    // Read raw MPEG bytes into a buffer (limit to stream size)
-    let mut raw = Vec::with_capacity(stream_size);
-    let _bytes_copied = copy(&mut source.limit(stream_size), &mut raw)
-        .map_err(MpegError::from_io(MpegErrorKind::EncodeStream))?;
+    // without the fix, the stream data is written as it is stored, so layers aren't split either
+    if !options.mpeg_fix {
+        let _bytes = copy(&mut source.limit(info.size.into()), &mut sink)
+            .map_err(MpegError::from_io(MpegErrorKind::EncodeStream))?;
+
+        return Ok((sink, EncodeStats::default()));
+    }
+
+    if layer_count(info) > 1 {
+        return Err(MpegError::new(MpegErrorKind::MultipleLayers));
+    }
+
+    let mut sinks = [sink];
+    let stats = write_layers(info, index, source, &mut sinks, options)?;
+
+    let [sink] = sinks;
+    let stats = EncodeStats {
+        mpeg_fix: Some(stats),
+        ..EncodeStats::default()
+    };
+    Ok((sink, stats))
+}
+
+/// Encodes an MPEG stream by writing the frames of each layer to its own sink.
+///
+/// Each layer is a standalone MPEG stream with 2 channels, except for the final layer of a stream
+/// with an odd number of channels, which has 1 channel. Sinks are created in order of layers.
+pub(super) fn encode_layers<R: Read, W: Write, F: FnMut(usize) -> W>(
+    info: &StreamInfo,
+    index: u32,
+    source: &mut Reader<R>,
+    make_sink: F,
+    options: EncodeOptions<'_>,
+) -> Result<Vec<W>, MpegError> {
+    let mut sinks: Vec<W> = (0..layer_count(info)).map(make_sink).collect();
+    let _stats = write_layers(info, index, source, &mut sinks, options)?;
+
+    Ok(sinks)
+}
+
+// Writes the repaired frames of each layer to its sink, preceded by an ID3v2 tag and a Xing header frame if enabled.
+//
+// These headers need an index of the frames, so the frames are read twice: once to index them,
+// and again to copy them after the headers. Sources that can't seek back to the start of the stream
+// are only read once, one window at a time, and the frames of each layer are kept in memory
+// until the headers are written. Returns the statistics of removing the padding.
+fn write_layers<R: Read, W: Write>(
+    info: &StreamInfo,
+    index: u32,
+    source: &mut Reader<R>,
+    sinks: &mut [W],
+    options: EncodeOptions<'_>,
+) -> Result<MpegFixStats, MpegError> {
+    let needs_index = options.id3_tag || options.xing_header;
+
+    if needs_index && !source.can_seek() {
+        let (layers, stats) =
+            split_fsb5_mpeg(source.limit(info.size.into()), sinks.len(), options.strict_mpeg)
+                .map_err(MpegError::from_fix(MpegErrorKind::EncodeStream))?;
+
+        for (frames, sink) in zip(&layers, sinks.iter_mut()) {
+            write_headers(info, index, &frames.index, sink, options)?;
+            sink.write_all(&frames.data)
+                .map_err(MpegError::from_io(MpegErrorKind::EncodeStream))?;
+        }
+
+        return Ok(stats);
+    }
+
+    if needs_index {
+        let start_pos = source.position();
+
+        let layers =
+            index_fsb5_mpeg(source.limit(info.size.into()), sinks.len(), options.strict_mpeg)
+                .map_err(MpegError::from_fix(MpegErrorKind::EncodeStream))?;
+
+        source
+            .seek_to(start_pos)
+            .map_err(MpegError::from_read(MpegErrorKind::RewindStream))?;
+
+        for (frames, sink) in zip(&layers, sinks.iter_mut()) {
+            write_headers(info, index, frames, sink, options)?;
+        }
+    }
+
+    copy_fsb5_mpeg(source.limit(info.size.into()), sinks, options.strict_mpeg)
+        .map_err(MpegError::from_fix(MpegErrorKind::EncodeStream))
+}
+
+// Encodes an MPEG stream one window of frames at a time, so that the encoded stream can be read incrementally.
+// The ID3v2 tag and Xing header frame are written when the encoder is created, since they need an index of the frames.
+pub(super) struct Chunks {
+    frames: FrameReader,
+    start_pos: u64,
+    stream_size: u64,
+}
+
+impl Chunks {
+    pub(super) fn new<R: Read>(
+        info: &StreamInfo,
+        index: u32,
+        source: &mut Reader<R>,
+        header: &mut Vec<u8>,
+        options: EncodeOptions<'_>,
+    ) -> Result<Self, MpegError> {
+        if layer_count(info) > 1 {
+            return Err(MpegError::new(MpegErrorKind::MultipleLayers));
+        }
+
+        let start_pos = source.position();
+
+        if options.id3_tag || options.xing_header {
+            let layers = index_fsb5_mpeg(source.limit(info.size.into()), 1, options.strict_mpeg)
+                .map_err(MpegError::from_fix(MpegErrorKind::EncodeStream))?;
 
-    // Apply FSB5-specific MPEG padding removal
-    let fixed = fix_fsb5_mpeg(&raw);
+            source
+                .seek_to(start_pos)
+                .map_err(MpegError::from_read(MpegErrorKind::RewindStream))?;
 
-    // Write the repaired stream
-    sink.write_all(&fixed)
+            write_headers(info, index, &layers[0], header, options)?;
+        }
+
+        Ok(Self {
+            frames: FrameReader::new(options.strict_mpeg),
+            start_pos,
+            stream_size: info.size.into(),
+        })
+    }
+
+    // Appends the frames of the next window of stream data to `chunk`, returning false at the end of the stream.
+    pub(super) fn next_chunk<R: Read>(
+        &mut self,
+        source: &mut Reader<R>,
+        chunk: &mut Vec<u8>,
+    ) -> Result<bool, MpegError> {
+        let remaining = self.stream_size - source.bytes_since(self.start_pos) as u64;
+
+        self.frames
+            .copy_window(source.limit(remaining), chunk)
+            .map_err(MpegError::from_fix(MpegErrorKind::EncodeStream))
+    }
+}
+
+// Writes an ID3v2 tag and a Xing header frame for the indexed frames, if enabled.
+fn write_headers<W: Write>(
+    info: &StreamInfo,
+    index: u32,
+    frames: &FrameIndex,
+    sink: &mut W,
+    options: EncodeOptions<'_>,
+) -> Result<(), MpegError> {
+    if options.id3_tag {
+        Id3Tag {
+            title: info.name.as_deref(),
+            // track numbers start at 1
+            track: index + 1,
+            length_ms: frames.samples * 1000 / u64::from(info.sample_rate.get()),
+        }
+        .write(&mut *sink)
+        .map_err(MpegError::from_io(MpegErrorKind::CreateHeader))?;
+    }
+
+    if options.xing_header {
+        if let Some(frame) = xing_frame(frames) {
+            sink.write_all(&frame)
+                .map_err(MpegError::from_io(MpegErrorKind::CreateHeader))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the frames of an MPEG stream without encoding them, checking the CRC-16 of every protected frame.
+pub(super) fn check<R: Read>(
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+) -> Result<MpegStats, MpegError> {
+    let mut stats = check_fsb5_mpeg(source.limit(info.size.into()))
         .map_err(MpegError::from_io(MpegErrorKind::EncodeStream))?;
 
-    Ok(sink)
+    // frames of layers are interleaved, and each layer holds every sample frame for its channels
+    stats.samples /= layer_count(info) as u64;
+    Ok(stats)
+}
+
+// Returns the number of layers that make up the stream; each layer holds up to 2 channels.
+pub(super) fn layer_count(info: &StreamInfo) -> usize {
+    usize::from(info.channels.get()).div_ceil(2)
 }
 
+/// Frame statistics of an MPEG stream, used to find damaged frames.
+///
+/// Frames are protected by a CRC-16 if the protection bit in their header is clear.
+/// Only the CRCs of Layer III frames are verified; other protected frames are counted, but never as mismatches.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MpegStats {
+    pub(super) frames: u32,
+    pub(super) protected_frames: u32,
+    pub(super) crc_mismatches: u32,
+    pub(super) skipped_free_format_headers: u32,
+    pub(super) samples: u64,
+    pub(super) fix: MpegFixStats,
+}
+
+impl MpegStats {
+    /// Returns the number of frames in the stream.
+    #[must_use]
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Returns the number of frames that are protected by a CRC-16.
+    #[must_use]
+    pub fn protected_frames(&self) -> u32 {
+        self.protected_frames
+    }
+
+    /// Returns the number of protected frames whose CRC-16 didn't match the frame data.
+    #[must_use]
+    pub fn crc_mismatches(&self) -> u32 {
+        self.crc_mismatches
+    }
+
+    /// Returns the number of free format frame headers that were skipped,
+    /// because no following header was found to measure the length of their frame.
+    /// The skipped headers are treated as invalid data, so their frames are missing from encoded streams.
+    #[must_use]
+    pub fn skipped_free_format_headers(&self) -> u32 {
+        self.skipped_free_format_headers
+    }
+
+    /// Returns the number of sample frames (samples per channel) held by the frames of the stream.
+    /// Streams with more than 2 channels are made up of layers, whose frames are counted once per sample frame.
+    ///
+    /// This is counted from the frames themselves, so it can be used to check the sample count stored in the
+    /// file header (see [`StreamInfo::sample_count`]), which includes the decoder delay and padding of the encoder.
+    ///
+    /// [`StreamInfo::sample_count`]: crate::StreamInfo::sample_count
+    #[must_use]
+    pub fn sample_count(&self) -> u64 {
+        self.samples
+    }
+
+    /// Returns the playback duration of the frames of the stream, at the given sample rate.
+    /// See [`sample_count`](Self::sample_count) for more information.
+    #[must_use]
+    pub fn duration(&self, sample_rate: NonZeroU32) -> Duration {
+        samples_to_duration(self.samples, sample_rate)
+    }
+
+    /// Returns the [`MpegFixStats`] of the stream, which tell how much of its data was padding,
+    /// and how much wasn't recognized as frames.
+    #[must_use]
+    pub fn fix_stats(&self) -> MpegFixStats {
+        self.fix
+    }
+}
+
+/// The format of a decoded MPEG stream, which differs from the format declared by its stream header.
+///
+/// MPEG streams decoded with `EncodeOptions::decode_mpeg` (which requires the `decode-mpeg` feature) are written
+/// in the format of their decoded frames. Streams whose sample rate or number of channels differs from the stream
+/// header are listed in [`ExtractionReport::format_mismatches`], since the stream header describes them wrong.
+///
+/// [`ExtractionReport::format_mismatches`]: crate::ExtractionReport::format_mismatches
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FormatMismatch {
+    pub(super) declared_sample_rate: u32,
+    pub(super) declared_channels: u16,
+    pub(super) sample_rate: u32,
+    pub(super) channels: u16,
+}
+
+impl FormatMismatch {
+    /// Returns the sample rate declared by the stream header, in Hz.
+    #[must_use]
+    pub fn declared_sample_rate(&self) -> u32 {
+        self.declared_sample_rate
+    }
+
+    /// Returns the number of channels declared by the stream header.
+    #[must_use]
+    pub fn declared_channels(&self) -> u16 {
+        self.declared_channels
+    }
+
+    /// Returns the sample rate of the decoded frames, in Hz, which the stream was written with.
+    #[must_use]
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Returns the number of channels of the decoded frames, which the stream was written with.
+    /// For streams with more than 2 channels, this is the total number of channels of their layers.
+    #[must_use]
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+}
+
+/// Represents an error that can occur when encoding an MPEG stream.
+///
 /// See [`MpegErrorKind`] for the different kinds of errors that can occur.
 #[derive(Debug)]
 pub struct MpegError {
     kind: MpegErrorKind,
-    source: IoError,
+    source: Option<MpegErrorSource>,
 }
 
-/// A variant of a [`MpegError`].
+/// A variant of an [`MpegError`].
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 #[non_exhaustive]
 pub enum MpegErrorKind {
-    /// Failed to write the ID3v2 header due to an underlying I/O error.
+    /// The stream has more than 2 channels, so it's made up of multiple layers that each need their own writer.
+    MultipleLayers,
+    /// Failed to write the ID3v2 header or the Xing header frame due to an underlying I/O error.
     CreateHeader,
     /// Failed to encode the entire stream via copying from reader to writer.
     EncodeStream,
+    /// Failed to seek back to the start of the stream data, after indexing or decoding its frames.
+    RewindStream,
+    /// The decoded stream is too large to fit in a WAVE file with 32-bit sizes.
+    StreamTooLarge,
+    /// Failed to write the decoded stream as a WAVE file due to an underlying I/O error.
+    WritePcm,
+    /// A malformed frame was found after the first frame of the stream, at the given offset
+    /// within the stream data. This is only returned if [`EncodeOptions::strict_mpeg`] is enabled.
+    ///
+    /// [`EncodeOptions::strict_mpeg`]: crate::encode::EncodeOptions::strict_mpeg
+    MalformedFrame {
+        /// The offset of the malformed frame within the stream data.
+        offset: u64,
+    },
+}
+
+#[derive(Debug)]
+enum MpegErrorSource {
+    Io(IoError),
+    Read(ReadError),
 }
 
 impl MpegError {
-    fn from_io(kind: MpegErrorKind) -> impl FnOnce(IoError) -> Self {
-        move |source| Self { kind, source }
+    pub(super) fn new(kind: MpegErrorKind) -> Self {
+        Self { kind, source: None }
+    }
+
+    pub(super) fn from_io(kind: MpegErrorKind) -> impl FnOnce(IoError) -> Self {
+        move |source| Self {
+            kind,
+            source: Some(MpegErrorSource::Io(source)),
+        }
+    }
+
+    pub(super) fn from_read(kind: MpegErrorKind) -> impl FnOnce(ReadError) -> Self {
+        move |source| Self {
+            kind,
+            source: Some(MpegErrorSource::Read(source)),
+        }
+    }
+
+    // Malformed frames get their own kind, since they aren't caused by an underlying I/O error.
+    pub(super) fn from_fix(kind: MpegErrorKind) -> impl FnOnce(FixError) -> Self {
+        move |source| match source {
+            FixError::Io(source) => Self::from_io(kind)(source),
+            FixError::Malformed(offset) => Self::new(MpegErrorKind::MalformedFrame { offset }),
+        }
     }
 
     /// Returns the [`MpegErrorKind`] associated with this error.
@@ -69,15 +435,256 @@ impl Display for MpegError {
 
 impl Error for MpegError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
-        Some(&self.source)
+        match &self.source {
+            Some(source) => match source {
+                MpegErrorSource::Io(e) => Some(e),
+                MpegErrorSource::Read(e) => Some(e),
+            },
+            None => None,
+        }
     }
 }
 
 impl Display for MpegErrorKind {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         f.write_str(match self {
-            Self::CreateHeader => "failed to encode ID3v2 header",
+            Self::MultipleLayers => {
+                "MPEG stream has multiple layers, which can't be written to one writer"
+            }
+            Self::CreateHeader => "failed to encode ID3v2 header or Xing header frame",
             Self::EncodeStream => "failed to encode full MPEG stream",
+            Self::RewindStream => "failed to seek back to the start of the MPEG stream",
+            Self::StreamTooLarge => "decoded MPEG stream is too large for a WAVE file",
+            Self::WritePcm => "failed to write decoded MPEG stream as WAVE file",
+            Self::MalformedFrame { offset } => {
+                return f.write_fmt(format_args!(
+                    "MPEG stream has a malformed frame at offset {offset}"
+                ));
+            }
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{check, encode, encode_layers, write_headers, MpegError, MpegErrorKind};
+    use crate::{
+        encode::{mpeg_fix::index_fsb5_mpeg, EncodeOptions, MpegFrames},
+        header::StreamInfo,
+        read::Reader,
+    };
+    use id3::{Tag, TagLike};
+    use std::{io::Cursor, time::Duration};
+
+    // MPEG-1 Layer III, 128 kbps, 44100 Hz, no padding
+    const FRAME_HEADER: [u8; 4] = [0xFF, 0xFB, 0x90, 0x64];
+    const FRAME_SIZE: usize = 417;
+
+    fn frame(fill: u8) -> Vec<u8> {
+        let mut frame = vec![fill; FRAME_SIZE];
+        frame[..4].copy_from_slice(&FRAME_HEADER);
+        frame
+    }
+
+    #[test]
+    fn count_samples_from_frames() {
+        // 4 channels are stored as 2 stereo layers, so each sample frame is held by 2 MPEG frames
+        let data = (0..8).flat_map(frame).collect::<Vec<_>>();
+        let info = StreamInfo::dummy(4, 1, 0xD08);
+
+        // the sample count of the file header is wrong, but the duration can still be measured from the frames
+        let stats = check(&info, &mut Reader::new(data.as_slice())).unwrap();
+        assert_eq!(stats.frames(), 8);
+        assert_eq!(stats.sample_count(), 1152 * 4);
+        assert_eq!(stats.duration(info.sample_rate()), Duration::from_nanos(104_489_795));
+    }
+
+    #[test]
+    fn split_frames_into_layers() {
+        // 3 channels are stored as a stereo layer and a mono layer, with frames padded to 4 bytes
+        let mut data = Vec::new();
+        for fill in [1, 2, 3] {
+            data.extend(frame(fill));
+            data.extend([0; 3]);
+        }
+
+        let info = StreamInfo::dummy(3, 1152 * 2, 0x4EC);
+        let options = EncodeOptions::new().id3_tag(false).xing_header(false);
+        let layers =
+            encode_layers(&info, 0, &mut Reader::new(data.as_slice()), |_| Vec::new(), options)
+                .unwrap();
+
+        assert_eq!(layers.len(), 2);
+        assert_eq!(layers[0], [frame(1), frame(3)].concat());
+        assert_eq!(layers[1], frame(2));
+
+        assert!(
+            encode(&info, 0, &mut Reader::new(data.as_slice()), Vec::new(), options)
+                .is_err_and(|e| e.kind() == MpegErrorKind::MultipleLayers)
+        );
+    }
+
+    #[test]
+    fn copy_frames_without_fix() {
+        // 3 channels are stored as a stereo layer and a mono layer, with frames padded to 4 bytes
+        let mut data = Vec::new();
+        for fill in [1, 2, 3] {
+            data.extend(frame(fill));
+            data.extend([0; 3]);
+        }
+
+        let info = StreamInfo::dummy(3, 1152 * 2, 0x4EC);
+        let options = EncodeOptions::new().mpeg_fix(false);
+
+        // the padding is kept, no headers are written, and layers aren't split
+        let (output, stats) =
+            encode(&info, 0, &mut Reader::new(data.as_slice()), Vec::new(), options).unwrap();
+        assert_eq!(output, data);
+        assert_eq!(stats.mpeg_fix, None);
+    }
+
+    #[test]
+    fn fail_on_malformed_frames_in_strict_mode() {
+        // the middle frame has a reserved bitrate index
+        let mut malformed = frame(2);
+        malformed[2] = 0xF0;
+        let data = [frame(1), malformed, frame(3)].concat();
+        let info = StreamInfo::dummy(2, 1152 * 3, 1251);
+
+        let options = EncodeOptions::new().id3_tag(false).xing_header(false);
+        let (output, _stats) =
+            encode(&info, 0, &mut Reader::new(data.as_slice()), Vec::new(), options).unwrap();
+        assert_eq!(output, [frame(1), frame(3)].concat());
+
+        let is_malformed =
+            |e: &MpegError| e.kind() == MpegErrorKind::MalformedFrame { offset: 417 };
+        for options in [options, EncodeOptions::default()] {
+            let options = options.strict_mpeg(true);
+
+            assert!(
+                encode(&info, 0, &mut Reader::new(data.as_slice()), Vec::new(), options)
+                    .is_err_and(|e| is_malformed(&e))
+            );
+            assert!(encode(
+                &info,
+                0,
+                &mut Reader::seekable(Cursor::new(data.as_slice())),
+                Vec::new(),
+                options
+            )
+            .is_err_and(|e| is_malformed(&e)));
+        }
+    }
+
+    #[test]
+    fn write_id3_tag_before_frames() {
+        let data = [frame(1), frame(2)].concat();
+        let info = StreamInfo {
+            name: Some("theme".into()),
+            ..StreamInfo::dummy(2, 1152 * 2, 834)
+        };
+
+        let (output, _stats) = encode(
+            &info,
+            4,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::default(),
+        )
+        .unwrap();
+
+        let tag = Tag::read_from2(Cursor::new(&output)).unwrap();
+        assert_eq!(tag.title(), Some("theme"));
+        assert_eq!(tag.track(), Some(5));
+        // 2 frames of 1152 samples at 44100 Hz
+        assert_eq!(tag.duration(), Some(52));
+        assert!(output.ends_with(&data));
+
+        // sources that can seek are read twice instead of being buffered, with the same output
+        let (unbuffered, _stats) = encode(
+            &info,
+            4,
+            &mut Reader::seekable(Cursor::new(data.as_slice())),
+            Vec::new(),
+            EncodeOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(unbuffered, output);
+
+        let (output, _stats) = encode(
+            &info,
+            4,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::new().id3_tag(false).xing_header(false),
+        )
+        .unwrap();
+
+        assert_eq!(output, data);
+    }
+
+    #[test]
+    fn copy_frames_like_buffered_fix() {
+        // padded frames and stray bytes that span several windows of stream data
+        let mut data = Vec::new();
+        for fill in (0..=u8::MAX).cycle().take(400) {
+            data.extend(frame(fill));
+            data.extend([0; 3]);
+            if fill % 50 == 0 {
+                data.extend([0x55; 7]);
+            }
+        }
+        let size = u32::try_from(data.len()).unwrap();
+
+        // frames used to be found in the whole of the buffered stream data, then written after the headers
+        let buffered = |info: &StreamInfo, layers: usize| {
+            let indexes = index_fsb5_mpeg(data.as_slice(), layers, false).unwrap();
+            let mut outputs = Vec::new();
+
+            for frames in &indexes {
+                let mut output = Vec::new();
+                write_headers(info, 2, frames, &mut output, EncodeOptions::default()).unwrap();
+                outputs.push(output);
+            }
+
+            let frames = MpegFrames::new(&data).map_while(Result::ok);
+            for (i, frame) in frames.enumerate() {
+                outputs[i % layers].extend_from_slice(frame.data());
+            }
+
+            outputs
+        };
+
+        let info = StreamInfo::dummy(2, 1152 * 400, size);
+        let expected = buffered(&info, 1);
+
+        let options = EncodeOptions::default();
+        let (output, _stats) =
+            encode(&info, 2, &mut Reader::new(data.as_slice()), Vec::new(), options).unwrap();
+        assert_eq!([output], expected.as_slice());
+
+        // sources that can seek are read twice, with the same output
+        let mut source = Reader::seekable(Cursor::new(data.as_slice()));
+        let (output, _stats) = encode(&info, 2, &mut source, Vec::new(), options).unwrap();
+        assert_eq!([output], expected.as_slice());
+
+        let info = StreamInfo::dummy(3, 1152 * 200, size);
+        let expected = buffered(&info, 2);
+
+        let layers = encode_layers(
+            &info,
+            2,
+            &mut Reader::new(data.as_slice()),
+            |_| Vec::new(),
+            EncodeOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(layers, expected);
+
+        let mut source = Reader::seekable(Cursor::new(data.as_slice()));
+        let layers =
+            encode_layers(&info, 2, &mut source, |_| Vec::new(), EncodeOptions::default()).unwrap();
+        assert_eq!(layers, expected);
+    }
+}
@@ -0,0 +1,407 @@
+use super::{
+    mpeg::{layer_count, FormatMismatch, MpegError, MpegErrorKind},
+    mpeg_fix::{for_each_fsb5_frame, FrameInfo, MpegFixStats},
+    wav::{WavSpec, WavWriter},
+    EncodeOptions,
+};
+use crate::{header::StreamInfo, read::Reader};
+use std::io::{Read, Result as IoResult, Write};
+use symphonia_bundle_mp3::MpaDecoder;
+use symphonia_core::{
+    audio::SampleBuffer,
+    codecs::{
+        CodecParameters, CodecType, Decoder, DecoderOptions, CODEC_TYPE_MP1, CODEC_TYPE_MP2,
+        CODEC_TYPE_MP3,
+    },
+    formats::Packet,
+};
+
+/// Decodes an MPEG stream with Symphonia's MPEG audio decoder, writing a WAVE file with 16-bit PCM samples.
+///
+/// The sample rate and number of channels of the file are taken from the decoded frames, rather than the
+/// stream header. If they differ from the stream header, the difference is returned as a [`FormatMismatch`],
+/// along with the [`MpegFixStats`] of finding the frames. Streams with more than 2 channels have the channels
+/// of their layers interleaved, so they are written to a single file.
+///
+/// The number of decoded samples isn't known until every frame is decoded, so the file is buffered in memory,
+/// unless the source can seek back to decode the stream a second time.
+pub(super) fn decode<R: Read, W: Write>(
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+    mut sink: W,
+    options: EncodeOptions<'_>,
+) -> Result<(W, MpegFixStats, Option<FormatMismatch>), MpegError> {
+    if source.can_seek() {
+        let start_pos = source.position();
+
+        let mut data_size = 0;
+        let (_stats, format) = decode_frames(info, source, options, |_format, samples| {
+            data_size += samples.len() as u64 * 2;
+            Ok(())
+        })?;
+
+        source
+            .seek_to(start_pos)
+            .map_err(MpegError::from_read(MpegErrorKind::RewindStream))?;
+
+        let spec = decode_spec(info, format, options);
+        if spec.file_size(data_size).is_none() {
+            return Err(MpegError::new(MpegErrorKind::StreamTooLarge));
+        }
+
+        spec.write_header(data_size, &mut sink)
+            .map_err(MpegError::from_io(MpegErrorKind::WritePcm))?;
+
+        let mut data = Vec::new();
+        let (stats, _format) = decode_frames(info, source, options, |_format, samples| {
+            data.clear();
+            data.extend(samples.iter().flat_map(|&sample| spec.i16_bytes(sample)));
+            sink.write_all(&data)
+        })?;
+
+        spec.write_trailer(data_size, &mut sink)
+            .map_err(MpegError::from_io(MpegErrorKind::WritePcm))?;
+
+        return Ok((sink, stats, mismatch(info, format)));
+    }
+
+    let mut writer = None;
+    let mut data = Vec::new();
+
+    let (stats, format) = decode_frames(info, source, options, |format, samples| {
+        // the format of the file is known once the first frame of every layer is decoded
+        let spec = decode_spec(info, Some(format), options);
+        let writer = writer.get_or_insert_with(|| WavWriter::buffered(spec));
+
+        data.clear();
+        data.extend(samples.iter().flat_map(|&sample| spec.i16_bytes(sample)));
+        writer.write_data(&data)
+    })?;
+
+    // streams without any frames are written as empty files, in the format of the stream header
+    writer
+        .unwrap_or_else(|| WavWriter::buffered(decode_spec(info, None, options)))
+        .finish_into(sink)
+        .map(|sink| (sink, stats, mismatch(info, format)))
+        .map_err(MpegError::from_io(MpegErrorKind::WritePcm))
+}
+
+// The sample rate and number of channels of the decoded frames.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct DecodedFormat {
+    sample_rate: u32,
+    channels: u16,
+}
+
+// Returns the format of the WAVE file that the decoded stream is written as,
+// falling back to the format of the stream header if no frames were decoded.
+fn decode_spec<'a>(
+    info: &'a StreamInfo,
+    format: Option<DecodedFormat>,
+    options: EncodeOptions<'a>,
+) -> WavSpec<'a> {
+    let format = format.unwrap_or(DecodedFormat {
+        sample_rate: info.sample_rate.get(),
+        channels: info.channels.get().into(),
+    });
+
+    // The container is chosen from the declared sample count, since the size of the decoded data isn't known yet.
+    let expected_size = u64::from(info.num_samples.get()) * u64::from(format.channels) * 2;
+    WavSpec::pcm16(format.channels, format.sample_rate).with_options(info, options, expected_size)
+}
+
+fn mismatch(info: &StreamInfo, format: Option<DecodedFormat>) -> Option<FormatMismatch> {
+    let format = format?;
+
+    let mismatch = FormatMismatch {
+        declared_sample_rate: info.sample_rate.get(),
+        declared_channels: info.channels.get().into(),
+        sample_rate: format.sample_rate,
+        channels: format.channels,
+    };
+
+    (mismatch.sample_rate != mismatch.declared_sample_rate
+        || mismatch.channels != mismatch.declared_channels)
+        .then_some(mismatch)
+}
+
+// Decodes the frames of the stream, passing the interleaved samples of each round of layers to `on_samples`,
+// along with the format of the decoded frames. Returns the statistics of finding the frames, and the format
+// of the decoded frames, if any frames were found.
+fn decode_frames<R: Read>(
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+    options: EncodeOptions<'_>,
+    mut on_samples: impl FnMut(DecodedFormat, &[i16]) -> IoResult<()>,
+) -> Result<(MpegFixStats, Option<DecodedFormat>), MpegError> {
+    let mut decoder = FrameDecoder::new(layer_count(info));
+    let mut format = None;
+    let mut samples = Vec::new();
+
+    let mut flush = |decoder: &mut FrameDecoder| {
+        samples.clear();
+        decoder.interleave(&mut samples);
+
+        if samples.is_empty() {
+            return Ok(());
+        }
+
+        let format = *format.get_or_insert_with(|| decoder.format(info));
+        on_samples(format, &samples)
+    };
+
+    let stats = for_each_fsb5_frame(
+        source.limit(info.size.into()),
+        options.strict_mpeg,
+        |frame, frame_info| {
+            if decoder.decode(frame, frame_info) {
+                flush(&mut decoder)?;
+            }
+            Ok(())
+        },
+    )
+    .map_err(MpegError::from_fix(MpegErrorKind::EncodeStream))?;
+
+    // a stream cut short can end partway through a round of layers
+    flush(&mut decoder).map_err(MpegError::from_io(MpegErrorKind::EncodeStream))?;
+
+    Ok((stats, format))
+}
+
+// Decodes the frames of every layer of a stream, each with its own decoder.
+// Layers are interleaved one frame at a time, so frames are routed to each layer in turn.
+struct FrameDecoder {
+    layers: Vec<LayerDecoder>,
+    next_layer: usize,
+}
+
+impl FrameDecoder {
+    fn new(layers: usize) -> Self {
+        Self {
+            layers: (0..layers).map(|_| LayerDecoder::default()).collect(),
+            next_layer: 0,
+        }
+    }
+
+    // Decodes the frame with the decoder of the next layer,
+    // returning whether every layer has decoded a frame since the samples were last interleaved.
+    fn decode(&mut self, frame: &[u8], info: FrameInfo) -> bool {
+        self.layers[self.next_layer].decode(frame, info);
+        self.next_layer = (self.next_layer + 1) % self.layers.len();
+        self.next_layer == 0
+    }
+
+    // Appends the samples decoded by every layer to `out`, interleaving the channels of the layers.
+    // Layers that decoded fewer samples than the others are padded with silence.
+    fn interleave(&mut self, out: &mut Vec<i16>) {
+        let frames = self.layers.iter().map(LayerDecoder::frames).max().unwrap_or(0);
+
+        for index in 0..frames {
+            for layer in &self.layers {
+                let channels = usize::from(layer.channels.unwrap_or(0));
+                let start = index * channels;
+
+                out.extend(
+                    (start..start + channels).map(|i| layer.samples.get(i).copied().unwrap_or(0)),
+                );
+            }
+        }
+
+        for layer in &mut self.layers {
+            layer.samples.clear();
+        }
+        self.next_layer = 0;
+    }
+
+    // Returns the format of the decoded frames: the sample rate of the first layer that decoded a frame,
+    // and the total number of channels of the layers.
+    fn format(&self, info: &StreamInfo) -> DecodedFormat {
+        DecodedFormat {
+            sample_rate: self
+                .layers
+                .iter()
+                .find_map(|layer| layer.sample_rate)
+                .unwrap_or(info.sample_rate.get()),
+            channels: self.layers.iter().map(|layer| layer.channels.unwrap_or(0)).sum(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct LayerDecoder {
+    decoder: Option<MpaDecoder>,
+    buffer: Option<SampleBuffer<i16>>,
+    sample_rate: Option<u32>,
+    channels: Option<u16>,
+    // the interleaved samples decoded since the samples of the layers were last interleaved
+    samples: Vec<i16>,
+}
+
+impl LayerDecoder {
+    fn decode(&mut self, frame: &[u8], info: FrameInfo) {
+        let decoder = self.decoder.get_or_insert_with(|| {
+            MpaDecoder::try_new(
+                CodecParameters::new().for_codec(codec_type(info)),
+                &DecoderOptions::default(),
+            )
+            .expect("decoder supports every MPEG audio layer")
+        });
+
+        let Ok(buf) = decoder.decode(&Packet::new_from_slice(0, 0, 0, frame)) else {
+            // Frames that can't be decoded (such as frames with a different format than the first frame
+            // decoded by the layer) are replaced with silence, so that the layers stay in sync.
+            let channels = *self.channels.get_or_insert(if info.mono { 1 } else { 2 });
+            let len = info.samples() as usize * usize::from(channels);
+            self.samples.resize(self.samples.len() + len, 0);
+            return;
+        };
+
+        let spec = *buf.spec();
+        let channels =
+            u16::try_from(spec.channels.count()).expect("MPEG audio frames have 1 or 2 channels");
+
+        self.sample_rate = self.sample_rate.or(Some(spec.rate));
+        self.channels = self.channels.or(Some(channels));
+
+        let buffer = self
+            .buffer
+            .get_or_insert_with(|| SampleBuffer::new(buf.capacity() as u64, spec));
+        buffer.copy_interleaved_ref(buf);
+        self.samples.extend_from_slice(buffer.samples());
+    }
+
+    // Returns the number of sample frames decoded since the samples were last interleaved.
+    fn frames(&self) -> usize {
+        match self.channels {
+            Some(channels) => self.samples.len() / usize::from(channels),
+            None => 0,
+        }
+    }
+}
+
+fn codec_type(info: FrameInfo) -> CodecType {
+    match info.layer {
+        1 => CODEC_TYPE_MP1,
+        2 => CODEC_TYPE_MP2,
+        _ => CODEC_TYPE_MP3,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::decode;
+    use crate::{
+        encode::{EncodeOptions, MpegErrorKind},
+        header::StreamInfo,
+        read::Reader,
+    };
+    use std::{io::Cursor, num::NonZeroU32};
+
+    // MPEG-1 Layer III, 128 kbps, 44100 Hz, joint stereo, no padding
+    const FRAME_HEADER: [u8; 4] = [0xFF, 0xFB, 0x90, 0x64];
+    const FRAME_SIZE: usize = 417;
+
+    // A frame with empty side information, which decodes to 1152 silent samples per channel.
+    fn silent_frame() -> Vec<u8> {
+        let mut frame = vec![0; FRAME_SIZE];
+        frame[..4].copy_from_slice(&FRAME_HEADER);
+        frame
+    }
+
+    fn read_u16(data: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes([data[offset], data[offset + 1]])
+    }
+
+    fn read_u32(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn decode_to_wave_file() {
+        // frames are padded to 4 bytes, like in sound banks
+        let data = [silent_frame(), vec![0; 3]].concat().repeat(3);
+        let info = StreamInfo::dummy(2, 1152 * 3, 1260);
+        let options = EncodeOptions::new().loop_chunk(false);
+
+        let (output, stats, mismatch) =
+            decode(&info, &mut Reader::new(data.as_slice()), Vec::new(), options).unwrap();
+
+        assert_eq!(output[..4], *b"RIFF");
+        assert_eq!(read_u16(&output, 22), 2);
+        assert_eq!(read_u32(&output, 24), 44100);
+        assert_eq!(read_u32(&output, 40), 1152 * 3 * 2 * 2);
+        assert!(output[44..].iter().all(|&byte| byte == 0));
+        assert_eq!(stats.frames(), 3);
+        assert_eq!(mismatch, None);
+
+        // sources that can seek are decoded twice instead of being buffered, with the same output
+        let (unbuffered, _stats, _mismatch) = decode(
+            &info,
+            &mut Reader::seekable(Cursor::new(data.as_slice())),
+            Vec::new(),
+            options,
+        )
+        .unwrap();
+        assert_eq!(unbuffered, output);
+    }
+
+    #[test]
+    fn interleave_layers() {
+        // 4 channels are stored as 2 stereo layers, whose frames alternate
+        let data = silent_frame().repeat(4);
+        let info = StreamInfo::dummy(4, 1152 * 2, 1668);
+
+        let (output, _stats, mismatch) = decode(
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::new(),
+        )
+        .unwrap();
+
+        // files with more than 2 channels have a longer `fmt ` chunk, so the `data` chunk is found by its ID
+        let data_chunk = output.windows(4).position(|id| id == b"data").unwrap();
+        assert_eq!(read_u16(&output, 22), 4);
+        assert_eq!(read_u32(&output, data_chunk + 4), 1152 * 2 * 4 * 2);
+        assert_eq!(mismatch, None);
+    }
+
+    #[test]
+    fn report_format_mismatch() {
+        let data = silent_frame();
+        let info = StreamInfo {
+            sample_rate: NonZeroU32::new(48000).unwrap(),
+            ..StreamInfo::dummy(1, 1152, 417)
+        };
+
+        let (output, _stats, mismatch) = decode(
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::new(),
+        )
+        .unwrap();
+
+        // the file is written in the format of the decoded frames
+        assert_eq!(read_u16(&output, 22), 2);
+        assert_eq!(read_u32(&output, 24), 44100);
+
+        let mismatch = mismatch.unwrap();
+        assert_eq!(mismatch.declared_sample_rate(), 48000);
+        assert_eq!(mismatch.declared_channels(), 1);
+        assert_eq!(mismatch.sample_rate(), 44100);
+        assert_eq!(mismatch.channels(), 2);
+    }
+
+    #[test]
+    fn fail_on_malformed_frames_in_strict_mode() {
+        let mut malformed = silent_frame();
+        malformed[2] = 0xF0;
+        let data = [silent_frame(), malformed, silent_frame()].concat();
+        let info = StreamInfo::dummy(2, 1152 * 3, 1251);
+        let options = EncodeOptions::new().strict_mpeg(true);
+
+        assert!(decode(&info, &mut Reader::new(data.as_slice()), Vec::new(), options)
+            .is_err_and(|e| e.kind() == MpegErrorKind::MalformedFrame { offset: 417 }));
+    }
+}
@@ -1,37 +1,948 @@
-use std::cmp::min;
-
-/// Remove FSB5-specific padding from raw MPEG data, mirroring the provided C# logic.
-/// This function scans frames, calculates their length based on MPEG header fields,
-/// copies each valid frame, and skips inter-frame padding (alignment to 4-byte boundaries)
-/// and runs of zero bytes that FSB5 may insert.
-///
-/// Behavior notes (following the C# reference):
-/// - A frame is identified by the 4-byte header beginning with 0xFF and next byte's high 4 bits == 0xF (sync).
-/// - MPEG version and layer are decoded from the header; bitrate and sample rate are resolved via tables.
-/// - Frame length is computed as:
-///   * Layer I: (12 * bitrate * 1000 / sample_rate + padding) * 4
-///   * Layer II and III: 144 * bitrate * 1000 / sample_rate + padding
-///   (This mirrors the original C# tool; it does not distinguish MPEG-2/2.5 Layer III's 72 factor.)
-/// - After each frame, if the next two bytes do not look like a header, seek to the next 4-byte-aligned
-///   offset for the next frame and skip runs of zero bytes.
-/// - Stops when remaining bytes are insufficient to read a header or full frame payload.
-pub(super) fn fix_fsb5_mpeg(input: &[u8]) -> Vec<u8> {
+use super::mpeg::MpegStats;
+use std::{
+    cmp::min,
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io::{sink, Error as IoError, ErrorKind, Read, Result as IoResult, Write},
+    iter::FusedIterator,
+};
+
+/// Removes the FSB5-specific padding from raw MPEG data, returning the frames of the data.
+///
+/// FSB5 sound banks store MPEG streams with their frames padded to a multiple of 4 bytes, which most
+/// decoders don't expect. This is done to the stream data of MPEG streams when they are encoded
+/// (unless [`EncodeOptions::mpeg_fix`] is disabled), but it also works on raw MPEG data from other sources.
+/// To read the data from a reader and write the frames to a writer instead, use [`copy_without_fsb_padding`].
+///
+/// The frames are found with [`MpegFrames`] following these rules, which only change in breaking releases:
+/// - A frame starts with a 4-byte header, whose first 12 bits are set (the sync word). Headers with
+///   a reserved layer, bitrate, or sample rate are skipped, as are MPEG-2.5 headers, whose sync word is 11 bits.
+/// - The length of a frame is computed from its header:
+///   * Layer I: `(12 * bitrate * 1000 / sample_rate + padding) * 4`
+///   * Layer II, and Layer III of MPEG-1: `144 * bitrate * 1000 / sample_rate + padding`
+///   * Layer III of MPEG-2 and 2.5: `72 * bitrate * 1000 / sample_rate + padding`
+///
+///   Free format frames (with a bitrate index of 0) don't store their bitrate, so the length of the first one
+///   is measured as the distance to the next free format header, and used for every free format frame.
+/// - If the 2 bytes after a frame aren't a sync word, the frame is followed by padding: the next frame
+///   is expected where the frame's length rounds up to a multiple of 4 bytes, after any zero bytes that follow.
+///   A frame that starts within those rounded-up bytes is skipped, since FSB5 never stores one there.
+/// - Otherwise, the next frame is searched for one byte at a time. Unlike the C# tool this is ported from,
+///   which steps back one byte and searches from there, headers found by searching are only kept if their frame
+///   is followed by another header with the same version, layer, and sample rate (or by the end of the data),
+///   so that stray sync words in padding or garbage aren't mistaken for frames.
+/// - A final frame cut short by the end of the data is left out.
+///
+/// The frames are returned along with [`MpegFixStats`], which tell how much of the data was skipped.
+///
+/// [`EncodeOptions::mpeg_fix`]: crate::encode::EncodeOptions::mpeg_fix
+#[must_use]
+pub fn remove_fsb_padding(input: &[u8]) -> (Vec<u8>, MpegFixStats) {
     let mut out = Vec::with_capacity(input.len());
-    let mut pos: usize = 0;
-    let end = input.len();
+    let mut frames = MpegFrames::new(input);
+
+    for frame in frames.by_ref().map_while(Result::ok) {
+        out.extend_from_slice(frame.data);
+    }
+
+    (out, frames.fix_stats())
+}
+
+/// Removes the FSB5-specific padding from raw MPEG data read from `source`, writing the frames to `sink`.
+/// Returns the frame statistics of the data, including whether the CRC-16 of every protected frame matches,
+/// and the [`MpegFixStats`] of removing the padding (see [`MpegStats::fix_stats`]).
+///
+/// Frames are found like with [`remove_fsb_padding`], but the data is read one window at a time,
+/// so it isn't buffered in memory. Only the CRCs of Layer III frames are verified, since the protected data
+/// of Layer I and II frames depends on their bit allocation, which would require parsing the audio data.
+///
+/// # Errors
+///
+/// This function returns an error if reading from `source` or writing to `sink` fails.
+pub fn copy_without_fsb_padding<R: Read, W: Write>(source: R, mut sink: W) -> IoResult<MpegStats> {
+    let mut stats = MpegStats::default();
+
+    let walker = walk_frames_from(source, false, |frame, info| {
+        sink.write_all(frame)?;
+
+        stats.frames += 1;
+        stats.samples += u64::from(info.samples());
+
+        if info.protected {
+            stats.protected_frames += 1;
+
+            if verify_crc(frame, info) == Some(false) {
+                stats.crc_mismatches += 1;
+            }
+        }
+
+        Ok(())
+    })?;
+
+    stats.skipped_free_format_headers = walker.skipped_free_format;
+    stats.fix = walker.stats;
+    Ok(stats)
+}
+
+/// Index the frames of raw MPEG data read from `source`, routing frames to each of `layers` layers in turn.
+/// Frames are found the same way as [`remove_fsb_padding`], but only their positions are kept,
+/// so the data can be read again and copied with [`copy_fsb5_mpeg`] without buffering the frames.
+///
+/// Streams with more than 2 channels are stored as stereo layers (the final layer is mono for odd numbers of channels).
+/// Layers are interleaved one frame at a time, so frames are routed to each layer in turn.
+pub(super) fn index_fsb5_mpeg<R: Read>(
+    source: R,
+    layers: usize,
+    strict: bool,
+) -> Result<Vec<FrameIndex>, FixError> {
+    let mut out = vec![FrameIndex::default(); layers];
+    let mut index = 0;
+
+    let _walker = walk_frames_from(source, strict, |frame, info| {
+        out[index].push(frame, info);
+        index = (index + 1) % layers;
+        Ok(())
+    })?;
+
+    Ok(out)
+}
+
+/// Copy the frames of raw MPEG data read from `source` to `sinks`, routing frames to each sink in turn.
+/// FSB5-specific padding is removed, like with [`remove_fsb_padding`].
+pub(super) fn copy_fsb5_mpeg<R: Read, W: Write>(
+    source: R,
+    sinks: &mut [W],
+    strict: bool,
+) -> Result<MpegFixStats, FixError> {
+    let mut index = 0;
+
+    let walker = walk_frames_from(source, strict, |frame, _info| {
+        sinks[index].write_all(frame)?;
+        index = (index + 1) % sinks.len();
+        Ok(())
+    })?;
+
+    Ok(walker.stats)
+}
+
+/// Counts the frames of raw MPEG data, checking the CRC-16 of every protected frame.
+/// See [`copy_without_fsb_padding`], which this delegates to without writing the frames anywhere.
+pub(super) fn check_fsb5_mpeg<R: Read>(source: R) -> IoResult<MpegStats> {
+    copy_without_fsb_padding(source, sink())
+}
+
+/// Remove FSB5-specific padding from raw MPEG data read from `source`, returning the frames of each of `layers` layers.
+/// Frames are routed to each layer in turn, like with [`index_fsb5_mpeg`], and the data is read one window at a time.
+///
+/// Unlike [`copy_fsb5_mpeg`], the frames are kept in memory, for sources that can't be read twice
+/// to write headers that need an index of the frames before the frames themselves.
+pub(super) fn split_fsb5_mpeg<R: Read>(
+    source: R,
+    layers: usize,
+    strict: bool,
+) -> Result<(Vec<FixedFrames>, MpegFixStats), FixError> {
+    let mut out = vec![FixedFrames::default(); layers];
+    let mut index = 0;
+
+    let walker = walk_frames_from(source, strict, |frame, info| {
+        out[index].push(frame, info);
+        index = (index + 1) % layers;
+        Ok(())
+    })?;
+
+    Ok((out, walker.stats))
+}
+
+/// Calls `on_frame` with each frame of raw MPEG data read from `source`, in order.
+/// FSB5-specific padding is removed, like with [`remove_fsb_padding`], and the data is read one window at a time.
+#[cfg(feature = "decode-mpeg")]
+pub(super) fn for_each_fsb5_frame<R: Read>(
+    source: R,
+    strict: bool,
+    on_frame: impl FnMut(&[u8], FrameInfo) -> IoResult<()>,
+) -> Result<MpegFixStats, FixError> {
+    walk_frames_from(source, strict, on_frame).map(|walker| walker.stats)
+}
+
+/// Frames of an MPEG stream with the FSB5-specific padding removed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(super) struct FixedFrames {
+    pub(super) data: Vec<u8>,
+    pub(super) index: FrameIndex,
+}
+
+impl FixedFrames {
+    fn push(&mut self, frame: &[u8], info: FrameInfo) {
+        self.data.extend_from_slice(frame);
+        self.index.push(frame, info);
+    }
+}
+
+/// The positions and properties of the frames of an MPEG stream, without their data.
+/// This is everything needed to build the headers written before the frames.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(super) struct FrameIndex {
+    /// The header of the first frame.
+    first_header: Option<[u8; 4]>,
+    /// The offset of every frame within the frames of the stream, in order.
+    pub(super) offsets: Vec<usize>,
+    /// The total length of the frames, in bytes.
+    len: usize,
+    /// The number of samples per channel held by the frames.
+    pub(super) samples: u64,
+    /// Whether every frame has the same bitrate as the first frame.
+    constant_bitrate: bool,
+}
+
+impl FrameIndex {
+    fn push(&mut self, frame: &[u8], info: FrameInfo) {
+        let header = [frame[0], frame[1], frame[2], frame[3]];
+
+        if let Some(first) = self.first_header {
+            self.constant_bitrate &= header[2] >> 4 == first[2] >> 4;
+        } else {
+            self.first_header = Some(header);
+            self.constant_bitrate = true;
+        }
+
+        self.offsets.push(self.len);
+        self.len += frame.len();
+        self.samples += u64::from(info.samples());
+    }
+}
+
+// The Xing header holds a 4-byte ID, 4 bytes of flags, the number of frames, the number of bytes,
+// and a 100-entry seek table.
+const XING_HEADER_SIZE: usize = 4 + 4 + 4 + 4 + 100;
+
+// Flags for the optional fields present in the Xing header: the number of frames, the number of bytes, and the seek table.
+const XING_FLAGS: u32 = 0x01 | 0x02 | 0x04;
+
+/// Build a frame holding a Xing header, which lets players find the duration of a stream and seek within it.
+/// The frame is written before the frames of the stream, which decoders treat as a silent frame.
+///
+/// Streams whose frames all have the same bitrate get an "Info" header instead of a "Xing" header.
+/// Returns `None` if there are no frames, if they aren't Layer III frames, or if the stream is larger than 4 GiB.
+pub(super) fn xing_frame(frames: &FrameIndex) -> Option<Vec<u8>> {
+    // Xing header layout taken from:
+    // [1]: https://www.codeproject.com/Articles/8295/MPEG-Audio-Frame-Header#XINGHeader
+    let header = frames.first_header?;
+
+    let mpeg_version_index = 3u8.wrapping_sub((header[1] >> 3) & 0x03);
+    let layer = 4 - i32::from((header[1] >> 1) & 0x03);
+    if layer != 3 {
+        return None;
+    }
+
+    let sample_rate =
+        get_mpeg_sample_rate(mpeg_version_index as usize, ((header[2] >> 2) & 0x03) as usize);
+    let mono = header[3] >> 6 == 0x03;
+
+    // The Xing header follows the side information, which is left empty.
+    let xing_offset = 4 + side_info_len(mpeg_version_index, mono);
+
+    // Use the lowest bitrate whose frames are long enough to hold the Xing header
+    let (bitrate_index, frame_len) = (1..15u8).find_map(|bitrate_index| {
+        let bitrate_kbps = get_mpeg_bitrate(mpeg_version_index, layer, bitrate_index.into());
+        let frame_len =
+            get_mpeg_frame_len_bytes(mpeg_version_index, layer, bitrate_kbps, sample_rate, 0)
+                .unsigned_abs() as usize;
+
+        (frame_len >= xing_offset + XING_HEADER_SIZE).then_some((bitrate_index, frame_len))
+    })?;
+
+    let num_frames = u32::try_from(frames.offsets.len()).ok()?;
+    let num_bytes = u32::try_from(frame_len + frames.len).ok()?;
+
+    let mut frame = vec![0; frame_len];
+    // Keep the version, layer, sample rate, and channel mode of the stream, but without a CRC or padding
+    frame[..4].copy_from_slice(&[
+        0xFF,
+        header[1] | 0x01,
+        (bitrate_index << 4) | (header[2] & 0x0D),
+        header[3],
+    ]);
+
+    let xing = &mut frame[xing_offset..xing_offset + XING_HEADER_SIZE];
+    xing[..4].copy_from_slice(if frames.constant_bitrate {
+        b"Info"
+    } else {
+        b"Xing"
+    });
+    xing[4..8].copy_from_slice(&XING_FLAGS.to_be_bytes());
+    xing[8..12].copy_from_slice(&num_frames.to_be_bytes());
+    xing[12..16].copy_from_slice(&num_bytes.to_be_bytes());
+
+    // Entry i of the seek table holds the position of the frame at i% of the stream's duration,
+    // as a fraction of the file size (out of 256).
+    for (i, entry) in xing[16..].iter_mut().enumerate() {
+        let offset = frame_len + frames.offsets[i * frames.offsets.len() / 100];
+        *entry = u8::try_from(offset * 256 / num_bytes as usize).unwrap_or(u8::MAX);
+    }
+
+    Some(frame)
+}
+
+/// Header information of a single MPEG frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) struct FrameInfo {
+    mpeg_version_index: u8,
+    pub(super) layer: i32,
+    /// Whether the protection bit is clear, meaning a CRC-16 follows the 4-byte header.
+    protected: bool,
+    pub(super) mono: bool,
+}
+
+impl FrameInfo {
+    /// Returns the number of samples per channel held by the frame.
+    pub(super) fn samples(self) -> u32 {
+        match self.layer {
+            1 => 384,
+            // MPEG-2/2.5 Layer III frames have 1 granule instead of 2
+            3 if self.mpeg_version_index >= 1 => 576,
+            _ => 1152,
+        }
+    }
+}
+
+/// An iterator over the frames of raw MPEG data, such as the stream data of an MPEG stream in a sound bank.
+///
+/// Frames are found the same way as when MPEG streams are encoded, so the FSB5-specific padding
+/// between frames is skipped, along with any other data that doesn't hold a frame. Frames borrow
+/// their data from the input, so nothing is copied.
+///
+/// If the final frame is cut short by the end of the data, a [`FrameError`] is returned in its place,
+/// after which the iterator ends. In [strict](Self::strict) mode, the iterator also ends with an error
+/// at the first malformed frame.
+///
+/// # Example
+///
+/// ```
+/// use fsbex::encode::MpegFrames;
+///
+/// # let data = [0xFF, 0xF3, 0x80, 0xC4].into_iter().chain([0; 204]).collect::<Vec<_>>();
+/// // `data` holds the raw stream data of an MPEG stream
+/// let mut samples = 0;
+///
+/// for frame in MpegFrames::new(&data) {
+///     samples += frame?.samples();
+/// }
+///
+/// assert_eq!(samples, 576);
+/// # Ok::<(), fsbex::encode::FrameError>(())
+/// ```
+#[derive(Debug)]
+pub struct MpegFrames<'a> {
+    input: &'a [u8],
+    pos: usize,
+    walker: FrameWalker,
+    finished: bool,
+}
+
+impl<'a> MpegFrames<'a> {
+    /// Creates an iterator over the frames of `input`, which holds raw MPEG data.
+    #[must_use]
+    pub fn new(input: &'a [u8]) -> Self {
+        Self {
+            input,
+            pos: 0,
+            walker: FrameWalker::default(),
+            finished: false,
+        }
+    }
+
+    /// Sets whether the iterator ends with a [`FrameErrorKind::Malformed`] error once data that isn't a valid frame
+    /// is found where the next frame is expected, instead of skipping data until the next frame is found.
+    /// Data before the first frame is still skipped. This is disabled by default.
+    ///
+    /// Frames are expected right after the previous frame, or after its padding. Headers with a reserved bitrate
+    /// or sample rate, or other data without a sync word, are malformed.
+    #[must_use]
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.walker.strict = strict;
+        self
+    }
+
+    /// Returns the number of free format frame headers that have been skipped so far,
+    /// because no following header was found to measure the length of their frame.
+    ///
+    /// See [`MpegStats::skipped_free_format_headers`] for more information.
+    #[must_use]
+    pub fn skipped_free_format_headers(&self) -> u32 {
+        self.walker.skipped_free_format
+    }
+
+    /// Returns the [`MpegFixStats`] of the frames and skipped data so far.
+    #[must_use]
+    pub fn fix_stats(&self) -> MpegFixStats {
+        self.walker.stats
+    }
+}
+
+impl<'a> Iterator for MpegFrames<'a> {
+    type Item = Result<Frame<'a>, FrameError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        match self.walker.find(self.input, self.pos, true) {
+            Search::Frame {
+                start,
+                len,
+                info,
+                next,
+            } => {
+                self.pos = next;
+
+                Some(Ok(Frame {
+                    data: &self.input[start..start + len],
+                    offset: start,
+                    info,
+                }))
+            }
+            Search::Truncated { start, len } => {
+                self.finished = true;
+
+                Some(Err(FrameError {
+                    kind: FrameErrorKind::Truncated,
+                    offset: start,
+                    frame_len: len,
+                    available: self.input.len() - start,
+                }))
+            }
+            Search::Malformed(start) => {
+                self.finished = true;
+
+                Some(Err(FrameError {
+                    kind: FrameErrorKind::Malformed,
+                    offset: start,
+                    frame_len: 0,
+                    available: self.input.len() - start,
+                }))
+            }
+            Search::Stopped(_) => {
+                self.finished = true;
+                None
+            }
+        }
+    }
+}
+
+impl FusedIterator for MpegFrames<'_> {}
+
+/// Statistics of removing the FSB5-specific padding from raw MPEG data, which tell how much of the data
+/// was kept as frames, and how much was skipped.
+///
+/// Every byte of the data is either part of a frame, padding, or unrecognized, so these add up to the size of the data.
+/// Streams whose padding is removed have their statistics listed in [`ExtractionReport::mpeg_fix_stats`]
+/// when they're extracted.
+///
+/// [`ExtractionReport::mpeg_fix_stats`]: crate::ExtractionReport::mpeg_fix_stats
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MpegFixStats {
+    frames: u32,
+    frame_bytes: u64,
+    padding_bytes: u64,
+    unrecognized_bytes: u64,
+    truncated: bool,
+}
+
+impl MpegFixStats {
+    /// Returns the number of frames that were kept.
+    #[must_use]
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Returns the number of bytes of the frames that were kept, which is the size of the output.
+    #[must_use]
+    pub fn frame_bytes(&self) -> u64 {
+        self.frame_bytes
+    }
+
+    /// Returns the number of bytes skipped as padding after frames: the bytes that align the next frame
+    /// to a multiple of 4 bytes, and runs of zero bytes.
+    #[must_use]
+    pub fn padding_bytes(&self) -> u64 {
+        self.padding_bytes
+    }
+
+    /// Returns the number of bytes skipped because they weren't part of a frame or padding,
+    /// including the bytes of a final frame cut short by the end of the data.
+    /// Data that is mostly intact has few of these, so many unrecognized bytes mean the data was mis-parsed or damaged.
+    #[must_use]
+    pub fn unrecognized_bytes(&self) -> u64 {
+        self.unrecognized_bytes
+    }
+
+    /// Returns whether the data ended with a frame cut short by the end of the data, which was left out.
+    #[must_use]
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+/// A frame of raw MPEG data, found with [`MpegFrames`].
+///
+/// The frame holds its 4-byte header, followed by its payload.
+/// The properties of the frame are decoded from the header when they are requested.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Frame<'a> {
+    data: &'a [u8],
+    offset: usize,
+    info: FrameInfo,
+}
+
+impl<'a> Frame<'a> {
+    /// Returns the data of the frame, including its header.
+    #[must_use]
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns the payload of the frame, which follows its 4-byte header.
+    /// For protected frames, the payload starts with the CRC-16 of the frame.
+    #[must_use]
+    pub fn payload(&self) -> &'a [u8] {
+        &self.data[4..]
+    }
+
+    /// Returns the 4-byte header of the frame.
+    #[must_use]
+    pub fn header(&self) -> [u8; 4] {
+        [self.data[0], self.data[1], self.data[2], self.data[3]]
+    }
+
+    /// Returns the offset of the frame within the data it was found in.
+    #[must_use]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the MPEG version of the frame.
+    #[must_use]
+    pub fn version(&self) -> MpegVersion {
+        match self.info.mpeg_version_index {
+            0 => MpegVersion::Mpeg1,
+            1 => MpegVersion::Mpeg2,
+            _ => MpegVersion::Mpeg2_5,
+        }
+    }
+
+    /// Returns the layer of the frame, from 1 to 3.
+    #[must_use]
+    pub fn layer(&self) -> u8 {
+        4 - ((self.data[1] >> 1) & 0x03)
+    }
+
+    /// Returns the bitrate of the frame in kbps, or `None` for free format frames, which don't store their bitrate.
+    #[must_use]
+    pub fn bitrate(&self) -> Option<u32> {
+        let bitrate_index = usize::from(self.data[2] >> 4);
+        if bitrate_index == 0 {
+            return None;
+        }
+
+        let bitrate =
+            get_mpeg_bitrate(self.info.mpeg_version_index, self.info.layer, bitrate_index);
+        u32::try_from(bitrate).ok()
+    }
+
+    /// Returns the sample rate of the frame in Hz.
+    #[must_use]
+    pub fn sample_rate(&self) -> u32 {
+        let sample_rate_index = usize::from((self.data[2] >> 2) & 0x03);
+        get_mpeg_sample_rate(self.info.mpeg_version_index.into(), sample_rate_index).unsigned_abs()
+    }
+
+    /// Returns the channel mode of the frame.
+    #[must_use]
+    pub fn channel_mode(&self) -> ChannelMode {
+        match self.data[3] >> 6 {
+            0 => ChannelMode::Stereo,
+            1 => ChannelMode::JointStereo,
+            2 => ChannelMode::DualChannel,
+            _ => ChannelMode::Mono,
+        }
+    }
+
+    /// Returns whether the frame has a padding byte, which keeps the average bitrate exact.
+    #[must_use]
+    pub fn padding(&self) -> bool {
+        self.data[2] & 0x02 != 0
+    }
+
+    /// Returns whether the frame is protected by a CRC-16, which follows its header.
+    #[must_use]
+    pub fn protected(&self) -> bool {
+        self.info.protected
+    }
+
+    /// Returns the number of samples per channel held by the frame.
+    #[must_use]
+    pub fn samples(&self) -> u32 {
+        self.info.samples()
+    }
+}
+
+/// The MPEG version of a [`Frame`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MpegVersion {
+    /// MPEG-1, with sample rates of 32000, 44100, or 48000 Hz.
+    Mpeg1,
+    /// MPEG-2, with sample rates of 16000, 22050, or 24000 Hz.
+    Mpeg2,
+    /// MPEG-2.5, with sample rates of 8000, 11025, or 12000 Hz.
+    Mpeg2_5,
+}
+
+/// The channel mode of a [`Frame`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChannelMode {
+    /// 2 independent channels.
+    Stereo,
+    /// 2 channels, which may be encoded together to save space.
+    JointStereo,
+    /// 2 unrelated mono channels.
+    DualChannel,
+    /// A single channel.
+    Mono,
+}
+
+/// Represents an error that can occur when iterating over the frames of raw MPEG data with [`MpegFrames`].
+///
+/// See [`FrameErrorKind`] for the different kinds of errors that can occur.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameError {
+    kind: FrameErrorKind,
+    offset: usize,
+    frame_len: usize,
+    available: usize,
+}
+
+/// A variant of a [`FrameError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FrameErrorKind {
+    /// The final frame is cut short by the end of the data.
+    Truncated,
+    /// Data that isn't a valid frame was found where the next frame was expected.
+    /// This is only returned in [strict](MpegFrames::strict) mode.
+    Malformed,
+}
+
+impl FrameError {
+    /// Returns the [`FrameErrorKind`] associated with this error.
+    #[must_use]
+    pub fn kind(&self) -> FrameErrorKind {
+        self.kind
+    }
+
+    /// Returns the offset of the frame's header within the data.
+    #[must_use]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the frame in bytes, as given by its header.
+    /// This is 0 for [`FrameErrorKind::Malformed`] errors, since the length of a malformed frame isn't known.
+    #[must_use]
+    pub fn frame_len(&self) -> usize {
+        self.frame_len
+    }
+
+    /// Returns the number of bytes of the frame that are left in the data.
+    #[must_use]
+    pub fn available(&self) -> usize {
+        self.available
+    }
+}
+
+impl Display for FrameError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self.kind {
+            FrameErrorKind::Truncated => f.write_fmt(format_args!(
+                "{} - frame at offset {} needs {} bytes, but only {} are left",
+                self.kind, self.offset, self.frame_len, self.available
+            )),
+            FrameErrorKind::Malformed => {
+                f.write_fmt(format_args!("{} - at offset {}", self.kind, self.offset))
+            }
+        }
+    }
+}
+
+impl Error for FrameError {}
+
+impl Display for FrameErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Self::Truncated => "MPEG frame is cut short by the end of the data",
+            Self::Malformed => "MPEG frame is malformed",
+        })
+    }
+}
+
+// Raw MPEG data is read in windows of this size, so that streams aren't buffered in memory.
+const WINDOW_SIZE: usize = 0x10000;
+
+// Frames are only searched for while at least this many bytes follow them in the window (unless the data ends),
+// which covers the longest frame, the free format search, and the alignment after a frame.
+const LOOKAHEAD: usize = MAX_FREE_FORMAT_SEARCH * 2;
+
+// Calls `on_frame` with each complete frame (header and payload) found in the data read from `source`, in order.
+// The data is read one window at a time. Returns the walker that found the frames, which holds the statistics
+// of the data that was skipped. See `FrameWalker::strict` for the meaning of `strict`.
+fn walk_frames_from<R: Read>(
+    mut source: R,
+    strict: bool,
+    mut on_frame: impl FnMut(&[u8], FrameInfo) -> IoResult<()>,
+) -> Result<FrameWalker, FixError> {
+    let mut frames = FrameReader::new(strict);
+    while frames.next_window(&mut source, &mut on_frame)? {}
 
-    while pos + 4 <= end {
+    Ok(frames.walker)
+}
+
+/// An error that stops the frames of raw MPEG data from being found.
+#[derive(Debug)]
+pub(super) enum FixError {
+    Io(IoError),
+    /// In strict mode, the frame header at this offset within the data failed validation,
+    /// or its frame was cut short by the end of the data.
+    Malformed(u64),
+}
+
+impl From<IoError> for FixError {
+    fn from(error: IoError) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<FixError> for IoError {
+    fn from(error: FixError) -> Self {
+        match error {
+            FixError::Io(error) => error,
+            FixError::Malformed(offset) => IoError::new(
+                ErrorKind::InvalidData,
+                format!("malformed MPEG frame at offset {offset}"),
+            ),
+        }
+    }
+}
+
+/// Reads raw MPEG data one window at a time, finding the frames in each window.
+/// The reader keeps the data that follows the final frame of a window, so it can be resumed with more data.
+pub(super) struct FrameReader {
+    walker: FrameWalker,
+    window: Vec<u8>,
+    finished: bool,
+}
+
+impl FrameReader {
+    /// Creates a reader that finds frames like [`MpegFrames`], or fails on malformed frames if `strict` is set.
+    pub(super) fn new(strict: bool) -> Self {
+        Self {
+            walker: FrameWalker {
+                strict,
+                ..FrameWalker::default()
+            },
+            window: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Fills the window with data from `source`, then calls `on_frame` with each complete frame found in it.
+    /// Returns `false` once the end of the data is reached, after which no more frames are found.
+    fn next_window<R: Read>(
+        &mut self,
+        source: R,
+        on_frame: impl FnMut(&[u8], FrameInfo) -> IoResult<()>,
+    ) -> Result<bool, FixError> {
+        if self.finished {
+            return Ok(false);
+        }
+
+        let space = (WINDOW_SIZE - self.window.len()) as u64;
+        let _bytes_read = source.take(space).read_to_end(&mut self.window)?;
+
+        // the window is only left partly filled at the end of the data
+        let at_end = self.window.len() < WINDOW_SIZE;
+        let end = self.walker.walk(&self.window, at_end, on_frame)?;
+
+        self.finished = at_end;
+        self.walker.offset += end as u64;
+        drop(self.window.drain(..end));
+
+        Ok(!at_end)
+    }
+
+    /// Like [`copy_fsb5_mpeg`] with a single sink, but only copies the frames of the next window of data,
+    /// returning `false` once the end of the data is reached.
+    pub(super) fn copy_window<R: Read, W: Write>(
+        &mut self,
+        source: R,
+        sink: &mut W,
+    ) -> Result<bool, FixError> {
+        self.next_window(source, |frame, _info| sink.write_all(frame))
+    }
+}
+
+// Finds frames in raw MPEG data, which can be passed in pieces.
+#[derive(Debug, Default)]
+struct FrameWalker {
+    // Free format frames don't store their bitrate, so the length of the first frame is measured and reused.
+    free_format_len: Option<usize>,
+    skipped_free_format: u32,
+    // Whether the search stopped somewhere other than where the next frame was expected,
+    // so the frame found next has to be confirmed by the header that follows it.
+    lost_sync: bool,
+    stats: MpegFixStats,
+    // In strict mode, once a frame is found, data that isn't a valid frame where the next frame is expected
+    // is malformed, instead of being skipped until the next frame is found.
+    strict: bool,
+    found_frame: bool,
+    // The offset of the input within the data, when the data is passed in pieces.
+    offset: u64,
+}
+
+// The result of searching for the next frame in raw MPEG data.
+enum Search {
+    // A complete frame of `len` bytes at `start`, after which the next frame is expected at `next`.
+    Frame {
+        start: usize,
+        len: usize,
+        info: FrameInfo,
+        next: usize,
+    },
+    // The frame of `len` bytes at `start`, where the next frame was expected, is cut short by the end of the input.
+    Truncated {
+        start: usize,
+        len: usize,
+    },
+    // In strict mode, the data at this position, where the next frame was expected, isn't a valid frame.
+    Malformed(usize),
+    // No frame was found before the search stopped at this position.
+    Stopped(usize),
+}
+
+impl FrameWalker {
+    // Calls `on_frame` with each complete frame found in the input, in order.
+    // Unless `at_end` is set, more data follows the input, so frames are only searched for while
+    // `LOOKAHEAD` bytes follow them. Returns the position where the search stopped.
+    // In strict mode, frames cut short by the end of the data are malformed too.
+    fn walk(
+        &mut self,
+        input: &[u8],
+        at_end: bool,
+        mut on_frame: impl FnMut(&[u8], FrameInfo) -> IoResult<()>,
+    ) -> Result<usize, FixError> {
+        let mut pos = 0;
+
+        loop {
+            match self.find(input, pos, at_end) {
+                Search::Frame {
+                    start,
+                    len,
+                    info,
+                    next,
+                } => {
+                    on_frame(&input[start..start + len], info)?;
+                    pos = next;
+                }
+                Search::Truncated { start, .. } | Search::Malformed(start) if self.strict => {
+                    return Err(FixError::Malformed(self.offset + start as u64));
+                }
+                Search::Truncated { start, .. } | Search::Malformed(start) => return Ok(start),
+                Search::Stopped(end) => return Ok(end),
+            }
+        }
+    }
+
+    // Searches for the next frame in the input, starting at `pos`, where the next frame is expected
+    // unless the previous search lost sync. See `walk` for the meaning of `at_end`.
+    fn find(&mut self, input: &[u8], mut pos: usize, at_end: bool) -> Search {
+        let start = pos;
+        let end = input.len();
+        // where the next frame is expected: right after the previous frame, or after its padding
+        let expected = (!self.lost_sync).then_some(pos);
+
+        while pos + 4 <= end && (at_end || pos + LOOKAHEAD <= end) {
+            let Some((frame_len, info)) = self.parse_header(input, pos) else {
+                if self.strict && self.found_frame && expected == Some(pos) {
+                    return Search::Malformed(pos);
+                }
+
+                // Not a valid header; advance by 1 and keep scanning
+                pos += 1;
+                continue;
+            };
+
+            // Ensure we have the full frame payload
+            if pos + frame_len > end {
+                if expected == Some(pos) {
+                    // Not enough data for full frame; stop
+                    self.stats.unrecognized_bytes += (end - start) as u64;
+                    self.stats.truncated = true;
+
+                    return Search::Truncated {
+                        start: pos,
+                        len: frame_len,
+                    };
+                }
+
+                // A header found by scanning can be a false header with a bogus length
+                pos += 1;
+                continue;
+            }
+
+            // Headers found by scanning (after padding that wasn't skipped, or garbage) are only trusted
+            // if another header of the same stream follows the frame, since padding can hold stray sync bytes
+            let next = next_frame_pos(input, pos + frame_len, frame_len);
+            if expected != Some(pos) && !follows_frame(input, next, input[pos + 1], input[pos + 2])
+            {
+                pos += 1;
+                continue;
+            }
+
+            self.lost_sync = false;
+            self.found_frame = true;
+            self.stats.frames += 1;
+            self.stats.frame_bytes += frame_len as u64;
+            self.stats.padding_bytes += (next - pos - frame_len) as u64;
+            self.stats.unrecognized_bytes += (pos - start) as u64;
+
+            // Advance position past the frame and its padding
+            return Search::Frame {
+                start: pos,
+                len: frame_len,
+                info,
+                next,
+            };
+        }
+
+        self.lost_sync = expected != Some(pos);
+        // at the end of the data, the bytes left are too few to hold a header
+        let skipped = if at_end { end } else { pos };
+        self.stats.unrecognized_bytes += (skipped - start) as u64;
+
+        Search::Stopped(pos)
+    }
+
+    // Returns the length and header information of the frame whose header is at `pos`,
+    // or `None` if the header isn't valid.
+    fn parse_header(&mut self, input: &[u8], pos: usize) -> Option<(usize, FrameInfo)> {
         // Read 4-byte header
         let b0 = input[pos];
         let b1 = input[pos + 1];
         let b2 = input[pos + 2];
-        let _b3 = input[pos + 3];
 
         // Validate basic sync (0xFF, next high nibble 0xF)
         if b0 != 0xFF || (b1 & 0xF0) != 0xF0 {
-            // Not a header; advance by 1 and keep scanning
-            pos += 1;
-            continue;
+            // Not a header
+            return None;
         }
 
         // Decode MPEG version as in C#:
@@ -39,65 +950,157 @@ pub(super) fn fix_fsb5_mpeg(input: &[u8]) -> Vec<u8> {
         // -> maps to { 0: MPEG1, 1: MPEG2, 2: MPEG2.5 }
         let mpeg_version_index = 3u8.wrapping_sub((b1 >> 3) & 0x03);
         // layer = 4 - ((header[1] >> 1) & 0x03) -> 1,2,3
-        let layer = 4i32 - ((b1 >> 1) & 0x03) as i32;
+        let layer = 4 - i32::from((b1 >> 1) & 0x03);
         if !(1..=3).contains(&layer) {
-            pos += 1;
-            continue;
+            return None;
         }
 
         let bitrate_index = ((b2 >> 4) & 0x0F) as usize;
         let sample_rate_index = ((b2 >> 2) & 0x03) as usize;
-        let padding = ((b2 >> 1) & 0x01) as i32;
+        let padding = i32::from((b2 >> 1) & 0x01);
 
-        // Resolve bitrate and sample rate
-        let bitrate_kbps = get_mpeg_bitrate(mpeg_version_index, layer, bitrate_index);
-        if bitrate_kbps <= 0 {
-            pos += 1;
-            continue;
-        }
+        // Resolve sample rate and bitrate
         let sample_rate = get_mpeg_sample_rate(mpeg_version_index as usize, sample_rate_index);
         if sample_rate <= 0 {
-            pos += 1;
-            continue;
+            return None;
         }
 
-        // Compute frame length in bytes
-        let frame_len = get_mpeg_frame_len_bytes(layer, bitrate_kbps, sample_rate, padding);
-        if frame_len < 4 {
+        let frame_len = if bitrate_index == 0 {
+            // Free format: measure the distance to the next header of the same stream
+            let Some(len) = self.free_format_len.or_else(|| measure_free_format_len(input, pos))
+            else {
+                // No matching header nearby; treat this header like any other invalid one
+                self.skipped_free_format += 1;
+                return None;
+            };
+
+            self.free_format_len = Some(len);
+            len + padding.unsigned_abs() as usize
+        } else {
+            let bitrate_kbps = get_mpeg_bitrate(mpeg_version_index, layer, bitrate_index);
+            if bitrate_kbps <= 0 {
+                return None;
+            }
+
+            // Compute frame length in bytes
+            let frame_len = get_mpeg_frame_len_bytes(
+                mpeg_version_index,
+                layer,
+                bitrate_kbps,
+                sample_rate,
+                padding,
+            );
+            if frame_len < 4 {
+                return None;
+            }
+            frame_len.unsigned_abs() as usize
+        };
+
+        let info = FrameInfo {
+            mpeg_version_index,
+            layer,
+            protected: b1 & 0x01 == 0,
+            mono: input[pos + 3] >> 6 == 0x03,
+        };
+
+        Some((frame_len, info))
+    }
+}
+
+// Returns the position of the next frame after a frame of `frame_len` bytes that ends at `pos`.
+// If the next 2 bytes don't look like an MPEG header, the frame is followed by padding, so the position is
+// aligned to the next 4-byte boundary (based on the frame length), and runs of zero bytes are skipped.
+fn next_frame_pos(input: &[u8], mut pos: usize, frame_len: usize) -> usize {
+    let end = input.len();
+
+    if pos + 2 <= end && !(input[pos] == 0xFF && (input[pos + 1] & 0xF0) == 0xF0) {
+        // Seek the difference between next multiple of 4 and the frame length
+        let seek = frame_len.next_multiple_of(4) - frame_len;
+        pos = min(pos + seek, end);
+
+        // Skip trailing zeros
+        while pos < end && input[pos] == 0 {
             pos += 1;
-            continue;
-        }
-        // Ensure we have the full frame payload
-        if pos + frame_len as usize > end {
-            // Not enough data for full frame; stop
-            break;
         }
+    }
 
-        // Copy header + payload
-        out.extend_from_slice(&input[pos..pos + frame_len as usize]);
+    pos
+}
 
-        // Advance position
-        pos += frame_len as usize;
+// Returns whether the header at `pos` could follow a frame whose header has the bytes `b1` and `b2`:
+// it has the same version, layer, and sample rate, and a valid bitrate index.
+// The end of the input (including a header cut short by it) also counts, since the final frame isn't followed by a header.
+fn follows_frame(input: &[u8], pos: usize, b1: u8, b2: u8) -> bool {
+    let Some(header) = input.get(pos..pos + 3) else {
+        return true;
+    };
 
-        // Peek next 2 bytes; if not looking like an MPEG header, align and skip zeros
-        if pos + 2 <= end && !(input[pos] == 0xFF && (input[pos + 1] & 0xF0) == 0xF0) {
-            // Align to next 4-byte boundary based on the frame length just processed
-            // Seek the difference between next multiple of 4 and the frame length
-            let seek = next_multiple_of_4(frame_len) - frame_len;
-            pos = min(pos + seek as usize, end);
+    header[0] == 0xFF
+        && header[1] & 0xFE == b1 & 0xFE
+        && header[2] & 0x0C == b2 & 0x0C
+        && header[2] >> 4 != 0x0F
+}
 
-            // Skip trailing zeros
-            while pos < end && input[pos] == 0 {
-                pos += 1;
-            }
-            if pos < end {
-                // Step back one byte like the C# logic
-                pos = pos.saturating_sub(1);
-            }
-        }
+// Free format frames can be at most 2880 bytes long (MPEG-1 Layer III at 640 kbps and 32000 Hz),
+// so headers further away than this aren't searched for.
+const MAX_FREE_FORMAT_SEARCH: usize = 0x1000;
+
+/// Measure the length of the free format frame at `pos`, without padding,
+/// by searching for the next free format header with the same version, layer, and sample rate.
+/// Returns `None` if no such header is found within `MAX_FREE_FORMAT_SEARCH` bytes.
+fn measure_free_format_len(input: &[u8], pos: usize) -> Option<usize> {
+    let (b1, b2) = (input[pos + 1], input[pos + 2]);
+    let padding = usize::from((b2 >> 1) & 0x01);
+    let search_end = min(pos + MAX_FREE_FORMAT_SEARCH, input.len().saturating_sub(2));
+
+    (pos + 4..search_end)
+        .find(|&next| {
+            input[next] == 0xFF
+                && input[next + 1] & 0xFE == b1 & 0xFE
+                && input[next + 2] & 0xFC == b2 & 0xFC
+        })
+        .map(|next| next - pos - padding)
+}
+
+// Returns whether the CRC-16 of a protected Layer III frame matches its header and side information,
+// or `None` if the frame's CRC can't be verified.
+fn verify_crc(frame: &[u8], info: FrameInfo) -> Option<bool> {
+    if !info.protected || info.layer != 3 {
+        return None;
     }
 
-    out
+    let expected = u16::from_be_bytes(frame.get(4..6)?.try_into().unwrap());
+    let side_info = frame.get(6..6 + side_info_len(info.mpeg_version_index, info.mono))?;
+
+    // the last 2 bytes of the header are protected along with the side information
+    Some(crc16(frame[2..4].iter().chain(side_info)) == expected)
+}
+
+// Returns the length of the side information of a Layer III frame.
+// MPEG-2/2.5 frames have half as many granules, so their side information is shorter.
+fn side_info_len(mpeg_version_index: u8, mono: bool) -> usize {
+    match (mpeg_version_index == 0, mono) {
+        (true, true) | (false, false) => 17,
+        (true, false) => 32,
+        (false, true) => 9,
+    }
+}
+
+// CRC-16 with polynomial 0x8005 and an initial value of 0xFFFF, as used by MPEG audio.
+fn crc16<'a>(data: impl IntoIterator<Item = &'a u8>) -> u16 {
+    data.into_iter().fold(0xFFFF, |mut crc, &byte| {
+        crc ^= u16::from(byte) << 8;
+
+        for _ in 0..8 {
+            crc = if crc & 0x8000 == 0 {
+                crc << 1
+            } else {
+                (crc << 1) ^ 0x8005
+            };
+        }
+
+        crc
+    })
 }
 
 // Tables ported from the C# reference code
@@ -134,7 +1137,7 @@ fn get_mpeg_bitrate(mpeg_version_index: u8, mut layer: i32, bitrate_index: usize
         layer = 2;
     }
     // Layer is 1..3, decrement to index into tables
-    let layer_idx = (layer - 1) as usize;
+    let layer_idx = layer.unsigned_abs() as usize - 1;
     match mpeg_version_index {
         0 => match layer_idx {
             0 => V1_BITRATES_L1[bitrate_index],
@@ -163,29 +1166,500 @@ fn get_mpeg_sample_rate(mpeg_version_index: usize, sample_rate_index: usize) ->
     }
 }
 
-/// Compute frame length in bytes based on layer, bitrate (kbps), sample rate (Hz), and padding.
-/// Mirrors the C# logic (Layer I has special formula; Layer II/III share the 144 factor).
+/// Compute frame length in bytes based on mpegVersion index (0:MPEG1, 1:MPEG2, 2:MPEG2.5),
+/// layer, bitrate (kbps), sample rate (Hz), and padding.
+/// Layer I has a special formula; MPEG-2/2.5 Layer III frames hold 576 samples instead of 1152, so they use the 72 factor.
 fn get_mpeg_frame_len_bytes(
+    mpeg_version_index: u8,
     layer: i32,
     bitrate_kbps: i32,
     sample_rate_hz: i32,
     padding: i32,
 ) -> i32 {
-    if layer == 1 {
+    match layer {
         // Layer I: (12 * bitrate * 1000 / sample_rate + padding) * 4
-        ((12 * bitrate_kbps * 1000) / sample_rate_hz + padding) * 4
-    } else {
+        1 => ((12 * bitrate_kbps * 1000) / sample_rate_hz + padding) * 4,
+        // MPEG-2/2.5 Layer III: 72 * bitrate * 1000 / sample_rate + padding
+        3 if mpeg_version_index >= 1 => (72 * bitrate_kbps * 1000) / sample_rate_hz + padding,
         // Layer II/III: 144 * bitrate * 1000 / sample_rate + padding
-        (144 * bitrate_kbps * 1000) / sample_rate_hz + padding
+        _ => (144 * bitrate_kbps * 1000) / sample_rate_hz + padding,
     }
 }
 
-/// Get next multiple of 4 for the given number
-fn next_multiple_of_4(n: i32) -> i32 {
-    let rem = n % 4;
-    if rem == 0 {
-        n
-    } else {
-        n + (4 - rem)
+#[cfg(test)]
+mod test {
+    use super::{
+        check_fsb5_mpeg, copy_fsb5_mpeg, copy_without_fsb_padding, crc16, get_mpeg_frame_len_bytes,
+        index_fsb5_mpeg, remove_fsb_padding, split_fsb5_mpeg, xing_frame, ChannelMode, FixError,
+        FixedFrames, FrameErrorKind, MpegFixStats, MpegFrames, MpegVersion, WINDOW_SIZE,
+    };
+    use crate::encode::mpeg::MpegStats;
+
+    // Removes the padding from the whole of the data at once with `MpegFrames`, also indexing the frames.
+    // The windowed functions are checked against this, since they should find the same frames.
+    fn fix_fsb5_mpeg(input: &[u8], strict: bool) -> Result<(FixedFrames, MpegFixStats), FixError> {
+        let mut out = FixedFrames::default();
+        let mut frames = MpegFrames::new(input).strict(strict);
+
+        for frame in frames.by_ref() {
+            match frame {
+                Ok(frame) => out.push(frame.data, frame.info),
+                Err(e) if strict => return Err(FixError::Malformed(e.offset as u64)),
+                Err(_) => break,
+            }
+        }
+
+        Ok((out, frames.fix_stats()))
+    }
+
+    // MPEG-2 Layer III, 64 kbps, 22050 Hz, mono
+    const HEADER_22050: [u8; 4] = [0xFF, 0xF3, 0x80, 0xC4];
+
+    fn frame(header: [u8; 4], len: usize, fill: u8) -> Vec<u8> {
+        let mut frame = vec![fill; len];
+        frame[..4].copy_from_slice(&header);
+        frame
+    }
+
+    #[test]
+    fn frame_lengths() {
+        // MPEG-1 Layer III, 128 kbps, 44100 Hz
+        assert_eq!(get_mpeg_frame_len_bytes(0, 3, 128, 44100, 0), 417);
+        // MPEG-2 Layer III, 64 kbps, 22050 Hz
+        assert_eq!(get_mpeg_frame_len_bytes(1, 3, 64, 22050, 0), 208);
+        assert_eq!(get_mpeg_frame_len_bytes(1, 3, 64, 22050, 1), 209);
+        // MPEG-2.5 Layer III, 32 kbps, 11025 Hz
+        assert_eq!(get_mpeg_frame_len_bytes(2, 3, 32, 11025, 0), 208);
+        // MPEG-2 Layer II keeps the 144 factor
+        assert_eq!(get_mpeg_frame_len_bytes(1, 2, 64, 22050, 0), 417);
+    }
+
+    #[test]
+    fn keep_whole_22050_hz_frames() {
+        // frames are padded to a multiple of 4 bytes
+        let mut data = frame(HEADER_22050, 208, 0x11);
+        data.extend(frame([0xFF, 0xF3, 0x82, 0xC4], 209, 0x22));
+        data.extend([0; 3]);
+        data.extend(frame(HEADER_22050, 208, 0x33));
+
+        let expected = [
+            frame(HEADER_22050, 208, 0x11),
+            frame([0xFF, 0xF3, 0x82, 0xC4], 209, 0x22),
+            frame(HEADER_22050, 208, 0x33),
+        ]
+        .concat();
+
+        let (fixed, _stats) = fix_fsb5_mpeg(&data, false).unwrap();
+        assert_eq!(fixed.data, expected);
+        assert_eq!(fixed.index.samples, 576 * 3);
+    }
+
+    #[test]
+    fn remove_padding_between_frames() {
+        // MPEG-2 Layer III, 64 kbps, 22050 Hz, mono, with a padding byte
+        const PADDED_HEADER: [u8; 4] = [0xFF, 0xF3, 0x82, 0xC4];
+
+        // data before the first frame is skipped, frames directly followed by a header aren't aligned,
+        // and runs of zeros longer than the alignment are skipped
+        let mut data = vec![0x12, 0x34];
+        data.extend(frame(PADDED_HEADER, 209, 0x11));
+        data.extend(frame(HEADER_22050, 208, 0x22));
+        data.extend([0; 7]);
+        data.extend(frame(PADDED_HEADER, 209, 0x33));
+        data.extend([0; 3]);
+        data.extend(frame(HEADER_22050, 208, 0x44));
+
+        let expected = [
+            frame(PADDED_HEADER, 209, 0x11),
+            frame(HEADER_22050, 208, 0x22),
+            frame(PADDED_HEADER, 209, 0x33),
+            frame(HEADER_22050, 208, 0x44),
+        ]
+        .concat();
+        assert_eq!(remove_fsb_padding(&data).0, expected);
+
+        let mut copied = Vec::new();
+        let stats = copy_without_fsb_padding(data.as_slice(), &mut copied).unwrap();
+        assert_eq!(copied, expected);
+        assert_eq!(stats.frames(), 4);
+        assert_eq!(stats.sample_count(), 576 * 4);
+    }
+
+    #[test]
+    fn count_skipped_bytes() {
+        // MPEG-2 Layer III, 64 kbps, 22050 Hz, mono, with a padding byte
+        const PADDED_HEADER: [u8; 4] = [0xFF, 0xF3, 0x82, 0xC4];
+
+        let mut data = vec![0x12, 0x34];
+        data.extend(frame(PADDED_HEADER, 209, 0x11));
+        data.extend(frame(HEADER_22050, 208, 0x22));
+        data.extend([0; 7]);
+        data.extend(frame(PADDED_HEADER, 209, 0x33));
+        data.extend([0; 3]);
+        data.extend(frame(HEADER_22050, 208, 0x44));
+        data.extend(&frame(HEADER_22050, 208, 0x55)[..100]);
+
+        let expected = MpegFixStats {
+            frames: 4,
+            frame_bytes: 834,
+            padding_bytes: 10,
+            unrecognized_bytes: 102,
+            truncated: true,
+        };
+
+        let (output, stats) = remove_fsb_padding(&data);
+        assert_eq!(stats, expected);
+        assert_eq!(
+            stats.frame_bytes() + stats.padding_bytes() + stats.unrecognized_bytes(),
+            data.len() as u64
+        );
+        assert_eq!(output.len() as u64, stats.frame_bytes());
+
+        let stats = copy_without_fsb_padding(data.as_slice(), Vec::new()).unwrap();
+        assert_eq!(stats.fix_stats(), expected);
+    }
+
+    #[test]
+    fn skip_frames_within_alignment() {
+        // the second frame starts 1 byte after the first frame instead of 3, so it's skipped
+        let mut data = frame([0xFF, 0xF3, 0x82, 0xC4], 209, 0x11);
+        data.push(0);
+        data.extend(frame(HEADER_22050, 208, 0x22));
+        data.extend(frame(HEADER_22050, 208, 0x33));
+
+        let expected = [
+            frame([0xFF, 0xF3, 0x82, 0xC4], 209, 0x11),
+            frame(HEADER_22050, 208, 0x33),
+        ]
+        .concat();
+        assert_eq!(remove_fsb_padding(&data).0, expected);
+    }
+
+    #[test]
+    fn skip_false_headers_in_padding() {
+        // a false header in garbage between frames, whose "frame" would end inside the next frame
+        let mut data = frame(HEADER_22050, 208, 0x11);
+        data.extend([0, 0, 0x12]);
+        data.extend(frame(HEADER_22050, 24, 0x55));
+        data.extend(frame(HEADER_22050, 208, 0x22));
+        data.extend(frame(HEADER_22050, 208, 0x33));
+
+        let expected = [
+            frame(HEADER_22050, 208, 0x11),
+            frame(HEADER_22050, 208, 0x22),
+            frame(HEADER_22050, 208, 0x33),
+        ]
+        .concat();
+
+        assert_eq!(fix_fsb5_mpeg(&data, false).unwrap().0.data, expected);
+        assert_eq!(check_fsb5_mpeg(data.as_slice()).unwrap().frames(), 3);
+
+        let mut copied = [Vec::new()];
+        let _stats = copy_fsb5_mpeg(data.as_slice(), &mut copied, false).unwrap();
+        assert_eq!(copied[0], expected);
+    }
+
+    #[test]
+    fn skip_false_headers_after_final_frame() {
+        // garbage after the final frame holds a false header, with enough data after it for a whole frame
+        let mut data = frame(HEADER_22050, 208, 0x11).repeat(2);
+        data.extend([0, 0, 0xA5, 0xFF, 0xF3, 0x80, 0xC4]);
+        data.extend([0xFF; 300]);
+
+        let expected = frame(HEADER_22050, 208, 0x11).repeat(2);
+        assert_eq!(fix_fsb5_mpeg(&data, false).unwrap().0.data, expected);
+        assert_eq!(check_fsb5_mpeg(data.as_slice()).unwrap().frames(), 2);
+
+        // frames found after garbage are still kept when they're confirmed by the next header,
+        // or when they're the final frame
+        let mut data = vec![0x12, 0xFF, 0x34];
+        data.extend(frame(HEADER_22050, 208, 0x11));
+        data.extend(frame(HEADER_22050, 208, 0x22));
+        data.extend([0xFF, 0x00, 0x56]);
+        data.extend(frame(HEADER_22050, 208, 0x33));
+
+        let expected = [
+            frame(HEADER_22050, 208, 0x11),
+            frame(HEADER_22050, 208, 0x22),
+            frame(HEADER_22050, 208, 0x33),
+        ]
+        .concat();
+        assert_eq!(fix_fsb5_mpeg(&data, false).unwrap().0.data, expected);
+    }
+
+    #[test]
+    fn iterate_frames() {
+        // MPEG-1 Layer III, 128 kbps, 44100 Hz, joint stereo
+        const HEADER_44100: [u8; 4] = [0xFF, 0xFB, 0x90, 0x64];
+        // MPEG-1 Layer III, free format, 44100 Hz, mono
+        const FREE_FORMAT_HEADER: [u8; 4] = [0xFF, 0xFB, 0x00, 0xC4];
+
+        let mut data = frame(HEADER_22050, 208, 0x11);
+        data.extend(frame([0xFF, 0xF3, 0x82, 0xC4], 209, 0x22));
+        data.extend([0; 3]);
+        data.extend(frame(HEADER_44100, 417, 0x33));
+        data.extend(frame(FREE_FORMAT_HEADER, 300, 0x44).repeat(2));
+
+        let frames = MpegFrames::new(&data).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(frames.len(), 5);
+
+        assert_eq!(frames[0].version(), MpegVersion::Mpeg2);
+        assert_eq!(frames[0].layer(), 3);
+        assert_eq!(frames[0].bitrate(), Some(64));
+        assert_eq!(frames[0].sample_rate(), 22050);
+        assert_eq!(frames[0].channel_mode(), ChannelMode::Mono);
+        assert!(!frames[0].padding());
+        assert!(!frames[0].protected());
+        assert_eq!(frames[0].samples(), 576);
+        assert_eq!(frames[0].data(), frame(HEADER_22050, 208, 0x11));
+        assert_eq!(frames[0].payload(), [0x11; 204]);
+
+        // the padding after the second frame is skipped
+        assert!(frames[1].padding());
+        assert_eq!(frames[1].data().len(), 209);
+        assert_eq!(frames[2].offset(), 420);
+
+        assert_eq!(frames[2].version(), MpegVersion::Mpeg1);
+        assert_eq!(frames[2].bitrate(), Some(128));
+        assert_eq!(frames[2].sample_rate(), 44100);
+        assert_eq!(frames[2].channel_mode(), ChannelMode::JointStereo);
+        assert_eq!(frames[2].samples(), 1152);
+
+        assert_eq!(frames[3].bitrate(), None);
+        assert_eq!(frames[4].header(), FREE_FORMAT_HEADER);
+        assert_eq!(frames[4].data().len(), 300);
+    }
+
+    #[test]
+    fn report_truncated_final_frame() {
+        let mut data = frame(HEADER_22050, 208, 0x11);
+        data.extend(&frame(HEADER_22050, 208, 0x22)[..100]);
+
+        let mut frames = MpegFrames::new(&data);
+        assert_eq!(frames.next().unwrap().unwrap().offset(), 0);
+
+        let error = frames.next().unwrap().unwrap_err();
+        assert_eq!(error.kind(), FrameErrorKind::Truncated);
+        assert_eq!(error.offset(), 208);
+        assert_eq!(error.frame_len(), 208);
+        assert_eq!(error.available(), 100);
+        assert!(frames.next().is_none());
+
+        // the truncated frame is left out when padding is removed
+        assert_eq!(
+            fix_fsb5_mpeg(&data, false).unwrap().0.data,
+            frame(HEADER_22050, 208, 0x11)
+        );
+    }
+
+    #[test]
+    fn fail_on_malformed_frames_in_strict_mode() {
+        // the middle frame has a reserved bitrate index
+        let data = [
+            frame(HEADER_22050, 208, 0x11),
+            frame([0xFF, 0xF3, 0xF0, 0xC4], 208, 0x22),
+            frame(HEADER_22050, 208, 0x33),
+        ]
+        .concat();
+
+        // lenient mode skips the malformed frame
+        let (fixed, stats) = fix_fsb5_mpeg(&data, false).unwrap();
+        let expected = [
+            frame(HEADER_22050, 208, 0x11),
+            frame(HEADER_22050, 208, 0x33),
+        ]
+        .concat();
+        assert_eq!(fixed.data, expected);
+        assert_eq!(stats.unrecognized_bytes(), 208);
+
+        assert!(matches!(fix_fsb5_mpeg(&data, true), Err(FixError::Malformed(208))));
+        assert!(matches!(
+            copy_fsb5_mpeg(data.as_slice(), &mut [Vec::new()], true),
+            Err(FixError::Malformed(208))
+        ));
+
+        let mut frames = MpegFrames::new(&data).strict(true);
+        assert_eq!(frames.next().unwrap().unwrap().offset(), 0);
+
+        let error = frames.next().unwrap().unwrap_err();
+        assert_eq!(error.kind(), FrameErrorKind::Malformed);
+        assert_eq!(error.offset(), 208);
+        assert_eq!(error.available(), 416);
+        assert!(frames.next().is_none());
+
+        // data before the first frame is still skipped
+        let data = [&[0x55; 10], &frame(HEADER_22050, 208, 0x44)[..], &data].concat();
+        assert!(matches!(fix_fsb5_mpeg(&data, true), Err(FixError::Malformed(426))));
+    }
+
+    #[test]
+    fn fail_on_malformed_frames_across_windows() {
+        let mut data = frame(HEADER_22050, 208, 0x11).repeat(400);
+        let offset = 350 * 208;
+        data[offset + 2] = 0x8C; // reserved sample rate
+        assert!(offset > WINDOW_SIZE);
+
+        let index = index_fsb5_mpeg(data.as_slice(), 1, false).unwrap();
+        assert_eq!(index[0].offsets.len(), 399);
+
+        assert!(matches!(
+            index_fsb5_mpeg(data.as_slice(), 1, true),
+            Err(FixError::Malformed(o)) if o == offset as u64
+        ));
+        assert!(matches!(
+            copy_fsb5_mpeg(data.as_slice(), &mut [Vec::new()], true),
+            Err(FixError::Malformed(o)) if o == offset as u64
+        ));
+        assert!(matches!(
+            fix_fsb5_mpeg(&data, true),
+            Err(FixError::Malformed(o)) if o == offset as u64
+        ));
+
+        // frames cut short by the end of the data are malformed too
+        let data = frame(HEADER_22050, 208, 0x11).repeat(400);
+        assert!(matches!(
+            copy_fsb5_mpeg(&data[..data.len() - 100], &mut [Vec::new()], true),
+            Err(FixError::Malformed(o)) if o == 399 * 208
+        ));
+    }
+
+    #[test]
+    fn walk_frames_across_windows() {
+        // MPEG-1 Layer III, free format, 44100 Hz, mono
+        const FREE_FORMAT_HEADER: [u8; 4] = [0xFF, 0xFB, 0x00, 0xC4];
+
+        // frames of different lengths, padding, and a free format section straddle the window boundaries
+        let mut data = Vec::new();
+        for fill in (0..=u8::MAX).cycle().take(400) {
+            data.extend(frame(HEADER_22050, 208, fill));
+            data.extend(frame([0xFF, 0xF3, 0x82, 0xC4], 209, fill));
+            data.extend([0; 3]);
+        }
+        for fill in 0..100u8 {
+            data.extend(frame(FREE_FORMAT_HEADER, 300, fill));
+        }
+        assert!(data.len() > WINDOW_SIZE * 2);
+
+        let (fixed, _stats) = fix_fsb5_mpeg(&data, false).unwrap();
+        assert_eq!(fixed.index.offsets.len(), 900);
+
+        let mut copied = [Vec::new()];
+        let _stats = copy_fsb5_mpeg(data.as_slice(), &mut copied, false).unwrap();
+        assert_eq!(copied[0], fixed.data);
+
+        let (split, _stats) = split_fsb5_mpeg(data.as_slice(), 1, false).unwrap();
+        assert_eq!(split[0], fixed);
+
+        let index = index_fsb5_mpeg(data.as_slice(), 1, false).unwrap();
+        assert_eq!(index, [fixed.index]);
+    }
+
+    #[test]
+    fn crc_check_value() {
+        assert_eq!(crc16(b"123456789"), 0xAEE7);
+    }
+
+    #[test]
+    fn verify_protected_frames() {
+        // MPEG-1 Layer III, 128 kbps, 44100 Hz, mono, protected by a CRC
+        const HEADER: [u8; 4] = [0xFF, 0xFA, 0x90, 0xC4];
+
+        let mut protected = frame(HEADER, 417, 0x00);
+        protected[6..23].copy_from_slice(&[0x5A; 17]);
+        let crc = crc16(protected[2..4].iter().chain(&protected[6..23]));
+        protected[4..6].copy_from_slice(&crc.to_be_bytes());
+
+        // damaging the side information invalidates the CRC
+        let mut damaged = protected.clone();
+        damaged[10] ^= 0xFF;
+
+        let mut data = frame([0xFF, 0xFB, 0x90, 0xC4], 417, 0x00);
+        data.extend([0; 3]);
+        data.extend(&protected);
+        data.extend([0; 3]);
+        data.extend(&damaged);
+
+        let stats = check_fsb5_mpeg(data.as_slice()).unwrap();
+        assert_eq!(stats.frames(), 3);
+        assert_eq!(stats.sample_count(), 1152 * 3);
+        assert_eq!(stats.protected_frames(), 2);
+        assert_eq!(stats.crc_mismatches(), 1);
+    }
+
+    #[test]
+    fn measure_free_format_frames() {
+        // MPEG-1 Layer III, free format, 44100 Hz, mono
+        const HEADER: [u8; 4] = [0xFF, 0xFB, 0x00, 0xC4];
+
+        let data = [
+            frame(HEADER, 300, 0x11),
+            frame(HEADER, 300, 0x22),
+            frame(HEADER, 300, 0x33),
+        ]
+        .concat();
+
+        // the length of the final frame is known from the first frame
+        assert_eq!(fix_fsb5_mpeg(&data, false).unwrap().0.data, data);
+        assert_eq!(check_fsb5_mpeg(data.as_slice()).unwrap().frames(), 3);
+
+        // a lone free format frame can't be measured
+        let data = frame(HEADER, 300, 0x11);
+        assert!(fix_fsb5_mpeg(&data, false).unwrap().0.data.is_empty());
+        assert_eq!(
+            check_fsb5_mpeg(data.as_slice()).unwrap(),
+            MpegStats {
+                skipped_free_format_headers: 1,
+                fix: MpegFixStats {
+                    unrecognized_bytes: 300,
+                    ..MpegFixStats::default()
+                },
+                ..MpegStats::default()
+            }
+        );
+    }
+
+    #[test]
+    fn build_info_frame_for_constant_bitrate() {
+        // MPEG-1 Layer III, 128 kbps, 44100 Hz, joint stereo
+        const HEADER: [u8; 4] = [0xFF, 0xFB, 0x90, 0x64];
+
+        let (fixed, _stats) = fix_fsb5_mpeg(&frame(HEADER, 417, 0x11).repeat(4), false).unwrap();
+        assert_eq!(fixed.index.offsets, [0, 417, 834, 1251]);
+
+        // 48 kbps is the lowest bitrate whose frames fit 32 bytes of side information and the Xing header
+        let xing = xing_frame(&fixed.index).unwrap();
+        assert_eq!(xing.len(), 156);
+        assert_eq!(xing[..4], [0xFF, 0xFB, 0x30, 0x64]);
+        assert_eq!(xing[36..40], *b"Info");
+        assert_eq!(xing[40..44], 7u32.to_be_bytes());
+        assert_eq!(xing[44..48], 4u32.to_be_bytes());
+        assert_eq!(xing[48..52], (156u32 + 417 * 4).to_be_bytes());
+        // the seek table points at the first frame at 0%, and the third frame at 50%
+        assert_eq!(xing[52], 21);
+        assert_eq!(xing[52 + 50], 138);
+    }
+
+    #[test]
+    fn build_xing_frame_for_variable_bitrate() {
+        let data = [
+            frame([0xFF, 0xFB, 0x90, 0x64], 417, 0x11),
+            frame([0xFF, 0xFB, 0xA0, 0x64], 522, 0x22),
+        ]
+        .concat();
+
+        let (fixed, _stats) = fix_fsb5_mpeg(&data, false).unwrap();
+        assert_eq!(fixed.index.offsets, [0, 417]);
+        assert_eq!(xing_frame(&fixed.index).unwrap()[36..40], *b"Xing");
+    }
+
+    #[test]
+    fn skip_xing_frame_for_other_layers() {
+        // MPEG-1 Layer II, 128 kbps, 44100 Hz
+        let (fixed, _stats) =
+            fix_fsb5_mpeg(&frame([0xFF, 0xFD, 0x80, 0x64], 417, 0x11), false).unwrap();
+        assert_eq!(fixed.index.offsets, [0]);
+        assert_eq!(xing_frame(&fixed.index), None);
+
+        assert_eq!(xing_frame(&fix_fsb5_mpeg(&[], false).unwrap().0.index), None);
     }
 }
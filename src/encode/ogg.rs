@@ -0,0 +1,230 @@
+use std::{
+    cmp::min,
+    io::{Error as IoError, Write},
+};
+
+// Ogg page information taken from:
+// [1]: https://www.xiph.org/ogg/doc/framing.html
+
+const MAX_SEGMENTS: usize = 255;
+
+// Pages are written once they hold at least this many bytes, so they stay reasonably small.
+const TARGET_PAGE_SIZE: usize = 0x1000;
+
+const FLAG_CONTINUED: u8 = 0x01;
+const FLAG_FIRST_PAGE: u8 = 0x02;
+const FLAG_LAST_PAGE: u8 = 0x04;
+
+// Granule position of a page on which no packet ends.
+const NO_GRANULE: u64 = u64::MAX;
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+/// Writes packets of a single logical bitstream to a sink as Ogg pages.
+pub(super) struct OggWriter<W: Write> {
+    sink: W,
+    serial: u32,
+    sequence: u32,
+    granule: u64,
+    segments: Vec<u8>,
+    data: Vec<u8>,
+    continued: bool,
+}
+
+impl<W: Write> OggWriter<W> {
+    pub(super) fn new(sink: W, serial: u32) -> Self {
+        Self {
+            sink,
+            serial,
+            sequence: 0,
+            granule: NO_GRANULE,
+            segments: Vec::with_capacity(MAX_SEGMENTS),
+            data: Vec::new(),
+            continued: false,
+        }
+    }
+
+    /// Adds a packet to the current page, writing pages as they fill up.
+    /// The granule position is the position of the last sample that can be decoded once the packet ends.
+    pub(super) fn write_packet(&mut self, packet: &[u8], granule: u64) -> Result<(), IoError> {
+        if self.data.len() >= TARGET_PAGE_SIZE {
+            self.flush_page()?;
+        }
+
+        // Packets are split into segments of 255 bytes; a shorter segment (possibly empty) ends the packet.
+        let full_segments = packet.len() / MAX_SEGMENTS;
+
+        for index in 0..=full_segments {
+            let start = index * MAX_SEGMENTS;
+            let segment = &packet[start..min(start + MAX_SEGMENTS, packet.len())];
+
+            if index == full_segments {
+                self.granule = granule;
+            }
+
+            self.push_segment(segment)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the current page, so the next packet starts on a new page.
+    pub(super) fn flush_page(&mut self) -> Result<(), IoError> {
+        if self.segments.is_empty() {
+            return Ok(());
+        }
+
+        self.write_page(0)
+    }
+
+    /// Returns a mutable reference to the sink, which every complete page has been written to.
+    #[cfg(feature = "decode-vorbis")]
+    pub(super) fn sink_mut(&mut self) -> &mut W {
+        &mut self.sink
+    }
+
+    /// Writes the final page of the bitstream, then flushes the sink.
+    pub(super) fn finish(mut self) -> Result<W, IoError> {
+        self.write_page(FLAG_LAST_PAGE)?;
+        self.sink.flush().map(|()| self.sink)
+    }
+
+    fn push_segment(&mut self, segment: &[u8]) -> Result<(), IoError> {
+        self.segments
+            .push(segment.len().try_into().expect("segment length is at most 255"));
+        self.data.extend_from_slice(segment);
+
+        if self.segments.len() == MAX_SEGMENTS {
+            self.write_page(0)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_page(&mut self, flags: u8) -> Result<(), IoError> {
+        let mut flags = flags;
+
+        if self.continued {
+            flags |= FLAG_CONTINUED;
+        }
+
+        if self.sequence == 0 {
+            flags |= FLAG_FIRST_PAGE;
+        }
+
+        let mut page = Vec::with_capacity(27 + self.segments.len() + self.data.len());
+        page.extend_from_slice(b"OggS");
+        page.push(0);
+        page.push(flags);
+        page.extend_from_slice(&self.granule.to_le_bytes());
+        page.extend_from_slice(&self.serial.to_le_bytes());
+        page.extend_from_slice(&self.sequence.to_le_bytes());
+        page.extend_from_slice(&[0; 4]);
+        page.push(self.segments.len().try_into().expect("page has at most 255 segments"));
+        page.extend_from_slice(&self.segments);
+        page.extend_from_slice(&self.data);
+
+        let crc = crc32(&page);
+        page[22..26].copy_from_slice(&crc.to_le_bytes());
+
+        self.sink.write_all(&page)?;
+
+        // a packet continues on the next page if the final segment of this page didn't end it
+        self.continued = self.segments.last() == Some(&u8::MAX);
+        self.sequence += 1;
+        self.granule = NO_GRANULE;
+        self.segments.clear();
+        self.data.clear();
+
+        Ok(())
+    }
+}
+
+// Ogg uses CRC-32 with polynomial 0x04C11DB7, no bit reflection, and an initial value of 0.
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut index = 0;
+
+    while index < 256 {
+        #[allow(clippy::cast_possible_truncation)]
+        let mut crc = (index as u32) << 24;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc = if crc & 0x8000_0000 == 0 {
+                crc << 1
+            } else {
+                (crc << 1) ^ 0x04C1_1DB7
+            };
+            bit += 1;
+        }
+
+        table[index] = crc;
+        index += 1;
+    }
+
+    table
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    data.iter().fold(0, |crc, &byte| {
+        (crc << 8) ^ CRC_TABLE[(((crc >> 24) as u8) ^ byte) as usize]
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{crc32, OggWriter};
+
+    #[test]
+    fn crc_check_value() {
+        assert_eq!(crc32(b"123456789"), 0x89A1_897F);
+    }
+
+    #[test]
+    fn write_pages() {
+        let mut writer = OggWriter::new(Vec::new(), 0x1234);
+        writer.write_packet(&[1; 3], 0).unwrap();
+        writer.flush_page().unwrap();
+        writer.write_packet(&[2; 255], 960).unwrap();
+        let output = writer.finish().unwrap();
+
+        // first page
+        assert_eq!(output[0..6], *b"OggS\x00\x02");
+        assert_eq!(output[6..14], 0u64.to_le_bytes());
+        assert_eq!(output[14..18], 0x1234u32.to_le_bytes());
+        assert_eq!(output[18..22], 0u32.to_le_bytes());
+        assert_eq!(output[26..28], [1, 3]);
+        assert_eq!(output[28..31], [1; 3]);
+
+        // final page; a packet with a length that is a multiple of 255 ends with an empty segment
+        let page = &output[31..];
+        assert_eq!(page[0..6], *b"OggS\x00\x04");
+        assert_eq!(page[6..14], 960u64.to_le_bytes());
+        assert_eq!(page[18..22], 1u32.to_le_bytes());
+        assert_eq!(page[26..29], [2, 255, 0]);
+        assert_eq!(page.len(), 29 + 255);
+
+        // CRC is calculated with the CRC field set to 0
+        let mut unchecked = page.to_vec();
+        unchecked[22..26].fill(0);
+        assert_eq!(page[22..26], crc32(&unchecked).to_le_bytes());
+    }
+
+    #[test]
+    fn continue_packet_on_next_page() {
+        let mut writer = OggWriter::new(Vec::new(), 0);
+        writer.write_packet(&vec![0; 255 * 256], 100).unwrap();
+        let output = writer.finish().unwrap();
+
+        // 255 full segments fill the first page, without ending the packet
+        assert_eq!(output[5], 0x02);
+        assert_eq!(output[6..14], u64::MAX.to_le_bytes());
+        assert_eq!(output[26], 255);
+
+        let page = &output[27 + 255 + 255 * 255..];
+        assert_eq!(page[5], 0x01 | 0x04);
+        assert_eq!(page[6..14], 100u64.to_le_bytes());
+        assert_eq!(page[26..29], [2, 255, 0]);
+    }
+}
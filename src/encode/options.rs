@@ -0,0 +1,417 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// Options that control how stream data is encoded.
+///
+/// By default, streams are written in a container for their original format wherever possible,
+/// without decoding the audio data. WAVE files include the loop points of looping streams,
+/// but no other metadata. MP3 files start with an ID3v2 tag and a Xing header frame.
+///
+/// # Examples
+///
+/// ```
+/// use fsbex::encode::EncodeOptions;
+///
+/// let options = EncodeOptions::new();
+/// # #[cfg(feature = "decode-mpeg")]
+/// let options = options.decode_mpeg(true);
+/// ```
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EncodeOptions<'a> {
+    pub(crate) decode_mpeg: bool,
+    pub(crate) decode_vorbis: bool,
+    pub(crate) repackage_vorbis: bool,
+    pub(crate) loop_chunk: bool,
+    pub(crate) cue_chunk: bool,
+    pub(crate) info_chunk: bool,
+    pub(crate) id3_tag: bool,
+    pub(crate) xing_header: bool,
+    pub(crate) mpeg_fix: bool,
+    pub(crate) strict_mpeg: bool,
+    pub(crate) wav_container: WavContainer,
+    pub(crate) pcm_output: PcmOutput,
+    pub(crate) strict: bool,
+    pub(crate) output_mode: OutputMode,
+    pub(crate) empty_streams: EmptyStreams,
+    pub(crate) bext: Option<BextInfo<'a>>,
+    pub(crate) source_name: Option<&'a str>,
+}
+
+impl Default for EncodeOptions<'_> {
+    fn default() -> Self {
+        Self {
+            decode_mpeg: false,
+            decode_vorbis: false,
+            repackage_vorbis: false,
+            loop_chunk: true,
+            cue_chunk: false,
+            info_chunk: false,
+            id3_tag: true,
+            xing_header: true,
+            mpeg_fix: true,
+            strict_mpeg: false,
+            wav_container: WavContainer::Riff,
+            pcm_output: PcmOutput::Wav,
+            strict: false,
+            output_mode: OutputMode::Encode,
+            empty_streams: EmptyStreams::Write,
+            bext: None,
+            source_name: None,
+        }
+    }
+}
+
+impl<'a> EncodeOptions<'a> {
+    /// Creates a new [`EncodeOptions`] with the default options.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether MPEG streams are decoded and written as WAVE files with 16-bit PCM samples,
+    /// instead of being written as MP3 files. This requires the `decode-mpeg` feature,
+    /// which decodes streams with Symphonia's MPEG audio decoder.
+    ///
+    /// Files are written with the sample rate and number of channels of the decoded frames. Streams whose stream
+    /// header declares a different format are listed in [`ExtractionReport::format_mismatches`] when extracted.
+    /// Streams with more than 2 channels have the channels of their layers interleaved in a single file,
+    /// and frames that can't be decoded are written as silence.
+    ///
+    /// [`ExtractionReport::format_mismatches`]: crate::ExtractionReport::format_mismatches
+    #[cfg(feature = "decode-mpeg")]
+    #[must_use]
+    pub fn decode_mpeg(mut self, decode: bool) -> Self {
+        self.decode_mpeg = decode;
+        self
+    }
+
+    /// Sets whether Vorbis streams are decoded and written as WAVE files with 16-bit PCM samples,
+    /// instead of being re-encoded as Ogg Vorbis files.
+    ///
+    /// Decoding requires the `decode-vorbis` feature, which is also enabled by the `vorbis` feature.
+    /// With only the `decode-vorbis` feature enabled, this or [`repackage_vorbis`](Self::repackage_vorbis)
+    /// must be set for Vorbis streams to be encoded.
+    #[must_use]
+    pub fn decode_vorbis(mut self, decode: bool) -> Self {
+        self.decode_vorbis = decode;
+        self
+    }
+
+    /// Sets whether Vorbis streams are repackaged as Ogg Vorbis files from their original audio packets,
+    /// instead of being re-encoded. Repackaging is lossless and much faster than re-encoding, and doesn't need
+    /// the `vorbis` feature (only the `decode-vorbis` feature). This is disabled by default,
+    /// and has no effect if [`decode_vorbis`](Self::decode_vorbis) is enabled.
+    #[must_use]
+    pub fn repackage_vorbis(mut self, repackage: bool) -> Self {
+        self.repackage_vorbis = repackage;
+        self
+    }
+
+    /// Sets whether WAVE files for looping streams include a `smpl` chunk with the loop points
+    /// (or, for FLAC files, whether the loop points are written as Vorbis comments).
+    /// This is enabled by default, but can be disabled for programs that don't handle the chunk correctly.
+    ///
+    /// The loop is written as a forward loop that repeats indefinitely.
+    /// Loops ending past the final sample of the stream are cut short to end at the final sample.
+    #[must_use]
+    pub fn loop_chunk(mut self, enabled: bool) -> Self {
+        self.loop_chunk = enabled;
+        self
+    }
+
+    /// Sets whether WAVE files for looping streams include a `cue ` chunk with cue points at the loop start
+    /// and end, along with a `LIST` chunk of type `adtl` that labels them "loop start" and "loop end".
+    /// Some editors show cue points, but not the loops of the `smpl` chunk. This is disabled by default.
+    ///
+    /// Like the loop of the `smpl` chunk, the loop end is the final sample of the loop.
+    /// When both chunks are written, the loop of the `smpl` chunk refers to the cue point at the loop start.
+    #[must_use]
+    pub fn cue_chunk(mut self, enabled: bool) -> Self {
+        self.cue_chunk = enabled;
+        self
+    }
+
+    /// Sets whether WAVE files include a `LIST` chunk of type `INFO`, which holds the name of the stream
+    /// (`INAM`) and the name of the sound bank set with [`source_name`](Self::source_name) (`ISRC`).
+    /// This is disabled by default, so that WAVE files are identical to those written by older versions.
+    ///
+    /// Names longer than 255 bytes are cut short. No chunk is written if neither name is known.
+    #[must_use]
+    pub fn info_chunk(mut self, enabled: bool) -> Self {
+        self.info_chunk = enabled;
+        self
+    }
+
+    /// Sets whether MP3 files start with an ID3v2.3 tag, which holds the name of the stream (`TIT2`),
+    /// its index in the sound bank counting from 1 (`TRCK`), and its duration in milliseconds (`TLEN`).
+    /// This is enabled by default. When disabled, MP3 files only hold the MPEG frames of the stream.
+    #[must_use]
+    pub fn id3_tag(mut self, enabled: bool) -> Self {
+        self.id3_tag = enabled;
+        self
+    }
+
+    /// Sets whether MP3 files start with a frame holding a Xing header, which lets players find the duration
+    /// of variable bitrate streams and seek within them. Streams with a constant bitrate get an "Info" header instead.
+    /// This is enabled by default. The header is only written for MPEG Layer III streams.
+    #[must_use]
+    pub fn xing_header(mut self, enabled: bool) -> Self {
+        self.xing_header = enabled;
+        self
+    }
+
+    /// Sets whether the padding that FMOD inserts between the frames of MPEG streams is removed,
+    /// so that MP3 files only hold complete frames. This is enabled by default.
+    ///
+    /// When disabled, the stream data of MPEG streams is written exactly as it is stored, without an ID3v2 tag
+    /// or a Xing header frame, and streams made up of layers are written to a single writer.
+    #[must_use]
+    pub fn mpeg_fix(mut self, enabled: bool) -> Self {
+        self.mpeg_fix = enabled;
+        self
+    }
+
+    /// Sets whether removing the padding of MPEG streams fails on malformed frames, instead of skipping them.
+    /// This is disabled by default, and has no effect if [`mpeg_fix`](Self::mpeg_fix) is disabled.
+    ///
+    /// When enabled, data after the first frame that isn't a valid frame header where the next frame is expected
+    /// (like a reserved bitrate or sample rate), or a final frame cut short by the end of the stream, fails with
+    /// [`MpegErrorKind::MalformedFrame`]. Otherwise, such data is skipped until the next frame.
+    ///
+    /// [`MpegErrorKind::MalformedFrame`]: crate::encode::MpegErrorKind::MalformedFrame
+    #[must_use]
+    pub fn strict_mpeg(mut self, strict: bool) -> Self {
+        self.strict_mpeg = strict;
+        self
+    }
+
+    /// Sets the container of WAVE files. See [`WavContainer`] for the available containers.
+    #[must_use]
+    pub fn wav_container(mut self, container: WavContainer) -> Self {
+        self.wav_container = container;
+        self
+    }
+
+    /// Sets the file format of streams that are written as PCM samples (including decoded streams).
+    /// See [`PcmOutput`] for the available formats.
+    ///
+    /// Streams that aren't written as PCM samples ignore this option, unless [`strict`](Self::strict) is enabled
+    /// or FLAC files are chosen.
+    #[must_use]
+    pub fn pcm_output(mut self, output: PcmOutput) -> Self {
+        self.pcm_output = output;
+        self
+    }
+
+    /// Sets whether encoding fails when an option can't be applied to a stream, instead of ignoring the option.
+    ///
+    /// When enabled, choosing a [`PcmOutput`] other than [`PcmOutput::Wav`] for a stream that isn't written
+    /// as PCM samples fails with [`EncodeError::UnsupportedOutput`].
+    ///
+    /// [`EncodeError::UnsupportedOutput`]: crate::encode::EncodeError::UnsupportedOutput
+    #[must_use]
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Sets the origination information of a `bext` chunk, which makes WAVE files Broadcast Wave Format (BWF) files.
+    /// No `bext` chunk is written by default. See [`BextInfo`] for the fields of the chunk.
+    ///
+    /// The chunk is written before the `data` chunk. Like other metadata chunks, it isn't written to Wave64 files.
+    #[must_use]
+    pub fn bext_chunk(mut self, info: BextInfo<'a>) -> Self {
+        self.bext = Some(info);
+        self
+    }
+
+    /// Sets whether streams are encoded, or copied exactly as they are stored in the sound bank.
+    /// See [`OutputMode`] for the available modes.
+    #[must_use]
+    pub fn output_mode(mut self, mode: OutputMode) -> Self {
+        self.output_mode = mode;
+        self
+    }
+
+    /// Sets how placeholder streams without stream data are written.
+    /// See [`EmptyStreams`] for the available behaviors.
+    #[must_use]
+    pub fn empty_streams(mut self, empty_streams: EmptyStreams) -> Self {
+        self.empty_streams = empty_streams;
+        self
+    }
+
+    /// Sets the name of the sound bank that streams are extracted from, such as its file name.
+    /// This is only written to files when [`info_chunk`](Self::info_chunk) is enabled.
+    #[must_use]
+    pub fn source_name(mut self, name: &'a str) -> Self {
+        self.source_name = Some(name);
+        self
+    }
+}
+
+/// Origination information written to the `bext` chunk of Broadcast Wave Format (BWF) files.
+///
+/// Fields that aren't set are filled in when a stream is written: the description is the name of the stream,
+/// the originator is `fsbex` with its version, and the origination date and time are the current date and time (UTC).
+/// Text longer than its field in the chunk is cut short.
+///
+/// # Example
+/// ```
+/// use fsbex::encode::{BextInfo, EncodeOptions};
+///
+/// let bext = BextInfo::new().originator("my-tool").time_reference(48000 * 60);
+/// let options = EncodeOptions::new().bext_chunk(bext);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BextInfo<'a> {
+    pub(crate) description: Option<&'a str>,
+    pub(crate) originator: Option<&'a str>,
+    pub(crate) originator_reference: Option<&'a str>,
+    pub(crate) origination_date: Option<&'a str>,
+    pub(crate) origination_time: Option<&'a str>,
+    pub(crate) time_reference: u64,
+}
+
+impl<'a> BextInfo<'a> {
+    /// Creates a new [`BextInfo`] where every field is filled in when a stream is written.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the description of the sound (up to 256 bytes), instead of the name of the stream.
+    #[must_use]
+    pub fn description(mut self, description: &'a str) -> Self {
+        self.description = Some(description);
+        self
+    }
+
+    /// Sets the name of the originator (up to 32 bytes), instead of `fsbex` with its version.
+    #[must_use]
+    pub fn originator(mut self, originator: &'a str) -> Self {
+        self.originator = Some(originator);
+        self
+    }
+
+    /// Sets a reference chosen by the originator (up to 32 bytes). This is empty by default.
+    #[must_use]
+    pub fn originator_reference(mut self, reference: &'a str) -> Self {
+        self.originator_reference = Some(reference);
+        self
+    }
+
+    /// Sets the origination date, in the format `yyyy-mm-dd`, instead of the current date.
+    #[must_use]
+    pub fn origination_date(mut self, date: &'a str) -> Self {
+        self.origination_date = Some(date);
+        self
+    }
+
+    /// Sets the origination time, in the format `hh:mm:ss`, instead of the current time.
+    #[must_use]
+    pub fn origination_time(mut self, time: &'a str) -> Self {
+        self.origination_time = Some(time);
+        self
+    }
+
+    /// Sets the time reference: the position of the first sample, counted in samples since midnight.
+    /// This is 0 by default.
+    #[must_use]
+    pub fn time_reference(mut self, samples: u64) -> Self {
+        self.time_reference = samples;
+        self
+    }
+}
+
+/// A mode of writing stream data.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum OutputMode {
+    /// Streams are encoded to a file format that can be played, such as WAVE files for PCM streams.
+    #[default]
+    Encode,
+    /// The stream data is copied exactly as it is stored in the sound bank, for every audio format.
+    /// No headers are added, and nothing is fixed or rebuilt, so the output can only be read by tools
+    /// that know the audio format of the stream (which is returned by [`Stream::format`]).
+    ///
+    /// All other options are ignored, and streams made up of layers are copied to a single writer.
+    ///
+    /// [`Stream::format`]: crate::Stream::format
+    Raw,
+}
+
+/// A way of writing placeholder streams, which have no stream data.
+///
+/// FMOD Studio writes placeholder streams for disabled assets. Their size (see [`StreamInfo::size`]) is 0 bytes.
+///
+/// [`StreamInfo::size`]: crate::StreamInfo::size
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EmptyStreams {
+    /// Streams written as PCM samples are written as files with a header, but no samples,
+    /// so that they can still be opened. Streams of other formats (such as MP3 files) are written as empty files.
+    #[default]
+    Write,
+    /// Nothing is written for placeholder streams, so writers are returned as they were.
+    /// [`Bank::extract_with`] doesn't create a writer for them, and reports them with [`SinkFactory::skipped`] instead.
+    ///
+    /// [`Bank::extract_with`]: crate::Bank::extract_with
+    /// [`SinkFactory::skipped`]: crate::SinkFactory::skipped
+    Skip,
+}
+
+/// A container for WAVE files, which holds PCM samples.
+///
+/// The size fields of standard RIFF files are 32 bits wide, so files larger than 4 GiB need a different container.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum WavContainer {
+    /// A standard RIFF file (.wav). Files larger than 4 GiB are written as RF64 files instead.
+    #[default]
+    Riff,
+    /// An RF64 file (.wav), which extends RIFF with 64-bit size fields in a `ds64` chunk.
+    Rf64,
+    /// A Sony Wave64 file (.w64), which uses GUIDs as chunk IDs and 64-bit size fields.
+    /// Metadata chunks (such as the `smpl` chunk with loop points) aren't written to Wave64 files.
+    Wave64,
+}
+
+/// A file format for streams that are written as PCM samples.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PcmOutput {
+    /// A WAVE file (.wav), in the container chosen with [`EncodeOptions::wav_container`].
+    #[default]
+    Wav,
+    /// An AIFF file (.aiff) with big-endian samples. Streams with 32-bit float samples are written as AIFF-C files (.aifc).
+    /// Metadata (such as loop points) isn't written to AIFF files, and files can't be larger than 4 GiB.
+    Aiff,
+    /// A FLAC file (.flac), which holds the stream name (`TITLE`) and loop points (`LOOPSTART` and `LOOPLENGTH`)
+    /// as Vorbis comments. Loop points are only written when [`EncodeOptions::loop_chunk`] is enabled.
+    ///
+    /// FLAC files can only hold integer samples of up to 24 bits, so streams with 32-bit integer or float samples,
+    /// and streams that aren't written as PCM samples, are always rejected with [`EncodeError::UnsupportedOutput`].
+    ///
+    /// Requires the `flac` feature.
+    ///
+    /// [`EncodeError::UnsupportedOutput`]: crate::encode::EncodeError::UnsupportedOutput
+    #[cfg(feature = "flac")]
+    Flac,
+    /// A Core Audio Format file (.caf) with little-endian samples, which holds the stream name in an `info` chunk.
+    /// Streams with more than 2 channels have a `chan` chunk with their speaker layout, like WAVE files.
+    /// Files can be larger than 4 GiB.
+    Caf,
+}
+
+impl Display for PcmOutput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Self::Wav => "WAVE",
+            Self::Aiff => "AIFF",
+            #[cfg(feature = "flac")]
+            Self::Flac => "FLAC",
+            Self::Caf => "CAF",
+        })
+    }
+}
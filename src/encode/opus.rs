@@ -0,0 +1,315 @@
+use super::ogg::OggWriter;
+use crate::{
+    header::StreamInfo,
+    read::{ReadError, Reader},
+};
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io::{Error as IoError, Read, Write},
+};
+
+// Ogg Opus file information taken from:
+// [1]: https://www.rfc-editor.org/rfc/rfc7845 (sections 4 and 5)
+// [2]: https://www.rfc-editor.org/rfc/rfc6716 (section 3.1)
+
+// Granule positions of Opus streams always count samples at 48 kHz.
+const GRANULE_RATE: u64 = 48000;
+
+// Number of samples at the start of the stream that are only used to prime the decoder.
+const PRE_SKIP: u16 = 312;
+
+const SERIAL: u32 = 0x4653_4235;
+const VENDOR: &str = concat!("fsbex ", env!("CARGO_PKG_VERSION"));
+
+/// Encodes an Opus stream by muxing the raw Opus packets into an Ogg Opus file.
+///
+/// The packets are copied verbatim; only the Ogg headers and pages are constructed.
+pub(super) fn encode<R: Read, W: Write>(
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+    sink: W,
+) -> Result<W, OpusError> {
+    let channels = info.channels.get();
+
+    // Channel mapping family 0 only covers mono and stereo streams.
+    if channels > 2 {
+        return Err(OpusError::new(OpusErrorKind::UnsupportedChannels));
+    }
+
+    let mut writer = OggWriter::new(sink, SERIAL);
+
+    (|| {
+        writer.write_packet(&id_header(info.sample_rate.get(), channels), 0)?;
+        writer.flush_page()?;
+        writer.write_packet(&comment_header(), 0)?;
+        writer.flush_page()
+    })()
+    .map_err(OpusError::from_io(OpusErrorKind::CreateHeaders))?;
+
+    // the final packet can decode to more samples than the stream contains, which is trimmed by its granule position
+    let end_granule = u64::from(PRE_SKIP)
+        + u64::from(info.num_samples.get()) * GRANULE_RATE / u64::from(info.sample_rate.get());
+
+    let start_pos = source.position();
+    let stream_size = info.size as usize;
+    let mut granule = u64::from(PRE_SKIP);
+    let mut pending: Option<(Vec<u8>, u64)> = None;
+
+    while source.bytes_since(start_pos) < stream_size {
+        let packet_size = source
+            .le_u16()
+            .map_err(OpusError::from_read(OpusErrorKind::ReadPacket))?;
+
+        // signals end of stream data
+        if packet_size == u16::MIN || packet_size == u16::MAX {
+            break;
+        }
+
+        let packet = source
+            .take(packet_size.into())
+            .map_err(OpusError::from_read(OpusErrorKind::ReadPacket))?;
+
+        granule +=
+            packet_samples(&packet).ok_or_else(|| OpusError::new(OpusErrorKind::InvalidPacket))?;
+
+        // packets are written one step behind, so the final packet is known when it's written
+        if let Some((packet, granule)) = pending.replace((packet, granule)) {
+            writer
+                .write_packet(&packet, granule)
+                .map_err(OpusError::from_io(OpusErrorKind::EncodePacket))?;
+        }
+    }
+
+    if let Some((packet, granule)) = pending {
+        writer
+            .write_packet(&packet, granule.min(end_granule))
+            .map_err(OpusError::from_io(OpusErrorKind::EncodePacket))?;
+    }
+
+    writer
+        .finish()
+        .map_err(OpusError::from_io(OpusErrorKind::FinishStream))
+}
+
+fn id_header(sample_rate: u32, channels: u8) -> Vec<u8> {
+    let mut data = Vec::with_capacity(19);
+
+    data.extend_from_slice(b"OpusHead");
+    data.push(1);
+    data.push(channels);
+    data.extend_from_slice(&PRE_SKIP.to_le_bytes());
+    data.extend_from_slice(&sample_rate.to_le_bytes());
+    data.extend_from_slice(&0i16.to_le_bytes());
+    data.push(0);
+
+    data
+}
+
+fn comment_header() -> Vec<u8> {
+    let mut data = Vec::with_capacity(16 + VENDOR.len());
+
+    data.extend_from_slice(b"OpusTags");
+    data.extend_from_slice(&u32::try_from(VENDOR.len()).unwrap_or_default().to_le_bytes());
+    data.extend_from_slice(VENDOR.as_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes());
+
+    data
+}
+
+// Returns the number of samples (at 48 kHz) that an Opus packet decodes to, based on its TOC byte.
+fn packet_samples(packet: &[u8]) -> Option<u64> {
+    let toc = *packet.first()?;
+    let config = toc >> 3;
+
+    let frame_samples = match config {
+        // SILK-only: 10, 20, 40, or 60 ms
+        0..=11 => [480, 960, 1920, 2880][usize::from(config % 4)],
+        // hybrid: 10 or 20 ms
+        12..=15 => [480, 960][usize::from(config % 2)],
+        // CELT-only: 2.5, 5, 10, or 20 ms
+        _ => [120, 240, 480, 960][usize::from(config % 4)],
+    };
+
+    let frames = match toc & 0x03 {
+        0 => 1,
+        1 | 2 => 2,
+        _ => packet.get(1)? & 0x3F,
+    };
+
+    Some(frame_samples * u64::from(frames))
+}
+
+/// Represents an error that can occur when encoding an Opus stream.
+///
+/// See [`OpusErrorKind`] for the different kinds of errors that can occur.
+#[derive(Debug)]
+pub struct OpusError {
+    kind: OpusErrorKind,
+    source: Option<OpusErrorSource>,
+}
+
+/// A variant of an [`OpusError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum OpusErrorKind {
+    /// The stream has more than 2 channels, which requires a channel mapping that isn't stored in the sound bank.
+    UnsupportedChannels,
+    /// Failed to write the Ogg Opus headers due to an underlying I/O error.
+    CreateHeaders,
+    /// Failed to read an Opus packet from the stream data.
+    ReadPacket,
+    /// An Opus packet was empty or had an invalid TOC byte.
+    InvalidPacket,
+    /// Failed to encode an Opus packet to the writer.
+    EncodePacket,
+    /// Failed to write the final page and flush the writer after encoding the entire stream.
+    FinishStream,
+}
+
+#[derive(Debug)]
+enum OpusErrorSource {
+    Io(IoError),
+    Read(ReadError),
+}
+
+impl OpusError {
+    fn new(kind: OpusErrorKind) -> Self {
+        Self { kind, source: None }
+    }
+
+    fn from_io(kind: OpusErrorKind) -> impl FnOnce(IoError) -> Self {
+        move |source| Self {
+            kind,
+            source: Some(OpusErrorSource::Io(source)),
+        }
+    }
+
+    fn from_read(kind: OpusErrorKind) -> impl FnOnce(ReadError) -> Self {
+        move |source| Self {
+            kind,
+            source: Some(OpusErrorSource::Read(source)),
+        }
+    }
+
+    /// Returns the [`OpusErrorKind`] associated with this error.
+    #[must_use]
+    pub fn kind(&self) -> OpusErrorKind {
+        self.kind
+    }
+}
+
+impl Display for OpusError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.kind.fmt(f)
+    }
+}
+
+impl Error for OpusError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.source {
+            Some(source) => match source {
+                OpusErrorSource::Io(e) => Some(e),
+                OpusErrorSource::Read(e) => Some(e),
+            },
+            None => None,
+        }
+    }
+}
+
+impl Display for OpusErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Self::UnsupportedChannels => "Opus streams with more than 2 channels are not supported",
+            Self::CreateHeaders => "failed to encode Ogg Opus headers",
+            Self::ReadPacket => "failed to read packet from Opus stream",
+            Self::InvalidPacket => "Opus packet was invalid",
+            Self::EncodePacket => "failed to encode Opus packet",
+            Self::FinishStream => "failed to finalize writing Opus stream data",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{encode, packet_samples, OpusErrorKind};
+    use crate::{
+        header::StreamInfo,
+        read::{Reader, ShortReader},
+    };
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn count_packet_samples() {
+        // CELT-only, 20 ms, 1 frame
+        assert_eq!(packet_samples(&[0xF8]), Some(960));
+        // SILK-only, 60 ms, 2 frames
+        assert_eq!(packet_samples(&[0x19]), Some(5760));
+        // hybrid, 10 ms, 3 frames given by the frame count byte
+        assert_eq!(packet_samples(&[0x63, 0x03]), Some(1440));
+        assert_eq!(packet_samples(&[0x63]), None);
+        assert_eq!(packet_samples(&[]), None);
+    }
+
+    #[test]
+    fn encode_ogg_pages() {
+        let mut data = Vec::new();
+        for _ in 0..3 {
+            data.extend_from_slice(&3u16.to_le_bytes());
+            data.extend_from_slice(&[0xF8, 0xFF, 0xFE]);
+        }
+
+        let info = StreamInfo {
+            sample_rate: NonZeroU32::new(48000).unwrap(),
+            ..StreamInfo::dummy(2, 2000, 15)
+        };
+        let output = encode(&info, &mut Reader::new(data.as_slice()), Vec::new()).unwrap();
+
+        // identification header page
+        assert_eq!(output[0..6], *b"OggS\x00\x02");
+        assert_eq!(output[26..28], [1, 19]);
+        assert_eq!(output[28..36], *b"OpusHead");
+        assert_eq!(output[36..38], [1, 2]);
+        assert_eq!(output[38..40], 312u16.to_le_bytes());
+        assert_eq!(output[40..44], 48000u32.to_le_bytes());
+
+        // comment header page
+        let page = &output[47..];
+        assert_eq!(page[0..6], *b"OggS\x00\x00");
+        assert_eq!(page[28..36], *b"OpusTags");
+
+        // audio page; the final granule position is trimmed to the sample count
+        let page = &output[47 + 27 + 1 + usize::from(page[27])..];
+        assert_eq!(page[0..6], *b"OggS\x00\x04");
+        assert_eq!(page[6..14], (312u64 + 2000).to_le_bytes());
+        assert_eq!(page[26..30], [3, 3, 3, 3]);
+        assert_eq!(page[30..], [0xF8, 0xFF, 0xFE].repeat(3));
+    }
+
+    #[test]
+    fn reject_multichannel() {
+        let info = StreamInfo::dummy(6, 960, 4);
+        let data = [0; 4];
+
+        assert!(encode(&info, &mut Reader::new(data.as_slice()), Vec::new())
+            .is_err_and(|e| e.kind() == OpusErrorKind::UnsupportedChannels));
+    }
+
+    #[test]
+    fn encode_from_short_reads() {
+        let mut data = Vec::new();
+        for _ in 0..3 {
+            data.extend_from_slice(&3u16.to_le_bytes());
+            data.extend_from_slice(&[0xF8, 0xFF, 0xFE]);
+        }
+
+        let info = StreamInfo {
+            sample_rate: NonZeroU32::new(48000).unwrap(),
+            ..StreamInfo::dummy(2, 2000, 15)
+        };
+        let expected = encode(&info, &mut Reader::new(data.as_slice()), Vec::new()).unwrap();
+        let output = encode(&info, &mut Reader::new(ShortReader::new(&data)), Vec::new()).unwrap();
+
+        assert_eq!(output, expected);
+    }
+}
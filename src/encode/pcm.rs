@@ -1,8 +1,13 @@
+use super::{
+    wav::{Format, WavSpec},
+    EncodeOptions, EncodeStats,
+};
 use crate::{
     header::StreamInfo,
     read::{ReadError, Reader},
 };
 use std::{
+    cmp::min,
     error::Error,
     fmt::{Display, Formatter, Result as FmtResult},
     io::{copy, Error as IoError, Read, Write},
@@ -14,88 +19,270 @@ pub(super) fn encode<R: Read, W: Write, const BYTE_DEPTH: usize>(
     info: &StreamInfo,
     source: &mut Reader<R>,
     mut sink: W,
-) -> Result<W, PcmError> {
-    // write the WAVE file header
-    write_header(
-        info.size.get(),
-        info.channels.get().into(),
-        info.sample_rate.get(),
+    options: EncodeOptions<'_>,
+) -> Result<(W, EncodeStats), PcmError> {
+    // The stream data should hold a whole number of sample frames (1 sample for every channel).
+    // Any bytes past the final complete frame can't form a full frame, so they are dropped.
+    let frame_size = BYTE_DEPTH * usize::from(info.channels.get());
+    let stream_size = info.size as usize;
+    let data_size = stream_size - stream_size % frame_size;
+
+    if data_size == 0 {
+        return Err(PcmError::new(PcmErrorKind::IncompleteFrame));
+    }
+
+    let data_size_u64 = data_size
+        .try_into()
+        .expect("data size is at most the stream size, which is a u32");
+
+    let spec = WavSpec {
         format,
-        BYTE_DEPTH.try_into().expect("byte depth is less than u16::MAX"),
-        &mut sink,
-    )
-    .map_err(PcmError::from_io(PcmErrorKind::CreateHeader))?;
+        bits_per_sample: (BYTE_DEPTH * 8).try_into().expect("bit depth is less than u16::MAX"),
+        ..WavSpec::pcm16(info.channels.get().into(), info.sample_rate.get())
+    }
+    .with_options(info, options, data_size_u64);
+
+    // write the WAVE file header
+    spec.write_header(data_size_u64, &mut sink)
+        .map_err(PcmError::from_io(PcmErrorKind::CreateHeader))?;
 
     let start_pos = source.position();
-    let stream_size = info.size.get() as usize;
 
-    // Stream samples are encoded as little-endian.
-    // However, samples can be stored as big-endian; when this happens, the samples have to be converted.
+    // Samples are written as little-endian to WAVE files, and as big-endian to AIFF files.
+    // Samples stored in the other byte order have to be converted; float samples are always stored as little-endian.
+    // 8-bit samples are stored as signed integers, but WAVE files expect them to be unsigned, so they are converted too (AIFF files keep them signed).
     // Otherwise, the stream data can be directly copied from reader to writer.
+    let stored_big_endian = format == Format::Integer && order == Endianness::Big;
+    let swap_bytes = BYTE_DEPTH > 1 && stored_big_endian != spec.is_big_endian();
+    let flip_sign = BYTE_DEPTH == 1 && spec.is_unsigned_8_bit();
 
-    if format == Format::Float || order == Endianness::Little {
+    if !swap_bytes && !flip_sign {
         // There could be more data after the stream, so a limit is placed on the number of bytes read.
-        return copy(&mut source.limit(stream_size), &mut sink)
-            .map(|_| sink)
-            .map_err(PcmError::from_io(PcmErrorKind::EncodeStream));
+        let _bytes = copy(&mut source.limit(data_size as u64), &mut sink)
+            .map_err(PcmError::from_io(PcmErrorKind::EncodeStream))?;
+    } else {
+        while source.bytes_since(start_pos) < data_size {
+            let mut sample = source
+                .take_const::<BYTE_DEPTH>()
+                .map_err(PcmError::from_read(PcmErrorKind::DecodeSample))?;
+
+            if flip_sign {
+                // offset by 128 to convert from signed to unsigned
+                sample[0] ^= 0x80;
+            } else {
+                sample.reverse();
+            }
+
+            sink.write_all(&sample)
+                .map_err(PcmError::from_io(PcmErrorKind::EncodeSample))?;
+        }
     }
 
-    while source.position() - start_pos < stream_size {
-        let mut sample = source
-            .take_const::<BYTE_DEPTH>()
-            .map_err(PcmError::from_read(PcmErrorKind::DecodeSample))?;
+    // an odd amount of data (only possible for mono 8 or 24-bit streams) is followed by a padding byte
+    spec.write_trailer(data_size_u64, &mut sink)
+        .and_then(|()| sink.flush())
+        .map_err(PcmError::from_io(PcmErrorKind::FinishStream))?;
 
-        // This is optimized out when BYTE_DEPTH == 1
-        sample.reverse();
+    let stats = EncodeStats {
+        converted_8_bit: flip_sign,
+        truncated_bytes: (stream_size % frame_size)
+            .try_into()
+            .expect("bytes of an incomplete frame are fewer than the frame size, which is small"),
+        ..EncodeStats::default()
+    };
+    Ok((sink, stats))
+}
 
-        sink.write_all(&sample)
-            .map_err(PcmError::from_io(PcmErrorKind::EncodeSample))?;
+// Writes a file with a header, but no samples, for a stream without stream data.
+pub(super) fn encode_empty<W: Write>(
+    format: Format,
+    bits_per_sample: u16,
+    info: &StreamInfo,
+    mut sink: W,
+    options: EncodeOptions<'_>,
+) -> Result<W, PcmError> {
+    let spec = WavSpec {
+        format,
+        bits_per_sample,
+        ..WavSpec::pcm16(info.channels.get().into(), info.sample_rate.get())
     }
+    .with_options(info, options, 0);
 
-    sink.flush()
+    spec.write_header(0, &mut sink)
+        .map_err(PcmError::from_io(PcmErrorKind::CreateHeader))?;
+
+    spec.write_trailer(0, &mut sink)
+        .and_then(|()| sink.flush())
         .map(|()| sink)
         .map_err(PcmError::from_io(PcmErrorKind::FinishStream))
 }
 
-fn write_header<W: Write>(
-    file_size: u32,
-    channels: u16,
-    sample_rate: u32,
+// Number of sample frames converted at a time by `BlockDecoder`.
+const FRAMES_PER_BLOCK: usize = 1024;
+
+// Converts the stream data to 16-bit samples, a block of frames at a time.
+// Like when encoding, bytes past the final complete frame are dropped.
+pub(super) struct BlockDecoder {
     format: Format,
-    byte_depth: u16,
-    sink: &mut W,
-) -> Result<(), IoError> {
-    // WAVE file header information taken from:
-    // [1]: https://www-mmsp.ece.mcgill.ca/Documents/AudioFormats/WAVE/WAVE.html
-    // [2]: http://soundfile.sapp.org/doc/WaveFormat/
-
-    let format_id = match format {
-        Format::Integer => 1u16,
-        Format::Float => 3u16,
-    };
-    let bytes_per_second = sample_rate * u32::from(channels) * u32::from(byte_depth);
-
-    sink.write_all(b"RIFF")?;
-    sink.write_all(&(file_size - 8).to_le_bytes())?;
-    sink.write_all(b"WAVE")?;
-    sink.write_all(b"fmt ")?;
-    sink.write_all(&16u32.to_le_bytes())?;
-    sink.write_all(&format_id.to_le_bytes())?;
-    sink.write_all(&channels.to_le_bytes())?;
-    sink.write_all(&sample_rate.to_le_bytes())?;
-    sink.write_all(&bytes_per_second.to_le_bytes())?;
-    sink.write_all(&(channels * byte_depth).to_le_bytes())?;
-    sink.write_all(&(byte_depth * 8).to_le_bytes())?;
-    sink.write_all(b"data")?;
-    sink.write_all(&(file_size - 40).to_le_bytes())?;
-
-    Ok(())
+    order: Endianness,
+    byte_depth: usize,
+    frame_size: usize,
+    pub(super) frames_left: usize,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-pub(super) enum Format {
-    Integer,
-    Float,
+impl BlockDecoder {
+    pub(super) fn new(
+        format: Format,
+        order: Endianness,
+        byte_depth: usize,
+        info: &StreamInfo,
+    ) -> Self {
+        let frame_size = byte_depth * usize::from(info.channels.get());
+
+        Self {
+            format,
+            order,
+            byte_depth,
+            frame_size,
+            frames_left: info.size as usize / frame_size,
+        }
+    }
+
+    // Replaces the contents of `samples` with the samples of the next block of frames,
+    // returning false once every frame has been converted.
+    pub(super) fn next_block<R: Read>(
+        &mut self,
+        source: &mut Reader<R>,
+        samples: &mut Vec<i16>,
+    ) -> Result<bool, PcmError> {
+        samples.clear();
+
+        if self.frames_left == 0 {
+            return Ok(false);
+        }
+
+        let frames = min(self.frames_left, FRAMES_PER_BLOCK);
+        let data = source
+            .take(frames * self.frame_size)
+            .map_err(PcmError::from_read(PcmErrorKind::DecodeSample))?;
+
+        samples.extend(data.chunks_exact(self.byte_depth).map(|sample| self.convert(sample)));
+
+        self.frames_left -= frames;
+        Ok(true)
+    }
+
+    // Integer samples keep their most significant 16 bits, and float samples are scaled from [-1.0, 1.0].
+    #[allow(clippy::cast_possible_truncation)]
+    fn convert(&self, sample: &[u8]) -> i16 {
+        match (self.format, sample) {
+            (Format::Float, _) => {
+                let sample =
+                    f32::from_le_bytes(sample.try_into().expect("float samples are 4 bytes"));
+                (sample * 32768.0).clamp(i16::MIN.into(), i16::MAX.into()) as i16
+            }
+            (Format::Integer, &[byte]) => i16::from(byte.cast_signed()) << 8,
+            (Format::Integer, &[first, second]) => match self.order {
+                Endianness::Little => i16::from_le_bytes([first, second]),
+                Endianness::Big => i16::from_be_bytes([first, second]),
+            },
+            (Format::Integer, &[.., low, high]) => i16::from_le_bytes([low, high]),
+            (Format::Integer, []) => unreachable!("samples are at least 1 byte"),
+        }
+    }
+}
+
+// Encodes a PCM stream a block of frames at a time, so that the encoded stream can be read incrementally.
+// The header and trailer are written like `encode` does, and samples are converted the same way.
+pub(super) struct Chunks {
+    frame_size: usize,
+    byte_depth: usize,
+    swap_bytes: bool,
+    flip_sign: bool,
+    bytes_left: usize,
+    trailer: Vec<u8>,
+}
+
+impl Chunks {
+    pub(super) fn new(
+        format: Format,
+        order: Endianness,
+        byte_depth: usize,
+        info: &StreamInfo,
+        header: &mut Vec<u8>,
+        options: EncodeOptions<'_>,
+    ) -> Result<Self, PcmError> {
+        let frame_size = byte_depth * usize::from(info.channels.get());
+        let stream_size = info.size as usize;
+        let data_size = stream_size - stream_size % frame_size;
+
+        if data_size == 0 {
+            return Err(PcmError::new(PcmErrorKind::IncompleteFrame));
+        }
+
+        let data_size_u64 = data_size
+            .try_into()
+            .expect("data size is at most the stream size, which is a u32");
+
+        let spec = WavSpec {
+            format,
+            bits_per_sample: (byte_depth * 8).try_into().expect("bit depth is less than u16::MAX"),
+            ..WavSpec::pcm16(info.channels.get().into(), info.sample_rate.get())
+        }
+        .with_options(info, options, data_size_u64);
+
+        spec.write_header(data_size_u64, &mut *header)
+            .map_err(PcmError::from_io(PcmErrorKind::CreateHeader))?;
+
+        let mut trailer = Vec::new();
+        spec.write_trailer(data_size_u64, &mut trailer)
+            .map_err(PcmError::from_io(PcmErrorKind::FinishStream))?;
+
+        let stored_big_endian = format == Format::Integer && order == Endianness::Big;
+
+        Ok(Self {
+            frame_size,
+            byte_depth,
+            swap_bytes: byte_depth > 1 && stored_big_endian != spec.is_big_endian(),
+            flip_sign: byte_depth == 1 && spec.is_unsigned_8_bit(),
+            bytes_left: data_size,
+            trailer,
+        })
+    }
+
+    // Appends the next block of converted frames to `chunk`, followed by the trailer once every frame has been
+    // converted, returning false at the end of the stream.
+    pub(super) fn next_chunk<R: Read>(
+        &mut self,
+        source: &mut Reader<R>,
+        chunk: &mut Vec<u8>,
+    ) -> Result<bool, PcmError> {
+        let len = min(self.bytes_left, FRAMES_PER_BLOCK * self.frame_size);
+        let mut data = source
+            .take(len)
+            .map_err(PcmError::from_read(PcmErrorKind::DecodeSample))?;
+
+        if self.flip_sign {
+            for sample in &mut data {
+                // offset by 128 to convert from signed to unsigned
+                *sample ^= 0x80;
+            }
+        } else if self.swap_bytes {
+            for sample in data.chunks_exact_mut(self.byte_depth) {
+                sample.reverse();
+            }
+        }
+
+        chunk.extend_from_slice(&data);
+        self.bytes_left -= len;
+
+        if self.bytes_left > 0 {
+            return Ok(true);
+        }
+
+        chunk.append(&mut self.trailer);
+        Ok(false)
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -110,13 +297,15 @@ pub(super) enum Endianness {
 #[derive(Debug)]
 pub struct PcmError {
     kind: PcmErrorKind,
-    source: PcmErrorSource,
+    source: Option<PcmErrorSource>,
 }
 
 /// A variant of a [`PcmError`].
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 #[non_exhaustive]
 pub enum PcmErrorKind {
+    /// The stream data was too small to hold a single sample for every channel.
+    IncompleteFrame,
     /// Failed to write the file header due to an underlying I/O error.
     CreateHeader,
     /// Failed to encode the entire stream via copying from reader to writer.
@@ -136,17 +325,21 @@ enum PcmErrorSource {
 }
 
 impl PcmError {
+    fn new(kind: PcmErrorKind) -> Self {
+        Self { kind, source: None }
+    }
+
     fn from_io(kind: PcmErrorKind) -> impl FnOnce(IoError) -> Self {
         move |source| Self {
             kind,
-            source: PcmErrorSource::Io(source),
+            source: Some(PcmErrorSource::Io(source)),
         }
     }
 
     fn from_read(kind: PcmErrorKind) -> impl FnOnce(ReadError) -> Self {
         move |source| Self {
             kind,
-            source: PcmErrorSource::Read(source),
+            source: Some(PcmErrorSource::Read(source)),
         }
     }
 
@@ -166,8 +359,11 @@ impl Display for PcmError {
 impl Error for PcmError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match &self.source {
-            PcmErrorSource::Io(e) => Some(e),
-            PcmErrorSource::Read(e) => Some(e),
+            Some(source) => match source {
+                PcmErrorSource::Io(e) => Some(e),
+                PcmErrorSource::Read(e) => Some(e),
+            },
+            None => None,
         }
     }
 }
@@ -175,6 +371,7 @@ impl Error for PcmError {
 impl Display for PcmErrorKind {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         f.write_str(match self {
+            Self::IncompleteFrame => "PCM stream did not contain a complete sample frame",
             Self::CreateHeader => "failed to encode file header",
             Self::EncodeStream => "failed to encode full PCM stream",
             Self::DecodeSample => "failed to decode sample from PCM stream",
@@ -183,3 +380,253 @@ impl Display for PcmErrorKind {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{encode, Endianness, PcmErrorKind};
+    use crate::{
+        encode::{wav::Format, EncodeOptions, PcmOutput},
+        header::StreamInfo,
+        read::{Reader, ShortReader},
+    };
+
+    #[test]
+    fn encode_float_stream() {
+        let samples = [0.5f32, -0.25, 1.0, -1.0];
+        let data: Vec<_> = samples.iter().flat_map(|sample| sample.to_le_bytes()).collect();
+
+        let info = StreamInfo::dummy(2, 2, 16);
+        let (output, _stats) = encode::<_, _, 4>(
+            Format::Float,
+            Endianness::Little,
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(output[0..4], *b"RIFF");
+        assert_eq!(output[4..8], 66u32.to_le_bytes());
+        assert_eq!(output[8..16], *b"WAVEfmt ");
+        // extended fmt chunk with IEEE float format tag and 32-bit samples
+        assert_eq!(output[16..20], 18u32.to_le_bytes());
+        assert_eq!(output[20..22], 3u16.to_le_bytes());
+        assert_eq!(output[22..24], 2u16.to_le_bytes());
+        assert_eq!(output[28..32], (44100u32 * 8).to_le_bytes());
+        assert_eq!(output[32..34], 8u16.to_le_bytes());
+        assert_eq!(output[34..36], 32u16.to_le_bytes());
+        assert_eq!(output[36..38], 0u16.to_le_bytes());
+        // fact chunk with the number of sample frames
+        assert_eq!(output[38..42], *b"fact");
+        assert_eq!(output[42..46], 4u32.to_le_bytes());
+        assert_eq!(output[46..50], 2u32.to_le_bytes());
+        assert_eq!(output[50..54], *b"data");
+        assert_eq!(output[54..58], 16u32.to_le_bytes());
+
+        let decoded: Vec<_> = output[58..]
+            .chunks_exact(4)
+            .map(|sample| f32::from_le_bytes(sample.try_into().unwrap()))
+            .collect();
+        assert_eq!(decoded, samples);
+    }
+
+    #[test]
+    fn encode_integer_header() {
+        let data = [1, 0, 2, 0];
+
+        let info = StreamInfo::dummy(1, 2, 4);
+        let (output, _stats) = encode::<_, _, 2>(
+            Format::Integer,
+            Endianness::Little,
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(output.len(), 44 + 4);
+        assert_eq!(output[4..8], 40u32.to_le_bytes());
+        assert_eq!(output[16..22], [16, 0, 0, 0, 1, 0]);
+        assert_eq!(output[36..40], *b"data");
+        assert_eq!(output[44..], data);
+    }
+
+    #[test]
+    fn convert_8_bit_samples_to_unsigned() {
+        let data = [0x00, 0x7F, 0x80, 0xFF];
+
+        let info = StreamInfo::dummy(1, 4, 4);
+        let (output, stats) = encode::<_, _, 1>(
+            Format::Integer,
+            Endianness::Little,
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::default(),
+        )
+        .unwrap();
+
+        // 0, 127, -128, -1 as unsigned samples with a bias of 128
+        assert_eq!(output[34..36], 8u16.to_le_bytes());
+        assert_eq!(output[44..], [0x80, 0xFF, 0x00, 0x7F]);
+        assert!(stats.converted_8_bit);
+    }
+
+    #[test]
+    fn drop_incomplete_frame() {
+        // 2 channels of 24-bit samples, with 4 bytes left over after the final complete frame
+        let data: Vec<_> = (0..16).collect();
+
+        let info = StreamInfo::dummy(2, 2, 16);
+        let (output, stats) = encode::<_, _, 3>(
+            Format::Integer,
+            Endianness::Little,
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(output[32..36], [6, 0, 24, 0]);
+        assert_eq!(output[40..44], 12u32.to_le_bytes());
+        assert_eq!(output[44..], data[..12]);
+        assert_eq!(stats.truncated_bytes, 4);
+    }
+
+    #[test]
+    fn swap_big_endian_samples() {
+        let samples = [1i16, -2, 0x1234, i16::MIN];
+        let data: Vec<_> = samples.iter().flat_map(|sample| sample.to_be_bytes()).collect();
+
+        let info = StreamInfo::dummy(2, 2, 8);
+        let (output, _stats) = encode::<_, _, 2>(
+            Format::Integer,
+            Endianness::Big,
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::default(),
+        )
+        .unwrap();
+
+        let decoded: Vec<_> = output[44..]
+            .chunks_exact(2)
+            .map(|sample| i16::from_le_bytes(sample.try_into().unwrap()))
+            .collect();
+        assert_eq!(decoded, samples);
+    }
+
+    #[test]
+    fn write_aiff_with_big_endian_samples() {
+        let samples = [1i16, -2, 0x1234, i16::MIN];
+        let data: Vec<_> = samples.iter().flat_map(|sample| sample.to_le_bytes()).collect();
+
+        let info = StreamInfo::dummy(2, 2, 8);
+        let (output, _stats) = encode::<_, _, 2>(
+            Format::Integer,
+            Endianness::Little,
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::new().pcm_output(PcmOutput::Aiff),
+        )
+        .unwrap();
+
+        assert_eq!(output.len(), 54 + 8);
+        assert_eq!(output[0..4], *b"FORM");
+        assert_eq!(output[4..8], 54u32.to_be_bytes());
+        assert_eq!(output[8..16], *b"AIFFCOMM");
+        // 2 channels, 2 sample frames, 16-bit samples
+        assert_eq!(output[20..22], 2u16.to_be_bytes());
+        assert_eq!(output[22..26], 2u32.to_be_bytes());
+        assert_eq!(output[26..28], 16u16.to_be_bytes());
+        assert_eq!(output[38..42], *b"SSND");
+        assert_eq!(output[42..46], 16u32.to_be_bytes());
+
+        let decoded: Vec<_> = output[54..]
+            .chunks_exact(2)
+            .map(|sample| i16::from_be_bytes(sample.try_into().unwrap()))
+            .collect();
+        assert_eq!(decoded, samples);
+    }
+
+    #[test]
+    fn keep_8_bit_samples_signed_in_aiff() {
+        let data = [0x00, 0x7F, 0x80];
+
+        let info = StreamInfo::dummy(1, 3, 3);
+        let (output, stats) = encode::<_, _, 1>(
+            Format::Integer,
+            Endianness::Little,
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::new().pcm_output(PcmOutput::Aiff),
+        )
+        .unwrap();
+
+        // the odd amount of sample data is followed by a padding byte
+        assert_eq!(output[54..], [0x00, 0x7F, 0x80, 0]);
+        assert!(!stats.converted_8_bit);
+    }
+
+    #[test]
+    fn encode_32_bit_stream() {
+        let data: Vec<_> = (0..24).collect();
+
+        let info = StreamInfo::dummy(3, 2, 24);
+        let (output, _stats) = encode::<_, _, 4>(
+            Format::Integer,
+            Endianness::Little,
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::default(),
+        )
+        .unwrap();
+
+        // streams with more than 2 channels use the extensible format, with an integer PCM sub-format
+        assert_eq!(output[20..24], [0xFE, 0xFF, 3, 0]);
+        assert_eq!(output[28..32], (44100u32 * 12).to_le_bytes());
+        assert_eq!(output[32..36], [12, 0, 32, 0]);
+        assert_eq!(output[40..46], [0x07, 0, 0, 0, 1, 0]);
+        assert_eq!(output[80..], data);
+    }
+
+    #[test]
+    fn reject_stream_without_complete_frame() {
+        let data = [0; 7];
+
+        let info = StreamInfo::dummy(2, 1, 7);
+        assert!(encode::<_, _, 4>(
+            Format::Integer,
+            Endianness::Little,
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::default(),
+        )
+        .is_err_and(|e| e.kind() == PcmErrorKind::IncompleteFrame));
+    }
+
+    #[test]
+    fn encode_from_short_reads() {
+        let data: Vec<u8> = (0..=255).collect();
+
+        let info = StreamInfo::dummy(2, 64, 256);
+        let (output, _stats) = encode::<_, _, 2>(
+            Format::Integer,
+            Endianness::Little,
+            &info,
+            &mut Reader::new(ShortReader::new(&data)),
+            Vec::new(),
+            EncodeOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(output[44..], data);
+    }
+}
@@ -0,0 +1,235 @@
+use crate::{header::StreamInfo, read::Reader};
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io::{copy, Error as IoError, Read, Write},
+};
+
+/// Copies the stream data to the writer exactly as it is stored in the sound bank.
+pub(super) fn encode<R: Read, W: Write>(
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+    mut sink: W,
+) -> Result<W, RawError> {
+    let stream_size = info.size.into();
+
+    // There could be more data after the stream, so a limit is placed on the number of bytes read.
+    let copied = copy(&mut source.limit(info.size.into()), &mut sink)
+        .map_err(RawError::from_io(RawErrorKind::CopyStream))?;
+
+    if copied < stream_size {
+        return Err(RawError::new(RawErrorKind::IncompleteStream));
+    }
+
+    sink.flush()
+        .map(|()| sink)
+        .map_err(RawError::from_io(RawErrorKind::FinishStream))
+}
+
+// Number of bytes copied at a time by `Chunks`.
+const CHUNK_SIZE: u64 = 0x10000;
+
+// Copies the stream data a chunk at a time, so that the stream can be read incrementally.
+pub(super) struct Chunks {
+    bytes_left: u64,
+}
+
+impl Chunks {
+    pub(super) fn new(info: &StreamInfo) -> Self {
+        Self {
+            bytes_left: info.size.into(),
+        }
+    }
+
+    // Appends the next chunk of stream data to `chunk`, returning false at the end of the stream.
+    pub(super) fn next_chunk<R: Read>(
+        &mut self,
+        source: &mut Reader<R>,
+        chunk: &mut Vec<u8>,
+    ) -> Result<bool, RawError> {
+        let len = self.bytes_left.min(CHUNK_SIZE);
+        let copied = copy(&mut source.limit(len), chunk)
+            .map_err(RawError::from_io(RawErrorKind::CopyStream))?;
+
+        if copied < len {
+            return Err(RawError::new(RawErrorKind::IncompleteStream));
+        }
+
+        self.bytes_left -= len;
+        Ok(self.bytes_left > 0)
+    }
+}
+
+/// Represents an error that can occur when copying the stream data of a stream without encoding it.
+///
+/// See [`RawErrorKind`] for the different kinds of errors that can occur.
+#[derive(Debug)]
+pub struct RawError {
+    kind: RawErrorKind,
+    source: Option<IoError>,
+}
+
+/// A variant of a [`RawError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RawErrorKind {
+    /// Failed to copy the stream data from reader to writer.
+    CopyStream,
+    /// The reader ended before the entire stream data was copied.
+    IncompleteStream,
+    /// Failed to flush the writer after copying the entire stream.
+    FinishStream,
+}
+
+impl RawError {
+    fn new(kind: RawErrorKind) -> Self {
+        Self { kind, source: None }
+    }
+
+    fn from_io(kind: RawErrorKind) -> impl FnOnce(IoError) -> Self {
+        move |source| Self {
+            kind,
+            source: Some(source),
+        }
+    }
+
+    /// Returns the [`RawErrorKind`] associated with this error.
+    #[must_use]
+    pub fn kind(&self) -> RawErrorKind {
+        self.kind
+    }
+}
+
+impl Display for RawError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.kind.fmt(f)
+    }
+}
+
+impl Error for RawError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.source {
+            Some(e) => Some(e),
+            None => None,
+        }
+    }
+}
+
+impl Display for RawErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Self::CopyStream => "failed to copy stream data",
+            Self::IncompleteStream => "stream data ended before the size of the stream",
+            Self::FinishStream => "failed to finalize writing stream data",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RawErrorKind;
+    use crate::{
+        encode::{encode, encode_layers, EncodeError, EncodeOptions, OutputMode, PcmOutput},
+        header::{AudioFormat, StreamInfo},
+        read::Reader,
+    };
+
+    const FORMATS: [AudioFormat; 17] = [
+        AudioFormat::Pcm8,
+        AudioFormat::Pcm16,
+        AudioFormat::Pcm24,
+        AudioFormat::Pcm32,
+        AudioFormat::PcmFloat,
+        AudioFormat::GcAdpcm,
+        AudioFormat::ImaAdpcm,
+        AudioFormat::Vag,
+        AudioFormat::HeVag,
+        AudioFormat::Xma,
+        AudioFormat::Mpeg,
+        AudioFormat::Celt,
+        AudioFormat::Atrac9,
+        AudioFormat::Xwma,
+        AudioFormat::Vorbis,
+        AudioFormat::FAdpcm,
+        AudioFormat::Opus,
+    ];
+
+    #[test]
+    fn copy_stream_data_for_every_format() {
+        // data that isn't valid for any format, followed by the data of the next stream
+        let data: Vec<_> = (0..=255).collect();
+        let info = StreamInfo::dummy(3, 1, 200);
+        let options = EncodeOptions::new().output_mode(OutputMode::Raw);
+
+        for format in FORMATS {
+            let output = encode(
+                format,
+                1,
+                0,
+                &info,
+                &mut Reader::new(data.as_slice()),
+                Vec::new(),
+                options,
+            )
+            .unwrap();
+
+            assert_eq!(output, data[..200], "{format}");
+        }
+    }
+
+    #[test]
+    fn ignore_other_options() {
+        let data = [0x80; 16];
+        let info = StreamInfo::dummy(2, 4, 16);
+        let options = EncodeOptions::new()
+            .output_mode(OutputMode::Raw)
+            .decode_vorbis(true)
+            .pcm_output(PcmOutput::Aiff)
+            .strict(true);
+
+        let output = encode(
+            AudioFormat::Pcm8,
+            0,
+            0,
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            options,
+        )
+        .unwrap();
+        assert_eq!(output, data);
+
+        // streams made up of layers are copied to a single writer
+        let info = StreamInfo::dummy(6, 4, 16);
+        let output = encode_layers(
+            AudioFormat::Mpeg,
+            0,
+            0,
+            &info,
+            &mut Reader::new(data.as_slice()),
+            |_| Vec::new(),
+            options,
+        )
+        .unwrap();
+        assert_eq!(output, [data]);
+    }
+
+    #[test]
+    fn reject_truncated_stream() {
+        let data = [0; 8];
+        let info = StreamInfo::dummy(1, 1, 16);
+
+        assert!(encode(
+            AudioFormat::Pcm16,
+            0,
+            0,
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::new().output_mode(OutputMode::Raw),
+        )
+        .is_err_and(
+            |e| matches!(e, EncodeError::Raw(e) if e.kind() == RawErrorKind::IncompleteStream)
+        ));
+    }
+}
@@ -0,0 +1,516 @@
+#[cfg(feature = "decode-vorbis")]
+use super::vorbis;
+#[cfg(feature = "flac")]
+use super::PcmOutput;
+use super::{
+    check_output, encode, mpeg,
+    pcm::{self, Endianness},
+    raw,
+    samples::BlockDecoder,
+    wav::{Format, WavSpec},
+    EmptyStreams, EncodeError, EncodeOptions, OutputMode,
+};
+use crate::header::{AudioFormat, BankFlags, StreamInfo};
+use crate::read::Reader;
+use std::{
+    cmp::min,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    io::{BufRead, Cursor, Error as IoError, Read, Result as IoResult},
+};
+
+/// A reader over the encoded file of a stream, which encodes the stream as it is read.
+///
+/// This type is returned from [`Stream::into_reader`] and [`Stream::into_reader_with_options`].
+/// The bytes read are the same as the bytes written by [`Stream::write_with_options`] with the same options,
+/// so the reader can be passed to anything that expects a [`Read`] (such as an HTTP response body)
+/// without holding the whole encoded file in memory.
+///
+/// Raw stream data, PCM streams, streams decoded to PCM samples (such as IMA ADPCM streams), MPEG streams,
+/// and Vorbis streams are encoded incrementally. Other streams, and streams written as FLAC files,
+/// are encoded in full when the reader is created.
+///
+/// Errors that occur while encoding are returned as [`std::io::Error`]s wrapping the [`EncodeError`],
+/// after which the reader returns no more bytes.
+///
+/// [`Stream::into_reader`]: crate::Stream::into_reader
+/// [`Stream::into_reader_with_options`]: crate::Stream::into_reader_with_options
+/// [`Stream::write_with_options`]: crate::Stream::write_with_options
+pub struct StreamReader {
+    format: AudioFormat,
+    source: Reader<Cursor<Box<[u8]>>>,
+    encoder: ChunkEncoder,
+    chunk: Vec<u8>,
+    position: usize,
+    finished: bool,
+}
+
+enum ChunkEncoder {
+    Raw(raw::Chunks),
+    Pcm(pcm::Chunks),
+    Decoded(DecodedChunks),
+    Mpeg(mpeg::Chunks),
+    #[cfg(feature = "decode-vorbis")]
+    Ogg(Box<vorbis::Chunks>),
+    #[cfg(feature = "vorbis")]
+    Reencoded(Box<vorbis::ReencodedChunks>),
+    // the whole file was encoded when the reader was created
+    Encoded,
+}
+
+impl StreamReader {
+    pub(crate) fn new(
+        format: AudioFormat,
+        flags: u32,
+        index: u32,
+        info: &StreamInfo,
+        data: Box<[u8]>,
+        options: EncodeOptions<'_>,
+    ) -> Result<Self, EncodeError> {
+        let mut source = Reader::seekable(Cursor::new(data));
+        let mut chunk = Vec::new();
+        let encoder =
+            ChunkEncoder::new(format, flags, index, info, &mut source, &mut chunk, options)?;
+
+        Ok(Self {
+            format,
+            source,
+            finished: matches!(encoder, ChunkEncoder::Encoded),
+            encoder,
+            chunk,
+            position: 0,
+        })
+    }
+}
+
+impl ChunkEncoder {
+    // Creates the encoder for the stream, appending the start of the encoded file (such as its header) to `chunk`.
+    // Mirrors `encode`, falling back to it for streams that can't be encoded incrementally.
+    fn new(
+        format: AudioFormat,
+        flags: u32,
+        index: u32,
+        info: &StreamInfo,
+        source: &mut Reader<Cursor<Box<[u8]>>>,
+        chunk: &mut Vec<u8>,
+        options: EncodeOptions<'_>,
+    ) -> Result<Self, EncodeError> {
+        if info.size == 0 && options.empty_streams == EmptyStreams::Skip {
+            return Ok(Self::Encoded);
+        }
+
+        if options.output_mode == OutputMode::Raw {
+            return Ok(Self::Raw(raw::Chunks::new(info)));
+        }
+
+        #[cfg(feature = "flac")]
+        let buffered = info.size == 0 || options.pcm_output == PcmOutput::Flac;
+        #[cfg(not(feature = "flac"))]
+        let buffered = info.size == 0;
+
+        if !buffered {
+            if let Some(encoder) =
+                Self::incremental(format, flags, index, info, source, chunk, options)?
+            {
+                return Ok(encoder);
+            }
+        }
+
+        let _file = encode(format, flags, index, info, source, &mut *chunk, options)?;
+        Ok(Self::Encoded)
+    }
+
+    // Creates an encoder that encodes the stream a chunk at a time, if the format can be encoded incrementally.
+    fn incremental(
+        format: AudioFormat,
+        flags: u32,
+        index: u32,
+        info: &StreamInfo,
+        source: &mut Reader<Cursor<Box<[u8]>>>,
+        chunk: &mut Vec<u8>,
+        options: EncodeOptions<'_>,
+    ) -> Result<Option<Self>, EncodeError> {
+        check_output(format, options)?;
+
+        // method of determining sample endianness for PCM24, PCM32, and PCMFLOAT is currently unknown
+        let pcm = |sample_format, order, byte_depth, chunk: &mut Vec<u8>| {
+            pcm::Chunks::new(sample_format, order, byte_depth, info, chunk, options)
+                .map(Self::Pcm)
+                .map(Some)
+        };
+
+        Ok(match format {
+            AudioFormat::Pcm8 => pcm(Format::Integer, Endianness::Little, 1, chunk)?,
+            AudioFormat::Pcm16 => {
+                let order = if BankFlags::new(flags).big_endian_pcm() {
+                    Endianness::Big
+                } else {
+                    Endianness::Little
+                };
+
+                pcm(Format::Integer, order, 2, chunk)?
+            }
+            AudioFormat::Pcm24 => pcm(Format::Integer, Endianness::Little, 3, chunk)?,
+            AudioFormat::Pcm32 => pcm(Format::Integer, Endianness::Little, 4, chunk)?,
+            AudioFormat::PcmFloat => pcm(Format::Float, Endianness::Little, 4, chunk)?,
+            AudioFormat::Mpeg if options.mpeg_fix && !options.decode_mpeg => {
+                Some(Self::Mpeg(mpeg::Chunks::new(info, index, source, chunk, options)?))
+            }
+            AudioFormat::ImaAdpcm
+            | AudioFormat::FAdpcm
+            | AudioFormat::GcAdpcm
+            | AudioFormat::HeVag => {
+                let decoder = BlockDecoder::new(format, flags, info, source)?;
+                let samples = decoder
+                    .samples_left()
+                    .expect("only Vorbis streams have an unknown decoded length");
+                let data_size = (samples * usize::from(info.channels.get()) * 2) as u64;
+                let spec = WavSpec::pcm16(info.channels.get().into(), info.sample_rate.get())
+                    .with_options(info, options, data_size);
+
+                Some(Self::Decoded(DecodedChunks::new(decoder, spec, data_size, chunk)))
+            }
+            #[cfg(feature = "decode-vorbis")]
+            AudioFormat::Vorbis if options.decode_vorbis => {
+                let mut decoder = vorbis::BlockDecoder::new(info, source)?;
+                let spec = vorbis::decode_spec(info, options);
+                let data_size = vorbis::decoded_data_size(info, source, &mut decoder, spec)?;
+                let decoder = BlockDecoder::Vorbis(Box::new(decoder));
+
+                Some(Self::Decoded(DecodedChunks::new(decoder, spec, data_size, chunk)))
+            }
+            #[cfg(feature = "decode-vorbis")]
+            AudioFormat::Vorbis if options.repackage_vorbis => {
+                Some(Self::Ogg(Box::new(vorbis::Chunks::new(info, source, chunk)?)))
+            }
+            #[cfg(feature = "vorbis")]
+            AudioFormat::Vorbis => Some(Self::Reencoded(Box::new(vorbis::ReencodedChunks::new(
+                info, source, chunk,
+            )?))),
+            _ => None,
+        })
+    }
+
+    // Appends the next chunk of the encoded file to `chunk`, returning false at the end of the file.
+    fn next_chunk(
+        &mut self,
+        source: &mut Reader<Cursor<Box<[u8]>>>,
+        chunk: &mut Vec<u8>,
+    ) -> Result<bool, EncodeError> {
+        Ok(match self {
+            Self::Raw(chunks) => chunks.next_chunk(source, chunk)?,
+            Self::Pcm(chunks) => chunks.next_chunk(source, chunk)?,
+            Self::Decoded(chunks) => chunks.next_chunk(source, chunk)?,
+            Self::Mpeg(chunks) => chunks.next_chunk(source, chunk)?,
+            #[cfg(feature = "decode-vorbis")]
+            Self::Ogg(chunks) => chunks.next_chunk(source, chunk)?,
+            #[cfg(feature = "vorbis")]
+            Self::Reencoded(chunks) => chunks.next_chunk(source, chunk)?,
+            Self::Encoded => false,
+        })
+    }
+}
+
+// Writes decoded samples as 16-bit PCM samples, a block at a time.
+// The header and trailer are written when the encoder is created, since the size of the decoded data is known by then.
+struct DecodedChunks {
+    decoder: BlockDecoder,
+    big_endian: bool,
+    block: Vec<i16>,
+    trailer: Vec<u8>,
+}
+
+impl DecodedChunks {
+    fn new(decoder: BlockDecoder, spec: WavSpec<'_>, data_size: u64, header: &mut Vec<u8>) -> Self {
+        let mut trailer = Vec::new();
+
+        spec.write_header(data_size, &mut *header)
+            .and_then(|()| spec.write_trailer(data_size, &mut trailer))
+            .expect("writing to a Vec<u8> can't fail");
+
+        Self {
+            decoder,
+            big_endian: spec.is_big_endian(),
+            block: Vec::new(),
+            trailer,
+        }
+    }
+
+    fn next_chunk(
+        &mut self,
+        source: &mut Reader<Cursor<Box<[u8]>>>,
+        chunk: &mut Vec<u8>,
+    ) -> Result<bool, EncodeError> {
+        if self.decoder.next_block(source, &mut self.block)? {
+            for &sample in &self.block {
+                let bytes = if self.big_endian {
+                    sample.to_be_bytes()
+                } else {
+                    sample.to_le_bytes()
+                };
+
+                chunk.extend_from_slice(&bytes);
+            }
+
+            return Ok(true);
+        }
+
+        chunk.append(&mut self.trailer);
+        Ok(false)
+    }
+}
+
+impl Read for StreamReader {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        let available = self.fill_buf()?;
+        let len = min(available.len(), buf.len());
+
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl BufRead for StreamReader {
+    fn fill_buf(&mut self) -> IoResult<&[u8]> {
+        // chunks can be empty (e.g. a Vorbis packet that only primes the decoder), so chunks are encoded until one isn't
+        while self.position == self.chunk.len() && !self.finished {
+            self.chunk.clear();
+            self.position = 0;
+
+            match self.encoder.next_chunk(&mut self.source, &mut self.chunk) {
+                Ok(more) => self.finished = !more,
+                Err(e) => {
+                    self.finished = true;
+                    self.chunk.clear();
+                    return Err(IoError::other(e));
+                }
+            }
+        }
+
+        Ok(&self.chunk[self.position..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position = min(self.position + amount, self.chunk.len());
+    }
+}
+
+impl Debug for StreamReader {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("StreamReader")
+            .field("format", &self.format)
+            .field("finished", &self.finished)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::StreamReader;
+    use crate::{
+        encode::{encode, EmptyStreams, EncodeError, EncodeOptions, OutputMode, PcmOutput},
+        header::{AudioFormat, BankFlags, StreamInfo},
+        read::Reader,
+    };
+    use std::io::{ErrorKind, Read};
+
+    // Reads the whole encoded file in small pieces, so that chunks are split across reads.
+    fn read_all(
+        format: AudioFormat,
+        flags: u32,
+        info: &StreamInfo,
+        data: &[u8],
+        options: EncodeOptions<'_>,
+    ) -> Vec<u8> {
+        let mut reader = StreamReader::new(format, flags, 0, info, data.into(), options).unwrap();
+        let mut output = Vec::new();
+        let mut buf = [0; 7];
+
+        loop {
+            let len = reader.read(&mut buf).unwrap();
+            if len == 0 {
+                return output;
+            }
+
+            output.extend_from_slice(&buf[..len]);
+        }
+    }
+
+    fn encode_all(
+        format: AudioFormat,
+        flags: u32,
+        info: &StreamInfo,
+        data: &[u8],
+        options: EncodeOptions<'_>,
+    ) -> Vec<u8> {
+        encode(format, flags, 0, info, &mut Reader::new(data), Vec::new(), options).unwrap()
+    }
+
+    #[test]
+    fn read_same_bytes_as_encoder() {
+        // enough data for several blocks of frames, with a trailing incomplete frame
+        let data: Vec<u8> = (0..0x3001u32).map(|i| (i * 37 % 251) as u8).collect();
+        let adpcm_data: Vec<u8> = data.iter().map(|byte| byte & 0x0F).collect();
+
+        let wav = EncodeOptions::default();
+        let aiff = EncodeOptions::new().pcm_output(PcmOutput::Aiff);
+        let raw = EncodeOptions::new().output_mode(OutputMode::Raw);
+        let big_endian = BankFlags::BIG_ENDIAN_PCM;
+
+        for (format, flags, channels, data, options) in [
+            (AudioFormat::Pcm8, 0, 2, &data, wav),
+            (AudioFormat::Pcm16, 0, 2, &data, wav),
+            (AudioFormat::Pcm16, big_endian, 2, &data, wav),
+            (AudioFormat::Pcm16, 0, 2, &data, aiff),
+            (AudioFormat::Pcm24, 0, 1, &data, wav),
+            (AudioFormat::PcmFloat, 0, 1, &data, wav),
+            (AudioFormat::ImaAdpcm, 0, 2, &adpcm_data, wav),
+            (AudioFormat::FAdpcm, 0, 2, &adpcm_data, aiff),
+            (AudioFormat::HeVag, 0, 1, &adpcm_data, wav),
+            (AudioFormat::Xma, 0, 2, &data, wav),
+            (AudioFormat::Atrac9, 0, 2, &data, raw),
+        ] {
+            let info = StreamInfo {
+                format,
+                ..StreamInfo::dummy(channels, 0x1000, 0x3001)
+            };
+
+            assert_eq!(
+                read_all(format, flags, &info, data, options),
+                encode_all(format, flags, &info, data, options),
+                "{format} ({options:?})"
+            );
+        }
+    }
+
+    #[test]
+    fn read_mpeg_frames() {
+        // MPEG-1 Layer III, 128 kbps, 44100 Hz, no padding
+        let data: Vec<u8> = (0..4u8)
+            .flat_map(|fill| {
+                let mut frame = vec![fill; 417];
+                frame[..4].copy_from_slice(&[0xFF, 0xFB, 0x90, 0x64]);
+                frame
+            })
+            .collect();
+
+        let info = StreamInfo {
+            name: Some("theme".into()),
+            ..StreamInfo::dummy(2, 1152 * 4, 417 * 4)
+        };
+
+        for options in [
+            EncodeOptions::default(),
+            EncodeOptions::new().id3_tag(false).xing_header(false),
+        ] {
+            assert_eq!(
+                read_all(AudioFormat::Mpeg, 0, &info, &data, options),
+                encode_all(AudioFormat::Mpeg, 0, &info, &data, options)
+            );
+        }
+    }
+
+    #[cfg(feature = "decode-vorbis")]
+    #[test]
+    fn read_vorbis_streams() {
+        // audio packets that only hold the packet type and mode number
+        let data = [1, 0, 0].repeat(4);
+        let info = StreamInfo {
+            vorbis_crc32: Some(0xA722_97FF),
+            ..StreamInfo::dummy(2, 300, 12)
+        };
+
+        let decode = EncodeOptions::new().decode_vorbis(true);
+        assert_eq!(
+            read_all(AudioFormat::Vorbis, 0, &info, &data, decode),
+            encode_all(AudioFormat::Vorbis, 0, &info, &data, decode)
+        );
+
+        let repackage = EncodeOptions::new().repackage_vorbis(true);
+        assert_eq!(
+            read_all(AudioFormat::Vorbis, 0, &info, &data, repackage),
+            encode_all(AudioFormat::Vorbis, 0, &info, &data, repackage)
+        );
+
+        // re-encoded streams get a random serial number, which is left out of the comparison along with
+        // the checksums of pages
+        #[cfg(feature = "vorbis")]
+        {
+            let without_serial = |mut file: Vec<u8>| {
+                let pages: Vec<_> = file
+                    .windows(4)
+                    .enumerate()
+                    .filter_map(|(start, window)| (window == b"OggS").then_some(start))
+                    .collect();
+                for start in pages {
+                    file[start + 14..start + 18].fill(0);
+                    file[start + 22..start + 26].fill(0);
+                }
+                file
+            };
+
+            let read = read_all(AudioFormat::Vorbis, 0, &info, &data, EncodeOptions::new());
+            let encoded = encode_all(AudioFormat::Vorbis, 0, &info, &data, EncodeOptions::new());
+            assert_eq!(without_serial(read), without_serial(encoded));
+        }
+
+        // without the `vorbis` feature, streams have to be decoded or repackaged
+        #[cfg(not(feature = "vorbis"))]
+        assert!(StreamReader::new(
+            AudioFormat::Vorbis,
+            0,
+            0,
+            &info,
+            data.into(),
+            EncodeOptions::new()
+        )
+        .is_err_and(|e| matches!(e, EncodeError::UnsupportedFormat { .. })));
+    }
+
+    #[test]
+    fn move_between_threads() {
+        // readers can be handed to other threads, even while they hold a Vorbis encoder
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<StreamReader>();
+    }
+
+    #[test]
+    fn read_empty_streams() {
+        let info = StreamInfo::dummy(2, 1, 0);
+        let skip = EncodeOptions::new().empty_streams(EmptyStreams::Skip);
+        let write = EncodeOptions::new().empty_streams(EmptyStreams::Write);
+
+        assert!(read_all(AudioFormat::Pcm16, 0, &info, &[], skip).is_empty());
+        assert_eq!(
+            read_all(AudioFormat::Pcm16, 0, &info, &[], write),
+            encode_all(AudioFormat::Pcm16, 0, &info, &[], write)
+        );
+    }
+
+    #[test]
+    fn end_after_error() {
+        // the stream header claims more data than there is
+        let info = StreamInfo::dummy(1, 64, 0x100);
+        let mut reader = StreamReader::new(
+            AudioFormat::ImaAdpcm,
+            0,
+            0,
+            &info,
+            [0; 0x10].into(),
+            EncodeOptions::default(),
+        )
+        .unwrap();
+
+        let mut output = Vec::new();
+        let error = reader.read_to_end(&mut output).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Other);
+        assert!(matches!(
+            error.get_ref().and_then(|e| e.downcast_ref()),
+            Some(EncodeError::ImaAdpcm(_))
+        ));
+
+        // only the file header was read
+        assert_eq!(output.len(), 44);
+        assert_eq!(reader.read(&mut [0; 16]).unwrap(), 0);
+    }
+}
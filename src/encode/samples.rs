@@ -0,0 +1,423 @@
+#[cfg(feature = "decode-vorbis")]
+use super::vorbis;
+use super::{
+    celt::{CeltError, CeltErrorKind},
+    fadpcm, gc_adpcm, hevag, ima_adpcm,
+    pcm::{self, Endianness},
+    wav::Format,
+    EncodeError,
+};
+use crate::header::{AudioFormat, BankFlags, Loop, StreamInfo};
+use crate::read::Reader;
+use std::{
+    borrow::Cow,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    io::{empty, Cursor, Read},
+    iter::FusedIterator,
+    mem::replace,
+    num::{NonZeroU32, NonZeroU8},
+};
+
+/// An iterator over the decoded audio samples of a stream.
+///
+/// This type is returned from [`Stream::samples`] and [`Stream::into_samples`].
+/// Samples are 16-bit integers, interleaved by channel: the first sample of every channel is returned,
+/// then the second sample of every channel, and so on. Use [`Samples::channels`] and [`Samples::sample_rate`]
+/// to interpret them.
+///
+/// Stream data is decoded lazily, one block at a time, so the decoded stream is never held in memory at once.
+/// When iterating, `Some(Err(EncodeError))` is returned if a block could not be decoded, after which the iterator ends.
+///
+/// [`Stream::samples`]: crate::Stream::samples
+/// [`Stream::into_samples`]: crate::Stream::into_samples
+pub struct Samples<'stream> {
+    format: AudioFormat,
+    flags: u32,
+    info: Cow<'stream, StreamInfo>,
+    source: Reader<Cursor<Cow<'stream, [u8]>>>,
+    decoder: BlockDecoder,
+    block: Vec<i16>,
+    position: usize,
+    finished: bool,
+}
+
+// Returns the error that decoding the stream to 16-bit samples would fail with, without reading any stream data.
+pub(crate) fn check_decode(info: &StreamInfo) -> Result<(), EncodeError> {
+    BlockDecoder::new(info.format, info.flags.bits(), info, &Reader::new(empty())).map(drop)
+}
+
+// Decodes the stream data of any format that can be decoded to 16-bit samples.
+pub(super) enum BlockDecoder {
+    Pcm(pcm::BlockDecoder),
+    ImaAdpcm(ima_adpcm::BlockDecoder),
+    FAdpcm(fadpcm::BlockDecoder),
+    GcAdpcm(gc_adpcm::BlockDecoder),
+    HeVag(hevag::BlockDecoder),
+    #[cfg(feature = "decode-vorbis")]
+    Vorbis(Box<vorbis::BlockDecoder>),
+}
+
+impl BlockDecoder {
+    pub(super) fn new<R: Read>(
+        format: AudioFormat,
+        flags: u32,
+        info: &StreamInfo,
+        source: &Reader<R>,
+    ) -> Result<Self, EncodeError> {
+        // method of determining sample endianness for PCM24, PCM32, and PCMFLOAT is currently unknown
+        Ok(match format {
+            AudioFormat::Pcm8 => {
+                Self::Pcm(pcm::BlockDecoder::new(Format::Integer, Endianness::Little, 1, info))
+            }
+            AudioFormat::Pcm16 => {
+                let order = if BankFlags::new(flags).big_endian_pcm() {
+                    Endianness::Big
+                } else {
+                    Endianness::Little
+                };
+
+                Self::Pcm(pcm::BlockDecoder::new(Format::Integer, order, 2, info))
+            }
+            AudioFormat::Pcm24 => {
+                Self::Pcm(pcm::BlockDecoder::new(Format::Integer, Endianness::Little, 3, info))
+            }
+            AudioFormat::Pcm32 => {
+                Self::Pcm(pcm::BlockDecoder::new(Format::Integer, Endianness::Little, 4, info))
+            }
+            AudioFormat::PcmFloat => {
+                Self::Pcm(pcm::BlockDecoder::new(Format::Float, Endianness::Little, 4, info))
+            }
+            AudioFormat::ImaAdpcm => Self::ImaAdpcm(ima_adpcm::BlockDecoder::new(info, source)),
+            AudioFormat::FAdpcm => Self::FAdpcm(fadpcm::BlockDecoder::new(info, source)),
+            AudioFormat::GcAdpcm => Self::GcAdpcm(gc_adpcm::BlockDecoder::new(info, source)?),
+            AudioFormat::HeVag => Self::HeVag(hevag::BlockDecoder::new(info, source)),
+            #[cfg(feature = "decode-vorbis")]
+            AudioFormat::Vorbis => Self::Vorbis(Box::new(vorbis::BlockDecoder::new(info, source)?)),
+            // the error names the version of CELT that a decoder would need
+            AudioFormat::Celt => {
+                let version = info.celt_version().expect("CELT streams have a CELT version");
+                return Err(CeltError::new(CeltErrorKind::DecodeUnsupported { version }).into());
+            }
+            // other formats are only copied or repackaged when encoded, without being decoded
+            _ => return Err(EncodeError::UnsupportedFormat { format }),
+        })
+    }
+
+    // Returns the number of samples per channel left to decode,
+    // or `None` for Vorbis streams, whose decoded length isn't known until they are decoded.
+    #[cfg_attr(not(feature = "decode-vorbis"), allow(clippy::unnecessary_wraps))]
+    pub(super) fn samples_left(&self) -> Option<usize> {
+        match self {
+            Self::Pcm(decoder) => Some(decoder.frames_left),
+            Self::ImaAdpcm(decoder) => Some(decoder.samples_left),
+            Self::FAdpcm(decoder) => Some(decoder.samples_left),
+            Self::GcAdpcm(decoder) => Some(decoder.samples_left),
+            Self::HeVag(decoder) => Some(decoder.samples_left),
+            #[cfg(feature = "decode-vorbis")]
+            Self::Vorbis(_) => None,
+        }
+    }
+
+    // Replaces the contents of `block` with the next decoded block of interleaved samples,
+    // returning false at the end of the stream.
+    pub(super) fn next_block<R: Read>(
+        &mut self,
+        source: &mut Reader<R>,
+        block: &mut Vec<i16>,
+    ) -> Result<bool, EncodeError> {
+        Ok(match self {
+            Self::Pcm(decoder) => decoder.next_block(source, block)?,
+            Self::ImaAdpcm(decoder) => decoder.next_block(source, block)?,
+            Self::FAdpcm(decoder) => decoder.next_block(source, block)?,
+            Self::GcAdpcm(decoder) => decoder.next_block(source, block)?,
+            Self::HeVag(decoder) => decoder.next_block(source, block)?,
+            #[cfg(feature = "decode-vorbis")]
+            Self::Vorbis(decoder) => {
+                block.clear();
+
+                let Some(channels) = decoder.next_block::<_, i16>(source)? else {
+                    return Ok(false);
+                };
+
+                let frames = channels.first().map_or(0, Vec::len);
+                for index in 0..frames {
+                    block.extend(channels.iter().map(|channel| channel[index]));
+                }
+
+                true
+            }
+        })
+    }
+}
+
+impl<'stream> Samples<'stream> {
+    pub(crate) fn new(
+        format: AudioFormat,
+        flags: u32,
+        info: Cow<'stream, StreamInfo>,
+        data: Cow<'stream, [u8]>,
+    ) -> Result<Self, EncodeError> {
+        let source = Reader::new(Cursor::new(data));
+        let decoder = BlockDecoder::new(format, flags, &info, &source)?;
+
+        Ok(Self {
+            format,
+            flags,
+            info,
+            source,
+            decoder,
+            block: Vec::new(),
+            position: 0,
+            finished: false,
+        })
+    }
+
+    /// Starts decoding the stream again from its first sample, even after the iterator has ended.
+    ///
+    /// # Errors
+    /// This function returns an error if the decoder can't be created again,
+    /// which is only possible if creating it failed in the first place.
+    pub fn rewind(&mut self) -> Result<(), EncodeError> {
+        // the stream data is moved into a new reader, which starts at the first byte
+        let empty = Reader::new(Cursor::new(Cow::Borrowed(&[][..])));
+        let data = replace(&mut self.source, empty).into_inner().into_inner();
+        self.source = Reader::new(Cursor::new(data));
+
+        self.decoder = BlockDecoder::new(self.format, self.flags, &self.info, &self.source)?;
+        self.block.clear();
+        self.position = 0;
+        self.finished = false;
+        Ok(())
+    }
+
+    /// Returns the number of channels that samples are interleaved by.
+    #[must_use]
+    pub fn channels(&self) -> NonZeroU8 {
+        self.info.channels
+    }
+
+    /// Returns the sample rate (Hz) of the stream.
+    #[must_use]
+    pub fn sample_rate(&self) -> NonZeroU32 {
+        self.info.sample_rate
+    }
+
+    /// Returns the number of samples per channel declared by the stream header.
+    ///
+    /// Fewer samples can be returned if the stream data is cut short.
+    #[must_use]
+    pub fn sample_count(&self) -> NonZeroU32 {
+        self.info.num_samples
+    }
+
+    /// Returns the loop points of the stream, if it has any.
+    #[must_use]
+    pub fn loop_info(&self) -> Option<Loop> {
+        self.info.stream_loop
+    }
+
+    // Replaces the current block with the next decoded block, returning false at the end of the stream.
+    fn next_block(&mut self) -> Result<bool, EncodeError> {
+        self.decoder.next_block(&mut self.source, &mut self.block)
+    }
+}
+
+impl Iterator for Samples<'_> {
+    type Item = Result<i16, EncodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // decoded blocks can be empty (e.g. the first packet of a Vorbis stream), so blocks are decoded until one has samples
+        while self.position == self.block.len() {
+            if self.finished {
+                return None;
+            }
+
+            self.position = 0;
+
+            match self.next_block() {
+                Ok(true) => {}
+                Ok(false) => {
+                    self.finished = true;
+                    self.block.clear();
+                }
+                Err(e) => {
+                    self.finished = true;
+                    self.block.clear();
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        let sample = self.block[self.position];
+        self.position += 1;
+        Some(Ok(sample))
+    }
+}
+
+impl FusedIterator for Samples<'_> {}
+
+impl Debug for Samples<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Samples")
+            .field("format", &self.format)
+            .field("channels", &self.info.channels)
+            .field("sample_rate", &self.info.sample_rate)
+            .field("sample_count", &self.info.num_samples)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Samples;
+    use crate::{
+        encode::{
+            encode, CeltErrorKind, CeltVersion, EncodeError, EncodeOptions, GcAdpcmErrorKind,
+        },
+        header::{AudioFormat, BankFlags, StreamInfo},
+        read::Reader,
+    };
+    use std::borrow::Cow;
+
+    fn samples<'a>(
+        format: AudioFormat,
+        flags: u32,
+        info: &'a StreamInfo,
+        data: &'a [u8],
+    ) -> Result<Samples<'a>, EncodeError> {
+        Samples::new(format, flags, Cow::Borrowed(info), Cow::Borrowed(data))
+    }
+
+    fn decode(format: AudioFormat, flags: u32, info: &StreamInfo, data: &[u8]) -> Vec<i16> {
+        samples(format, flags, info, data)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn convert_pcm_samples() {
+        let info = StreamInfo::dummy(2, 2, 4);
+        assert_eq!(
+            decode(AudioFormat::Pcm8, 0, &info, &[0x01, 0x80, 0xFF, 0x7F]),
+            [0x0100, -0x8000, -0x0100, 0x7F00]
+        );
+
+        // 16-bit samples are stored in big-endian byte order if the sound bank's flags say so
+        let data = [0x12, 0x34, 0xFF, 0xFE];
+        assert_eq!(decode(AudioFormat::Pcm16, 0, &info, &data), [0x3412, -0x0101]);
+        let big_endian = BankFlags::BIG_ENDIAN_PCM;
+        assert_eq!(
+            decode(AudioFormat::Pcm16, big_endian, &info, &data),
+            [0x1234, -0x0002]
+        );
+
+        // the most significant 16 bits are kept, and a trailing incomplete frame is dropped
+        let info = StreamInfo::dummy(1, 2, 7);
+        let data = [0x00, 0x34, 0x12, 0xFF, 0xCD, 0xAB, 0x99];
+        assert_eq!(decode(AudioFormat::Pcm24, 0, &info, &data), [0x1234, -0x5433]);
+
+        let data: Vec<_> = [0.5f32, -1.0, 2.0].iter().flat_map(|s| s.to_le_bytes()).collect();
+        let info = StreamInfo::dummy(1, 3, 12);
+        assert_eq!(decode(AudioFormat::PcmFloat, 0, &info, &data), [16384, -32768, 32767]);
+    }
+
+    #[test]
+    fn decode_same_samples_as_encoder() {
+        // IMA ADPCM, FADPCM and HEVAG blocks of 2 channels, with the final block cut short
+        let data: Vec<u8> = (0..0x200u32).map(|i| (i * 37 % 251) as u8 & 0x0F).collect();
+
+        for format in [
+            AudioFormat::ImaAdpcm,
+            AudioFormat::FAdpcm,
+            AudioFormat::HeVag,
+        ] {
+            let info = StreamInfo {
+                format,
+                ..StreamInfo::dummy(2, 400, 0x200)
+            };
+
+            let encoded = encode(
+                format,
+                0,
+                0,
+                &info,
+                &mut Reader::new(data.as_slice()),
+                Vec::new(),
+                EncodeOptions::default(),
+            )
+            .unwrap();
+
+            let expected: Vec<_> = encoded[44..]
+                .chunks_exact(2)
+                .map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+                .collect();
+
+            let samples = samples(format, 0, &info, &data).unwrap();
+            assert_eq!(samples.channels().get(), 2);
+            assert_eq!(samples.sample_rate().get(), 44100);
+            assert_eq!(samples.sample_count().get(), 400);
+            assert_eq!(samples.collect::<Result<Vec<_>, _>>().unwrap(), expected, "{format}");
+        }
+    }
+
+    #[test]
+    fn end_after_error() {
+        // the stream header claims more data than there is
+        let info = StreamInfo::dummy(1, 64, 0x100);
+        let mut samples = samples(AudioFormat::ImaAdpcm, 0, &info, &[0; 0x10]).unwrap();
+
+        assert!(matches!(samples.next(), Some(Err(EncodeError::ImaAdpcm(_)))));
+        assert!(samples.next().is_none());
+    }
+
+    #[test]
+    fn reject_undecodable_streams() {
+        let info = StreamInfo::dummy(2, 2, 4);
+
+        for format in [
+            AudioFormat::Mpeg,
+            AudioFormat::Xma,
+            AudioFormat::Unknown(99),
+        ] {
+            assert!(matches!(
+                samples(format, 0, &info, &[0; 4]),
+                Err(EncodeError::UnsupportedFormat { format: f }) if f == format
+            ));
+        }
+
+        let Err(EncodeError::GcAdpcm(error)) = samples(AudioFormat::GcAdpcm, 0, &info, &[0; 4])
+        else {
+            panic!("GC ADPCM streams can't be decoded without coefficients");
+        };
+        assert_eq!(error.kind(), GcAdpcmErrorKind::MissingCoefficients);
+    }
+
+    #[test]
+    fn name_celt_version_when_rejecting_celt_streams() {
+        let info = StreamInfo {
+            format: AudioFormat::Celt,
+            ..StreamInfo::dummy(2, 2, 4)
+        };
+        assert_eq!(info.celt_version(), Some(CeltVersion::V0_11));
+
+        let Err(EncodeError::Celt(error)) = samples(AudioFormat::Celt, 0, &info, &[0; 4]) else {
+            panic!("CELT streams can't be decoded");
+        };
+        assert_eq!(
+            error.kind(),
+            CeltErrorKind::DecodeUnsupported {
+                version: CeltVersion::V0_11
+            }
+        );
+        assert_eq!(error.to_string(), "decoding CELT 0.11 streams is not supported");
+
+        // the same error is returned without reading any stream data
+        assert!(info.check_decode().is_err_and(|e| matches!(
+            e,
+            EncodeError::Celt(e) if e.kind() == error.kind()
+        )));
+        assert!(StreamInfo::dummy(2, 2, 4).check_decode().is_ok());
+        assert_eq!(StreamInfo::dummy(2, 2, 4).celt_version(), None);
+    }
+}
@@ -0,0 +1,213 @@
+use super::Samples;
+use rodio::{ChannelCount, Sample, SampleRate, Source};
+use std::time::Duration;
+
+/// A [`rodio::Source`] that plays the decoded audio samples of a stream.
+///
+/// This type is returned from [`Stream::into_source`], and requires the `rodio` feature.
+/// Streams can be decoded if they can be iterated over with [`Samples`].
+/// Playback ends early if the stream data could not be decoded.
+///
+/// By default, the stream is played once. With [`SampleSource::looping`], the stream is played
+/// from its start up to the end of its loop, then the loop is repeated forever.
+///
+/// # Examples
+///
+/// ```no_run
+/// use fsbex::Bank;
+/// use std::{error::Error, fs::File, io::BufReader};
+///
+/// fn first_stream_source() -> Result<fsbex::encode::SampleSource, Box<dyn Error>> {
+///     let bank = Bank::new(BufReader::new(File::open("example.fsb")?))?;
+///     let stream = bank.into_iter().next().ok_or("sound bank has no streams")?;
+///     Ok(stream.into_source()?.looping(true))
+/// }
+/// ```
+///
+/// [`Stream::into_source`]: crate::Stream::into_source
+#[derive(Debug)]
+pub struct SampleSource {
+    samples: Samples<'static>,
+    looping: bool,
+    // index of the next interleaved sample within the stream
+    position: u64,
+}
+
+impl SampleSource {
+    /// Creates a source that plays the provided samples.
+    #[must_use]
+    pub fn new(samples: Samples<'static>) -> Self {
+        Self {
+            samples,
+            looping: false,
+            position: 0,
+        }
+    }
+
+    /// Sets whether the loop of the stream is repeated forever, instead of the stream being played once.
+    /// Streams without loop points are repeated from their start.
+    #[must_use]
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    // Returns the range of interleaved samples that are repeated when looping, as a start and an exclusive end.
+    // The end is `None` if the loop lasts until the end of the stream.
+    fn loop_range(&self) -> (u64, Option<u64>) {
+        let channels = u64::from(self.samples.channels().get());
+
+        match self.samples.loop_info() {
+            Some(stream_loop) => (
+                u64::from(stream_loop.start()) * channels,
+                Some((u64::from(stream_loop.end()) + 1) * channels),
+            ),
+            None => (0, None),
+        }
+    }
+
+    // Decodes the stream again up to the start of its loop. Returns `None` if playback should stop instead,
+    // which happens if the loop is empty or the stream could not be decoded up to the start of the loop.
+    fn restart_loop(&mut self, start: u64) -> Option<()> {
+        if self.position <= start {
+            return None;
+        }
+
+        self.samples.rewind().ok()?;
+        self.position = 0;
+
+        while self.position < start {
+            let _sample = self.samples.next()?.ok()?;
+            self.position += 1;
+        }
+
+        Some(())
+    }
+}
+
+impl From<Samples<'static>> for SampleSource {
+    fn from(samples: Samples<'static>) -> Self {
+        Self::new(samples)
+    }
+}
+
+impl Iterator for SampleSource {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.looping {
+            let (start, end) = self.loop_range();
+
+            if end.is_some_and(|end| self.position >= end) && self.restart_loop(start).is_none() {
+                self.looping = false;
+                return None;
+            }
+        }
+
+        let sample = match self.samples.next() {
+            Some(Ok(sample)) => sample,
+            // decoding errors end playback, even when looping
+            Some(Err(_)) => {
+                self.looping = false;
+                return None;
+            }
+            None if self.looping => {
+                let (start, _) = self.loop_range();
+
+                let Some(sample) =
+                    self.restart_loop(start).and_then(|()| self.samples.next()?.ok())
+                else {
+                    self.looping = false;
+                    return None;
+                };
+
+                sample
+            }
+            None => return None,
+        };
+
+        self.position += 1;
+        Some(Sample::from(sample) / 32768.0)
+    }
+}
+
+impl Source for SampleSource {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.samples.channels().into()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.samples.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        if self.looping {
+            return None;
+        }
+
+        let nanos = u64::from(self.samples.sample_count().get()) * 1_000_000_000
+            / u64::from(self.samples.sample_rate().get());
+
+        Some(Duration::from_nanos(nanos))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SampleSource;
+    use crate::{
+        encode::Samples,
+        header::{AudioFormat, Loop, StreamInfo},
+    };
+    use rodio::Source;
+    use std::{borrow::Cow, time::Duration};
+
+    fn pcm_source(stream_loop: Option<Loop>) -> SampleSource {
+        let data: Vec<_> = [1i16, 2, 3, 4].iter().flat_map(|s| s.to_le_bytes()).collect();
+        let info = StreamInfo {
+            stream_loop,
+            ..StreamInfo::dummy(1, 4, 8)
+        };
+
+        Samples::new(AudioFormat::Pcm16, 0, Cow::Owned(info), Cow::Owned(data))
+            .unwrap()
+            .into()
+    }
+
+    fn to_i16(samples: impl Iterator<Item = rodio::Sample>) -> Vec<i16> {
+        #[allow(clippy::cast_possible_truncation)]
+        samples.map(|sample| (sample * 32768.0) as i16).collect()
+    }
+
+    #[test]
+    fn play_stream_once() {
+        let source = pcm_source(Some(Loop::dummy(1, 2)));
+
+        assert_eq!(source.channels().get(), 1);
+        assert_eq!(source.sample_rate().get(), 44100);
+        assert_eq!(source.total_duration(), Some(Duration::from_nanos(90_702)));
+        assert_eq!(to_i16(source), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn repeat_loop() {
+        // samples after the end of the loop are never played
+        let source = pcm_source(Some(Loop::dummy(1, 2))).looping(true);
+        assert_eq!(source.total_duration(), None);
+        assert_eq!(to_i16(source.take(7)), [1, 2, 3, 2, 3, 2, 3]);
+
+        // streams without loop points are repeated from their start
+        let source = pcm_source(None).looping(true);
+        assert_eq!(to_i16(source.take(6)), [1, 2, 3, 4, 1, 2]);
+    }
+
+    #[test]
+    fn send_to_playback_thread() {
+        fn assert_send<T: Send + 'static>() {}
+        assert_send::<SampleSource>();
+    }
+}
@@ -0,0 +1,175 @@
+use crate::header::StreamInfo;
+use crate::read::Reader;
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io::{copy, Error as IoError, Read, Write},
+};
+
+// VAG file header information taken from vgmstream:
+// [1]: https://github.com/vgmstream/vgmstream/blob/master/src/meta/vag.c
+
+const VERSION: u32 = 0x20;
+
+// The name field holds up to 16 bytes, and is terminated with a null byte if it's shorter.
+// Names are cut off at 15 bytes so that the terminator always fits.
+const NAME_SIZE: usize = 0x10;
+const MAX_NAME_LEN: usize = NAME_SIZE - 1;
+
+/// Encodes a VAG stream by wrapping the raw stream data in a `VAGp` file.
+///
+/// The stream data is copied verbatim; only the file header is constructed.
+/// Streams with more than 1 channel keep their frame interleave (0x10 bytes),
+/// and the number of channels is written to the header for tools that support interleaved VAG files.
+pub(super) fn encode<R: Read, W: Write>(
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+    mut sink: W,
+) -> Result<W, VagError> {
+    write_header(info, &mut sink).map_err(VagError::from_io(VagErrorKind::CreateHeader))?;
+
+    let stream_size = info.size as usize;
+
+    // There could be more data after the stream, so a limit is placed on the number of bytes read.
+    let _bytes_copied = copy(&mut source.limit(stream_size as u64), &mut sink)
+        .map_err(VagError::from_io(VagErrorKind::EncodeStream))?;
+
+    sink.flush()
+        .map(|()| sink)
+        .map_err(VagError::from_io(VagErrorKind::FinishStream))
+}
+
+fn write_header<W: Write>(info: &StreamInfo, sink: &mut W) -> Result<(), IoError> {
+    let channels = info.channels.get();
+
+    // the data size field holds the size of a single channel
+    let channel_size = info.size / u32::from(channels);
+
+    let mut name = [0; NAME_SIZE];
+    let name_bytes = truncate_name(info.name.as_deref().unwrap_or_default()).as_bytes();
+    name[..name_bytes.len()].copy_from_slice(name_bytes);
+
+    // all values are stored as big-endian
+    sink.write_all(b"VAGp")?;
+    sink.write_all(&VERSION.to_be_bytes())?;
+    sink.write_all(&[0; 4])?;
+    sink.write_all(&channel_size.to_be_bytes())?;
+    sink.write_all(&info.sample_rate.get().to_be_bytes())?;
+    sink.write_all(&[0; 10])?;
+    sink.write_all(&[channels, 0])?;
+    sink.write_all(&name)
+}
+
+// Shortens a name to fit in the header, without splitting a multi-byte character.
+fn truncate_name(name: &str) -> &str {
+    let end = name
+        .char_indices()
+        .map(|(index, char)| index + char.len_utf8())
+        .take_while(|&end| end <= MAX_NAME_LEN)
+        .last()
+        .unwrap_or_default();
+
+    &name[..end]
+}
+
+/// Represents an error that can occur when encoding a VAG stream.
+///
+/// See [`VagErrorKind`] for the different kinds of errors that can occur.
+#[derive(Debug)]
+pub struct VagError {
+    kind: VagErrorKind,
+    source: IoError,
+}
+
+/// A variant of a [`VagError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum VagErrorKind {
+    /// Failed to write the file header due to an underlying I/O error.
+    CreateHeader,
+    /// Failed to copy the stream data to the writer.
+    EncodeStream,
+    /// Failed to flush the writer after encoding the entire stream.
+    FinishStream,
+}
+
+impl VagError {
+    fn from_io(kind: VagErrorKind) -> impl FnOnce(IoError) -> Self {
+        move |source| Self { kind, source }
+    }
+
+    /// Returns the [`VagErrorKind`] associated with this error.
+    #[must_use]
+    pub fn kind(&self) -> VagErrorKind {
+        self.kind
+    }
+}
+
+impl Display for VagError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.kind.fmt(f)
+    }
+}
+
+impl Error for VagError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl Display for VagErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Self::CreateHeader => "failed to encode file header",
+            Self::EncodeStream => "failed to encode VAG stream data",
+            Self::FinishStream => "failed to finalize writing VAG stream data",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{encode, truncate_name};
+    use crate::{header::StreamInfo, read::Reader};
+
+    #[test]
+    fn truncate_long_names() {
+        assert_eq!(truncate_name("short"), "short");
+        assert_eq!(truncate_name("exactly_15_byte"), "exactly_15_byte");
+        assert_eq!(truncate_name("a_much_longer_stream_name"), "a_much_longer_s");
+        // the 3-byte character would end past the limit, so it is dropped entirely
+        assert_eq!(truncate_name("fourteen_bytes€"), "fourteen_bytes");
+        assert_eq!(truncate_name(""), "");
+    }
+
+    #[test]
+    fn encode_mono_stream() {
+        let data = [0x11; 0x30];
+        let info = StreamInfo {
+            name: Some("music".into()),
+            ..StreamInfo::dummy(1, 84, 0x20)
+        };
+
+        let output = encode(&info, &mut Reader::new(data.as_slice()), Vec::new()).unwrap();
+
+        assert_eq!(output[0x00..0x08], *b"VAGp\x00\x00\x00\x20");
+        assert_eq!(output[0x0C..0x10], 0x20u32.to_be_bytes());
+        assert_eq!(output[0x10..0x14], 44100u32.to_be_bytes());
+        assert_eq!(output[0x1E], 1);
+        assert_eq!(output[0x20..0x30], *b"music\0\0\0\0\0\0\0\0\0\0\0");
+        assert_eq!(output[0x30..], [0x11; 0x20]);
+    }
+
+    #[test]
+    fn encode_stereo_stream() {
+        let data = [0x22; 0x40];
+        let info = StreamInfo::dummy(2, 56, 0x40);
+
+        let output = encode(&info, &mut Reader::new(data.as_slice()), Vec::new()).unwrap();
+
+        assert_eq!(output[0x0C..0x10], 0x20u32.to_be_bytes());
+        assert_eq!(output[0x1E], 2);
+        assert_eq!(output[0x20..0x30], [0; 0x10]);
+        assert_eq!(output.len(), 0x30 + 0x40);
+    }
+}
@@ -1,76 +1,606 @@
-use super::vorbis_lookup::VORBIS_LOOKUP;
+use super::ogg::OggWriter;
+use super::vorbis_lookup::setup_header;
+use super::{
+    wav::{WavSpec, WavWriter},
+    EncodeOptions,
+};
 use crate::header::StreamInfo;
 use crate::read::{ReadError, Reader};
 use lewton::{
-    audio::{read_audio_packet_generic, PreviousWindowRight},
+    audio::{get_decoded_sample_count, read_audio_packet_generic, PreviousWindowRight},
     header::{read_header_ident, read_header_setup, IdentHeader, SetupHeader},
+    samples::Sample,
 };
 use std::{
     error::Error,
     fmt::{Display, Formatter, Result as FmtResult},
     io::{Error as IoError, Read, Write},
 };
+#[cfg(feature = "vorbis")]
+use std::{
+    io::Result as IoResult,
+    sync::{Arc, Mutex, PoisonError},
+};
 use tap::Pipe;
-use vorbis_rs::{VorbisBitrateManagementStrategy, VorbisEncoderBuilder};
+#[cfg(feature = "vorbis")]
+use vorbis_rs::{VorbisBitrateManagementStrategy, VorbisEncoder, VorbisEncoderBuilder};
+
+const SERIAL: u32 = 0x4653_4235;
+const VENDOR: &str = concat!("fsbex ", env!("CARGO_PKG_VERSION"));
 
 pub(super) fn encode<R: Read, W: Write>(
     info: &StreamInfo,
     source: &mut Reader<R>,
     sink: W,
+    options: EncodeOptions<'_>,
+) -> Result<W, VorbisError> {
+    let mut decoder = BlockDecoder::new(info, source)?;
+
+    if options.decode_vorbis {
+        return decode_stream(info, source, sink, &mut decoder, options);
+    }
+
+    // streams that aren't repackaged were rejected before getting here without the `vorbis` feature
+    #[cfg(feature = "vorbis")]
+    if !options.repackage_vorbis {
+        return reencode_stream(info, source, sink, &mut decoder);
+    }
+
+    repackage_stream(info, source, sink, &decoder)
+}
+
+// Re-encodes the decoded stream as an Ogg Vorbis file.
+#[cfg(feature = "vorbis")]
+fn reencode_stream<R: Read, W: Write>(
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+    sink: W,
+    decoder: &mut BlockDecoder,
 ) -> Result<W, VorbisError> {
-    // The stream should have contained the CRC32 of a setup header in a header chunk.
-    // Otherwise, the stream cannot be encoded correctly.
-    let crc32 = info
-        .vorbis_crc32
-        .ok_or_else(|| VorbisError::new(VorbisErrorKind::MissingCrc32))?;
-
-    // construct headers needed for decoding packets from stream data
-    let (id_header, setup_header) =
-        init_headers(info.sample_rate.get(), info.channels.get(), crc32)?;
-
-    // construct encoder that prioritizes audio quality
-    let mut encoder = VorbisEncoderBuilder::new(info.sample_rate, info.channels, sink)
+    let mut encoder = create_encoder(info, sink)?;
+
+    decode_blocks::<_, f32>(decoder, source, |block| {
+        encoder
+            .encode_audio_block(block)
+            .map_err(VorbisError::from_vorbis(VorbisErrorKind::EncodeBlock))
+    })?;
+
+    encoder
+        .finish()
+        .map_err(VorbisError::from_vorbis(VorbisErrorKind::FinishStream))
+}
+
+// Reads the audio packets of the stream without decoding them, checking that the mode of every packet
+// is known to the setup header. Returns the number of packets.
+pub(super) fn check<R: Read>(
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+) -> Result<u32, VorbisError> {
+    let decoder = BlockDecoder::new(info, source)?;
+    let (id_header, setup_header) = &decoder.headers;
+    let mut packets = 0;
+
+    while let Some(packet) = decoder.next_packet(source)? {
+        let _samples = get_decoded_sample_count(id_header, setup_header, &packet)
+            .map_err(Into::into)
+            .map_err(VorbisError::from_lewton(VorbisErrorKind::DecodePacket))?;
+        packets += 1;
+    }
+
+    Ok(packets)
+}
+
+// Rebuilds the stream as an Ogg Vorbis file with its original audio packets.
+#[cfg(feature = "symphonia")]
+pub(super) fn rebuild<R: Read, W: Write>(
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+    sink: W,
+) -> Result<W, VorbisError> {
+    let decoder = BlockDecoder::new(info, source)?;
+    repackage_stream(info, source, sink, &decoder)
+}
+
+// Repackages the audio packets of the stream as an Ogg Vorbis file, without decoding or re-encoding them.
+fn repackage_stream<R: Read, W: Write>(
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+    sink: W,
+    decoder: &BlockDecoder,
+) -> Result<W, VorbisError> {
+    let mut repackager = Repackager::new(info, sink)?;
+    while repackager.next_packet(decoder, source)? {}
+    repackager.finish()
+}
+
+// Writes the audio packets of a stream to an Ogg Vorbis file one at a time.
+// The headers that FMOD leaves out are rebuilt: the identification and setup headers (like when decoding),
+// and a comment header without any comments.
+struct Repackager<W: Write> {
+    writer: OggWriter<W>,
+    end_granule: u64,
+    granule: Option<u64>,
+    pending: Option<(Vec<u8>, u64)>,
+}
+
+impl<W: Write> Repackager<W> {
+    // Writes the headers of the Ogg Vorbis file.
+    fn new(info: &StreamInfo, sink: W) -> Result<Self, VorbisError> {
+        let crc32 = info
+            .vorbis_crc32
+            .expect("CRC32 was checked when creating the decoder");
+        let setup_header_data =
+            setup_header(crc32).expect("CRC32 was looked up when creating the decoder");
+
+        let mut writer = OggWriter::new(sink, SERIAL);
+
+        (|| {
+            writer.write_packet(
+                &init_id_header_data(info.sample_rate.get(), info.channels.get())?,
+                0,
+            )?;
+            writer.flush_page()?;
+            writer.write_packet(&comment_header(), 0)?;
+            writer.write_packet(setup_header_data, 0)?;
+            writer.flush_page()
+        })()
+        .map_err(VorbisError::from_io(VorbisErrorKind::WriteOgg))?;
+
+        // The first packet only primes the decoder, so it decodes to no samples.
+        // The final packet can decode to more samples than the stream contains, which is trimmed by its granule position.
+        Ok(Self {
+            writer,
+            end_granule: u64::from(info.num_samples.get()),
+            granule: None,
+            pending: None,
+        })
+    }
+
+    // Reads the next audio packet, writing the packet before it, and returns false at the end of the stream.
+    fn next_packet<R: Read>(
+        &mut self,
+        decoder: &BlockDecoder,
+        source: &mut Reader<R>,
+    ) -> Result<bool, VorbisError> {
+        let Some(packet) = decoder.next_packet(source)? else {
+            return Ok(false);
+        };
+
+        let (id_header, setup_header) = &decoder.headers;
+        let packet_samples = get_decoded_sample_count(id_header, setup_header, &packet)
+            .map_err(Into::into)
+            .map_err(VorbisError::from_lewton(VorbisErrorKind::DecodePacket))?;
+        let packet_granule = self.granule.map_or(0, |granule| granule + packet_samples as u64);
+        self.granule = Some(packet_granule);
+
+        // packets are written one step behind, so the final packet is known when it's written
+        if let Some((packet, granule)) = self.pending.replace((packet, packet_granule)) {
+            self.writer
+                .write_packet(&packet, granule)
+                .map_err(VorbisError::from_io(VorbisErrorKind::WriteOgg))?;
+        }
+
+        Ok(packet_granule < self.end_granule)
+    }
+
+    // Writes the final packet, ending the Ogg Vorbis file.
+    fn finish(mut self) -> Result<W, VorbisError> {
+        if let Some((packet, granule)) = self.pending.take() {
+            self.writer
+                .write_packet(&packet, granule.min(self.end_granule))
+                .map_err(VorbisError::from_io(VorbisErrorKind::WriteOgg))?;
+        }
+
+        self.writer
+            .finish()
+            .map_err(VorbisError::from_io(VorbisErrorKind::FinishStream))
+    }
+}
+
+// Repackages a stream a page at a time, so that the encoded stream can be read incrementally.
+pub(super) struct Chunks {
+    decoder: BlockDecoder,
+    repackager: Option<Repackager<Vec<u8>>>,
+}
+
+impl Chunks {
+    pub(super) fn new<R: Read>(
+        info: &StreamInfo,
+        source: &Reader<R>,
+        header: &mut Vec<u8>,
+    ) -> Result<Self, VorbisError> {
+        let decoder = BlockDecoder::new(info, source)?;
+        let mut repackager = Repackager::new(info, Vec::new())?;
+        header.append(repackager.writer.sink_mut());
+
+        Ok(Self {
+            decoder,
+            repackager: Some(repackager),
+        })
+    }
+
+    // Appends the next complete page of packets to `chunk`, returning false at the end of the stream.
+    pub(super) fn next_chunk<R: Read>(
+        &mut self,
+        source: &mut Reader<R>,
+        chunk: &mut Vec<u8>,
+    ) -> Result<bool, VorbisError> {
+        let Some(repackager) = self.repackager.as_mut() else {
+            return Ok(false);
+        };
+
+        while repackager.next_packet(&self.decoder, source)? {
+            let pages = repackager.writer.sink_mut();
+
+            if !pages.is_empty() {
+                chunk.append(pages);
+                return Ok(true);
+            }
+        }
+
+        if let Some(repackager) = self.repackager.take() {
+            chunk.append(&mut repackager.finish()?);
+        }
+
+        Ok(false)
+    }
+}
+
+// Creates an encoder that prioritizes audio quality, which writes the headers of the Ogg Vorbis file to `sink`.
+#[cfg(feature = "vorbis")]
+fn create_encoder<W: Write>(info: &StreamInfo, sink: W) -> Result<VorbisEncoder<W>, VorbisError> {
+    VorbisEncoderBuilder::new(info.sample_rate, info.channels, sink)
         .map_err(VorbisError::from_vorbis(VorbisErrorKind::CreateEncoder))?
         .bitrate_management_strategy(VorbisBitrateManagementStrategy::QualityVbr {
             target_quality: 1.0,
         })
         .build()
-        .map_err(VorbisError::from_vorbis(VorbisErrorKind::CreateEncoder))?;
+        .map_err(VorbisError::from_vorbis(VorbisErrorKind::CreateEncoder))
+}
+
+// Re-encodes a stream one audio packet at a time, so that the encoded stream can be read incrementally.
+// The encoder writes pages to a buffer shared with this type, which is emptied after every packet.
+#[cfg(feature = "vorbis")]
+pub(super) struct ReencodedChunks {
+    decoder: BlockDecoder,
+    encoder: Option<SendEncoder>,
+    pages: SharedPages,
+}
 
-    let start_pos = source.position();
-    let stream_size = info.size.get() as usize;
-    let mut window = PreviousWindowRight::new();
+#[cfg(feature = "vorbis")]
+impl ReencodedChunks {
+    pub(super) fn new<R: Read>(
+        info: &StreamInfo,
+        source: &Reader<R>,
+        header: &mut Vec<u8>,
+    ) -> Result<Self, VorbisError> {
+        let decoder = BlockDecoder::new(info, source)?;
+        let pages = SharedPages::default();
+        let encoder = create_encoder(info, pages.clone())?;
+        pages.take(header);
+
+        Ok(Self {
+            decoder,
+            encoder: Some(SendEncoder(encoder)),
+            pages,
+        })
+    }
+
+    // Appends the pages completed by encoding the next audio packet to `chunk`, returning false at the end
+    // of the stream. Pages are only completed every few packets, so the chunk is often empty.
+    pub(super) fn next_chunk<R: Read>(
+        &mut self,
+        source: &mut Reader<R>,
+        chunk: &mut Vec<u8>,
+    ) -> Result<bool, VorbisError> {
+        let Some(SendEncoder(encoder)) = self.encoder.as_mut() else {
+            return Ok(false);
+        };
+
+        if let Some(block) = self.decoder.next_block::<_, f32>(source)? {
+            encoder
+                .encode_audio_block(block)
+                .map_err(VorbisError::from_vorbis(VorbisErrorKind::EncodeBlock))?;
+            self.pages.take(chunk);
+            return Ok(true);
+        }
+
+        if let Some(SendEncoder(encoder)) = self.encoder.take() {
+            let _pages = encoder
+                .finish()
+                .map_err(VorbisError::from_vorbis(VorbisErrorKind::FinishStream))?;
+        }
+
+        self.pages.take(chunk);
+        Ok(false)
+    }
+}
+
+// The encoder isn't `Send` or `Sync`, since it holds pointers to the state of libvorbis.
+// The state is owned by the encoder and has no ties to the thread it was created on, so the encoder can be
+// moved to another thread, and nothing can be done through a shared reference to this wrapper.
+#[cfg(feature = "vorbis")]
+struct SendEncoder(VorbisEncoder<SharedPages>);
+
+// SAFETY: the state of libvorbis is only accessed through the encoder, which is moved along with it
+#[cfg(feature = "vorbis")]
+unsafe impl Send for SendEncoder {}
+
+// SAFETY: the wrapper has no methods, so the encoder can't be accessed through a shared reference
+#[cfg(feature = "vorbis")]
+unsafe impl Sync for SendEncoder {}
+
+// A buffer for the pages written by the encoder, which is shared with `ReencodedChunks`,
+// since the encoder doesn't give access to its writer.
+#[cfg(feature = "vorbis")]
+#[derive(Clone, Default)]
+struct SharedPages(Arc<Mutex<Vec<u8>>>);
+
+#[cfg(feature = "vorbis")]
+impl SharedPages {
+    // Moves the pages written so far to the end of `chunk`.
+    fn take(&self, chunk: &mut Vec<u8>) {
+        chunk.append(&mut self.0.lock().unwrap_or_else(PoisonError::into_inner));
+    }
+}
+
+#[cfg(feature = "vorbis")]
+impl Write for SharedPages {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        Ok(())
+    }
+}
+
+// Vorbis comment header with the vendor string of `fsbex`, and no comments.
+fn comment_header() -> Vec<u8> {
+    let mut data = Vec::with_capacity(16 + VENDOR.len());
+
+    data.push(3);
+    data.extend_from_slice(b"vorbis");
+    data.extend_from_slice(&u32::try_from(VENDOR.len()).unwrap_or_default().to_le_bytes());
+    data.extend_from_slice(VENDOR.as_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes());
+    // framing bit
+    data.push(1);
+
+    data
+}
+
+// Writes the decoded stream as a WAVE file with 16-bit PCM samples.
+// The number of decoded samples isn't known until every packet is decoded, so the file is buffered in memory,
+// unless the source can seek back to decode the stream a second time.
+fn decode_stream<R: Read, W: Write>(
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+    sink: W,
+    decoder: &mut BlockDecoder,
+    options: EncodeOptions<'_>,
+) -> Result<W, VorbisError> {
+    let spec = decode_spec(info, options);
+
+    if source.can_seek() {
+        return decode_stream_twice(info, source, sink, decoder, spec);
+    }
+
+    let mut writer = WavWriter::buffered(spec);
+    let mut data = Vec::new();
+
+    decode_blocks::<_, i16>(decoder, source, |block| {
+        let frames = block.first().map_or(0, Vec::len);
+
+        data.clear();
+        for index in 0..frames {
+            for channel in &block {
+                data.extend_from_slice(&spec.i16_bytes(channel[index]));
+            }
+        }
+
+        writer
+            .write_data(&data)
+            .map_err(|_| VorbisError::new(VorbisErrorKind::StreamTooLarge))
+    })?;
+
+    writer
+        .finish_into(sink)
+        .map_err(VorbisError::from_io(VorbisErrorKind::WritePcm))
+}
+
+// Returns the format of the WAVE file that the decoded stream is written as.
+pub(super) fn decode_spec<'a>(info: &'a StreamInfo, options: EncodeOptions<'a>) -> WavSpec<'a> {
+    // The container is chosen from the declared sample count, since the size of the decoded data isn't known yet.
+    let expected_size = u64::from(info.num_samples.get()) * u64::from(info.channels.get()) * 2;
+    WavSpec::pcm16(info.channels.get().into(), info.sample_rate.get()).with_options(
+        info,
+        options,
+        expected_size,
+    )
+}
+
+// Decodes the stream once to find the size of the decoded data, then seeks back to the start of the stream data.
+pub(super) fn decoded_data_size<R: Read>(
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+    decoder: &mut BlockDecoder,
+    spec: WavSpec<'_>,
+) -> Result<u64, VorbisError> {
+    let mut frames = 0;
+
+    decode_blocks::<_, i16>(decoder, source, |block| {
+        frames += block.first().map_or(0, Vec::len) as u64;
+        Ok(())
+    })?;
+
+    let data_size = frames * u64::from(info.channels.get()) * 2;
+    if spec.file_size(data_size).is_none() {
+        return Err(VorbisError::new(VorbisErrorKind::StreamTooLarge));
+    }
+
+    decoder.rewind(source)?;
+    Ok(data_size)
+}
+
+// Writes the decoded stream without buffering it, by decoding it once to find the size of the decoded data,
+// then seeking back to decode it again while writing it.
+fn decode_stream_twice<R: Read, W: Write>(
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+    mut sink: W,
+    decoder: &mut BlockDecoder,
+    spec: WavSpec<'_>,
+) -> Result<W, VorbisError> {
+    let data_size = decoded_data_size(info, source, decoder, spec)?;
+
+    spec.write_header(data_size, &mut sink)
+        .map_err(VorbisError::from_io(VorbisErrorKind::WritePcm))?;
+
+    let mut data = Vec::new();
+
+    decode_blocks::<_, i16>(decoder, source, |block| {
+        let frames = block.first().map_or(0, Vec::len);
+
+        data.clear();
+        for index in 0..frames {
+            for channel in &block {
+                data.extend_from_slice(&spec.i16_bytes(channel[index]));
+            }
+        }
+
+        sink.write_all(&data)
+            .map_err(VorbisError::from_io(VorbisErrorKind::WritePcm))
+    })?;
+
+    spec.write_trailer(data_size, &mut sink)
+        .map_err(VorbisError::from_io(VorbisErrorKind::WritePcm))?;
+
+    Ok(sink)
+}
+
+// Decodes the audio packets of the stream, passing each block of samples to `f`.
+fn decode_blocks<R: Read, S: Sample>(
+    decoder: &mut BlockDecoder,
+    source: &mut Reader<R>,
+    mut f: impl FnMut(Vec<Vec<S>>) -> Result<(), VorbisError>,
+) -> Result<(), VorbisError> {
+    while let Some(block) = decoder.next_block(source)? {
+        f(block)?;
+    }
+
+    Ok(())
+}
+
+// Decodes the stream one audio packet at a time, producing a block of samples for every channel.
+pub(super) struct BlockDecoder {
+    headers: (IdentHeader, SetupHeader),
+    window: PreviousWindowRight,
+    stream_size: usize,
+    start_pos: u64,
+    num_samples: usize,
+    samples_left: usize,
+}
+
+impl BlockDecoder {
+    pub(super) fn new<R: Read>(info: &StreamInfo, source: &Reader<R>) -> Result<Self, VorbisError> {
+        // The stream should have contained the CRC32 of a setup header in a header chunk.
+        // Otherwise, the stream cannot be encoded correctly.
+        let crc32 = info
+            .vorbis_crc32
+            .ok_or_else(|| VorbisError::new(VorbisErrorKind::MissingCrc32))?;
+
+        // construct headers needed for decoding packets from stream data
+        let headers = init_headers(info.sample_rate.get(), info.channels.get(), crc32)?;
+
+        // The final packet can decode to more samples than the stream contains due to encoder padding.
+        // Samples past the declared sample count are dropped, so the encoder ends the stream at the right granule position.
+        let num_samples = info.num_samples.get() as usize;
+
+        Ok(Self {
+            headers,
+            window: PreviousWindowRight::new(),
+            stream_size: info.size as usize,
+            start_pos: source.position(),
+            num_samples,
+            samples_left: num_samples,
+        })
+    }
+
+    // Decodes the next audio packet, returning `None` at the end of the stream data.
+    // A packet can decode to an empty block, since the first packet only primes the decoder.
+    pub(super) fn next_block<R: Read, S: Sample>(
+        &mut self,
+        source: &mut Reader<R>,
+    ) -> Result<Option<Vec<Vec<S>>>, VorbisError> {
+        if self.samples_left == 0 {
+            return Ok(None);
+        }
+
+        let Some(packet) = self.next_packet(source)? else {
+            return Ok(None);
+        };
+
+        let (id_header, setup_header) = &self.headers;
+        let mut block: Vec<Vec<S>> =
+            read_audio_packet_generic(id_header, setup_header, &packet, &mut self.window)
+                .map_err(Into::into)
+                .map_err(VorbisError::from_lewton(VorbisErrorKind::DecodePacket))?;
+
+        self.samples_left -= trim_block(&mut block, self.samples_left);
+
+        Ok(Some(block))
+    }
+
+    // Reads the next audio packet without decoding it, returning `None` at the end of the stream data.
+    fn next_packet<R: Read>(&self, source: &mut Reader<R>) -> Result<Option<Vec<u8>>, VorbisError> {
+        if source.bytes_since(self.start_pos) >= self.stream_size {
+            return Ok(None);
+        }
 
-    while source.position() - start_pos < stream_size {
-        // let packet_size = source
-        //     .le_u16()
-        //     .map_err(VorbisError::from_read(VorbisErrorKind::ReadPacket))?;
         let Ok(packet_size) = source.le_u16() else {
-            break;
+            return Ok(None);
         };
 
         // signals end of stream data
         if packet_size == u16::MIN || packet_size == u16::MAX {
-            break;
+            return Ok(None);
         }
 
-        let packet = source
+        // a packet cut short by the end of the stream data (e.g. in a truncated sound bank) can't be decoded
+        if source.bytes_since(self.start_pos) + packet_size as usize > self.stream_size {
+            return Ok(None);
+        }
+
+        source
             .take(packet_size as usize)
-            .map_err(VorbisError::from_read(VorbisErrorKind::ReadPacket))?;
+            .map(Some)
+            .map_err(VorbisError::from_read(VorbisErrorKind::ReadPacket))
+    }
 
-        let block: Vec<_> =
-            read_audio_packet_generic(&id_header, &setup_header, &packet, &mut window)
-                .map_err(Into::into)
-                .map_err(VorbisError::from_lewton(VorbisErrorKind::DecodePacket))?;
+    // Seeks back to the start of the stream data, so that the stream can be decoded again.
+    fn rewind<R: Read>(&mut self, source: &mut Reader<R>) -> Result<(), VorbisError> {
+        source
+            .seek_to(self.start_pos)
+            .map_err(VorbisError::from_read(VorbisErrorKind::RewindStream))?;
 
-        encoder
-            .encode_audio_block(block)
-            .map_err(VorbisError::from_vorbis(VorbisErrorKind::EncodeBlock))?;
+        self.window = PreviousWindowRight::new();
+        self.samples_left = self.num_samples;
+        Ok(())
     }
+}
 
-    encoder
-        .finish()
-        .map_err(VorbisError::from_vorbis(VorbisErrorKind::FinishStream))
+// Shortens every channel of a decoded block to at most `max_len` samples, returning the new length.
+fn trim_block<S>(block: &mut [Vec<S>], max_len: usize) -> usize {
+    for channel in &mut *block {
+        channel.truncate(max_len);
+    }
+
+    block.first().map_or(0, Vec::len)
 }
 
 // default block sizes for FMOD sound banks:
@@ -91,9 +621,8 @@ fn init_headers(
         .map_err(VorbisError::from_lewton(VorbisErrorKind::CreateHeaders))?;
 
     // construct setup header from lookup table
-    let setup_header_data = *VORBIS_LOOKUP
-        .get(&crc32)
-        .ok_or_else(|| VorbisError::new(VorbisErrorKind::Crc32Lookup))?;
+    let setup_header_data = setup_header(crc32)
+        .ok_or_else(|| VorbisError::new(VorbisErrorKind::Crc32Lookup { crc32 }))?;
 
     let setup_header = read_header_setup(
         setup_header_data,
@@ -147,7 +676,10 @@ pub enum VorbisErrorKind {
     /// Failed to create the file headers needed for the Vorbis decoder.
     CreateHeaders,
     /// The stream's associated CRC32 checksum was found, but it did not match any existing entries in the lookup table.
-    Crc32Lookup,
+    Crc32Lookup {
+        /// The CRC32 checksum of the setup header that the stream was encoded with.
+        crc32: u32,
+    },
     /// Failed to create the Vorbis encoder for writing audio samples.
     CreateEncoder,
     /// Failed to read an audio packet from the stream data.
@@ -158,11 +690,21 @@ pub enum VorbisErrorKind {
     EncodeBlock,
     /// Failed to flush the writer after encoding the entire stream.
     FinishStream,
+    /// The decoded stream was too large to fit in a WAVE file.
+    StreamTooLarge,
+    /// Failed to write the decoded stream as a WAVE file due to an underlying I/O error.
+    WritePcm,
+    /// Failed to seek back to the start of the stream data, after decoding it to find the size of the decoded data.
+    RewindStream,
+    /// Failed to write the audio packets of the stream as an Ogg Vorbis file due to an underlying I/O error.
+    WriteOgg,
 }
 
 #[derive(Debug)]
 enum VorbisErrorSource {
+    #[cfg(feature = "vorbis")]
     Encode(vorbis_rs::VorbisError),
+    Io(IoError),
     Decode(lewton::VorbisError),
     Read(ReadError),
 }
@@ -172,6 +714,7 @@ impl VorbisError {
         Self { kind, source: None }
     }
 
+    #[cfg(feature = "vorbis")]
     fn from_vorbis(kind: VorbisErrorKind) -> impl FnOnce(vorbis_rs::VorbisError) -> Self {
         move |source| Self {
             kind,
@@ -186,6 +729,13 @@ impl VorbisError {
         }
     }
 
+    fn from_io(kind: VorbisErrorKind) -> impl FnOnce(IoError) -> Self {
+        move |source| Self {
+            kind,
+            source: Some(VorbisErrorSource::Io(source)),
+        }
+    }
+
     fn from_read(kind: VorbisErrorKind) -> impl FnOnce(ReadError) -> Self {
         move |source| Self {
             kind,
@@ -210,7 +760,9 @@ impl Error for VorbisError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match &self.source {
             Some(source) => match source {
+                #[cfg(feature = "vorbis")]
                 VorbisErrorSource::Encode(e) => Some(e),
+                VorbisErrorSource::Io(e) => Some(e),
                 VorbisErrorSource::Decode(e) => Some(e),
                 VorbisErrorSource::Read(e) => Some(e),
             },
@@ -221,15 +773,123 @@ impl Error for VorbisError {
 
 impl Display for VorbisErrorKind {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        f.write_str(match self {
-            Self::MissingCrc32 => "file header did not contain CRC32 of Vorbis setup header",
-            Self::CreateHeaders => "failed to create dummy Vorbis headers",
-            Self::Crc32Lookup => "CRC32 of Vorbis setup header was not found in lookup table",
-            Self::CreateEncoder => "failed to create Vorbis stream encoder",
-            Self::ReadPacket => "failed to read audio packet from Vorbis stream",
-            Self::DecodePacket => "failed to decode audio packet from Vorbis stream",
-            Self::EncodeBlock => "failed to encode block of samples",
-            Self::FinishStream => "failed to finalize writing Vorbis stream data",
-        })
+        match self {
+            Self::MissingCrc32 => {
+                f.write_str("file header did not contain CRC32 of Vorbis setup header")
+            }
+            Self::CreateHeaders => f.write_str("failed to create dummy Vorbis headers"),
+            Self::Crc32Lookup { crc32 } => f.write_fmt(format_args!(
+                "CRC32 of Vorbis setup header was not found in lookup table (0x{crc32:08X})"
+            )),
+            Self::CreateEncoder => f.write_str("failed to create Vorbis stream encoder"),
+            Self::ReadPacket => f.write_str("failed to read audio packet from Vorbis stream"),
+            Self::DecodePacket => f.write_str("failed to decode audio packet from Vorbis stream"),
+            Self::EncodeBlock => f.write_str("failed to encode block of samples"),
+            Self::FinishStream => f.write_str("failed to finalize writing Vorbis stream data"),
+            Self::StreamTooLarge => f.write_str("decoded Vorbis stream was too large to encode"),
+            Self::WritePcm => f.write_str("failed to write decoded Vorbis stream data"),
+            Self::RewindStream => {
+                f.write_str("failed to seek back to the start of the Vorbis stream")
+            }
+            Self::WriteOgg => f.write_str("failed to write Vorbis packets to Ogg file"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{encode, init_headers, trim_block, VorbisErrorKind};
+    use crate::{encode::EncodeOptions, header::StreamInfo, read::Reader};
+    use std::io::Cursor;
+
+    #[test]
+    fn look_up_setup_header() {
+        assert!(init_headers(44100, 2, 0xA722_97FF).is_ok());
+    }
+
+    #[test]
+    fn report_unknown_crc32() {
+        let Err(error) = init_headers(44100, 2, 0x1234_ABCD) else {
+            panic!("CRC32 should not be in the lookup table");
+        };
+
+        assert_eq!(error.kind(), VorbisErrorKind::Crc32Lookup { crc32: 0x1234_ABCD });
+        assert_eq!(
+            error.to_string(),
+            "CRC32 of Vorbis setup header was not found in lookup table (0x1234ABCD)"
+        );
+    }
+
+    #[test]
+    fn trim_padding_samples() {
+        let mut block = vec![vec![0.5; 256], vec![-0.5; 256]];
+
+        assert_eq!(trim_block(&mut block, 1000), 256);
+        assert_eq!(trim_block(&mut block, 100), 100);
+        assert!(block.iter().all(|channel| channel.len() == 100));
+        assert_eq!(trim_block::<f32>(&mut [], 100), 0);
+    }
+
+    #[test]
+    fn decode_to_wave_file() {
+        // the stream ends immediately, so only the file header is written
+        let data = [0; 2];
+        let info = StreamInfo {
+            vorbis_crc32: Some(0xA722_97FF),
+            ..StreamInfo::dummy(2, 1024, 2)
+        };
+
+        let output = encode(
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::new().decode_vorbis(true),
+        )
+        .unwrap();
+
+        assert_eq!(output[0..4], *b"RIFF");
+        assert_eq!(output[22..24], 2u16.to_le_bytes());
+        assert_eq!(output[34..36], 16u16.to_le_bytes());
+        assert_eq!(output[40..44], 0u32.to_le_bytes());
+        assert_eq!(output.len(), 44);
+
+        // sources that can seek are decoded twice instead of being buffered, with the same output
+        let unbuffered = encode(
+            &info,
+            &mut Reader::seekable(Cursor::new(data)),
+            Vec::new(),
+            EncodeOptions::new().decode_vorbis(true),
+        )
+        .unwrap();
+
+        assert_eq!(unbuffered, output);
+    }
+
+    #[test]
+    fn repackage_audio_packets() {
+        // audio packets that only hold the packet type and mode number
+        let data = [1, 0, 0].repeat(4);
+        let info = StreamInfo {
+            vorbis_crc32: Some(0xA722_97FF),
+            ..StreamInfo::dummy(2, 300, 12)
+        };
+
+        let output = encode(
+            &info,
+            &mut Reader::new(data.as_slice()),
+            Vec::new(),
+            EncodeOptions::new().repackage_vorbis(true),
+        )
+        .unwrap();
+
+        // the identification header is on its own page, followed by a page with the other headers
+        assert_eq!(output[..4], *b"OggS");
+        assert_eq!(output[28..35], *b"\x01vorbis");
+        assert_eq!(output[58..62], *b"OggS");
+
+        // the final page ends at the last sample of the stream, which is within the fourth packet
+        let last_page = output.windows(4).rposition(|window| window == b"OggS").unwrap();
+        assert_eq!(output[last_page + 6..last_page + 14], 300u64.to_le_bytes());
+        assert_eq!(output[last_page + 26], 4);
     }
 }
@@ -0,0 +1,1339 @@
+use super::{aiff, caf, BextInfo, EncodeOptions, PcmOutput, WavContainer};
+use crate::header::{Loop, StreamInfo};
+use std::{
+    io::{Cursor, Error as IoError, ErrorKind, Seek, SeekFrom, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// WAVE file header information taken from:
+// [1]: https://www-mmsp.ece.mcgill.ca/Documents/AudioFormats/WAVE/WAVE.html
+// [2]: http://soundfile.sapp.org/doc/WaveFormat/
+// [3]: https://tech.ebu.ch/docs/tech/tech3306v1_1.pdf (RF64)
+// [4]: https://www.ambisonia.com/Members/mleese/sony_wave64.pdf (Wave64)
+// [5]: https://tech.ebu.ch/docs/tech/tech3285.pdf (Broadcast Wave Format)
+
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+// Sub-format GUIDs of the extensible format start with the format tag of the plain format (as a u32),
+// followed by these bytes.
+const SUBFORMAT_GUID_SUFFIX: [u8; 14] = [
+    0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum Format {
+    Integer,
+    Float,
+}
+
+// The ds64 chunk of RF64 files holds the 64-bit sizes of the RIFF and data chunks, the sample count of the fact chunk,
+// and the length of a table of other chunk sizes, which is always empty.
+const DS64_CHUNK_SIZE: u32 = 8 + 8 + 8 + 4;
+
+// Wave64 files identify chunks with GUIDs instead of 4-byte IDs. The GUIDs of chunks written to Wave64 files
+// start with the 4-byte ID of the equivalent RIFF chunk (in lowercase for the WAVE chunk), followed by these bytes.
+const W64_GUID_SUFFIX: [u8; 12] = [
+    0xF3, 0xAC, 0xD3, 0x11, 0x8C, 0xD1, 0x00, 0xC0, 0x4F, 0x8E, 0xDB, 0x8A,
+];
+
+// The GUID of the RIFF chunk of Wave64 files doesn't follow the pattern of other chunks.
+const W64_RIFF_GUID: [u8; 16] = [
+    0x72, 0x69, 0x66, 0x66, 0x2E, 0x91, 0xCF, 0x11, 0xA5, 0xD6, 0x28, 0xDB, 0x04, 0xC1, 0x00, 0x00,
+];
+
+// IDs of the cue points at the start and end of a loop. The loop of the smpl chunk refers to the loop start.
+const LOOP_START_CUE_ID: u32 = 1;
+const LOOP_END_CUE_ID: u32 = 2;
+
+// Longer strings in the INFO list are cut short, since some readers use fixed-size buffers for them.
+const MAX_INFO_TEXT_LEN: usize = 255;
+
+// The bext chunk holds fixed-size text fields for the description (256 bytes), originator (32 bytes),
+// originator reference (32 bytes), origination date (10 bytes), and origination time (8 bytes),
+// followed by a 64-bit time reference, the version, a 64-byte UMID, 10 bytes of loudness values,
+// and 180 reserved bytes. The coding history that can follow is left empty.
+const BEXT_CHUNK_SIZE: u32 = 256 + 32 + 32 + 10 + 8 + 8 + 2 + 64 + 10 + 180;
+
+const ORIGINATOR: &str = concat!("fsbex ", env!("CARGO_PKG_VERSION"));
+
+// Describes the samples stored in the data chunk of a WAVE file, and the metadata chunks that follow it.
+// Samples can also be written to an AIFF file, which has no metadata chunks, or to a CAF file, which only holds the name.
+// FLAC files are written by a `FlacWriter` wrapping the sink, so only the samples are written for them.
+#[derive(Clone, Copy)]
+pub(super) struct WavSpec<'a> {
+    pub(super) format: Format,
+    pub(super) bits_per_sample: u16,
+    pub(super) channels: u16,
+    pub(super) sample_rate: u32,
+    pub(super) sample_loop: Option<SampleLoop>,
+    // The loop written as cue points, which is set independently of the loop of the smpl chunk.
+    pub(super) cue_loop: Option<SampleLoop>,
+    pub(super) info: InfoList<'a>,
+    // Files that would be too large for `WavContainer::Riff` have already been switched to `WavContainer::Rf64`.
+    pub(super) container: WavContainer,
+    pub(super) output: PcmOutput,
+    pub(super) bext: Option<BextInfo<'a>>,
+}
+
+// A forward loop between two sample frames, both inclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) struct SampleLoop {
+    pub(super) start: u32,
+    pub(super) end: u32,
+}
+
+impl SampleLoop {
+    // Loop ends past the final sample frame are clamped to it, since players can reject loops outside the data.
+    // Returns `None` if the whole loop is past the final sample frame.
+    fn new(stream_loop: Loop, num_samples: u32) -> Option<Self> {
+        let last_sample = num_samples.checked_sub(1)?;
+        let start = stream_loop.start();
+
+        (start <= last_sample).then(|| Self {
+            start,
+            end: stream_loop.end().min(last_sample),
+        })
+    }
+
+    // Returns the loop of the stream described by `info`, if loop points are enabled by `options`.
+    pub(super) fn with_options(info: &StreamInfo, options: EncodeOptions<'_>) -> Option<Self> {
+        Self::from_stream(info).filter(|_| options.loop_chunk)
+    }
+
+    fn from_stream(info: &StreamInfo) -> Option<Self> {
+        info.stream_loop
+            .and_then(|stream_loop| Self::new(stream_loop, info.num_samples.get()))
+    }
+}
+
+// Text stored in the INFO list of a LIST chunk. The list is only written if it holds any text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(super) struct InfoList<'a> {
+    name: Option<&'a str>,
+    source: Option<&'a str>,
+}
+
+impl<'a> InfoList<'a> {
+    pub(super) fn name(self) -> Option<&'a str> {
+        self.name
+    }
+
+    fn entries(self) -> impl Iterator<Item = (&'static [u8; 4], &'a str)> {
+        [(b"INAM", self.name), (b"ISRC", self.source)]
+            .into_iter()
+            .filter_map(|(id, text)| text.map(|text| (id, truncate_text(text, MAX_INFO_TEXT_LEN))))
+    }
+
+    // Returns the LIST chunk holding the entries as null-terminated strings, or `None` if the list is empty.
+    fn chunk(self) -> Option<Chunk> {
+        let entries: Vec<_> =
+            self.entries().map(|(id, text)| Chunk::text(*id, &[], text)).collect();
+
+        (!entries.is_empty()).then(|| Chunk::list(*b"INFO", entries))
+    }
+}
+
+// A chunk written after the data chunk of a WAVE file, holding its contents without the chunk header.
+// Metadata chunks are built this way so that any chunk can follow the data, since the sizes of all chunks
+// have to be known before the header is written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Chunk {
+    id: [u8; 4],
+    data: Vec<u8>,
+}
+
+impl Chunk {
+    fn new(id: [u8; 4], data: Vec<u8>) -> Self {
+        Self { id, data }
+    }
+
+    // Creates a chunk holding `prefix`, followed by `text` as a null-terminated string.
+    fn text(id: [u8; 4], prefix: &[u8], text: &str) -> Self {
+        Self::new(id, [prefix, text.as_bytes(), &[0]].concat())
+    }
+
+    // Creates a LIST chunk of `list_type`, which holds other chunks.
+    fn list(list_type: [u8; 4], chunks: Vec<Self>) -> Self {
+        let mut data = list_type.to_vec();
+
+        for chunk in chunks {
+            chunk.write(&mut data).expect("writing to a Vec<u8> can't fail");
+        }
+
+        Self::new(*b"LIST", data)
+    }
+
+    // Returns the size of the chunk, including its header and the padding byte that follows odd sizes.
+    fn padded_size(&self) -> u64 {
+        let size = 8 + self.data.len() as u64;
+        size + size % 2
+    }
+
+    fn write<W: Write>(&self, sink: &mut W) -> Result<(), IoError> {
+        sink.write_all(&self.id)?;
+        sink.write_all(
+            &u32::try_from(self.data.len())
+                .expect("metadata chunks are smaller than 4 GiB")
+                .to_le_bytes(),
+        )?;
+        sink.write_all(&self.data)?;
+        write_padding(self.data.len() as u64 % 2, sink)
+    }
+}
+
+// Cuts `text` short at a character boundary, so that it's at most `max_len` bytes long.
+fn truncate_text(text: &str, max_len: usize) -> &str {
+    let len = (0..=max_len.min(text.len()))
+        .rev()
+        .find(|&len| text.is_char_boundary(len))
+        .unwrap_or_default();
+
+    &text[..len]
+}
+
+impl<'a> WavSpec<'a> {
+    pub(super) fn pcm16(channels: u16, sample_rate: u32) -> Self {
+        Self {
+            format: Format::Integer,
+            bits_per_sample: 16,
+            channels,
+            sample_rate,
+            sample_loop: None,
+            cue_loop: None,
+            info: InfoList::default(),
+            container: WavContainer::Riff,
+            output: PcmOutput::Wav,
+            bext: None,
+        }
+    }
+
+    // Adds the metadata chunks enabled by `options` for the stream described by `info`,
+    // then chooses the container for a data chunk of `data_size` bytes.
+    // The container has to be chosen before the header is written, since RIFF and RF64 headers have different sizes.
+    pub(super) fn with_options(
+        mut self,
+        info: &'a StreamInfo,
+        options: EncodeOptions<'a>,
+        data_size: u64,
+    ) -> Self {
+        self.sample_loop = SampleLoop::with_options(info, options);
+        self.cue_loop = SampleLoop::from_stream(info).filter(|_| options.cue_chunk);
+
+        // CAF files always hold the name, since no older version wrote them
+        if options.info_chunk || options.pcm_output == PcmOutput::Caf {
+            self.info = InfoList {
+                name: info.name.as_deref(),
+                source: options.source_name,
+            };
+        }
+
+        self.bext = options.bext.map(|bext| BextInfo {
+            description: bext.description.or(info.name.as_deref()),
+            ..bext
+        });
+
+        self.output = options.pcm_output;
+        self.container = match options.wav_container {
+            WavContainer::Riff if self.file_size(data_size).is_none() => WavContainer::Rf64,
+            container => container,
+        };
+
+        self
+    }
+
+    // Returns whether samples are written in big-endian byte order, which AIFF files use.
+    pub(super) fn is_big_endian(self) -> bool {
+        self.output == PcmOutput::Aiff
+    }
+
+    // Returns whether 8-bit samples are written as unsigned integers, which WAVE files use.
+    // AIFF and FLAC files store them as signed integers instead.
+    pub(super) fn is_unsigned_8_bit(self) -> bool {
+        self.output == PcmOutput::Wav
+    }
+
+    // Returns a 16-bit sample in the byte order of the file.
+    pub(super) fn i16_bytes(self, sample: i16) -> [u8; 2] {
+        if self.is_big_endian() {
+            sample.to_be_bytes()
+        } else {
+            sample.to_le_bytes()
+        }
+    }
+
+    fn block_align(self) -> u16 {
+        self.channels * self.bits_per_sample.div_ceil(8)
+    }
+
+    // Streams with more than 2 channels use the WAVE_FORMAT_EXTENSIBLE format, which describes the speaker layout.
+    // Mono and stereo streams keep the plain fmt chunk, since some readers don't support the extensible format.
+    fn is_extensible(self) -> bool {
+        self.channels > 2
+    }
+
+    // Metadata chunks aren't written to Wave64 files, including the bext chunk before the data chunk.
+    fn has_bext(self) -> bool {
+        self.bext.is_some() && self.container != WavContainer::Wave64
+    }
+
+    // Formats other than integer PCM need a fact chunk, as well as a fmt chunk with an extension.
+    fn has_fact(self) -> bool {
+        self.format != Format::Integer || self.is_extensible()
+    }
+
+    fn fmt_size(self) -> u32 {
+        if self.is_extensible() {
+            40
+        } else if self.has_fact() {
+            18
+        } else {
+            16
+        }
+    }
+
+    // Wave64 files store the sample count of the fact chunk as a 64-bit integer.
+    fn fact_size(self) -> u64 {
+        if self.container == WavContainer::Wave64 {
+            8
+        } else {
+            4
+        }
+    }
+
+    fn chunk_header_size(self) -> u64 {
+        if self.container == WavContainer::Wave64 {
+            24
+        } else {
+            8
+        }
+    }
+
+    // Returns the size of a chunk (including its header) with padding, since chunks are aligned to
+    // 2 bytes in RIFF and RF64 files, and to 8 bytes in Wave64 files.
+    fn padded_chunk_size(self, size: u64) -> u64 {
+        let size = self.chunk_header_size() + size;
+
+        if self.container == WavContainer::Wave64 {
+            size.next_multiple_of(8)
+        } else {
+            size.next_multiple_of(2)
+        }
+    }
+
+    // Returns the size of everything in the file before the contents of the data chunk.
+    fn header_size(self) -> u64 {
+        match self.output {
+            PcmOutput::Aiff => return aiff::header_size(self),
+            PcmOutput::Caf => return caf::header_size(self),
+            #[cfg(feature = "flac")]
+            PcmOutput::Flac => return 0,
+            PcmOutput::Wav => {}
+        }
+
+        let riff_header_size = match self.container {
+            WavContainer::Riff => 12,
+            WavContainer::Rf64 => 12 + 8 + u64::from(DS64_CHUNK_SIZE),
+            WavContainer::Wave64 => 16 + 8 + 16,
+        };
+        let fact_size = if self.has_fact() {
+            self.padded_chunk_size(self.fact_size())
+        } else {
+            0
+        };
+
+        let bext_size = if self.has_bext() {
+            8 + u64::from(BEXT_CHUNK_SIZE)
+        } else {
+            0
+        };
+
+        riff_header_size
+            + self.padded_chunk_size(self.fmt_size().into())
+            + fact_size
+            + bext_size
+            + self.chunk_header_size()
+    }
+
+    // Returns the size of everything in the file after the contents of the data chunk.
+    // Chunks are padded, so the padding bytes after the data are counted.
+    // Metadata chunks aren't written to Wave64 files.
+    fn trailer_size(self, data_size: u64) -> u64 {
+        match self.output {
+            PcmOutput::Aiff => return data_size % 2,
+            PcmOutput::Caf => return 0,
+            #[cfg(feature = "flac")]
+            PcmOutput::Flac => return 0,
+            PcmOutput::Wav => {}
+        }
+
+        if self.container == WavContainer::Wave64 {
+            return data_size.next_multiple_of(8) - data_size;
+        }
+
+        data_size % 2 + self.trailer_chunks().iter().map(Chunk::padded_size).sum::<u64>()
+    }
+
+    // Returns the metadata chunks that follow the data chunk of WAVE files, in the order they are written.
+    // The labels of cue points are written after the cue chunk, since they refer to its cue points.
+    fn trailer_chunks(self) -> Vec<Chunk> {
+        let mut chunks = Vec::new();
+
+        if let Some(sample_loop) = self.sample_loop {
+            // the loop refers to the cue point at its start, if there is one
+            let cue_id = if self.cue_loop.is_some() {
+                LOOP_START_CUE_ID
+            } else {
+                0
+            };
+
+            chunks.push(smpl_chunk(sample_loop, cue_id, self.sample_rate));
+        }
+
+        if let Some(cue_loop) = self.cue_loop {
+            chunks.push(cue_chunk(cue_loop));
+            chunks.push(Chunk::list(
+                *b"adtl",
+                vec![
+                    Chunk::text(*b"labl", &LOOP_START_CUE_ID.to_le_bytes(), "loop start"),
+                    Chunk::text(*b"labl", &LOOP_END_CUE_ID.to_le_bytes(), "loop end"),
+                ],
+            ));
+        }
+
+        chunks.extend(self.info.chunk());
+        chunks
+    }
+
+    // Returns the size of the whole file, or `None` if the file would be too large for its container.
+    // The size fields of RIFF and AIFF files are 32 bits wide, so these files can't be larger than 4 GiB.
+    pub(super) fn file_size(self, data_size: u64) -> Option<u64> {
+        let has_64_bit_sizes = match self.output {
+            PcmOutput::Wav => self.container != WavContainer::Riff,
+            PcmOutput::Aiff => false,
+            PcmOutput::Caf => true,
+            #[cfg(feature = "flac")]
+            PcmOutput::Flac => true,
+        };
+
+        self.header_size()
+            .checked_add(data_size)?
+            .checked_add(self.trailer_size(data_size))
+            .filter(|&size| has_64_bit_sizes || size - 8 <= u32::MAX.into())
+    }
+
+    fn write_chunk_id<W: Write>(self, id: [u8; 4], sink: &mut W) -> Result<(), IoError> {
+        sink.write_all(&id)?;
+
+        if self.container == WavContainer::Wave64 {
+            sink.write_all(&W64_GUID_SUFFIX)?;
+        }
+
+        Ok(())
+    }
+
+    // Writes the header of a chunk with `size` bytes of contents.
+    // Sizes in Wave64 files are 64 bits wide and include the chunk header.
+    fn write_chunk_header<W: Write>(
+        self,
+        id: [u8; 4],
+        size: u64,
+        sink: &mut W,
+    ) -> Result<(), IoError> {
+        self.write_chunk_id(id, sink)?;
+
+        if self.container == WavContainer::Wave64 {
+            sink.write_all(&(self.chunk_header_size() + size).to_le_bytes())
+        } else {
+            sink.write_all(&u32::try_from(size).unwrap_or(u32::MAX).to_le_bytes())
+        }
+    }
+
+    // Writes a header for a data chunk of a known size.
+    // `write_trailer` must be called after writing the data.
+    pub(super) fn write_header<W: Write>(
+        self,
+        data_size: u64,
+        sink: &mut W,
+    ) -> Result<(), IoError> {
+        let file_size = self.file_size(data_size).ok_or_else(too_large)?;
+
+        match self.output {
+            PcmOutput::Aiff => return aiff::write_header(self, data_size, file_size, sink),
+            PcmOutput::Caf => return caf::write_header(self, Some(data_size), sink),
+            #[cfg(feature = "flac")]
+            PcmOutput::Flac => return Ok(()),
+            PcmOutput::Wav => {}
+        }
+
+        let format_id = match self.format {
+            Format::Integer => 1u16,
+            Format::Float => 3u16,
+        };
+        let block_align = self.block_align();
+        let bytes_per_second = self.sample_rate * u32::from(block_align);
+        let sample_count = data_size / u64::from(block_align);
+
+        match self.container {
+            WavContainer::Riff => {
+                sink.write_all(b"RIFF")?;
+                sink.write_all(
+                    &u32::try_from(file_size - 8)
+                        .expect("RIFF size was checked to fit in 32 bits")
+                        .to_le_bytes(),
+                )?;
+                sink.write_all(b"WAVE")?;
+            }
+            WavContainer::Rf64 => {
+                // the 32-bit size fields are replaced by the 64-bit sizes in the ds64 chunk
+                sink.write_all(b"RF64")?;
+                sink.write_all(&u32::MAX.to_le_bytes())?;
+                sink.write_all(b"WAVE")?;
+                sink.write_all(b"ds64")?;
+                sink.write_all(&DS64_CHUNK_SIZE.to_le_bytes())?;
+                sink.write_all(&(file_size - 8).to_le_bytes())?;
+                sink.write_all(&data_size.to_le_bytes())?;
+                sink.write_all(&sample_count.to_le_bytes())?;
+                sink.write_all(&0u32.to_le_bytes())?;
+            }
+            WavContainer::Wave64 => {
+                sink.write_all(&W64_RIFF_GUID)?;
+                sink.write_all(&file_size.to_le_bytes())?;
+                self.write_chunk_id(*b"wave", sink)?;
+            }
+        }
+
+        self.write_chunk_header(*b"fmt ", self.fmt_size().into(), sink)?;
+
+        if self.is_extensible() {
+            sink.write_all(&WAVE_FORMAT_EXTENSIBLE.to_le_bytes())?;
+        } else {
+            sink.write_all(&format_id.to_le_bytes())?;
+        }
+
+        sink.write_all(&self.channels.to_le_bytes())?;
+        sink.write_all(&self.sample_rate.to_le_bytes())?;
+        sink.write_all(&bytes_per_second.to_le_bytes())?;
+        sink.write_all(&block_align.to_le_bytes())?;
+        sink.write_all(&self.bits_per_sample.to_le_bytes())?;
+
+        if self.is_extensible() {
+            // extension size, valid bits per sample, channel mask, and sub-format GUID
+            sink.write_all(&22u16.to_le_bytes())?;
+            sink.write_all(&self.bits_per_sample.to_le_bytes())?;
+            sink.write_all(&channel_mask(self.channels).to_le_bytes())?;
+            sink.write_all(&format_id.to_le_bytes())?;
+            sink.write_all(&SUBFORMAT_GUID_SUFFIX)?;
+        } else if self.has_fact() {
+            sink.write_all(&0u16.to_le_bytes())?;
+        }
+
+        write_padding(
+            self.padded_chunk_size(self.fmt_size().into())
+                - self.chunk_header_size()
+                - u64::from(self.fmt_size()),
+            sink,
+        )?;
+
+        if self.has_fact() {
+            self.write_chunk_header(*b"fact", self.fact_size(), sink)?;
+
+            if self.container == WavContainer::Wave64 {
+                sink.write_all(&sample_count.to_le_bytes())?;
+            } else {
+                sink.write_all(&u32::try_from(sample_count).unwrap_or(u32::MAX).to_le_bytes())?;
+            }
+        }
+
+        if let Some(bext) = self.bext.filter(|_| self.has_bext()) {
+            write_bext(bext, sink)?;
+        }
+
+        if self.container == WavContainer::Rf64 {
+            sink.write_all(b"data")?;
+            sink.write_all(&u32::MAX.to_le_bytes())
+        } else {
+            self.write_chunk_header(*b"data", data_size, sink)
+        }
+    }
+
+    // Writes a header for a data chunk whose size isn't known yet, which is patched by `WavWriter::finish`.
+    // CAF files mark the size as unknown, so they are valid even if the header is never patched.
+    fn write_streaming_header<W: Write>(self, sink: &mut W) -> Result<(), IoError> {
+        if self.output == PcmOutput::Caf {
+            caf::write_header(self, None, sink)
+        } else {
+            self.write_header(0, sink)
+        }
+    }
+
+    // Writes the padding byte that follows a data chunk of an odd size, then the metadata chunks.
+    pub(super) fn write_trailer<W: Write>(
+        self,
+        data_size: u64,
+        sink: &mut W,
+    ) -> Result<(), IoError> {
+        if self.output != PcmOutput::Wav || self.container == WavContainer::Wave64 {
+            return write_padding(self.trailer_size(data_size), sink);
+        }
+
+        write_padding(data_size % 2, sink)?;
+
+        for chunk in self.trailer_chunks() {
+            chunk.write(sink)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Creates a smpl chunk holding a single loop.
+// smpl chunk layout taken from:
+// [1]: https://www.recordingblogs.com/wiki/sample-chunk-of-a-wave-file
+fn smpl_chunk(sample_loop: SampleLoop, cue_id: u32, sample_rate: u32) -> Chunk {
+    let mut data = Vec::with_capacity(36 + 24);
+
+    // manufacturer and product
+    data.extend_from_slice(&[0; 8]);
+    // sample period (ns), MIDI unity note (middle C), and MIDI pitch fraction
+    data.extend_from_slice(&(1_000_000_000 / sample_rate).to_le_bytes());
+    data.extend_from_slice(&60u32.to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes());
+    // SMPTE format and offset
+    data.extend_from_slice(&[0; 8]);
+    // number of loops and size of sampler data
+    data.extend_from_slice(&1u32.to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes());
+    // cue point ID, loop type (forward), start, end, fraction, and play count (infinite)
+    data.extend_from_slice(&cue_id.to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data.extend_from_slice(&sample_loop.start.to_le_bytes());
+    data.extend_from_slice(&sample_loop.end.to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes());
+
+    Chunk::new(*b"smpl", data)
+}
+
+// Creates a cue chunk with cue points at the start and end of a loop.
+// cue chunk layout taken from:
+// [1]: https://www.recordingblogs.com/wiki/cue-chunk-of-a-wave-file
+fn cue_chunk(cue_loop: SampleLoop) -> Chunk {
+    let mut data = Vec::with_capacity(4 + 2 * 24);
+
+    // number of cue points
+    data.extend_from_slice(&2u32.to_le_bytes());
+
+    for (id, position) in [
+        (LOOP_START_CUE_ID, cue_loop.start),
+        (LOOP_END_CUE_ID, cue_loop.end),
+    ] {
+        // ID, position, chunk holding the sample, chunk start, block start, and sample offset
+        data.extend_from_slice(&id.to_le_bytes());
+        data.extend_from_slice(&position.to_le_bytes());
+        data.extend_from_slice(b"data");
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&position.to_le_bytes());
+    }
+
+    Chunk::new(*b"cue ", data)
+}
+
+// Returns the default speaker layout for a number of channels, as a WAVE channel mask.
+// Layouts without a common speaker arrangement aren't assigned to speakers (mask of 0).
+pub(crate) fn channel_mask(channels: u16) -> u32 {
+    const FRONT_LEFT: u32 = 0x1;
+    const FRONT_RIGHT: u32 = 0x2;
+    const FRONT_CENTER: u32 = 0x4;
+    const LOW_FREQUENCY: u32 = 0x8;
+    const BACK_LEFT: u32 = 0x10;
+    const BACK_RIGHT: u32 = 0x20;
+    const BACK_CENTER: u32 = 0x100;
+    const SIDE_LEFT: u32 = 0x200;
+    const SIDE_RIGHT: u32 = 0x400;
+
+    const STEREO: u32 = FRONT_LEFT | FRONT_RIGHT;
+    const QUAD: u32 = STEREO | BACK_LEFT | BACK_RIGHT;
+    const SURROUND_5_1: u32 = QUAD | FRONT_CENTER | LOW_FREQUENCY;
+
+    match channels {
+        1 => FRONT_CENTER,
+        2 => STEREO,
+        3 => STEREO | FRONT_CENTER,
+        4 => QUAD,
+        5 => QUAD | FRONT_CENTER,
+        6 => SURROUND_5_1,
+        7 => STEREO | FRONT_CENTER | LOW_FREQUENCY | BACK_CENTER | SIDE_LEFT | SIDE_RIGHT,
+        8 => SURROUND_5_1 | SIDE_LEFT | SIDE_RIGHT,
+        _ => 0,
+    }
+}
+
+// Writes a bext chunk, filling in the fields that weren't set.
+fn write_bext<W: Write>(bext: BextInfo<'_>, sink: &mut W) -> Result<(), IoError> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let (date, time) = utc_date_time(timestamp);
+
+    sink.write_all(b"bext")?;
+    sink.write_all(&BEXT_CHUNK_SIZE.to_le_bytes())?;
+    write_fixed_text(bext.description.unwrap_or_default(), 256, sink)?;
+    write_fixed_text(bext.originator.unwrap_or(ORIGINATOR), 32, sink)?;
+    write_fixed_text(bext.originator_reference.unwrap_or_default(), 32, sink)?;
+    write_fixed_text(bext.origination_date.unwrap_or(&date), 10, sink)?;
+    write_fixed_text(bext.origination_time.unwrap_or(&time), 8, sink)?;
+    // the time reference is stored as its low 32 bits followed by its high 32 bits
+    sink.write_all(&bext.time_reference.to_le_bytes())?;
+    // version 1, with an unset UMID; loudness values are only defined for version 2
+    sink.write_all(&1u16.to_le_bytes())?;
+    sink.write_all(&[0; 64])?;
+    sink.write_all(&[0; 10])?;
+    sink.write_all(&[0; 180])
+}
+
+// Writes text to a field of `len` bytes. Shorter text is padded with null bytes.
+fn write_fixed_text<W: Write>(text: &str, len: usize, sink: &mut W) -> Result<(), IoError> {
+    let text = truncate_text(text, len);
+
+    sink.write_all(text.as_bytes())?;
+    write_padding((len - text.len()) as u64, sink)
+}
+
+// Formats a Unix timestamp as a UTC date (`yyyy-mm-dd`) and time (`hh:mm:ss`).
+// Dates are converted with the algorithm from http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn utc_date_time(timestamp: u64) -> (String, String) {
+    let (days, seconds) = (timestamp / 86_400, timestamp % 86_400);
+
+    // days are counted from 0000-03-01, so that leap days are at the end of each year
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+
+    (
+        format!("{year:04}-{month:02}-{day:02}"),
+        format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60),
+    )
+}
+
+fn write_padding<W: Write>(len: u64, sink: &mut W) -> Result<(), IoError> {
+    for _ in 0..len {
+        sink.write_all(&[0])?;
+    }
+
+    Ok(())
+}
+
+fn seek_offset(len: u64) -> Result<i64, IoError> {
+    i64::try_from(len).map_err(|_| too_large())
+}
+
+fn too_large() -> IoError {
+    IoError::new(
+        ErrorKind::InvalidInput,
+        "WAVE file would be too large for its container",
+    )
+}
+
+// Writes a WAVE file whose data size isn't known in advance.
+// A header with placeholder sizes is written first, then patched once all data has been written.
+// Only the MPEG and Vorbis decoders need this, since other decoders know their output size in advance.
+#[cfg_attr(
+    not(any(feature = "decode-mpeg", feature = "decode-vorbis")),
+    allow(dead_code)
+)]
+pub(super) struct WavWriter<'a, W> {
+    spec: WavSpec<'a>,
+    sink: W,
+    data_size: u64,
+}
+
+#[cfg_attr(
+    not(any(feature = "decode-mpeg", feature = "decode-vorbis")),
+    allow(dead_code)
+)]
+impl<'a, W: Write> WavWriter<'a, W> {
+    pub(super) fn new(spec: WavSpec<'a>, mut sink: W) -> Result<Self, IoError> {
+        spec.write_streaming_header(&mut sink)?;
+
+        Ok(Self {
+            spec,
+            sink,
+            data_size: 0,
+        })
+    }
+
+    pub(super) fn write_data(&mut self, data: &[u8]) -> Result<(), IoError> {
+        // reject data that can't be described by the 32-bit size fields before any of it is written
+        let data_size = u64::try_from(data.len())
+            .ok()
+            .and_then(|len| self.data_size.checked_add(len))
+            .filter(|&size| self.spec.file_size(size).is_some())
+            .ok_or_else(too_large)?;
+
+        self.sink.write_all(data)?;
+        self.data_size = data_size;
+        Ok(())
+    }
+}
+
+#[cfg_attr(
+    not(any(feature = "decode-mpeg", feature = "decode-vorbis")),
+    allow(dead_code)
+)]
+impl<W: Write + Seek> WavWriter<'_, W> {
+    // Patches the header of a file written to a seekable sink.
+    // The sink doesn't have to start at position 0; the header is found relative to the end of the data.
+    pub(super) fn finish(mut self) -> Result<W, IoError> {
+        self.spec.write_trailer(self.data_size, &mut self.sink)?;
+
+        let file_size = self.spec.file_size(self.data_size).ok_or_else(too_large)?;
+        let _end = self.sink.seek(SeekFrom::Current(-seek_offset(file_size)?))?;
+
+        self.spec.write_header(self.data_size, &mut self.sink)?;
+
+        let _end = self
+            .sink
+            .seek(SeekFrom::Current(seek_offset(file_size - self.spec.header_size())?))?;
+        self.sink.flush()?;
+
+        Ok(self.sink)
+    }
+}
+
+#[cfg_attr(
+    not(any(feature = "decode-mpeg", feature = "decode-vorbis")),
+    allow(dead_code)
+)]
+impl<'a> WavWriter<'a, Cursor<Vec<u8>>> {
+    // Creates a writer that keeps the whole file in memory, for sinks that can't seek.
+    pub(super) fn buffered(spec: WavSpec<'a>) -> Self {
+        Self::new(spec, Cursor::new(Vec::new())).expect("writing to a Vec<u8> can't fail")
+    }
+
+    // Patches the header of the buffered file, then writes the file to `sink`.
+    pub(super) fn finish_into<W: Write>(self, mut sink: W) -> Result<W, IoError> {
+        let buf = self.finish()?.into_inner();
+
+        sink.write_all(&buf)?;
+        sink.flush()?;
+
+        Ok(sink)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        channel_mask, truncate_text, utc_date_time, BextInfo, Format, InfoList, PcmOutput,
+        SampleLoop, WavContainer, WavSpec, WavWriter,
+    };
+    use crate::{
+        encode::EncodeOptions,
+        header::{Loop, StreamInfo},
+    };
+    use std::io::Cursor;
+
+    fn float_spec() -> WavSpec<'static> {
+        WavSpec {
+            format: Format::Float,
+            bits_per_sample: 32,
+            channels: 2,
+            sample_rate: 48000,
+            sample_loop: None,
+            cue_loop: None,
+            info: InfoList::default(),
+            container: WavContainer::Riff,
+            output: PcmOutput::Wav,
+            bext: None,
+        }
+    }
+
+    #[test]
+    fn patch_sizes_on_finish() {
+        let mut writer = WavWriter::new(WavSpec::pcm16(1, 44100), Cursor::new(Vec::new())).unwrap();
+        writer.write_data(&[1, 2]).unwrap();
+        writer.write_data(&[3, 4, 5, 6]).unwrap();
+        let output = writer.finish().unwrap().into_inner();
+
+        let mut expected = Vec::new();
+        WavSpec::pcm16(1, 44100).write_header(6, &mut expected).unwrap();
+        expected.extend_from_slice(&[1, 2, 3, 4, 5, 6]);
+
+        assert_eq!(output, expected);
+        assert_eq!(output[4..8], 42u32.to_le_bytes());
+        assert_eq!(output[40..44], 6u32.to_le_bytes());
+    }
+
+    #[test]
+    fn patch_fact_chunk_on_finish() {
+        let mut writer = WavWriter::new(float_spec(), Cursor::new(Vec::new())).unwrap();
+        writer.write_data(&[0; 24]).unwrap();
+        let output = writer.finish().unwrap().into_inner();
+
+        assert_eq!(output.len(), 58 + 24);
+        assert_eq!(output[4..8], 74u32.to_le_bytes());
+        assert_eq!(output[38..42], *b"fact");
+        assert_eq!(output[46..50], 3u32.to_le_bytes());
+        assert_eq!(output[54..58], 24u32.to_le_bytes());
+    }
+
+    #[test]
+    fn pad_odd_data_size() {
+        let spec = WavSpec {
+            format: Format::Integer,
+            bits_per_sample: 8,
+            channels: 1,
+            sample_rate: 8000,
+            sample_loop: None,
+            cue_loop: None,
+            info: InfoList::default(),
+            container: WavContainer::Riff,
+            output: PcmOutput::Wav,
+            bext: None,
+        };
+
+        let mut writer = WavWriter::new(spec, Cursor::new(Vec::new())).unwrap();
+        writer.write_data(&[0x80, 0x81, 0x82]).unwrap();
+        let output = writer.finish().unwrap().into_inner();
+
+        // the data chunk size excludes the padding byte, but the RIFF chunk size includes it
+        assert_eq!(output.len(), 44 + 4);
+        assert_eq!(output[4..8], 40u32.to_le_bytes());
+        assert_eq!(output[40..44], 3u32.to_le_bytes());
+        assert_eq!(output[44..], [0x80, 0x81, 0x82, 0]);
+    }
+
+    #[test]
+    fn finish_at_nonzero_position() {
+        let mut sink = Cursor::new(Vec::new());
+        sink.get_mut().extend_from_slice(b"prefix");
+        sink.set_position(6);
+
+        let mut writer = WavWriter::new(WavSpec::pcm16(2, 22050), sink).unwrap();
+        writer.write_data(&[7; 8]).unwrap();
+        let sink = writer.finish().unwrap();
+
+        assert_eq!(sink.position(), 6 + 44 + 8);
+        let output = sink.into_inner();
+        assert_eq!(output[..6], *b"prefix");
+        assert_eq!(output[6..10], *b"RIFF");
+        assert_eq!(output[46..50], 8u32.to_le_bytes());
+    }
+
+    #[test]
+    fn buffered_matches_seekable() {
+        let mut seekable = WavWriter::new(float_spec(), Cursor::new(Vec::new())).unwrap();
+        let mut buffered = WavWriter::buffered(float_spec());
+
+        for chunk in [[1; 8], [2; 8]] {
+            seekable.write_data(&chunk).unwrap();
+            buffered.write_data(&chunk).unwrap();
+        }
+
+        assert_eq!(
+            seekable.finish().unwrap().into_inner(),
+            buffered.finish_into(Vec::new()).unwrap()
+        );
+    }
+
+    #[test]
+    fn reject_data_over_4_gib() {
+        let spec = WavSpec::pcm16(1, 44100);
+        assert!(spec.write_header(u64::from(u32::MAX) - 35, &mut Vec::new()).is_err());
+        assert!(spec.write_header(u64::from(u32::MAX) - 37, &mut Vec::new()).is_ok());
+
+        let mut writer = WavWriter::buffered(spec);
+        writer.data_size = u64::from(u32::MAX) - 38;
+        writer.write_data(&[0]).unwrap();
+        assert!(writer.write_data(&[0]).is_err());
+        assert_eq!(writer.data_size, u64::from(u32::MAX) - 37);
+    }
+
+    #[test]
+    fn write_extensible_header_for_surround() {
+        let spec = WavSpec {
+            format: Format::Integer,
+            bits_per_sample: 24,
+            channels: 6,
+            sample_rate: 48000,
+            sample_loop: None,
+            cue_loop: None,
+            info: InfoList::default(),
+            container: WavContainer::Riff,
+            output: PcmOutput::Wav,
+            bext: None,
+        };
+
+        let mut output = Vec::new();
+        spec.write_header(36, &mut output).unwrap();
+
+        assert_eq!(output.len(), 80);
+        assert_eq!(output[4..8], (72u32 + 36).to_le_bytes());
+        assert_eq!(output[16..20], 40u32.to_le_bytes());
+        assert_eq!(output[20..22], 0xFFFEu16.to_le_bytes());
+        assert_eq!(output[22..24], 6u16.to_le_bytes());
+        assert_eq!(output[32..36], [18, 0, 24, 0]);
+        // extension size, valid bits per sample, and 5.1 channel mask
+        assert_eq!(output[36..38], 22u16.to_le_bytes());
+        assert_eq!(output[38..40], 24u16.to_le_bytes());
+        assert_eq!(output[40..44], 0x3Fu32.to_le_bytes());
+        // KSDATAFORMAT_SUBTYPE_PCM
+        assert_eq!(
+            output[44..60],
+            [
+                0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38,
+                0x9B, 0x71
+            ]
+        );
+        assert_eq!(output[60..64], *b"fact");
+        assert_eq!(output[68..72], 2u32.to_le_bytes());
+        assert_eq!(output[72..76], *b"data");
+        assert_eq!(output[76..80], 36u32.to_le_bytes());
+    }
+
+    #[test]
+    fn keep_plain_header_for_stereo() {
+        let mut output = Vec::new();
+        WavSpec::pcm16(2, 44100).write_header(4, &mut output).unwrap();
+
+        assert_eq!(output.len(), 44);
+        assert_eq!(output[16..22], [16, 0, 0, 0, 1, 0]);
+    }
+
+    #[test]
+    fn choose_channel_masks() {
+        assert_eq!(channel_mask(4), 0x33);
+        assert_eq!(channel_mask(6), 0x3F);
+        assert_eq!(channel_mask(8), 0x63F);
+        assert_eq!(channel_mask(12), 0);
+    }
+
+    #[test]
+    fn write_smpl_chunk_after_data() {
+        let info = StreamInfo {
+            stream_loop: Some(Loop::dummy(2, 9)),
+            ..StreamInfo::dummy(1, 20, 40)
+        };
+        let spec = WavSpec::pcm16(1, 44100).with_options(&info, EncodeOptions::default(), 40);
+
+        let mut writer = WavWriter::new(spec, Cursor::new(Vec::new())).unwrap();
+        writer.write_data(&[0; 40]).unwrap();
+        let output = writer.finish().unwrap().into_inner();
+
+        assert_eq!(output.len(), 44 + 40 + 68);
+        assert_eq!(output[4..8], (36u32 + 40 + 68).to_le_bytes());
+        assert_eq!(output[40..44], 40u32.to_le_bytes());
+        assert_eq!(output[84..88], *b"smpl");
+        assert_eq!(output[88..92], 60u32.to_le_bytes());
+        assert_eq!(output[100..104], 22675u32.to_le_bytes());
+        // number of loops
+        assert_eq!(output[120..124], 1u32.to_le_bytes());
+        // loop type, start, end, fraction, and play count
+        assert_eq!(output[132..136], 0u32.to_le_bytes());
+        assert_eq!(output[136..140], 2u32.to_le_bytes());
+        assert_eq!(output[140..144], 9u32.to_le_bytes());
+        assert_eq!(output[148..152], 0u32.to_le_bytes());
+    }
+
+    #[test]
+    fn write_cue_points_after_smpl_chunk() {
+        let info = StreamInfo {
+            stream_loop: Some(Loop::dummy(2, 9)),
+            ..StreamInfo::dummy(1, 20, 40)
+        };
+        let options = EncodeOptions::new().cue_chunk(true);
+        let spec = WavSpec::pcm16(1, 44100).with_options(&info, options, 40);
+
+        let mut writer = WavWriter::new(spec, Cursor::new(Vec::new())).unwrap();
+        writer.write_data(&[0; 40]).unwrap();
+        let output = writer.finish().unwrap().into_inner();
+
+        assert_eq!(output.len(), 84 + 68 + 60 + 58);
+        assert_eq!(output[4..8], (76u32 + 68 + 60 + 58).to_le_bytes());
+        // the loop of the smpl chunk refers to the cue point at the loop start
+        assert_eq!(output[84..88], *b"smpl");
+        assert_eq!(output[128..132], 1u32.to_le_bytes());
+        // number of cue points, then the ID, position, chunk, and sample offset of both cue points
+        assert_eq!(output[152..160], *b"cue \x34\0\0\0");
+        assert_eq!(output[160..164], 2u32.to_le_bytes());
+        assert_eq!(output[164..168], 1u32.to_le_bytes());
+        assert_eq!(output[168..172], 2u32.to_le_bytes());
+        assert_eq!(output[172..176], *b"data");
+        assert_eq!(output[184..188], 2u32.to_le_bytes());
+        assert_eq!(output[188..192], 2u32.to_le_bytes());
+        assert_eq!(output[192..196], 9u32.to_le_bytes());
+        assert_eq!(output[208..212], 9u32.to_le_bytes());
+        // labels are padded to an even size
+        assert_eq!(output[212..224], *b"LIST\x32\0\0\0adtl");
+        assert_eq!(output[224..248], *b"labl\x0F\0\0\0\x01\0\0\0loop start\0\0");
+        assert_eq!(output[248..], *b"labl\x0D\0\0\0\x02\0\0\0loop end\0\0");
+    }
+
+    #[test]
+    fn write_cue_points_without_smpl_chunk() {
+        let info = StreamInfo {
+            stream_loop: Some(Loop::dummy(2, 9)),
+            ..StreamInfo::dummy(1, 20, 40)
+        };
+        let options = EncodeOptions::new().loop_chunk(false).cue_chunk(true);
+        let spec = WavSpec::pcm16(1, 44100).with_options(&info, options, 40);
+
+        assert_eq!(spec.sample_loop, None);
+        assert_eq!(spec.cue_loop, Some(SampleLoop { start: 2, end: 9 }));
+
+        let mut output = Vec::new();
+        spec.write_trailer(40, &mut output).unwrap();
+
+        assert_eq!(output.len(), 60 + 58);
+        assert_eq!(output[0..4], *b"cue ");
+        assert_eq!(output[60..64], *b"LIST");
+    }
+
+    #[test]
+    fn skip_smpl_chunk_when_disabled() {
+        let info = StreamInfo {
+            stream_loop: Some(Loop::dummy(2, 9)),
+            ..StreamInfo::dummy(1, 20, 40)
+        };
+        let spec = WavSpec::pcm16(1, 44100).with_options(
+            &info,
+            EncodeOptions::new().loop_chunk(false),
+            40,
+        );
+
+        assert_eq!(spec.sample_loop, None);
+    }
+
+    #[test]
+    fn clamp_loop_end_to_last_sample() {
+        assert_eq!(
+            SampleLoop::new(Loop::dummy(4, 30), 20),
+            Some(SampleLoop { start: 4, end: 19 })
+        );
+        assert_eq!(SampleLoop::new(Loop::dummy(20, 30), 20), None);
+    }
+
+    #[test]
+    fn write_info_list_after_data() {
+        let info = StreamInfo {
+            name: Some("music".into()),
+            ..StreamInfo::dummy(1, 2, 4)
+        };
+        let options = EncodeOptions::new().info_chunk(true).source_name("bank.fsb");
+        let spec = WavSpec::pcm16(1, 44100).with_options(&info, options, 4);
+
+        let mut output = Vec::new();
+        spec.write_header(4, &mut output).unwrap();
+        output.extend_from_slice(&[0; 4]);
+        spec.write_trailer(4, &mut output).unwrap();
+
+        // "bank.fsb" is padded to an even size after its null terminator, but "music" isn't
+        assert_eq!(output.len(), 44 + 4 + 12 + 14 + 18);
+        assert_eq!(output[4..8], (36u32 + 4 + 12 + 14 + 18).to_le_bytes());
+        assert_eq!(output[48..52], *b"LIST");
+        assert_eq!(output[52..56], (4u32 + 14 + 18).to_le_bytes());
+        assert_eq!(output[56..60], *b"INFO");
+        assert_eq!(output[60..64], *b"INAM");
+        assert_eq!(output[64..68], 6u32.to_le_bytes());
+        assert_eq!(output[68..74], *b"music\0");
+        assert_eq!(output[74..78], *b"ISRC");
+        assert_eq!(output[78..82], 9u32.to_le_bytes());
+        assert_eq!(output[82..], *b"bank.fsb\0\0");
+    }
+
+    #[test]
+    fn skip_info_list_by_default() {
+        let info = StreamInfo {
+            name: Some("music".into()),
+            ..StreamInfo::dummy(1, 2, 4)
+        };
+        let spec = WavSpec::pcm16(1, 44100).with_options(&info, EncodeOptions::default(), 40);
+        assert_eq!(spec.info, InfoList::default());
+
+        let info = StreamInfo::dummy(1, 2, 4);
+        let spec =
+            WavSpec::pcm16(1, 44100).with_options(&info, EncodeOptions::new().info_chunk(true), 4);
+        assert_eq!(spec.info.chunk(), None);
+    }
+
+    #[test]
+    fn truncate_long_info_text() {
+        assert_eq!(truncate_text("short", 255), "short");
+        assert_eq!(truncate_text(&"a".repeat(300), 255).len(), 255);
+        // a 2-byte character straddling the limit is dropped entirely
+        assert_eq!(truncate_text(&("a".repeat(254) + "\u{e9}"), 255).len(), 254);
+    }
+
+    #[test]
+    fn switch_to_rf64_for_large_data() {
+        let info = StreamInfo::dummy(2, 1, 4);
+        let data_size = 5 << 30;
+
+        let spec = WavSpec::pcm16(2, 44100).with_options(
+            &info,
+            EncodeOptions::default(),
+            u64::from(u32::MAX) - 37,
+        );
+        assert!(spec.container == WavContainer::Riff);
+
+        let spec =
+            WavSpec::pcm16(2, 44100).with_options(&info, EncodeOptions::default(), data_size);
+        assert!(spec.container == WavContainer::Rf64);
+
+        let mut output = Vec::new();
+        spec.write_header(data_size, &mut output).unwrap();
+
+        assert_eq!(output.len(), 80);
+        assert_eq!(output[0..4], *b"RF64");
+        assert_eq!(output[4..8], u32::MAX.to_le_bytes());
+        assert_eq!(output[12..20], [b'd', b's', b'6', b'4', 28, 0, 0, 0]);
+        // RIFF size, data size, sample count, and table length
+        assert_eq!(output[20..28], (72 + data_size).to_le_bytes());
+        assert_eq!(output[28..36], data_size.to_le_bytes());
+        assert_eq!(output[36..44], (data_size / 4).to_le_bytes());
+        assert_eq!(output[44..48], 0u32.to_le_bytes());
+        assert_eq!(output[48..52], *b"fmt ");
+        assert_eq!(output[72..76], *b"data");
+        assert_eq!(output[76..80], u32::MAX.to_le_bytes());
+    }
+
+    #[test]
+    fn force_rf64_for_small_data() {
+        let info = StreamInfo::dummy(1, 2, 4);
+        let options = EncodeOptions::new().wav_container(WavContainer::Rf64);
+        let spec = WavSpec::pcm16(1, 44100).with_options(&info, options, 4);
+
+        let mut writer = WavWriter::new(spec, Cursor::new(Vec::new())).unwrap();
+        writer.write_data(&[1, 2, 3, 4]).unwrap();
+        let output = writer.finish().unwrap().into_inner();
+
+        assert_eq!(output.len(), 80 + 4);
+        assert_eq!(output[0..4], *b"RF64");
+        assert_eq!(output[20..28], 76u64.to_le_bytes());
+        assert_eq!(output[28..36], 4u64.to_le_bytes());
+        assert_eq!(output[80..], [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn patch_caf_data_size_on_finish() {
+        let info = StreamInfo {
+            name: Some("bgm".into()),
+            ..StreamInfo::dummy(1, 2, 4)
+        };
+        let options = EncodeOptions::new().pcm_output(PcmOutput::Caf);
+        let spec = WavSpec::pcm16(1, 44100).with_options(&info, options, 4);
+
+        let mut writer = WavWriter::new(spec, Cursor::new(Vec::new())).unwrap();
+        writer.write_data(&[1, 2, 3, 4]).unwrap();
+        let output = writer.finish().unwrap().into_inner();
+
+        // the info chunk holds the stream name, even though the INFO list of WAVE files is disabled
+        assert_eq!(output.len(), 94 + 4);
+        assert_eq!(output[0..4], *b"caff");
+        assert_eq!(output[52..64], *b"info\0\0\0\0\0\0\0\x0E");
+        assert_eq!(output[64..78], *b"\0\0\0\x01title\0bgm\0");
+        assert_eq!(output[78..82], *b"data");
+        assert_eq!(output[82..90], 8u64.to_be_bytes());
+        assert_eq!(output[94..], [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn write_bext_chunk_before_data() {
+        let info = StreamInfo {
+            name: Some("bgm_title".into()),
+            ..StreamInfo::dummy(1, 2, 4)
+        };
+        let bext = BextInfo::new()
+            .originator_reference("a reference longer than 32 bytes!")
+            .origination_date("2024-05-06")
+            .origination_time("07:08:09")
+            .time_reference(0x1_0000_0002);
+        let options = EncodeOptions::new().bext_chunk(bext);
+        let spec = WavSpec::pcm16(1, 44100).with_options(&info, options, 4);
+
+        let mut output = Vec::new();
+        spec.write_header(4, &mut output).unwrap();
+
+        let bext = &output[36..36 + 8 + 602];
+        assert_eq!(output.len(), 36 + 8 + 602 + 8);
+        assert_eq!(output[4..8], (36 + 8 + 602 + 4u32).to_le_bytes());
+        assert_eq!(bext[0..8], [b'b', b'e', b'x', b't', 0x5A, 0x02, 0, 0]);
+        // the description defaults to the stream name, and text fields are padded with null bytes
+        assert_eq!(bext[8..18], *b"bgm_title\0");
+        assert!(bext[18..264].iter().all(|&byte| byte == 0));
+        assert_eq!(bext[264..296], *format!("{:\0<32}", super::ORIGINATOR).as_bytes());
+        assert_eq!(bext[296..328], *b"a reference longer than 32 bytes");
+        assert_eq!(bext[328..346], *b"2024-05-0607:08:09");
+        assert_eq!(bext[346..354], [2, 0, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(bext[354..356], 1u16.to_le_bytes());
+        assert_eq!(output[646..650], *b"data");
+
+        // Wave64 files don't hold metadata chunks
+        let options = options.wav_container(WavContainer::Wave64);
+        let spec = WavSpec::pcm16(1, 44100).with_options(&info, options, 4);
+        assert_eq!(spec.header_size(), WavSpec { bext: None, ..spec }.header_size());
+    }
+
+    #[test]
+    fn format_utc_date_time() {
+        let format = |timestamp| {
+            let (date, time) = utc_date_time(timestamp);
+            format!("{date} {time}")
+        };
+
+        assert_eq!(format(0), "1970-01-01 00:00:00");
+        assert_eq!(format(951_782_400), "2000-02-29 00:00:00");
+        assert_eq!(format(1_700_000_000), "2023-11-14 22:13:20");
+        assert_eq!(format(4_107_542_399), "2100-02-28 23:59:59");
+    }
+
+    #[test]
+    fn write_wave64_file() {
+        let info = StreamInfo {
+            stream_loop: Some(Loop::dummy(0, 1)),
+            ..StreamInfo::dummy(2, 2, 6)
+        };
+        let options = EncodeOptions::new().wav_container(WavContainer::Wave64);
+        let spec = WavSpec::pcm16(2, 44100).with_options(&info, options, 6);
+
+        let mut writer = WavWriter::new(spec, Cursor::new(Vec::new())).unwrap();
+        writer.write_data(&[7; 6]).unwrap();
+        let output = writer.finish().unwrap().into_inner();
+
+        // data is padded to 8 bytes, and the smpl chunk is left out
+        assert_eq!(output.len(), 104 + 8);
+        assert_eq!(output[0..4], *b"riff");
+        assert_eq!(output[16..24], 112u64.to_le_bytes());
+        assert_eq!(output[24..28], *b"wave");
+        assert_eq!(output[28..40], super::W64_GUID_SUFFIX);
+        assert_eq!(output[40..44], *b"fmt ");
+        assert_eq!(output[56..64], 40u64.to_le_bytes());
+        assert_eq!(output[80..84], *b"data");
+        assert_eq!(output[96..104], 30u64.to_le_bytes());
+        assert_eq!(output[104..], [7, 7, 7, 7, 7, 7, 0, 0]);
+
+        // the 18-byte fmt chunk of float files is padded to 8 bytes, and the fact chunk holds a 64-bit sample count
+        let spec = WavSpec {
+            container: WavContainer::Wave64,
+            ..float_spec()
+        };
+        let mut output = Vec::new();
+        spec.write_header(16, &mut output).unwrap();
+
+        assert_eq!(output.len(), 40 + 48 + 32 + 24);
+        assert_eq!(output[88..92], *b"fact");
+        assert_eq!(output[112..120], 2u64.to_le_bytes());
+    }
+}
@@ -0,0 +1,249 @@
+use crate::{header::StreamInfo, read::Reader};
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io::{copy, Error as IoError, Read, Write},
+};
+
+// XMA2 RIFF file header information taken from:
+// [1]: https://learn.microsoft.com/en-us/windows/win32/api/xma2defs/ns-xma2defs-xma2waveformatex
+// [2]: https://github.com/vgmstream/vgmstream/blob/master/src/coding/ffmpeg_decoder_utils.c
+
+// Stream data in sound banks is divided into blocks of this size.
+const BLOCK_SIZE: u32 = 0x8000;
+
+const FORMAT_TAG: u16 = 0x0166;
+const FMT_CHUNK_SIZE: u32 = 0x34;
+const XMA2_EXTRA_SIZE: u16 = 0x22;
+const ENCODER_VERSION: u8 = 4;
+
+/// Encodes an XMA stream by wrapping the raw stream data in an XMA2 RIFF file.
+///
+/// The stream data is copied verbatim; only the file header is constructed.
+pub(super) fn encode<R: Read, W: Write>(
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+    mut sink: W,
+) -> Result<W, XmaError> {
+    write_header(info, &mut sink)?;
+
+    let stream_size = info.size as usize;
+
+    // There could be more data after the stream, so a limit is placed on the number of bytes read.
+    let _bytes_copied = copy(&mut source.limit(stream_size as u64), &mut sink)
+        .map_err(XmaError::from_io(XmaErrorKind::EncodeStream))?;
+
+    sink.flush()
+        .map(|()| sink)
+        .map_err(XmaError::from_io(XmaErrorKind::FinishStream))
+}
+
+fn write_header<W: Write>(info: &StreamInfo, sink: &mut W) -> Result<(), XmaError> {
+    let channels = u16::from(info.channels.get());
+    let sample_rate = info.sample_rate.get();
+    let num_samples = info.num_samples.get();
+    let data_size = info.size;
+    let block_count = data_size.div_ceil(BLOCK_SIZE);
+
+    // The seek table chunk contains the total number of samples at the end of each block, stored as big-endian.
+    let seek_table = info.xma_seek_table.as_deref().unwrap_or_default();
+    let seek_size = u32::try_from(4 * seek_table.len())
+        .map_err(|_| XmaError::new(XmaErrorKind::StreamTooLarge))?;
+    let seek_chunk_size = if seek_table.is_empty() {
+        0
+    } else {
+        8 + seek_size
+    };
+
+    let riff_size = (4 + 8 + FMT_CHUNK_SIZE + 8)
+        .checked_add(seek_chunk_size)
+        .and_then(|size| size.checked_add(data_size))
+        .ok_or_else(|| XmaError::new(XmaErrorKind::StreamTooLarge))?;
+
+    let block_count =
+        u16::try_from(block_count).map_err(|_| XmaError::new(XmaErrorKind::StreamTooLarge))?;
+
+    (|| {
+        sink.write_all(b"RIFF")?;
+        sink.write_all(&riff_size.to_le_bytes())?;
+        sink.write_all(b"WAVE")?;
+
+        // base WAVEFORMATEX fields
+        sink.write_all(b"fmt ")?;
+        sink.write_all(&FMT_CHUNK_SIZE.to_le_bytes())?;
+        sink.write_all(&FORMAT_TAG.to_le_bytes())?;
+        sink.write_all(&channels.to_le_bytes())?;
+        sink.write_all(&sample_rate.to_le_bytes())?;
+        sink.write_all(&(sample_rate * u32::from(channels) * 2).to_le_bytes())?;
+        sink.write_all(&(channels * 2).to_le_bytes())?;
+        sink.write_all(&16u16.to_le_bytes())?;
+        sink.write_all(&XMA2_EXTRA_SIZE.to_le_bytes())?;
+
+        // XMA2-specific fields; streams contain up to 2 channels each
+        sink.write_all(&channels.div_ceil(2).to_le_bytes())?;
+        sink.write_all(&channel_mask(channels).to_le_bytes())?;
+        sink.write_all(&num_samples.to_le_bytes())?;
+        sink.write_all(&BLOCK_SIZE.to_le_bytes())?;
+        sink.write_all(&0u32.to_le_bytes())?;
+        sink.write_all(&num_samples.to_le_bytes())?;
+        sink.write_all(&0u32.to_le_bytes())?;
+        sink.write_all(&0u32.to_le_bytes())?;
+        sink.write_all(&[0, ENCODER_VERSION])?;
+        sink.write_all(&block_count.to_le_bytes())?;
+
+        if !seek_table.is_empty() {
+            sink.write_all(b"seek")?;
+            sink.write_all(&seek_size.to_le_bytes())?;
+
+            for entry in seek_table {
+                sink.write_all(&entry.to_be_bytes())?;
+            }
+        }
+
+        sink.write_all(b"data")?;
+        sink.write_all(&data_size.to_le_bytes())
+    })()
+    .map_err(XmaError::from_io(XmaErrorKind::CreateHeader))
+}
+
+// Returns the default speaker layout for a number of channels.
+fn channel_mask(channels: u16) -> u32 {
+    match channels {
+        1 => 0x04,
+        2 => 0x03,
+        3 => 0x07,
+        4 => 0x33,
+        5 => 0x37,
+        6 => 0x3F,
+        7 => 0x013F,
+        8 => 0x063F,
+        _ => 0,
+    }
+}
+
+/// Represents an error that can occur when encoding an XMA stream.
+///
+/// See [`XmaErrorKind`] for the different kinds of errors that can occur.
+#[derive(Debug)]
+pub struct XmaError {
+    kind: XmaErrorKind,
+    source: Option<IoError>,
+}
+
+/// A variant of an [`XmaError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum XmaErrorKind {
+    /// The stream was too large to fit in an XMA2 RIFF file.
+    StreamTooLarge,
+    /// Failed to write the file header due to an underlying I/O error.
+    CreateHeader,
+    /// Failed to encode the entire stream via copying from reader to writer.
+    EncodeStream,
+    /// Failed to flush the writer after encoding the entire stream.
+    FinishStream,
+}
+
+impl XmaError {
+    fn new(kind: XmaErrorKind) -> Self {
+        Self { kind, source: None }
+    }
+
+    fn from_io(kind: XmaErrorKind) -> impl FnOnce(IoError) -> Self {
+        move |source| Self {
+            kind,
+            source: Some(source),
+        }
+    }
+
+    /// Returns the [`XmaErrorKind`] associated with this error.
+    #[must_use]
+    pub fn kind(&self) -> XmaErrorKind {
+        self.kind
+    }
+}
+
+impl Display for XmaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.kind.fmt(f)
+    }
+}
+
+impl Error for XmaError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.source {
+            Some(source) => Some(source),
+            None => None,
+        }
+    }
+}
+
+impl Display for XmaErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Self::StreamTooLarge => "XMA stream was too large to encode",
+            Self::CreateHeader => "failed to encode file header",
+            Self::EncodeStream => "failed to encode full XMA stream",
+            Self::FinishStream => "failed to finalize writing XMA stream data",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::encode;
+    use crate::{header::StreamInfo, read::Reader};
+
+    fn u16_at(data: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
+    }
+
+    fn u32_at(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn encode_without_seek_table() {
+        let info = StreamInfo::dummy(2, 512, 0x8001);
+        let data = vec![0xAB; 0x8001];
+        let output = encode(&info, &mut Reader::new(data.as_slice()), Vec::new()).unwrap();
+
+        assert_eq!(output[0..4], *b"RIFF");
+        assert_eq!(u32_at(&output, 4) as usize, output.len() - 8);
+        assert_eq!(output[8..16], *b"WAVEfmt ");
+        assert_eq!(u32_at(&output, 0x10), 0x34);
+        assert_eq!(u16_at(&output, 0x14), 0x0166);
+        assert_eq!(u16_at(&output, 0x16), 2);
+        assert_eq!(u32_at(&output, 0x18), 44100);
+        assert_eq!(u16_at(&output, 0x24), 0x22);
+        assert_eq!(u16_at(&output, 0x26), 1);
+        assert_eq!(u32_at(&output, 0x28), 0x03);
+        assert_eq!(u32_at(&output, 0x2C), 512);
+        assert_eq!(u32_at(&output, 0x30), 0x8000);
+        assert_eq!(u32_at(&output, 0x38), 512);
+        assert_eq!(output[0x45], 4);
+        assert_eq!(u16_at(&output, 0x46), 2);
+        assert_eq!(output[0x48..0x4C], *b"data");
+        assert_eq!(u32_at(&output, 0x4C), 0x8001);
+        assert_eq!(output[0x50..], data);
+    }
+
+    #[test]
+    fn encode_with_seek_table() {
+        let info = StreamInfo {
+            xma_seek_table: Some(vec![0x1000, 0x2000].into_boxed_slice()),
+            ..StreamInfo::dummy(6, 512, 4)
+        };
+        let data = [1, 2, 3, 4, 5, 6];
+        let output = encode(&info, &mut Reader::new(data.as_slice()), Vec::new()).unwrap();
+
+        assert_eq!(u32_at(&output, 4) as usize, output.len() - 8);
+        assert_eq!(u16_at(&output, 0x26), 3);
+        assert_eq!(u32_at(&output, 0x28), 0x3F);
+        assert_eq!(output[0x48..0x4C], *b"seek");
+        assert_eq!(u32_at(&output, 0x4C), 8);
+        assert_eq!(output[0x50..0x58], [0, 0, 0x10, 0, 0, 0, 0x20, 0]);
+        assert_eq!(output[0x58..0x5C], *b"data");
+        assert_eq!(output[0x60..], [1, 2, 3, 4]);
+    }
+}
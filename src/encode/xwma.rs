@@ -0,0 +1,251 @@
+use crate::header::{StreamInfo, XwmaConfig};
+use crate::read::Reader;
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io::{copy, Error as IoError, Read, Write},
+};
+
+// xWMA file header information taken from:
+// [1]: https://learn.microsoft.com/en-us/windows/win32/xaudio2/xaudio2-audio-data-formats
+// [2]: https://github.com/vgmstream/vgmstream/blob/master/src/meta/fsb5.c
+
+const FORMAT_TAG_WMAV2: u16 = 0x0161;
+const FORMAT_TAG_WMAPRO: u16 = 0x0162;
+
+const FMT_CHUNK_SIZE: u32 = 0x12;
+
+/// Encodes an xWMA stream by wrapping the raw stream data in an xWMA RIFF file.
+///
+/// The stream data is copied verbatim; only the file header is constructed.
+pub(super) fn encode<R: Read, W: Write>(
+    info: &StreamInfo,
+    source: &mut Reader<R>,
+    mut sink: W,
+) -> Result<W, XwmaError> {
+    // The stream should have contained its format information in a header chunk.
+    // Otherwise, the file header cannot be constructed.
+    let config = info
+        .xwma_config
+        .as_ref()
+        .ok_or_else(|| XwmaError::new(XwmaErrorKind::MissingConfig))?;
+
+    write_header(info, config, &mut sink)?;
+
+    let stream_size = info.size as usize;
+
+    // There could be more data after the stream, so a limit is placed on the number of bytes read.
+    let _bytes_copied = copy(&mut source.limit(stream_size as u64), &mut sink)
+        .map_err(XwmaError::from_io(XwmaErrorKind::EncodeStream))?;
+
+    sink.flush()
+        .map(|()| sink)
+        .map_err(XwmaError::from_io(XwmaErrorKind::FinishStream))
+}
+
+fn write_header<W: Write>(
+    info: &StreamInfo,
+    config: &XwmaConfig,
+    sink: &mut W,
+) -> Result<(), XwmaError> {
+    let format_tag = config.format_tag;
+
+    if format_tag != FORMAT_TAG_WMAV2 && format_tag != FORMAT_TAG_WMAPRO {
+        return Err(XwmaError::new(XwmaErrorKind::UnknownFormatTag { format_tag }));
+    }
+
+    let data_size = info.size;
+
+    // The dpds chunk contains the total number of bytes decoded by the end of each packet.
+    // It's needed for seeking, but decoders can read the stream without it, so it's left out if the table is empty.
+    let seek_table = &config.seek_table;
+    let seek_size = u32::try_from(4 * seek_table.len())
+        .map_err(|_| XwmaError::new(XwmaErrorKind::StreamTooLarge))?;
+    let seek_chunk_size = if seek_table.is_empty() {
+        0
+    } else {
+        8 + seek_size
+    };
+
+    let riff_size = (4 + 8 + FMT_CHUNK_SIZE + 8)
+        .checked_add(seek_chunk_size)
+        .and_then(|size| size.checked_add(data_size))
+        .ok_or_else(|| XwmaError::new(XwmaErrorKind::StreamTooLarge))?;
+
+    (|| {
+        sink.write_all(b"RIFF")?;
+        sink.write_all(&riff_size.to_le_bytes())?;
+        sink.write_all(b"XWMA")?;
+
+        sink.write_all(b"fmt ")?;
+        sink.write_all(&FMT_CHUNK_SIZE.to_le_bytes())?;
+        sink.write_all(&format_tag.to_le_bytes())?;
+        sink.write_all(&u16::from(info.channels.get()).to_le_bytes())?;
+        sink.write_all(&info.sample_rate.get().to_le_bytes())?;
+        sink.write_all(&config.avg_bytes_per_sec.to_le_bytes())?;
+        sink.write_all(&config.block_align.to_le_bytes())?;
+        sink.write_all(&16u16.to_le_bytes())?;
+        sink.write_all(&0u16.to_le_bytes())?;
+
+        if !seek_table.is_empty() {
+            sink.write_all(b"dpds")?;
+            sink.write_all(&seek_size.to_le_bytes())?;
+
+            for entry in seek_table {
+                sink.write_all(&entry.to_le_bytes())?;
+            }
+        }
+
+        sink.write_all(b"data")?;
+        sink.write_all(&data_size.to_le_bytes())
+    })()
+    .map_err(XwmaError::from_io(XwmaErrorKind::CreateHeader))
+}
+
+/// Represents an error that can occur when encoding an xWMA stream.
+///
+/// See [`XwmaErrorKind`] for the different kinds of errors that can occur.
+#[derive(Debug)]
+pub struct XwmaError {
+    kind: XwmaErrorKind,
+    source: Option<IoError>,
+}
+
+/// A variant of an [`XwmaError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum XwmaErrorKind {
+    /// The format information of the stream was not found in the stream header within the sound bank.
+    MissingConfig,
+    /// The format tag of the stream was neither WMA 2 nor WMA Pro, which are the only formats supported by xWMA.
+    UnknownFormatTag {
+        /// The format tag stored in the stream header.
+        format_tag: u16,
+    },
+    /// The stream was too large to fit in an xWMA RIFF file.
+    StreamTooLarge,
+    /// Failed to write the file header due to an underlying I/O error.
+    CreateHeader,
+    /// Failed to encode the entire stream via copying from reader to writer.
+    EncodeStream,
+    /// Failed to flush the writer after encoding the entire stream.
+    FinishStream,
+}
+
+impl XwmaError {
+    fn new(kind: XwmaErrorKind) -> Self {
+        Self { kind, source: None }
+    }
+
+    fn from_io(kind: XwmaErrorKind) -> impl FnOnce(IoError) -> Self {
+        move |source| Self {
+            kind,
+            source: Some(source),
+        }
+    }
+
+    /// Returns the [`XwmaErrorKind`] associated with this error.
+    #[must_use]
+    pub fn kind(&self) -> XwmaErrorKind {
+        self.kind
+    }
+}
+
+impl Display for XwmaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.kind.fmt(f)
+    }
+}
+
+impl Error for XwmaError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.source {
+            Some(source) => Some(source),
+            None => None,
+        }
+    }
+}
+
+impl Display for XwmaErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::MissingConfig => {
+                f.write_str("file header did not contain format information of xWMA stream")
+            }
+            Self::UnknownFormatTag { format_tag } => f.write_fmt(format_args!(
+                "format tag of xWMA stream was not recognized (0x{format_tag:04X})"
+            )),
+            Self::StreamTooLarge => f.write_str("xWMA stream was too large to encode"),
+            Self::CreateHeader => f.write_str("failed to encode file header"),
+            Self::EncodeStream => f.write_str("failed to encode full xWMA stream"),
+            Self::FinishStream => f.write_str("failed to finalize writing xWMA stream data"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{encode, XwmaErrorKind};
+    use crate::{
+        header::{StreamInfo, XwmaConfig},
+        read::Reader,
+    };
+
+    fn info_with_config(format_tag: u16, seek_table: &[u32]) -> StreamInfo {
+        StreamInfo {
+            xwma_config: Some(XwmaConfig {
+                format_tag,
+                block_align: 0x1000,
+                avg_bytes_per_sec: 8000,
+                seek_table: seek_table.into(),
+            }),
+            ..StreamInfo::dummy(2, 4096, 0x10)
+        }
+    }
+
+    #[test]
+    fn encode_with_seek_table() {
+        let data = [0xAB; 0x10];
+        let info = info_with_config(0x0161, &[0x4000, 0x8000]);
+
+        let output = encode(&info, &mut Reader::new(data.as_slice()), Vec::new()).unwrap();
+
+        assert_eq!(output[0x00..0x04], *b"RIFF");
+        assert_eq!(output[0x04..0x08], 0x46u32.to_le_bytes());
+        assert_eq!(output[0x08..0x10], *b"XWMAfmt ");
+        assert_eq!(output[0x14..0x18], [0x61, 0x01, 2, 0]);
+        assert_eq!(output[0x1C..0x20], 8000u32.to_le_bytes());
+        assert_eq!(output[0x20..0x22], 0x1000u16.to_le_bytes());
+        assert_eq!(output[0x26..0x2E], *b"dpds\x08\x00\x00\x00");
+        assert_eq!(output[0x2E..0x32], 0x4000u32.to_le_bytes());
+        assert_eq!(output[0x32..0x36], 0x8000u32.to_le_bytes());
+        assert_eq!(output[0x36..0x3E], *b"data\x10\x00\x00\x00");
+        assert_eq!(output[0x3E..], data);
+        assert_eq!(output.len(), 0x4E);
+    }
+
+    #[test]
+    fn encode_without_seek_table() {
+        let data = [0xAB; 0x10];
+        let info = info_with_config(0x0162, &[]);
+
+        let output = encode(&info, &mut Reader::new(data.as_slice()), Vec::new()).unwrap();
+
+        assert_eq!(output[0x14..0x16], [0x62, 0x01]);
+        assert_eq!(output[0x26..0x2E], *b"data\x10\x00\x00\x00");
+        assert_eq!(output.len(), 0x2E + 0x10);
+    }
+
+    #[test]
+    fn reject_missing_or_unknown_config() {
+        let data = [0; 0x10];
+
+        let info = StreamInfo::dummy(2, 4096, 0x10);
+        assert!(encode(&info, &mut Reader::new(data.as_slice()), Vec::new())
+            .is_err_and(|e| e.kind() == XwmaErrorKind::MissingConfig));
+
+        let info = info_with_config(0x0160, &[]);
+        assert!(encode(&info, &mut Reader::new(data.as_slice()), Vec::new())
+            .is_err_and(|e| e.kind() == XwmaErrorKind::UnknownFormatTag { format_tag: 0x0160 }));
+    }
+}
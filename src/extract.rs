@@ -0,0 +1,1111 @@
+use crate::checksum::{ChecksumAlgorithm, StreamChecksums};
+use crate::encode::{
+    EncodeError, EncodeOptions, EncodeStats, FormatMismatch, MpegFixStats, OutputMode,
+};
+use crate::header::{sanitize_file_path, AudioFormat, StreamInfo};
+use crate::read::ReadError;
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    fs::{create_dir_all, File},
+    io::{BufWriter, Error as IoError, ErrorKind, Result as IoResult, Write},
+    path::{Path, PathBuf},
+};
+
+/// Creates the writers that streams are written to by [`Bank::extract_with`].
+///
+/// [`Bank::extract_with`] calls [`create`](Self::create) once for every stream, in order of streams.
+/// This lets callers decide where each stream is written, or whether it's written at all.
+/// See [`DirectoryFactory`] for a factory that writes every stream to a file in a directory.
+///
+/// # Examples
+///
+/// Writing streams with a name to memory, skipping streams without a name:
+///
+/// ```
+/// use fsbex::{SinkFactory, StreamInfo};
+/// use std::{cell::RefCell, convert::Infallible, io::Write, rc::Rc};
+///
+/// #[derive(Default)]
+/// struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+///
+/// impl Write for SharedBuffer {
+///     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+///         self.0.borrow_mut().write(buf)
+///     }
+///
+///     fn flush(&mut self) -> std::io::Result<()> {
+///         Ok(())
+///     }
+/// }
+///
+/// #[derive(Default)]
+/// struct MemoryFactory {
+///     files: Vec<(String, Rc<RefCell<Vec<u8>>>)>,
+/// }
+///
+/// impl SinkFactory for MemoryFactory {
+///     type Sink = SharedBuffer;
+///     type Error = Infallible;
+///
+///     fn create(&mut self, _index: u32, info: &StreamInfo) -> Result<Option<SharedBuffer>, Infallible> {
+///         let Some(name) = info.name() else {
+///             return Ok(None);
+///         };
+///
+///         let sink = SharedBuffer::default();
+///         self.files.push((name.to_owned(), sink.0.clone()));
+///         Ok(Some(sink))
+///     }
+/// }
+/// ```
+///
+/// [`Bank::extract_with`]: crate::Bank::extract_with
+pub trait SinkFactory {
+    /// The writer that streams are written to.
+    type Sink: Write;
+
+    /// The error returned when a writer can't be created.
+    type Error;
+
+    /// Creates the writer for the stream at `index`, which is described by `info`.
+    /// Returning `Ok(None)` skips the stream, so that its data is never encoded.
+    ///
+    /// # Errors
+    /// This function returns an error if the writer could not be created.
+    /// The error stops the extraction of the remaining streams.
+    fn create(&mut self, index: u32, info: &StreamInfo) -> Result<Option<Self::Sink>, Self::Error>;
+
+    /// Returns the options used to encode streams. The default options are used unless this is overridden.
+    fn options(&self) -> EncodeOptions<'_> {
+        EncodeOptions::default()
+    }
+
+    /// Called instead of [`create`](Self::create) for placeholder streams without stream data,
+    /// when they are skipped with [`EmptyStreams::Skip`]. This lets callers report a warning for the stream.
+    /// Nothing is done unless this is overridden.
+    ///
+    /// [`EmptyStreams::Skip`]: crate::encode::EmptyStreams::Skip
+    fn skipped(&mut self, index: u32, info: &StreamInfo) {
+        let _ = (index, info);
+    }
+
+    /// Returns the algorithm used to compute the checksums of every stream's data and of its output,
+    /// which are listed in [`ExtractionReport::checksums`]. No checksums are computed unless this is overridden.
+    ///
+    /// Stream data is read into memory before it's encoded when checksums are computed,
+    /// even if the source of the sound bank can seek.
+    fn checksum(&self) -> Option<ChecksumAlgorithm> {
+        None
+    }
+}
+
+impl<F: SinkFactory + ?Sized> SinkFactory for &mut F {
+    type Sink = F::Sink;
+    type Error = F::Error;
+
+    fn create(&mut self, index: u32, info: &StreamInfo) -> Result<Option<Self::Sink>, Self::Error> {
+        (**self).create(index, info)
+    }
+
+    fn options(&self) -> EncodeOptions<'_> {
+        (**self).options()
+    }
+
+    fn skipped(&mut self, index: u32, info: &StreamInfo) {
+        (**self).skipped(index, info);
+    }
+
+    fn checksum(&self) -> Option<ChecksumAlgorithm> {
+        (**self).checksum()
+    }
+}
+
+/// A [`SinkFactory`] that writes every stream to a file in a directory.
+///
+/// Files are named after the names of streams in the sound bank (or `stream_{index}` for streams without a name),
+/// with the extension of the file format that streams are encoded as, such as `.wav` or `.ogg`.
+/// See [`StreamInfo::suggested_filename`] for more information.
+///
+/// See [`Bank::extract_all_to_dir`] for a shorthand that creates this factory and reports the files it wrote.
+///
+/// # Examples
+///
+/// ```no_run
+/// use fsbex::{Bank, DirectoryFactory};
+/// use fsbex::encode::EncodeOptions;
+/// use std::{error::Error, fs::File, io::BufReader};
+///
+/// fn extract_all(path: &str) -> Result<(), Box<dyn Error>> {
+///     let bank = Bank::new(BufReader::new(File::open(path)?))?;
+///
+///     let options = EncodeOptions::new().info_chunk(true);
+///     let factory = DirectoryFactory::new("output", bank.format())
+///         .with_options(options)
+///         .skip_existing(true);
+///
+///     bank.extract_with(factory)?;
+///     Ok(())
+/// }
+/// ```
+///
+/// [`Bank::extract_all_to_dir`]: crate::Bank::extract_all_to_dir
+#[derive(Clone, Debug)]
+pub struct DirectoryFactory<'a> {
+    dir: PathBuf,
+    format: AudioFormat,
+    options: ExtractOptions<'a>,
+    create_dir: bool,
+    pub(crate) created: Vec<(u32, PathBuf)>,
+}
+
+impl<'a> DirectoryFactory<'a> {
+    /// Creates a new [`DirectoryFactory`] that writes streams of `format` to files in `dir`, with the default options.
+    /// The format should be the format of the sound bank (see [`Bank::format`]).
+    /// The directory must already exist, unless [`create_dir`](Self::create_dir) is enabled.
+    ///
+    /// [`Bank::format`]: crate::Bank::format
+    #[must_use]
+    pub fn new<P: Into<PathBuf>>(dir: P, format: AudioFormat) -> Self {
+        Self::with_extract_options(dir, format, ExtractOptions::new())
+    }
+
+    // Creates a factory that names and writes files as `options` describe, for `Bank::extract_all_to_dir`.
+    pub(crate) fn with_extract_options<P: Into<PathBuf>>(
+        dir: P,
+        format: AudioFormat,
+        options: ExtractOptions<'a>,
+    ) -> Self {
+        Self {
+            dir: dir.into(),
+            format,
+            options,
+            create_dir: false,
+            created: Vec::new(),
+        }
+    }
+
+    /// Sets the options used to encode streams, which also decide the extension of files.
+    #[must_use]
+    pub fn with_options(mut self, options: EncodeOptions<'a>) -> Self {
+        self.options = self.options.encode_options(options);
+        self
+    }
+
+    /// Sets whether streams are skipped if their file already exists, instead of overwriting the file.
+    /// This is disabled by default.
+    #[must_use]
+    pub fn skip_existing(mut self, skip: bool) -> Self {
+        self.options = self.options.skip_existing(skip);
+        self
+    }
+
+    /// Sets the index of the sound bank that streams are extracted from, which is added to the start of file names
+    /// (as `bank_{index}_`). This keeps the files of sound banks parsed with [`Bank::parse_all`] from colliding
+    /// when they are written to the same directory. No index is added by default.
+    ///
+    /// [`Bank::parse_all`]: crate::Bank::parse_all
+    #[must_use]
+    pub fn bank_index(mut self, index: u32) -> Self {
+        self.options = self.options.bank_index(index);
+        self
+    }
+
+    /// Sets whether files are named after the names of streams in the sound bank.
+    /// When disabled, every file is named after the index of its stream (as `stream_{index}`),
+    /// even if the stream has a name. This is enabled by default.
+    #[must_use]
+    pub fn use_names(mut self, enabled: bool) -> Self {
+        self.options = self.options.use_names(enabled);
+        self
+    }
+
+    /// Sets whether the directories in the names of streams are recreated below the directory, instead of being
+    /// removed from file names. For example, a stream named `music/theme` is written to `music/theme.wav`
+    /// instead of `theme.wav`. This is disabled by default.
+    ///
+    /// Directories are sanitized with [`sanitize_file_path`], so files are never written outside of the directory,
+    /// even for names like `../theme`. The file names themselves are the same either way, and are unique within
+    /// the sound bank, so streams named `sfx/hit` and `music/hit` are written to `sfx/hit.wav` and `music/hit_2.wav`.
+    /// Directories are created as needed. Has no effect if [`use_names`](Self::use_names) is disabled.
+    ///
+    /// [`sanitize_file_path`]: crate::sanitize_file_path
+    #[must_use]
+    pub fn subdirectories(mut self, enabled: bool) -> Self {
+        self.options = self.options.subdirectories(enabled);
+        self
+    }
+
+    /// Sets whether the directory (and its parents) are created before the first file is written,
+    /// if they don't exist yet. This is disabled by default.
+    #[must_use]
+    pub fn create_dir(mut self, create: bool) -> Self {
+        self.create_dir = create;
+        self
+    }
+
+    /// Sets the algorithm used to compute the checksums of every stream's data and of its file.
+    /// See [`SinkFactory::checksum`] for more information. No checksums are computed by default.
+    #[must_use]
+    pub fn checksum(mut self, algorithm: ChecksumAlgorithm) -> Self {
+        self.options = self.options.checksum(algorithm);
+        self
+    }
+
+    fn file_name(&self, index: u32, info: &StreamInfo) -> String {
+        let name = if self.options.use_names {
+            info.file_name(self.format, self.options.encode_options)
+        } else {
+            format!(
+                "stream_{index}.{}",
+                self.format.extension(self.options.encode_options)
+            )
+        };
+
+        match self.options.bank_index {
+            Some(bank_index) => format!("bank_{bank_index}_{name}"),
+            None => name,
+        }
+    }
+
+    // Returns the path of the file relative to the directory, which only has directories with `subdirectories`.
+    fn file_path(&self, index: u32, info: &StreamInfo) -> PathBuf {
+        let file_name = self.file_name(index, info);
+
+        let parent = info
+            .name()
+            .filter(|_| self.options.subdirectories && self.options.use_names)
+            .and_then(sanitize_file_path)
+            .and_then(|path| path.parent().map(Path::to_path_buf));
+
+        match parent {
+            Some(parent) => parent.join(file_name),
+            None => PathBuf::from(file_name),
+        }
+    }
+}
+
+impl SinkFactory for DirectoryFactory<'_> {
+    type Sink = BufWriter<File>;
+    type Error = IoError;
+
+    fn create(&mut self, index: u32, info: &StreamInfo) -> Result<Option<Self::Sink>, Self::Error> {
+        if self.create_dir {
+            create_dir_all(&self.dir)?;
+            self.create_dir = false;
+        }
+
+        let path = self.dir.join(self.file_path(index, info));
+
+        if let Some(parent) = path.parent().filter(|_| self.options.subdirectories) {
+            create_dir_all(parent)?;
+        }
+
+        let file = if self.options.skip_existing {
+            match File::create_new(&path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => return Ok(None),
+                Err(e) => return Err(e),
+            }
+        } else {
+            File::create(&path)?
+        };
+
+        self.created.push((index, path));
+        Ok(Some(BufWriter::new(file)))
+    }
+
+    fn options(&self) -> EncodeOptions<'_> {
+        self.options.encode_options
+    }
+
+    fn checksum(&self) -> Option<ChecksumAlgorithm> {
+        self.options.checksum
+    }
+}
+
+/// Options that control how streams are written to files by [`Bank::extract_all_to_dir`].
+///
+/// By default, streams are encoded with the default [`EncodeOptions`], files are named after the names of streams,
+/// and existing files are overwritten. The most common encoding options can be set directly on [`ExtractOptions`],
+/// which changes them in its [`EncodeOptions`]; setting [`encode_options`](Self::encode_options) afterwards
+/// replaces them.
+///
+/// # Examples
+///
+/// ```no_run
+/// use fsbex::{Bank, ExtractOptions};
+/// use fsbex::encode::OutputMode;
+/// use std::{error::Error, fs::File, io::BufReader};
+///
+/// fn extract_raw(path: &str) -> Result<(), Box<dyn Error>> {
+///     let mut bank = Bank::new(BufReader::new(File::open(path)?))?;
+///
+///     let options = ExtractOptions::new()
+///         .output_mode(OutputMode::Raw)
+///         .use_names(false)
+///         .skip_existing(true);
+///
+///     bank.extract_all_to_dir("output", &options)?;
+///     Ok(())
+/// }
+/// ```
+///
+/// [`Bank::extract_all_to_dir`]: crate::Bank::extract_all_to_dir
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub struct ExtractOptions<'a> {
+    encode_options: EncodeOptions<'a>,
+    skip_existing: bool,
+    bank_index: Option<u32>,
+    use_names: bool,
+    subdirectories: bool,
+    checksum: Option<ChecksumAlgorithm>,
+}
+
+impl Default for ExtractOptions<'_> {
+    fn default() -> Self {
+        Self {
+            encode_options: EncodeOptions::default(),
+            skip_existing: false,
+            bank_index: None,
+            use_names: true,
+            subdirectories: false,
+            checksum: None,
+        }
+    }
+}
+
+impl<'a> ExtractOptions<'a> {
+    /// Creates a new [`ExtractOptions`] with the default options.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the options used to encode streams, which also decide the extension of files.
+    #[must_use]
+    pub fn encode_options(mut self, options: EncodeOptions<'a>) -> Self {
+        self.encode_options = options;
+        self
+    }
+
+    /// Sets whether streams are skipped if their file already exists, instead of overwriting the file.
+    /// Skipped streams are listed in [`ExtractionReport::skipped_streams`]. This is disabled by default.
+    #[must_use]
+    pub fn skip_existing(mut self, skip: bool) -> Self {
+        self.skip_existing = skip;
+        self
+    }
+
+    /// Sets the index of the sound bank that streams are extracted from, which is added to the start of file names.
+    /// See [`DirectoryFactory::bank_index`] for more information. No index is added by default.
+    #[must_use]
+    pub fn bank_index(mut self, index: u32) -> Self {
+        self.bank_index = Some(index);
+        self
+    }
+
+    /// Sets whether files are named after the names of streams in the sound bank, instead of their indices.
+    /// See [`DirectoryFactory::use_names`] for more information. This is enabled by default.
+    #[must_use]
+    pub fn use_names(mut self, enabled: bool) -> Self {
+        self.use_names = enabled;
+        self
+    }
+
+    /// Sets whether the directories in the names of streams are recreated below the output directory, instead of
+    /// being removed from file names. See [`DirectoryFactory::subdirectories`] for more information.
+    /// This is disabled by default.
+    #[must_use]
+    pub fn subdirectories(mut self, enabled: bool) -> Self {
+        self.subdirectories = enabled;
+        self
+    }
+
+    /// Sets whether streams are encoded, or copied exactly as they are stored in the sound bank.
+    /// See [`EncodeOptions::output_mode`] for more information.
+    #[must_use]
+    pub fn output_mode(mut self, mode: OutputMode) -> Self {
+        self.encode_options = self.encode_options.output_mode(mode);
+        self
+    }
+
+    /// Sets whether the padding between the frames of MPEG streams is removed.
+    /// See [`EncodeOptions::mpeg_fix`] for more information. This is enabled by default.
+    #[must_use]
+    pub fn mpeg_fix(mut self, enabled: bool) -> Self {
+        self.encode_options = self.encode_options.mpeg_fix(enabled);
+        self
+    }
+
+    /// Sets whether MPEG streams are decoded and written as WAVE files, instead of being written as MP3 files.
+    /// See [`EncodeOptions::decode_mpeg`] for more information. This is disabled by default,
+    /// and requires the `decode-mpeg` feature.
+    #[cfg(feature = "decode-mpeg")]
+    #[must_use]
+    pub fn decode_mpeg(mut self, decode: bool) -> Self {
+        self.encode_options = self.encode_options.decode_mpeg(decode);
+        self
+    }
+
+    /// Sets whether removing the padding of MPEG streams fails on malformed frames, instead of skipping them.
+    /// See [`EncodeOptions::strict_mpeg`] for more information. This is disabled by default.
+    #[must_use]
+    pub fn strict_mpeg(mut self, strict: bool) -> Self {
+        self.encode_options = self.encode_options.strict_mpeg(strict);
+        self
+    }
+
+    /// Sets whether metadata is written to files: the loop points, cue points, and `INFO` chunks of WAVE files
+    /// (and the files written as PCM samples in other formats), and the ID3v2 tags of MP3 files.
+    ///
+    /// By default, only loop points and ID3v2 tags are written. Each of them can be set separately
+    /// with [`EncodeOptions`] instead.
+    #[must_use]
+    pub fn metadata(mut self, enabled: bool) -> Self {
+        self.encode_options = self
+            .encode_options
+            .loop_chunk(enabled)
+            .cue_chunk(enabled)
+            .info_chunk(enabled)
+            .id3_tag(enabled);
+        self
+    }
+
+    /// Sets whether encoding fails when an option can't be applied to a stream, instead of ignoring the option.
+    /// See [`EncodeOptions::strict`] for more information. This is disabled by default.
+    #[must_use]
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.encode_options = self.encode_options.strict(strict);
+        self
+    }
+
+    /// Sets the algorithm used to compute the checksums of every stream's data and of its file,
+    /// which are listed in [`ExtractionReport::checksums`]. This saves reading the files again to hash them.
+    /// See [`SinkFactory::checksum`] for more information. No checksums are computed by default.
+    #[must_use]
+    pub fn checksum(mut self, algorithm: ChecksumAlgorithm) -> Self {
+        self.checksum = Some(algorithm);
+        self
+    }
+}
+
+/// A summary of the streams extracted by [`Bank::extract_with`] or [`Bank::extract_all_to_dir`],
+/// including the streams that couldn't be fully extracted.
+///
+/// Sound banks can be truncated (e.g. when carved out of a memory dump), so that their data ends before the end
+/// of the final streams. The stream cut short by the end of the data is still extracted from the data that's left,
+/// and listed in [`partial_streams`](Self::partial_streams). Streams after it have no data left, so they aren't
+/// extracted, and are listed in [`missing_streams`](Self::missing_streams) instead.
+///
+/// [`Bank::extract_with`]: crate::Bank::extract_with
+/// [`Bank::extract_all_to_dir`]: crate::Bank::extract_all_to_dir
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ExtractionReport {
+    pub(crate) extracted_streams: Vec<u32>,
+    pub(crate) partial_streams: Vec<PartialStream>,
+    pub(crate) missing_streams: Vec<u32>,
+    pub(crate) skipped_streams: Vec<u32>,
+    pub(crate) files: Vec<ExtractedFile>,
+    pub(crate) checksums: Vec<StreamChecksums>,
+    pub(crate) mpeg_fix_stats: Vec<(u32, MpegFixStats)>,
+    pub(crate) format_mismatches: Vec<(u32, FormatMismatch)>,
+    pub(crate) converted_8_bit_streams: Vec<u32>,
+    pub(crate) truncated_pcm_streams: Vec<(u32, u32)>,
+}
+
+impl ExtractionReport {
+    /// Returns the files written by [`Bank::extract_all_to_dir`], in order of streams.
+    /// This is always empty for reports returned by [`Bank::extract_with`], since writers aren't always files.
+    ///
+    /// [`Bank::extract_with`]: crate::Bank::extract_with
+    /// [`Bank::extract_all_to_dir`]: crate::Bank::extract_all_to_dir
+    #[must_use]
+    pub fn files(&self) -> &[ExtractedFile] {
+        &self.files
+    }
+
+    /// Returns the indices of streams that were encoded to their writers, in order of streams.
+    /// This includes streams that were extracted with only part of their stream data.
+    #[must_use]
+    pub fn extracted_streams(&self) -> &[u32] {
+        &self.extracted_streams
+    }
+
+    /// Returns the indices of streams that were skipped without being encoded, either because no writer
+    /// was created for them (e.g. their file already existed), or because they were placeholder streams
+    /// skipped with [`EmptyStreams::Skip`].
+    ///
+    /// [`EmptyStreams::Skip`]: crate::encode::EmptyStreams::Skip
+    #[must_use]
+    pub fn skipped_streams(&self) -> &[u32] {
+        &self.skipped_streams
+    }
+
+    /// Returns the streams that were extracted with only part of their stream data.
+    #[must_use]
+    pub fn partial_streams(&self) -> &[PartialStream] {
+        &self.partial_streams
+    }
+
+    /// Returns the indices of streams that weren't extracted, because none of their stream data was left.
+    #[must_use]
+    pub fn missing_streams(&self) -> &[u32] {
+        &self.missing_streams
+    }
+
+    /// Returns the checksums of the streams that were extracted, in order of streams, if an algorithm was chosen
+    /// with [`SinkFactory::checksum`] or [`ExtractOptions::checksum`]. This is empty otherwise.
+    #[must_use]
+    pub fn checksums(&self) -> &[StreamChecksums] {
+        &self.checksums
+    }
+
+    /// Returns the index and [`MpegFixStats`] of every MPEG stream that was extracted with its padding removed,
+    /// in order of streams. These tell how much of each stream's data was skipped as padding or unrecognized data,
+    /// which would otherwise be lost silently.
+    ///
+    /// Streams extracted with [`EncodeOptions::mpeg_fix`] disabled, or in [`OutputMode::Raw`], aren't listed.
+    ///
+    /// [`EncodeOptions::mpeg_fix`]: crate::encode::EncodeOptions::mpeg_fix
+    #[must_use]
+    pub fn mpeg_fix_stats(&self) -> &[(u32, MpegFixStats)] {
+        &self.mpeg_fix_stats
+    }
+
+    /// Returns the index and [`FormatMismatch`] of every MPEG stream that was decoded in a different format
+    /// than its stream header declares, in order of streams. Decoded streams are written in the format
+    /// of their decoded frames, so these are warnings about stream headers, rather than errors.
+    ///
+    /// This is always empty without the `decode-mpeg` feature, which is needed to decode MPEG streams.
+    #[must_use]
+    pub fn format_mismatches(&self) -> &[(u32, FormatMismatch)] {
+        &self.format_mismatches
+    }
+
+    /// Returns the indices of PCM8 streams whose samples were converted from signed to unsigned integers,
+    /// in order of streams. WAVE files store 8-bit samples as unsigned integers, so the samples of these streams
+    /// differ from their stream data, e.g. when comparing checksums of extracted samples with other tools.
+    ///
+    /// Streams written as AIFF or FLAC files keep their samples signed, so they aren't listed.
+    #[must_use]
+    pub fn converted_8_bit_streams(&self) -> &[u32] {
+        &self.converted_8_bit_streams
+    }
+
+    /// Returns the index of every PCM stream whose stream data ended with an incomplete sample frame,
+    /// along with the number of bytes that were dropped from its end, in order of streams.
+    /// Only complete sample frames are written, so these bytes are missing from the output.
+    #[must_use]
+    pub fn truncated_pcm_streams(&self) -> &[(u32, u32)] {
+        &self.truncated_pcm_streams
+    }
+
+    /// Returns whether every stream was fully extracted (or skipped).
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.partial_streams.is_empty() && self.missing_streams.is_empty()
+    }
+
+    // Lists the statistics of encoding the stream at `index`.
+    pub(crate) fn add_stats(&mut self, index: u32, stats: EncodeStats) {
+        self.mpeg_fix_stats.extend(stats.mpeg_fix.map(|fix| (index, fix)));
+        self.format_mismatches
+            .extend(stats.format_mismatch.map(|mismatch| (index, mismatch)));
+        if stats.converted_8_bit {
+            self.converted_8_bit_streams.push(index);
+        }
+        if stats.truncated_bytes != 0 {
+            self.truncated_pcm_streams.push((index, stats.truncated_bytes));
+        }
+    }
+}
+
+/// The progress of an extraction, reported by [`Bank::extract_with_progress`] and
+/// [`Bank::extract_all_to_dir_with_progress`].
+///
+/// Progress is reported while a stream is written, after every [`Progress::INTERVAL`] bytes of output,
+/// and once more after the stream is done, with [`is_stream_done`](Self::is_stream_done) set.
+/// Streams that are skipped or missing are only reported once they're done.
+///
+/// Sizes of stream data are read from the file header, while [`bytes_written`](Self::bytes_written) counts
+/// encoded output, which can be larger (e.g. ADPCM decoded to PCM samples) or smaller than the stream data.
+/// The fraction of the sound bank that's done, by size of stream data, is returned by [`fraction`](Self::fraction).
+///
+/// [`Bank::extract_with_progress`]: crate::Bank::extract_with_progress
+/// [`Bank::extract_all_to_dir_with_progress`]: crate::Bank::extract_all_to_dir_with_progress
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Progress {
+    pub(crate) index: u32,
+    pub(crate) num_streams: u32,
+    pub(crate) bytes_written: u64,
+    pub(crate) stream_size: u32,
+    pub(crate) completed_size: u64,
+    pub(crate) total_size: u64,
+    pub(crate) stream_done: bool,
+}
+
+impl Progress {
+    /// The number of bytes written to a writer between reports of progress.
+    pub const INTERVAL: u64 = 0x10_0000;
+
+    /// Returns the index of the stream being written.
+    #[must_use]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the number of streams in the sound bank.
+    #[must_use]
+    pub fn num_streams(&self) -> u32 {
+        self.num_streams
+    }
+
+    /// Returns the number of bytes written for the current stream so far.
+    #[must_use]
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Returns the size of the current stream's data, in bytes, as stored in the file header.
+    #[must_use]
+    pub fn stream_size(&self) -> u32 {
+        self.stream_size
+    }
+
+    /// Returns the total size of the data of the streams before the current stream, in bytes.
+    #[must_use]
+    pub fn completed_size(&self) -> u64 {
+        self.completed_size
+    }
+
+    /// Returns the total size of the data of every stream in the sound bank, in bytes, as stored in the file header.
+    #[must_use]
+    pub fn total_size(&self) -> u64 {
+        self.total_size
+    }
+
+    /// Returns whether the current stream is done, so that this is the final report for the stream.
+    #[must_use]
+    pub fn is_stream_done(&self) -> bool {
+        self.stream_done
+    }
+
+    /// Returns the fraction of the sound bank's stream data that's done, from `0.0` to `1.0`.
+    /// Only streams that are done are counted, since encoded output can't be compared to the size of stream data.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn fraction(&self) -> f64 {
+        let done = if self.stream_done {
+            self.completed_size + u64::from(self.stream_size)
+        } else {
+            self.completed_size
+        };
+
+        if self.total_size == 0 {
+            1.0
+        } else {
+            done as f64 / self.total_size as f64
+        }
+    }
+
+    pub(crate) fn start_stream(&mut self, index: u32, size: u32) {
+        if self.stream_done {
+            self.completed_size += u64::from(self.stream_size);
+        }
+
+        self.index = index;
+        self.stream_size = size;
+        self.bytes_written = 0;
+        self.stream_done = false;
+    }
+
+    pub(crate) fn finish_stream<P: FnMut(Self)>(&mut self, on_progress: &mut P) {
+        self.stream_done = true;
+        on_progress(*self);
+    }
+}
+
+// Counts the bytes written to the writer of a stream, reporting progress at every interval.
+pub(crate) struct ProgressSink<'p, W, P> {
+    inner: W,
+    progress: Progress,
+    next_report: u64,
+    on_progress: &'p mut P,
+}
+
+impl<'p, W: Write, P: FnMut(Progress)> ProgressSink<'p, W, P> {
+    pub(crate) fn new(inner: W, progress: Progress, on_progress: &'p mut P) -> Self {
+        Self {
+            inner,
+            progress,
+            next_report: Progress::INTERVAL,
+            on_progress,
+        }
+    }
+
+    pub(crate) fn into_progress(self) -> Progress {
+        self.progress
+    }
+}
+
+impl<W: Write, P: FnMut(Progress)> Write for ProgressSink<'_, W, P> {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        let written = self.inner.write(buf)?;
+        self.progress.bytes_written += written as u64;
+
+        if self.progress.bytes_written >= self.next_report {
+            self.next_report = self.progress.bytes_written + Progress::INTERVAL;
+            (self.on_progress)(self.progress);
+        }
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        self.inner.flush()
+    }
+}
+
+/// A summary of the streams extracted by [`Bank::extract_lenient`] or `Bank::extract_all_parallel`,
+/// including the errors of streams that failed to be extracted.
+///
+/// Streams that fail to be extracted don't stop the extraction, so this is returned even if some streams failed.
+/// Streams that were extracted are listed in [`ExtractionReport::extracted_streams`],
+/// and streams that were only extracted in part (or not at all) because the sound bank was truncated
+/// are listed in the [`ExtractionReport`] too.
+///
+/// [`Bank::extract_lenient`]: crate::Bank::extract_lenient
+#[derive(Debug)]
+pub struct LenientReport<E> {
+    pub(crate) report: ExtractionReport,
+    pub(crate) errors: Vec<ExtractError<E>>,
+}
+
+impl<E> LenientReport<E> {
+    /// Returns the [`ExtractionReport`] of the streams that were extracted, skipped, or missing.
+    #[must_use]
+    pub fn report(&self) -> &ExtractionReport {
+        &self.report
+    }
+
+    /// Returns the errors of streams that failed to be extracted, in order of streams.
+    #[must_use]
+    pub fn errors(&self) -> &[ExtractError<E>] {
+        &self.errors
+    }
+
+    /// Returns whether no stream failed to be extracted.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Splits the report into its [`ExtractionReport`] and the errors of streams that failed to be extracted.
+    #[must_use]
+    pub fn into_parts(self) -> (ExtractionReport, Vec<ExtractError<E>>) {
+        (self.report, self.errors)
+    }
+}
+
+/// A file written by [`Bank::extract_all_to_dir`]. See [`ExtractionReport::files`] for more information.
+///
+/// [`Bank::extract_all_to_dir`]: crate::Bank::extract_all_to_dir
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ExtractedFile {
+    pub(crate) index: u32,
+    pub(crate) path: PathBuf,
+    pub(crate) size: u64,
+}
+
+impl ExtractedFile {
+    /// Returns the index of the stream within the sound bank.
+    #[must_use]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the path of the file, which starts with the directory that streams were extracted to.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the size of the file, in bytes.
+    #[must_use]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+/// A stream that was extracted with only part of its stream data, because the sound bank was truncated.
+/// See [`ExtractionReport`] for more information.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PartialStream {
+    pub(crate) index: u32,
+    pub(crate) missing: u32,
+}
+
+impl PartialStream {
+    /// Returns the index of the stream within the sound bank.
+    #[must_use]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the number of bytes of stream data that were missing.
+    #[must_use]
+    pub fn missing_bytes(&self) -> u32 {
+        self.missing
+    }
+}
+
+/// Represents an error that can occur when extracting streams with [`Bank::extract_with`].
+///
+/// See [`ExtractErrorKind`] for the different kinds of errors that can occur.
+///
+/// [`Bank::extract_with`]: crate::Bank::extract_with
+#[derive(Debug)]
+pub struct ExtractError<E> {
+    index: u32,
+    source: ExtractErrorSource<E>,
+}
+
+/// A variant of an [`ExtractError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ExtractErrorKind {
+    /// The [`SinkFactory`] failed to create the writer for a stream.
+    CreateSink,
+    /// Failed to encode a stream to its writer.
+    EncodeStream,
+    /// The underlying reader failed to advance to the next stream.
+    ReadStream,
+}
+
+#[derive(Debug)]
+enum ExtractErrorSource<E> {
+    Factory(E),
+    Encode(EncodeError),
+    Read(ReadError),
+}
+
+impl<E> ExtractError<E> {
+    pub(crate) fn from_factory(index: u32) -> impl FnOnce(E) -> Self {
+        move |source| Self {
+            index,
+            source: ExtractErrorSource::Factory(source),
+        }
+    }
+
+    pub(crate) fn from_encode(index: u32) -> impl FnOnce(EncodeError) -> Self {
+        move |source| Self {
+            index,
+            source: ExtractErrorSource::Encode(source),
+        }
+    }
+
+    pub(crate) fn from_read(index: u32) -> impl FnOnce(ReadError) -> Self {
+        move |source| Self {
+            index,
+            source: ExtractErrorSource::Read(source),
+        }
+    }
+
+    /// Returns the index of the stream where the error occurred.
+    #[must_use]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the [`ExtractErrorKind`] associated with this error.
+    #[must_use]
+    pub fn kind(&self) -> ExtractErrorKind {
+        match self.source {
+            ExtractErrorSource::Factory(_) => ExtractErrorKind::CreateSink,
+            ExtractErrorSource::Encode(_) => ExtractErrorKind::EncodeStream,
+            ExtractErrorSource::Read(_) => ExtractErrorKind::ReadStream,
+        }
+    }
+}
+
+impl<E> Display for ExtractError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_fmt(format_args!("{} at index {}", self.kind(), self.index))
+    }
+}
+
+impl<E: Error + 'static> Error for ExtractError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.source {
+            ExtractErrorSource::Factory(e) => Some(e),
+            ExtractErrorSource::Encode(e) => Some(e),
+            ExtractErrorSource::Read(e) => Some(e),
+        }
+    }
+}
+
+impl Display for ExtractErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Self::CreateSink => "failed to create writer for stream",
+            Self::EncodeStream => "failed to encode stream",
+            Self::ReadStream => "failed to advance to next stream",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DirectoryFactory, ExtractOptions, Progress, ProgressSink, SinkFactory};
+    use crate::{
+        encode::{EncodeOptions, OutputMode, PcmOutput},
+        header::{AudioFormat, StreamInfo},
+    };
+    use std::{env::temp_dir, fs, io::Write, path::Path};
+
+    // Creates the information of a stream with the given file name stem.
+    fn info_with_stem(stem: &str) -> StreamInfo {
+        StreamInfo {
+            file_stem: stem.into(),
+            ..StreamInfo::dummy(1, 2, 4)
+        }
+    }
+
+    #[test]
+    fn name_files_after_streams() {
+        let info = info_with_stem("music_bgm_01");
+        let factory = DirectoryFactory::new("output", AudioFormat::Mpeg);
+
+        assert_eq!(factory.file_name(0, &info), "music_bgm_01.mp3");
+        assert_eq!(factory.file_name(0, &StreamInfo::dummy(1, 2, 4)), "stream_0.mp3");
+
+        #[cfg(feature = "decode-mpeg")]
+        {
+            let factory = factory.with_options(EncodeOptions::new().decode_mpeg(true));
+            assert_eq!(factory.file_name(0, &info), "music_bgm_01.wav");
+        }
+
+        let factory = DirectoryFactory::new("output", AudioFormat::Pcm16)
+            .with_options(EncodeOptions::new().pcm_output(PcmOutput::Aiff));
+        assert_eq!(factory.file_name(0, &info), "music_bgm_01.aiff");
+        assert_eq!(
+            info.suggested_filename(EncodeOptions::new().pcm_output(PcmOutput::Aiff)),
+            "music_bgm_01.aiff"
+        );
+
+        let factory = factory.bank_index(2);
+        assert_eq!(factory.file_name(0, &info), "bank_2_music_bgm_01.aiff");
+        assert_eq!(
+            factory.file_name(0, &StreamInfo::dummy(1, 2, 4)),
+            "bank_2_stream_0.aiff"
+        );
+
+        // without names, files are named after the index of their stream
+        let factory = factory.use_names(false);
+        assert_eq!(factory.file_name(7, &info), "bank_2_stream_7.aiff");
+    }
+
+    #[test]
+    fn set_encode_options_from_extract_options() {
+        let options = ExtractOptions::new()
+            .output_mode(OutputMode::Raw)
+            .mpeg_fix(false)
+            .metadata(true)
+            .strict(true);
+
+        let expected = EncodeOptions::new()
+            .output_mode(OutputMode::Raw)
+            .mpeg_fix(false)
+            .loop_chunk(true)
+            .cue_chunk(true)
+            .info_chunk(true)
+            .id3_tag(true)
+            .strict(true);
+
+        assert_eq!(options.encode_options, expected);
+        assert!(options.use_names);
+
+        // encoding options set directly are replaced by a later call to `encode_options`
+        let options = options.encode_options(EncodeOptions::new());
+        assert_eq!(options.encode_options, EncodeOptions::new());
+
+        let options = ExtractOptions::new().metadata(false);
+        assert!(!options.encode_options.loop_chunk && !options.encode_options.id3_tag);
+    }
+
+    #[test]
+    fn report_progress_at_intervals() {
+        let mut reports = Vec::new();
+        let mut on_progress = |progress: Progress| reports.push(progress.bytes_written());
+        let mut sink = ProgressSink::new(Vec::new(), Progress::default(), &mut on_progress);
+
+        let chunk = vec![0; usize::try_from(Progress::INTERVAL / 4).unwrap()];
+        for _ in 0..9 {
+            sink.write_all(&chunk).unwrap();
+        }
+
+        assert_eq!(sink.into_progress().bytes_written(), Progress::INTERVAL * 9 / 4);
+        assert_eq!(reports, [Progress::INTERVAL, Progress::INTERVAL * 2]);
+    }
+
+    #[test]
+    fn skip_existing_files() {
+        let dir = temp_dir().join(format!("fsbex-directory-factory-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("stream_0.wav"), b"existing").unwrap();
+
+        let info = info_with_stem("stream_0");
+        let mut factory = DirectoryFactory::new(&dir, AudioFormat::Pcm16).skip_existing(true);
+
+        assert!(factory.create(0, &info).unwrap().is_none());
+        assert!(factory.create(1, &info_with_stem("stream_1")).unwrap().is_some());
+        assert!(dir.join("stream_1.wav").exists());
+
+        // existing files are overwritten by default
+        let mut factory = DirectoryFactory::new(&dir, AudioFormat::Pcm16);
+        assert!(factory.create(0, &info).unwrap().is_some());
+        assert_eq!(fs::read(dir.join("stream_0.wav")).unwrap(), b"");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn keep_files_within_directory() {
+        let root = temp_dir().join(format!("fsbex-subdirectories-{}", std::process::id()));
+        let dir = root.join("output");
+        fs::create_dir_all(&dir).unwrap();
+
+        // stems are assigned from the last component of names, like `assign_stems` does
+        let info = StreamInfo {
+            name: Some("../../escape/theme".into()),
+            ..info_with_stem("theme")
+        };
+
+        // directories are removed from names by default
+        let mut factory = DirectoryFactory::new(&dir, AudioFormat::Pcm16);
+        assert_eq!(factory.file_path(0, &info), Path::new("theme.wav"));
+        assert!(factory.create(0, &info).unwrap().is_some());
+        assert!(dir.join("theme.wav").exists());
+
+        // directories are recreated below the directory, without leaving it
+        let mut factory = factory.subdirectories(true);
+        assert_eq!(factory.file_path(0, &info), Path::new("escape").join("theme.wav"));
+        assert!(factory.create(0, &info).unwrap().is_some());
+        assert!(dir.join("escape").join("theme.wav").exists());
+        assert!(!root.join("escape").exists());
+
+        // names without directories are written to the directory itself
+        assert_eq!(
+            factory.file_path(1, &info_with_stem("stream_1")),
+            Path::new("stream_1.wav")
+        );
+
+        // without names, there are no directories either
+        let factory = factory.use_names(false);
+        assert_eq!(factory.file_path(0, &info), Path::new("stream_0.wav"));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,456 @@
+//! A C API for using `fsbex` from other languages, enabled with the `ffi` feature.
+//!
+//! The crate is only built as a Rust library by default. The dynamic library (such as `fsbex.dll` or `libfsbex.so`)
+//! is built by overriding the crate type, and `include/fsbex.h` declares the functions and types of this module
+//! for C and C++. The header is generated with [cbindgen](https://github.com/mozilla/cbindgen) from this module:
+//!
+//! ```text
+//! cargo rustc --lib --release --features ffi --crate-type cdylib
+//! cbindgen --config cbindgen.toml --output include/fsbex.h src/ffi.rs
+//! ```
+//!
+//! Sound banks are opened from a file path or from memory as an [`FsbexBank`] handle, which must be freed
+//! with [`fsbex_bank_free`]. Every other function returns [`FSBEX_OK`] on success, or one of the
+//! `FSBEX_ERROR_*` codes on failure, in which case [`fsbex_last_error_message`] describes the error.
+//! Strings are UTF-8, and paths must be valid UTF-8.
+//!
+//! ```c
+//! FsbexBank *bank;
+//! if (fsbex_bank_open_file("sounds.fsb", &bank) != FSBEX_OK) {
+//!     fprintf(stderr, "%s\n", fsbex_last_error_message());
+//!     return 1;
+//! }
+//!
+//! uint32_t count;
+//! fsbex_bank_stream_count(bank, &count);
+//!
+//! for (uint32_t i = 0; i < count; i++) {
+//!     FsbexStreamInfo info;
+//!     fsbex_bank_stream_info(bank, i, &info);
+//!     printf("%s: %u samples\n", info.name ? info.name : "unnamed stream", info.sample_count);
+//! }
+//!
+//! fsbex_bank_free(bank);
+//! ```
+
+use crate::{encode::EncodeOptions, Bank, SeekStreamErrorKind};
+use std::{
+    cell::RefCell,
+    error::Error,
+    ffi::{c_char, c_int, CStr, CString},
+    fs::{self, File},
+    io::{BufWriter, Cursor, Write},
+    panic::{catch_unwind, AssertUnwindSafe},
+    ptr, slice,
+};
+
+/// The function succeeded.
+pub const FSBEX_OK: c_int = 0;
+/// A required pointer argument was null, or a string argument was not valid UTF-8.
+pub const FSBEX_ERROR_INVALID_ARGUMENT: c_int = 1;
+/// A file could not be opened, read, or created.
+pub const FSBEX_ERROR_IO: c_int = 2;
+/// The data is not a sound bank that can be parsed.
+pub const FSBEX_ERROR_DECODE: c_int = 3;
+/// The sound bank has no stream at the given index.
+pub const FSBEX_ERROR_UNKNOWN_STREAM: c_int = 4;
+/// The stream data could not be encoded.
+pub const FSBEX_ERROR_ENCODE: c_int = 5;
+/// The buffer is too small to hold the encoded stream. The required size is written to `written`.
+pub const FSBEX_ERROR_BUFFER_TOO_SMALL: c_int = 6;
+/// `fsbex` panicked. This is a bug; the handle that was passed in should not be used again, except to free it.
+pub const FSBEX_ERROR_PANIC: c_int = 7;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// A sound bank opened with [`fsbex_bank_open_file`] or [`fsbex_bank_open_memory`].
+///
+/// This is an opaque handle, which holds the data of the sound bank in memory.
+/// It must be freed with [`fsbex_bank_free`].
+#[derive(Debug)]
+pub struct FsbexBank {
+    bank: Bank<Cursor<Vec<u8>>>,
+    names: Vec<Option<CString>>,
+    extension: CString,
+}
+
+/// Information about a stream of a sound bank, filled in by [`fsbex_bank_stream_info`].
+///
+/// Strings are owned by the sound bank, and remain valid until it is freed.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct FsbexStreamInfo {
+    /// The audio format of the stream, as the format value of FSB5 file headers
+    /// (such as 2 for 16-bit PCM, 11 for MPEG, and 15 for Vorbis).
+    pub format: u32,
+    /// The sample rate of the stream, in Hz.
+    pub sample_rate: u32,
+    /// The number of channels in the stream.
+    pub channels: u32,
+    /// The number of samples in the stream, per channel.
+    pub sample_count: u32,
+    /// Whether the stream has loop points. If not, `loop_start` and `loop_end` are 0.
+    pub has_loop: bool,
+    /// The first sample of the loop.
+    pub loop_start: u32,
+    /// The last sample of the loop (inclusive).
+    pub loop_end: u32,
+    /// The size of the stream's data in the sound bank, in bytes.
+    pub size: u32,
+    /// The name of the stream, or null if the stream has no name.
+    pub name: *const c_char,
+    /// The file extension of the format the stream is extracted as, without a leading dot (such as `wav`).
+    pub extension: *const c_char,
+}
+
+// An error reported to C callers, with the code returned from the function and the message
+// returned from `fsbex_last_error_message`.
+struct FfiError {
+    code: c_int,
+    message: String,
+}
+
+impl FfiError {
+    fn new(code: c_int, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    // The message includes the messages of underlying errors, since C callers can't walk the chain of sources.
+    fn from_error(code: c_int, error: &dyn Error) -> Self {
+        let mut message = error.to_string();
+        let mut source = error.source();
+
+        while let Some(error) = source {
+            message.push_str(": ");
+            message.push_str(&error.to_string());
+            source = error.source();
+        }
+
+        Self::new(code, message)
+    }
+}
+
+// Runs `f`, converting errors and panics to error codes, and storing the error message for the calling thread.
+fn run<F: FnOnce() -> Result<(), FfiError>>(f: F) -> c_int {
+    let error = match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => return FSBEX_OK,
+        Ok(Err(error)) => error,
+        Err(_) => FfiError::new(FSBEX_ERROR_PANIC, "fsbex panicked"),
+    };
+
+    // messages can't contain interior null bytes, so they are cut off at the first one
+    let message = CString::new(error.message).unwrap_or_else(|e| {
+        let end = e.nul_position();
+        let mut bytes = e.into_vec();
+        bytes.truncate(end);
+        CString::new(bytes).unwrap_or_default()
+    });
+
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+    error.code
+}
+
+fn null_argument(name: &str) -> FfiError {
+    FfiError::new(FSBEX_ERROR_INVALID_ARGUMENT, format!("argument `{name}` is null"))
+}
+
+// Reads a path argument as a UTF-8 string.
+//
+// SAFETY: `path` must be null or point to a null-terminated string.
+unsafe fn path_arg<'a>(path: *const c_char) -> Result<&'a str, FfiError> {
+    if path.is_null() {
+        return Err(null_argument("path"));
+    }
+
+    // SAFETY: the caller guarantees that `path` points to a null-terminated string
+    unsafe { CStr::from_ptr(path) }.to_str().map_err(|_| {
+        FfiError::new(FSBEX_ERROR_INVALID_ARGUMENT, "argument `path` is not valid UTF-8")
+    })
+}
+
+fn open_bank(data: Vec<u8>) -> Result<Box<FsbexBank>, FfiError> {
+    let bank = Bank::seekable(Cursor::new(data))
+        .map_err(|e| FfiError::from_error(FSBEX_ERROR_DECODE, &e))?;
+
+    // names with interior null bytes can't be passed to C, so they are treated as missing
+    let names = bank
+        .stream_infos()
+        .iter()
+        .map(|info| info.name().and_then(|name| CString::new(name).ok()))
+        .collect();
+
+    let extension = CString::new(bank.format().extension(EncodeOptions::default()))
+        .expect("file extensions do not contain null bytes");
+
+    Ok(Box::new(FsbexBank {
+        bank,
+        names,
+        extension,
+    }))
+}
+
+fn stream_error(error: &crate::SeekStreamError) -> FfiError {
+    let code = match error.kind() {
+        SeekStreamErrorKind::UnknownStream => FSBEX_ERROR_UNKNOWN_STREAM,
+        _ => FSBEX_ERROR_ENCODE,
+    };
+
+    FfiError::from_error(code, error)
+}
+
+/// Opens the sound bank at `path`, reading the whole file into memory.
+/// On success, the sound bank is written to `out`, and must be freed with [`fsbex_bank_free`].
+///
+/// Returns [`FSBEX_ERROR_IO`] if the file could not be read,
+/// or [`FSBEX_ERROR_DECODE`] if the file is not a sound bank that can be parsed.
+///
+/// # Safety
+///
+/// `path` must point to a null-terminated string, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn fsbex_bank_open_file(
+    path: *const c_char,
+    out: *mut *mut FsbexBank,
+) -> c_int {
+    run(|| {
+        if out.is_null() {
+            return Err(null_argument("out"));
+        }
+
+        // SAFETY: the caller guarantees that `path` points to a null-terminated string
+        let path = unsafe { path_arg(path) }?;
+        let data = fs::read(path).map_err(|e| FfiError::from_error(FSBEX_ERROR_IO, &e))?;
+        let bank = open_bank(data)?;
+
+        // SAFETY: the caller guarantees that `out` is valid for writes
+        unsafe { out.write(Box::into_raw(bank)) };
+        Ok(())
+    })
+}
+
+/// Opens a sound bank from `len` bytes at `data`, which are copied, so the memory can be freed afterwards.
+/// On success, the sound bank is written to `out`, and must be freed with [`fsbex_bank_free`].
+///
+/// Returns [`FSBEX_ERROR_DECODE`] if the data is not a sound bank that can be parsed.
+///
+/// # Safety
+///
+/// `data` must be valid for reads of `len` bytes, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn fsbex_bank_open_memory(
+    data: *const u8,
+    len: usize,
+    out: *mut *mut FsbexBank,
+) -> c_int {
+    run(|| {
+        if data.is_null() {
+            return Err(null_argument("data"));
+        }
+        if out.is_null() {
+            return Err(null_argument("out"));
+        }
+
+        // SAFETY: the caller guarantees that `data` is valid for reads of `len` bytes
+        let data = unsafe { slice::from_raw_parts(data, len) };
+        let bank = open_bank(data.to_vec())?;
+
+        // SAFETY: the caller guarantees that `out` is valid for writes
+        unsafe { out.write(Box::into_raw(bank)) };
+        Ok(())
+    })
+}
+
+/// Writes the number of streams in the sound bank to `out`.
+///
+/// # Safety
+///
+/// `bank` must be a sound bank that hasn't been freed, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn fsbex_bank_stream_count(bank: *const FsbexBank, out: *mut u32) -> c_int {
+    run(|| {
+        // SAFETY: the caller guarantees that `bank` is null or a sound bank that hasn't been freed
+        let bank = unsafe { bank.as_ref() }.ok_or_else(|| null_argument("bank"))?;
+        if out.is_null() {
+            return Err(null_argument("out"));
+        }
+
+        // SAFETY: the caller guarantees that `out` is valid for writes
+        unsafe { out.write(bank.bank.num_streams().get()) };
+        Ok(())
+    })
+}
+
+/// Writes information about the stream at `index` to `out`.
+///
+/// Returns [`FSBEX_ERROR_UNKNOWN_STREAM`] if the sound bank has no stream at `index`.
+///
+/// # Safety
+///
+/// `bank` must be a sound bank that hasn't been freed, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn fsbex_bank_stream_info(
+    bank: *const FsbexBank,
+    index: u32,
+    out: *mut FsbexStreamInfo,
+) -> c_int {
+    run(|| {
+        // SAFETY: the caller guarantees that `bank` is null or a sound bank that hasn't been freed
+        let bank = unsafe { bank.as_ref() }.ok_or_else(|| null_argument("bank"))?;
+        if out.is_null() {
+            return Err(null_argument("out"));
+        }
+
+        let info = bank.bank.stream_info(index).ok_or_else(|| {
+            FfiError::new(
+                FSBEX_ERROR_UNKNOWN_STREAM,
+                format!("there is no stream at index {index}"),
+            )
+        })?;
+        let name = bank.names[index as usize]
+            .as_deref()
+            .map_or(ptr::null(), CStr::as_ptr);
+        let (loop_start, loop_end) = info.loop_points().unwrap_or_default();
+
+        let info = FsbexStreamInfo {
+            format: info.codec().value(),
+            sample_rate: info.sample_rate().get(),
+            channels: info.channels().get().into(),
+            sample_count: info.sample_count().get(),
+            has_loop: info.loop_info().is_some(),
+            loop_start,
+            loop_end,
+            size: info.size(),
+            name,
+            extension: bank.extension.as_ptr(),
+        };
+
+        // SAFETY: the caller guarantees that `out` is valid for writes
+        unsafe { out.write(info) };
+        Ok(())
+    })
+}
+
+/// Extracts the stream at `index` into a buffer of `capacity` bytes at `buffer`, writing the size of the
+/// extracted file to `written`. The stream is encoded like when it's written to a file.
+///
+/// If the buffer is too small, nothing is written to it, and [`FSBEX_ERROR_BUFFER_TOO_SMALL`] is returned
+/// with the required size written to `written`. The required size can be queried by passing a null `buffer`
+/// with a `capacity` of 0.
+///
+/// Returns [`FSBEX_ERROR_UNKNOWN_STREAM`] if the sound bank has no stream at `index`,
+/// or [`FSBEX_ERROR_ENCODE`] if the stream could not be encoded.
+///
+/// # Safety
+///
+/// `bank` must be a sound bank that hasn't been freed and isn't used by another thread at the same time,
+/// `buffer` must be valid for writes of `capacity` bytes (or null if `capacity` is 0),
+/// and `written` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn fsbex_bank_extract_to_buffer(
+    bank: *mut FsbexBank,
+    index: u32,
+    buffer: *mut u8,
+    capacity: usize,
+    written: *mut usize,
+) -> c_int {
+    run(|| {
+        // SAFETY: the caller guarantees that `bank` is null or a sound bank that isn't used elsewhere
+        let bank = unsafe { bank.as_mut() }.ok_or_else(|| null_argument("bank"))?;
+        if buffer.is_null() && capacity > 0 {
+            return Err(null_argument("buffer"));
+        }
+        if written.is_null() {
+            return Err(null_argument("written"));
+        }
+
+        let data = bank
+            .bank
+            .extract_stream(index, Vec::new())
+            .map_err(|e| stream_error(&e))?;
+
+        // SAFETY: the caller guarantees that `written` is valid for writes
+        unsafe { written.write(data.len()) };
+
+        if data.len() > capacity {
+            return Err(FfiError::new(
+                FSBEX_ERROR_BUFFER_TOO_SMALL,
+                format!(
+                    "buffer of {capacity} bytes is too small for the extracted stream ({} bytes)",
+                    data.len()
+                ),
+            ));
+        }
+
+        if !data.is_empty() {
+            // SAFETY: the caller guarantees that `buffer` is valid for writes of `capacity` bytes,
+            // which is at least the length of the data
+            unsafe { buffer.copy_from_nonoverlapping(data.as_ptr(), data.len()) };
+        }
+
+        Ok(())
+    })
+}
+
+/// Extracts the stream at `index` to a file at `path`, which is created or overwritten.
+///
+/// Returns [`FSBEX_ERROR_IO`] if the file could not be created, [`FSBEX_ERROR_UNKNOWN_STREAM`]
+/// if the sound bank has no stream at `index`, or [`FSBEX_ERROR_ENCODE`] if the stream could not be encoded
+/// (including when the file could not be written).
+///
+/// # Safety
+///
+/// `bank` must be a sound bank that hasn't been freed and isn't used by another thread at the same time,
+/// and `path` must point to a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn fsbex_bank_extract_to_file(
+    bank: *mut FsbexBank,
+    index: u32,
+    path: *const c_char,
+) -> c_int {
+    run(|| {
+        // SAFETY: the caller guarantees that `bank` is null or a sound bank that isn't used elsewhere
+        let bank = unsafe { bank.as_mut() }.ok_or_else(|| null_argument("bank"))?;
+        // SAFETY: the caller guarantees that `path` points to a null-terminated string
+        let path = unsafe { path_arg(path) }?;
+
+        if bank.bank.stream_info(index).is_none() {
+            return Err(FfiError::new(
+                FSBEX_ERROR_UNKNOWN_STREAM,
+                format!("there is no stream at index {index}"),
+            ));
+        }
+
+        let file = File::create(path).map_err(|e| FfiError::from_error(FSBEX_ERROR_IO, &e))?;
+
+        bank.bank
+            .extract_stream(index, BufWriter::new(file))
+            .map_err(|e| stream_error(&e))?
+            .flush()
+            .map_err(|e| FfiError::from_error(FSBEX_ERROR_IO, &e))
+    })
+}
+
+/// Frees a sound bank. Passing null does nothing.
+///
+/// # Safety
+///
+/// `bank` must be null, or a sound bank that hasn't been freed already.
+#[no_mangle]
+pub unsafe extern "C" fn fsbex_bank_free(bank: *mut FsbexBank) {
+    if !bank.is_null() {
+        // SAFETY: the caller guarantees that `bank` was returned from opening a sound bank and hasn't been freed
+        drop(unsafe { Box::from_raw(bank) });
+    }
+}
+
+/// Returns a message describing the last error on the calling thread, or null if no function has failed
+/// on this thread. The message remains valid until another function fails on the same thread.
+#[no_mangle]
+pub extern "C" fn fsbex_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_deref().map_or(ptr::null(), CStr::as_ptr))
+}
@@ -0,0 +1,213 @@
+use crate::bank::DecodeError;
+use crate::decrypt::looks_encrypted;
+use crate::header::is_magic;
+use crate::scan::SCANNED_HEADER_SIZE;
+use crate::studio::is_bank_file;
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    fs::File,
+    io::{BufRead, BufReader, Error as IoError, Read, Result as IoResult, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+/// A buffered sound bank file, opened with [`Bank::open`].
+///
+/// Reads are buffered with a [`BufReader`], and the file can seek, so streams of a sound bank opened
+/// with [`Bank::open`] can be accessed in any order like sound banks created with [`Bank::seekable`].
+///
+/// [`Bank::open`]: crate::Bank::open
+/// [`Bank::seekable`]: crate::Bank::seekable
+#[derive(Debug)]
+pub struct BankFile {
+    inner: BufReader<File>,
+    path: PathBuf,
+}
+
+impl BankFile {
+    // Opens the file at `path`, then checks its file signature without consuming it,
+    // so that files that aren't sound banks are reported with a helpful error.
+    pub(crate) fn open(path: &Path) -> Result<Self, OpenError> {
+        let mut inner = File::open(path)
+            .map(BufReader::new)
+            .map_err(OpenError::from_io(path, OpenErrorKind::Open))?;
+
+        let mut prefix = Vec::with_capacity(SCANNED_HEADER_SIZE);
+        let read = (&mut inner)
+            .take(SCANNED_HEADER_SIZE as u64)
+            .read_to_end(&mut prefix)
+            .map_err(OpenError::from_io(path, OpenErrorKind::Read))?;
+
+        // the prefix is still buffered, so seeking back doesn't read it again
+        let read = i64::try_from(read).expect("prefix is at most 72 bytes");
+        inner
+            .seek_relative(-read)
+            .map_err(OpenError::from_io(path, OpenErrorKind::Read))?;
+
+        let magic = prefix
+            .get(..4)
+            .map(|magic| magic.try_into().expect("slice has 4 bytes"));
+        if magic.is_some_and(is_magic) {
+            Ok(Self {
+                inner,
+                path: path.to_path_buf(),
+            })
+        } else if is_bank_file(&prefix) {
+            Err(OpenError::new(path, OpenErrorKind::StudioBank))
+        } else if looks_encrypted(&prefix) {
+            Err(OpenError::new(path, OpenErrorKind::Encrypted))
+        } else {
+            Err(OpenError::new(path, OpenErrorKind::Magic))
+        }
+    }
+
+    /// Returns the path that the file was opened from.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Unwraps this [`BankFile`], returning the underlying buffered file.
+    #[must_use]
+    pub fn into_inner(self) -> BufReader<File> {
+        self.inner
+    }
+}
+
+impl Read for BankFile {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl BufRead for BankFile {
+    fn fill_buf(&mut self) -> IoResult<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt);
+    }
+}
+
+impl Seek for BankFile {
+    fn seek(&mut self, pos: SeekFrom) -> IoResult<u64> {
+        self.inner.seek(pos)
+    }
+
+    fn stream_position(&mut self) -> IoResult<u64> {
+        self.inner.stream_position()
+    }
+}
+
+/// Represents an error that can occur when opening a sound bank file with [`Bank::open`].
+///
+/// The error holds the path of the file, which is included when it is displayed.
+/// See [`OpenErrorKind`] for the different kinds of errors that can occur.
+///
+/// [`Bank::open`]: crate::Bank::open
+#[derive(Debug)]
+pub struct OpenError {
+    path: PathBuf,
+    kind: OpenErrorKind,
+    source: Option<OpenErrorSource>,
+}
+
+#[derive(Debug)]
+enum OpenErrorSource {
+    Io(IoError),
+    Decode(DecodeError),
+}
+
+/// A variant of an [`OpenError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum OpenErrorKind {
+    /// Failed to open the file.
+    Open,
+    /// Failed to read the file signature of the file.
+    Read,
+    /// The file is an FMOD Studio bank file (.bank), whose sound banks can be read with
+    /// [`StudioBank`](crate::studio::StudioBank).
+    StudioBank,
+    /// The file looks like an encrypted FSB5 sound bank, which can be read with [`Bank::decrypt`]
+    /// if its key is known, or found with [`guess_key`](crate::guess_key).
+    ///
+    /// [`Bank::decrypt`]: crate::Bank::decrypt
+    Encrypted,
+    /// The file is not a sound bank, since it had no known file signature.
+    Magic,
+    /// The file header of the sound bank could not be parsed.
+    Parse,
+}
+
+impl OpenError {
+    fn new(path: &Path, kind: OpenErrorKind) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            kind,
+            source: None,
+        }
+    }
+
+    fn from_io(path: &Path, kind: OpenErrorKind) -> impl FnOnce(IoError) -> Self + '_ {
+        move |source| Self {
+            path: path.to_path_buf(),
+            kind,
+            source: Some(OpenErrorSource::Io(source)),
+        }
+    }
+
+    pub(crate) fn from_decode(path: &Path) -> impl FnOnce(DecodeError) -> Self + '_ {
+        move |source| Self {
+            path: path.to_path_buf(),
+            kind: OpenErrorKind::Parse,
+            source: Some(OpenErrorSource::Decode(source)),
+        }
+    }
+
+    /// Returns the path of the file that couldn't be opened.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the [`OpenErrorKind`] associated with this error.
+    #[must_use]
+    pub fn kind(&self) -> OpenErrorKind {
+        self.kind
+    }
+}
+
+impl Display for OpenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_fmt(format_args!("{} - file at {}", self.kind, self.path.display()))
+    }
+}
+
+impl Error for OpenError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.source {
+            Some(OpenErrorSource::Io(source)) => Some(source),
+            Some(OpenErrorSource::Decode(source)) => Some(source),
+            None => None,
+        }
+    }
+}
+
+impl Display for OpenErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Self::Open => "failed to open sound bank file",
+            Self::Read => "failed to read file signature",
+            Self::StudioBank => {
+                "file is an FMOD Studio bank file (read its sound banks with `StudioBank` instead)"
+            }
+            Self::Encrypted => {
+                "file looks like an encrypted sound bank (read it with `Bank::decrypt` instead)"
+            }
+            Self::Magic => "file is not a sound bank (no file signature found)",
+            Self::Parse => "failed to parse sound bank",
+        })
+    }
+}
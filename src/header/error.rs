@@ -3,6 +3,7 @@ use std::{
     error::Error,
     ffi::FromBytesUntilNulError,
     fmt::{Display, Formatter, Result as FmtResult},
+    io::Error as IoError,
     str::Utf8Error,
 };
 
@@ -17,7 +18,9 @@ pub(crate) struct HeaderError {
 pub(crate) enum HeaderErrorKind {
     Magic,
     Version,
-    UnknownVersion { version: u32 },
+    UnknownVersion {
+        version: u32,
+    },
     StreamCount,
     ZeroStreams,
     StreamHeadersSize,
@@ -25,13 +28,30 @@ pub(crate) enum HeaderErrorKind {
     TotalStreamSize,
     ZeroTotalStreamSize,
     AudioFormat,
-    UnknownAudioFormat { flag: u32 },
+    UnknownAudioFormat {
+        flag: u32,
+    },
     EncodingFlags,
     Metadata,
     StreamHeader,
-    ZeroStreamSize { index: u32 },
-    WrongHeaderSize { expected: usize, actual: usize },
+    MixedAudioFormats {
+        index: u32,
+    },
+    WrongHeaderSize {
+        expected: u64,
+        actual: u64,
+    },
     NameTable,
+    Banks,
+    Seek {
+        offset: u64,
+    },
+    #[cfg(feature = "async")]
+    Position,
+    #[cfg(feature = "mmap")]
+    Map,
+    EmptyKey,
+    WrongKey,
 }
 
 #[derive(Debug)]
@@ -39,6 +59,7 @@ pub(crate) enum HeaderErrorSource {
     Read(ReadError),
     Stream(StreamError),
     NameTable(NameError),
+    Io(IoError),
 }
 
 impl HeaderError {
@@ -56,6 +77,24 @@ impl HeaderError {
     pub(crate) fn factory(kind: HeaderErrorKind) -> impl FnOnce(ReadError) -> Self {
         move |source| Self::new_with_source(kind, source)
     }
+
+    pub(crate) fn io_factory(kind: HeaderErrorKind) -> impl FnOnce(IoError) -> Self {
+        move |source| Self {
+            kind,
+            source: Some(HeaderErrorSource::Io(source)),
+        }
+    }
+}
+
+impl HeaderError {
+    // Sound banks that fail to decrypt have no file signature, but can still be read.
+    pub(crate) fn is_missing_magic(&self) -> bool {
+        matches!(self.kind, HeaderErrorKind::Magic) && self.source.is_none()
+    }
+
+    pub(crate) fn is_wrong_key(&self) -> bool {
+        matches!(self.kind, HeaderErrorKind::WrongKey)
+    }
 }
 
 #[cfg(test)]
@@ -105,11 +144,19 @@ impl Display for HeaderError {
             EncodingFlags => f.write_str("failed to read encoding flags"),
             Metadata => f.write_str("failed to read (unused) metadata bytes"),
             StreamHeader => f.write_str("failed to parse stream header"),
-            ZeroStreamSize { index } => f.write_fmt(format_args!("size of data of stream at index {index} was 0 bytes")),
+            MixedAudioFormats { index } => f.write_fmt(format_args!("audio format of stream at index {index} was different from previous streams")),
             WrongHeaderSize { expected, actual } => {
                 f.write_fmt(format_args!("total size of base header and stream headers ({actual} bytes) was different from expected ({expected} bytes)"))
             }
-            NameTable => f.write_str("failed to read stream names")
+            NameTable => f.write_str("failed to read stream names"),
+            Banks => f.write_str("failed to read data of sound banks"),
+            EmptyKey => f.write_str("decryption key was empty"),
+            WrongKey => f.write_str("no file signature found after decryption (the key or scheme may be wrong)"),
+            Seek { offset } => f.write_fmt(format_args!("failed to seek to sound bank at offset {offset}")),
+            #[cfg(feature = "async")]
+            Position => f.write_str("failed to get position of sound bank in source"),
+            #[cfg(feature = "mmap")]
+            Map => f.write_str("failed to open and map sound bank file"),
         }
     }
 }
@@ -121,6 +168,7 @@ impl Error for HeaderError {
                 HeaderErrorSource::Read(e) => Some(e),
                 HeaderErrorSource::Stream(e) => Some(e),
                 HeaderErrorSource::NameTable(e) => Some(e),
+                HeaderErrorSource::Io(e) => Some(e),
             },
             None => None,
         }
@@ -140,7 +188,11 @@ pub(crate) enum StreamErrorKind {
     StreamInfo,
     UnknownSampleRate { flag: u8 },
     ZeroSamples,
+    ZeroFrequency,
+    ChannelCount { channels: u16 },
     Chunk,
+    DataOffset,
+    DataOffsetOutOfBounds,
 }
 
 #[derive(Debug)]
@@ -169,6 +221,10 @@ impl StreamError {
 
 #[cfg(test)]
 impl StreamError {
+    pub(crate) fn index(&self) -> u32 {
+        self.index
+    }
+
     pub(crate) fn kind(&self) -> StreamErrorKind {
         self.kind
     }
@@ -193,7 +249,17 @@ impl Display for StreamError {
                 f.write_fmt(format_args!("sample rate flag was not recognized (0x{flag:02x})"))
             }
             ZeroSamples => f.write_str("number of samples was 0"),
+            ZeroFrequency => f.write_str("default frequency (sample rate) was 0"),
+            ChannelCount { channels } => f.write_fmt(format_args!(
+                "number of channels was not between 1 and 255 ({channels})"
+            )),
             Chunk => f.write_str("failed to parse stream header chunk"),
+            DataOffset => {
+                f.write_str("data offset of stream was before the data of the previous stream")
+            }
+            DataOffsetOutOfBounds => {
+                f.write_str("data offset of stream was past the end of stream data")
+            }
         }?;
 
         f.write_fmt(format_args!(" - stream header at index {}", self.index))
@@ -226,17 +292,27 @@ pub(crate) enum ChunkErrorKind {
     UnknownType { flag: u8 },
     ChannelCount,
     ZeroChannels,
+    TooManyChannels { channels: u8 },
     SampleRate,
     ZeroSampleRate,
     LoopStart,
     LoopEnd,
-    ZeroLengthLoop,
+    LoopTooLong,
+    LoopEndBeforeStart { start: u32, end: u32 },
     DspCoefficients,
+    XmaSeekTable,
+    XmaSeekTableSize { size: u32 },
+    Atrac9Config,
+    Atrac9SyncByte { byte: u8 },
+    XwmaConfig,
     VorbisCrc32,
+    VorbisSeekTable,
+    PeakVolume,
+    UnknownChunk,
     VorbisLayerCount,
     TooManyVorbisLayers { layers: u32 },
     ZeroVorbisLayers,
-    WrongChunkSize { expected: u32, actual: usize },
+    WrongChunkSize { expected: u32, actual: u64 },
 }
 
 impl ChunkError {
@@ -280,13 +356,31 @@ impl Display for ChunkError {
             }
             ChannelCount => f.write_str("failed to read number of channels"),
             ZeroChannels => f.write_str("number of channels was 0"),
+            TooManyChannels { channels } => {
+                f.write_fmt(format_args!("number of channels was more than 32 ({channels})"))
+            }
             SampleRate => f.write_str("failed to read sample rate"),
             ZeroSampleRate => f.write_str("sample rate was 0"),
             LoopStart => f.write_str("failed to read starting position of loop in stream"),
             LoopEnd => f.write_str("failed to read ending position of loop in stream"),
-            ZeroLengthLoop => f.write_str("length of loop in stream was 0"),
+            LoopTooLong => f.write_str("length of loop in stream was larger than the maximum sample count"),
+            LoopEndBeforeStart { start, end } => f.write_fmt(format_args!(
+                "ending position of loop in stream ({end}) was before starting position ({start})"
+            )),
             DspCoefficients => f.write_str("failed to read DSP coefficients of stream"),
+            XmaSeekTable => f.write_str("failed to read seek table of XMA stream"),
+            XmaSeekTableSize { size } => f.write_fmt(format_args!(
+                "size of seek table of XMA stream was not a multiple of 4 bytes ({size} bytes)"
+            )),
+            Atrac9Config => f.write_str("failed to read configuration data of ATRAC9 stream"),
+            Atrac9SyncByte { byte } => f.write_fmt(format_args!(
+                "configuration data of ATRAC9 stream had wrong sync byte (0x{byte:02x})"
+            )),
+            XwmaConfig => f.write_str("failed to read format information of xWMA stream"),
             VorbisCrc32 => f.write_str("failed to read CRC32 of Vorbis setup header"),
+            VorbisSeekTable => f.write_str("failed to read seek table of Vorbis stream"),
+            PeakVolume => f.write_str("failed to read peak volume of stream"),
+            UnknownChunk => f.write_str("failed to read data of stream header chunk of unknown type"),
             VorbisLayerCount => {
                 f.write_str("failed to read number of layers per channel in Vorbis stream")
             }
@@ -316,12 +410,13 @@ impl Error for ChunkError {
 pub(crate) struct NameError {
     index: u32,
     kind: NameErrorKind,
-    source: NameErrorSource,
+    source: Option<NameErrorSource>,
 }
 
 #[derive(Debug)]
 pub(crate) enum NameErrorKind {
     NameOffset,
+    InvalidOffset { offset: u32 },
     Name,
     Utf8,
 }
@@ -334,24 +429,36 @@ enum NameErrorSource {
 }
 
 impl NameError {
-    fn new(index: u32, kind: NameErrorKind, source: NameErrorSource) -> Self {
+    pub(crate) fn new(index: u32, kind: NameErrorKind) -> Self {
+        Self {
+            index,
+            kind,
+            source: None,
+        }
+    }
+
+    fn new_with_source(index: u32, kind: NameErrorKind, source: NameErrorSource) -> Self {
         Self {
             index,
             kind,
-            source,
+            source: Some(source),
         }
     }
 
     pub(crate) fn read_factory(index: u32, kind: NameErrorKind) -> impl FnOnce(ReadError) -> Self {
-        move |source| Self::new(index, kind, NameErrorSource::Read(source))
+        move |source| Self::new_with_source(index, kind, NameErrorSource::Read(source))
     }
 
     pub(crate) fn cstr_factory(index: u32) -> impl FnOnce(FromBytesUntilNulError) -> Self {
-        move |source| Self::new(index, NameErrorKind::Name, NameErrorSource::CStr(source))
+        move |source| {
+            Self::new_with_source(index, NameErrorKind::Name, NameErrorSource::CStr(source))
+        }
     }
 
     pub(crate) fn utf8_factory(index: u32) -> impl FnOnce(Utf8Error) -> Self {
-        move |source| Self::new(index, NameErrorKind::Utf8, NameErrorSource::Utf8(source))
+        move |source| {
+            Self::new_with_source(index, NameErrorKind::Utf8, NameErrorSource::Utf8(source))
+        }
     }
 }
 
@@ -370,6 +477,9 @@ impl Display for NameError {
 
         match self.kind {
             NameOffset => f.write_str("failed to read offset of stream name"),
+            InvalidOffset { offset } => f.write_fmt(format_args!(
+                "offset of stream name was outside of name table ({offset})"
+            )),
             Name => f.write_str("failed to read stream name"),
             Utf8 => f.write_str("stream name was not valid UTF-8"),
         }?;
@@ -381,9 +491,12 @@ impl Display for NameError {
 impl Error for NameError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match &self.source {
-            NameErrorSource::Read(e) => Some(e),
-            NameErrorSource::CStr(e) => Some(e),
-            NameErrorSource::Utf8(e) => Some(e),
+            Some(source) => match source {
+                NameErrorSource::Read(e) => Some(e),
+                NameErrorSource::CStr(e) => Some(e),
+                NameErrorSource::Utf8(e) => Some(e),
+            },
+            None => None,
         }
     }
 }
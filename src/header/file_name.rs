@@ -0,0 +1,271 @@
+use super::StreamInfo;
+use std::{collections::HashSet, path::PathBuf};
+
+// Device names that can't be used as file names on Windows, even with an extension.
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+// Most file systems limit names to 255 bytes, which leaves room for suffixes, prefixes, and extensions.
+const MAX_NAME_LEN: usize = 200;
+
+// Gives every stream a file name stem that's unique within the sound bank, so that streams can be written
+// to the same directory. Streams without a name are named after their index, padded with zeros so that
+// their files sort in order of streams. Stems are compared ignoring case, since file systems often do,
+// and stems that were already taken get a numeric suffix, in order of streams.
+pub(super) fn assign_stems(stream_info: &mut [StreamInfo]) {
+    let width = stream_info.len().saturating_sub(1).to_string().len();
+
+    let stems: Vec<String> = stream_info
+        .iter()
+        .zip(0..)
+        .map(|(info, index)| {
+            match info.name() {
+                Some(name) => sanitize_file_name(name),
+                None => None,
+            }
+            .unwrap_or_else(|| format!("stream_{index:0width$}"))
+        })
+        .collect();
+
+    // suffixed stems must not collide with the original stems of later streams
+    let original: HashSet<String> = stems.iter().map(|stem| stem.to_lowercase()).collect();
+    let mut taken = HashSet::with_capacity(stems.len());
+
+    for (info, stem) in stream_info.iter_mut().zip(stems) {
+        let stem = if taken.insert(stem.to_lowercase()) {
+            stem
+        } else {
+            let mut suffix = 2u64;
+
+            loop {
+                let candidate = format!("{stem}_{suffix}");
+                let lowercase = candidate.to_lowercase();
+
+                if !original.contains(&lowercase) && taken.insert(lowercase) {
+                    break candidate;
+                }
+
+                suffix += 1;
+            }
+        };
+
+        info.file_stem = stem.into();
+    }
+}
+
+/// Converts a stream name into a file name that's safe to use on common platforms, without any directories.
+///
+/// Stream names are often the paths the streams were authored at, such as `music/theme` or even `../music/theme`,
+/// so only the last component of the name is kept (`theme`), and `.` and `..` components are ignored.
+/// Both `/` and `\` separate components. In the file name:
+/// - characters that aren't allowed in file names on Windows, macOS, or Linux (such as `:`, `*`, and
+///   control characters) are replaced with `_`
+/// - names that end with a dot or a space, which Windows rejects, get a trailing `_`
+/// - device names reserved by Windows (such as `CON` and `NUL`, even with an extension) are prefixed with `_`
+/// - names are cut short at 200 bytes, leaving room for an extension within the common limit of 255 bytes
+///
+/// This is the policy used to name files in [`StreamInfo::suggested_filename`] and by [`DirectoryFactory`].
+/// Returns `None` if no file name is left, such as for empty names or names like `../`.
+///
+/// [`DirectoryFactory`]: crate::DirectoryFactory
+///
+/// # Examples
+///
+/// ```
+/// use fsbex::sanitize_file_name;
+///
+/// assert_eq!(sanitize_file_name("../music/theme").as_deref(), Some("theme"));
+/// assert_eq!(sanitize_file_name("hit: loud?").as_deref(), Some("hit_ loud_"));
+/// assert_eq!(sanitize_file_name("con").as_deref(), Some("_con"));
+/// assert_eq!(sanitize_file_name("sfx/.."), Some("sfx".into()));
+/// assert_eq!(sanitize_file_name(".."), None);
+/// ```
+#[must_use]
+pub fn sanitize_file_name(name: &str) -> Option<String> {
+    components(name).last().map(sanitize_component)
+}
+
+/// Converts a stream name into a relative path that's safe to create below a directory, keeping its directories.
+///
+/// Like [`sanitize_file_name`], but every component of the name is kept as a directory, and sanitized the same way
+/// as the file name. Since `.` and `..` components are ignored, and names starting with a separator or a drive
+/// letter are treated as relative, the path never leaves the directory it's joined to.
+/// Returns `None` if no file name is left.
+///
+/// This is the policy used by [`DirectoryFactory::subdirectories`](crate::DirectoryFactory::subdirectories).
+///
+/// # Examples
+///
+/// ```
+/// use fsbex::sanitize_file_path;
+/// use std::path::Path;
+///
+/// let path = sanitize_file_path("../../music\\ambient/forest").unwrap();
+/// assert_eq!(path, Path::new("music").join("ambient").join("forest"));
+///
+/// let path = sanitize_file_path("C:/Windows/nul.txt").unwrap();
+/// assert_eq!(path, Path::new("C_").join("Windows").join("_nul.txt"));
+/// ```
+#[must_use]
+pub fn sanitize_file_path(name: &str) -> Option<PathBuf> {
+    let path: PathBuf = components(name).map(sanitize_component).collect();
+    (path.components().next().is_some()).then_some(path)
+}
+
+// Splits a stream name into its path components, without empty, `.`, and `..` components.
+fn components(name: &str) -> impl Iterator<Item = &str> {
+    name.split(['/', '\\'])
+        .filter(|component| !matches!(*component, "" | "." | ".."))
+}
+
+// Stream names can hold characters that aren't allowed in file names on some platforms.
+// Windows also rejects device names (even with an extension), and names that end with a dot or a space.
+fn sanitize_component(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    if sanitized.len() > MAX_NAME_LEN {
+        let end = (0..=MAX_NAME_LEN)
+            .rev()
+            .find(|&end| sanitized.is_char_boundary(end))
+            .unwrap_or_default();
+        sanitized.truncate(end);
+    }
+
+    if sanitized.ends_with(['.', ' ']) {
+        sanitized.push('_');
+    }
+
+    let device = sanitized.split('.').next().unwrap_or_default().trim_end();
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(device))
+    {
+        sanitized.insert(0, '_');
+    }
+
+    sanitized
+}
+
+#[cfg(test)]
+mod test {
+    use super::{assign_stems, sanitize_file_name, sanitize_file_path, MAX_NAME_LEN};
+    use crate::header::StreamInfo;
+    use std::path::{Component, Path};
+
+    fn stems(names: &[Option<&str>]) -> Vec<String> {
+        let mut stream_info: Vec<StreamInfo> = names
+            .iter()
+            .map(|name| StreamInfo {
+                name: name.map(Into::into),
+                ..StreamInfo::dummy(1, 2, 4)
+            })
+            .collect();
+
+        assign_stems(&mut stream_info);
+        stream_info.iter().map(|info| info.file_stem.to_string()).collect()
+    }
+
+    #[test]
+    fn replace_invalid_file_name_characters() {
+        let sanitize = |name| sanitize_file_name(name).unwrap();
+
+        assert_eq!(sanitize("a:b*c?d\"e<f>g|h\ni"), "a_b_c_d_e_f_g_h_i");
+        assert_eq!(sanitize("bgm (loop) #1"), "bgm (loop) #1");
+        assert_eq!(sanitize("fade out."), "fade out._");
+        assert_eq!(sanitize("con"), "_con");
+        assert_eq!(sanitize("Lpt1.old"), "_Lpt1.old");
+        assert_eq!(sanitize("console"), "console");
+        assert_eq!(sanitize_file_name(""), None);
+    }
+
+    #[test]
+    fn cap_file_name_length() {
+        let name = "a".repeat(MAX_NAME_LEN + 50);
+        assert_eq!(sanitize_file_name(&name).unwrap().len(), MAX_NAME_LEN);
+
+        // names are cut at a character boundary, and can't end with a space after being cut
+        let name = format!("{} é", "a".repeat(MAX_NAME_LEN - 2));
+        assert_eq!(
+            sanitize_file_name(&name).unwrap(),
+            format!("{} _", "a".repeat(MAX_NAME_LEN - 2))
+        );
+    }
+
+    #[test]
+    fn strip_directories_from_file_names() {
+        assert_eq!(sanitize_file_name("../music/theme").unwrap(), "theme");
+        assert_eq!(sanitize_file_name("..\\..\\windows\\win.ini").unwrap(), "win.ini");
+        assert_eq!(sanitize_file_name("/etc/passwd").unwrap(), "passwd");
+        assert_eq!(sanitize_file_name("C:\\boot.ini").unwrap(), "boot.ini");
+        assert_eq!(sanitize_file_name("music/..").unwrap(), "music");
+        assert_eq!(sanitize_file_name("..."), Some(String::from("..._")));
+
+        for name in ["..", "../", "./.", "/", "\\\\"] {
+            assert_eq!(sanitize_file_name(name), None, "{name}");
+        }
+    }
+
+    #[test]
+    fn keep_paths_within_directory() {
+        let sanitize = |name| sanitize_file_path(name).unwrap();
+
+        assert_eq!(sanitize("../music/theme"), Path::new("music").join("theme"));
+        assert_eq!(sanitize("..\\../sfx/./hit"), Path::new("sfx").join("hit"));
+        assert_eq!(sanitize("/etc/passwd"), Path::new("etc").join("passwd"));
+        assert_eq!(
+            sanitize("C:\\Windows\\aux"),
+            Path::new("C_").join("Windows").join("_aux")
+        );
+        assert_eq!(sanitize_file_path("../.."), None);
+
+        // every component of the path is a normal component, so it can't escape the directory it's joined to
+        for name in [
+            "../../../etc/passwd",
+            "/root/.ssh",
+            "\\\\server\\share\\x",
+            "a/../../b",
+        ] {
+            let path = sanitize(name);
+            assert!(path.components().all(|c| matches!(c, Component::Normal(_))), "{name}");
+        }
+    }
+
+    #[test]
+    fn suffix_duplicate_stems() {
+        assert_eq!(
+            stems(&[Some("hit"), Some("Hit"), Some("hit_2"), Some("hit"), None]),
+            ["hit", "Hit_3", "hit_2", "hit_4", "stream_4"]
+        );
+
+        // directories are removed from names, so streams in different directories can collide
+        assert_eq!(
+            stems(&[
+                Some("sfx/hit"),
+                Some("music/hit"),
+                Some("a:b"),
+                Some(""),
+                Some("../")
+            ]),
+            ["hit", "hit_2", "a_b", "stream_3", "stream_4"]
+        );
+    }
+
+    #[test]
+    fn pad_stream_indices() {
+        let names = vec![None; 11];
+        let stems = stems(&names);
+
+        assert_eq!(stems[0], "stream_00");
+        assert_eq!(stems[10], "stream_10");
+        assert_eq!(self::stems(&[None]), ["stream_0"]);
+    }
+}
@@ -0,0 +1,55 @@
+/// Flags of a sound bank's file header, describing how the bank was built.
+///
+/// FSB3 and FSB4 banks store their flags differently, so the flags of these banks are converted
+/// to the layout used by FSB5 banks. Bits without an accessor can still be read with [`BankFlags::bits`].
+/// With the `serde` feature, flags are serialized as their raw value.
+///
+/// # Examples
+///
+/// ```
+/// use fsbex::Bank;
+/// use std::error::Error;
+///
+/// fn print_flags(bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+///     let flags = Bank::new(bytes)?.flags();
+///
+///     println!("raw flags: {:#010x}", flags.bits());
+///     println!("basic stream headers: {}", flags.basic_headers());
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct BankFlags(u32);
+
+impl BankFlags {
+    pub(crate) const BIG_ENDIAN_PCM: u32 = 0x01;
+    pub(crate) const BASIC_HEADERS: u32 = 0x02;
+
+    pub(crate) fn new(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// Returns the raw value of the flags.
+    #[must_use]
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Returns whether samples of PCM16 streams are stored in big-endian byte order.
+    #[must_use]
+    pub fn big_endian_pcm(self) -> bool {
+        self.0 & Self::BIG_ENDIAN_PCM != 0
+    }
+
+    /// Returns whether only the first stream has a full stream header.
+    /// The headers of other streams only store their position and length, and share everything else with the first stream.
+    #[must_use]
+    pub fn basic_headers(self) -> bool {
+        self.0 & Self::BASIC_HEADERS != 0
+    }
+}
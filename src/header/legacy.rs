@@ -0,0 +1,640 @@
+use super::error::{HeaderError, HeaderErrorKind, NameError, StreamError, StreamErrorKind};
+use super::{
+    file_name, AudioFormat, BankFlags, DspCoeffs, Header, Loop, StreamInfo,
+    MAX_PREALLOCATED_STREAMS,
+};
+use crate::read::Reader;
+use std::{
+    cmp::min,
+    io::Read,
+    num::{NonZeroU32, NonZeroU8},
+    str::from_utf8,
+};
+use tap::Pipe;
+
+// FSB3 and FSB4 file layouts taken from:
+// [1]: https://github.com/vgmstream/vgmstream/blob/master/src/meta/fsb.c
+// [2]: https://github.com/gdawg/fsbext/blob/master/src/fmod.h
+
+pub(super) const FSB3_MAGIC: [u8; 4] = *b"FSB3";
+pub(super) const FSB4_MAGIC: [u8; 4] = *b"FSB4";
+pub(super) const FSB3_MAGIC_BE: [u8; 4] = *b"3BSF";
+pub(super) const FSB4_MAGIC_BE: [u8; 4] = *b"4BSF";
+
+// Names are stored in a fixed-size field, without a null terminator if they fill the whole field.
+const NAME_SIZE: usize = 30;
+
+// Flags of the file header.
+const FLAG_BASIC_HEADERS: u32 = 0x02;
+const FLAG_BIG_ENDIAN_PCM: u32 = 0x08;
+
+// Mode flags of sample headers. Unlike FSB5 banks, the codec is chosen by the mode of each sample.
+const MODE_LOOP_NORMAL: u32 = 0x02;
+const MODE_8_BITS: u32 = 0x08;
+const MODE_16_BITS: u32 = 0x10;
+const MODE_MPEG: u32 = 0x200;
+const MODE_IMA_ADPCM: u32 = 0x0040_0000;
+const MODE_VAG: u32 = 0x0080_0000;
+const MODE_XMA: u32 = 0x0100_0000;
+const MODE_GC_ADPCM: u32 = 0x0200_0000;
+const MODE_CELT: u32 = 0x0800_0000;
+const MODE_MPEG_LAYER3: u32 = 0x1000_0000;
+
+// The data of every stream starts at a multiple of this size in banks with padding between streams.
+const STREAM_ALIGNMENT: u32 = 32;
+
+// Versions of sound banks that are older than FSB5. Sample headers of all versions share the same base layout,
+// and the mode flags of samples are the same.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LegacyVersion {
+    Fsb3_0,
+    Fsb3_1,
+    Fsb4,
+}
+
+impl LegacyVersion {
+    fn parse(magic: [u8; 4], version: u32) -> Result<Self, HeaderError> {
+        // the major version is stored in the upper 16 bits (e.g. 0x00040000 for version 4.0)
+        match (magic, version) {
+            (FSB3_MAGIC, 0x0003_0000) => Ok(Self::Fsb3_0),
+            (FSB3_MAGIC, 0x0003_0001) => Ok(Self::Fsb3_1),
+            (FSB4_MAGIC, version) if version >> 16 == 4 => Ok(Self::Fsb4),
+            _ => Err(HeaderError::new(HeaderErrorKind::UnknownVersion { version })),
+        }
+    }
+
+    // The file header has a fixed size, including the signature.
+    // FSB4 file headers also hold a hash and a GUID.
+    fn header_size(self) -> u64 {
+        match self {
+            Self::Fsb3_0 | Self::Fsb3_1 => 0x18,
+            Self::Fsb4 => 0x30,
+        }
+    }
+
+    // Full sample headers can be followed by extra data (e.g. DSP coefficients), which is included in their size.
+    // FSB3.0 sample headers end after the number of channels, without the 3D distances and variations.
+    fn sample_header_size(self) -> u64 {
+        match self {
+            Self::Fsb3_0 => 0x40,
+            Self::Fsb3_1 | Self::Fsb4 => 0x50,
+        }
+    }
+
+    // Only FSB4 banks can pad stream data; stream data of FSB3 banks is always stored back to back.
+    fn aligns_stream_data(self) -> bool {
+        self == Self::Fsb4
+    }
+}
+
+// Parses the rest of an FSB3 or FSB4 file header, after the signature.
+pub(super) fn parse<R: Read>(
+    reader: &mut Reader<R>,
+    magic: [u8; 4],
+) -> Result<Header, HeaderError> {
+    let num_streams: NonZeroU32 = reader
+        .u32()
+        .map_err(HeaderError::factory(HeaderErrorKind::StreamCount))?
+        .try_into()
+        .map_err(|_| HeaderError::new(HeaderErrorKind::ZeroStreams))?;
+
+    let stream_headers_size = reader
+        .u32()
+        .map_err(HeaderError::factory(HeaderErrorKind::StreamHeadersSize))?;
+
+    let total_stream_size: NonZeroU32 = reader
+        .u32()
+        .map_err(HeaderError::factory(HeaderErrorKind::TotalStreamSize))?
+        .try_into()
+        .map_err(|_| HeaderError::new(HeaderErrorKind::ZeroTotalStreamSize))?;
+
+    let raw_version = reader.u32().map_err(HeaderError::factory(HeaderErrorKind::Version))?;
+    let version = LegacyVersion::parse(magic, raw_version)?;
+
+    let header_flags = reader
+        .u32()
+        .map_err(HeaderError::factory(HeaderErrorKind::EncodingFlags))?;
+
+    // read hash and GUID of FSB4 banks
+    let (hash, guid) = if version == LegacyVersion::Fsb4 {
+        let hash: [u8; 8] = reader
+            .take_const()
+            .map_err(HeaderError::factory(HeaderErrorKind::Metadata))?;
+
+        let guid: [u8; 16] = reader
+            .take_const()
+            .map_err(HeaderError::factory(HeaderErrorKind::Metadata))?;
+
+        (hash.into(), Some(guid))
+    } else {
+        (Box::default(), None)
+    };
+
+    reader
+        .advance_to(version.header_size())
+        .map_err(HeaderError::factory(HeaderErrorKind::Metadata))?;
+
+    // The number of streams hasn't been validated yet, so it isn't trusted for preallocation.
+    let mut streams: Vec<(u32, StreamInfo)> =
+        Vec::with_capacity(min(num_streams.get() as usize, MAX_PREALLOCATED_STREAMS));
+
+    for index in 0..num_streams.get() {
+        // Banks with basic headers only have a full header for the first sample.
+        // Other samples only store their length, and share everything else with the first sample.
+        let stream = match streams.first() {
+            Some(first) if header_flags & FLAG_BASIC_HEADERS != 0 => {
+                parse_basic_header(reader, index, first)?
+            }
+            _ => parse_sample_header(reader, index, version)?,
+        };
+
+        streams.push(stream);
+    }
+
+    let header_size = version.header_size() + u64::from(stream_headers_size);
+
+    // make sure base header + stream headers have been read
+    reader.advance_to(header_size).map_err(HeaderError::factory(
+        HeaderErrorKind::WrongHeaderSize {
+            expected: header_size,
+            actual: reader.position(),
+        },
+    ))?;
+
+    // All streams of a bank are expected to have the same format, like in FSB5 banks.
+    let mut format = None;
+
+    for ((_, info), index) in streams.iter().zip(0..) {
+        match format {
+            Some(format) if format != info.format => {
+                return Err(HeaderError::new(HeaderErrorKind::MixedAudioFormats { index }))
+            }
+            _ => format = Some(info.format),
+        }
+    }
+
+    // Some banks pad the data of every stream to a multiple of 32 bytes, which is detected from the total size.
+    // Like in FSB5 banks, the padding is counted as part of the stream that it follows.
+    let data_size: u64 = streams.iter().map(|(_, info)| u64::from(info.size)).sum();
+
+    if version.aligns_stream_data() && data_size < total_stream_size.get().into() {
+        let last = streams.len() - 1;
+
+        for (_, info) in &mut streams[..last] {
+            info.size = info
+                .size
+                .checked_next_multiple_of(STREAM_ALIGNMENT)
+                .unwrap_or(info.size);
+        }
+    }
+
+    // flags are converted to the layout of FSB5 banks
+    let mut flags = 0;
+    if header_flags & FLAG_BIG_ENDIAN_PCM != 0 {
+        flags |= BankFlags::BIG_ENDIAN_PCM;
+    }
+    if header_flags & FLAG_BASIC_HEADERS != 0 {
+        flags |= BankFlags::BASIC_HEADERS;
+    }
+
+    // stream data is stored in the same order as sample headers
+    let mut data_offset = 0;
+    for (_, info) in &mut streams {
+        info.data_offset = data_offset;
+        info.flags = BankFlags::new(flags);
+        data_offset += u64::from(info.size);
+    }
+
+    let mut stream_info: Box<[StreamInfo]> = streams.into_iter().map(|(_, info)| info).collect();
+    file_name::assign_stems(&mut stream_info);
+
+    Ok(Header {
+        version: if version == LegacyVersion::Fsb4 { 4 } else { 3 },
+        minor_version: raw_version & 0xFFFF,
+        format: format.expect("number of streams was validated to be nonzero"),
+        flags,
+        hash,
+        guid,
+        stream_info,
+        // offsets aren't stored in FSB3 and FSB4 banks, since they follow from stream sizes
+        offset_warnings: Box::default(),
+    })
+}
+
+// Parses a full sample header, returning the mode flags and the stream information.
+fn parse_sample_header<R: Read>(
+    reader: &mut Reader<R>,
+    index: u32,
+    version: LegacyVersion,
+) -> Result<(u32, StreamInfo), HeaderError> {
+    let read_err = |e| StreamError::new_with_source(index, StreamErrorKind::StreamInfo, e);
+
+    let start_pos = reader.position();
+    let header_size = reader.u16().map_err(read_err)?;
+    let name: [u8; NAME_SIZE] = reader.take_const().map_err(read_err)?;
+    let num_samples = reader.u32().map_err(read_err)?;
+    let size = reader.u32().map_err(read_err)?;
+    let loop_start = reader.u32().map_err(read_err)?;
+    let loop_end = reader.u32().map_err(read_err)?;
+    let mode = reader.u32().map_err(read_err)?;
+    let sample_rate = reader.u32().map_err(read_err)?;
+
+    // skip default volume, pan, and priority
+    reader.skip(6).map_err(read_err)?;
+    let channels = reader.u16().map_err(read_err)?;
+
+    // skip 3D distances and variations
+    reader
+        .advance_to(start_pos + version.sample_header_size())
+        .map_err(read_err)?;
+
+    let channels: NonZeroU8 = u8::try_from(channels)
+        .ok()
+        .and_then(NonZeroU8::new)
+        .ok_or_else(|| StreamError::new(index, StreamErrorKind::ChannelCount { channels }))?;
+
+    // used for decoding and encoding GC ADPCM streams; each channel has its own coefficients
+    let dsp_coeffs = if mode & MODE_GC_ADPCM != 0 {
+        (0..channels.get())
+            .map(|_| DspCoeffs::parse(reader))
+            .collect::<Result<Box<_>, _>>()
+            .map_err(read_err)?
+            .pipe(Some)
+    } else {
+        None
+    };
+
+    // skip other extra data
+    reader
+        .advance_to(start_pos + u64::from(header_size))
+        .map_err(read_err)?;
+
+    let name_len = name.iter().position(|&b| b == 0).unwrap_or(NAME_SIZE);
+    let name = from_utf8(&name[..name_len]).map_err(NameError::utf8_factory(index))?;
+
+    let info = StreamInfo {
+        format: parse_format(mode)?,
+        sample_rate: sample_rate
+            .try_into()
+            .map_err(|_| StreamError::new(index, StreamErrorKind::ZeroFrequency))?,
+        channels,
+        num_samples: num_samples
+            .try_into()
+            .map_err(|_| StreamError::new(index, StreamErrorKind::ZeroSamples))?,
+        stream_loop: parse_loop(mode, loop_start, loop_end),
+        dsp_coeffs,
+        xma_seek_table: None,
+        atrac9_config: None,
+        xwma_config: None,
+        vorbis_crc32: None,
+        vorbis_seek_table: None,
+        peak_volume: None,
+        unknown_chunks: Vec::new(),
+        channels_overridden: false,
+        data_offset: 0,
+        data_start: 0,
+        bank_offset: 0,
+        size,
+        name: (!name.is_empty()).then(|| name.into()),
+        flags: BankFlags::new(0),
+        file_stem: Box::default(),
+    };
+
+    Ok((mode, info))
+}
+
+// Parses a basic sample header, which only holds the number of samples and the size of the stream.
+// Streams with basic headers have no name, and loop over all of their samples if the first stream loops.
+fn parse_basic_header<R: Read>(
+    reader: &mut Reader<R>,
+    index: u32,
+    &(mode, ref first): &(u32, StreamInfo),
+) -> Result<(u32, StreamInfo), HeaderError> {
+    let read_err = |e| StreamError::new_with_source(index, StreamErrorKind::StreamInfo, e);
+
+    let num_samples: NonZeroU32 = reader
+        .u32()
+        .map_err(read_err)?
+        .try_into()
+        .map_err(|_| StreamError::new(index, StreamErrorKind::ZeroSamples))?;
+
+    let size = reader.u32().map_err(read_err)?;
+
+    let info = StreamInfo {
+        num_samples,
+        stream_loop: parse_loop(mode, 0, num_samples.get() - 1),
+        size,
+        name: None,
+        ..first.clone()
+    };
+
+    Ok((mode, info))
+}
+
+// Loop ends are stored as the index of the final sample frame in the loop, like in FSB5 banks.
+// Samples that don't loop still store loop points, which are ignored.
+fn parse_loop(mode: u32, start: u32, end: u32) -> Option<Loop> {
+    if mode & MODE_LOOP_NORMAL == 0 || end < start {
+        return None;
+    }
+
+    Loop::parse(0, start, end).ok()
+}
+
+// Only codecs whose stream data has the same layout as in FSB5 banks are supported.
+fn parse_format(mode: u32) -> Result<AudioFormat, HeaderError> {
+    if mode & (MODE_MPEG | MODE_MPEG_LAYER3) != 0 {
+        Ok(AudioFormat::Mpeg)
+    } else if mode & MODE_IMA_ADPCM != 0 {
+        Ok(AudioFormat::ImaAdpcm)
+    } else if mode & MODE_GC_ADPCM != 0 {
+        Ok(AudioFormat::GcAdpcm)
+    } else if mode & (MODE_VAG | MODE_XMA | MODE_CELT) != 0 {
+        Err(HeaderError::new(HeaderErrorKind::UnknownAudioFormat { flag: mode }))
+    } else if mode & MODE_16_BITS != 0 {
+        Ok(AudioFormat::Pcm16)
+    } else if mode & MODE_8_BITS != 0 {
+        Ok(AudioFormat::Pcm8)
+    } else {
+        Err(HeaderError::new(HeaderErrorKind::UnknownAudioFormat { flag: mode }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        FLAG_BASIC_HEADERS, FLAG_BIG_ENDIAN_PCM, FSB3_MAGIC, FSB4_MAGIC, MODE_16_BITS, MODE_8_BITS,
+        MODE_GC_ADPCM, MODE_LOOP_NORMAL, MODE_MPEG,
+    };
+    use crate::header::error::{HeaderErrorKind::*, StreamErrorKind::*};
+    use crate::header::{AudioFormat, Header};
+    use crate::read::Reader;
+    use std::num::NonZeroU32;
+
+    fn file_header(num_streams: u32, headers_size: u32, data_size: u32, flags: u32) -> Vec<u8> {
+        let mut header = FSB4_MAGIC.to_vec();
+        header.extend(num_streams.to_le_bytes());
+        header.extend(headers_size.to_le_bytes());
+        header.extend(data_size.to_le_bytes());
+        header.extend(0x0004_0000_u32.to_le_bytes());
+        header.extend(flags.to_le_bytes());
+        header.resize(0x30, 0);
+        header
+    }
+
+    fn fsb3_file_header(
+        version: u32,
+        num_streams: u32,
+        headers_size: u32,
+        data_size: u32,
+    ) -> Vec<u8> {
+        let mut header = FSB3_MAGIC.to_vec();
+        header.extend(num_streams.to_le_bytes());
+        header.extend(headers_size.to_le_bytes());
+        header.extend(data_size.to_le_bytes());
+        header.extend(version.to_le_bytes());
+        header.extend(0_u32.to_le_bytes());
+        header
+    }
+
+    fn sample_header(name: &str, size: u32, mode: u32, channels: u16, extra: &[u8]) -> Vec<u8> {
+        let mut header = u16::try_from(0x50 + extra.len()).unwrap().to_le_bytes().to_vec();
+        let mut name = name.as_bytes().to_vec();
+        name.resize(30, 0);
+        header.extend(name);
+        header.extend(1000_u32.to_le_bytes()); // samples
+        header.extend(size.to_le_bytes());
+        header.extend(100_u32.to_le_bytes()); // loop start
+        header.extend(899_u32.to_le_bytes()); // loop end
+        header.extend(mode.to_le_bytes());
+        header.extend(22050_u32.to_le_bytes());
+        header.extend([0; 6]);
+        header.extend(channels.to_le_bytes());
+        header.resize(0x50, 0);
+        header.extend(extra);
+        header
+    }
+
+    #[test]
+    fn parse_full_sample_headers() {
+        let mut file = file_header(2, 160, 128, FLAG_BIG_ENDIAN_PCM);
+        file.extend(sample_header("intro", 30, MODE_16_BITS | MODE_LOOP_NORMAL, 2, &[]));
+        file.extend(sample_header(
+            "a_name_that_fills_the_field_xx",
+            64,
+            MODE_16_BITS,
+            1,
+            &[],
+        ));
+
+        let mut reader = Reader::new(file.as_slice());
+        let header = Header::parse(&mut reader).unwrap();
+        assert_eq!(reader.position(), 0x30 + 160);
+
+        assert_eq!(header.format, AudioFormat::Pcm16);
+        assert_eq!(header.flags, 0x01);
+        assert!(header.stream_info[0].flags().big_endian_pcm());
+
+        let [first, second] = &*header.stream_info else {
+            panic!("expected 2 streams");
+        };
+
+        assert_eq!(first.name.as_deref(), Some("intro"));
+        assert_eq!(first.channels.get(), 2);
+        assert_eq!(first.sample_rate.get(), 22050);
+        assert_eq!(first.num_samples.get(), 1000);
+        let stream_loop = first.stream_loop.unwrap();
+        assert_eq!((stream_loop.start(), stream_loop.end()), (100, 899));
+        // stream data is aligned to 32 bytes, since the total size is larger than the sum of stream sizes
+        assert_eq!(first.size, 32);
+
+        assert_eq!(second.name.as_deref(), Some("a_name_that_fills_the_field_xx"));
+        assert_eq!(second.stream_loop, None);
+        assert_eq!(second.size, 64);
+    }
+
+    #[test]
+    fn parse_hash_and_guid() {
+        let mut file = file_header(1, 80, 32, 0);
+        let metadata: Vec<u8> = (1..=24).collect();
+        file[0x18..0x30].copy_from_slice(&metadata);
+        file.extend(sample_header("", 32, MODE_16_BITS, 1, &[]));
+
+        let header = Header::parse(&mut Reader::new(file.as_slice())).unwrap();
+        assert_eq!((header.version, header.minor_version), (4, 0));
+        assert_eq!(*header.hash, metadata[..8]);
+        assert_eq!(header.guid.unwrap(), metadata[8..]);
+
+        // FSB3 banks have neither a hash nor a GUID
+        let mut file = fsb3_file_header(0x0003_0001, 1, 80, 32);
+        file.extend(sample_header("", 32, MODE_16_BITS, 1, &[]));
+
+        let header = Header::parse(&mut Reader::new(file.as_slice())).unwrap();
+        assert_eq!((header.version, header.minor_version), (3, 1));
+        assert!(header.hash.is_empty());
+        assert_eq!(header.guid, None);
+    }
+
+    #[test]
+    fn parse_big_endian_header() {
+        let mut file = file_header(1, 0x50, 32, 0);
+        file.extend(sample_header("big", 30, MODE_16_BITS | MODE_LOOP_NORMAL, 2, &[]));
+        let expected = Header::parse(&mut Reader::new(file.as_slice())).unwrap();
+
+        // byte-swap the signature and every number of the file header and sample header
+        file[..4].reverse();
+        for offset in [4, 8, 12, 16, 20] {
+            file[offset..offset + 4].reverse();
+        }
+        for (offset, len) in [
+            (0, 2),
+            (32, 4),
+            (36, 4),
+            (40, 4),
+            (44, 4),
+            (48, 4),
+            (52, 4),
+            (62, 2),
+        ] {
+            file[0x30 + offset..0x30 + offset + len].reverse();
+        }
+        assert_eq!(file[..4], *b"4BSF");
+
+        let header = Header::parse(&mut Reader::new(file.as_slice())).unwrap();
+        assert_eq!(header, expected);
+        assert_eq!(header.stream_info[0].name.as_deref(), Some("big"));
+    }
+
+    #[test]
+    fn parse_basic_sample_headers() {
+        let mut file = file_header(3, 96, 600, FLAG_BASIC_HEADERS);
+        file.extend(sample_header("", 200, MODE_MPEG | MODE_LOOP_NORMAL, 2, &[]));
+        for (samples, size) in [(500_u32, 300_u32), (10, 100)] {
+            file.extend(samples.to_le_bytes());
+            file.extend(size.to_le_bytes());
+        }
+
+        let header = Header::parse(&mut Reader::new(file.as_slice())).unwrap();
+        assert_eq!(header.format, AudioFormat::Mpeg);
+        assert_eq!(header.flags, 0x02);
+
+        let info = &header.stream_info[1];
+        assert_eq!(info.name, None);
+        assert_eq!(info.channels.get(), 2);
+        assert_eq!(info.sample_rate.get(), 22050);
+        assert_eq!(info.num_samples.get(), 500);
+        assert_eq!(info.size, 300);
+
+        // streams with basic headers loop over all of their samples
+        let stream_loop = info.stream_loop.unwrap();
+        assert_eq!(
+            (stream_loop.start(), stream_loop.len()),
+            (0, NonZeroU32::new(500).unwrap())
+        );
+        assert_eq!(header.stream_info[0].name, None);
+    }
+
+    #[test]
+    fn parse_dsp_coefficients() {
+        let mut extra = Vec::new();
+        for channel in 0..2_i16 {
+            for coefficient in 0..16 {
+                extra.extend((channel * 16 + coefficient).to_be_bytes());
+            }
+            extra.extend([0; 4]);
+            extra.extend((channel + 1).to_be_bytes());
+            extra.extend((-channel).to_be_bytes());
+            extra.extend([0; 6]);
+        }
+
+        let mut file = file_header(1, 172, 64, 0);
+        file.extend(sample_header("gc", 64, MODE_GC_ADPCM | MODE_16_BITS, 2, &extra));
+
+        let header = Header::parse(&mut Reader::new(file.as_slice())).unwrap();
+        assert_eq!(header.format, AudioFormat::GcAdpcm);
+
+        let coeffs = header.stream_info[0].dsp_coeffs.as_deref().unwrap();
+        assert_eq!(coeffs.len(), 2);
+        assert_eq!(coeffs[1].coefficients[0], 16);
+        assert_eq!((coeffs[1].hist1, coeffs[1].hist2), (2, -1));
+    }
+
+    #[test]
+    fn reject_invalid_headers() {
+        let mut file = file_header(1, 80, 64, 0);
+        file[16..20].copy_from_slice(&0x0003_0000_u32.to_le_bytes());
+        assert!(
+            Header::parse(&mut Reader::new(file.as_slice())).is_err_and(|e| e.kind()
+                == UnknownVersion {
+                    version: 0x0003_0000
+                })
+        );
+
+        let mut file = file_header(2, 160, 128, 0);
+        file.extend(sample_header("pcm", 64, MODE_16_BITS, 1, &[]));
+        file.extend(sample_header("mpeg", 64, MODE_MPEG, 1, &[]));
+        assert!(Header::parse(&mut Reader::new(file.as_slice()))
+            .is_err_and(|e| e.kind() == MixedAudioFormats { index: 1 }));
+
+        let mut file = file_header(1, 80, 64, 0);
+        file.extend(sample_header("silent", 64, MODE_16_BITS, 0, &[]));
+        assert!(Header::parse(&mut Reader::new(file.as_slice()))
+            .is_err_and(|e| e.is_stream_err_kind(ChannelCount { channels: 0 })));
+
+        let mut file = file_header(1, 80, 64, 0);
+        file.extend(sample_header("vag", 64, 0x0080_0000, 1, &[]));
+        assert!(Header::parse(&mut Reader::new(file.as_slice()))
+            .is_err_and(|e| e.kind() == UnknownAudioFormat { flag: 0x0080_0000 }));
+    }
+
+    #[test]
+    fn parse_fsb3_sample_headers() {
+        // FSB3.0 sample headers end after the number of channels
+        let mut sample = sample_header("fsb3.0", 30, MODE_8_BITS | MODE_LOOP_NORMAL, 1, &[]);
+        sample.truncate(0x40);
+        sample[..2].copy_from_slice(&0x40_u16.to_le_bytes());
+
+        let mut file = fsb3_file_header(0x0003_0000, 2, 0x80, 60);
+        file.extend(&sample);
+        file.extend(&sample);
+
+        let mut reader = Reader::new(file.as_slice());
+        let header = Header::parse(&mut reader).unwrap();
+        assert_eq!(reader.position(), 0x18 + 0x80);
+        assert_eq!(header.format, AudioFormat::Pcm8);
+        assert_eq!(header.stream_info.len(), 2);
+
+        let info = &header.stream_info[1];
+        assert_eq!(info.name.as_deref(), Some("fsb3.0"));
+        assert_eq!(info.channels.get(), 1);
+        assert!(info.stream_loop.is_some());
+        // stream data of FSB3 banks isn't aligned, even if the total size is larger than the sum of stream sizes
+        assert_eq!(header.stream_info[0].size, 30);
+
+        let mut file = fsb3_file_header(0x0003_0001, 1, 0x50, 64);
+        file.extend(sample_header("fsb3.1", 64, MODE_MPEG, 2, &[]));
+
+        let mut reader = Reader::new(file.as_slice());
+        let header = Header::parse(&mut reader).unwrap();
+        assert_eq!(reader.position(), 0x18 + 0x50);
+        assert_eq!(header.format, AudioFormat::Mpeg);
+        assert_eq!(header.stream_info[0].name.as_deref(), Some("fsb3.1"));
+
+        let file = fsb3_file_header(0x0003_0002, 1, 0x50, 64);
+        assert!(
+            Header::parse(&mut Reader::new(file.as_slice())).is_err_and(|e| e.kind()
+                == UnknownVersion {
+                    version: 0x0003_0002
+                })
+        );
+
+        // FSB3 banks don't use FSB4 version numbers
+        let file = fsb3_file_header(0x0004_0000, 1, 0x50, 64);
+        assert!(
+            Header::parse(&mut Reader::new(file.as_slice())).is_err_and(|e| e.kind()
+                == UnknownVersion {
+                    version: 0x0004_0000
+                })
+        );
+    }
+}
@@ -1,91 +1,148 @@
-use crate::read::Reader;
+use crate::encode::{
+    atrac9_superframe_size, celt_version, check_decode, CeltVersion, EncodeError, EncodeOptions,
+};
+use crate::read::{ReadError, Reader};
 pub(crate) mod error;
+mod file_name;
+mod flags;
+mod legacy;
+mod offsets;
+mod options;
 use bilge::prelude::*;
 use error::{
     ChunkError, ChunkErrorKind, HeaderError, HeaderErrorKind, NameError, NameErrorKind,
     StreamError, StreamErrorKind,
 };
 use std::{
+    cmp::min,
+    error::Error,
     ffi::CStr,
     fmt::{Display, Formatter, Result as FmtResult},
     io::Read,
     iter::zip,
     num::{NonZeroU32, NonZeroU8},
     ops::Mul,
+    str::FromStr,
+    time::Duration,
 };
 use tap::Pipe;
 
+pub use file_name::{sanitize_file_name, sanitize_file_path};
+pub use flags::BankFlags;
+pub use offsets::{OffsetWarning, OffsetWarningKind};
+pub use options::ParseOptions;
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub(crate) struct Header {
+    pub(crate) version: u8,
+    pub(crate) minor_version: u32,
     pub(crate) format: AudioFormat,
     pub(crate) flags: u32,
+    pub(crate) hash: Box<[u8]>,
+    pub(crate) guid: Option<[u8; 16]>,
     pub(crate) stream_info: Box<[StreamInfo]>,
+    pub(crate) offset_warnings: Box<[OffsetWarning]>,
 }
 
 impl Header {
+    #[cfg(test)]
     pub(crate) fn parse<R: Read>(reader: &mut Reader<R>) -> Result<Self, HeaderError> {
-        // check for file signature
-        match reader.take_const() {
-            Ok(data) if data == FSB5_MAGIC => Ok(()),
-            Err(e) => Err(HeaderError::new_with_source(HeaderErrorKind::Magic, e)),
+        Self::parse_with_options(reader, ParseOptions::default())
+    }
+
+    pub(crate) fn parse_with_options<R: Read>(
+        reader: &mut Reader<R>,
+        options: ParseOptions,
+    ) -> Result<Self, HeaderError> {
+        // Check for file signature; FSB3 and FSB4 banks have a different layout, so they're parsed separately.
+        // Big-endian banks (made for some consoles) have a byte-swapped signature, and every number
+        // in their file header is big-endian.
+        let mut magic: [u8; 4] = reader
+            .take_const()
+            .map_err(HeaderError::factory(HeaderErrorKind::Magic))?;
+
+        if matches!(magic, FSB5_MAGIC_BE | legacy::FSB3_MAGIC_BE | legacy::FSB4_MAGIC_BE) {
+            magic.reverse();
+            reader.set_big_endian(true);
+        }
+
+        match magic {
+            FSB5_MAGIC => Ok(()),
+            legacy::FSB3_MAGIC | legacy::FSB4_MAGIC => return legacy::parse(reader, magic),
             _ => Err(HeaderError::new(HeaderErrorKind::Magic)),
         }?;
 
         // determines how encoding flags are read
-        let version = reader
-            .le_u32()
-            .map_err(HeaderError::factory(HeaderErrorKind::Version))?
-            .try_into()?;
+        let minor_version = reader.u32().map_err(HeaderError::factory(HeaderErrorKind::Version))?;
+        let version = Version::try_from(minor_version)?;
 
         let num_streams = reader
-            .le_u32()
+            .u32()
             .map_err(HeaderError::factory(HeaderErrorKind::StreamCount))?
             .try_into()
             .map_err(|_| HeaderError::new(HeaderErrorKind::ZeroStreams))?;
 
         let stream_headers_size = reader
-            .le_u32()
+            .u32()
             .map_err(HeaderError::factory(HeaderErrorKind::StreamHeadersSize))?;
 
         let name_table_size = reader
-            .le_u32()
+            .u32()
             .map_err(HeaderError::factory(HeaderErrorKind::NameTableSize))?;
 
         let total_stream_size = reader
-            .le_u32()
+            .u32()
             .map_err(HeaderError::factory(HeaderErrorKind::TotalStreamSize))?
             .try_into()
             .map_err(|_| HeaderError::new(HeaderErrorKind::ZeroTotalStreamSize))?;
 
         let format = reader
-            .le_u32()
+            .u32()
             .map_err(HeaderError::factory(HeaderErrorKind::AudioFormat))
-            .and_then(AudioFormat::parse)?;
+            .map(AudioFormat::parse)?;
 
-        // read encoding flags
-        let (flags, base_header_size) = match version {
-            Version::V0 => (0, 64),
+        // Read encoding flags.
+        // Version 0 headers (from early FMOD Studio versions) have no encoding flags, but an extra unknown field,
+        // so the hash and stream headers start 4 bytes later. Stream headers and chunks have the same layout in both versions.
+        let (flags, hash_offset, base_header_size) = match version {
+            Version::V0 => (0, 0x28, 64),
             Version::V1 => {
                 reader
                     .skip(4)
                     .map_err(HeaderError::factory(HeaderErrorKind::EncodingFlags))?;
 
                 let flags = reader
-                    .le_u32()
+                    .u32()
                     .map_err(HeaderError::factory(HeaderErrorKind::EncodingFlags))?;
 
-                (flags, 60)
+                (flags, 0x24, 60)
             }
         };
 
+        // The hash is kept as stored, since it's used to tell apart builds of the same bank.
+        reader
+            .advance_to(hash_offset)
+            .map_err(HeaderError::factory(HeaderErrorKind::Metadata))?;
+
+        let hash: [u8; 16] = reader
+            .take_const()
+            .map_err(HeaderError::factory(HeaderErrorKind::Metadata))?;
+
         // skip unknown header data
         reader
             .advance_to(base_header_size)
             .map_err(HeaderError::factory(HeaderErrorKind::Metadata))?;
 
-        let mut stream_info = parse_stream_headers(reader, num_streams, total_stream_size)?;
+        let (mut stream_info, offset_warnings) = parse_stream_headers(
+            reader,
+            num_streams,
+            total_stream_size,
+            format,
+            BankFlags::new(flags),
+            options,
+        )?;
 
-        let header_size = base_header_size + stream_headers_size as usize;
+        let header_size = base_header_size + u64::from(stream_headers_size);
 
         // make sure base header + stream headers have been read
         reader.advance_to(header_size).map_err(HeaderError::factory(
@@ -97,34 +154,52 @@ impl Header {
 
         // Read stream names, if present.
         // The name table has two parts: name offsets, then names (stored as null-terminated strings).
-        // Differences in consecutive offsets are calculated to get the actual name lengths:
-        // for example, if the first name offset is 0 and the second name offset is 12,
-        // then the first name's length (including the null terminator) is 12 - 0 = 12.
-        // The final name offset is subtracted from the name table size to get the final name's length.
+        // Offsets are relative to the start of the name table, so the first name usually starts
+        // right after the offsets. Names aren't required to be stored in order of streams.
         if name_table_size != 0 {
-            let mut name_offsets = Vec::with_capacity(num_streams.get() as usize + 1);
+            let mut name_offsets = Vec::with_capacity(stream_info.len());
 
             for index in 0..num_streams.get() {
                 let offset = reader
-                    .le_u32()
+                    .u32()
                     .map_err(NameError::read_factory(index, NameErrorKind::NameOffset))?;
 
                 name_offsets.push(offset);
             }
-            name_offsets.push(name_table_size);
 
-            read_stream_names(reader, &name_offsets, &mut stream_info)?;
+            read_stream_names(reader, name_table_size, &name_offsets, &mut stream_info)?;
         }
 
+        file_name::assign_stems(&mut stream_info);
+
         Ok(Self {
+            version: 5,
+            minor_version,
             format,
             flags,
+            hash: hash.into(),
+            guid: None,
             stream_info: stream_info.into_boxed_slice(),
+            offset_warnings: offset_warnings.into_boxed_slice(),
         })
     }
 }
 
-const FSB5_MAGIC: [u8; 4] = *b"FSB5";
+pub(crate) const FSB5_MAGIC: [u8; 4] = *b"FSB5";
+const FSB5_MAGIC_BE: [u8; 4] = *b"5BSF";
+
+// Returns whether `magic` is the file signature of a sound bank of any supported version or byte order.
+pub(crate) fn is_magic(magic: [u8; 4]) -> bool {
+    matches!(
+        magic,
+        FSB5_MAGIC
+            | FSB5_MAGIC_BE
+            | legacy::FSB3_MAGIC
+            | legacy::FSB4_MAGIC
+            | legacy::FSB3_MAGIC_BE
+            | legacy::FSB4_MAGIC_BE
+    )
+}
 
 enum Version {
     V0,
@@ -143,11 +218,28 @@ impl TryFrom<u32> for Version {
     }
 }
 
-/// Represents known audio formats of streams within a sound bank.
+/// Represents audio formats of streams within a sound bank.
+///
+/// Formats are displayed as short lowercase names (such as `pcm16`, `mpeg`, or `vorbis`),
+/// which can be parsed back with [`FromStr`], ignoring case. A longer description is returned by
+/// [`description`](Self::description).
+///
+/// # Examples
+///
+/// ```
+/// use fsbex::AudioFormat;
+///
+/// assert_eq!(AudioFormat::FAdpcm.to_string(), "fadpcm");
+/// assert_eq!("MPEG".parse(), Ok(AudioFormat::Mpeg));
+/// assert_eq!("unknown(99)".parse(), Ok(AudioFormat::Unknown(99)));
+/// assert!("mp3".parse::<AudioFormat>().is_err());
+/// ```
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 #[non_exhaustive]
 pub enum AudioFormat {
     /// PCM with 8-bit integer samples.
+    /// Samples are stored as signed integers, and they are converted to unsigned integers when encoded,
+    /// since WAVE files store 8-bit samples as unsigned. This means encoded stream data differs from the original data.
     Pcm8,
     /// PCM with 16-bit integer samples.
     Pcm16,
@@ -175,6 +267,8 @@ pub enum AudioFormat {
     Mpeg,
     /// CELT, developed by the [Xiph.Org Foundation](https://en.wikipedia.org/wiki/Xiph.Org_Foundation).
     /// The CELT format is obsolete, and its functionality has been merged into Opus.
+    /// Streams in FSB5 sound banks are always encoded with CELT 0.11;
+    /// older, incompatible CELT versions were only used by earlier sound bank versions.
     Celt,
     /// ATRAC9, used in PlayStation games and debuting with the PS Vita.
     /// ATRAC9 is part of the ATRAC family of audio formats.
@@ -189,36 +283,45 @@ pub enum AudioFormat {
     /// Opus, developed by the [Xiph.Org Foundation](https://en.wikipedia.org/wiki/Xiph.Org_Foundation).
     /// Opus is intended to replace older Xiph.Org formats such as Vorbis.
     Opus,
+    /// A format that isn't known, with the format value stored in the file header.
+    /// Streams of unknown formats can only be written as raw stream data.
+    Unknown(u32),
 }
 
 impl AudioFormat {
-    fn parse(value: u32) -> Result<Self, HeaderError> {
-        match value {
-            1 => Ok(Self::Pcm8),
-            2 => Ok(Self::Pcm16),
-            3 => Ok(Self::Pcm24),
-            4 => Ok(Self::Pcm32),
-            5 => Ok(Self::PcmFloat),
-            6 => Ok(Self::GcAdpcm),
-            7 => Ok(Self::ImaAdpcm),
-            8 => Ok(Self::Vag),
-            9 => Ok(Self::HeVag),
-            10 => Ok(Self::Xma),
-            11 => Ok(Self::Mpeg),
-            12 => Ok(Self::Celt),
-            13 => Ok(Self::Atrac9),
-            14 => Ok(Self::Xwma),
-            15 => Ok(Self::Vorbis),
-            16 => Ok(Self::FAdpcm),
-            17 => Ok(Self::Opus),
-            flag => Err(HeaderError::new(HeaderErrorKind::UnknownAudioFormat { flag })),
-        }
+    const KNOWN: [Self; 17] = [
+        Self::Pcm8,
+        Self::Pcm16,
+        Self::Pcm24,
+        Self::Pcm32,
+        Self::PcmFloat,
+        Self::GcAdpcm,
+        Self::ImaAdpcm,
+        Self::Vag,
+        Self::HeVag,
+        Self::Xma,
+        Self::Mpeg,
+        Self::Celt,
+        Self::Atrac9,
+        Self::Xwma,
+        Self::Vorbis,
+        Self::FAdpcm,
+        Self::Opus,
+    ];
+
+    /// Returns whether streams of this format can be encoded by this build of the crate.
+    /// Streams of unknown formats, and Vorbis streams without the `decode-vorbis` feature,
+    /// can only be written as raw stream data.
+    #[must_use]
+    pub fn is_supported(self) -> bool {
+        let vorbis_supported = cfg!(feature = "decode-vorbis");
+        !matches!(self, Self::Unknown(_)) && (self != Self::Vorbis || vorbis_supported)
     }
-}
 
-impl Display for AudioFormat {
-    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        f.write_str(match self {
+    /// Returns a longer, human-readable description of the format, such as `PCM (16-bit, integer)`.
+    #[must_use]
+    pub fn description(self) -> &'static str {
+        match self {
             Self::Pcm8 => "PCM (8-bit, integer)",
             Self::Pcm16 => "PCM (16-bit, integer)",
             Self::Pcm24 => "PCM (24-bit, integer)",
@@ -236,59 +339,212 @@ impl Display for AudioFormat {
             Self::Vorbis => "Vorbis",
             Self::FAdpcm => "FADPCM",
             Self::Opus => "Opus",
-        })
+            Self::Unknown(_) => "unknown format",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Pcm8 => "pcm8",
+            Self::Pcm16 => "pcm16",
+            Self::Pcm24 => "pcm24",
+            Self::Pcm32 => "pcm32",
+            Self::PcmFloat => "pcmfloat",
+            Self::GcAdpcm => "gcadpcm",
+            Self::ImaAdpcm => "imaadpcm",
+            Self::Vag => "vag",
+            Self::HeVag => "hevag",
+            Self::Xma => "xma",
+            Self::Mpeg => "mpeg",
+            Self::Celt => "celt",
+            Self::Atrac9 => "atrac9",
+            Self::Xwma => "xwma",
+            Self::Vorbis => "vorbis",
+            Self::FAdpcm => "fadpcm",
+            Self::Opus => "opus",
+            Self::Unknown(_) => "unknown",
+        }
+    }
+
+    // Used to check whether data looks like a file header, where unknown format values are unlikely.
+    pub(crate) fn parse_known(value: u32) -> Option<Self> {
+        Some(Self::parse(value)).filter(|format| !matches!(format, Self::Unknown(_)))
+    }
+
+    // Unknown format values are kept, so that streams can still be written as raw stream data.
+    pub(crate) fn parse(value: u32) -> Self {
+        match value {
+            1 => Self::Pcm8,
+            2 => Self::Pcm16,
+            3 => Self::Pcm24,
+            4 => Self::Pcm32,
+            5 => Self::PcmFloat,
+            6 => Self::GcAdpcm,
+            7 => Self::ImaAdpcm,
+            8 => Self::Vag,
+            9 => Self::HeVag,
+            10 => Self::Xma,
+            11 => Self::Mpeg,
+            12 => Self::Celt,
+            13 => Self::Atrac9,
+            14 => Self::Xwma,
+            15 => Self::Vorbis,
+            16 => Self::FAdpcm,
+            17 => Self::Opus,
+            value => Self::Unknown(value),
+        }
+    }
+
+    // The inverse of `AudioFormat::parse`, giving the format value stored in FSB5 file headers.
+    #[cfg(feature = "ffi")]
+    pub(crate) fn value(self) -> u32 {
+        match self {
+            Self::Pcm8 => 1,
+            Self::Pcm16 => 2,
+            Self::Pcm24 => 3,
+            Self::Pcm32 => 4,
+            Self::PcmFloat => 5,
+            Self::GcAdpcm => 6,
+            Self::ImaAdpcm => 7,
+            Self::Vag => 8,
+            Self::HeVag => 9,
+            Self::Xma => 10,
+            Self::Mpeg => 11,
+            Self::Celt => 12,
+            Self::Atrac9 => 13,
+            Self::Xwma => 14,
+            Self::Vorbis => 15,
+            Self::FAdpcm => 16,
+            Self::Opus => 17,
+            Self::Unknown(value) => value,
+        }
+    }
+}
+
+impl Display for AudioFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Unknown(value) => f.write_fmt(format_args!("unknown({value})")),
+            _ => f.write_str(self.name()),
+        }
+    }
+}
+
+impl FromStr for AudioFormat {
+    type Err = ParseAudioFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(value) = s
+            .get(..8)
+            .filter(|prefix| prefix.eq_ignore_ascii_case("unknown("))
+            .and_then(|_| s[8..].strip_suffix(')'))
+        {
+            return value
+                .parse()
+                .map(Self::Unknown)
+                .map_err(|_| ParseAudioFormatError(()));
+        }
+
+        Self::KNOWN
+            .into_iter()
+            .find(|format| format.name().eq_ignore_ascii_case(s))
+            .ok_or(ParseAudioFormatError(()))
+    }
+}
+
+/// Represents an error that can occur when parsing an [`AudioFormat`] from its name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ParseAudioFormatError(());
+
+impl Display for ParseAudioFormatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str("name of audio format was not recognized")
     }
 }
 
+impl Error for ParseAudioFormatError {}
+
+const MAX_PREALLOCATED_STREAMS: usize = 4096;
+
 fn parse_stream_headers<R: Read>(
     reader: &mut Reader<R>,
     num_streams: NonZeroU32,
     total_stream_size: NonZeroU32,
-) -> Result<Vec<StreamInfo>, HeaderError> {
+    format: AudioFormat,
+    flags: BankFlags,
+    options: ParseOptions,
+) -> Result<(Vec<StreamInfo>, Vec<OffsetWarning>), HeaderError> {
     let num_streams_usize = num_streams.get() as usize;
 
-    let mut stream_headers = Vec::with_capacity(num_streams_usize);
-    let mut stream_offsets = Vec::with_capacity(num_streams_usize + 1);
+    // The number of streams hasn't been validated yet, so it isn't trusted for preallocation.
+    let capacity = min(num_streams_usize, MAX_PREALLOCATED_STREAMS);
+    let mut stream_headers: Vec<StreamHeader> = Vec::with_capacity(capacity);
+    let mut stream_offsets = Vec::with_capacity(capacity + 1);
 
     for index in 0..num_streams.get() {
         // Stream headers contain information such as sample rate (Hz) and number of channels.
         // They can also contain metadata chunks useful for decoding and encoding stream data.
         // Sometimes, flags for header fields are set to 0 while the actual values are stored in chunks.
-        let mut stream_header = match reader.le_u64() {
-            Ok(n) => RawStreamHeader::from(n).parse(index),
-            Err(e) => Err(StreamError::new_with_source(index, StreamErrorKind::StreamInfo, e)),
-        }?;
-
-        if stream_header.has_chunks {
-            parse_stream_chunks(reader, &mut stream_header)
-                .map_err(|e| e.into_stream_err(index))?;
-        }
+        let raw_header = reader
+            .u64()
+            .map(RawStreamHeader::from)
+            .map_err(|e| StreamError::new_with_source(index, StreamErrorKind::StreamInfo, e))?;
+
+        // Banks with basic headers only have a full header for the first stream.
+        // Other streams only store their data offset and length, and share everything else with the first stream.
+        let stream_header = match stream_headers.first() {
+            Some(first) if flags.basic_headers() => raw_header.parse_basic(index, first)?,
+            _ => parse_full_stream_header(reader, index, raw_header, options)?,
+        };
 
         stream_offsets.push(stream_header.data_offset);
         stream_headers.push(stream_header);
     }
-    stream_offsets.push(total_stream_size.get());
 
     // Only stream offsets are stored in stream headers, so they are processed to get stream lengths.
-    // Stream lengths are calculated the same way as name lengths in the name table.
+    // Stream lengths are calculated the same way as name lengths in the name table,
+    // after making sure that offsets don't go backwards or past the end of stream data.
+    let data_size = total_stream_size.get().into();
+    let warnings = offsets::validate(&mut stream_offsets, data_size, options.lenient_offsets)?;
+    stream_offsets.push(data_size);
 
     let mut stream_info = Vec::with_capacity(num_streams_usize);
 
-    for ((size, header), index) in zip(
-        stream_offsets.windows(2).map(|window| window[1] - window[0]),
-        stream_headers,
-    )
-    .zip(0..)
-    {
-        stream_info.push(
-            header.with_stream_size(
-                size.try_into()
-                    .map_err(|_| HeaderError::new(HeaderErrorKind::ZeroStreamSize { index }))?,
-            ),
-        );
+    // Placeholder streams (e.g. for disabled assets) have no data, so their size is 0 bytes.
+    for (window, header) in zip(stream_offsets.windows(2), stream_headers) {
+        // offsets are at most the total size of stream data, so sizes between them fit in a u32
+        let size = u32::try_from(window[1] - window[0])
+            .expect("offsets were validated to be ordered and within stream data");
+
+        stream_info.push(header.with_data(format, flags, window[0], size));
+    }
+
+    Ok((stream_info, warnings))
+}
+
+fn parse_full_stream_header<R: Read>(
+    reader: &mut Reader<R>,
+    index: u32,
+    raw_header: RawStreamHeader,
+    options: ParseOptions,
+) -> Result<StreamHeader, HeaderError> {
+    let sample_rate_flag = raw_header.sample_rate().value();
+    let mut stream_header = raw_header.parse(index)?;
+
+    if stream_header.has_chunks {
+        parse_stream_chunks(reader, &mut stream_header, options)
+            .map_err(|e| e.into_stream_err(index))?;
+    }
+
+    // Sample rates without a flag of their own are stored in a chunk, which overrides the flag.
+    if stream_header.sample_rate.is_none() {
+        let kind = StreamErrorKind::UnknownSampleRate {
+            flag: sample_rate_flag,
+        };
+        return Err(StreamError::new(index, kind).into());
     }
 
-    Ok(stream_info)
+    Ok(stream_header)
 }
 
 #[bitsize(64)]
@@ -301,40 +557,44 @@ struct RawStreamHeader {
     num_samples: u30,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 #[cfg_attr(test, derive(PartialEq, Eq))]
 struct StreamHeader {
     has_chunks: bool,
-    sample_rate: NonZeroU32,
+    sample_rate: Option<NonZeroU32>,
     channels: NonZeroU8,
-    data_offset: u32,
+    data_offset: u64,
     num_samples: NonZeroU32,
     stream_loop: Option<Loop>,
-    dsp_coeffs: Option<Box<[i16]>>,
+    dsp_coeffs: Option<Box<[DspCoeffs]>>,
+    xma_seek_table: Option<Box<[u32]>>,
+    atrac9_config: Option<Box<[[u8; 4]]>>,
+    xwma_config: Option<XwmaConfig>,
     vorbis_crc32: Option<u32>,
+    vorbis_seek_table: Option<Box<[VorbisSeekEntry]>>,
+    peak_volume: Option<u32>,
+    unknown_chunks: Vec<(u32, Vec<u8>)>,
+    channels_overridden: bool,
 }
 
 impl RawStreamHeader {
     fn parse(self, stream_index: u32) -> Result<StreamHeader, StreamError> {
         let sample_rate = match self.sample_rate().value() {
-            0 => Ok(4000),
-            1 => Ok(8000),
-            2 => Ok(11000),
-            3 => Ok(11025),
-            4 => Ok(16000),
-            5 => Ok(22050),
-            6 => Ok(24000),
-            7 => Ok(32000),
-            8 => Ok(44100),
-            9 => Ok(48000),
-            10 => Ok(96000),
-            flag => Err(StreamError::new(
-                stream_index,
-                StreamErrorKind::UnknownSampleRate { flag },
-            )),
-        }?
-        .try_into()
-        .unwrap();
+            0 => Some(4000),
+            1 => Some(8000),
+            2 => Some(11000),
+            3 => Some(11025),
+            4 => Some(16000),
+            5 => Some(22050),
+            6 => Some(24000),
+            7 => Some(32000),
+            8 => Some(44100),
+            9 => Some(48000),
+            10 => Some(96000),
+            // reserved for sample rates stored in a chunk
+            _ => None,
+        }
+        .and_then(NonZeroU32::new);
 
         let channels = match self.channels().value() {
             0 => 1,
@@ -358,90 +618,208 @@ impl RawStreamHeader {
             has_chunks: self.has_chunks(),
             sample_rate,
             channels,
-            data_offset: self.data_offset().value() * 32,
+            data_offset: u64::from(self.data_offset().value()) * 32,
             num_samples,
             stream_loop: None,
             dsp_coeffs: None,
+            xma_seek_table: None,
+            atrac9_config: None,
+            xwma_config: None,
             vorbis_crc32: None,
+            vorbis_seek_table: None,
+            peak_volume: None,
+            unknown_chunks: Vec::new(),
+            channels_overridden: false,
+        })
+    }
+
+    // Basic stream headers can't have chunks, and their other fields are ignored.
+    // Loops are specific to the samples of a stream, so they aren't shared.
+    fn parse_basic(
+        self,
+        stream_index: u32,
+        first: &StreamHeader,
+    ) -> Result<StreamHeader, StreamError> {
+        let num_samples = self
+            .num_samples()
+            .value()
+            .try_into()
+            .map_err(|_| StreamError::new(stream_index, StreamErrorKind::ZeroSamples))?;
+
+        Ok(StreamHeader {
+            has_chunks: false,
+            data_offset: u64::from(self.data_offset().value()) * 32,
+            num_samples,
+            stream_loop: None,
+            ..first.clone()
         })
     }
 }
 
+// Streams with more channels than this are assumed to be corrupt.
+const MAX_CHANNELS: u8 = 32;
+
+// The channel count of stream headers can't express more than 8 channels, so larger counts are stored in a chunk.
+fn read_channels_chunk<R: Read>(
+    reader: &mut Reader<R>,
+    index: u32,
+) -> Result<NonZeroU8, ChunkError> {
+    let channels = reader
+        .u8()
+        .map_err(ChunkError::factory(index, ChunkErrorKind::ChannelCount))?;
+
+    if channels > MAX_CHANNELS {
+        return Err(ChunkError::new(index, ChunkErrorKind::TooManyChannels { channels }));
+    }
+
+    channels
+        .try_into()
+        .map_err(|_| ChunkError::new(index, ChunkErrorKind::ZeroChannels))
+}
+
+// Seek tables are made of 32-bit entries; empty seek tables are allowed.
+fn read_xma_seek_table<R: Read>(
+    reader: &mut Reader<R>,
+    index: u32,
+    size: u32,
+) -> Result<Box<[u32]>, ChunkError> {
+    if !size.is_multiple_of(4) {
+        return Err(ChunkError::new(index, ChunkErrorKind::XmaSeekTableSize { size }));
+    }
+
+    (0..size / 4)
+        .map(|_| reader.u32())
+        .collect::<Result<Box<_>, _>>()
+        .map_err(ChunkError::factory(index, ChunkErrorKind::XmaSeekTable))
+}
+
+// Newer banks store the frame size before the configuration data, which isn't needed.
+// Streams with more than 2 channels can be made up of multiple layers, each with its own config.
+fn read_atrac9_config<R: Read>(
+    reader: &mut Reader<R>,
+    index: u32,
+    mut size: u32,
+) -> Result<Box<[[u8; 4]]>, ChunkError> {
+    // configuration data always starts with this byte
+    const SYNC_BYTE: u8 = 0xFE;
+
+    if size == 0x0C || size == 0x18 {
+        reader
+            .skip(4)
+            .map_err(ChunkError::factory(index, ChunkErrorKind::Atrac9Config))?;
+        size -= 4;
+    }
+
+    let configs = (0..size / 4)
+        .map(|_| reader.take_const())
+        .collect::<Result<Box<[[u8; 4]]>, _>>()
+        .map_err(ChunkError::factory(index, ChunkErrorKind::Atrac9Config))?;
+
+    match configs.iter().find(|config| config[0] != SYNC_BYTE) {
+        Some(&[byte, ..]) => Err(ChunkError::new(index, ChunkErrorKind::Atrac9SyncByte { byte })),
+        None => Ok(configs),
+    }
+}
+
+// Some banks store seek entries after the checksum, which aren't needed for decoding or encoding.
+fn read_vorbis_seek_table<R: Read>(
+    reader: &mut Reader<R>,
+    index: u32,
+    size: u32,
+) -> Result<(u32, Box<[VorbisSeekEntry]>), ChunkError> {
+    let crc32 = reader
+        .u32()
+        .map_err(ChunkError::factory(index, ChunkErrorKind::VorbisCrc32))?;
+
+    let seek_table = (0..size.saturating_sub(4) / 8)
+        .map(|_| {
+            Ok(VorbisSeekEntry {
+                granule: reader.u32()?,
+                offset: reader.u32()?,
+            })
+        })
+        .collect::<Result<Box<_>, ReadError>>()
+        .map_err(ChunkError::factory(index, ChunkErrorKind::VorbisSeekTable))?;
+
+    Ok((crc32, seek_table))
+}
+
 fn parse_stream_chunks<R: Read>(
     reader: &mut Reader<R>,
     stream: &mut StreamHeader,
+    options: ParseOptions,
 ) -> Result<(), ChunkError> {
-    use crate::header::Loop;
+    use crate::header::{Loop, XwmaConfig};
     use StreamChunkKind::*;
 
     for index in 0.. {
-        let chunk = match reader.le_u32() {
-            Ok(n) => RawStreamChunk::from(n).parse(index),
-            Err(e) => Err(ChunkError::new_with_source(index, ChunkErrorKind::Flag, e)),
-        }?;
+        let raw_chunk = reader
+            .u32()
+            .map(RawStreamChunk::from)
+            .map_err(|e| ChunkError::new_with_source(index, ChunkErrorKind::Flag, e))?;
+
+        // chunks of unknown types are kept instead of being rejected, if enabled
+        let chunk = match raw_chunk.parse(index) {
+            Err(_) if options.unknown_chunks => raw_chunk.unknown(),
+            chunk => chunk?,
+        };
 
         let start_position = reader.position();
 
         match chunk.kind {
             Channels => {
-                stream.channels = reader
-                    .u8()
-                    .map_err(ChunkError::factory(index, ChunkErrorKind::ChannelCount))?
-                    .try_into()
-                    .map_err(|_| ChunkError::new(index, ChunkErrorKind::ZeroChannels))?;
+                stream.channels = read_channels_chunk(reader, index)?;
+                stream.channels_overridden = true;
             }
             SampleRate => {
                 stream.sample_rate = reader
-                    .le_u32()
+                    .u32()
                     .map_err(ChunkError::factory(index, ChunkErrorKind::SampleRate))?
-                    .try_into()
-                    .map_err(|_| ChunkError::new(index, ChunkErrorKind::ZeroSampleRate))?;
+                    .pipe(NonZeroU32::new)
+                    .ok_or_else(|| ChunkError::new(index, ChunkErrorKind::ZeroSampleRate))?
+                    .pipe(Some);
             }
             Loop => {
-                let start = reader
-                    .le_u32()
-                    .map_err(ChunkError::factory(index, ChunkErrorKind::LoopStart))?;
-
-                let end = reader
-                    .le_u32()
-                    .map_err(ChunkError::factory(index, ChunkErrorKind::LoopEnd))?;
-
-                stream.stream_loop = Some(Loop::parse(index, start, end)?);
+                stream.stream_loop = Some(Loop::read(reader, index, stream.num_samples)?);
             }
             DspCoefficients => {
-                // used for decoding and encoding GC ADPCM streams
-
-                let channels = stream.channels.get();
-
-                let mut dsp_coeffs = Vec::with_capacity(channels as usize);
-
-                for _ in 0..channels {
-                    let mut coeff = 0;
+                // used for decoding and encoding GC ADPCM streams; each channel has its own coefficients
 
-                    for _ in 0..16 {
-                        coeff += reader
-                            .be_i16()
-                            .map_err(ChunkError::factory(index, ChunkErrorKind::DspCoefficients))?;
-                    }
-
-                    reader
-                        .skip(14)
-                        .map_err(ChunkError::factory(index, ChunkErrorKind::DspCoefficients))?;
-
-                    dsp_coeffs.push(coeff);
-                }
+                stream.dsp_coeffs = (0..stream.channels.get())
+                    .map(|_| DspCoeffs::parse(reader))
+                    .collect::<Result<Box<_>, _>>()
+                    .map_err(ChunkError::factory(index, ChunkErrorKind::DspCoefficients))?
+                    .pipe(Some);
+            }
+            XmaSeekTable => {
+                // used for building the seek chunk of XMA2 RIFF files
+                stream.xma_seek_table = Some(read_xma_seek_table(reader, index, chunk.size)?);
+            }
+            Atrac9Config => {
+                // used for building the header of ATRAC9 RIFF files
+                stream.atrac9_config = Some(read_atrac9_config(reader, index, chunk.size)?);
+            }
+            XwmaConfig => {
+                // used for building the header of xWMA RIFF files
 
-                stream.dsp_coeffs = Some(dsp_coeffs.into_boxed_slice());
+                stream.xwma_config = XwmaConfig::parse(reader, chunk.size)
+                    .map_err(ChunkError::factory(index, ChunkErrorKind::XwmaConfig))?
+                    .pipe(Some);
             }
             VorbisSeekTable => {
                 // Vorbis is a variable bitrate codec, so seek tables are used to seek to specific times.
                 // This chunk starts with the CRC32 checksum of a Vorbis setup header.
                 // When encoding this stream, the checksum is used to recover the original setup header.
-                // The seek table is discarded because it isn't useful for stream decoding or encoding.
 
-                stream.vorbis_crc32 = reader
-                    .le_u32()
-                    .map_err(ChunkError::factory(index, ChunkErrorKind::VorbisCrc32))?
+                let (crc32, seek_table) = read_vorbis_seek_table(reader, index, chunk.size)?;
+                stream.vorbis_crc32 = Some(crc32);
+                stream.vorbis_seek_table = Some(seek_table);
+            }
+            PeakVolume => {
+                // used by FMOD for loudness normalization; stored as bits so that stream information can be compared
+                stream.peak_volume = reader
+                    .u32()
+                    .map_err(ChunkError::factory(index, ChunkErrorKind::PeakVolume))?
                     .pipe(Some);
             }
             VorbisIntraLayers => {
@@ -449,7 +827,7 @@ fn parse_stream_chunks<R: Read>(
                 // For decoding and encoding purposes, layers simply mean that more channels are present.
 
                 let layers = reader
-                    .le_u32()
+                    .u32()
                     .map_err(ChunkError::factory(index, ChunkErrorKind::VorbisLayerCount))?;
 
                 stream.channels = layers
@@ -461,12 +839,18 @@ fn parse_stream_chunks<R: Read>(
                     .try_into()
                     .map_err(|_| ChunkError::new(index, ChunkErrorKind::ZeroVorbisLayers))?;
             }
+            Unknown { flag } => {
+                let data = reader
+                    .take(min(chunk.size as usize, options.max_unknown_chunk_size))
+                    .map_err(ChunkError::factory(index, ChunkErrorKind::UnknownChunk))?;
+                stream.unknown_chunks.push((flag.into(), data));
+            }
             _ => {}
         }
 
         // make sure the entire chunk has been read before continuing
         reader
-            .advance_to(start_position + chunk.size as usize)
+            .advance_to(start_position + u64::from(chunk.size))
             .map_err(ChunkError::factory(
                 index,
                 ChunkErrorKind::WrongChunkSize {
@@ -484,7 +868,7 @@ fn parse_stream_chunks<R: Read>(
 }
 
 #[bitsize(32)]
-#[derive(FromBits)]
+#[derive(Clone, Copy, FromBits)]
 struct RawStreamChunk {
     more_chunks: bool,
     size: u24,
@@ -510,6 +894,7 @@ enum StreamChunkKind {
     PeakVolume,
     VorbisIntraLayers,
     OpusDataSize,
+    Unknown { flag: u8 },
 }
 
 impl RawStreamChunk {
@@ -538,9 +923,22 @@ impl RawStreamChunk {
             kind,
         })
     }
+
+    fn unknown(self) -> StreamChunk {
+        StreamChunk {
+            more_chunks: self.more_chunks(),
+            size: self.size().value(),
+            kind: StreamChunkKind::Unknown {
+                flag: self.kind().value(),
+            },
+        }
+    }
 }
 
 /// Loop information associated with a stream.
+///
+/// With the `serde` feature, loops are serialized with the fields `start` and `end`,
+/// which are the first and last sample frames of the loop (as returned by [`StreamInfo::loop_points`]).
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Loop {
     start: u32,
@@ -548,75 +946,579 @@ pub struct Loop {
 }
 
 impl Loop {
+    fn read<R: Read>(
+        reader: &mut Reader<R>,
+        index: u32,
+        num_samples: NonZeroU32,
+    ) -> Result<Self, ChunkError> {
+        let start = reader
+            .u32()
+            .map_err(ChunkError::factory(index, ChunkErrorKind::LoopStart))?;
+
+        // Loop ends are inclusive. Some banks store an end of 0 to loop until the last sample frame.
+        let end = match reader
+            .u32()
+            .map_err(ChunkError::factory(index, ChunkErrorKind::LoopEnd))?
+        {
+            0 => num_samples.get() - 1,
+            end => end,
+        };
+
+        Self::parse(index, start, end)
+    }
+
+    // A loop can start and end on the same sample frame, since both positions are part of the loop.
     fn parse(index: u32, start: u32, end: u32) -> Result<Self, ChunkError> {
-        let len = NonZeroU32::new(end - start)
-            .ok_or_else(|| ChunkError::new(index, ChunkErrorKind::ZeroLengthLoop))?;
+        let len = end
+            .checked_sub(start)
+            .ok_or_else(|| {
+                ChunkError::new(index, ChunkErrorKind::LoopEndBeforeStart { start, end })
+            })?
+            .checked_add(1)
+            .and_then(NonZeroU32::new)
+            .ok_or_else(|| ChunkError::new(index, ChunkErrorKind::LoopTooLong))?;
 
         Ok(Self { start, len })
     }
 
+    #[cfg(feature = "serde")]
+    pub(crate) fn from_points(start: u32, end: u32) -> Option<Self> {
+        Self::parse(0, start, end).ok()
+    }
+
     /// Returns the starting position of the loop.
-    /// This value refers to the offset, in bytes, from the start of the stream data.
+    /// This value refers to the index of a sample frame in the stream.
     #[must_use]
     pub fn start(&self) -> u32 {
         self.start
     }
 
     /// Returns the ending position of the loop.
-    /// This value refers to the offset, in bytes, from the start of the stream data.
-    #[allow(clippy::missing_panics_doc)]
+    /// This value refers to the index of the last sample frame in the loop, so it's included in the loop.
     #[must_use]
-    pub fn end(&self) -> NonZeroU32 {
-        (self.start + self.len.get())
-            .try_into()
-            .expect("the sum of u32 and NonZeroU32 must be NonZeroU32")
+    pub fn end(&self) -> u32 {
+        self.start + (self.len.get() - 1)
     }
 
-    /// Returns the length of the loop, in bytes.
+    /// Returns the length of the loop, in sample frames.
+    /// Both the start and the end of the loop are counted, so a loop that starts and ends on the same sample frame
+    /// has a length of 1.
     #[must_use]
     pub fn len(&self) -> NonZeroU32 {
         self.len
     }
 }
 
+/// An entry in the seek table of a Vorbis stream.
+///
+/// With the `serde` feature, entries are serialized with the fields `granule` and `offset`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VorbisSeekEntry {
+    granule: u32,
+    offset: u32,
+}
+
+impl VorbisSeekEntry {
+    /// Returns the position of the entry.
+    /// This value refers to the index of a sample frame in the stream.
+    #[must_use]
+    pub fn granule(&self) -> u32 {
+        self.granule
+    }
+
+    /// Returns the offset (in bytes) of the Vorbis packet at the position of the entry, in the stream data.
+    #[must_use]
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+}
+
+/// Decoding state of a single channel of a GC ADPCM stream.
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub(crate) struct StreamInfo {
+pub(crate) struct DspCoeffs {
+    pub(crate) coefficients: [i16; 16],
+    pub(crate) hist1: i16,
+    pub(crate) hist2: i16,
+}
+
+impl DspCoeffs {
+    fn parse<R: Read>(reader: &mut Reader<R>) -> Result<Self, ReadError> {
+        // The layout is the same as the channel-specific part of a standard DSP file header:
+        // 16 coefficients, gain, initial predictor/scale, initial history samples, then loop context.
+        // Every frame starts with its own predictor/scale, so the initial value isn't needed.
+        let mut coefficients = [0; 16];
+
+        for coefficient in &mut coefficients {
+            *coefficient = reader.be_i16()?;
+        }
+
+        reader.skip(4)?;
+        let hist1 = reader.be_i16()?;
+        let hist2 = reader.be_i16()?;
+        reader.skip(6)?;
+
+        Ok(Self {
+            coefficients,
+            hist1,
+            hist2,
+        })
+    }
+}
+
+/// Format information of an xWMA stream, used to build the file header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct XwmaConfig {
+    pub(crate) format_tag: u16,
+    pub(crate) block_align: u16,
+    pub(crate) avg_bytes_per_sec: u32,
+    pub(crate) seek_table: Box<[u32]>,
+}
+
+impl XwmaConfig {
+    fn parse<R: Read>(reader: &mut Reader<R>, size: u32) -> Result<Self, ReadError> {
+        // The format values are stored as big-endian, followed by a table with the number of bytes
+        // decoded by the end of each packet, which is the content of the dpds chunk in xWMA files.
+        let format_tag = reader.be_u16()?;
+        let block_align = reader.be_u16()?;
+        let avg_bytes_per_sec = reader.be_u32()?;
+
+        let seek_table = (0..size.saturating_sub(8) / 4)
+            .map(|_| reader.be_u32())
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            format_tag,
+            block_align,
+            avg_bytes_per_sec,
+            seek_table,
+        })
+    }
+}
+
+/// Information about a stream, read from the file header of a sound bank.
+///
+/// This type can be accessed without reading any stream data, through [`Bank::stream_info`] and
+/// [`Bank::stream_infos`], so it's suited for listing the contents of a sound bank.
+/// It's also passed to a [`SinkFactory`] when creating the writer for a stream.
+///
+/// Sample counts and loop points are measured in sample frames, where each frame holds
+/// one sample for every channel. Offsets and sizes are measured in bytes.
+///
+/// Values that every stream has are returned directly, using nonzero types where the file header
+/// is validated to never hold 0. Values that only exist for some formats or sound banks are returned as an [`Option`].
+///
+/// # Serialization
+///
+/// With the `serde` feature, stream information is serialized with the following fields,
+/// named after the accessor that returns their value:
+/// `name`, `codec`, `sample_rate`, `channels`, `sample_count`, `loop` (from [`StreamInfo::loop_info`]), `data_offset`,
+/// `size`, `peak_volume`, `channels_overridden`, `atrac9_config`, `xma_seek_table`, `vorbis_crc32`,
+/// `vorbis_seek_table`, and `unknown_chunks`. Fields without a value are `null`.
+/// Chunks of unknown types are summarized by their `type` and `size` (in bytes), without their data.
+/// Audio formats are serialized as their name, as displayed by [`AudioFormat`].
+///
+/// # Examples
+///
+/// ```
+/// use fsbex::Bank;
+/// use std::error::Error;
+///
+/// fn list_streams(bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+///     let bank = Bank::new(bytes)?;
+///
+///     for (index, info) in bank.stream_infos().iter().enumerate() {
+///         println!(
+///             "{index}: {} ({}), {} Hz, {} channel(s), {} frames ({:.2?}), {} bytes",
+///             info.name().unwrap_or("<unnamed>"),
+///             info.codec(),
+///             info.sample_rate(),
+///             info.channels(),
+///             info.sample_count(),
+///             info.duration(),
+///             info.size(),
+///         );
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+///
+/// [`Bank::stream_info`]: crate::Bank::stream_info
+/// [`Bank::stream_infos`]: crate::Bank::stream_infos
+/// [`SinkFactory`]: crate::SinkFactory
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct StreamInfo {
+    pub(crate) format: AudioFormat,
     pub(crate) sample_rate: NonZeroU32,
     pub(crate) channels: NonZeroU8,
     pub(crate) num_samples: NonZeroU32,
     pub(crate) stream_loop: Option<Loop>,
-    pub(crate) _dsp_coeffs: Option<Box<[i16]>>,
+    pub(crate) dsp_coeffs: Option<Box<[DspCoeffs]>>,
+    pub(crate) xma_seek_table: Option<Box<[u32]>>,
+    pub(crate) atrac9_config: Option<Box<[[u8; 4]]>>,
+    pub(crate) xwma_config: Option<XwmaConfig>,
     pub(crate) vorbis_crc32: Option<u32>,
-    pub(crate) size: NonZeroU32,
+    pub(crate) vorbis_seek_table: Option<Box<[VorbisSeekEntry]>>,
+    pub(crate) peak_volume: Option<u32>,
+    pub(crate) unknown_chunks: Vec<(u32, Vec<u8>)>,
+    pub(crate) channels_overridden: bool,
+    pub(crate) data_offset: u64,
+    // position of the start of stream data in the sound bank, and of the sound bank in its file
+    pub(crate) data_start: u64,
+    pub(crate) bank_offset: u64,
+    pub(crate) size: u32,
+    pub(crate) flags: BankFlags,
     pub(crate) name: Option<Box<str>>,
+    pub(crate) file_stem: Box<str>,
 }
 
 impl StreamHeader {
-    fn with_stream_size(self, size: NonZeroU32) -> StreamInfo {
+    fn with_data(
+        self,
+        format: AudioFormat,
+        flags: BankFlags,
+        data_offset: u64,
+        size: u32,
+    ) -> StreamInfo {
         // The stream name is read from the name table (if it exists), so its value is set to None for now.
         StreamInfo {
-            sample_rate: self.sample_rate,
+            format,
+            sample_rate: self
+                .sample_rate
+                .expect("sample rate was validated after parsing stream chunks"),
             channels: self.channels,
             num_samples: self.num_samples,
             stream_loop: self.stream_loop,
-            _dsp_coeffs: self.dsp_coeffs,
+            dsp_coeffs: self.dsp_coeffs,
+            xma_seek_table: self.xma_seek_table,
+            atrac9_config: self.atrac9_config,
+            xwma_config: self.xwma_config,
             vorbis_crc32: self.vorbis_crc32,
+            vorbis_seek_table: self.vorbis_seek_table,
+            peak_volume: self.peak_volume,
+            unknown_chunks: self.unknown_chunks,
+            channels_overridden: self.channels_overridden,
+            data_offset,
+            data_start: 0,
+            bank_offset: 0,
             size,
+            flags,
             name: None,
+            file_stem: Box::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Loop {
+    pub(crate) fn dummy(start: u32, end: u32) -> Self {
+        Self::parse(0, start, end).unwrap()
+    }
+}
+
+impl StreamInfo {
+    /// Returns the audio format of the stream.
+    /// Every stream in a sound bank has the same format, which is also returned by [`Bank::format`].
+    ///
+    /// [`Bank::format`]: crate::Bank::format
+    #[must_use]
+    pub fn codec(&self) -> AudioFormat {
+        self.format
+    }
+
+    /// Returns the version of CELT that the stream was encoded with, if it's a CELT stream.
+    /// Streams in FSB5 sound banks are always encoded with CELT 0.11 (see [`CeltVersion`]).
+    #[must_use]
+    pub fn celt_version(&self) -> Option<CeltVersion> {
+        celt_version(self.format)
+    }
+
+    /// Checks whether the stream can be decoded to 16-bit samples with [`Stream::samples`],
+    /// without reading any stream data.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error that [`Stream::samples`] would fail with: [`EncodeError::UnsupportedFormat`] for formats
+    /// that can't be decoded, or an error of the format's own type for streams that can't be decoded
+    /// (such as a GC ADPCM stream without coefficients). CELT streams fail with [`CeltErrorKind::DecodeUnsupported`],
+    /// which names the version of CELT that a decoder would need.
+    ///
+    /// [`Stream::samples`]: crate::Stream::samples
+    /// [`CeltErrorKind::DecodeUnsupported`]: crate::encode::CeltErrorKind::DecodeUnsupported
+    pub fn check_decode(&self) -> Result<(), EncodeError> {
+        check_decode(self)
+    }
+
+    /// Returns the sample rate (Hz) of the stream.
+    #[must_use]
+    pub fn sample_rate(&self) -> NonZeroU32 {
+        self.sample_rate
+    }
+
+    /// Returns the number of channels in the stream.
+    /// Every sample frame holds one sample for each channel.
+    #[must_use]
+    pub fn channels(&self) -> NonZeroU8 {
+        self.channels
+    }
+
+    /// Returns the number of sample frames in the stream, which is the number of samples per channel.
+    /// The duration of the stream is this value divided by the sample rate.
+    #[must_use]
+    pub fn sample_count(&self) -> NonZeroU32 {
+        self.num_samples
+    }
+
+    /// Returns the playback duration of the stream, computed from its number of sample frames and its sample rate.
+    /// Both values are validated to be nonzero when the file header is parsed, so the duration is always known.
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        samples_to_duration(self.num_samples.get().into(), self.sample_rate)
+    }
+
+    /// Returns loop information, if it exists. Loop positions are measured in sample frames.
+    #[must_use]
+    pub fn loop_info(&self) -> Option<Loop> {
+        self.stream_loop
+    }
+
+    /// Returns the first and last sample frames of the loop, if it exists. Both positions are inclusive.
+    #[must_use]
+    pub fn loop_points(&self) -> Option<(u32, u32)> {
+        self.stream_loop
+            .map(|stream_loop| (stream_loop.start(), stream_loop.end()))
+    }
+
+    /// Returns the configuration data of an ATRAC9 stream, if it exists.
+    ///
+    /// This is the data stored in the extensible header of ATRAC9 RIFF files.
+    /// Streams with more than 2 channels can be made up of multiple layers, each with its own configuration data.
+    #[must_use]
+    pub fn atrac9_config(&self) -> Option<&[[u8; 4]]> {
+        self.atrac9_config.as_deref()
+    }
+
+    /// Returns the block alignment (superframe size, in bytes) of an ATRAC9 stream,
+    /// derived from its configuration data.
+    ///
+    /// Superframes of every layer are stored one after another, so the block alignment of streams
+    /// with multiple layers is the sum of the superframe sizes of their layers.
+    /// Returns `None` if the stream has no configuration data, if the configuration data is invalid,
+    /// or if the block alignment doesn't fit in 16 bits.
+    #[must_use]
+    pub fn atrac9_block_align(&self) -> Option<u16> {
+        self.atrac9_config()
+            .filter(|configs| !configs.is_empty())?
+            .iter()
+            .try_fold(0u16, |size, &config| {
+                size.checked_add(atrac9_superframe_size(config)?)
+            })
+    }
+
+    /// Returns the seek table of an XMA stream, if it exists.
+    ///
+    /// Every entry is the offset (in bytes) of a block of XMA packets in the stream data,
+    /// as stored in the `seek` chunk of XMA2 RIFF files. The table can be empty.
+    #[must_use]
+    pub fn xma_seek_table(&self) -> Option<&[u32]> {
+        self.xma_seek_table.as_deref()
+    }
+
+    /// Returns the CRC32 checksum of the setup header of a Vorbis stream, if it exists.
+    ///
+    /// FSB5 banks don't store the setup headers of Vorbis streams. Instead, the checksum identifies
+    /// which of the setup headers used by FMOD belongs to the stream.
+    #[must_use]
+    pub fn vorbis_crc32(&self) -> Option<u32> {
+        self.vorbis_crc32
+    }
+
+    /// Returns the seek table of a Vorbis stream, if it exists. The table can be empty.
+    #[must_use]
+    pub fn vorbis_seek_table(&self) -> Option<&[VorbisSeekEntry]> {
+        self.vorbis_seek_table.as_deref()
+    }
+
+    /// Returns the peak volume of the stream, if it exists.
+    /// FMOD uses this value for loudness normalization.
+    #[must_use]
+    pub fn peak_volume(&self) -> Option<f32> {
+        self.peak_volume.map(f32::from_bits)
+    }
+
+    /// Returns the stream header chunks of unknown types, as pairs of chunk type and chunk data.
+    ///
+    /// Chunks of unknown types are only kept if enabled with [`ParseOptions::unknown_chunks`],
+    /// and their data is truncated to [`ParseOptions::max_unknown_chunk_size`].
+    #[must_use]
+    pub fn unknown_chunks(&self) -> &[(u32, Vec<u8>)] {
+        &self.unknown_chunks
+    }
+
+    /// Returns whether the number of channels was read from a channels chunk,
+    /// which overrides the number of channels in the stream header.
+    /// Streams with more than 8 channels always have a channels chunk.
+    #[must_use]
+    pub fn channels_overridden(&self) -> bool {
+        self.channels_overridden
+    }
+
+    /// Returns the offset of the stream's data from the start of the sound bank's stream data, in bytes.
+    ///
+    /// Offsets are 64-bit, since the stream data of a sound bank starts after its file header,
+    /// so streams of very large sound banks can start beyond 4 GiB into the file.
+    #[must_use]
+    pub fn data_offset(&self) -> u64 {
+        self.data_offset
+    }
+
+    /// Returns the size of the stream's data in the sound bank, in bytes, which can include padding.
+    /// Placeholder streams, which FMOD writes for disabled assets, have a size of 0 bytes.
+    #[must_use]
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the offset of the stream's data from the start of the sound bank (its file signature), in bytes.
+    ///
+    /// Unlike [`data_offset`](Self::data_offset), this includes the size of the sound bank's file header,
+    /// so the stream's data is the [`data_size`](Self::data_size) bytes at this offset in the sound bank,
+    /// which are exactly the bytes read when the stream is extracted.
+    #[must_use]
+    pub fn data_offset_in_bank(&self) -> u64 {
+        self.data_start + self.data_offset
+    }
+
+    /// Returns the offset of the stream's data from the start of the file holding the sound bank, in bytes.
+    ///
+    /// This is the same as [`data_offset_in_bank`](Self::data_offset_in_bank), unless the sound bank starts
+    /// within a larger file: sound banks parsed with [`Bank::at_offset`] (such as those found with
+    /// [`scan`](crate::scan)) or [`Bank::parse_all`], or read from an FMOD Studio bank file with
+    /// [`StudioBank`](crate::studio::StudioBank), add the offset of the sound bank in its file.
+    /// For other sound banks, the file is assumed to start where parsing started.
+    ///
+    /// [`Bank::at_offset`]: crate::Bank::at_offset
+    /// [`Bank::parse_all`]: crate::Bank::parse_all
+    #[must_use]
+    pub fn data_offset_in_file(&self) -> u64 {
+        self.bank_offset + self.data_offset_in_bank()
+    }
+
+    /// Returns the size of the stream's data in the sound bank, in bytes.
+    /// This is the same as [`size`](Self::size), as a `u64` for computing positions in files.
+    #[must_use]
+    pub fn data_size(&self) -> u64 {
+        u64::from(self.size)
+    }
+
+    /// Returns whether the stream is a placeholder without any data, which FMOD writes for disabled assets.
+    /// Placeholder streams still have a stream header, so their other information is valid.
+    #[must_use]
+    pub fn is_placeholder(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Returns the flags of the sound bank that the stream belongs to, which describe how its data is stored,
+    /// such as the byte order of PCM16 samples. These are the same flags returned by [`Bank::flags`].
+    ///
+    /// [`Bank::flags`]: crate::Bank::flags
+    #[must_use]
+    pub fn flags(&self) -> BankFlags {
+        self.flags
+    }
+
+    /// Returns the name of the stream, if it exists.
+    /// Names are only stored in sound banks built with a name table, or in the sample headers of FSB3 and FSB4 banks.
+    #[must_use]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns a file name for the stream, with the extension of the file format it's written as
+    /// when encoded with `options`, such as `.wav` or `.ogg` (see [`AudioFormat::extension`]).
+    ///
+    /// Files are named after the stream's name, or `stream_{index}` for streams without a name, where the index
+    /// is padded with zeros so that files sort in order of streams. Names are sanitized with [`sanitize_file_name`],
+    /// which removes their directories, replaces characters that can't be used in file names on common platforms
+    /// with `_`, and prefixes names reserved by Windows with `_`.
+    /// File names are unique within a sound bank, even on file systems that ignore case: when names collide,
+    /// the later streams get a numeric suffix (such as `hit_2`), so the same stream always gets the same name.
+    ///
+    /// This is the name used by [`DirectoryFactory`] and [`Bank::extract_all_to_dir`].
+    ///
+    /// [`DirectoryFactory`]: crate::DirectoryFactory
+    /// [`Bank::extract_all_to_dir`]: crate::Bank::extract_all_to_dir
+    #[must_use]
+    pub fn suggested_filename(&self, options: EncodeOptions<'_>) -> String {
+        self.file_name(self.format, options)
+    }
+
+    pub(crate) fn file_name(&self, format: AudioFormat, options: EncodeOptions<'_>) -> String {
+        format!("{}.{}", self.file_stem, format.extension(options))
+    }
+}
+
+// Converts a number of sample frames to a duration, rounding down to the nanosecond.
+pub(crate) fn samples_to_duration(samples: u64, sample_rate: NonZeroU32) -> Duration {
+    let sample_rate = u64::from(sample_rate.get());
+    let nanos = (samples % sample_rate) * 1_000_000_000 / sample_rate;
+
+    Duration::new(
+        samples / sample_rate,
+        u32::try_from(nanos).expect("nanoseconds of a fraction of a second fit in u32"),
+    )
+}
+
+#[cfg(test)]
+impl StreamInfo {
+    pub(crate) fn dummy(channels: u8, num_samples: u32, size: u32) -> Self {
+        Self {
+            format: AudioFormat::Pcm16,
+            sample_rate: NonZeroU32::new(44100).unwrap(),
+            channels: NonZeroU8::new(channels).unwrap(),
+            num_samples: NonZeroU32::new(num_samples).unwrap(),
+            stream_loop: None,
+            dsp_coeffs: None,
+            xma_seek_table: None,
+            atrac9_config: None,
+            xwma_config: None,
+            vorbis_crc32: None,
+            vorbis_seek_table: None,
+            peak_volume: None,
+            unknown_chunks: Vec::new(),
+            channels_overridden: false,
+            data_offset: 0,
+            data_start: 0,
+            bank_offset: 0,
+            size,
+            flags: BankFlags::new(0),
+            name: None,
+            file_stem: "stream_0".into(),
         }
     }
 }
 
 fn read_stream_names<R: Read>(
     reader: &mut Reader<R>,
+    name_table_size: u32,
     name_offsets: &[u32],
     stream_info: &mut [StreamInfo],
 ) -> Result<(), NameError> {
-    for (name_len, index) in name_offsets.windows(2).map(|window| window[1] - window[0]).zip(0..) {
-        stream_info[index as usize].name = reader
-            .take(name_len as usize)
-            .map_err(NameError::read_factory(index, NameErrorKind::Name))?
-            .pipe_as_ref(CStr::from_bytes_until_nul)
+    // the rest of the name table is read at once, since names can be in any order
+    let names_start = name_offsets.len() * 4;
+    let names = reader
+        .take((name_table_size as usize).saturating_sub(names_start))
+        .map_err(NameError::read_factory(0, NameErrorKind::Name))?;
+
+    for (&offset, index) in zip(name_offsets, 0..) {
+        let name = (offset as usize)
+            .checked_sub(names_start)
+            .and_then(|start| names.get(start..))
+            .ok_or_else(|| NameError::new(index, NameErrorKind::InvalidOffset { offset }))?;
+
+        stream_info[index as usize].name = CStr::from_bytes_until_nul(name)
             .map_err(NameError::cstr_factory(index))?
             .to_str()
             .map_err(NameError::utf8_factory(index))?
@@ -630,8 +1532,14 @@ fn read_stream_names<R: Read>(
 #[cfg(test)]
 mod test {
     use super::error::{ChunkErrorKind::*, HeaderErrorKind::*, StreamErrorKind::*};
-    use super::{Header, RawStreamChunk, RawStreamHeader, StreamHeader, FSB5_MAGIC};
-    use crate::read::Reader;
+    use super::{
+        DspCoeffs, Header, HeaderError, OffsetWarningKind, ParseOptions, RawStreamChunk,
+        RawStreamHeader, StreamHeader, XwmaConfig, FSB5_MAGIC,
+    };
+    use crate::{
+        read::Reader,
+        test_bank::{TestBank, TestStream},
+    };
     use std::num::{NonZeroU32, NonZeroU8};
 
     #[test]
@@ -731,11 +1639,63 @@ mod test {
         reader = Reader::new(data.as_slice());
         assert!(Header::parse(&mut reader).is_err_and(|e| e.kind() == AudioFormat));
 
+        // unknown formats are kept, so parsing continues with the encoding flags
         let data = b"FSB5\x01\x00\x00\x000000000000000000\x00\x00\x00\x00";
         reader = Reader::new(data.as_slice());
-        assert!(
-            Header::parse(&mut reader).is_err_and(|e| e.kind() == UnknownAudioFormat { flag: 0 })
-        );
+        assert!(Header::parse(&mut reader).is_err_and(|e| e.kind() == EncodingFlags));
+
+        // 44100 Hz, 1 channel, 1 sample
+        let data = header_with_chunks(99, (8 << 1) | (1 << 34), &[]);
+        let header = Header::parse(&mut Reader::new(data.as_slice())).unwrap();
+        assert_eq!(header.format, super::AudioFormat::Unknown(99));
+    }
+
+    #[test]
+    fn stream_duration() {
+        use super::StreamInfo;
+        use std::time::Duration;
+
+        // 1.5 seconds at 44100 Hz, regardless of the number of channels
+        assert_eq!(StreamInfo::dummy(2, 66150, 0).duration(), Duration::from_millis(1500));
+        assert_eq!(StreamInfo::dummy(1, 1, 0).duration(), Duration::from_nanos(22_675));
+
+        let info = StreamInfo {
+            sample_rate: NonZeroU32::new(1).unwrap(),
+            ..StreamInfo::dummy(1, u32::MAX, 0)
+        };
+        assert_eq!(info.duration(), Duration::from_secs(u32::MAX.into()));
+    }
+
+    #[cfg(feature = "ffi")]
+    #[test]
+    fn audio_format_values() {
+        use super::AudioFormat;
+
+        for value in 0..20 {
+            assert_eq!(AudioFormat::parse(value).value(), value);
+        }
+    }
+
+    #[test]
+    fn audio_format_names() {
+        use super::AudioFormat;
+
+        for format in AudioFormat::KNOWN.into_iter().chain([AudioFormat::Unknown(18)]) {
+            let name = format.to_string();
+            assert_eq!(name, name.to_lowercase());
+            assert_eq!(name.parse(), Ok(format));
+            assert_eq!(name.to_uppercase().parse(), Ok(format));
+        }
+
+        assert_eq!(AudioFormat::PcmFloat.to_string(), "pcmfloat");
+        assert_eq!(AudioFormat::Unknown(18).to_string(), "unknown(18)");
+        assert!("".parse::<AudioFormat>().is_err());
+        assert!("unknown(x)".parse::<AudioFormat>().is_err());
+        assert!("pcm".parse::<AudioFormat>().is_err());
+
+        assert!(AudioFormat::Mpeg.is_supported());
+        assert!(!AudioFormat::Unknown(18).is_supported());
+        assert_eq!(AudioFormat::Vorbis.is_supported(), cfg!(feature = "decode-vorbis"));
     }
 
     #[test]
@@ -799,6 +1759,53 @@ mod test {
         assert!(Header::parse(&mut reader).is_err_and(|e| e.is_stream_err_kind(StreamInfo)));
     }
 
+    #[test]
+    fn read_name_table() {
+        // 44100 Hz, 1 channel, 2 samples
+        const STREAM_HEADER: u64 = (8 << 1) | (2 << 34);
+
+        fn bank(name_table: &[u8]) -> Vec<u8> {
+            let mut buf = Vec::from(FSB5_MAGIC);
+            buf.extend_from_slice(&1u32.to_le_bytes());
+            buf.extend_from_slice(&2u32.to_le_bytes());
+            buf.extend_from_slice(&16u32.to_le_bytes());
+            buf.extend_from_slice(&u32::try_from(name_table.len()).unwrap().to_le_bytes());
+            buf.extend_from_slice(&64u32.to_le_bytes());
+            buf.extend_from_slice(&2u32.to_le_bytes());
+            buf.resize(60, 0);
+            // data offsets are stored in units of 32 bytes
+            buf.extend_from_slice(&STREAM_HEADER.to_le_bytes());
+            buf.extend_from_slice(&(STREAM_HEADER | (1 << 7)).to_le_bytes());
+            buf.extend_from_slice(name_table);
+            buf
+        }
+
+        let data = bank(&[]);
+        let header = Header::parse(&mut Reader::new(data.as_slice())).unwrap();
+        assert!(header.stream_info.iter().all(|info| info.name.is_none()));
+
+        // names are stored out of order and followed by padding
+        let mut name_table = Vec::new();
+        name_table.extend_from_slice(&18u32.to_le_bytes());
+        name_table.extend_from_slice(&8u32.to_le_bytes());
+        name_table.extend_from_slice(b"music/bgm\0intro\0\0\0\0");
+
+        let data = bank(&name_table);
+        let mut reader = Reader::new(data.as_slice());
+        let header = Header::parse(&mut reader).unwrap();
+        assert_eq!(reader.position(), data.len() as u64);
+        assert_eq!(header.stream_info[0].name.as_deref(), Some("intro"));
+        assert_eq!(header.stream_info[1].name.as_deref(), Some("music/bgm"));
+
+        // offsets point into the name offsets or past the end of the table
+        for offset in [4u32, 40] {
+            name_table[..4].copy_from_slice(&offset.to_le_bytes());
+            let data = bank(&name_table);
+            assert!(Header::parse(&mut Reader::new(data.as_slice()))
+                .is_err_and(|e| e.kind() == NameTable));
+        }
+    }
+
     #[test]
     fn read_stream_info() {
         let data = b"FSB5\x01\x00\x00\x00\x01\x00\x00\x00000000000000\x01\x00\x00\x00000000000000000000000000000000000000";
@@ -832,9 +1839,8 @@ mod test {
     fn parse_stream_info() {
         let data = 0b011010000101100111100000001011_111001101101001101000100110_11_1110_0;
         let mode = RawStreamHeader::from(data);
-        assert!(mode
-            .parse(0)
-            .is_err_and(|e| e.kind() == UnknownSampleRate { flag: 0b1110 }));
+        // reserved sample rate flags are checked after parsing stream chunks
+        assert!(mode.parse(0).is_ok_and(|header| header.sample_rate.is_none()));
 
         let data = 0b000000000000000000000000000000_111001101101001101000100110_11_0000_0;
         let mode = RawStreamHeader::from(data);
@@ -846,13 +1852,20 @@ mod test {
             mode,
             StreamHeader {
                 has_chunks: false,
-                sample_rate: NonZeroU32::new(44100).unwrap(),
+                sample_rate: NonZeroU32::new(44100),
                 channels: NonZeroU8::new(2).unwrap(),
                 data_offset: 32,
                 num_samples: NonZeroU32::new(1).unwrap(),
                 stream_loop: None,
                 dsp_coeffs: None,
+                xma_seek_table: None,
+                atrac9_config: None,
+                xwma_config: None,
                 vorbis_crc32: None,
+                vorbis_seek_table: None,
+                peak_volume: None,
+                unknown_chunks: Vec::new(),
+                channels_overridden: false,
             }
         );
     }
@@ -904,4 +1917,431 @@ mod test {
             test_invalid_flag(flag);
         }
     }
+
+    // Creates a version 1 file header containing a single stream with the given format and chunks.
+    fn header_with_chunks(format: u32, stream_header: u64, chunks: &[(u32, &[u8])]) -> Vec<u8> {
+        let mut stream = TestStream::new(stream_header, &[0; 32]);
+        for (kind, data) in chunks {
+            stream = stream.chunk(*kind, data);
+        }
+        TestBank::new(format).stream(stream).build()
+    }
+
+    #[test]
+    fn parse_big_endian_header() {
+        // 44100 Hz, 1 channel, 100 samples
+        const STREAM_HEADER: u64 = (8 << 1) | (100 << 34);
+
+        let mut loop_chunk = 10u32.to_le_bytes().to_vec();
+        loop_chunk.extend_from_slice(&50u32.to_le_bytes());
+        let file = header_with_chunks(2, STREAM_HEADER, &[(3, &loop_chunk)]);
+        let expected = Header::parse(&mut Reader::new(file.as_slice())).unwrap();
+
+        let mut file = Vec::from(*b"5BSF");
+        for field in [1u32, 1, 8 + 4 + 8, 0, 32, 2] {
+            file.extend_from_slice(&field.to_be_bytes());
+        }
+        file.resize(60, 0);
+        file.extend_from_slice(&(STREAM_HEADER | 1).to_be_bytes());
+        file.extend_from_slice(&((8u32 << 1) | (3 << 25)).to_be_bytes());
+        file.extend_from_slice(&10u32.to_be_bytes());
+        file.extend_from_slice(&50u32.to_be_bytes());
+        file.resize(file.len() + 32, 0);
+
+        let header = Header::parse(&mut Reader::new(file.as_slice())).unwrap();
+        assert_eq!(header, expected);
+        assert_eq!(header.stream_info[0].stream_loop.map(|l| l.start()), Some(10));
+    }
+
+    #[test]
+    fn parse_loop_chunk() {
+        // 44100 Hz, 1 channel, 100 samples
+        const STREAM_HEADER: u64 = (8 << 1) | (100 << 34);
+
+        fn parse(start: u32, end: u32) -> Result<Header, HeaderError> {
+            let mut chunk = Vec::new();
+            chunk.extend_from_slice(&start.to_le_bytes());
+            chunk.extend_from_slice(&end.to_le_bytes());
+
+            let data = header_with_chunks(2, STREAM_HEADER, &[(3, &chunk)]);
+            Header::parse(&mut Reader::new(data.as_slice()))
+        }
+
+        let stream_loop = parse(10, 50).unwrap().stream_info[0].stream_loop.unwrap();
+        assert_eq!(stream_loop.start(), 10);
+        assert_eq!(stream_loop.end(), 50);
+        assert_eq!(stream_loop.len().get(), 41);
+
+        // an end of 0 loops until the last sample frame
+        let stream_loop = parse(10, 0).unwrap().stream_info[0].stream_loop.unwrap();
+        assert_eq!(stream_loop.start(), 10);
+        assert_eq!(stream_loop.end(), 99);
+        assert_eq!(stream_loop.len().get(), 90);
+
+        // a loop can start and end on the same sample frame
+        let stream_loop = parse(10, 10).unwrap().stream_info[0].stream_loop.unwrap();
+        assert_eq!(stream_loop.end(), 10);
+        assert_eq!(stream_loop.len().get(), 1);
+
+        assert!(parse(0, u32::MAX).is_err_and(|e| e.is_chunk_err_kind(LoopTooLong)));
+        assert!(parse(50, 10)
+            .is_err_and(|e| e.is_chunk_err_kind(LoopEndBeforeStart { start: 50, end: 10 })));
+    }
+
+    #[test]
+    fn parse_sample_rate_chunk() {
+        // 1 channel, 100 samples, with the given sample rate flag
+        const STREAM_HEADER: u64 = 100 << 34;
+
+        fn parse(flag: u64, chunk: Option<u32>) -> Result<Header, HeaderError> {
+            let chunk = chunk.map(u32::to_le_bytes);
+            let chunks: &[(u32, &[u8])] = match &chunk {
+                Some(rate) => &[(2, rate)],
+                None => &[],
+            };
+
+            let data = header_with_chunks(2, STREAM_HEADER | (flag << 1), chunks);
+            Header::parse(&mut Reader::new(data.as_slice()))
+        }
+
+        let sample_rate = |header: Header| header.stream_info[0].sample_rate.get();
+
+        assert_eq!(parse(8, None).map(sample_rate).unwrap(), 44100);
+        // the chunk overrides the flag, even if they disagree
+        assert_eq!(parse(8, Some(32000)).map(sample_rate).unwrap(), 32000);
+        assert_eq!(parse(0b1110, Some(37800)).map(sample_rate).unwrap(), 37800);
+
+        assert!(parse(0b1110, None)
+            .is_err_and(|e| e.is_stream_err_kind(UnknownSampleRate { flag: 0b1110 })));
+        assert!(parse(8, Some(0)).is_err_and(|e| e.is_chunk_err_kind(ZeroSampleRate)));
+    }
+
+    #[test]
+    fn parse_channels_chunk() {
+        // 44100 Hz, 2 channels, 100 samples
+        const STREAM_HEADER: u64 = (8 << 1) | (1 << 5) | (100 << 34);
+
+        fn parse(chunk: Option<u8>) -> Result<Header, HeaderError> {
+            let chunk = chunk.map(|channels| [channels]);
+            let chunks: &[(u32, &[u8])] = match &chunk {
+                Some(channels) => &[(1, channels)],
+                None => &[],
+            };
+
+            let data = header_with_chunks(2, STREAM_HEADER, chunks);
+            Header::parse(&mut Reader::new(data.as_slice()))
+        }
+
+        let info = &parse(None).unwrap().stream_info[0];
+        assert_eq!(info.channels.get(), 2);
+        assert!(!info.channels_overridden());
+
+        let info = &parse(Some(12)).unwrap().stream_info[0];
+        assert_eq!(info.channels.get(), 12);
+        assert!(info.channels_overridden());
+
+        assert!(parse(Some(0)).is_err_and(|e| e.is_chunk_err_kind(ZeroChannels)));
+        assert!(
+            parse(Some(33)).is_err_and(|e| e.is_chunk_err_kind(TooManyChannels { channels: 33 }))
+        );
+    }
+
+    #[test]
+    fn parse_chunk_chain_with_peak_volume() {
+        // 44100 Hz, 1 channel, 100 samples
+        const STREAM_HEADER: u64 = (8 << 1) | (100 << 34);
+
+        let mut loop_chunk = 10u32.to_le_bytes().to_vec();
+        loop_chunk.extend_from_slice(&50u32.to_le_bytes());
+        let peak_volume = 0.75f32.to_le_bytes();
+        let sample_rate = 32000u32.to_le_bytes();
+
+        let data = header_with_chunks(
+            2,
+            STREAM_HEADER,
+            &[(3, &loop_chunk), (13, &peak_volume), (2, &sample_rate)],
+        );
+        let header = Header::parse(&mut Reader::new(data.as_slice())).unwrap();
+        let info = &header.stream_info[0];
+
+        assert_eq!(info.peak_volume(), Some(0.75));
+        assert_eq!(info.loop_info().map(|l| l.start()), Some(10));
+        assert_eq!(info.loop_points(), Some((10, 50)));
+        assert_eq!(info.sample_rate.get(), 32000);
+
+        let data = header_with_chunks(2, STREAM_HEADER, &[]);
+        let header = Header::parse(&mut Reader::new(data.as_slice())).unwrap();
+        assert_eq!(header.stream_info[0].peak_volume(), None);
+    }
+
+    #[test]
+    fn keep_unknown_chunks() {
+        // 44100 Hz, 1 channel, 100 samples
+        const STREAM_HEADER: u64 = (8 << 1) | (100 << 34);
+
+        let sample_rate = 32000u32.to_le_bytes();
+        let data = header_with_chunks(
+            2,
+            STREAM_HEADER,
+            &[(20, &[1, 2, 3, 4, 5, 6]), (2, &sample_rate), (5, &[7, 8])],
+        );
+
+        assert!(Header::parse(&mut Reader::new(data.as_slice()))
+            .is_err_and(|e| e.is_chunk_err_kind(UnknownType { flag: 20 })));
+
+        let options = ParseOptions::new().unknown_chunks(true).max_unknown_chunk_size(4);
+        let header =
+            Header::parse_with_options(&mut Reader::new(data.as_slice()), options).unwrap();
+        let info = &header.stream_info[0];
+
+        assert_eq!(info.unknown_chunks(), [(20, vec![1, 2, 3, 4]), (5, vec![7, 8])]);
+        assert_eq!(info.sample_rate.get(), 32000);
+
+        let data = header_with_chunks(2, STREAM_HEADER, &[(2, &sample_rate)]);
+        let header =
+            Header::parse_with_options(&mut Reader::new(data.as_slice()), options).unwrap();
+        assert!(header.stream_info[0].unknown_chunks().is_empty());
+    }
+
+    #[test]
+    fn parse_basic_stream_headers() {
+        // 44100 Hz, 2 channels, 100 samples, followed by a peak volume chunk
+        const FIRST_HEADER: u64 = (8 << 1) | (1 << 5) | (100 << 34);
+        // 48000 Hz, 1 channel, 50 samples
+        const SECOND_HEADER: u64 = (9 << 1) | (50 << 34);
+
+        fn bank(flags: u32) -> Vec<u8> {
+            let first = TestStream::new(FIRST_HEADER, &[0; 32]).chunk(13, &0.5f32.to_le_bytes());
+            let second = TestStream::new(SECOND_HEADER, &[0; 32]);
+            TestBank::new(2).flags(flags).stream(first).stream(second).build()
+        }
+
+        // full headers: every stream header is parsed on its own
+        let data = bank(0);
+        let header = Header::parse(&mut Reader::new(data.as_slice())).unwrap();
+        let second = &header.stream_info[1];
+
+        assert_eq!(header.flags, 0);
+        assert_eq!(second.sample_rate.get(), 48000);
+        assert_eq!(second.channels.get(), 1);
+        assert_eq!(second.num_samples.get(), 50);
+        assert_eq!(second.peak_volume(), None);
+        assert_eq!(second.size, 32);
+
+        // basic headers: streams after the first only take their data offset and length from their header
+        let data = bank(0x02);
+        let header = Header::parse(&mut Reader::new(data.as_slice())).unwrap();
+        let second = &header.stream_info[1];
+
+        assert_eq!(header.flags, 0x02);
+        assert_eq!(second.sample_rate.get(), 44100);
+        assert_eq!(second.channels.get(), 2);
+        assert_eq!(second.num_samples.get(), 50);
+        assert_eq!(second.peak_volume(), Some(0.5));
+        assert_eq!(second.size, 32);
+        assert_eq!(header.stream_info[0].size, 32);
+    }
+
+    #[test]
+    fn reject_data_offset_past_next_stream() {
+        // 44100 Hz, 1 channel, 10 samples, with data offsets of 64 and 32 bytes
+        const FIRST_HEADER: u64 = (8 << 1) | (2 << 7) | (10 << 34);
+        const SECOND_HEADER: u64 = (8 << 1) | (1 << 7) | (10 << 34);
+
+        let mut data = Vec::from(*b"FSB5");
+        for field in [1u32, 2, 16, 0, 96, 2] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        data.resize(60, 0);
+        data.extend_from_slice(&FIRST_HEADER.to_le_bytes());
+        data.extend_from_slice(&SECOND_HEADER.to_le_bytes());
+
+        assert!(Header::parse(&mut Reader::new(data.as_slice()))
+            .is_err_and(|e| e.is_stream_err_kind(DataOffset)));
+    }
+
+    #[test]
+    fn clamp_data_offsets_in_lenient_mode() {
+        // 44100 Hz, 1 channel, 10 samples, with data offsets of 64, 32, and 128 bytes
+        const HEADERS: [u64; 3] = [
+            (8 << 1) | (2 << 7) | (10 << 34),
+            (8 << 1) | (1 << 7) | (10 << 34),
+            (8 << 1) | (4 << 7) | (10 << 34),
+        ];
+
+        let mut data = Vec::from(*b"FSB5");
+        for field in [1u32, 3, 24, 0, 96, 2] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        data.resize(60, 0);
+        for header in HEADERS {
+            data.extend_from_slice(&header.to_le_bytes());
+        }
+
+        let options = ParseOptions::new().lenient_offsets(true);
+        let header =
+            Header::parse_with_options(&mut Reader::new(data.as_slice()), options).unwrap();
+
+        let offsets: Vec<_> = header
+            .stream_info
+            .iter()
+            .map(|info| (info.data_offset, info.size))
+            .collect();
+        assert_eq!(offsets, [(64, 0), (64, 32), (96, 0)]);
+
+        let warnings: Vec<_> = header
+            .offset_warnings
+            .iter()
+            .map(|warning| (warning.index(), warning.kind(), warning.offset()))
+            .collect();
+        assert_eq!(
+            warnings,
+            [
+                (1, OffsetWarningKind::Overlap, 32),
+                (2, OffsetWarningKind::OutOfBounds, 128)
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_dsp_coefficients_chunk() {
+        // 44100 Hz, 1 channel, 14 samples
+        const STREAM_HEADER: u64 = (8 << 1) | (14 << 34);
+
+        let mut chunk = Vec::new();
+        for coefficient in 0..16i16 {
+            chunk.extend_from_slice(&(coefficient - 8).to_be_bytes());
+        }
+        chunk.extend_from_slice(&[0; 4]);
+        chunk.extend_from_slice(&100i16.to_be_bytes());
+        chunk.extend_from_slice(&(-100i16).to_be_bytes());
+        chunk.extend_from_slice(&[0; 6]);
+
+        let data = header_with_chunks(6, STREAM_HEADER, &[(7, &chunk)]);
+        let header = Header::parse(&mut Reader::new(data.as_slice())).unwrap();
+
+        assert_eq!(
+            header.stream_info[0].dsp_coeffs.as_deref(),
+            Some(
+                [DspCoeffs {
+                    coefficients: core::array::from_fn(|i| i16::try_from(i).unwrap() - 8),
+                    hist1: 100,
+                    hist2: -100,
+                }]
+                .as_slice()
+            )
+        );
+
+        let data = header_with_chunks(6, STREAM_HEADER, &[(7, &chunk[..40])]);
+        assert!(Header::parse(&mut Reader::new(data.as_slice())).is_err_and(|e| e
+            .is_chunk_err_kind(WrongChunkSize {
+                expected: 40,
+                actual: 46
+            })));
+    }
+
+    #[test]
+    fn parse_xma_seek_table_chunk() {
+        // 44100 Hz, 2 channels, 512 samples
+        const STREAM_HEADER: u64 = (8 << 1) | (1 << 5) | (512 << 34);
+
+        let mut chunk = Vec::new();
+        chunk.extend_from_slice(&0x100u32.to_le_bytes());
+        chunk.extend_from_slice(&0x200u32.to_le_bytes());
+
+        let data = header_with_chunks(10, STREAM_HEADER, &[(6, &chunk)]);
+        let header = Header::parse(&mut Reader::new(data.as_slice())).unwrap();
+
+        assert_eq!(
+            header.stream_info[0].xma_seek_table(),
+            Some([0x100, 0x200].as_slice())
+        );
+
+        let data = header_with_chunks(10, STREAM_HEADER, &[(6, &[])]);
+        let header = Header::parse(&mut Reader::new(data.as_slice())).unwrap();
+        assert_eq!(header.stream_info[0].xma_seek_table(), Some([].as_slice()));
+
+        let data = header_with_chunks(10, STREAM_HEADER, &[(6, &chunk[..6])]);
+        assert!(Header::parse(&mut Reader::new(data.as_slice()))
+            .is_err_and(|e| e.is_chunk_err_kind(XmaSeekTableSize { size: 6 })));
+    }
+
+    #[test]
+    fn parse_vorbis_seek_table_chunk() {
+        // 44100 Hz, 2 channels, 4096 samples
+        const STREAM_HEADER: u64 = (8 << 1) | (1 << 5) | (4096 << 34);
+
+        let mut chunk = 0x1234_5678u32.to_le_bytes().to_vec();
+
+        let data = header_with_chunks(15, STREAM_HEADER, &[(11, &chunk)]);
+        let header = Header::parse(&mut Reader::new(data.as_slice())).unwrap();
+        assert_eq!(header.stream_info[0].vorbis_crc32(), Some(0x1234_5678));
+        assert_eq!(header.stream_info[0].vorbis_seek_table(), Some([].as_slice()));
+
+        for (granule, offset) in [(0u32, 0u32), (2048, 0x400)] {
+            chunk.extend_from_slice(&granule.to_le_bytes());
+            chunk.extend_from_slice(&offset.to_le_bytes());
+        }
+
+        let data = header_with_chunks(15, STREAM_HEADER, &[(11, &chunk)]);
+        let header = Header::parse(&mut Reader::new(data.as_slice())).unwrap();
+        let seek_table = header.stream_info[0].vorbis_seek_table().unwrap();
+        assert_eq!(seek_table.len(), 2);
+        assert_eq!((seek_table[1].granule(), seek_table[1].offset()), (2048, 0x400));
+    }
+
+    #[test]
+    fn parse_atrac9_config_chunk() {
+        // 48000 Hz, 2 channels, 1024 samples
+        const STREAM_HEADER: u64 = (9 << 1) | (1 << 5) | (1024 << 34);
+        const CONFIG: [u8; 4] = [0xFE, 0x74, 0x20, 0x10];
+
+        let data = header_with_chunks(13, STREAM_HEADER, &[(9, &CONFIG)]);
+        let header = Header::parse(&mut Reader::new(data.as_slice())).unwrap();
+        assert_eq!(header.stream_info[0].atrac9_config(), Some([CONFIG].as_slice()));
+        // frame size of 0x101 bytes, 4 frames per superframe
+        assert_eq!(header.stream_info[0].atrac9_block_align(), Some(0x404));
+
+        // newer banks store the frame size before the configuration data of each layer
+        let mut chunk = 0x100u32.to_le_bytes().to_vec();
+        chunk.extend_from_slice(&CONFIG);
+        chunk.extend_from_slice(&CONFIG);
+
+        let data = header_with_chunks(13, STREAM_HEADER, &[(9, &chunk)]);
+        let header = Header::parse(&mut Reader::new(data.as_slice())).unwrap();
+        assert_eq!(
+            header.stream_info[0].atrac9_config(),
+            Some([CONFIG, CONFIG].as_slice())
+        );
+        assert_eq!(header.stream_info[0].atrac9_block_align(), Some(0x808));
+
+        let mut config = CONFIG;
+        config[0] = 0xFD;
+        let data = header_with_chunks(13, STREAM_HEADER, &[(9, &config)]);
+        assert!(Header::parse(&mut Reader::new(data.as_slice()))
+            .is_err_and(|e| e.is_chunk_err_kind(Atrac9SyncByte { byte: 0xFD })));
+    }
+
+    #[test]
+    fn parse_xwma_config_chunk() {
+        // 44100 Hz, 2 channels, 4096 samples
+        const STREAM_HEADER: u64 = (8 << 1) | (1 << 5) | (4096 << 34);
+
+        let mut chunk = [0x01, 0x61, 0x10, 0x00].to_vec();
+        chunk.extend_from_slice(&8000u32.to_be_bytes());
+        chunk.extend_from_slice(&0x4000u32.to_be_bytes());
+        chunk.extend_from_slice(&0x8000u32.to_be_bytes());
+
+        let data = header_with_chunks(14, STREAM_HEADER, &[(10, &chunk)]);
+        let header = Header::parse(&mut Reader::new(data.as_slice())).unwrap();
+        assert_eq!(
+            header.stream_info[0].xwma_config,
+            Some(XwmaConfig {
+                format_tag: 0x0161,
+                block_align: 0x1000,
+                avg_bytes_per_sec: 8000,
+                seek_table: [0x4000, 0x8000].into(),
+            })
+        );
+    }
 }
@@ -0,0 +1,189 @@
+use super::error::{StreamError, StreamErrorKind};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// A problem with the data offset of a stream, found while parsing a sound bank's file header.
+///
+/// Since stream sizes are derived from the data offset of the next stream, offsets that go backwards
+/// or point past the end of stream data would otherwise produce garbage stream data.
+/// By default, these offsets are rejected; with [`ParseOptions::lenient_offsets`] enabled, they are clamped
+/// so that streams don't overlap, and a warning is recorded for each affected stream.
+/// Warnings can be accessed with [`Bank::offset_warnings`].
+///
+/// # Examples
+///
+/// ```
+/// use fsbex::{Bank, ParseOptions};
+/// use std::error::Error;
+///
+/// fn report_offsets(bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+///     let bank = Bank::with_options(bytes, ParseOptions::new().lenient_offsets(true))?;
+///
+///     for warning in bank.offset_warnings() {
+///         println!("{warning}");
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+///
+/// [`ParseOptions::lenient_offsets`]: crate::ParseOptions::lenient_offsets
+/// [`Bank::offset_warnings`]: crate::Bank::offset_warnings
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OffsetWarning {
+    index: u32,
+    kind: OffsetWarningKind,
+    offset: u64,
+}
+
+/// The kind of problem described by an [`OffsetWarning`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum OffsetWarningKind {
+    /// The data offset of the stream was before the end of the previous stream's data,
+    /// so the two streams overlapped.
+    Overlap,
+    /// The data offset of the stream was past the end of stream data.
+    OutOfBounds,
+}
+
+impl OffsetWarning {
+    /// Returns the index of the stream with the offending data offset.
+    #[must_use]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the kind of problem with the data offset.
+    #[must_use]
+    pub fn kind(&self) -> OffsetWarningKind {
+        self.kind
+    }
+
+    /// Returns the data offset stored in the stream header, before it was clamped.
+    #[must_use]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+impl Display for OffsetWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_fmt(format_args!(
+            "{} (data offset {:#x}) - stream header at index {}",
+            self.kind, self.offset, self.index
+        ))
+    }
+}
+
+impl Display for OffsetWarningKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Self::Overlap => "stream data overlapped the data of the previous stream",
+            Self::OutOfBounds => "stream data started past the end of stream data",
+        })
+    }
+}
+
+impl From<OffsetWarningKind> for StreamErrorKind {
+    fn from(value: OffsetWarningKind) -> Self {
+        match value {
+            OffsetWarningKind::Overlap => Self::DataOffset,
+            OffsetWarningKind::OutOfBounds => Self::DataOffsetOutOfBounds,
+        }
+    }
+}
+
+// Checks that data offsets are non-decreasing and within the stream data, which ends at `data_size`.
+// Offending offsets are rejected, or clamped to the previous offset and the end of stream data if `lenient`,
+// so that sizes derived from them never underflow. Returns a warning for each clamped offset.
+pub(super) fn validate(
+    offsets: &mut [u64],
+    data_size: u64,
+    lenient: bool,
+) -> Result<Vec<OffsetWarning>, StreamError> {
+    let mut warnings = Vec::new();
+    let mut previous = 0;
+
+    for (offset, index) in offsets.iter_mut().zip(0..) {
+        let kind = if *offset > data_size {
+            OffsetWarningKind::OutOfBounds
+        } else if *offset < previous {
+            OffsetWarningKind::Overlap
+        } else {
+            previous = *offset;
+            continue;
+        };
+
+        if !lenient {
+            return Err(StreamError::new(index, kind.into()));
+        }
+
+        warnings.push(OffsetWarning {
+            index,
+            kind,
+            offset: *offset,
+        });
+
+        *offset = (*offset).clamp(previous, data_size);
+        previous = *offset;
+    }
+
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{validate, OffsetWarning, OffsetWarningKind};
+    use crate::header::error::StreamErrorKind;
+
+    #[test]
+    fn accept_ordered_offsets() {
+        let mut offsets = [0, 32, 32, 96];
+
+        assert_eq!(validate(&mut offsets, 96, false).unwrap(), []);
+        assert_eq!(offsets, [0, 32, 32, 96]);
+    }
+
+    #[test]
+    fn reject_offsets_in_strict_mode() {
+        assert!(validate(&mut [0, 64, 32], 96, false)
+            .is_err_and(|e| e.index() == 2 && e.kind() == StreamErrorKind::DataOffset));
+
+        assert!(validate(&mut [0, 128], 96, false)
+            .is_err_and(|e| e.index() == 1 && e.kind() == StreamErrorKind::DataOffsetOutOfBounds));
+    }
+
+    #[test]
+    fn clamp_offsets_in_lenient_mode() {
+        let mut offsets = [0, 64, 32, 128, 80];
+        let warnings = validate(&mut offsets, 96, true).unwrap();
+
+        assert_eq!(offsets, [0, 64, 64, 96, 96]);
+        assert_eq!(
+            warnings,
+            [
+                OffsetWarning {
+                    index: 2,
+                    kind: OffsetWarningKind::Overlap,
+                    offset: 32,
+                },
+                OffsetWarning {
+                    index: 3,
+                    kind: OffsetWarningKind::OutOfBounds,
+                    offset: 128,
+                },
+                OffsetWarning {
+                    index: 4,
+                    kind: OffsetWarningKind::Overlap,
+                    offset: 80,
+                },
+            ]
+        );
+    }
+}
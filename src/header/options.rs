@@ -0,0 +1,92 @@
+use crate::decrypt::XorScheme;
+
+/// Options that control how the file header of a sound bank is parsed.
+///
+/// By default, stream header chunks of unknown types are rejected, since they usually mean that
+/// the file header is corrupt or was parsed incorrectly.
+///
+/// # Examples
+///
+/// ```
+/// use fsbex::{Bank, ParseOptions};
+/// use std::error::Error;
+///
+/// fn read_unknown_chunks(bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+///     let options = ParseOptions::new().unknown_chunks(true).max_unknown_chunk_size(256);
+///
+///     for stream in Bank::with_options(bytes, options)? {
+///         for (kind, data) in stream.unknown_chunks() {
+///             println!("chunk of type {kind}: {data:02x?}");
+///         }
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ParseOptions {
+    pub(crate) unknown_chunks: bool,
+    pub(crate) max_unknown_chunk_size: usize,
+    pub(crate) xor_scheme: XorScheme,
+    pub(crate) lenient_offsets: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            unknown_chunks: false,
+            max_unknown_chunk_size: 4096,
+            xor_scheme: XorScheme::ReverseBits,
+            lenient_offsets: false,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Creates a new [`ParseOptions`] with the default options.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether stream header chunks of unknown types are kept, instead of being rejected.
+    /// Kept chunks can be accessed with [`StreamInfo::unknown_chunks`]. This is disabled by default.
+    ///
+    /// [`StreamInfo::unknown_chunks`]: crate::StreamInfo::unknown_chunks
+    #[must_use]
+    pub fn unknown_chunks(mut self, keep: bool) -> Self {
+        self.unknown_chunks = keep;
+        self
+    }
+
+    /// Sets the maximum number of bytes kept from each chunk of an unknown type; the rest of the chunk is skipped.
+    /// This limits memory usage for corrupt or malicious files. The default is 4096 bytes.
+    #[must_use]
+    pub fn max_unknown_chunk_size(mut self, size: usize) -> Self {
+        self.max_unknown_chunk_size = size;
+        self
+    }
+
+    /// Sets the scheme used to decrypt sound banks created with [`Bank::decrypt_with_options`].
+    /// The default is [`XorScheme::ReverseBits`], which is the scheme used by FMOD.
+    ///
+    /// [`Bank::decrypt_with_options`]: crate::Bank::decrypt_with_options
+    #[must_use]
+    pub fn xor_scheme(mut self, scheme: XorScheme) -> Self {
+        self.xor_scheme = scheme;
+        self
+    }
+
+    /// Sets whether stream data offsets that overlap or go past the end of stream data are accepted,
+    /// instead of being rejected. This is disabled by default.
+    ///
+    /// Accepted offsets are clamped so that streams don't overlap, and a warning is recorded for each of them,
+    /// which can be accessed with [`Bank::offset_warnings`].
+    ///
+    /// [`Bank::offset_warnings`]: crate::Bank::offset_warnings
+    #[must_use]
+    pub fn lenient_offsets(mut self, lenient: bool) -> Self {
+        self.lenient_offsets = lenient;
+        self
+    }
+}
@@ -1,6 +1,6 @@
 //! # fsbex
 //!
-//! `fsbex` is a library for extracting audio from FMOD sound banks. Only FSB version 5 is supported for now.
+//! `fsbex` is a library for extracting audio from FMOD sound banks. FSB version 5 is supported, as well as FSB version 3 and 4 banks with PCM, IMA ADPCM, MPEG, or GC ADPCM streams.
 //!
 //! ## Example
 //!
@@ -10,14 +10,13 @@
 //! use fsbex::{Bank, AudioFormat};
 //! use std::{
 //!     error::Error,
-//!     io::{BufReader, BufWriter},
+//!     io::BufWriter,
 //!     fs::File,
 //! };
 //!
 //! fn main() -> Result<(), Box<dyn Error>> {
-//!     // open file for reading sound bank
-//!     let file = BufReader::new(File::open("example.fsb")?);
-//!     let bank = Bank::new(file)?;
+//!     // open file and parse sound bank
+//!     let bank = Bank::open("example.fsb")?;
 //!
 //!     // report number of streams contained within the sound bank
 //!     println!("{} streams within this sound bank", bank.num_streams());
@@ -50,17 +49,142 @@
 //! `fsbex` supports encoding stream data for the following formats:
 //! - PCM (8, 16, 24, 32-bit integer)
 //! - PCM (32-bit float)
-//! - Vorbis
+//! - Vorbis (requires the `decode-vorbis` feature, which is enabled by default; re-encoded as Ogg Vorbis with the `vorbis` feature, which is also enabled by default; can also be repackaged as Ogg Vorbis from the original audio packets, or decoded to 16-bit PCM)
+//! - MPEG (MP3 files with an ID3v2 tag and a Xing header; streams with more than 2 channels are written as one file per stereo layer; can also be decoded to 16-bit PCM with the `decode-mpeg` feature)
+//! - FADPCM (decoded to 16-bit PCM)
+//! - IMA ADPCM (decoded to 16-bit PCM)
+//! - GC ADPCM (decoded to 16-bit PCM)
+//! - XMA (XMA2 RIFF container)
+//! - ATRAC9 (ATRAC9 RIFF container)
+//! - CELT (raw length-prefixed CELT 0.11 packets)
+//! - Opus (Ogg Opus container)
+//! - HEVAG (decoded to 16-bit PCM; only the standard VAG filter coefficients are supported)
+//! - VAG (`VAGp` container)
+//! - xWMA (xWMA RIFF container)
+//!
+//! Streams written as PCM samples (including decoded streams) can also be written as AIFF or CAF files, or as FLAC files with the `flac` feature.
+//!
+//! Sound banks embedded in FMOD Studio bank files (.bank) can be read with the [`studio`] module.
+//!
+//! ## Serialization
+//!
+//! With the `serde` feature, the metadata of sound banks and streams implements `serde::Serialize`,
+//! so an inventory of a sound bank can be stored without its stream data. See the implementation
+//! on [`Bank`] for the serialized fields.
+//!
+//! ## Memory-mapped files
+//!
+//! The raw data of streams in sound banks held in memory can be borrowed with [`Bank::stream_data`],
+//! without copying it. With the `mmap` feature, `Bank::from_mmap` maps a sound bank file into memory,
+//! so that large sound banks are parsed and accessed without read calls or intermediate buffers.
+//! Mapping files is `unsafe`, since the file must not be modified while it's mapped.
+//!
+//! ## Streaming output
+//!
+//! [`Stream::into_reader`] converts a stream into a [`Read`](std::io::Read) over its encoded file,
+//! which encodes the stream as it is read, such as when it is served over HTTP.
+//! Most formats are encoded a chunk at a time, so the encoded file is never held in memory at once.
+//!
+//! ## Asynchronous I/O
+//!
+//! With the `async` feature, `AsyncBank` parses sound banks from tokio's asynchronous readers and extracts
+//! streams to asynchronous writers, so that extraction doesn't block a thread while waiting for I/O.
+//!
+//! ## Parallel extraction
+//!
+//! With the `parallel` feature, `Bank::extract_all_parallel` encodes streams across a
+//! [rayon](https://crates.io/crates/rayon) thread pool, which is much faster for sound banks with many streams
+//! that are expensive to encode. Errors of single streams are collected instead of stopping the extraction,
+//! unless `ParallelOptions::fail_fast` is set.
+//!
+//! ## Playback
+//!
+//! The decoded samples of a stream can be iterated over with [`Stream::samples`].
+//! With the `rodio` feature, `Stream::into_source` converts a stream into a `rodio::Source`,
+//! so that it can be played with [rodio](https://crates.io/crates/rodio).
+//!
+//! With the `symphonia` feature, `Stream::media_source` writes a stream in memory to a format that
+//! [Symphonia](https://crates.io/crates/symphonia) can decode (fixed MP3 files, rebuilt Ogg Vorbis files,
+//! or WAVE files), so that streams can be decoded without temporary files.
+//!
+//! ## C API
+//!
+//! With the `ffi` feature, the `ffi` module exposes a C API for opening sound banks, reading stream
+//! information, and extracting streams, so that `fsbex` can be used as a dynamic library from other languages.
+//! `include/fsbex.h` declares the API for C and C++.
+//!
+//! ## WebAssembly
+//!
+//! The crate builds for `wasm32-unknown-unknown` without the `vorbis` feature, which needs libvorbis;
+//! Vorbis streams then have to be repackaged as Ogg Vorbis files from their original audio packets
+//! (or decoded), which the `wasm` module does.
+//! With the `wasm` feature, the `wasm` module exposes a JavaScript API with wasm-bindgen for parsing
+//! sound banks, listing their streams, and extracting streams in web browsers.
 
+#[cfg(feature = "async")]
+mod asynchronous;
 mod bank;
+mod checksum;
+mod decrypt;
 pub mod encode;
+mod extract;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod file;
 mod header;
+mod metadata;
+#[cfg(feature = "parallel")]
+mod parallel;
 mod read;
+mod scan;
+#[cfg(feature = "serde")]
+mod serialize;
 mod stream;
+pub mod studio;
+#[cfg(test)]
+mod test_bank;
+mod verify;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "async")]
+pub use asynchronous::AsyncBank;
+pub use bank::{
+    Bank, DecodeError, ExtractByNameError, ExtractByNameErrorKind, LazyStreamError, NameMatch,
+    SeekStreamError, SeekStreamErrorKind,
+};
+pub use checksum::{Checksum, ChecksumAlgorithm, StreamChecksums};
+pub use decrypt::{guess_key, guess_key_with_max_len, Decrypt, KeyCandidate, XorScheme};
+pub use extract::{
+    DirectoryFactory, ExtractError, ExtractErrorKind, ExtractOptions, ExtractedFile,
+    ExtractionReport, LenientReport, PartialStream, Progress, SinkFactory,
+};
+pub use file::{BankFile, OpenError, OpenErrorKind};
+pub use header::{
+    sanitize_file_name, sanitize_file_path, AudioFormat, BankFlags, Loop, OffsetWarning,
+    OffsetWarningKind, ParseAudioFormatError, ParseOptions, StreamInfo, VorbisSeekEntry,
+};
+#[cfg(feature = "parallel")]
+pub use parallel::ParallelOptions;
+pub use scan::{scan, FoundBank, Scan};
+pub use stream::{LazyStream, Stream, StreamIntoIter, Streams};
+pub use verify::{Finding, FindingKind, Severity, VerifyOptions, VerifyReport};
+
+// The FLAC decoder is only used by tests of the `flac` feature.
+#[cfg(all(test, not(feature = "flac")))]
+use claxon as _;
+
+// The async runtime is only used by tests of the `async` feature.
+#[cfg(all(test, not(feature = "async")))]
+use tokio as _;
+
+// The JSON serializer is only used by tests of the `serde` feature.
+#[cfg(all(test, not(feature = "serde")))]
+use serde_json as _;
 
-pub use bank::{Bank, DecodeError, LazyStreamError};
-pub use header::{AudioFormat, Loop};
-pub use stream::{LazyStream, Stream, StreamIntoIter};
+// Symphonia's decoders are only used by tests of the `symphonia` feature.
+#[cfg(all(test, not(feature = "symphonia")))]
+use symphonia as _;
 
 // Decoding and encoding involves casting values from u32 to usize.
 // To ensure correct conversions, only compilation targets where usize is at least 32 bits are allowed.
@@ -0,0 +1,208 @@
+// Writes the metadata of a sound bank as JSON, without depending on serde.
+// The output has the same fields as the `Serialize` implementation of `Bank` (enabled by the `serde` feature),
+// so the schema is documented once, on `Bank`. Fields must stay the same between releases.
+
+use crate::header::{OffsetWarningKind, StreamInfo};
+use crate::Bank;
+use std::io::{Read, Result as IoResult, Write};
+
+pub(crate) fn write_bank<R: Read, W: Write>(bank: &Bank<R>, out: &mut W) -> IoResult<()> {
+    write!(
+        out,
+        "{{\"index\":{},\"version\":{},\"minor_version\":{},\"format\":",
+        bank.index(),
+        bank.version(),
+        bank.minor_version()
+    )?;
+    write_str(out, &bank.format().to_string())?;
+    write!(out, ",\"flags\":{},\"hash\":", bank.flags().bits())?;
+    write_array(out, bank.hash(), |out, byte| write!(out, "{byte}"))?;
+    out.write_all(b",\"guid\":")?;
+    write_option(out, bank.guid(), |out, guid| {
+        write_array(out, &guid, |out, byte| write!(out, "{byte}"))
+    })?;
+
+    out.write_all(b",\"offset_warnings\":")?;
+    write_array(out, bank.offset_warnings(), |out, warning| {
+        let kind = match warning.kind() {
+            OffsetWarningKind::Overlap => "overlap",
+            OffsetWarningKind::OutOfBounds => "out_of_bounds",
+        };
+
+        write!(
+            out,
+            "{{\"index\":{},\"kind\":\"{kind}\",\"offset\":{}}}",
+            warning.index(),
+            warning.offset()
+        )
+    })?;
+
+    // Every stream is written on its own line, so that outputs can be compared with line-based diff tools.
+    write!(out, ",\"num_streams\":{},\"streams\":[", bank.num_streams())?;
+
+    for (index, info) in bank.stream_infos().iter().enumerate() {
+        if index > 0 {
+            out.write_all(b",")?;
+        }
+
+        out.write_all(b"\n")?;
+        write_stream(out, info)?;
+    }
+
+    out.write_all(b"\n]}")
+}
+
+fn write_stream<W: Write>(out: &mut W, info: &StreamInfo) -> IoResult<()> {
+    out.write_all(b"{\"name\":")?;
+    write_option(out, info.name(), write_str)?;
+    out.write_all(b",\"codec\":")?;
+    write_str(out, &info.codec().to_string())?;
+
+    write!(
+        out,
+        ",\"sample_rate\":{},\"channels\":{},\"sample_count\":{},\"loop\":",
+        info.sample_rate(),
+        info.channels(),
+        info.sample_count()
+    )?;
+    write_option(out, info.loop_info(), |out, stream_loop| {
+        write!(
+            out,
+            "{{\"start\":{},\"end\":{}}}",
+            stream_loop.start(),
+            stream_loop.end()
+        )
+    })?;
+
+    write!(
+        out,
+        ",\"data_offset\":{},\"size\":{},\"peak_volume\":",
+        info.data_offset(),
+        info.size()
+    )?;
+    // JSON has no representation of infinite values or NaN.
+    write_option(
+        out,
+        info.peak_volume().filter(|volume| volume.is_finite()),
+        |out, volume| write!(out, "{volume}"),
+    )?;
+
+    write!(
+        out,
+        ",\"channels_overridden\":{},\"atrac9_config\":",
+        info.channels_overridden()
+    )?;
+    write_option(out, info.atrac9_config(), |out, configs| {
+        write_array(out, configs, |out, config| {
+            write_array(out, config, |out, byte| write!(out, "{byte}"))
+        })
+    })?;
+
+    out.write_all(b",\"xma_seek_table\":")?;
+    write_option(out, info.xma_seek_table(), |out, table| {
+        write_array(out, table, |out, offset| write!(out, "{offset}"))
+    })?;
+    out.write_all(b",\"vorbis_crc32\":")?;
+    write_option(out, info.vorbis_crc32(), |out, crc32| write!(out, "{crc32}"))?;
+    out.write_all(b",\"vorbis_seek_table\":")?;
+    write_option(out, info.vorbis_seek_table(), |out, table| {
+        write_array(out, table, |out, entry| {
+            write!(
+                out,
+                "{{\"granule\":{},\"offset\":{}}}",
+                entry.granule(),
+                entry.offset()
+            )
+        })
+    })?;
+
+    out.write_all(b",\"unknown_chunks\":")?;
+    write_array(out, info.unknown_chunks(), |out, (kind, data)| {
+        write!(out, "{{\"type\":{kind},\"size\":{}}}", data.len())
+    })?;
+
+    out.write_all(b"}")
+}
+
+fn write_array<W: Write, T>(
+    out: &mut W,
+    items: &[T],
+    mut write_item: impl FnMut(&mut W, &T) -> IoResult<()>,
+) -> IoResult<()> {
+    out.write_all(b"[")?;
+
+    for (index, item) in items.iter().enumerate() {
+        if index > 0 {
+            out.write_all(b",")?;
+        }
+
+        write_item(out, item)?;
+    }
+
+    out.write_all(b"]")
+}
+
+fn write_option<W: Write, T>(
+    out: &mut W,
+    value: Option<T>,
+    write_value: impl FnOnce(&mut W, T) -> IoResult<()>,
+) -> IoResult<()> {
+    match value {
+        Some(value) => write_value(out, value),
+        None => out.write_all(b"null"),
+    }
+}
+
+// Escapes quotes, backslashes, and control characters, which can't appear unescaped in JSON strings.
+fn write_str<W: Write>(out: &mut W, s: &str) -> IoResult<()> {
+    out.write_all(b"\"")?;
+
+    let mut start = 0;
+
+    for (index, c) in s.char_indices() {
+        let escaped = match c {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
+            '\u{08}' => "\\b",
+            '\u{0C}' => "\\f",
+            c if c < ' ' => "",
+            _ => continue,
+        };
+
+        out.write_all(&s.as_bytes()[start..index])?;
+        start = index + c.len_utf8();
+
+        if escaped.is_empty() {
+            write!(out, "\\u{:04x}", u32::from(c))?;
+        } else {
+            out.write_all(escaped.as_bytes())?;
+        }
+    }
+
+    out.write_all(&s.as_bytes()[start..])?;
+    out.write_all(b"\"")
+}
+
+#[cfg(test)]
+mod test {
+    use super::write_str;
+
+    fn escape(s: &str) -> String {
+        let mut out = Vec::new();
+        write_str(&mut out, s).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn escape_json_strings() {
+        assert_eq!(escape("bgm_01"), "\"bgm_01\"");
+        assert_eq!(escape("say \"hi\""), "\"say \\\"hi\\\"\"");
+        assert_eq!(escape("a\\b/c"), "\"a\\\\b/c\"");
+        assert_eq!(escape("line\nbreak\ttab"), "\"line\\nbreak\\ttab\"");
+        assert_eq!(escape("\u{0}\u{1f}"), "\"\\u0000\\u001f\"");
+        assert_eq!(escape("ボイス"), "\"ボイス\"");
+    }
+}
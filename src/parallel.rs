@@ -0,0 +1,279 @@
+use crate::checksum::{checksum, ChecksumWriter, StreamChecksums};
+use crate::encode::{encode_with_stats, EmptyStreams};
+use crate::extract::{
+    ExtractError, ExtractionReport, LenientReport, PartialStream, Progress, ProgressSink,
+    SinkFactory,
+};
+use crate::header::{AudioFormat, Header, StreamInfo};
+use crate::read::Reader;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::{
+    borrow::Cow,
+    io::Read,
+    mem,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+/// Options that control how streams are extracted by [`Bank::extract_all_parallel`].
+///
+/// [`Bank::extract_all_parallel`]: crate::Bank::extract_all_parallel
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ParallelOptions {
+    fail_fast: bool,
+}
+
+impl ParallelOptions {
+    /// Creates a new [`ParallelOptions`] with the default options.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether the first stream that fails to be extracted stops the extraction, returning its error.
+    /// Otherwise, the errors of failed streams are listed in [`LenientReport::errors`]. This is disabled by default.
+    #[must_use]
+    pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+}
+
+// Extracts every stream for `Bank::extract_all_parallel_with_progress`, reading stream data from `read`,
+// which is at the start of the stream data.
+pub(crate) fn extract_all<R, F, P>(
+    header: &Header,
+    read: &mut Reader<R>,
+    mut factory: F,
+    options: ParallelOptions,
+    on_progress: P,
+) -> Result<LenientReport<F::Error>, ExtractError<F::Error>>
+where
+    R: Read,
+    F: SinkFactory,
+    F::Sink: Send,
+    F::Error: Send,
+    P: Fn(Progress) + Sync,
+{
+    let mut report = LenientReport {
+        report: ExtractionReport::default(),
+        errors: Vec::new(),
+    };
+    let num_streams = u32::try_from(header.stream_info.len())
+        .expect("stream count was already validated to be NonZeroU32");
+    let total_size = header.stream_info.iter().map(|info| u64::from(info.size)).sum();
+    let completed_size = AtomicU64::new(0);
+
+    let finish = |index: u32, stream_size: u32, bytes_written: u64| {
+        on_progress(Progress {
+            index,
+            num_streams,
+            bytes_written,
+            stream_size,
+            completed_size: completed_size.fetch_add(u64::from(stream_size), Ordering::Relaxed),
+            total_size,
+            stream_done: true,
+        });
+    };
+
+    let batch_size = rayon::current_num_threads() * PARALLEL_BATCH_STREAMS;
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut truncated = false;
+
+    for (info, index) in header.stream_info.iter().zip(0..) {
+        // no stream data is left after the stream where a truncated sound bank ends
+        if truncated {
+            report.report.missing_streams.push(index);
+            finish(index, info.size, 0);
+            continue;
+        }
+
+        let size = info.size as usize;
+
+        let sink = create_sink(&mut factory, index, info, options, &mut report)?;
+
+        let Some(sink) = sink else {
+            let available = read
+                .skip_available(size as u64)
+                .map_err(ExtractError::from_read(index))?;
+
+            truncated = available < size as u64;
+            finish(index, info.size, 0);
+            continue;
+        };
+
+        let data = read.take_available(size).map_err(ExtractError::from_read(index))?;
+
+        // streams cut short are encoded as if their size was the size of the data that's left
+        truncated = data.len() < size;
+        let info = if truncated {
+            let partial_info = StreamInfo {
+                size: u32::try_from(data.len()).expect("data is smaller than the stream"),
+                ..info.clone()
+            };
+            report.report.partial_streams.push(PartialStream {
+                index,
+                missing: info.size - partial_info.size,
+            });
+            Cow::Owned(partial_info)
+        } else {
+            Cow::Borrowed(info)
+        };
+
+        batch.push(ParallelJob {
+            index,
+            size: info.size,
+            info,
+            data,
+            sink,
+        });
+
+        if batch.len() == batch_size {
+            encode_batch(
+                header.format,
+                header.flags,
+                &mut batch,
+                &factory,
+                options,
+                &mut report,
+                &finish,
+            )?;
+        }
+    }
+
+    encode_batch(
+        header.format,
+        header.flags,
+        &mut batch,
+        &factory,
+        options,
+        &mut report,
+        &finish,
+    )?;
+
+    // errors of streams in a batch are collected after the errors from creating writers for the batch
+    report.errors.sort_by_key(ExtractError::index);
+
+    Ok(report)
+}
+
+// The number of streams read into memory for every thread, before they are encoded in parallel.
+const PARALLEL_BATCH_STREAMS: usize = 4;
+
+// A stream read into memory by `Bank::extract_all_parallel`, which is encoded to its writer on another thread.
+struct ParallelJob<'info, W> {
+    index: u32,
+    // size of the stream as stored in the file header, which is larger than `data` for streams cut short
+    size: u32,
+    info: Cow<'info, StreamInfo>,
+    data: Vec<u8>,
+    sink: W,
+}
+
+// Creates the writer of a stream for `Bank::extract_all_parallel`, returning `None` for skipped streams,
+// and for streams that failed to have their writer created when not failing fast.
+fn create_sink<F: SinkFactory>(
+    factory: &mut F,
+    index: u32,
+    info: &StreamInfo,
+    options: ParallelOptions,
+    report: &mut LenientReport<F::Error>,
+) -> Result<Option<F::Sink>, ExtractError<F::Error>> {
+    // placeholder streams are skipped before a writer is created for them
+    if info.size == 0 && factory.options().empty_streams == EmptyStreams::Skip {
+        factory.skipped(index, info);
+        report.report.skipped_streams.push(index);
+        return Ok(None);
+    }
+
+    match factory.create(index, info) {
+        Ok(Some(sink)) => Ok(Some(sink)),
+        Ok(None) => {
+            report.report.skipped_streams.push(index);
+            Ok(None)
+        }
+        Err(e) if options.fail_fast => Err(ExtractError::from_factory(index)(e)),
+        Err(e) => {
+            report.errors.push(ExtractError::from_factory(index)(e));
+            Ok(None)
+        }
+    }
+}
+
+// Encodes every stream of `batch` in parallel, emptying it.
+fn encode_batch<F>(
+    format: AudioFormat,
+    flags: u32,
+    batch: &mut Vec<ParallelJob<'_, F::Sink>>,
+    factory: &F,
+    options: ParallelOptions,
+    report: &mut LenientReport<F::Error>,
+    finish: &(dyn Fn(u32, u32, u64) + Sync),
+) -> Result<(), ExtractError<F::Error>>
+where
+    F: SinkFactory,
+    F::Sink: Send,
+    F::Error: Send,
+{
+    let encode_options = factory.options();
+    let algorithm = factory.checksum();
+    let failed = AtomicBool::new(false);
+
+    let results: Vec<_> = mem::take(batch)
+        .into_par_iter()
+        .filter_map(|job| {
+            // with fail-fast, streams that haven't started are skipped once any stream failed
+            if options.fail_fast && failed.load(Ordering::Relaxed) {
+                return None;
+            }
+
+            let mut on_write = |_| {};
+            let source_checksum = algorithm.map(|algorithm| checksum(algorithm, &job.data));
+            let sink = ChecksumWriter::new(
+                ProgressSink::new(job.sink, Progress::default(), &mut on_write),
+                algorithm,
+            );
+
+            match encode_with_stats(
+                format,
+                flags,
+                job.index,
+                &job.info,
+                &mut Reader::new(job.data.as_slice()),
+                sink,
+                encode_options,
+            ) {
+                Ok((sink, stats)) => {
+                    let (sink, output_checksum) = sink.finish();
+                    finish(job.index, job.size, sink.into_progress().bytes_written);
+
+                    let checksums = source_checksum.zip(output_checksum).map(|(source, output)| {
+                        StreamChecksums {
+                            index: job.index,
+                            source,
+                            output,
+                        }
+                    });
+                    Some(Ok((job.index, checksums, stats)))
+                }
+                Err(e) => {
+                    failed.store(true, Ordering::Relaxed);
+                    Some(Err(ExtractError::from_encode(job.index)(e)))
+                }
+            }
+        })
+        .collect();
+
+    for result in results {
+        match result {
+            Ok((index, checksums, stats)) => {
+                report.report.extracted_streams.push(index);
+                report.report.checksums.extend(checksums);
+                report.report.add_stats(index, stats);
+            }
+            Err(e) if options.fail_fast => return Err(e),
+            Err(e) => report.errors.push(e),
+        }
+    }
+
+    Ok(())
+}
@@ -2,14 +2,44 @@ use std::{
     cmp::min,
     error::Error,
     fmt::{Display, Formatter, Result as FmtResult},
-    io::{BufRead, Error as IoError, ErrorKind, Read},
+    io::{
+        copy, sink, BufRead, Error as IoError, ErrorKind, Read, Result as IoResult, Seek, SeekFrom,
+    },
     num::NonZeroUsize,
 };
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub(crate) struct Reader<R: Read> {
     inner: R,
-    position: usize,
+    position: u64,
+    big_endian: bool,
+    // Only set for sources that can seek, which lets stream data be read more than once instead of being buffered.
+    // The seek function is stored instead of requiring `R: Seek`, so that every reader has the same type.
+    seek: Option<fn(&mut R, SeekFrom) -> IoResult<u64>>,
+}
+
+// Seek functions are compared by whether they exist, since function pointers can't be compared meaningfully.
+impl<R: Read + PartialEq> PartialEq for Reader<R> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+            && self.position == other.position
+            && self.big_endian == other.big_endian
+            && self.seek.is_some() == other.seek.is_some()
+    }
+}
+
+impl<R: Read + Eq> Eq for Reader<R> {}
+
+impl<R: Read + Seek> Reader<R> {
+    pub(crate) fn seekable(reader: R) -> Self {
+        let mut reader = Self::new(reader);
+        reader.enable_seeking();
+        reader
+    }
+
+    pub(crate) fn enable_seeking(&mut self) {
+        self.seek = Some(R::seek);
+    }
 }
 
 impl<R: Read> Reader<R> {
@@ -17,63 +47,72 @@ impl<R: Read> Reader<R> {
         Self {
             inner: reader,
             position: 0,
+            big_endian: false,
+            seek: None,
         }
     }
 
+    // Sets the byte order of numbers read with `u16`, `u32`, and `u64`, which is little-endian by default.
+    // File headers of sound banks for some consoles are big-endian.
+    pub(crate) fn set_big_endian(&mut self, big_endian: bool) {
+        self.big_endian = big_endian;
+    }
+
     fn read_to_array<const LEN: usize>(&mut self, buf: &mut [u8; LEN]) -> ReadResult<()> {
-        match self.inner.read(buf) {
-            Ok(n) => {
-                self.position += n;
-
-                if n == LEN {
-                    Ok(())
-                } else {
-                    Err(self.to_error(ReadErrorKind::Incomplete(Needed::Size(
-                        NonZeroUsize::new(LEN - n).expect("n is guaranteed to not equal LEN"),
-                    ))))
-                }
-            }
-            Err(e) => match e.kind() {
-                // this I/O error is non-fatal, so reading is retried
-                ErrorKind::Interrupted => self.read_to_array(buf),
-                ErrorKind::UnexpectedEof => {
-                    Err(self.to_error(ReadErrorKind::Incomplete(Needed::Unknown)))
-                }
-                _ => Err(self.to_error_with_source(ReadErrorKind::Failure, e)),
-            },
-        }
+        self.read_to_slice(buf)
     }
 
+    // Like `Read::read_exact`, this keeps reading until the buffer is full,
+    // since sources can return fewer bytes than requested (e.g. a file read one buffered chunk at a time).
     fn read_to_slice(&mut self, buf: &mut [u8]) -> ReadResult<()> {
-        match self.inner.read(buf) {
-            Ok(n) => {
-                self.position += n;
-                let buf_len = buf.len();
-
-                if n == buf_len {
-                    Ok(())
-                } else {
-                    Err(self.to_error(ReadErrorKind::Incomplete(Needed::Size(
-                        NonZeroUsize::new(buf_len - n)
-                            .expect("n is guaranteed to not equal buf_len"),
-                    ))))
+        let mut filled = 0;
+
+        loop {
+            match self.inner.read(&mut buf[filled..]) {
+                Ok(n) => {
+                    self.position += n as u64;
+                    filled += n;
+
+                    match NonZeroUsize::new(buf.len() - filled) {
+                        None => return Ok(()),
+                        Some(needed) if n == 0 => {
+                            return Err(
+                                self.to_error(ReadErrorKind::Incomplete(Needed::Size(needed)))
+                            );
+                        }
+                        Some(_) => {}
+                    }
                 }
+                Err(e) => match e.kind() {
+                    // this I/O error is non-fatal, so reading is retried
+                    ErrorKind::Interrupted => {}
+                    ErrorKind::UnexpectedEof => {
+                        return Err(self.to_error(ReadErrorKind::Incomplete(Needed::Unknown)));
+                    }
+                    _ => return Err(self.to_error_with_source(ReadErrorKind::Failure, e)),
+                },
             }
-            Err(e) => match e.kind() {
-                // this I/O error is non-fatal, so reading is retried
-                ErrorKind::Interrupted => self.read_to_slice(buf),
-                ErrorKind::UnexpectedEof => {
-                    Err(self.to_error(ReadErrorKind::Incomplete(Needed::Unknown)))
-                }
-                _ => Err(self.to_error_with_source(ReadErrorKind::Failure, e)),
-            },
         }
     }
 
-    pub(crate) fn position(&self) -> usize {
+    pub(crate) fn position(&self) -> u64 {
         self.position
     }
 
+    pub(crate) fn into_inner(self) -> R {
+        self.inner
+    }
+
+    pub(crate) fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    // Returns the number of bytes read since `start`, such as the start of a stream.
+    // Streams are smaller than 4 GiB, so this always fits in a usize on supported targets.
+    pub(crate) fn bytes_since(&self, start: u64) -> usize {
+        usize::try_from(self.position - start).unwrap_or(usize::MAX)
+    }
+
     pub(crate) fn take_const<const LEN: usize>(&mut self) -> ReadResult<[u8; LEN]> {
         let mut buf = [0; LEN];
         Self::read_to_array(self, &mut buf)?;
@@ -86,17 +125,91 @@ impl<R: Read> Reader<R> {
         Ok(buf)
     }
 
-    pub(crate) fn skip(&mut self, amount: usize) -> ReadResult<()> {
-        let mut buf = vec![0; amount];
-        Self::read_to_slice(self, buf.as_mut_slice())
+    // Reads up to `len` bytes, stopping early at the end of the data instead of failing.
+    pub(crate) fn take_available(&mut self, len: usize) -> ReadResult<Vec<u8>> {
+        let mut buf = Vec::new();
+        let result = self.limit(len as u64).read_to_end(&mut buf);
+
+        match result {
+            Ok(_) => Ok(buf),
+            Err(e) => Err(self.to_error_with_source(ReadErrorKind::Failure, e)),
+        }
     }
 
-    pub(crate) fn advance_to(&mut self, position: usize) -> ReadResult<()> {
-        self.skip(position - self.position)
+    // Skips up to `amount` bytes, stopping early at the end of the data instead of failing.
+    // Returns the number of bytes that were skipped.
+    pub(crate) fn skip_available(&mut self, amount: u64) -> ReadResult<u64> {
+        let result = copy(&mut self.limit(amount), &mut sink());
+
+        match result {
+            Ok(n) => Ok(n),
+            Err(e) => Err(self.to_error_with_source(ReadErrorKind::Failure, e)),
+        }
+    }
+
+    // Skipped bytes are discarded as they are read, so skipping large amounts (e.g. entire streams)
+    // doesn't buffer them in memory.
+    pub(crate) fn skip(&mut self, amount: u64) -> ReadResult<()> {
+        let skipped = self.skip_available(amount)?;
+
+        match NonZeroUsize::new(usize::try_from(amount - skipped).unwrap_or(usize::MAX)) {
+            Some(needed) => Err(self.to_error(ReadErrorKind::Incomplete(Needed::Size(needed)))),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn advance_to(&mut self, position: u64) -> ReadResult<()> {
+        match position.checked_sub(self.position) {
+            Some(amount) => self.skip(amount),
+            None => Err(self.to_error(ReadErrorKind::Backwards { position })),
+        }
+    }
+
+    pub(crate) fn can_seek(&self) -> bool {
+        self.seek.is_some()
+    }
+
+    // Moves to `position`, which can be before the current position if the source can seek.
+    // Sources that can't seek only move forward, like with `advance_to`.
+    pub(crate) fn seek_to(&mut self, position: u64) -> ReadResult<()> {
+        let Some(seek) = self.seek else {
+            return self.advance_to(position);
+        };
+
+        let offset = if position >= self.position {
+            i64::try_from(position - self.position)
+        } else {
+            i64::try_from(self.position - position).map(|offset| -offset)
+        };
+
+        let result = offset
+            .map_err(|_| IoError::from(ErrorKind::InvalidInput))
+            .and_then(|offset| seek(&mut self.inner, SeekFrom::Current(offset)));
+
+        match result {
+            Ok(_) => {
+                self.position = position;
+                Ok(())
+            }
+            Err(e) => Err(self.to_error_with_source(ReadErrorKind::Failure, e)),
+        }
+    }
+
+    // Returns the number of bytes left in a source that can seek, or `None` if the source can't seek.
+    pub(crate) fn remaining(&mut self) -> Option<ReadResult<u64>> {
+        let seek = self.seek?;
+
+        let result = seek(&mut self.inner, SeekFrom::Current(0)).and_then(|current| {
+            let end = seek(&mut self.inner, SeekFrom::End(0))?;
+            let _current = seek(&mut self.inner, SeekFrom::Start(current))?;
+            Ok(end.saturating_sub(current))
+        });
+
+        Some(result.map_err(|e| self.to_error_with_source(ReadErrorKind::Failure, e)))
     }
 
     // `std::io::Take` isn't used here because constructing it requires taking ownership of the reader
-    pub(crate) fn limit(&mut self, limit: usize) -> CappedReader<'_, R> {
+    pub(crate) fn limit(&mut self, limit: u64) -> CappedReader<'_, R> {
         CappedReader {
             reader: self,
             limit,
@@ -109,6 +222,30 @@ impl<R: Read> Reader<R> {
         Ok(buf[0])
     }
 
+    pub(crate) fn u16(&mut self) -> ReadResult<u16> {
+        if self.big_endian {
+            self.be_u16()
+        } else {
+            self.le_u16()
+        }
+    }
+
+    pub(crate) fn u32(&mut self) -> ReadResult<u32> {
+        if self.big_endian {
+            self.be_u32()
+        } else {
+            self.le_u32()
+        }
+    }
+
+    pub(crate) fn u64(&mut self) -> ReadResult<u64> {
+        if self.big_endian {
+            self.be_u64()
+        } else {
+            self.le_u64()
+        }
+    }
+
     pub(crate) fn le_u16(&mut self) -> ReadResult<u16> {
         let mut buf = [0; 2];
         Self::read_to_array(self, &mut buf)?;
@@ -127,56 +264,102 @@ impl<R: Read> Reader<R> {
         Ok(u64::from_le_bytes(buf))
     }
 
+    pub(crate) fn be_u16(&mut self) -> ReadResult<u16> {
+        let mut buf = [0; 2];
+        Self::read_to_array(self, &mut buf)?;
+        Ok(u16::from_be_bytes(buf))
+    }
+
     pub(crate) fn be_i16(&mut self) -> ReadResult<i16> {
         let mut buf = [0; 2];
         Self::read_to_array(self, &mut buf)?;
         Ok(i16::from_be_bytes(buf))
     }
+
+    pub(crate) fn be_u32(&mut self) -> ReadResult<u32> {
+        let mut buf = [0; 4];
+        Self::read_to_array(self, &mut buf)?;
+        Ok(u32::from_be_bytes(buf))
+    }
+
+    pub(crate) fn be_u64(&mut self) -> ReadResult<u64> {
+        let mut buf = [0; 8];
+        Self::read_to_array(self, &mut buf)?;
+        Ok(u64::from_be_bytes(buf))
+    }
 }
 
 // essentially `std::io::Take` but with a mutable reference to a reader instead of owning it
 pub(crate) struct CappedReader<'reader, R: Read> {
     reader: &'reader mut Reader<R>,
-    limit: usize,
+    limit: u64,
 }
 
-impl<'reader, R: Read> Read for CappedReader<'reader, R> {
+impl<R: Read> Read for CappedReader<'_, R> {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
         if self.limit == 0 {
             return Ok(0);
         }
 
-        let max = min(buf.len(), self.limit);
+        let max = capped(buf.len(), self.limit);
         let n = self.reader.inner.read(&mut buf[..max])?;
-        self.reader.position += n;
-        self.limit -= n;
+        self.reader.position += n as u64;
+        self.limit -= n as u64;
         Ok(n)
     }
 }
 
-impl<'reader, R: BufRead> BufRead for CappedReader<'reader, R> {
+impl<R: BufRead> BufRead for CappedReader<'_, R> {
     fn fill_buf(&mut self) -> Result<&[u8], IoError> {
         if self.limit == 0 {
             return Ok(&[]);
         }
 
         let buf = self.reader.inner.fill_buf()?;
-        let cap = min(buf.len(), self.limit);
+        let cap = capped(buf.len(), self.limit);
         Ok(&buf[..cap])
     }
 
     fn consume(&mut self, amt: usize) {
-        let amt = min(amt, self.limit);
-        self.limit -= amt;
+        let amt = capped(amt, self.limit);
+        self.limit -= amt as u64;
         self.reader.inner.consume(amt);
     }
 }
 
+// Caps a length in memory to a limit, which can be larger than any usize.
+fn capped(len: usize, limit: u64) -> usize {
+    usize::try_from(limit).map_or(len, |limit| min(len, limit))
+}
+
+// Returns between 1 and 7 bytes per read, like a source that returns data in small chunks.
+#[cfg(test)]
+pub(crate) struct ShortReader<'data> {
+    data: &'data [u8],
+    reads: usize,
+}
+
+#[cfg(test)]
+impl<'data> ShortReader<'data> {
+    pub(crate) fn new(data: &'data [u8]) -> Self {
+        Self { data, reads: 0 }
+    }
+}
+
+#[cfg(test)]
+impl Read for ShortReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        self.reads += 1;
+        let len = buf.len().min(self.reads % 7 + 1);
+        self.data.read(&mut buf[..len])
+    }
+}
+
 type ReadResult<T> = Result<T, ReadError>;
 
 #[derive(Debug)]
 pub(crate) struct ReadError {
-    position: usize,
+    position: u64,
     kind: ReadErrorKind,
     source: Option<IoError>,
 }
@@ -186,6 +369,7 @@ pub(crate) struct ReadError {
 pub(crate) enum ReadErrorKind {
     Failure,
     Incomplete(Needed),
+    Backwards { position: u64 },
 }
 
 #[derive(Debug)]
@@ -230,6 +414,9 @@ impl Display for ReadError {
                 }
                 Needed::Unknown => f.write_str("incomplete data"),
             },
+            ReadErrorKind::Backwards { position } => f.write_fmt(format_args!(
+                "failed to advance to byte position {position}, which was already passed"
+            )),
         }?;
 
         f.write_fmt(format_args!(" - byte position {}", self.position))
@@ -247,9 +434,9 @@ impl Error for ReadError {
 
 #[cfg(test)]
 mod test {
-    use super::{Needed, ReadErrorKind, ReadResult, Reader};
+    use super::{Needed, ReadErrorKind, ReadResult, Reader, ShortReader};
     use std::{
-        io::{Error as IoError, ErrorKind, Read, Result as IoResult},
+        io::{Cursor, Error as IoError, ErrorKind, Read, Result as IoResult},
         num::NonZeroUsize,
     };
 
@@ -261,7 +448,7 @@ mod test {
         assert_eq!(reader.take_const().unwrap(), [97]);
         assert_eq!(reader.take_const().unwrap(), [98, 99]);
         assert_eq!(reader.take_const().unwrap(), [49, 50, 51]);
-        assert_eq!(reader.take_const().unwrap(), []);
+        assert_eq!(reader.take_const().unwrap(), [0u8; 0]);
         assert!(reader
             .take_const::<1>()
             .is_err_and(|e| e
@@ -283,6 +470,18 @@ mod test {
                 .is_kind(ReadErrorKind::Incomplete(Needed::Size(NonZeroUsize::new(1).unwrap())))));
     }
 
+    #[test]
+    fn read_available_bytes() {
+        let data = b"abc123";
+        let mut reader = Reader::new(data.as_slice());
+
+        assert_eq!(reader.take_available(2).unwrap(), b"ab");
+        assert_eq!(reader.skip_available(2).unwrap(), 2);
+        assert_eq!(reader.take_available(4).unwrap(), b"23");
+        assert_eq!(reader.position(), 6);
+        assert_eq!(reader.skip_available(1).unwrap(), 0);
+    }
+
     #[test]
     fn advance_to_position() {
         let data = b"abc123";
@@ -301,6 +500,47 @@ mod test {
             .advance_to(10)
             .is_err_and(|e| e
                 .is_kind(ReadErrorKind::Incomplete(Needed::Size(NonZeroUsize::new(4).unwrap())))));
+
+        assert!(reader
+            .advance_to(2)
+            .is_err_and(|e| e.is_kind(ReadErrorKind::Backwards { position: 2 })));
+    }
+
+    #[test]
+    fn seek_backwards() {
+        let data = b"abc123";
+        let mut reader = Reader::seekable(Cursor::new(data.as_slice()));
+
+        assert!(reader.seek_to(4).is_ok());
+        assert_eq!(reader.remaining().unwrap().unwrap(), 2);
+        assert_eq!(reader.take_const().unwrap(), *b"23");
+
+        assert!(reader.seek_to(1).is_ok());
+        assert_eq!(reader.position(), 1);
+        assert_eq!(reader.take_const().unwrap(), *b"bc");
+
+        // readers that can't seek only move forward
+        let mut reader = Reader::new(data.as_slice());
+        assert!(reader.remaining().is_none());
+        assert!(reader.seek_to(4).is_ok());
+        assert!(reader
+            .seek_to(1)
+            .is_err_and(|e| e.is_kind(ReadErrorKind::Backwards { position: 1 })));
+    }
+
+    #[test]
+    fn advance_past_u32_range() {
+        const POSITION: u64 = 5 << 30;
+
+        let data = std::io::repeat(0)
+            .take(POSITION)
+            .chain(b"\x01\x02\x03\x04".as_slice());
+        let mut reader = Reader::new(data);
+
+        assert!(reader.advance_to(POSITION).is_ok());
+        assert_eq!(reader.position(), POSITION);
+        assert_eq!(reader.le_u32().unwrap(), 0x0403_0201);
+        assert_eq!(reader.position(), POSITION + 4);
     }
 
     #[test]
@@ -333,6 +573,23 @@ mod test {
         assert_eq!(reader.u8().unwrap(), 34);
     }
 
+    #[test]
+    fn fill_buffers_from_short_reads() {
+        let data: Vec<u8> = (0..=255).collect();
+        let mut reader = Reader::new(ShortReader::new(&data));
+
+        assert_eq!(reader.take_const().unwrap(), [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        assert_eq!(reader.le_u64().unwrap(), 0x1110_0F0E_0D0C_0B0A);
+        assert_eq!(reader.be_u32().unwrap(), 0x1213_1415);
+        assert_eq!(reader.take(200).unwrap(), data[22..222]);
+        assert_eq!(reader.position(), 222);
+        assert!(reader
+            .take(40)
+            .is_err_and(|e| e
+                .is_kind(ReadErrorKind::Incomplete(Needed::Size(NonZeroUsize::new(6).unwrap())))));
+        assert_eq!(reader.position(), 256);
+    }
+
     #[test]
     fn handle_incomplete_data() {
         let data = b"\x00\x00";
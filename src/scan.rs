@@ -0,0 +1,323 @@
+use crate::header::{AudioFormat, FSB5_MAGIC};
+use std::{
+    cmp::min,
+    io::{Error as IoError, ErrorKind as IoErrorKind, Read},
+    num::NonZeroU32,
+};
+
+// Number of bytes read from the I/O stream at a time.
+const WINDOW_SIZE: usize = 64 * 1024;
+
+// A version 0 base header followed by the first stream header, which is enough to validate any FSB5 file header.
+pub(crate) const SCANNED_HEADER_SIZE: usize = 72;
+
+/// Searches an I/O stream for FSB5 sound banks, such as those inside packed archives or memory dumps.
+///
+/// The stream is read in fixed-size windows, so large files are never buffered in memory.
+/// Every `FSB5` signature is checked against the rest of the file header to avoid false positives,
+/// and the total size of each sound bank is calculated from its header so it can be carved out
+/// or parsed in place with [`Bank::at_offset`](crate::Bank::at_offset).
+///
+/// Searching continues right after each signature, so sound banks are found even when the header
+/// of a previous match claims a larger size than it really has.
+///
+/// # Examples
+///
+/// ```no_run
+/// use fsbex::{scan, Bank};
+/// use std::{error::Error, fs::File, io::BufReader};
+///
+/// fn list_banks(path: &str) -> Result<(), Box<dyn Error>> {
+///     for found in scan(File::open(path)?) {
+///         let found = found?;
+///         println!("{} bytes at offset {}", found.total_size(), found.offset());
+///
+///         let bank = Bank::at_offset(BufReader::new(File::open(path)?), found.offset())?;
+///         println!("{} streams", bank.num_streams());
+///     }
+///     Ok(())
+/// }
+/// ```
+pub fn scan<R: Read>(source: R) -> Scan<R> {
+    Scan {
+        source,
+        buf: Vec::new(),
+        buf_offset: 0,
+        position: 0,
+        finished: false,
+    }
+}
+
+/// An iterator over the FSB5 sound banks found in an I/O stream.
+///
+/// This struct is created by [`scan`]. Each item is either a [`FoundBank`],
+/// or an I/O error that occurred while reading, after which iteration ends.
+#[derive(Debug)]
+pub struct Scan<R> {
+    source: R,
+    buf: Vec<u8>,
+    // offset of the start of the buffer in the I/O stream
+    buf_offset: u64,
+    // position in the buffer to continue searching from
+    position: usize,
+    finished: bool,
+}
+
+impl<R: Read> Scan<R> {
+    // Discards the searched part of the buffer, then reads the next window of the I/O stream.
+    fn fill(&mut self) -> Result<(), IoError> {
+        drop(self.buf.drain(..self.position));
+        self.buf_offset += self.position as u64;
+        self.position = 0;
+
+        let len = self.buf.len();
+        self.buf.resize(len + WINDOW_SIZE, 0);
+
+        let result = loop {
+            match self.source.read(&mut self.buf[len..]) {
+                Err(e) if e.kind() == IoErrorKind::Interrupted => {}
+                result => break result,
+            }
+        };
+
+        match result {
+            Ok(read) => {
+                self.buf.truncate(len + read);
+                self.finished = read == 0;
+                Ok(())
+            }
+            Err(e) => {
+                self.buf.truncate(len);
+                self.finished = true;
+                Err(e)
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for Scan<R> {
+    type Item = Result<FoundBank, IoError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let found = self.buf[self.position..]
+                .windows(FSB5_MAGIC.len())
+                .position(|window| window == FSB5_MAGIC);
+
+            match found {
+                Some(found) => {
+                    let start = self.position + found;
+                    let available = self.buf.len() - start;
+
+                    if available >= SCANNED_HEADER_SIZE || self.finished {
+                        self.position = start + 1;
+                        let header =
+                            &self.buf[start..min(start + SCANNED_HEADER_SIZE, self.buf.len())];
+
+                        if let Some(bank) = FoundBank::parse(self.buf_offset + start as u64, header)
+                        {
+                            return Some(Ok(bank));
+                        }
+                        continue;
+                    }
+
+                    // the rest of the header is in the next window
+                    self.position = start;
+                }
+                // the end of the buffer could hold the start of a signature
+                None => {
+                    self.position =
+                        self.position.max(self.buf.len().saturating_sub(FSB5_MAGIC.len() - 1));
+                }
+            }
+
+            if self.finished {
+                return None;
+            }
+
+            if let Err(e) = self.fill() {
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+/// An FSB5 sound bank found in an I/O stream with [`scan`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FoundBank {
+    offset: u64,
+    version: u32,
+    num_streams: NonZeroU32,
+    format: AudioFormat,
+    total_size: u64,
+}
+
+impl FoundBank {
+    // Validates an FSB5 file header, including the first stream header.
+    // Returns `None` if the header is invalid or cut short by the end of the I/O stream.
+    pub(crate) fn parse(offset: u64, header: &[u8]) -> Option<Self> {
+        let field = |offset: usize| {
+            header
+                .get(offset..offset + 4)
+                .map(|bytes| u32::from_le_bytes(bytes.try_into().expect("slice has 4 bytes")))
+        };
+
+        let version = field(4)?;
+        let base_header_size = match version {
+            0 => 64,
+            1 => 60,
+            _ => return None,
+        };
+
+        let num_streams = NonZeroU32::new(field(8)?)?;
+        let stream_headers_size = field(12)?;
+        let name_table_size = field(16)?;
+        let total_stream_size = field(20)?;
+        let format = AudioFormat::parse_known(field(24)?)?;
+
+        // every stream has a stream header of at least 8 bytes, and a name offset if there are names
+        let num_streams_u64 = u64::from(num_streams.get());
+        if u64::from(stream_headers_size) < num_streams_u64 * 8
+            || (name_table_size != 0 && u64::from(name_table_size) < num_streams_u64 * 4)
+            || total_stream_size == 0
+        {
+            return None;
+        }
+
+        // the stream data of the first stream starts at the start of the stream data, and has samples
+        let first_stream = field(base_header_size).zip(field(base_header_size + 4))?;
+        let first_stream = u64::from(first_stream.0) | (u64::from(first_stream.1) << 32);
+        if (first_stream >> 7) & 0x07FF_FFFF != 0 || first_stream >> 34 == 0 {
+            return None;
+        }
+
+        Some(Self {
+            offset,
+            version,
+            num_streams,
+            format,
+            total_size: base_header_size as u64
+                + u64::from(stream_headers_size)
+                + u64::from(name_table_size)
+                + u64::from(total_stream_size),
+        })
+    }
+
+    /// Returns the offset of the sound bank in the I/O stream.
+    #[must_use]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the version of the sound bank's file header (0 or 1).
+    #[must_use]
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns the number of streams in the sound bank.
+    #[must_use]
+    pub fn num_streams(&self) -> NonZeroU32 {
+        self.num_streams
+    }
+
+    /// Returns the audio format of streams in the sound bank.
+    #[must_use]
+    pub fn format(&self) -> AudioFormat {
+        self.format
+    }
+
+    /// Returns the total size of the sound bank in bytes, calculated from the sizes in its file header.
+    ///
+    /// The I/O stream can end before the sound bank does, so this may extend past the end of the stream.
+    #[must_use]
+    pub fn total_size(&self) -> u64 {
+        self.total_size
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::scan;
+    use crate::{
+        header::AudioFormat,
+        test_bank::{TestBank, TestStream},
+        Bank,
+    };
+    use std::io::{Cursor, Read, Result as IoResult};
+
+    // Builds a version 1 sound bank containing a single PCM16 stream with 2 samples.
+    fn sound_bank(data: [u8; 4]) -> Vec<u8> {
+        // 44100 Hz, 1 channel, 2 samples
+        const STREAM_HEADER: u64 = (8 << 1) | (2 << 34);
+
+        TestBank::new(2).stream(TestStream::new(STREAM_HEADER, &data)).build()
+    }
+
+    // Reads at most a few bytes at a time, so signatures and headers are split between reads.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+            let len = buf.len().min(self.0.len()).min(5);
+            buf[..len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+            Ok(len)
+        }
+    }
+
+    fn blob() -> Vec<u8> {
+        let mut blob = b"junk FSB5 junk".to_vec();
+        blob.extend(sound_bank([1, 2, 3, 4]));
+        // a signature with an invalid header
+        blob.extend_from_slice(b"FSB5\x01\0\0\0\0\0\0\0");
+        blob.resize(blob.len() + 100, 0xFF);
+        blob.extend(sound_bank([5, 6, 7, 8]));
+        blob
+    }
+
+    #[test]
+    fn find_sound_banks() {
+        let blob = blob();
+        let found: Vec<_> = scan(blob.as_slice()).map(Result::unwrap).collect();
+
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].offset(), 14);
+        assert_eq!(found[1].offset(), 14 + 72 + 112);
+
+        for bank in &found {
+            assert_eq!(bank.version(), 1);
+            assert_eq!(bank.num_streams().get(), 1);
+            assert_eq!(bank.format(), AudioFormat::Pcm16);
+            assert_eq!(bank.total_size(), 72);
+        }
+
+        // the same banks are found when reads are split at every position
+        let trickled: Vec<_> = scan(Trickle(&blob)).map(Result::unwrap).collect();
+        assert_eq!(trickled, found);
+    }
+
+    #[test]
+    fn parse_found_bank_in_place() {
+        let blob = blob();
+        let found = scan(blob.as_slice()).nth(1).unwrap().unwrap();
+
+        let bank = Bank::at_offset(Cursor::new(blob.as_slice()), found.offset()).unwrap();
+        let output = bank.into_iter().next().unwrap().write(Vec::new()).unwrap();
+        assert_eq!(output[44..], [5, 6, 7, 8]);
+
+        assert!(Bank::at_offset(Cursor::new(blob.as_slice()), 0).is_err());
+    }
+
+    #[test]
+    fn skip_truncated_header() {
+        let mut blob = sound_bank([0; 4]);
+        blob.truncate(64);
+        assert_eq!(scan(blob.as_slice()).count(), 0);
+
+        // a header followed by truncated stream data is still found
+        let mut blob = sound_bank([0; 4]);
+        blob.truncate(69);
+        assert_eq!(scan(blob.as_slice()).count(), 1);
+    }
+}
@@ -0,0 +1,210 @@
+// Implementations of `serde` traits for sound bank metadata, enabled by the `serde` feature.
+// Field names are documented on the serialized types, so they must stay the same between releases.
+// Stream data is never serialized; only the information from a sound bank's file header is.
+
+use crate::header::{AudioFormat, Loop, StreamInfo};
+use crate::Bank;
+use serde::{
+    de::{Error as DeError, Unexpected},
+    ser::SerializeStruct,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::io::Read;
+
+impl<R: Read> Serialize for Bank<R> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Bank", 10)?;
+        state.serialize_field("index", &self.index())?;
+        state.serialize_field("version", &self.version())?;
+        state.serialize_field("minor_version", &self.minor_version())?;
+        state.serialize_field("format", &self.format())?;
+        state.serialize_field("flags", &self.flags())?;
+        state.serialize_field("hash", self.hash())?;
+        state.serialize_field("guid", &self.guid())?;
+        state.serialize_field("offset_warnings", self.offset_warnings())?;
+        state.serialize_field("num_streams", &self.num_streams())?;
+        state.serialize_field("streams", self.stream_infos())?;
+        state.end()
+    }
+}
+
+impl Serialize for StreamInfo {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let unknown_chunks: Vec<_> = self
+            .unknown_chunks()
+            .iter()
+            .map(|(kind, data)| ChunkSummary {
+                kind: *kind,
+                size: data.len(),
+            })
+            .collect();
+
+        let mut state = serializer.serialize_struct("StreamInfo", 15)?;
+        state.serialize_field("name", &self.name())?;
+        state.serialize_field("codec", &self.codec())?;
+        state.serialize_field("sample_rate", &self.sample_rate())?;
+        state.serialize_field("channels", &self.channels())?;
+        state.serialize_field("sample_count", &self.sample_count())?;
+        state.serialize_field("loop", &self.loop_info())?;
+        state.serialize_field("data_offset", &self.data_offset())?;
+        state.serialize_field("size", &self.size())?;
+        state.serialize_field("peak_volume", &self.peak_volume())?;
+        state.serialize_field("channels_overridden", &self.channels_overridden())?;
+        state.serialize_field("atrac9_config", &self.atrac9_config())?;
+        state.serialize_field("xma_seek_table", &self.xma_seek_table())?;
+        state.serialize_field("vorbis_crc32", &self.vorbis_crc32())?;
+        state.serialize_field("vorbis_seek_table", &self.vorbis_seek_table())?;
+        state.serialize_field("unknown_chunks", &unknown_chunks)?;
+        state.end()
+    }
+}
+
+// The data of unknown chunks is left out, since it can be large and has no known meaning.
+#[derive(Serialize)]
+struct ChunkSummary {
+    #[serde(rename = "type")]
+    kind: u32,
+    size: usize,
+}
+
+impl Serialize for AudioFormat {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for AudioFormat {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+
+        name.parse().map_err(|_| {
+            D::Error::invalid_value(Unexpected::Str(&name), &"the name of an audio format")
+        })
+    }
+}
+
+// Loops are stored as inclusive loop points, like `StreamInfo::loop_points`, rather than as a start and length.
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Loop")]
+struct LoopPoints {
+    start: u32,
+    end: u32,
+}
+
+impl Serialize for Loop {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        LoopPoints {
+            start: self.start(),
+            end: self.end(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Loop {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let LoopPoints { start, end } = LoopPoints::deserialize(deserializer)?;
+
+        Self::from_points(start, end)
+            .ok_or_else(|| D::Error::custom("loop end must not be before loop start"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{AudioFormat, Bank, BankFlags, Loop, OffsetWarning, VorbisSeekEntry};
+    use serde_json::{from_value, json, to_value, Value};
+
+    #[test]
+    fn round_trip_bank_metadata() {
+        // 44100 Hz, 1 channel, 2 samples, followed by a loop chunk
+        const STREAM_HEADER: u64 = 1 | (8 << 1) | (2 << 34);
+        const LOOP_CHUNK: u32 = (8 << 1) | (3 << 25);
+
+        let mut bank = Vec::from(*b"FSB5");
+        bank.extend_from_slice(&1u32.to_le_bytes());
+        bank.extend_from_slice(&1u32.to_le_bytes());
+        bank.extend_from_slice(&20u32.to_le_bytes());
+        bank.extend_from_slice(&8u32.to_le_bytes());
+        bank.extend_from_slice(&4u32.to_le_bytes());
+        bank.extend_from_slice(&2u32.to_le_bytes());
+        bank.resize(60, 0);
+        bank.extend_from_slice(&STREAM_HEADER.to_le_bytes());
+        bank.extend_from_slice(&LOOP_CHUNK.to_le_bytes());
+        bank.extend_from_slice(&0u32.to_le_bytes());
+        bank.extend_from_slice(&1u32.to_le_bytes());
+        bank.extend_from_slice(&4u32.to_le_bytes());
+        bank.extend_from_slice(b"bgm\0");
+        bank.extend_from_slice(&[0x12, 0x34, 0x56, 0x78]);
+
+        let bank = Bank::new(bank.as_slice()).unwrap();
+        let json = serde_json::to_string(&bank).unwrap();
+        let value: Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(value["index"], 0);
+        assert_eq!(value["version"], 5);
+        assert_eq!(value["minor_version"], 1);
+        assert_eq!(value["hash"], json!(bank.hash()));
+        assert_eq!(value["guid"], Value::Null);
+        assert_eq!(value["offset_warnings"], json!([]));
+
+        let stream = &value["streams"][0];
+        assert_eq!(stream["name"], "bgm");
+        assert_eq!(stream["sample_rate"], 44100);
+        assert_eq!(stream["channels"], 1);
+        assert_eq!(stream["sample_count"], 2);
+        assert_eq!(stream["data_offset"], 0);
+        assert_eq!(stream["size"], 4);
+        assert_eq!(stream["peak_volume"], Value::Null);
+        assert_eq!(stream["unknown_chunks"], json!([]));
+
+        // typed values are read back as they were
+        let format: AudioFormat = from_value(value["format"].clone()).unwrap();
+        let codec: AudioFormat = from_value(stream["codec"].clone()).unwrap();
+        let flags: BankFlags = from_value(value["flags"].clone()).unwrap();
+        let stream_loop: Option<Loop> = from_value(stream["loop"].clone()).unwrap();
+        let warnings: Vec<OffsetWarning> = from_value(value["offset_warnings"].clone()).unwrap();
+
+        assert_eq!(format, bank.format());
+        assert_eq!(codec, bank.stream_infos()[0].codec());
+        assert_eq!(flags, bank.flags());
+        assert_eq!(stream_loop, bank.stream_infos()[0].loop_info());
+        assert_eq!(warnings, bank.offset_warnings());
+
+        // serializing the bank again gives the same output, which is also written without serde
+        assert_eq!(to_value(&bank).unwrap(), value);
+        let metadata: Value = serde_json::from_str(&bank.metadata_json()).unwrap();
+        assert_eq!(metadata, value);
+    }
+
+    #[test]
+    fn serialize_audio_formats_by_name() {
+        assert_eq!(to_value(AudioFormat::FAdpcm).unwrap(), "fadpcm");
+        assert_eq!(to_value(AudioFormat::Unknown(99)).unwrap(), "unknown(99)");
+
+        let format: AudioFormat = from_value(json!("Vorbis")).unwrap();
+        assert_eq!(format, AudioFormat::Vorbis);
+        let format: AudioFormat = from_value(json!("unknown(99)")).unwrap();
+        assert_eq!(format, AudioFormat::Unknown(99));
+        assert!(from_value::<AudioFormat>(json!("mp3")).is_err());
+        assert!(from_value::<AudioFormat>(json!(11)).is_err());
+    }
+
+    #[test]
+    fn serialize_loop_points() {
+        let stream_loop = Loop::dummy(4, 9);
+        let value = to_value(stream_loop).unwrap();
+        assert_eq!(value, json!({ "start": 4, "end": 9 }));
+        assert_eq!(from_value::<Loop>(value).unwrap(), stream_loop);
+
+        assert!(from_value::<Loop>(json!({ "start": 9, "end": 4 })).is_err());
+        assert_eq!(
+            from_value::<Loop>(json!({ "start": 4, "end": 4 })).unwrap(),
+            Loop::dummy(4, 4)
+        );
+
+        let entry: VorbisSeekEntry = from_value(json!({ "granule": 1024, "offset": 96 })).unwrap();
+        assert_eq!((entry.granule(), entry.offset()), (1024, 96));
+        assert_eq!(to_value(entry).unwrap(), json!({ "granule": 1024, "offset": 96 }));
+    }
+}
@@ -1,8 +1,17 @@
-use crate::encode::{encode, EncodeError};
+#[cfg(feature = "rodio")]
+use crate::encode::SampleSource;
+#[cfg(feature = "symphonia")]
+use crate::encode::StreamMediaSource;
+use crate::encode::{
+    celt_version, channel_mask, check_mpeg, encode, encode_layers, CeltVersion, EncodeError,
+    EncodeOptions, MpegStats, Samples, StreamReader,
+};
 use crate::header::{AudioFormat, Loop, StreamInfo};
 use crate::read::Reader;
+use crate::{Bank, SeekStreamError};
 use std::{
-    io::{Read, Write},
+    borrow::Cow,
+    io::{Read, Seek, Write},
     num::{NonZeroU32, NonZeroU8},
 };
 
@@ -17,6 +26,7 @@ use std::{
 #[derive(Debug, PartialEq, Eq)]
 pub struct LazyStream<'bank, R: Read> {
     index: u32,
+    bank_index: u32,
     format: AudioFormat,
     flags: u32,
     info: &'bank StreamInfo,
@@ -26,6 +36,7 @@ pub struct LazyStream<'bank, R: Read> {
 impl<'bank, R: Read> LazyStream<'bank, R> {
     pub(crate) fn new(
         index: u32,
+        bank_index: u32,
         format: AudioFormat,
         flags: u32,
         info: &'bank StreamInfo,
@@ -33,6 +44,7 @@ impl<'bank, R: Read> LazyStream<'bank, R> {
     ) -> Self {
         Self {
             index,
+            bank_index,
             format,
             flags,
             info,
@@ -46,6 +58,15 @@ impl<'bank, R: Read> LazyStream<'bank, R> {
         self.index
     }
 
+    /// Returns the index of the sound bank that this stream belongs to, within its file.
+    /// See [`Bank::index`] for more information.
+    ///
+    /// [`Bank::index`]: crate::Bank::index
+    #[must_use]
+    pub fn bank_index(&self) -> u32 {
+        self.bank_index
+    }
+
     /// Returns the audio format of this stream. The format is the same for all streams in a sound bank.
     ///
     /// See [`AudioFormat`] for the list of known formats.
@@ -54,6 +75,13 @@ impl<'bank, R: Read> LazyStream<'bank, R> {
         self.format
     }
 
+    /// Returns the version of CELT that the stream was encoded with, if it's a CELT stream.
+    /// Streams in FSB5 sound banks are always encoded with CELT 0.11 (see [`CeltVersion`]).
+    #[must_use]
+    pub fn celt_version(&self) -> Option<CeltVersion> {
+        celt_version(self.format)
+    }
+
     /// Returns the sample rate (Hz) of the stream.
     #[must_use]
     pub fn sample_rate(&self) -> NonZeroU32 {
@@ -66,6 +94,17 @@ impl<'bank, R: Read> LazyStream<'bank, R> {
         self.info.channels
     }
 
+    /// Returns the speaker channel mask written to WAVE files for this stream, if any.
+    ///
+    /// WAVE files for streams with more than 2 channels use the `WAVE_FORMAT_EXTENSIBLE` format,
+    /// with a default speaker layout chosen by the number of channels (quad, 5.1, 7.1, etc.).
+    /// Returns `None` for mono and stereo streams, which are written without a channel mask.
+    #[must_use]
+    pub fn channel_mask(&self) -> Option<u32> {
+        let channels = self.info.channels.get();
+        (channels > 2).then(|| channel_mask(channels.into()))
+    }
+
     /// Returns the number of samples in the stream.
     #[must_use]
     pub fn sample_count(&self) -> NonZeroU32 {
@@ -78,12 +117,59 @@ impl<'bank, R: Read> LazyStream<'bank, R> {
         self.info.stream_loop
     }
 
+    /// Returns the peak volume of the stream, if it exists.
+    /// FMOD uses this value for loudness normalization.
+    #[must_use]
+    pub fn peak_volume(&self) -> Option<f32> {
+        self.info.peak_volume()
+    }
+
+    /// Returns the stream header chunks of unknown types, as pairs of chunk type and chunk data.
+    /// See [`StreamInfo::unknown_chunks`] for more information.
+    #[must_use]
+    pub fn unknown_chunks(&self) -> &[(u32, Vec<u8>)] {
+        self.info.unknown_chunks()
+    }
+
+    /// Returns whether the number of channels was read from a channels chunk in the stream header.
+    /// See [`StreamInfo::channels_overridden`] for more information.
+    #[must_use]
+    pub fn channels_overridden(&self) -> bool {
+        self.info.channels_overridden
+    }
+
+    /// Returns the offset of the stream's data from the start of the sound bank's stream data, in bytes.
+    #[must_use]
+    pub fn data_offset(&self) -> u64 {
+        self.info.data_offset
+    }
+
     /// Returns the size of the stream, in bytes.
     #[must_use]
-    pub fn size(&self) -> NonZeroU32 {
+    pub fn size(&self) -> u32 {
         self.info.size
     }
 
+    /// Returns the offset of the stream's data from the start of the sound bank, in bytes.
+    /// See [`StreamInfo::data_offset_in_bank`] for more information.
+    #[must_use]
+    pub fn data_offset_in_bank(&self) -> u64 {
+        self.info.data_offset_in_bank()
+    }
+
+    /// Returns the offset of the stream's data from the start of the file holding the sound bank, in bytes.
+    /// See [`StreamInfo::data_offset_in_file`] for more information.
+    #[must_use]
+    pub fn data_offset_in_file(&self) -> u64 {
+        self.info.data_offset_in_file()
+    }
+
+    /// Returns the size of the stream's data, in bytes.
+    #[must_use]
+    pub fn data_size(&self) -> u64 {
+        self.info.data_size()
+    }
+
     /// Returns the name of the stream, if it exists.
     #[must_use]
     pub fn name(&self) -> Option<&str> {
@@ -93,27 +179,100 @@ impl<'bank, R: Read> LazyStream<'bank, R> {
         }
     }
 
+    /// Returns a file name for the stream, with the extension of the file format it's written as
+    /// when encoded with `options`. File names are unique within a sound bank.
+    ///
+    /// See [`StreamInfo::suggested_filename`] for more information.
+    #[must_use]
+    pub fn suggested_filename(&self, options: EncodeOptions<'_>) -> String {
+        self.info.file_name(self.format, options)
+    }
+
     /// Encodes the stream data by writing audio samples to a writer.
     ///
+    /// Streams made up of multiple layers have to be written with [`write_layers`](Self::write_layers) instead.
+    ///
     /// # Errors
     /// This function returns an error if the stream data could not be successfully written.
     /// See [`EncodeError`] for more information.
     pub fn write<W: Write>(self, sink: W) -> Result<W, EncodeError> {
-        encode(self.format, self.flags, self.info, self.reader, sink)
+        self.write_with_options(sink, EncodeOptions::default())
+    }
+
+    /// Encodes the stream data by writing audio samples to a writer, using the provided [`EncodeOptions`].
+    ///
+    /// # Errors
+    /// This function returns an error if the stream data could not be successfully written.
+    /// See [`EncodeError`] for more information.
+    pub fn write_with_options<W: Write>(
+        self,
+        sink: W,
+        options: EncodeOptions<'_>,
+    ) -> Result<W, EncodeError> {
+        encode(
+            self.format,
+            self.flags,
+            self.index,
+            self.info,
+            self.reader,
+            sink,
+            options,
+        )
+    }
+
+    /// Encodes the stream data by writing audio samples to one writer per layer.
+    ///
+    /// MPEG streams with more than 2 channels are made up of stereo layers
+    /// (with a mono final layer for odd numbers of channels), which can't be written to a single writer.
+    /// `make_sink` is called with the index of each layer to create its writer, in order of layers.
+    /// Streams that aren't made up of layers are written to a single writer, created for layer 0.
+    ///
+    /// # Errors
+    /// This function returns an error if the stream data could not be successfully written.
+    /// See [`EncodeError`] for more information.
+    pub fn write_layers<W: Write, F: FnMut(usize) -> W>(
+        self,
+        make_sink: F,
+    ) -> Result<Vec<W>, EncodeError> {
+        encode_layers(
+            self.format,
+            self.flags,
+            self.index,
+            self.info,
+            self.reader,
+            make_sink,
+            EncodeOptions::default(),
+        )
+    }
+
+    /// Reads the frames of an MPEG stream without encoding them, checking the CRC-16 of every protected frame.
+    /// This is useful for finding damaged streams in a sound bank.
+    ///
+    /// Returns `None` if the stream's audio format isn't MPEG.
+    ///
+    /// # Errors
+    /// This function returns an error if the stream data could not be read.
+    /// See [`EncodeError`] for more information.
+    pub fn check_mpeg_frames(self) -> Result<Option<MpegStats>, EncodeError> {
+        check_mpeg(self.format, self.info, self.reader)
     }
 }
 
 /// An audio stream of data that has already been read.
 ///
 /// [`Stream`] is accessible through the [`Bank::into_iter`] method,
-/// which converts a [`Bank`] into a [`StreamIntoIter`] that iterates over [`Stream`] instances.
+/// which converts a [`Bank`] into a [`StreamIntoIter`] that iterates over [`Stream`] instances,
+/// or through the [`Bank::streams`] method for sound banks read from sources that can seek.
 ///
 /// See [`LazyStream`] for the version of an audio stream that does not immediately read its data into memory.
 ///
 /// [`Bank::into_iter`]: crate::Bank::into_iter
+/// [`Bank::streams`]: crate::Bank::streams
 /// [`Bank`]: crate::Bank
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Stream {
+    index: u32,
+    bank_index: u32,
     format: AudioFormat,
     flags: u32,
     info: StreamInfo,
@@ -121,8 +280,17 @@ pub struct Stream {
 }
 
 impl Stream {
-    pub(crate) fn new(format: AudioFormat, flags: u32, info: StreamInfo, data: Box<[u8]>) -> Self {
+    pub(crate) fn new(
+        index: u32,
+        bank_index: u32,
+        format: AudioFormat,
+        flags: u32,
+        info: StreamInfo,
+        data: Box<[u8]>,
+    ) -> Self {
         Self {
+            index,
+            bank_index,
             format,
             flags,
             info,
@@ -130,6 +298,21 @@ impl Stream {
         }
     }
 
+    /// Returns the index of this stream within the sound bank.
+    #[must_use]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the index of the sound bank that this stream belongs to, within its file.
+    /// See [`Bank::index`] for more information.
+    ///
+    /// [`Bank::index`]: crate::Bank::index
+    #[must_use]
+    pub fn bank_index(&self) -> u32 {
+        self.bank_index
+    }
+
     /// Returns the audio format of this stream. The format is the same for all streams in a sound bank.
     ///
     /// See [`AudioFormat`] for the list of known formats.
@@ -138,6 +321,13 @@ impl Stream {
         self.format
     }
 
+    /// Returns the version of CELT that the stream was encoded with, if it's a CELT stream.
+    /// Streams in FSB5 sound banks are always encoded with CELT 0.11 (see [`CeltVersion`]).
+    #[must_use]
+    pub fn celt_version(&self) -> Option<CeltVersion> {
+        celt_version(self.format)
+    }
+
     /// Returns the sample rate (Hz) of the stream.
     #[must_use]
     pub fn sample_rate(&self) -> NonZeroU32 {
@@ -150,6 +340,17 @@ impl Stream {
         self.info.channels
     }
 
+    /// Returns the speaker channel mask written to WAVE files for this stream, if any.
+    ///
+    /// WAVE files for streams with more than 2 channels use the `WAVE_FORMAT_EXTENSIBLE` format,
+    /// with a default speaker layout chosen by the number of channels (quad, 5.1, 7.1, etc.).
+    /// Returns `None` for mono and stereo streams, which are written without a channel mask.
+    #[must_use]
+    pub fn channel_mask(&self) -> Option<u32> {
+        let channels = self.info.channels.get();
+        (channels > 2).then(|| channel_mask(channels.into()))
+    }
+
     /// Returns the number of samples in the stream.
     #[must_use]
     pub fn sample_count(&self) -> NonZeroU32 {
@@ -162,12 +363,59 @@ impl Stream {
         self.info.stream_loop
     }
 
+    /// Returns the peak volume of the stream, if it exists.
+    /// FMOD uses this value for loudness normalization.
+    #[must_use]
+    pub fn peak_volume(&self) -> Option<f32> {
+        self.info.peak_volume()
+    }
+
+    /// Returns the stream header chunks of unknown types, as pairs of chunk type and chunk data.
+    /// See [`StreamInfo::unknown_chunks`] for more information.
+    #[must_use]
+    pub fn unknown_chunks(&self) -> &[(u32, Vec<u8>)] {
+        self.info.unknown_chunks()
+    }
+
+    /// Returns whether the number of channels was read from a channels chunk in the stream header.
+    /// See [`StreamInfo::channels_overridden`] for more information.
+    #[must_use]
+    pub fn channels_overridden(&self) -> bool {
+        self.info.channels_overridden
+    }
+
+    /// Returns the offset of the stream's data from the start of the sound bank's stream data, in bytes.
+    #[must_use]
+    pub fn data_offset(&self) -> u64 {
+        self.info.data_offset
+    }
+
     /// Returns the size of the stream, in bytes.
     #[must_use]
-    pub fn size(&self) -> NonZeroU32 {
+    pub fn size(&self) -> u32 {
         self.info.size
     }
 
+    /// Returns the offset of the stream's data from the start of the sound bank, in bytes.
+    /// See [`StreamInfo::data_offset_in_bank`] for more information.
+    #[must_use]
+    pub fn data_offset_in_bank(&self) -> u64 {
+        self.info.data_offset_in_bank()
+    }
+
+    /// Returns the offset of the stream's data from the start of the file holding the sound bank, in bytes.
+    /// See [`StreamInfo::data_offset_in_file`] for more information.
+    #[must_use]
+    pub fn data_offset_in_file(&self) -> u64 {
+        self.info.data_offset_in_file()
+    }
+
+    /// Returns the size of the stream's data, in bytes.
+    #[must_use]
+    pub fn data_size(&self) -> u64 {
+        self.info.data_size()
+    }
+
     /// Returns the name of the stream, if it exists.
     #[must_use]
     pub fn name(&self) -> Option<&str> {
@@ -177,14 +425,167 @@ impl Stream {
         }
     }
 
+    /// Returns a file name for the stream, with the extension of the file format it's written as
+    /// when encoded with `options`. File names are unique within a sound bank.
+    ///
+    /// See [`StreamInfo::suggested_filename`] for more information.
+    #[must_use]
+    pub fn suggested_filename(&self, options: EncodeOptions<'_>) -> String {
+        self.info.file_name(self.format, options)
+    }
+
     /// Encodes the stream data by writing audio samples to a writer.
     ///
+    /// Streams made up of multiple layers have to be written with [`write_layers`](Self::write_layers) instead.
+    ///
     /// # Errors
     /// This function returns an error if the stream data could not be successfully written.
     /// See [`EncodeError`] for more information.
     pub fn write<W: Write>(self, sink: W) -> Result<W, EncodeError> {
+        self.write_with_options(sink, EncodeOptions::default())
+    }
+
+    /// Encodes the stream data by writing audio samples to a writer, using the provided [`EncodeOptions`].
+    ///
+    /// # Errors
+    /// This function returns an error if the stream data could not be successfully written.
+    /// See [`EncodeError`] for more information.
+    pub fn write_with_options<W: Write>(
+        self,
+        sink: W,
+        options: EncodeOptions<'_>,
+    ) -> Result<W, EncodeError> {
         let mut reader = Reader::new(&*self.data);
-        encode(self.format, self.flags, &self.info, &mut reader, sink)
+        encode(
+            self.format,
+            self.flags,
+            self.index,
+            &self.info,
+            &mut reader,
+            sink,
+            options,
+        )
+    }
+
+    /// Encodes the stream data by writing audio samples to one writer per layer.
+    ///
+    /// MPEG streams with more than 2 channels are made up of stereo layers
+    /// (with a mono final layer for odd numbers of channels), which can't be written to a single writer.
+    /// `make_sink` is called with the index of each layer to create its writer, in order of layers.
+    /// Streams that aren't made up of layers are written to a single writer, created for layer 0.
+    ///
+    /// # Errors
+    /// This function returns an error if the stream data could not be successfully written.
+    /// See [`EncodeError`] for more information.
+    pub fn write_layers<W: Write, F: FnMut(usize) -> W>(
+        self,
+        make_sink: F,
+    ) -> Result<Vec<W>, EncodeError> {
+        let mut reader = Reader::new(&*self.data);
+        encode_layers(
+            self.format,
+            self.flags,
+            self.index,
+            &self.info,
+            &mut reader,
+            make_sink,
+            EncodeOptions::default(),
+        )
+    }
+
+    /// Reads the frames of an MPEG stream without encoding them, checking the CRC-16 of every protected frame.
+    /// This is useful for finding damaged streams in a sound bank.
+    ///
+    /// Returns `None` if the stream's audio format isn't MPEG.
+    ///
+    /// # Errors
+    /// This function returns an error if the stream data could not be read.
+    /// See [`EncodeError`] for more information.
+    pub fn check_mpeg_frames(&self) -> Result<Option<MpegStats>, EncodeError> {
+        let mut reader = Reader::new(&*self.data);
+        check_mpeg(self.format, &self.info, &mut reader)
+    }
+
+    /// Returns an iterator over the decoded audio samples of the stream, as interleaved 16-bit samples.
+    /// See [`Samples`] for more information.
+    ///
+    /// PCM, IMA ADPCM, FADPCM, GC ADPCM, and HEVAG streams can be decoded,
+    /// as well as Vorbis streams when the `decode-vorbis` feature is enabled.
+    /// Samples of other PCM bit depths are converted to 16 bits.
+    ///
+    /// # Errors
+    /// This function returns [`EncodeError::UnsupportedFormat`] if streams of this audio format can't be decoded,
+    /// or another [`EncodeError`] if the stream header lacks information needed for decoding
+    /// (such as the coefficients of a GC ADPCM stream). CELT streams return a [`CeltError`] that names
+    /// their CELT version. [`StreamInfo::check_decode`] returns the same error without creating the iterator.
+    ///
+    /// [`CeltError`]: crate::encode::CeltError
+    pub fn samples(&self) -> Result<Samples<'_>, EncodeError> {
+        Samples::new(
+            self.format,
+            self.flags,
+            Cow::Borrowed(&self.info),
+            Cow::Borrowed(&self.data),
+        )
+    }
+
+    /// Converts the stream into an iterator over its decoded audio samples, as interleaved 16-bit samples.
+    /// Unlike [`Stream::samples`], the iterator owns the stream data, so it can outlive the stream
+    /// (such as when it is handed to an audio playback library).
+    ///
+    /// # Errors
+    /// This function returns an error under the same conditions as [`Stream::samples`].
+    pub fn into_samples(self) -> Result<Samples<'static>, EncodeError> {
+        Samples::new(
+            self.format,
+            self.flags,
+            Cow::Owned(self.info),
+            Cow::Owned(self.data.into_vec()),
+        )
+    }
+
+    /// Converts the stream into a reader over its encoded file, which encodes the stream as it is read.
+    /// See [`StreamReader`] for more information.
+    ///
+    /// # Errors
+    /// This function returns an error if the start of the encoded file could not be written,
+    /// such as when the audio format or output isn't supported. See [`EncodeError`] for more information.
+    pub fn into_reader(self) -> Result<StreamReader, EncodeError> {
+        self.into_reader_with_options(EncodeOptions::default())
+    }
+
+    /// Converts the stream into a reader over its encoded file, using the provided [`EncodeOptions`].
+    /// See [`StreamReader`] for more information.
+    ///
+    /// # Errors
+    /// This function returns an error under the same conditions as [`Stream::into_reader`].
+    pub fn into_reader_with_options(
+        self,
+        options: EncodeOptions<'_>,
+    ) -> Result<StreamReader, EncodeError> {
+        StreamReader::new(self.format, self.flags, self.index, &self.info, self.data, options)
+    }
+
+    /// Converts the stream into a [`rodio::Source`] that plays its decoded audio samples.
+    /// This requires the `rodio` feature. See [`SampleSource`] for more information.
+    ///
+    /// # Errors
+    /// This function returns an error under the same conditions as [`Stream::samples`].
+    #[cfg(feature = "rodio")]
+    pub fn into_source(self) -> Result<SampleSource, EncodeError> {
+        self.into_samples().map(SampleSource::new)
+    }
+
+    /// Writes the stream in memory to a format that Symphonia can decode, returning it as a
+    /// [`symphonia_core::io::MediaSource`]. This requires the `symphonia` feature.
+    /// See [`StreamMediaSource`] for more information.
+    ///
+    /// # Errors
+    /// This function returns [`EncodeError::UnsupportedFormat`] if the stream can't be written to a format
+    /// that Symphonia decodes, or another [`EncodeError`] if the stream data could not be written.
+    #[cfg(feature = "symphonia")]
+    pub fn media_source(&self) -> Result<StreamMediaSource, EncodeError> {
+        StreamMediaSource::new(self.format, self.flags, self.index, &self.info, &self.data)
     }
 }
 
@@ -193,10 +594,15 @@ impl Stream {
 /// This type is returned from [`Bank::into_iter`].
 /// When iterating, `Some(Stream)` is returned if a stream was successfully read from the sound bank, and `None` otherwise.
 ///
+/// The sound bank is consumed, and streams are read in a single pass, which works with sources that can't seek.
+/// For sound banks read from sources that can seek, [`Bank::streams`] can iterate over streams any number of times.
+///
 /// [`Bank::into_iter`]: crate::Bank::into_iter
+/// [`Bank::streams`]: crate::Bank::streams
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct StreamIntoIter<R: Read> {
     index: u32,
+    bank_index: u32,
     format: AudioFormat,
     flags: u32,
     info: Box<[StreamInfo]>,
@@ -205,6 +611,7 @@ pub struct StreamIntoIter<R: Read> {
 
 impl<R: Read> StreamIntoIter<R> {
     pub(crate) fn new(
+        bank_index: u32,
         format: AudioFormat,
         flags: u32,
         info: Box<[StreamInfo]>,
@@ -212,6 +619,7 @@ impl<R: Read> StreamIntoIter<R> {
     ) -> Self {
         Self {
             index: 0,
+            bank_index,
             format,
             flags,
             info,
@@ -225,15 +633,21 @@ impl<R: Read> Iterator for StreamIntoIter<R> {
 
     fn next(&mut self) -> Option<Self::Item> {
         let stream = self.info.get(self.index as usize).cloned().and_then(|info| {
-            let size = info.size.get() as usize;
+            let size = info.size as usize;
             let start_pos = self.reader.position();
 
-            let stream =
-                self.reader.take(size).ok().map(|data| {
-                    Stream::new(self.format, self.flags, info, data.into_boxed_slice())
-                });
+            let stream = self.reader.take(size).ok().map(|data| {
+                Stream::new(
+                    self.index,
+                    self.bank_index,
+                    self.format,
+                    self.flags,
+                    info,
+                    data.into_boxed_slice(),
+                )
+            });
 
-            self.reader.advance_to(start_pos + size).ok()?;
+            self.reader.advance_to(start_pos + size as u64).ok()?;
 
             stream
         });
@@ -254,3 +668,49 @@ impl<R: Read> ExactSizeIterator for StreamIntoIter<R> {
         self.info.len()
     }
 }
+
+/// An iterator over the streams of a sound bank that can seek, which reads the data of every stream into memory.
+///
+/// This type is returned from [`Bank::streams`]. Unlike [`StreamIntoIter`], it only borrows the sound bank,
+/// and every stream is read by seeking to its own data, so a sound bank can be iterated over any number of times.
+/// When iterating, `Some(Err(SeekStreamError))` is returned for streams whose data couldn't be read,
+/// and iteration continues with the next stream.
+///
+/// [`Bank::streams`]: crate::Bank::streams
+#[derive(Debug)]
+pub struct Streams<'bank, R: Read + Seek> {
+    index: u32,
+    bank: &'bank mut Bank<R>,
+}
+
+impl<'bank, R: Read + Seek> Streams<'bank, R> {
+    pub(crate) fn new(bank: &'bank mut Bank<R>) -> Self {
+        Self { index: 0, bank }
+    }
+}
+
+impl<R: Read + Seek> Iterator for Streams<'_, R> {
+    type Item = Result<Stream, SeekStreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.bank.num_streams().get() {
+            return None;
+        }
+
+        let stream = self.bank.read_stream(self.index);
+        self.index += 1;
+
+        Some(stream)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len();
+        (len, Some(len))
+    }
+}
+
+impl<R: Read + Seek> ExactSizeIterator for Streams<'_, R> {
+    fn len(&self) -> usize {
+        (self.bank.num_streams().get() - self.index) as usize
+    }
+}
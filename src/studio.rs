@@ -0,0 +1,430 @@
+//! Types for reading the sound banks embedded in FMOD Studio bank files (.bank).
+
+use crate::bank::{Bank, DecodeError};
+use crate::header::FSB5_MAGIC;
+use std::{
+    cmp::min,
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io::{Error as IoError, Read, Seek, SeekFrom, Take},
+    ops::Range,
+};
+
+// FMOD Studio bank file layout taken from:
+// [1]: https://github.com/vgmstream/vgmstream/blob/master/src/meta/fsb5_fev.c
+
+const RIFF_MAGIC: [u8; 4] = *b"RIFF";
+const FEV_TYPE: [u8; 4] = *b"FEV ";
+const LIST_ID: [u8; 4] = *b"LIST";
+const SOUND_ID: [u8; 4] = *b"SND ";
+
+// Sound banks in the `SND ` chunk are preceded by zeros that align them to a multiple of (usually) 32 bytes.
+const MAX_SOUND_BANK_PADDING: u64 = 32;
+
+// The start of an FSB5 file header: signature, version, number of streams, and the sizes of
+// the stream headers, name table, and stream data.
+const FSB5_HEADER_START_SIZE: u64 = 24;
+
+// Returns whether `prefix` starts like an FMOD Studio bank file.
+pub(crate) fn is_bank_file(prefix: &[u8]) -> bool {
+    prefix.len() >= 12 && prefix[..4] == RIFF_MAGIC && prefix[8..12] == FEV_TYPE
+}
+
+/// An FMOD Studio bank file (.bank), which holds one or more FSB5 sound banks.
+///
+/// Bank files made by FMOD Studio are RIFF files of type `FEV `, which store the audio of their events
+/// as FSB5 sound banks in a `SND ` chunk. Only the RIFF chunks and the file headers of sound banks are read
+/// when a [`StudioBank`] is created; a sound bank is parsed when it's accessed with [`bank`](Self::bank),
+/// so the whole file is never read into memory.
+///
+/// # Examples
+///
+/// ```no_run
+/// use fsbex::{studio::StudioBank, DirectoryFactory};
+/// use std::{error::Error, fs::File, io::BufReader};
+///
+/// fn extract_bank_file(path: &str) -> Result<(), Box<dyn Error>> {
+///     let mut studio_bank = StudioBank::new(BufReader::new(File::open(path)?))?;
+///
+///     for index in 0..studio_bank.num_banks() {
+///         let bank = studio_bank.bank(index)?;
+///         let factory = DirectoryFactory::new("output", bank.format()).bank_index(bank.index());
+///         bank.extract_with(factory)?;
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct StudioBank<R> {
+    source: R,
+    sound_banks: Vec<Range<u64>>,
+}
+
+impl<R: Read + Seek> StudioBank<R> {
+    /// Creates a new [`StudioBank<R>`] by reading the RIFF chunks of a bank file from an I/O stream,
+    /// starting at its current position.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if:
+    /// - the I/O stream isn't an FMOD Studio bank file
+    /// - the RIFF chunks of the file couldn't be read
+    /// - the file holds no sound banks, which is the case for bank files whose audio is stored
+    ///   in a separate `.streams.bank` file
+    ///
+    /// See [`StudioError`] for more information.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn new(mut source: R) -> Result<Self, StudioError> {
+        let start = source
+            .stream_position()
+            .map_err(StudioError::from_io(StudioErrorKind::ReadChunk))?;
+
+        let header: [u8; 12] =
+            read_array(&mut source).map_err(StudioError::from_io(StudioErrorKind::Magic))?;
+
+        if header[..4] != RIFF_MAGIC || header[8..] != FEV_TYPE {
+            return Err(StudioError::new(StudioErrorKind::Magic));
+        }
+
+        let riff_size = u32::from_le_bytes(header[4..8].try_into().expect("slice has 4 bytes"));
+        let chunks = start + 12..start + 8 + u64::from(riff_size);
+
+        let mut sound_banks = Vec::new();
+        find_sound_banks(&mut source, chunks, &mut sound_banks)?;
+
+        if sound_banks.is_empty() {
+            return Err(StudioError::new(StudioErrorKind::NoSoundData));
+        }
+
+        Ok(Self {
+            source,
+            sound_banks,
+        })
+    }
+
+    /// Returns the number of sound banks in the bank file, which is at least 1.
+    #[must_use]
+    pub fn num_banks(&self) -> usize {
+        self.sound_banks.len()
+    }
+
+    /// Parses the sound bank at `index`, which reads its stream data from the bank file.
+    /// The index is also the [`Bank::index`] of the sound bank.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if there's no sound bank at `index`,
+    /// or if parsing of the sound bank's file header failed. See [`StudioError`] for more information.
+    pub fn bank(&mut self, index: usize) -> Result<Bank<Take<&mut R>>, StudioError> {
+        let range = self.seek_to_bank(index)?;
+        parse_bank(index, range.start, (&mut self.source).take(range.end - range.start))
+    }
+
+    /// Parses the sound bank at `index`, consuming this [`StudioBank<R>`].
+    /// See [`bank`](Self::bank) for more information.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if there's no sound bank at `index`,
+    /// or if parsing of the sound bank's file header failed. See [`StudioError`] for more information.
+    pub fn into_bank(mut self, index: usize) -> Result<Bank<Take<R>>, StudioError> {
+        let range = self.seek_to_bank(index)?;
+        parse_bank(index, range.start, self.source.take(range.end - range.start))
+    }
+
+    fn seek_to_bank(&mut self, index: usize) -> Result<Range<u64>, StudioError> {
+        let range = self
+            .sound_banks
+            .get(index)
+            .cloned()
+            .ok_or_else(|| StudioError::new(StudioErrorKind::UnknownBank { index }))?;
+
+        seek_to(&mut self.source, range.start)
+            .map_err(StudioError::from_io(StudioErrorKind::ReadSoundBank))?;
+
+        Ok(range)
+    }
+}
+
+fn parse_bank<S: Read>(index: usize, offset: u64, source: S) -> Result<Bank<S>, StudioError> {
+    let index = u32::try_from(index).expect("sound banks of a RIFF file can't outnumber u32::MAX");
+
+    Bank::new(source)
+        .map(|bank| bank.with_index(index).with_offset(offset))
+        .map_err(StudioError::from_decode)
+}
+
+// Walks the RIFF chunks in `chunks`, including the chunks in `LIST` chunks,
+// and adds the sound banks found in `SND ` chunks to `sound_banks`.
+fn find_sound_banks<R: Read + Seek>(
+    source: &mut R,
+    chunks: Range<u64>,
+    sound_banks: &mut Vec<Range<u64>>,
+) -> Result<(), StudioError> {
+    let mut position = chunks.start;
+
+    while position + 8 <= chunks.end {
+        seek_to(source, position).map_err(StudioError::from_io(StudioErrorKind::ReadChunk))?;
+        let chunk_header: [u8; 8] =
+            read_array(source).map_err(StudioError::from_io(StudioErrorKind::ReadChunk))?;
+
+        let id: [u8; 4] = chunk_header[..4].try_into().expect("slice has 4 bytes");
+        let size = u32::from_le_bytes(chunk_header[4..].try_into().expect("slice has 4 bytes"));
+        let data = position + 8..min(position + 8 + u64::from(size), chunks.end);
+
+        match id {
+            // `LIST` chunks start with their list type, followed by more chunks
+            LIST_ID => find_sound_banks(source, data.start + 4..data.end, sound_banks)?,
+            SOUND_ID => find_fsb5_banks(source, data.clone(), sound_banks)?,
+            _ => {}
+        }
+
+        // the data of RIFF chunks is padded to an even size
+        position = data.end + u64::from(size % 2);
+    }
+
+    Ok(())
+}
+
+// Finds the FSB5 sound banks stored back to back in the data of a `SND ` chunk.
+// The size of each sound bank is calculated from the sizes in its file header.
+fn find_fsb5_banks<R: Read + Seek>(
+    source: &mut R,
+    data: Range<u64>,
+    sound_banks: &mut Vec<Range<u64>>,
+) -> Result<(), StudioError> {
+    let read_err = || StudioError::from_io(StudioErrorKind::ReadChunk);
+    let mut position = data.start;
+
+    while position < data.end {
+        seek_to(source, position).map_err(read_err())?;
+
+        let mut padding = [0; 32];
+        let len = usize::try_from(min(MAX_SOUND_BANK_PADDING, data.end - position))
+            .expect("padding is at most 32 bytes");
+        source.read_exact(&mut padding[..len]).map_err(read_err())?;
+
+        position += padding[..len].iter().take_while(|&&byte| byte == 0).count() as u64;
+
+        if data.end - position < FSB5_HEADER_START_SIZE {
+            break;
+        }
+
+        seek_to(source, position).map_err(read_err())?;
+        let header: [u8; 24] = read_array(source).map_err(read_err())?;
+
+        if header[..4] != FSB5_MAGIC {
+            break;
+        }
+
+        let field = |offset: usize| {
+            u32::from_le_bytes(header[offset..offset + 4].try_into().expect("slice has 4 bytes"))
+        };
+
+        // version 0 file headers are 4 bytes longer than version 1 file headers
+        let base_header_size = if field(4) == 0 { 64 } else { 60 };
+        let size =
+            base_header_size + u64::from(field(12)) + u64::from(field(16)) + u64::from(field(20));
+
+        sound_banks.push(position..min(position + size, data.end));
+        position += size;
+    }
+
+    Ok(())
+}
+
+fn seek_to<R: Seek>(source: &mut R, position: u64) -> Result<(), IoError> {
+    source.seek(SeekFrom::Start(position)).map(|_| ())
+}
+
+fn read_array<R: Read, const LEN: usize>(source: &mut R) -> Result<[u8; LEN], IoError> {
+    let mut buf = [0; LEN];
+    source.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// Represents an error that can occur when reading an FMOD Studio bank file.
+///
+/// See [`StudioErrorKind`] for the different kinds of errors that can occur.
+#[derive(Debug)]
+pub struct StudioError {
+    kind: StudioErrorKind,
+    source: Option<StudioErrorSource>,
+}
+
+/// A variant of a [`StudioError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum StudioErrorKind {
+    /// The file is not an FMOD Studio bank file (a RIFF file of type `FEV `).
+    Magic,
+    /// Failed to read the RIFF chunks of the file due to an underlying I/O error.
+    ReadChunk,
+    /// The file holds no sound banks. This is the case for bank files whose audio is stored
+    /// in a separate `.streams.bank` file, which has to be read instead.
+    NoSoundData,
+    /// There is no sound bank at this index in the file.
+    UnknownBank {
+        /// The index of the sound bank.
+        index: usize,
+    },
+    /// Failed to parse a sound bank in the file.
+    ReadSoundBank,
+}
+
+#[derive(Debug)]
+enum StudioErrorSource {
+    Io(IoError),
+    Decode(DecodeError),
+}
+
+impl StudioError {
+    fn new(kind: StudioErrorKind) -> Self {
+        Self { kind, source: None }
+    }
+
+    fn from_io(kind: StudioErrorKind) -> impl FnOnce(IoError) -> Self {
+        move |source| Self {
+            kind,
+            source: Some(StudioErrorSource::Io(source)),
+        }
+    }
+
+    fn from_decode(source: DecodeError) -> Self {
+        Self {
+            kind: StudioErrorKind::ReadSoundBank,
+            source: Some(StudioErrorSource::Decode(source)),
+        }
+    }
+
+    /// Returns the [`StudioErrorKind`] associated with this error.
+    #[must_use]
+    pub fn kind(&self) -> StudioErrorKind {
+        self.kind
+    }
+}
+
+impl Display for StudioError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.kind.fmt(f)
+    }
+}
+
+impl Error for StudioError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.source {
+            Some(StudioErrorSource::Io(e)) => Some(e),
+            Some(StudioErrorSource::Decode(e)) => Some(e),
+            None => None,
+        }
+    }
+}
+
+impl Display for StudioErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Magic => f.write_str("file is not an FMOD Studio bank file"),
+            Self::ReadChunk => f.write_str("failed to read RIFF chunks of bank file"),
+            Self::NoSoundData => f.write_str(
+                "bank file holds no sound banks (its audio may be stored in a separate .streams.bank file)",
+            ),
+            Self::UnknownBank { index } => {
+                f.write_fmt(format_args!("no sound bank at index {index} in bank file"))
+            }
+            Self::ReadSoundBank => f.write_str("failed to parse sound bank in bank file"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{StudioBank, StudioErrorKind};
+    use crate::test_bank::{TestBank, TestStream};
+    use std::io::Cursor;
+
+    // Builds a version 1 sound bank containing a single PCM16 stream with 2 samples.
+    fn sound_bank(data: [u8; 4]) -> Vec<u8> {
+        // 44100 Hz, 1 channel, 2 samples
+        const STREAM_HEADER: u64 = (8 << 1) | (2 << 34);
+
+        TestBank::new(2).stream(TestStream::new(STREAM_HEADER, &data)).build()
+    }
+
+    fn chunk(id: [u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::from(id);
+        buf.extend_from_slice(&u32::try_from(data.len()).unwrap().to_le_bytes());
+        buf.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            buf.push(0);
+        }
+        buf
+    }
+
+    fn bank_file(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let body = chunks.concat();
+        let mut buf = Vec::from(*b"RIFF");
+        buf.extend_from_slice(&u32::try_from(body.len() + 4).unwrap().to_le_bytes());
+        buf.extend_from_slice(b"FEV ");
+        buf.extend(body);
+        buf
+    }
+
+    #[test]
+    fn find_embedded_sound_banks() {
+        // sound banks are padded with zeros to a multiple of 32 bytes
+        let mut sound_data = vec![0; 12];
+        sound_data.extend(sound_bank([0x12, 0x34, 0x56, 0x78]));
+        sound_data.resize(96, 0);
+        sound_data.extend(sound_bank([0x9A, 0xBC, 0xDE, 0xF0]));
+
+        let mut project = b"PROJ".to_vec();
+        project.extend(chunk(*b"BNKI", b"bank"));
+
+        let file = bank_file(&[
+            chunk(*b"FMT ", &[0; 9]),
+            chunk(*b"LIST", &project),
+            chunk(*b"SND ", &sound_data),
+        ]);
+
+        let mut studio_bank = StudioBank::new(Cursor::new(file.as_slice())).unwrap();
+        assert_eq!(studio_bank.num_banks(), 2);
+
+        let bank = studio_bank.bank(1).unwrap();
+        assert_eq!(bank.index(), 1);
+
+        // stream data is located within the bank file
+        let offset = usize::try_from(bank.stream_infos()[0].data_offset_in_file()).unwrap();
+        assert_eq!(file[offset..offset + 4], [0x9A, 0xBC, 0xDE, 0xF0]);
+        let output = bank.into_iter().next().unwrap().write(Vec::new()).unwrap();
+        assert_eq!(output[44..], [0x9A, 0xBC, 0xDE, 0xF0]);
+
+        // sound banks can be read again, in any order
+        let output = studio_bank.bank(0).unwrap().into_iter().next().unwrap();
+        assert_eq!(output.write(Vec::new()).unwrap()[44..], [0x12, 0x34, 0x56, 0x78]);
+
+        assert!(studio_bank
+            .bank(2)
+            .is_err_and(|e| e.kind() == StudioErrorKind::UnknownBank { index: 2 }));
+        assert!(studio_bank.into_bank(0).is_ok());
+    }
+
+    #[test]
+    fn reject_invalid_bank_files() {
+        let file = bank_file(&[chunk(*b"FMT ", &[0; 8])]);
+        assert!(StudioBank::new(Cursor::new(file.as_slice()))
+            .is_err_and(|e| e.kind() == StudioErrorKind::NoSoundData));
+
+        let file = sound_bank([0; 4]);
+        assert!(StudioBank::new(Cursor::new(file.as_slice()))
+            .is_err_and(|e| e.kind() == StudioErrorKind::Magic));
+
+        // a truncated sound bank is found, but fails to parse
+        let mut sound_data = sound_bank([0; 4]);
+        sound_data.truncate(40);
+        let file = bank_file(&[chunk(*b"SND ", &sound_data)]);
+        let mut studio_bank = StudioBank::new(Cursor::new(file.as_slice())).unwrap();
+        assert!(studio_bank
+            .bank(0)
+            .is_err_and(|e| e.kind() == StudioErrorKind::ReadSoundBank));
+    }
+}
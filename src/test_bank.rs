@@ -0,0 +1,145 @@
+// Builders for the sound banks used by tests, shared with the integration tests and benchmarks through
+// `tests/common`. Not every test target uses every builder.
+#![allow(dead_code)]
+
+// A sound bank with a single PCM16 stream named "bgm", whose 2 samples loop from the first to the last.
+pub(crate) fn looping_bank() -> Vec<u8> {
+    // 44100 Hz, 1 channel, 2 samples
+    const STREAM_HEADER: u64 = (8 << 1) | (2 << 34);
+
+    let stream = TestStream::new(STREAM_HEADER, &[0x12, 0x34, 0x56, 0x78]);
+    TestBank::new(2).stream(stream.looped(0, 1).name("bgm")).build()
+}
+
+// A stream of a sound bank built with `TestBank`.
+pub(crate) struct TestStream {
+    header: u64,
+    chunks: Vec<(u32, Vec<u8>)>,
+    name: Option<String>,
+    data: Vec<u8>,
+}
+
+impl TestStream {
+    // `header` is the stream header as stored in the sound bank, with the sample rate, channels, and sample
+    // count of the stream. The flag for following chunks and the data offset are added when the bank is built.
+    pub(crate) fn new(header: u64, data: &[u8]) -> Self {
+        Self {
+            header,
+            chunks: Vec::new(),
+            name: None,
+            data: data.to_vec(),
+        }
+    }
+
+    // Adds a chunk of the given kind after the stream header.
+    pub(crate) fn chunk(mut self, kind: u32, data: &[u8]) -> Self {
+        self.chunks.push((kind, data.to_vec()));
+        self
+    }
+
+    // Adds a loop chunk with the given start and end frames.
+    pub(crate) fn looped(self, start: u32, end: u32) -> Self {
+        let mut data = start.to_le_bytes().to_vec();
+        data.extend_from_slice(&end.to_le_bytes());
+        self.chunk(3, &data)
+    }
+
+    // Names the stream, which writes a name table into the sound bank.
+    pub(crate) fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_owned());
+        self
+    }
+}
+
+// Builds a version 1 sound bank. The data of each stream starts at a multiple of 32 bytes, and all data
+// but the last stream's is padded with zeros to get there.
+pub(crate) struct TestBank {
+    format: u32,
+    flags: u32,
+    streams: Vec<TestStream>,
+}
+
+impl TestBank {
+    pub(crate) fn new(format: u32) -> Self {
+        Self {
+            format,
+            flags: 0,
+            streams: Vec::new(),
+        }
+    }
+
+    pub(crate) fn flags(mut self, flags: u32) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    pub(crate) fn stream(mut self, stream: TestStream) -> Self {
+        self.streams.push(stream);
+        self
+    }
+
+    pub(crate) fn streams(self, streams: impl IntoIterator<Item = TestStream>) -> Self {
+        streams.into_iter().fold(self, Self::stream)
+    }
+
+    pub(crate) fn build(&self) -> Vec<u8> {
+        let mut headers = Vec::new();
+        let mut data = Vec::new();
+
+        for (index, stream) in self.streams.iter().enumerate() {
+            // data offsets are stored in units of 32 bytes
+            let offset = u64::try_from(data.len() / 32).unwrap();
+            let header = stream.header | u64::from(!stream.chunks.is_empty()) | (offset << 7);
+            headers.extend_from_slice(&header.to_le_bytes());
+
+            for (chunk_index, (kind, chunk)) in stream.chunks.iter().enumerate() {
+                let more_chunks = u32::from(chunk_index + 1 < stream.chunks.len());
+                let size = u32::try_from(chunk.len()).unwrap();
+                headers
+                    .extend_from_slice(&(more_chunks | (size << 1) | (kind << 25)).to_le_bytes());
+                headers.extend_from_slice(chunk);
+            }
+
+            data.extend_from_slice(&stream.data);
+            if index + 1 < self.streams.len() {
+                data.resize(data.len().next_multiple_of(32), 0);
+            }
+        }
+
+        let names = self.name_table();
+
+        let mut bank = Vec::from(*b"FSB5");
+        bank.extend_from_slice(&1u32.to_le_bytes());
+        for size in [self.streams.len(), headers.len(), names.len(), data.len()] {
+            bank.extend_from_slice(&u32::try_from(size).unwrap().to_le_bytes());
+        }
+        bank.extend_from_slice(&self.format.to_le_bytes());
+        bank.extend_from_slice(&0u32.to_le_bytes());
+        bank.extend_from_slice(&self.flags.to_le_bytes());
+        bank.resize(60, 0);
+        bank.extend_from_slice(&headers);
+        bank.extend_from_slice(&names);
+        bank.extend_from_slice(&data);
+        bank
+    }
+
+    // Name offsets for every stream followed by the names, if any stream is named. Unnamed streams get an
+    // empty name.
+    fn name_table(&self) -> Vec<u8> {
+        if self.streams.iter().all(|stream| stream.name.is_none()) {
+            return Vec::new();
+        }
+
+        let mut offsets = Vec::new();
+        let mut names = Vec::new();
+        for stream in &self.streams {
+            let offset = 4 * self.streams.len() + names.len();
+            offsets.extend_from_slice(&u32::try_from(offset).unwrap().to_le_bytes());
+            names.extend_from_slice(stream.name.as_deref().unwrap_or_default().as_bytes());
+            names.push(0);
+        }
+
+        offsets.extend_from_slice(&names);
+        offsets
+    }
+}
@@ -0,0 +1,248 @@
+use crate::encode::EncodeError;
+use crate::header::OffsetWarningKind;
+use crate::read::ReadError;
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+};
+
+/// Options that control how a sound bank is checked by [`Bank::verify_with_options`].
+///
+/// [`Bank::verify_with_options`]: crate::Bank::verify_with_options
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct VerifyOptions {
+    pub(crate) check_packets: bool,
+}
+
+impl VerifyOptions {
+    /// Creates a new [`VerifyOptions`] with the default options.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether the frames of MPEG streams and the packets of Vorbis streams are read and checked,
+    /// without encoding them. This reads the stream data of these streams into memory, one stream at a time,
+    /// so it's slower than only checking the sizes of streams. This is disabled by default.
+    ///
+    /// Vorbis streams are only checked with the `decode-vorbis` feature.
+    #[must_use]
+    pub fn check_packets(mut self, check_packets: bool) -> Self {
+        self.check_packets = check_packets;
+        self
+    }
+}
+
+/// The problems found by [`Bank::verify`], in the order they were found.
+///
+/// Problems with the file header are listed first, followed by the problems with each stream in order of streams.
+/// Every problem is either an error, which means that streams are damaged or can't be extracted,
+/// or a warning, which means that the sound bank is unusual but its streams can still be extracted.
+///
+/// [`Bank::verify`]: crate::Bank::verify
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub(crate) findings: Vec<Finding>,
+}
+
+impl VerifyReport {
+    pub(crate) fn push(&mut self, kind: FindingKind, index: Option<u32>, offset: Option<u64>) {
+        self.findings.push(Finding {
+            kind,
+            index,
+            offset,
+            source: None,
+        });
+    }
+
+    pub(crate) fn push_with_source(
+        &mut self,
+        kind: FindingKind,
+        index: Option<u32>,
+        offset: Option<u64>,
+        source: FindingSource,
+    ) {
+        self.findings.push(Finding {
+            kind,
+            index,
+            offset,
+            source: Some(source),
+        });
+    }
+
+    /// Returns every problem that was found, both errors and warnings.
+    #[must_use]
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings
+    }
+
+    /// Returns an iterator over the problems that are errors.
+    pub fn errors(&self) -> impl Iterator<Item = &Finding> {
+        self.findings
+            .iter()
+            .filter(|finding| finding.severity() == Severity::Error)
+    }
+
+    /// Returns an iterator over the problems that are warnings.
+    pub fn warnings(&self) -> impl Iterator<Item = &Finding> {
+        self.findings
+            .iter()
+            .filter(|finding| finding.severity() == Severity::Warning)
+    }
+
+    /// Returns whether no errors were found. The sound bank can still have warnings.
+    #[must_use]
+    pub fn is_intact(&self) -> bool {
+        self.errors().next().is_none()
+    }
+}
+
+/// A problem found by [`Bank::verify`]. See [`FindingKind`] for the different kinds of problems.
+///
+/// [`Bank::verify`]: crate::Bank::verify
+#[derive(Debug)]
+pub struct Finding {
+    kind: FindingKind,
+    index: Option<u32>,
+    offset: Option<u64>,
+    source: Option<FindingSource>,
+}
+
+/// Whether a [`Finding`] is an error or a warning.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Severity {
+    /// Streams are damaged, or can't be extracted.
+    Error,
+    /// The sound bank is unusual, but its streams can still be extracted.
+    Warning,
+}
+
+/// A variant of a [`Finding`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FindingKind {
+    /// Streams of the sound bank's audio format can't be encoded by this build of the crate.
+    /// See [`AudioFormat::is_supported`](crate::AudioFormat::is_supported).
+    UnsupportedFormat,
+    /// The data offset of the stream was clamped, because the sound bank was parsed
+    /// with [`ParseOptions::lenient_offsets`](crate::ParseOptions::lenient_offsets).
+    DataOffset(OffsetWarningKind),
+    /// The stream header had chunks of unknown types, which were kept with
+    /// [`ParseOptions::unknown_chunks`](crate::ParseOptions::unknown_chunks).
+    UnknownChunks,
+    /// The sound bank ended within the stream's data.
+    TruncatedStream {
+        /// The number of bytes of stream data that were missing.
+        missing: u32,
+    },
+    /// The sound bank ended before the stream's data.
+    MissingStream,
+    /// Protected frames of the MPEG stream failed their CRC-16 check.
+    MpegCrcMismatch {
+        /// The number of frames whose CRC-16 didn't match.
+        frames: u32,
+    },
+    /// Free format frame headers of the MPEG stream were skipped, since the length of their frame wasn't known.
+    MpegFreeFormatHeaders {
+        /// The number of frame headers that were skipped.
+        headers: u32,
+    },
+    /// No frames were found in the data of the MPEG stream.
+    NoMpegFrames,
+    /// The frames or packets of the stream could not be read.
+    InvalidStreamData,
+    /// The underlying reader failed to read stream data, so the streams after it weren't checked.
+    ReadFailed,
+}
+
+#[derive(Debug)]
+pub(crate) enum FindingSource {
+    Encode(EncodeError),
+    Read(ReadError),
+}
+
+impl Finding {
+    /// Returns the [`FindingKind`] of this problem.
+    #[must_use]
+    pub fn kind(&self) -> FindingKind {
+        self.kind
+    }
+
+    /// Returns whether this problem is an error or a warning.
+    #[must_use]
+    pub fn severity(&self) -> Severity {
+        match self.kind {
+            FindingKind::DataOffset(_)
+            | FindingKind::UnknownChunks
+            | FindingKind::MpegFreeFormatHeaders { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
+    /// Returns the index of the stream with this problem, or `None` for problems with the whole sound bank.
+    #[must_use]
+    pub fn index(&self) -> Option<u32> {
+        self.index
+    }
+
+    /// Returns the position of this problem in bytes from the start of the sound bank, if it's known.
+    ///
+    /// For problems with stream data, this is the position of the stream's data,
+    /// or the position where the data ended for [`FindingKind::TruncatedStream`].
+    #[must_use]
+    pub fn offset(&self) -> Option<u64> {
+        self.offset
+    }
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let severity = match self.severity() {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        f.write_fmt(format_args!("{severity}: {}", self.kind))?;
+
+        if let Some(index) = self.index {
+            f.write_fmt(format_args!(" - stream at index {index}"))?;
+        }
+        if let Some(offset) = self.offset {
+            f.write_fmt(format_args!(" (offset {offset:#x})"))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for Finding {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.source {
+            Some(FindingSource::Encode(e)) => Some(e),
+            Some(FindingSource::Read(e)) => Some(e),
+            None => None,
+        }
+    }
+}
+
+impl Display for FindingKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::UnsupportedFormat => f.write_str("audio format is not supported"),
+            Self::DataOffset(kind) => kind.fmt(f),
+            Self::UnknownChunks => f.write_str("stream header had unknown chunks"),
+            Self::TruncatedStream { missing } => {
+                f.write_fmt(format_args!("stream data was missing {missing} bytes"))
+            }
+            Self::MissingStream => f.write_str("stream data was missing"),
+            Self::MpegCrcMismatch { frames } => {
+                f.write_fmt(format_args!("{frames} MPEG frames failed their CRC check"))
+            }
+            Self::MpegFreeFormatHeaders { headers } => {
+                f.write_fmt(format_args!("{headers} free format MPEG frame headers were skipped"))
+            }
+            Self::NoMpegFrames => f.write_str("no MPEG frames were found"),
+            Self::InvalidStreamData => f.write_str("stream data could not be read"),
+            Self::ReadFailed => f.write_str("failed to read stream data"),
+        }
+    }
+}
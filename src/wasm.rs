@@ -0,0 +1,262 @@
+//! A JavaScript API for inspecting sound banks in web browsers, enabled with the `wasm` feature.
+//!
+//! The API is exported with [wasm-bindgen](https://github.com/rustwasm/wasm-bindgen) when building for
+//! `wasm32-unknown-unknown`. The `vorbis` feature (enabled by default) needs a C compiler for libvorbis,
+//! which isn't available for this target, so build without default features. Vorbis streams are
+//! repackaged as Ogg Vorbis files from their original audio packets (see [`EncodeOptions::repackage_vorbis`]):
+//!
+//! ```text
+//! cargo build --target wasm32-unknown-unknown --release --no-default-features --features decode-vorbis,wasm
+//! wasm-bindgen --target web --out-dir pkg target/wasm32-unknown-unknown/release/fsbex.wasm
+//! ```
+//!
+//! Sound banks are parsed from the bytes of a file with [`parse_bank`] (`parseBank` in JavaScript),
+//! which copies them into the memory of the WebAssembly module. Stream data is only encoded when extracted.
+//!
+//! ```js
+//! import init, { parseBank } from "./pkg/fsbex.js";
+//!
+//! await init();
+//! const bank = parseBank(new Uint8Array(await file.arrayBuffer()));
+//!
+//! for (const stream of bank.listStreams()) {
+//!     console.log(stream.fileName, stream.sampleCount);
+//! }
+//!
+//! const data = bank.extractStream(0);
+//! const url = URL.createObjectURL(new Blob([data], { type: bank.listStreams()[0].mimeType }));
+//! bank.free();
+//! ```
+
+use crate::{encode::EncodeOptions, Bank};
+use std::{error::Error, io::Cursor};
+use wasm_bindgen::prelude::{wasm_bindgen, JsError};
+
+/// Parses a sound bank from the bytes of a file, such as the contents of a file dropped onto a web page.
+///
+/// # Errors
+///
+/// This function returns an error if the data is not a sound bank that can be parsed.
+#[wasm_bindgen(js_name = parseBank)]
+pub fn parse_bank(data: Vec<u8>) -> Result<WasmBank, JsError> {
+    WasmBank::new(data).map_err(|e| js_error(&e))
+}
+
+/// A sound bank parsed with [`parse_bank`], holding the data of the sound bank in memory.
+///
+/// In JavaScript, call `free()` on the sound bank when it's no longer needed to release its memory.
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct WasmBank {
+    bank: Bank<Cursor<Vec<u8>>>,
+}
+
+impl WasmBank {
+    fn new(data: Vec<u8>) -> Result<Self, crate::DecodeError> {
+        Bank::seekable(Cursor::new(data)).map(|bank| Self { bank })
+    }
+}
+
+#[wasm_bindgen]
+impl WasmBank {
+    /// Returns the audio format of the streams in the sound bank, such as `vorbis`.
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    pub fn format(&self) -> String {
+        self.bank.format().to_string()
+    }
+
+    /// Returns the number of streams in the sound bank.
+    #[wasm_bindgen(getter, js_name = numStreams)]
+    #[must_use]
+    pub fn num_streams(&self) -> u32 {
+        self.bank.num_streams().get()
+    }
+
+    /// Returns information about every stream in the sound bank, in order of streams.
+    #[wasm_bindgen(js_name = listStreams)]
+    #[must_use]
+    pub fn list_streams(&self) -> Vec<WasmStreamInfo> {
+        let options = options();
+        let format = self.bank.format();
+
+        self.bank
+            .stream_infos()
+            .iter()
+            .zip(0..)
+            .map(|(info, index)| WasmStreamInfo {
+                index,
+                name: info.name().map(str::to_owned),
+                file_name: info.suggested_filename(options),
+                mime_type: format.mime_type(options),
+                sample_rate: info.sample_rate().get(),
+                channels: info.channels().get(),
+                sample_count: info.sample_count().get(),
+                loop_start: info.loop_points().map(|(start, _)| start),
+                loop_end: info.loop_points().map(|(_, end)| end),
+                size: info.size(),
+            })
+            .collect()
+    }
+
+    /// Extracts the stream at `index`, returning the bytes of the file it's written as
+    /// (such as an Ogg Vorbis file for Vorbis streams).
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if there's no stream at `index`, or if the stream could not be encoded.
+    #[wasm_bindgen(js_name = extractStream)]
+    pub fn extract_stream(&mut self, index: u32) -> Result<Vec<u8>, JsError> {
+        self.bank
+            .extract_stream_with_options(index, Vec::new(), options())
+            .map_err(|e| js_error(&e))
+    }
+
+    /// Returns the metadata of the sound bank and its streams as JSON.
+    /// See `Bank::metadata_json` for the fields.
+    #[wasm_bindgen(js_name = metadataJson)]
+    #[must_use]
+    pub fn metadata_json(&self) -> String {
+        self.bank.metadata_json()
+    }
+}
+
+/// Information about a stream of a sound bank, returned from [`WasmBank::list_streams`].
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct WasmStreamInfo {
+    index: u32,
+    name: Option<String>,
+    file_name: String,
+    mime_type: &'static str,
+    sample_rate: u32,
+    channels: u8,
+    sample_count: u32,
+    loop_start: Option<u32>,
+    loop_end: Option<u32>,
+    size: u32,
+}
+
+#[wasm_bindgen]
+impl WasmStreamInfo {
+    /// Returns the index of the stream within the sound bank, which is passed to [`WasmBank::extract_stream`].
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the name of the stream, if it exists.
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    pub fn name(&self) -> Option<String> {
+        self.name.clone()
+    }
+
+    /// Returns a file name for the extracted stream, which is unique within the sound bank.
+    #[wasm_bindgen(getter, js_name = fileName)]
+    #[must_use]
+    pub fn file_name(&self) -> String {
+        self.file_name.clone()
+    }
+
+    /// Returns the MIME type of the extracted stream, for creating a `Blob` to download.
+    #[wasm_bindgen(getter, js_name = mimeType)]
+    #[must_use]
+    pub fn mime_type(&self) -> String {
+        self.mime_type.to_owned()
+    }
+
+    /// Returns the sample rate of the stream, in Hz.
+    #[wasm_bindgen(getter, js_name = sampleRate)]
+    #[must_use]
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Returns the number of channels in the stream.
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    pub fn channels(&self) -> u8 {
+        self.channels
+    }
+
+    /// Returns the number of samples in the stream, per channel.
+    #[wasm_bindgen(getter, js_name = sampleCount)]
+    #[must_use]
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Returns the first sample of the loop, if the stream has loop points.
+    #[wasm_bindgen(getter, js_name = loopStart)]
+    #[must_use]
+    pub fn loop_start(&self) -> Option<u32> {
+        self.loop_start
+    }
+
+    /// Returns the last sample of the loop (inclusive), if the stream has loop points.
+    #[wasm_bindgen(getter, js_name = loopEnd)]
+    #[must_use]
+    pub fn loop_end(&self) -> Option<u32> {
+        self.loop_end
+    }
+
+    /// Returns the size of the stream's data in the sound bank, in bytes.
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+}
+
+// Vorbis streams are repackaged, since the `vorbis` feature isn't available for WebAssembly.
+fn options() -> EncodeOptions<'static> {
+    EncodeOptions::new().repackage_vorbis(true)
+}
+
+// The message includes the messages of underlying errors, since JavaScript errors don't have sources.
+fn js_error(error: &dyn Error) -> JsError {
+    let mut message = error.to_string();
+    let mut source = error.source();
+
+    while let Some(error) = source {
+        message.push_str(": ");
+        message.push_str(&error.to_string());
+        source = error.source();
+    }
+
+    JsError::new(&message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::WasmBank;
+    use crate::test_bank::looping_bank;
+
+    #[test]
+    fn list_and_extract_streams() {
+        let mut bank = WasmBank::new(looping_bank()).unwrap();
+        assert_eq!(bank.format(), "pcm16");
+        assert_eq!(bank.num_streams(), 1);
+
+        let streams = bank.list_streams();
+        assert_eq!(streams.len(), 1);
+
+        let stream = &streams[0];
+        assert_eq!(stream.index(), 0);
+        assert_eq!(stream.name().as_deref(), Some("bgm"));
+        assert_eq!(stream.file_name(), "bgm.wav");
+        assert_eq!(stream.mime_type(), "audio/wav");
+        assert_eq!((stream.sample_rate(), stream.channels()), (44100, 1));
+        assert_eq!(stream.sample_count(), 2);
+        assert_eq!((stream.loop_start(), stream.loop_end()), (Some(0), Some(1)));
+        assert_eq!(stream.size(), 4);
+
+        // streams can be extracted any number of times
+        let data = bank.extract_stream(0).unwrap();
+        assert_eq!(data[..4], *b"RIFF");
+        assert_eq!(data[44..48], [0x12, 0x34, 0x56, 0x78]);
+        assert_eq!(bank.extract_stream(0).unwrap(), data);
+    }
+}
@@ -0,0 +1,43 @@
+//! Extracts a sound bank from an in-memory asynchronous source on a tokio runtime.
+
+// tests share the dependencies of the library, most of which they don't use directly
+#![allow(unused_crate_dependencies)]
+
+mod common;
+
+use common::test_bank::looping_bank;
+use fsbex::{AsyncBank, Bank};
+use std::io::Cursor;
+use tokio::io::{duplex, AsyncReadExt};
+
+#[tokio::test]
+async fn extract_stream_from_async_cursor() {
+    let data = looping_bank();
+    let mut bank = AsyncBank::new(Cursor::new(data.clone())).await.unwrap();
+    assert_eq!(bank.num_streams().get(), 1);
+    assert_eq!(bank.find("bgm"), Some(0));
+
+    let info = bank.stream_info(0).unwrap();
+    assert_eq!(info.sample_count().get(), 2);
+    assert_eq!(info.loop_points(), Some((0, 1)));
+
+    let file = bank.extract_stream(0, Vec::new()).await.unwrap();
+    assert_eq!(file[..4], *b"RIFF");
+    assert_eq!(file[44..48], [0x12, 0x34, 0x56, 0x78]);
+
+    // the output is the same as extracting synchronously
+    let expected = Bank::seekable(Cursor::new(data))
+        .unwrap()
+        .extract_stream(0, Vec::new())
+        .unwrap();
+    assert_eq!(file, expected);
+
+    // streams can be extracted again, to any asynchronous sink
+    let (writer, mut reader) = duplex(4096);
+    let writer = bank.extract_stream(0, writer).await.unwrap();
+    drop(writer);
+
+    let mut piped = Vec::new();
+    let _read = reader.read_to_end(&mut piped).await.unwrap();
+    assert_eq!(piped, expected);
+}
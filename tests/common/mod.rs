@@ -0,0 +1,5 @@
+// Helpers shared by the integration tests and benchmarks.
+
+// the sound bank builders are shared with the unit tests of the library
+#[path = "../../src/test_bank.rs"]
+pub(crate) mod test_bank;
@@ -0,0 +1,142 @@
+//! Drives the C API of the `ffi` feature through its exported functions, like a C program would.
+
+// tests share the dependencies of the library, most of which they don't use directly
+#![allow(unused_crate_dependencies)]
+
+mod common;
+
+use common::test_bank::looping_bank;
+use fsbex::ffi::{
+    fsbex_bank_extract_to_buffer, fsbex_bank_extract_to_file, fsbex_bank_free,
+    fsbex_bank_open_file, fsbex_bank_open_memory, fsbex_bank_stream_count, fsbex_bank_stream_info,
+    fsbex_last_error_message, FsbexStreamInfo, FSBEX_ERROR_BUFFER_TOO_SMALL, FSBEX_ERROR_DECODE,
+    FSBEX_ERROR_IO, FSBEX_ERROR_UNKNOWN_STREAM, FSBEX_OK,
+};
+use std::{
+    env,
+    ffi::{CStr, CString},
+    fs, ptr,
+};
+
+fn last_error() -> String {
+    let message = fsbex_last_error_message();
+    assert!(!message.is_null());
+    // SAFETY: error messages are null-terminated strings that remain valid until the next error
+    unsafe { CStr::from_ptr(message) }.to_str().unwrap().to_owned()
+}
+
+#[test]
+fn extract_stream_through_c_api() {
+    let data = looping_bank();
+
+    let mut bank = ptr::null_mut();
+    // SAFETY: the arguments point to valid memory
+    assert_eq!(
+        unsafe { fsbex_bank_open_memory(data.as_ptr(), data.len(), &raw mut bank) },
+        FSBEX_OK
+    );
+    assert!(!bank.is_null());
+
+    let mut count = 0;
+    // SAFETY: the sound bank is open
+    assert_eq!(unsafe { fsbex_bank_stream_count(bank, &raw mut count) }, FSBEX_OK);
+    assert_eq!(count, 1);
+
+    let mut info = FsbexStreamInfo {
+        format: 0,
+        sample_rate: 0,
+        channels: 0,
+        sample_count: 0,
+        has_loop: false,
+        loop_start: 0,
+        loop_end: 0,
+        size: 0,
+        name: ptr::null(),
+        extension: ptr::null(),
+    };
+    // SAFETY: the sound bank is open
+    assert_eq!(unsafe { fsbex_bank_stream_info(bank, 0, &raw mut info) }, FSBEX_OK);
+    assert_eq!(
+        (info.format, info.sample_rate, info.channels, info.sample_count),
+        (2, 44100, 1, 2)
+    );
+    assert_eq!((info.has_loop, info.loop_start, info.loop_end), (true, 0, 1));
+    assert_eq!(info.size, 4);
+    // SAFETY: strings remain valid until the sound bank is freed
+    assert_eq!(unsafe { CStr::from_ptr(info.name) }, c"bgm");
+    // SAFETY: strings remain valid until the sound bank is freed
+    assert_eq!(unsafe { CStr::from_ptr(info.extension) }, c"wav");
+
+    // SAFETY: the sound bank is open
+    assert_eq!(
+        unsafe { fsbex_bank_stream_info(bank, 1, &raw mut info) },
+        FSBEX_ERROR_UNKNOWN_STREAM
+    );
+    assert_eq!(last_error(), "there is no stream at index 1");
+
+    // the required size is queried first
+    let mut written = 0;
+    // SAFETY: the sound bank is open, and the buffer is empty
+    assert_eq!(
+        unsafe { fsbex_bank_extract_to_buffer(bank, 0, ptr::null_mut(), 0, &raw mut written) },
+        FSBEX_ERROR_BUFFER_TOO_SMALL
+    );
+
+    let mut buffer = vec![0; written];
+    // SAFETY: the sound bank is open, and the buffer holds `written` bytes
+    assert_eq!(
+        unsafe {
+            fsbex_bank_extract_to_buffer(
+                bank,
+                0,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                &raw mut written,
+            )
+        },
+        FSBEX_OK
+    );
+    assert_eq!(written, buffer.len());
+    assert_eq!(buffer[..4], *b"RIFF");
+    assert_eq!(buffer[44..48], [0x12, 0x34, 0x56, 0x78]);
+
+    // files hold the same data as buffers
+    let path = env::temp_dir().join(format!("fsbex-ffi-{}.wav", std::process::id()));
+    let c_path = CString::new(path.to_str().unwrap()).unwrap();
+    // SAFETY: the sound bank is open, and the path is a null-terminated string
+    assert_eq!(
+        unsafe { fsbex_bank_extract_to_file(bank, 0, c_path.as_ptr()) },
+        FSBEX_OK
+    );
+    assert_eq!(fs::read(&path).unwrap(), buffer);
+    fs::remove_file(path).unwrap();
+
+    // SAFETY: the sound bank is open, and isn't used afterwards
+    unsafe { fsbex_bank_free(bank) };
+}
+
+#[test]
+fn report_open_errors() {
+    let mut bank = ptr::null_mut();
+
+    let data = b"not a sound bank";
+    // SAFETY: the arguments point to valid memory
+    assert_eq!(
+        unsafe { fsbex_bank_open_memory(data.as_ptr(), data.len(), &raw mut bank) },
+        FSBEX_ERROR_DECODE
+    );
+    assert!(bank.is_null());
+    assert!(!last_error().is_empty());
+
+    let path = c"this file does not exist.fsb";
+    // SAFETY: the path is a null-terminated string
+    assert_eq!(
+        unsafe { fsbex_bank_open_file(path.as_ptr(), &raw mut bank) },
+        FSBEX_ERROR_IO
+    );
+    assert!(bank.is_null());
+
+    // freeing null does nothing
+    // SAFETY: null is always allowed
+    unsafe { fsbex_bank_free(ptr::null_mut()) };
+}